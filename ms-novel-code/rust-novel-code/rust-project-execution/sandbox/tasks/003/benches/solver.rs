@@ -0,0 +1,45 @@
+//! Held-Karp timings per kernel path: `cargo bench` (add `--features parallel`
+//! for the rayon path, `-- held_karp/avx2` to run one path only).
+//!
+//! Every path solves the same seeded matrices, so the numbers compare
+//! directly across kernels and against earlier runs.
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use task_ws::instances::uniform_matrix;
+use task_ws::DpSolver;
+
+const SIZES: [usize; 4] = [8, 12, 16, 20];
+
+fn held_karp(c: &mut Criterion) {
+    let mut group = c.benchmark_group("held_karp");
+    group.sample_size(10);
+    for n in SIZES {
+        let dist = uniform_matrix(n, 1000, n as u64);
+        // Allocating the 2^n table is not what is being measured
+        let solver = || DpSolver::new(n, dist.clone());
+
+        group.bench_with_input(BenchmarkId::new("scalar", n), &n, |b, _| {
+            b.iter_batched_ref(solver, |s| s.compute_scalar(), BatchSize::LargeInput)
+        });
+        group.bench_with_input(BenchmarkId::new("dispatch", n), &n, |b, _| {
+            b.iter_batched_ref(solver, |s| s.compute(), BatchSize::LargeInput)
+        });
+        #[cfg(target_arch = "x86_64")]
+        if is_x86_feature_detected!("avx2") {
+            let full = (1 << n) - 1;
+            group.bench_with_input(BenchmarkId::new("avx2", n), &n, |b, _| {
+                // SAFETY: AVX2 was detected above
+                let run = |s: &mut DpSolver| unsafe { s.compute_simd(full) };
+                b.iter_batched_ref(solver, run, BatchSize::LargeInput)
+            });
+        }
+        #[cfg(feature = "parallel")]
+        group.bench_with_input(BenchmarkId::new("parallel", n), &n, |b, _| {
+            b.iter_batched_ref(solver, |s| s.compute_parallel(), BatchSize::LargeInput)
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, held_karp);
+criterion_main!(benches);
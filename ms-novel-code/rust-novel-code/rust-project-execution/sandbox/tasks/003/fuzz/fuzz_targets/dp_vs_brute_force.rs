@@ -0,0 +1,74 @@
+//! Differential fuzzing of Held-Karp against exhaustive search:
+//! `cargo fuzz run dp_vs_brute_force` from the crate root.
+//!
+//! Input bytes: `n - 1` (mod 9), a left shift (mod 17) applied to every
+//! entry, then little-endian `u16` entries row by row (missing bytes read
+//! as 0). Large shifts push sums past `u32::MAX`, where both sides must
+//! saturate the same way.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use task_ws::DpSolver;
+
+/// Largest instance the O(n!) reference can check quickly.
+const MAX_N: usize = 9;
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&size, rest)) = data.split_first() else { return };
+    let Some((&shift, rest)) = rest.split_first() else { return };
+    let n = usize::from(size) % MAX_N + 1;
+    let shift = u32::from(shift) % 17;
+    let mut entries = rest.chunks(2).map(|b| {
+        let raw = u16::from_le_bytes([b[0], b.get(1).copied().unwrap_or(0)]);
+        u32::from(raw) << shift
+    });
+    let dist: Vec<Vec<u32>> = (0..n)
+        .map(|i| {
+            (0..n)
+                .map(|j| if i == j { 0 } else { entries.next().unwrap_or(0) })
+                .collect()
+        })
+        .collect();
+
+    let mut solver = DpSolver::new(n, dist.clone());
+    let cost = solver.compute();
+    assert_eq!(cost, brute_force(&dist), "{:?}", dist);
+    if let Some(tour) = solver.tour() {
+        let mut cities = tour.clone();
+        cities.sort_unstable();
+        assert!(cities.into_iter().eq(0..n), "{:?}", tour);
+        assert_eq!(cycle_cost(&dist, &tour), cost, "{:?}", tour);
+    }
+});
+
+/// Saturating cost of visiting `tour` in order and returning to its start.
+fn cycle_cost(dist: &[Vec<u32>], tour: &[usize]) -> u32 {
+    (0..tour.len())
+        .map(|k| dist[tour[k]][tour[(k + 1) % tour.len()]])
+        .fold(0, u32::saturating_add)
+}
+
+/// Cheapest cycle over every order of cities `1..n` after city 0.
+fn brute_force(dist: &[Vec<u32>]) -> u32 {
+    fn go(dist: &[Vec<u32>], path: &mut Vec<usize>, used: u32, best: &mut u32) {
+        let n = dist.len();
+        if path.len() == n {
+            *best = (*best).min(cycle_cost(dist, path));
+            return;
+        }
+        for c in 1..n {
+            if used & (1 << c) == 0 {
+                path.push(c);
+                go(dist, path, used | (1 << c), best);
+                path.pop();
+            }
+        }
+    }
+    if dist.len() <= 1 {
+        return 0;
+    }
+    let mut best = u32::MAX;
+    go(dist, &mut vec![0], 1, &mut best);
+    best
+}
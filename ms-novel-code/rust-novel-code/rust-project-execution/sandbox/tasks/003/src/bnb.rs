@@ -0,0 +1,200 @@
+//! Depth-first branch and bound for instances past Held-Karp's reach.
+//!
+//! The incumbent starts as the best nearest-neighbor tour over all start
+//! cities. Costs are reduced once at the root by node potentials: Held-Karp
+//! penalties from subgradient ascent on 1-trees for symmetric instances,
+//! the assignment problem's dual for asymmetric ones. Every tour pays the
+//! same potentials, so the search bounds partial tours on the reduced costs
+//! with 1-trees (symmetric) or cheapest in/out edges (asymmetric).
+
+use crate::bounds::{ascent, assignment_duals, nearest_neighbor};
+
+/// Largest `n` the search handles (visited sets are `u64` masks).
+pub const BNB_MAX_N: usize = 64;
+
+/// Exact solver for mid-size instances (n ≈ 20–40) with `u32` weights.
+pub struct BnBSolver {
+    pub n: usize,
+    pub dist: Vec<Vec<u32>>,
+    /// Search nodes expanded by the last `compute`.
+    pub nodes: u64,
+    tour: Option<Vec<usize>>,
+}
+
+impl BnBSolver {
+    pub fn new(n: usize, dist: Vec<Vec<u32>>) -> Self {
+        BnBSolver { n, dist, nodes: 0, tour: None }
+    }
+
+    /// Compute the shortest Hamiltonian cycle length, saturating at
+    /// `u32::MAX` like [`crate::DpSolver`].
+    ///
+    /// Panics if `n` exceeds [`BNB_MAX_N`].
+    pub fn compute(&mut self) -> u32 {
+        let n = self.n;
+        assert!(n <= BNB_MAX_N, "branch and bound supports n <= {}, got {}", BNB_MAX_N, n);
+        self.nodes = 0;
+        if n <= 1 {
+            self.tour = Some((0..n).collect());
+            return 0;
+        }
+        let w: Vec<Vec<u64>> =
+            self.dist.iter().map(|r| r.iter().map(|&d| u64::from(d)).collect()).collect();
+        let (ub, ub_tour) = nearest_neighbor(&w, n);
+        let symmetric = (0..n).all(|i| (0..i).all(|j| w[i][j] == w[j][i]));
+        let (row, col) = if symmetric {
+            // A penalty `p` on a city is a potential of `-p` on both sides
+            let pot: Vec<f64> = ascent(&w, ub).0.iter().map(|p| -p).collect();
+            (pot.clone(), pot)
+        } else {
+            assignment_duals(&w)
+        };
+
+        let mut search = Search {
+            n,
+            reduced: (0..n)
+                .map(|a| (0..n).map(|b| w[a][b] as f64 - row[a] - col[b]).collect())
+                .collect(),
+            w,
+            row,
+            col,
+            symmetric,
+            best: ub,
+            best_tour: ub_tour,
+            path: vec![0],
+            nodes: 0,
+        };
+        search.dfs(0, 1, 0);
+        self.nodes = search.nodes;
+
+        let cost = search.best.min(u64::from(u32::MAX)) as u32;
+        // Mirror `DpSolver::tour`: no order for a saturated optimum
+        self.tour = (cost < u32::MAX).then_some(search.best_tour);
+        cost
+    }
+
+    /// Optimal visiting order from city 0, or `None` before `compute` or
+    /// when the optimum saturated.
+    pub fn tour(&self) -> Option<Vec<usize>> {
+        self.tour.clone()
+    }
+}
+
+struct Search {
+    n: usize,
+    w: Vec<Vec<u64>>,
+    /// `w[a][b] - row[a] - col[b]`
+    reduced: Vec<Vec<f64>>,
+    row: Vec<f64>,
+    col: Vec<f64>,
+    symmetric: bool,
+    best: u64,
+    best_tour: Vec<usize>,
+    path: Vec<usize>,
+    nodes: u64,
+}
+
+impl Search {
+    /// Extend the path ending at `c` (cost `g`, cities `visited`).
+    fn dfs(&mut self, c: usize, visited: u64, g: u64) {
+        self.nodes += 1;
+        let full = if self.n == 64 { u64::MAX } else { (1 << self.n) - 1 };
+        let left = full & !visited;
+        if left == 0 {
+            let total = g + self.w[c][0];
+            if total < self.best {
+                self.best = total;
+                self.best_tour.clone_from(&self.path);
+            }
+            return;
+        }
+        let bound = g as f64 + self.remaining_bound(c, left);
+        // Weights are integral: any completion costs at least ceil(bound)
+        let slack = 1e-9 * bound.abs().max(1.0) * self.n as f64;
+        if (bound - slack).ceil() >= self.best as f64 {
+            return;
+        }
+
+        let mut next: Vec<usize> = bits(left).collect();
+        next.sort_by(|&a, &b| self.reduced[c][a].total_cmp(&self.reduced[c][b]));
+        for u in next {
+            let step = g + self.w[c][u];
+            if step >= self.best {
+                continue;
+            }
+            self.path.push(u);
+            self.dfs(u, visited | 1 << u, step);
+            self.path.pop();
+        }
+    }
+
+    /// Lower bound on finishing from `c` through every city in `left` and
+    /// back to 0.
+    fn remaining_bound(&self, c: usize, left: u64) -> f64 {
+        // What the remaining edges pay in potentials: one leaving each of c
+        // and `left`, one entering each of `left` and 0
+        let potentials = self.row[c] + self.col[0] + bits(left).map(|u| self.row[u] + self.col[u]).sum::<f64>();
+        let relaxed = if self.symmetric {
+            // Dropping c and 0 from the rest of the tour leaves a spanning
+            // path of `left`: bound it by an MST plus the cheapest hook-ups
+            let hook = |a: usize| bits(left).map(|u| self.reduced[a][u]).fold(f64::INFINITY, f64::min);
+            self.mst(left) + hook(c) + hook(0)
+        } else {
+            // Every city still needs an incoming edge (from `left` or c) and
+            // an outgoing one (to `left` or 0); take the stronger sum
+            let cheapest = |edges: &mut dyn Iterator<Item = f64>| edges.fold(f64::INFINITY, f64::min);
+            let incoming: f64 = bits(left | 1)
+                .map(|v| {
+                    let from = if v == 0 { left } else { left | 1 << c };
+                    cheapest(&mut bits(from).filter(|&u| u != v).map(|u| self.reduced[u][v]))
+                })
+                .sum();
+            let outgoing: f64 = bits(left | 1 << c)
+                .map(|u| {
+                    let to = if u == c { left } else { left | 1 };
+                    cheapest(&mut bits(to).filter(|&v| v != u).map(|v| self.reduced[u][v]))
+                })
+                .sum();
+            incoming.max(outgoing)
+        };
+        relaxed + potentials
+    }
+
+    /// Minimum spanning tree weight of `set` under the reduced costs.
+    fn mst(&self, set: u64) -> f64 {
+        let nodes: Vec<usize> = bits(set).collect();
+        let mut key: Vec<f64> = nodes.iter().map(|&v| self.reduced[nodes[0]][v]).collect();
+        let mut done = vec![false; nodes.len()];
+        done[0] = true;
+        let mut total = 0.0;
+        for _ in 1..nodes.len() {
+            let mut k = usize::MAX;
+            for j in 0..nodes.len() {
+                if !done[j] && (k == usize::MAX || key[j] < key[k]) {
+                    k = j;
+                }
+            }
+            done[k] = true;
+            total += key[k];
+            for j in 0..nodes.len() {
+                let d = self.reduced[nodes[k]][nodes[j]];
+                if !done[j] && d < key[j] {
+                    key[j] = d;
+                }
+            }
+        }
+        total
+    }
+}
+
+/// Indices of the set bits of `mask`, lowest first.
+fn bits(mut mask: u64) -> impl Iterator<Item = usize> {
+    std::iter::from_fn(move || {
+        if mask == 0 {
+            return None;
+        }
+        let bit = mask.trailing_zeros() as usize;
+        mask &= mask - 1;
+        Some(bit)
+    })
+}
@@ -0,0 +1,201 @@
+//! Bounds on the optimal tour length.
+//!
+//! Lower bounds come from the Held-Karp 1-tree relaxation (improved by
+//! subgradient ascent on node penalties) for symmetric matrices and from
+//! the assignment relaxation for asymmetric ones. [`crate::BnBSolver`]
+//! prunes with the same machinery.
+
+/// Lower bound on the optimal cycle length of `dist`, saturating at
+/// `u32::MAX`.
+///
+/// Exact for n ≤ 3. Symmetric matrices cost O(n²) per ascent step, with
+/// the step count capped on large n; asymmetric ones O(n³).
+pub fn lower_bound(dist: &[Vec<u32>]) -> u32 {
+    let n = dist.len();
+    let w: Vec<Vec<u64>> = dist.iter().map(|r| r.iter().map(|&d| u64::from(d)).collect()).collect();
+    let bound = if n <= 3 {
+        // At most two distinct cycles
+        let cycle = |t: &[usize]| (0..n).map(|k| w[t[k]][t[(k + 1) % n]]).sum::<u64>();
+        let forward: Vec<usize> = (0..n).collect();
+        let backward: Vec<usize> = (0..n).map(|k| (n - k) % n).collect();
+        cycle(&forward).min(cycle(&backward))
+    } else if (0..n).all(|i| (0..i).all(|j| w[i][j] == w[j][i])) {
+        let (ub, _) = nearest_neighbor(&w, 8);
+        let (_, bound) = ascent(&w, ub);
+        // Integral weights: the optimum is at least the bound rounded up
+        let slack = 1e-9 * bound.abs().max(1.0) * n as f64;
+        ((bound - slack).ceil().max(0.0) as u64).min(ub)
+    } else {
+        let (row, col) = assignment_duals(&w);
+        (row.iter().sum::<f64>() + col.iter().sum::<f64>()).round().max(0.0) as u64
+    };
+    bound.min(u64::from(u32::MAX)) as u32
+}
+
+/// Best nearest-neighbor tour from the first `starts` cities, rotated to
+/// begin at 0: a cheap upper bound.
+pub(crate) fn nearest_neighbor(w: &[Vec<u64>], starts: usize) -> (u64, Vec<usize>) {
+    let n = w.len();
+    let mut best = (u64::MAX, Vec::new());
+    for s in 0..starts.clamp(1, n) {
+        let mut seen = vec![false; n];
+        let mut tour = vec![s];
+        seen[s] = true;
+        let mut cost = 0;
+        let mut c = s;
+        for _ in 1..n {
+            let next = (0..n).filter(|&j| !seen[j]).min_by_key(|&j| w[c][j]).unwrap();
+            cost += w[c][next];
+            seen[next] = true;
+            tour.push(next);
+            c = next;
+        }
+        cost += w[c][s];
+        if cost < best.0 {
+            let zero = tour.iter().position(|&v| v == 0).unwrap();
+            tour.rotate_left(zero);
+            best = (cost, tour);
+        }
+    }
+    best
+}
+
+/// 1-tree with city 0 as the special node under penalized costs: its
+/// Lagrangian value and every city's degree.
+pub(crate) fn one_tree(w: &[Vec<u64>], pen: &[f64]) -> (f64, Vec<usize>) {
+    let n = w.len();
+    let d = |a: usize, b: usize| w[a][b] as f64 + pen[a] + pen[b];
+    let mut deg = vec![0; n];
+    let mut total = 0.0;
+
+    // Prim over cities 1..n
+    let mut in_tree = vec![false; n];
+    let mut key = vec![f64::INFINITY; n];
+    let mut link = vec![usize::MAX; n];
+    key[1] = 0.0;
+    for _ in 1..n {
+        let v = (1..n)
+            .filter(|&v| !in_tree[v])
+            .min_by(|&a, &b| key[a].total_cmp(&key[b]))
+            .unwrap();
+        in_tree[v] = true;
+        if link[v] != usize::MAX {
+            total += key[v];
+            deg[v] += 1;
+            deg[link[v]] += 1;
+        }
+        for u in 1..n {
+            if !in_tree[u] && d(v, u) < key[u] {
+                key[u] = d(v, u);
+                link[u] = v;
+            }
+        }
+    }
+
+    // The two cheapest edges at city 0
+    let mut edges: Vec<usize> = (1..n).collect();
+    edges.sort_by(|&a, &b| d(0, a).total_cmp(&d(0, b)));
+    for &u in edges.iter().take(2) {
+        total += d(0, u);
+        deg[0] += 1;
+        deg[u] += 1;
+    }
+    (total - 2.0 * pen.iter().sum::<f64>(), deg)
+}
+
+/// Optimal dual `(row, col)` of the assignment problem without self-loops
+/// (Hungarian method), so `w[a][b] - row[a] - col[b] >= 0` off the diagonal.
+pub(crate) fn assignment_duals(w: &[Vec<u64>]) -> (Vec<f64>, Vec<f64>) {
+    let n = w.len();
+    let forbidden = i64::MAX / 4;
+    let cost = |i: usize, j: usize| if i == j { forbidden } else { w[i][j] as i64 };
+    // 1-based potentials and matching; index 0 is the virtual column
+    let mut u = vec![0i64; n + 1];
+    let mut v = vec![0i64; n + 1];
+    let mut matched = vec![0usize; n + 1];
+    let mut way = vec![0usize; n + 1];
+    for i in 1..=n {
+        matched[0] = i;
+        let mut j0 = 0;
+        let mut minv = vec![i64::MAX; n + 1];
+        let mut used = vec![false; n + 1];
+        loop {
+            used[j0] = true;
+            let i0 = matched[j0];
+            let mut delta = i64::MAX;
+            let mut j1 = 0;
+            for j in 1..=n {
+                if !used[j] {
+                    let cur = cost(i0 - 1, j - 1) - u[i0] - v[j];
+                    if cur < minv[j] {
+                        minv[j] = cur;
+                        way[j] = j0;
+                    }
+                    if minv[j] < delta {
+                        delta = minv[j];
+                        j1 = j;
+                    }
+                }
+            }
+            for j in 0..=n {
+                if used[j] {
+                    u[matched[j]] += delta;
+                    v[j] -= delta;
+                } else {
+                    minv[j] -= delta;
+                }
+            }
+            j0 = j1;
+            if matched[j0] == 0 {
+                break;
+            }
+        }
+        while j0 != 0 {
+            let j1 = way[j0];
+            matched[j0] = matched[j1];
+            j0 = j1;
+        }
+    }
+    (u[1..].iter().map(|&x| x as f64).collect(), v[1..].iter().map(|&x| x as f64).collect())
+}
+
+/// Subgradient ascent on the 1-tree bound, using `ub` to size the steps;
+/// returns the best penalties and the bound they give.
+///
+/// Runs up to 50·n iterations, fewer on large n so the O(n²) 1-trees stay
+/// within about 10⁸ steps in total.
+pub(crate) fn ascent(w: &[Vec<u64>], ub: u64) -> (Vec<f64>, f64) {
+    let n = w.len();
+    let mut pen = vec![0.0; n];
+    if n < 3 {
+        return (pen, f64::NEG_INFINITY);
+    }
+    let mut best_pen = pen.clone();
+    let mut best_bound = f64::NEG_INFINITY;
+    let mut step = 2.0;
+    let mut stall = 0;
+    for _ in 0..(50 * n).min(100_000_000 / (n * n)).max(1) {
+        let (bound, deg) = one_tree(w, &pen);
+        if bound > best_bound {
+            best_bound = bound;
+            best_pen.copy_from_slice(&pen);
+            stall = 0;
+        } else {
+            stall += 1;
+            if stall >= n.max(10) {
+                step /= 2.0;
+                stall = 0;
+            }
+        }
+        let norm: usize = deg.iter().map(|&g| (g as isize - 2).pow(2) as usize).sum();
+        let gap = ub as f64 - bound;
+        if norm == 0 || gap <= 0.0 || step < 1e-4 {
+            break; // the 1-tree is a tour, or the bound meets the incumbent
+        }
+        let t = step * gap / norm as f64;
+        for (p, &g) in pen.iter_mut().zip(&deg) {
+            *p += t * (g as f64 - 2.0);
+        }
+    }
+    (best_pen, best_bound)
+}
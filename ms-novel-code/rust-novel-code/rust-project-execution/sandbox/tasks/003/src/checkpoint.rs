@@ -0,0 +1,144 @@
+//! Resumable Held-Karp solves.
+//!
+//! Masks are filled in increasing order, so when a solve is cancelled every
+//! popcount layer whose highest mask was already reached is final. A
+//! [`Checkpoint`] keeps the tables together with the last such layer, and
+//! [`DpSolver::resume`] fills the remaining layers one after another, which
+//! is all a state ever depends on. With the `serde` feature checkpoints
+//! can be written to disk and resumed by another process.
+
+use crate::{
+    masks_with_popcount, progress, table_len, DpSolver, PathMode, TspError, Weight, NO_PARENT,
+};
+use alloc::vec::Vec;
+
+/// Snapshot of a full-table solve: the instance, its endpoints and mode,
+/// and the `dp`/`parent` tables with layers `1..=layer` final.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Checkpoint<W> {
+    pub dist: Vec<Vec<W>>,
+    pub start: usize,
+    pub end: Option<usize>,
+    pub mode: PathMode,
+    /// Last popcount layer that was complete when the snapshot was taken.
+    pub layer: usize,
+    pub dp: Vec<W>,
+    pub parent: Vec<u8>,
+}
+
+impl<W: Weight> DpSolver<W> {
+    /// Snapshot the tables of the last `compute*` call, finished or
+    /// cancelled, for [`DpSolver::resume`]. `None` if there is no full
+    /// table to save: nothing ran yet, n ≤ 1, or the last call was
+    /// `compute_low_memory`.
+    ///
+    /// The snapshot copies both tables, about
+    /// [`DpSolver::memory_estimate`] bytes.
+    pub fn save_checkpoint(&self) -> Option<Checkpoint<W>> {
+        if self.layers_done == 0 {
+            return None;
+        }
+        Some(Checkpoint {
+            dist: self.dist.clone(),
+            start: self.start,
+            end: self.end,
+            mode: self.mode,
+            layer: self.layers_done,
+            dp: self.dp.clone(),
+            parent: self.parent.clone(),
+        })
+    }
+
+    /// Load `checkpoint` in place of the current instance and finish its
+    /// solve, reporting like [`DpSolver::try_compute`]. The progress
+    /// callback, cancel token and arithmetic mode stay; if the resumed solve
+    /// is cancelled again, [`DpSolver::save_checkpoint`] picks up from there.
+    ///
+    /// Fails with [`TspError::InvalidCheckpoint`] if the tables do not fit
+    /// the instance.
+    pub fn resume(&mut self, checkpoint: Checkpoint<W>) -> Result<W, TspError> {
+        check(&checkpoint)?;
+        let Checkpoint { dist, start, end, mode, layer, dp, parent } = checkpoint;
+        let n = dist.len();
+        self.n = n;
+        self.dist = dist;
+        self.start = start;
+        self.end = end;
+        self.mode = mode;
+        self.dp = dp;
+        self.parent = parent;
+        self.last = None;
+        self.monitor.interrupted = false;
+        self.overflowed = false;
+        if n <= 1 {
+            return Ok(W::ZERO);
+        }
+        let full = (1 << n) - 1;
+        // SAFETY: `W::kernel` only hands out kernels the CPU supports
+        unsafe { self.fill_layers(W::kernel(), layer + 1, full) };
+        let cost = self.close(mode, full);
+        if self.monitor.interrupted {
+            return Err(TspError::Cancelled);
+        }
+        if self.overflowed || cost == W::INFINITY {
+            return Err(TspError::Overflow);
+        }
+        Ok(cost)
+    }
+
+    /// Fill popcount layers `first..=n` in order, stopping early (with
+    /// `layers_done` up to date) if cancelled.
+    ///
+    /// # Safety
+    ///
+    /// The CPU must support the kernel's target features.
+    unsafe fn fill_layers(&mut self, kernel: crate::Kernel<W>, first: usize, full: usize) {
+        let n = self.n;
+        let mut done = (1..=full).filter(|m: &usize| (m.count_ones() as usize) < first).count();
+        let cols = crate::kernels::columns(&self.dist);
+        self.layers_done = first - 1;
+        for k in first.max(2)..=n {
+            for mask in masks_with_popcount(n, k) {
+                done += 1;
+                if done.is_multiple_of(progress::STRIDE) && !self.monitor.checkpoint(done, full) {
+                    return;
+                }
+                self.relax_mask(kernel, &cols, mask);
+            }
+            self.layers_done = k;
+        }
+        self.monitor.finish(full);
+    }
+}
+
+/// Whether `checkpoint` is internally consistent; it may come from disk.
+fn check<W>(checkpoint: &Checkpoint<W>) -> Result<(), TspError> {
+    let n = checkpoint.dist.len();
+    let invalid = |reason| Err(TspError::InvalidCheckpoint(reason));
+    if checkpoint.dist.iter().any(|row| row.len() != n) {
+        return invalid("distance matrix is not square");
+    }
+    let Some(len) = table_len(n) else { return invalid("too many cities") };
+    if checkpoint.dp.len() != len || checkpoint.parent.len() != len {
+        return invalid("table sizes do not match the city count");
+    }
+    if checkpoint.parent.iter().any(|&p| p != NO_PARENT && usize::from(p) >= n) {
+        return invalid("parent out of range");
+    }
+    if checkpoint.layer == 0 || checkpoint.layer > n {
+        return invalid("layer out of range");
+    }
+    let in_range = |city: usize| city < n.max(1);
+    let open_start = match checkpoint.mode {
+        PathMode::Open { start } => start,
+        PathMode::Cycle => None,
+    };
+    if !in_range(checkpoint.start)
+        || !checkpoint.end.is_none_or(in_range)
+        || !open_start.is_none_or(in_range)
+    {
+        return invalid("endpoint out of range");
+    }
+    Ok(())
+}
@@ -0,0 +1,120 @@
+//! Christofides' 1.5-approximation for metric instances.
+//!
+//! Minimum spanning tree, plus a minimum-weight perfect matching on its
+//! odd-degree cities, gives a connected multigraph with all degrees even;
+//! walking an Euler circuit of it and skipping repeated cities yields a
+//! tour at most 1.5× optimal. The shortcuts are only free when the
+//! triangle inequality holds, so the matrix is checked first.
+
+use crate::matching::min_weight_perfect_matching;
+use crate::HeuristicTour;
+use std::io;
+
+/// Christofides tour for a symmetric matrix that satisfies the triangle
+/// inequality, starting at city 0.
+///
+/// Fails with `InvalidInput` (naming an offending entry) otherwise; the
+/// approximation bound would not hold.
+pub fn christofides(dist: &[Vec<u32>]) -> io::Result<HeuristicTour> {
+    check_metric(dist)?;
+    let n = dist.len();
+    let d = |a: usize, b: usize| i64::from(dist[a][b]);
+    let cost = |tour: &[usize]| -> u32 {
+        let total: i64 = (0..tour.len()).map(|k| d(tour[k], tour[(k + 1) % tour.len()])).sum();
+        total.min(i64::from(u32::MAX)) as u32
+    };
+    if n <= 3 {
+        // Every cycle is optimal on a symmetric matrix this small
+        let tour: Vec<usize> = (0..n).collect();
+        return Ok(HeuristicTour { cost: cost(&tour), tour, optimal: true });
+    }
+
+    // Multigraph: MST edges plus matching edges, as adjacency lists
+    let mut adj = vec![Vec::new(); n];
+    for (a, b) in minimum_spanning_tree(dist) {
+        adj[a].push(b);
+        adj[b].push(a);
+    }
+    let odd: Vec<usize> = (0..n).filter(|&v| adj[v].len() % 2 == 1).collect();
+    let mate = min_weight_perfect_matching(odd.len(), |i, j| d(odd[i], odd[j]));
+    for (i, &m) in mate.iter().enumerate() {
+        if i < m {
+            adj[odd[i]].push(odd[m]);
+            adj[odd[m]].push(odd[i]);
+        }
+    }
+
+    let mut seen = vec![false; n];
+    let tour: Vec<usize> = euler_circuit(adj)
+        .into_iter()
+        .filter(|&v| !std::mem::replace(&mut seen[v], true))
+        .collect();
+    Ok(HeuristicTour { cost: cost(&tour), tour, optimal: false })
+}
+
+/// Reject asymmetric matrices and triangle-inequality violations.
+fn check_metric(dist: &[Vec<u32>]) -> io::Result<()> {
+    let invalid = |msg: String| Err(io::Error::new(io::ErrorKind::InvalidInput, msg));
+    for (i, row) in dist.iter().enumerate() {
+        for (j, &d) in row.iter().enumerate() {
+            if d != dist[j][i] {
+                return invalid(format!("not symmetric: d({0},{1}) != d({1},{0})", i, j));
+            }
+        }
+    }
+    for (i, row) in dist.iter().enumerate() {
+        for (j, &via) in row.iter().enumerate() {
+            for (k, &direct) in row.iter().enumerate() {
+                if u64::from(direct) > u64::from(via) + u64::from(dist[j][k]) {
+                    return invalid(format!(
+                        "triangle inequality fails: d({0},{2}) > d({0},{1}) + d({1},{2})",
+                        i, j, k
+                    ));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Prim's algorithm on the dense matrix; returns the tree's edges.
+fn minimum_spanning_tree(dist: &[Vec<u32>]) -> Vec<(usize, usize)> {
+    let n = dist.len();
+    let mut in_tree = vec![false; n];
+    let mut key = dist[0].clone();
+    let mut link = vec![0; n];
+    let mut edges = Vec::with_capacity(n - 1);
+    in_tree[0] = true;
+    for _ in 1..n {
+        let v = (0..n).filter(|&v| !in_tree[v]).min_by_key(|&v| key[v]).unwrap();
+        in_tree[v] = true;
+        edges.push((link[v], v));
+        for u in 0..n {
+            if !in_tree[u] && dist[v][u] < key[u] {
+                key[u] = dist[v][u];
+                link[u] = v;
+            }
+        }
+    }
+    edges
+}
+
+/// Hierholzer's algorithm from city 0 on a connected multigraph with all
+/// degrees even.
+fn euler_circuit(mut adj: Vec<Vec<usize>>) -> Vec<usize> {
+    let mut circuit = Vec::new();
+    let mut stack = vec![0];
+    while let Some(&v) = stack.last() {
+        match adj[v].pop() {
+            Some(u) => {
+                // Drop the reverse copy of the edge just walked
+                let back = adj[u].iter().position(|&x| x == v).unwrap();
+                adj[u].swap_remove(back);
+                stack.push(u);
+            }
+            None => circuit.push(stack.pop().unwrap()),
+        }
+    }
+    circuit.reverse();
+    circuit
+}
@@ -0,0 +1,59 @@
+//! Metric closure of a graph with missing edges.
+//!
+//! Floyd–Warshall replaces every entry with the shortest-path distance
+//! between its cities, so the result is complete and obeys the triangle
+//! inequality, and Held-Karp (or any other solver) can run on it. A tour
+//! of the closure lists each city once; consecutive cities may be joined
+//! by a shortest path through others, which is how a sparse graph without
+//! a Hamiltonian cycle of its own is still toured.
+
+use crate::TspError;
+use alloc::vec::Vec;
+
+/// Shortest-path distances between every pair of cities of `dist`, where
+/// `None` marks a missing edge (row `i`, column `j` is the edge `i → j`).
+///
+/// Fails with [`TspError::Infeasible`] if some city cannot reach another,
+/// in which case no tour exists even in the closure, and with
+/// [`TspError::Overflow`] if a shortest path does not fit in `u32`.
+pub fn metric_closure(dist: &[Vec<Option<u32>>]) -> Result<Vec<Vec<u32>>, TspError> {
+    let n = dist.len();
+    if let Some((i, row)) = dist.iter().enumerate().find(|(_, row)| row.len() != n) {
+        return Err(TspError::BadRow { line: i + 1, expected: n, got: row.len() });
+    }
+    // u64 sums of two u32 paths never overflow
+    let mut d: Vec<Vec<Option<u64>>> = dist
+        .iter()
+        .enumerate()
+        .map(|(i, row)| {
+            row.iter().enumerate().map(|(j, &w)| if i == j { Some(0) } else { w.map(u64::from) }).collect()
+        })
+        .collect();
+    for k in 0..n {
+        // row k does not change while relaxing through k
+        let via = d[k].clone();
+        for row in d.iter_mut() {
+            let Some(ik) = row[k] else { continue };
+            for (ij, kj) in row.iter_mut().zip(&via) {
+                if let Some(kj) = kj {
+                    if ij.is_none_or(|ij| ik + kj < ij) {
+                        *ij = Some(ik + kj);
+                    }
+                }
+            }
+        }
+    }
+    d.into_iter()
+        .enumerate()
+        .map(|(from, row)| {
+            row.into_iter()
+                .enumerate()
+                .map(|(to, w)| match w {
+                    None => Err(TspError::Infeasible { from, to }),
+                    // u32::MAX is the solvers' INFINITY
+                    Some(w) => u32::try_from(w).ok().filter(|&w| w < u32::MAX).ok_or(TspError::Overflow),
+                })
+                .collect()
+        })
+        .collect()
+}
@@ -0,0 +1,97 @@
+//! Tour drawings for reviewing solutions by eye.
+//!
+//! [`render_dot`] pins every city at its coordinates, so Graphviz should
+//! lay it out with `neato -n` (e.g. `neato -n -Tsvg tour.dot`);
+//! [`render_svg`] needs no Graphviz at all.
+
+use std::fmt::Write;
+
+/// Largest side of the SVG drawing, in pixels.
+const SVG_SIZE: f64 = 800.0;
+/// Blank border around the SVG drawing, in pixels.
+const SVG_MARGIN: f64 = 20.0;
+
+/// The closed tour as an undirected Graphviz graph with positioned nodes
+/// named after their city index.
+///
+/// Panics if a city in `tour` has no entry in `coords`.
+pub fn render_dot(tour: &[usize], coords: &[(f64, f64)]) -> String {
+    let mut dot = String::from("graph tour {\n    node [shape=circle, fontsize=10];\n");
+    for &city in tour {
+        let (x, y) = coords[city];
+        writeln!(dot, "    {} [pos=\"{},{}!\"];", city, x, y).unwrap();
+    }
+    for (a, b) in edges(tour) {
+        writeln!(dot, "    {} -- {};", a, b).unwrap();
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+/// The closed tour as a standalone SVG image, scaled to fit 800 pixels
+/// with `y` pointing up.
+///
+/// Panics if a city in `tour` has no entry in `coords`.
+pub fn render_svg(tour: &[usize], coords: &[(f64, f64)]) -> String {
+    let points: Vec<(f64, f64)> = tour.iter().map(|&c| coords[c]).collect();
+    let (min_x, max_x) = bounds(points.iter().map(|p| p.0));
+    let (min_y, max_y) = bounds(points.iter().map(|p| p.1));
+    let span = (max_x - min_x).max(max_y - min_y);
+    let scale = if span > 0.0 { (SVG_SIZE - 2.0 * SVG_MARGIN) / span } else { 1.0 };
+    let place = |(x, y): (f64, f64)| {
+        (SVG_MARGIN + (x - min_x) * scale, SVG_MARGIN + (max_y - y) * scale)
+    };
+    let width = 2.0 * SVG_MARGIN + (max_x - min_x) * scale;
+    let height = 2.0 * SVG_MARGIN + (max_y - min_y) * scale;
+
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{:.0}\" height=\"{:.0}\">",
+        width, height
+    )
+    .unwrap();
+    let path: Vec<String> = points
+        .iter()
+        .map(|&p| {
+            let (x, y) = place(p);
+            format!("{:.1},{:.1}", x, y)
+        })
+        .collect();
+    writeln!(
+        svg,
+        "  <polygon points=\"{}\" fill=\"none\" stroke=\"steelblue\" stroke-width=\"1.5\"/>",
+        path.join(" ")
+    )
+    .unwrap();
+    for (&city, &p) in tour.iter().zip(&points) {
+        let (x, y) = place(p);
+        writeln!(svg, "  <circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"3\"/>", x, y).unwrap();
+        writeln!(
+            svg,
+            "  <text x=\"{:.1}\" y=\"{:.1}\" font-size=\"10\">{}</text>",
+            x + 4.0,
+            y - 4.0,
+            city
+        )
+        .unwrap();
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Consecutive pairs of the closed tour (none for fewer than two cities).
+fn edges(tour: &[usize]) -> Vec<(usize, usize)> {
+    match tour.len() {
+        0 | 1 => Vec::new(),
+        2 => vec![(tour[0], tour[1])],
+        len => (0..len).map(|k| (tour[k], tour[(k + 1) % len])).collect(),
+    }
+}
+
+fn bounds(values: impl Iterator<Item = f64>) -> (f64, f64) {
+    let (lo, hi) = values.fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), v| {
+        (lo.min(v), hi.max(v))
+    });
+    if lo > hi { (0.0, 0.0) } else { (lo, hi) }
+}
@@ -0,0 +1,166 @@
+//! Error type for the `try_*` API.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::io;
+
+/// Why an instance could not be parsed or solved.
+#[derive(Debug)]
+pub enum TspError {
+    /// The header line does not hold a city count.
+    InvalidN,
+    /// Row `line` (1-based) has the wrong number of values; from text
+    /// input, the values ran out `got` values into that row.
+    BadRow { line: usize, expected: usize, got: usize },
+    /// A value on input line `line` (1-based, after the header) is not a
+    /// valid number.
+    InvalidValue { line: usize, token: String },
+    /// Input line `line` goes on with `token` after the last value.
+    TrailingData { line: usize, token: String },
+    /// The matrix has `rows` rows for `n` cities.
+    NonSquare { n: usize, rows: usize },
+    /// City `city` was named as an endpoint but only `n` cities exist.
+    NoSuchCity { city: usize, n: usize },
+    /// A cycle was asked to end at its own start city.
+    EndIsStart { city: usize },
+    /// City `to` cannot be reached from city `from`, so no tour exists,
+    /// not even through the [`crate::metric_closure`].
+    Infeasible { from: usize, to: usize },
+    /// `n` cities exceed what the chosen solver accepts.
+    TooLarge { n: usize, max: usize },
+    /// The optimal cost does not fit in the weight type.
+    Overflow,
+    /// The solve was stopped through its [`crate::CancelToken`].
+    Cancelled,
+    /// A [`crate::Checkpoint`] does not describe a consistent solve.
+    InvalidCheckpoint(&'static str),
+    /// The distance matrix failed validation; see [`MatrixIssue`].
+    InvalidMatrix(Vec<MatrixIssue>),
+    /// Reading input or writing output failed, or a solver rejected the
+    /// instance for another reason.
+    #[cfg(feature = "std")]
+    Io(io::Error),
+}
+
+impl fmt::Display for TspError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TspError::InvalidN => write!(f, "Invalid N"),
+            TspError::BadRow { line, expected, got } => {
+                write!(f, "Line {}: expected {} values, got {}", line, expected, got)
+            }
+            TspError::InvalidValue { line, token } => {
+                write!(f, "Line {}: invalid value {:?}", line, token)
+            }
+            TspError::TrailingData { line, token } => {
+                write!(f, "Line {}: unexpected {:?} after the last value", line, token)
+            }
+            TspError::NonSquare { n, rows } => {
+                write!(f, "distance matrix has {} rows for N = {}", rows, n)
+            }
+            TspError::NoSuchCity { city, n } => {
+                write!(f, "city {} out of range for n = {}", city, n)
+            }
+            TspError::EndIsStart { city } => write!(f, "end city {} is also the start city", city),
+            TspError::Infeasible { from, to } => {
+                write!(f, "infeasible: city {} is unreachable from city {}", to, from)
+            }
+            TspError::TooLarge { n, max } => {
+                write!(f, "N = {} exceeds the solver limit of {}", n, max)
+            }
+            TspError::Overflow => write!(f, "optimal cost overflows the weight type"),
+            TspError::Cancelled => write!(f, "solve cancelled"),
+            TspError::InvalidCheckpoint(reason) => write!(f, "invalid checkpoint: {}", reason),
+            TspError::InvalidMatrix(issues) => {
+                write!(f, "invalid distance matrix: ")?;
+                for (k, issue) in issues.iter().take(SHOWN_ISSUES).enumerate() {
+                    write!(f, "{}{}", if k > 0 { "; " } else { "" }, issue)?;
+                }
+                if issues.len() > SHOWN_ISSUES {
+                    write!(f, "; and {} more", issues.len() - SHOWN_ISSUES)?;
+                }
+                Ok(())
+            }
+            #[cfg(feature = "std")]
+            TspError::Io(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+/// Issues spelled out by `TspError`'s `Display`; the rest are counted.
+const SHOWN_ISSUES: usize = 5;
+
+/// One problem found by [`crate::DpSolver::validate`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MatrixIssue {
+    /// The matrix has `got` rows instead of `expected`.
+    RowCount { expected: usize, got: usize },
+    /// Row `row` (0-based) has `got` entries instead of `expected`.
+    RowLength { row: usize, expected: usize, got: usize },
+    /// `d(city, city)` is not zero.
+    NonZeroDiagonal { city: usize },
+    /// `d(from, to)` is negative or NaN.
+    Negative { from: usize, to: usize },
+    /// `d(from, to)` reaches the weight type's `INFINITY`, which the
+    /// solvers reserve for unreachable states.
+    Overflowing { from: usize, to: usize },
+    /// `d(from, to) != d(to, from)`; only reported when symmetry is required.
+    Asymmetric { from: usize, to: usize },
+}
+
+impl fmt::Display for MatrixIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            MatrixIssue::RowCount { expected, got } => {
+                write!(f, "{} rows, expected {}", got, expected)
+            }
+            MatrixIssue::RowLength { row, expected, got } => {
+                write!(f, "row {} has {} entries, expected {}", row, got, expected)
+            }
+            MatrixIssue::NonZeroDiagonal { city } => write!(f, "d({0},{0}) is not zero", city),
+            MatrixIssue::Negative { from, to } => {
+                write!(f, "d({},{}) is negative or NaN", from, to)
+            }
+            MatrixIssue::Overflowing { from, to } => {
+                write!(f, "d({},{}) overflows the weight type", from, to)
+            }
+            MatrixIssue::Asymmetric { from, to } => {
+                write!(f, "d({0},{1}) != d({1},{0})", from, to)
+            }
+        }
+    }
+}
+
+impl core::error::Error for TspError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            #[cfg(feature = "std")]
+            TspError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<io::Error> for TspError {
+    fn from(err: io::Error) -> Self {
+        TspError::Io(err)
+    }
+}
+
+/// Keeps the io-based entry points working: I/O errors pass through and
+/// bad endpoints become `InvalidInput` and everything else `InvalidData`.
+#[cfg(feature = "std")]
+impl From<TspError> for io::Error {
+    fn from(err: TspError) -> Self {
+        match err {
+            TspError::Io(err) => err,
+            err @ (TspError::NoSuchCity { .. } | TspError::EndIsStart { .. }) => {
+                io::Error::new(io::ErrorKind::InvalidInput, err)
+            }
+            other => io::Error::new(io::ErrorKind::InvalidData, other),
+        }
+    }
+}
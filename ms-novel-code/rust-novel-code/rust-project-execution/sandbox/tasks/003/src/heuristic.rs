@@ -0,0 +1,191 @@
+//! Tour construction and local search for instances too large to solve
+//! exactly.
+//!
+//! A nearest-neighbor tour from city 0 is improved by 2-opt (segment
+//! reversal) and Or-opt (moving runs of up to three cities) until neither
+//! finds an improving move or the time budget runs out. Both moves are
+//! priced exactly on asymmetric matrices too, and city 0 never leaves the
+//! front of the tour. Symmetric instances go to the Lin–Kernighan engine
+//! in [`crate::lk`] instead when it is enabled, since its chains subsume
+//! 2-opt.
+
+use crate::lk::{self, LkConfig};
+use std::time::{Duration, Instant};
+
+/// Approximate solver for large `n`, with `u32` weights.
+pub struct HeuristicSolver {
+    pub n: usize,
+    pub dist: Vec<Vec<u32>>,
+    /// Wall-clock limit for local search (construction always finishes).
+    pub time_budget: Duration,
+    /// Lin–Kernighan settings for symmetric instances, or `None` to stop
+    /// after 2-opt/Or-opt.
+    pub lk: Option<LkConfig>,
+}
+
+/// Result of [`HeuristicSolver::solve`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HeuristicTour {
+    /// Cycle length, saturating at `u32::MAX`.
+    pub cost: u32,
+    /// Visiting order starting at city 0.
+    pub tour: Vec<usize>,
+    /// Whether the tour is known to be optimal; `false` means approximate.
+    pub optimal: bool,
+}
+
+impl HeuristicTour {
+    /// Relative distance of `cost` above a lower bound on the optimum,
+    /// such as [`crate::lower_bound`]: 0.0 means provably optimal.
+    pub fn gap(&self, lower_bound: u32) -> f64 {
+        if lower_bound == 0 {
+            return if self.cost == 0 { 0.0 } else { f64::INFINITY };
+        }
+        (f64::from(self.cost) - f64::from(lower_bound)) / f64::from(lower_bound)
+    }
+}
+
+impl HeuristicSolver {
+    /// Solver with a one-second search budget and default LK settings.
+    pub fn new(n: usize, dist: Vec<Vec<u32>>) -> Self {
+        HeuristicSolver {
+            n,
+            dist,
+            time_budget: Duration::from_secs(1),
+            lk: Some(LkConfig::default()),
+        }
+    }
+
+    pub fn with_time_budget(mut self, budget: Duration) -> Self {
+        self.time_budget = budget;
+        self
+    }
+
+    pub fn with_lk(mut self, config: Option<LkConfig>) -> Self {
+        self.lk = config;
+        self
+    }
+
+    /// Build and improve a tour.
+    pub fn solve(&self) -> HeuristicTour {
+        let n = self.n;
+        if n <= 3 {
+            // At most two distinct cycles: try both directions
+            let forward: Vec<usize> = (0..n).collect();
+            let mut backward = forward.clone();
+            backward[1.min(n)..].reverse();
+            let tour = if self.cost(&backward) < self.cost(&forward) { backward } else { forward };
+            return HeuristicTour { cost: self.saturated(&tour), tour, optimal: true };
+        }
+
+        let deadline = Instant::now() + self.time_budget;
+        let mut tour = self.nearest_neighbor();
+        let symmetric = (0..n).all(|i| (0..i).all(|j| self.dist[i][j] == self.dist[j][i]));
+        match &self.lk {
+            Some(config) if symmetric => {
+                tour = lk::improve(&self.dist, &tour, config, Some(deadline));
+            }
+            _ => {
+                while Instant::now() < deadline {
+                    if !self.two_opt(&mut tour, deadline) && !self.or_opt(&mut tour, deadline) {
+                        break; // local optimum
+                    }
+                }
+            }
+        }
+        HeuristicTour { cost: self.saturated(&tour), tour, optimal: false }
+    }
+
+    fn d(&self, a: usize, b: usize) -> i64 {
+        i64::from(self.dist[a][b])
+    }
+
+    fn cost(&self, tour: &[usize]) -> i64 {
+        (0..tour.len()).map(|k| self.d(tour[k], tour[(k + 1) % tour.len()])).sum()
+    }
+
+    fn saturated(&self, tour: &[usize]) -> u32 {
+        self.cost(tour).min(i64::from(u32::MAX)) as u32
+    }
+
+    /// Greedy tour from city 0.
+    fn nearest_neighbor(&self) -> Vec<usize> {
+        let n = self.n;
+        let mut seen = vec![false; n];
+        let mut tour = Vec::with_capacity(n);
+        let mut c = 0;
+        seen[0] = true;
+        tour.push(0);
+        for _ in 1..n {
+            let next = (0..n).filter(|&j| !seen[j]).min_by_key(|&j| self.dist[c][j]).unwrap();
+            seen[next] = true;
+            tour.push(next);
+            c = next;
+        }
+        tour
+    }
+
+    /// Apply the first improving segment reversal; `false` if there is none.
+    fn two_opt(&self, tour: &mut [usize], deadline: Instant) -> bool {
+        let n = tour.len();
+        // Path costs up to each position, walked forwards and backwards, so
+        // a reversed segment is priced in O(1) even when asymmetric
+        let mut fwd = vec![0i64; n];
+        let mut rev = vec![0i64; n];
+        for k in 1..n {
+            fwd[k] = fwd[k - 1] + self.d(tour[k - 1], tour[k]);
+            rev[k] = rev[k - 1] + self.d(tour[k], tour[k - 1]);
+        }
+        for i in 0..n - 2 {
+            if Instant::now() >= deadline {
+                return false;
+            }
+            let (a, b) = (tour[i], tour[i + 1]);
+            for j in i + 2..n {
+                if i == 0 && j == n - 1 {
+                    continue; // the two edges share city 0
+                }
+                let (c, e) = (tour[j], tour[(j + 1) % n]);
+                let before = self.d(a, b) + self.d(c, e) + fwd[j] - fwd[i + 1];
+                let after = self.d(a, c) + self.d(b, e) + rev[j] - rev[i + 1];
+                if after < before {
+                    tour[i + 1..=j].reverse();
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    /// Apply the first improving move of a run of 1–3 cities to another
+    /// gap, keeping its direction; `false` if there is none.
+    fn or_opt(&self, tour: &mut Vec<usize>, deadline: Instant) -> bool {
+        let n = tour.len();
+        for len in 1..=3.min(n - 2) {
+            for s in 1..=n - len {
+                if Instant::now() >= deadline {
+                    return false;
+                }
+                let e = s + len - 1;
+                let (p, q) = (tour[s - 1], tour[(e + 1) % n]);
+                let (first, last) = (tour[s], tour[e]);
+                let removed = self.d(p, q) - self.d(p, first) - self.d(last, q);
+                for k in 0..n {
+                    // Gap between tour[k] and its successor, outside the run
+                    if (s - 1..=e).contains(&k) {
+                        continue;
+                    }
+                    let (x, y) = (tour[k], tour[(k + 1) % n]);
+                    let delta = removed + self.d(x, first) + self.d(last, y) - self.d(x, y);
+                    if delta < 0 {
+                        let run: Vec<usize> = tour.drain(s..=e).collect();
+                        let at = tour.iter().position(|&c| c == x).unwrap() + 1;
+                        tour.splice(at..at, run);
+                        return true;
+                    }
+                }
+            }
+        }
+        false
+    }
+}
@@ -0,0 +1,95 @@
+//! Seeded random instances for stress tests and benchmarks.
+//!
+//! The same `(n, seed)` always produces the same instance on every
+//! platform. [`matrix_input`] and [`coords_input`] format instances the way
+//! `solve_tsp` reads them.
+
+use std::fmt::Write;
+
+/// `n`×`n` matrix with a zero diagonal and every other entry drawn
+/// uniformly from `1..=max` (so generally asymmetric).
+pub fn uniform_matrix(n: usize, max: u32, seed: u64) -> Vec<Vec<u32>> {
+    let mut rng = Rng::new(seed);
+    (0..n)
+        .map(|i| {
+            (0..n)
+                .map(|j| if i == j { 0 } else { 1 + (rng.next() % u64::from(max.max(1))) as u32 })
+                .collect()
+        })
+        .collect()
+}
+
+/// `n` points with integer coordinates spread uniformly over
+/// `[0, side) × [0, side)`.
+pub fn euclidean_points(n: usize, side: u32, seed: u64) -> Vec<(f64, f64)> {
+    let mut rng = Rng::new(seed);
+    let side = u64::from(side.max(1));
+    (0..n).map(|_| ((rng.next() % side) as f64, (rng.next() % side) as f64)).collect()
+}
+
+/// `n` points with integer coordinates in `[0, side]²`, drawn around
+/// `clusters` uniformly placed centers with a spread of `side / 20`; the
+/// kind of instance where nearest-neighbor tours do badly.
+pub fn clustered_points(n: usize, clusters: usize, side: u32, seed: u64) -> Vec<(f64, f64)> {
+    let mut rng = Rng::new(seed);
+    let side = f64::from(side.max(1));
+    let centers: Vec<(f64, f64)> =
+        (0..clusters.max(1)).map(|_| (rng.unit() * side, rng.unit() * side)).collect();
+    let sigma = side / 20.0;
+    (0..n)
+        .map(|k| {
+            let (cx, cy) = centers[k % centers.len()];
+            let (dx, dy) = rng.normal_pair();
+            let place = |c: f64, d: f64| (c + d * sigma).round().clamp(0.0, side);
+            (place(cx, dx), place(cy, dy))
+        })
+        .collect()
+}
+
+/// `dist` in the `N` + matrix input format.
+pub fn matrix_input(dist: &[Vec<u32>]) -> String {
+    let mut out = format!("{}\n", dist.len());
+    for row in dist {
+        let row: Vec<String> = row.iter().map(u32::to_string).collect();
+        writeln!(out, "{}", row.join(" ")).unwrap();
+    }
+    out
+}
+
+/// `points` in the `COORDS N` input format.
+pub fn coords_input(points: &[(f64, f64)]) -> String {
+    let mut out = format!("COORDS {}\n", points.len());
+    for &(x, y) in points {
+        writeln!(out, "{} {}", x, y).unwrap();
+    }
+    out
+}
+
+/// xorshift64*; small, fast and reproducible, which is all these need.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // Spread small seeds out and keep the state nonzero
+        Rng(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Uniform in `[0, 1)`.
+    fn unit(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Two independent standard normal samples (Box–Muller).
+    fn normal_pair(&mut self) -> (f64, f64) {
+        let radius = (-2.0 * (1.0 - self.unit()).ln()).sqrt();
+        let angle = std::f64::consts::TAU * self.unit();
+        (radius * angle.cos(), radius * angle.sin())
+    }
+}
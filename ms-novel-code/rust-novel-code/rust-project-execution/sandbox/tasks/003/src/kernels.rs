@@ -0,0 +1,211 @@
+//! Inner min-add kernels of the Held-Karp recurrence.
+//!
+//! A kernel relaxes one state `(mask, i)`: it scans row `prev = mask ^ (1 << i)`
+//! of the DP table and returns the cheapest `dp[prev][j] + dist[j][i]` along
+//! with the argmin `j`. Kernels read that row through a raw pointer so the
+//! parallel driver can fill other rows at the same time, and so the layered
+//! driver can keep rows somewhere other than `dp[prev * n..]`.
+//!
+//! The distances into `i` come as one contiguous column (the drivers keep a
+//! transposed copy of the matrix, see [`columns`]), so the SIMD kernels
+//! load them straight into a vector instead of gathering them entry by
+//! entry.
+
+use crate::{Weight, NO_PARENT};
+use alloc::vec::Vec;
+
+#[cfg(target_arch = "x86_64")]
+use core::arch::x86_64::{
+    __m256i, __m512i, _mm256_add_epi32, _mm256_blendv_epi8, _mm256_cmpeq_epi32,
+    _mm256_loadu_si256, _mm256_max_epu32, _mm256_min_epu32, _mm256_or_si256,
+    _mm256_set1_epi32, _mm256_setr_epi32, _mm256_storeu_si256, _mm256_xor_si256,
+    _mm512_add_epi32, _mm512_cmplt_epu32_mask, _mm512_loadu_si512, _mm512_mask_mov_epi32,
+    _mm512_set1_epi32, _mm512_setr_epi32, _mm512_storeu_si512,
+};
+
+#[cfg(target_arch = "aarch64")]
+use core::arch::aarch64::{
+    uint32x4_t, vaddq_u32, vbslq_u32, vcltq_u32, vdupq_n_u32, vld1q_u32, vqaddq_u32, vst1q_u32,
+};
+
+/// Signature shared by all kernels.
+///
+/// # Safety
+///
+/// `row` must point at the `col.len()` entries of the DP row for mask
+/// `prev` (wherever the caller stores it), `col[j]` must be `dist[j][i]`,
+/// and the CPU must support the target features the kernel was compiled
+/// with.
+pub type Kernel<W> = unsafe fn(row: *const W, col: &[W], prev: usize) -> (W, u8);
+
+/// `dist` transposed into one flat `n * n` vector: the `n` entries from
+/// `columns(dist)[i * n..]` are the `col` argument for target city `i`.
+pub fn columns<W: Weight>(dist: &[Vec<W>]) -> Vec<W> {
+    let n = dist.len();
+    (0..n).flat_map(|i| dist.iter().map(move |from| from[i])).collect()
+}
+
+/// Portable kernel for any [`Weight`].
+///
+/// # Safety
+///
+/// See [`Kernel`].
+pub unsafe fn relax_scalar<W: Weight>(row: *const W, col: &[W], prev: usize) -> (W, u8) {
+    let mut best = W::INFINITY;
+    let mut best_j = NO_PARENT;
+    for (j, &d) in col.iter().enumerate() {
+        if prev & (1 << j) != 0 {
+            let cost = (*row.add(j)).sat_add(d);
+            if cost < best { best = cost; best_j = j as u8; }
+        }
+    }
+    (best, best_j)
+}
+
+/// AVX2 kernel for `u32` weights: 8 predecessors per step plus a scalar tail.
+///
+/// # Safety
+///
+/// See [`Kernel`]; additionally requires AVX2.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+pub unsafe fn relax_avx2(row: *const u32, col: &[u32], prev: usize) -> (u32, u8) {
+    let lane = 8;
+    let chunks = col.len() / lane;
+    let all_ones = _mm256_set1_epi32(-1);
+    let lane_ids = _mm256_setr_epi32(0, 1, 2, 3, 4, 5, 6, 7);
+
+    let mut best_vec: __m256i = all_ones;
+    let mut best_ids: __m256i = all_ones;
+    for c in 0..chunks {
+        let j0 = c * lane;
+        let dp_vec = _mm256_loadu_si256(row.add(j0) as *const __m256i);
+        let dist_vec = _mm256_loadu_si256(col.as_ptr().add(j0) as *const __m256i);
+
+        // Saturating add: lanes that wrapped (sum < dp) become u32::MAX,
+        // which also keeps cities outside `prev` (dp = MAX) out of the min.
+        let sum = _mm256_add_epi32(dp_vec, dist_vec);
+        let no_wrap = _mm256_cmpeq_epi32(_mm256_max_epu32(sum, dp_vec), sum);
+        let sum = _mm256_or_si256(sum, _mm256_xor_si256(no_wrap, all_ones));
+
+        // Track the argmin per lane: keep the old index where best <= sum
+        let keep = _mm256_cmpeq_epi32(_mm256_min_epu32(best_vec, sum), best_vec);
+        let ids = _mm256_add_epi32(lane_ids, _mm256_set1_epi32(j0 as i32));
+        best_ids = _mm256_blendv_epi8(ids, best_ids, keep);
+        best_vec = _mm256_min_epu32(best_vec, sum);
+    }
+
+    let mut tmp = [0u32; 8];
+    let mut tmp_ids = [0u32; 8];
+    _mm256_storeu_si256(tmp.as_mut_ptr() as *mut __m256i, best_vec);
+    _mm256_storeu_si256(tmp_ids.as_mut_ptr() as *mut __m256i, best_ids);
+    let mut best = u32::MAX;
+    let mut best_j = NO_PARENT;
+    for (&v, &j) in tmp.iter().zip(&tmp_ids) {
+        if v < best { best = v; best_j = j as u8; }
+    }
+
+    for (j, &d) in col.iter().enumerate().skip(chunks * lane) {
+        if prev & (1 << j) != 0 {
+            let cost = (*row.add(j)).saturating_add(d);
+            if cost < best { best = cost; best_j = j as u8; }
+        }
+    }
+    (best, best_j)
+}
+
+/// AVX-512 kernel for `u32` weights: 16 predecessors per step plus a scalar
+/// tail, using mask registers for the saturation and argmin bookkeeping.
+///
+/// # Safety
+///
+/// See [`Kernel`]; additionally requires AVX-512F.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx512f")]
+pub unsafe fn relax_avx512(row: *const u32, col: &[u32], prev: usize) -> (u32, u8) {
+    let lane = 16;
+    let chunks = col.len() / lane;
+    let all_ones = _mm512_set1_epi32(-1);
+    let lane_ids = _mm512_setr_epi32(0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15);
+
+    let mut best_vec: __m512i = all_ones;
+    let mut best_ids: __m512i = all_ones;
+    for c in 0..chunks {
+        let j0 = c * lane;
+        let dp_vec = _mm512_loadu_si512(row.add(j0) as *const __m512i);
+        let dist_vec = _mm512_loadu_si512(col.as_ptr().add(j0) as *const __m512i);
+
+        // Lanes whose add wrapped (sum < dp) saturate to u32::MAX
+        let sum = _mm512_add_epi32(dp_vec, dist_vec);
+        let wrapped = _mm512_cmplt_epu32_mask(sum, dp_vec);
+        let sum = _mm512_mask_mov_epi32(sum, wrapped, all_ones);
+
+        let better = _mm512_cmplt_epu32_mask(sum, best_vec);
+        let ids = _mm512_add_epi32(lane_ids, _mm512_set1_epi32(j0 as i32));
+        best_vec = _mm512_mask_mov_epi32(best_vec, better, sum);
+        best_ids = _mm512_mask_mov_epi32(best_ids, better, ids);
+    }
+
+    let mut tmp = [0u32; 16];
+    let mut tmp_ids = [0u32; 16];
+    _mm512_storeu_si512(tmp.as_mut_ptr() as *mut __m512i, best_vec);
+    _mm512_storeu_si512(tmp_ids.as_mut_ptr() as *mut __m512i, best_ids);
+    let mut best = u32::MAX;
+    let mut best_j = NO_PARENT;
+    for (&v, &j) in tmp.iter().zip(&tmp_ids) {
+        if v < best { best = v; best_j = j as u8; }
+    }
+
+    for (j, &d) in col.iter().enumerate().skip(chunks * lane) {
+        if prev & (1 << j) != 0 {
+            let cost = (*row.add(j)).saturating_add(d);
+            if cost < best { best = cost; best_j = j as u8; }
+        }
+    }
+    (best, best_j)
+}
+
+/// NEON kernel for `u32` weights: 4 predecessors per step plus a scalar tail.
+/// NEON has a native saturating add, so no wrap fix-up is needed.
+///
+/// # Safety
+///
+/// See [`Kernel`]; additionally requires NEON.
+#[cfg(target_arch = "aarch64")]
+#[target_feature(enable = "neon")]
+pub unsafe fn relax_neon(row: *const u32, col: &[u32], prev: usize) -> (u32, u8) {
+    let lane = 4;
+    let chunks = col.len() / lane;
+    let lane_ids = vld1q_u32([0u32, 1, 2, 3].as_ptr());
+
+    let mut best_vec: uint32x4_t = vdupq_n_u32(u32::MAX);
+    let mut best_ids: uint32x4_t = vdupq_n_u32(u32::MAX);
+    for c in 0..chunks {
+        let j0 = c * lane;
+        let dp_vec = vld1q_u32(row.add(j0));
+        let sum = vqaddq_u32(dp_vec, vld1q_u32(col.as_ptr().add(j0)));
+
+        let better = vcltq_u32(sum, best_vec);
+        let ids = vaddq_u32(lane_ids, vdupq_n_u32(j0 as u32));
+        best_vec = vbslq_u32(better, sum, best_vec);
+        best_ids = vbslq_u32(better, ids, best_ids);
+    }
+
+    let mut tmp = [0u32; 4];
+    let mut tmp_ids = [0u32; 4];
+    vst1q_u32(tmp.as_mut_ptr(), best_vec);
+    vst1q_u32(tmp_ids.as_mut_ptr(), best_ids);
+    let mut best = u32::MAX;
+    let mut best_j = NO_PARENT;
+    for (&v, &j) in tmp.iter().zip(&tmp_ids) {
+        if v < best { best = v; best_j = j as u8; }
+    }
+
+    for (j, &d) in col.iter().enumerate().skip(chunks * lane) {
+        if prev & (1 << j) != 0 {
+            let cost = (*row.add(j)).saturating_add(d);
+            if cost < best { best = cost; best_j = j as u8; }
+        }
+    }
+    (best, best_j)
+}
@@ -1,357 +1,727 @@
 // src/lib.rs
+//
+// Without the default `std` feature only the I/O-free core is built:
+// `DpSolver` and its kernels, progress/cancellation and `TspError`, on
+// `no_std + alloc` (e.g. `wasm32-unknown-unknown`). The text front end,
+// the other solvers and the TSPLIB reader need `std`.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::{Debug, Display};
+
+#[cfg(feature = "std")]
+mod bnb;
+#[cfg(feature = "std")]
+mod bounds;
+mod checkpoint;
+#[cfg(feature = "std")]
+mod christofides;
+mod closure;
+#[cfg(feature = "std")]
+mod dot;
+mod error;
+#[cfg(feature = "std")]
+mod heuristic;
+#[cfg(feature = "std")]
+pub mod instances;
+pub mod kernels;
+#[cfg(feature = "std")]
+pub mod lk;
+mod low_memory;
+#[cfg(feature = "std")]
+mod matching;
+#[cfg(feature = "parallel")]
+mod parallel;
+mod progress;
+#[cfg(feature = "std")]
+mod scanner;
+#[cfg(feature = "std")]
+mod text_io;
+#[cfg(feature = "std")]
+pub mod tsplib;
+
+#[cfg(feature = "std")]
+pub use bnb::{BnBSolver, BNB_MAX_N};
+#[cfg(feature = "std")]
+pub use bounds::lower_bound;
+pub use checkpoint::Checkpoint;
+#[cfg(feature = "std")]
+pub use christofides::christofides;
+pub use closure::metric_closure;
+#[cfg(feature = "std")]
+pub use dot::{render_dot, render_svg};
+pub use error::{MatrixIssue, TspError};
+#[cfg(feature = "std")]
+pub use heuristic::{HeuristicSolver, HeuristicTour};
+pub use kernels::Kernel;
+pub use progress::{CancelToken, ProgressFn};
+#[cfg(feature = "std")]
+pub use text_io::{
+    solve_tsp, solve_tsp_tsplib, solve_tsp_with, try_solve, try_solve_tsp, try_solve_tsp_with,
+    Algorithm, AutoThresholds, OutputMode, Solution, SolveOptions, AUTO_BNB_MAX_N,
+    HELD_KARP_MAX_N,
+};
 
+/// Parent marker for states with no predecessor (the seed, or unreachable).
+pub const NO_PARENT: u8 = u8::MAX;
 
+/// All `n`-bit masks with exactly `k` bits set, in increasing order
+/// (Gosper's hack).
+pub(crate) fn masks_with_popcount(n: usize, k: usize) -> Vec<usize> {
+    let mut out = Vec::new();
+    if k == 0 || k > n {
+        return out;
+    }
+    let limit = 1usize << n;
+    let mut mask = (1usize << k) - 1;
+    while mask < limit {
+        out.push(mask);
+        let c = mask & mask.wrapping_neg();
+        let r = mask + c;
+        mask = (((r ^ mask) >> 2) / c) | r;
+    }
+    out
+}
 
-use std::io::{self, BufRead, Write};
-
+/// Edge weight usable by [`DpSolver`].
+///
+/// `INFINITY` marks unreachable states; `sat_add` must never wrap or produce
+/// NaN, so a min over candidates is always well defined.
+pub trait Weight: Copy + PartialOrd + Debug + Display + Send + Sync + 'static {
+    const ZERO: Self;
+    const INFINITY: Self;
 
+    /// Addition that clamps to `INFINITY` instead of overflowing (or NaN).
+    fn sat_add(self, other: Self) -> Self;
 
-#[cfg(target_arch = "x86_64")]
+    /// Fastest relaxation kernel available for this type on this CPU.
+    fn kernel() -> Kernel<Self> {
+        kernels::relax_scalar::<Self>
+    }
+}
 
-use std::arch::x86_64::{
+impl Weight for u32 {
+    const ZERO: Self = 0;
+    const INFINITY: Self = u32::MAX;
 
-    __m256i, _mm256_add_epi32, _mm256_loadu_si256, _mm256_min_epu32, _mm256_set1_epi32,
+    fn sat_add(self, other: Self) -> Self {
+        self.saturating_add(other)
+    }
 
-    _mm256_storeu_si256,
+    /// AVX-512, then AVX2 (x86_64) or NEON (aarch64), then scalar, as the
+    /// CPU allows (without `std`, as the compile-time target features
+    /// allow). The `force-scalar`, `force-avx2` and `force-avx512` features
+    /// pin a path for benchmarking, falling back to scalar on a CPU without
+    /// it; with more than one, the narrower path wins.
+    fn kernel() -> Kernel<Self> {
+        if cfg!(feature = "force-scalar") {
+            return kernels::relax_scalar::<u32>;
+        }
+        #[cfg(target_arch = "x86_64")]
+        let skip_avx2 = cfg!(feature = "force-avx512") && !cfg!(feature = "force-avx2");
+        #[cfg(all(target_arch = "x86_64", feature = "std"))]
+        {
+            if !cfg!(feature = "force-avx2") && is_x86_feature_detected!("avx512f") {
+                return kernels::relax_avx512;
+            }
+            if !skip_avx2 && is_x86_feature_detected!("avx2") {
+                return kernels::relax_avx2;
+            }
+        }
+        #[cfg(all(target_arch = "x86_64", not(feature = "std")))]
+        {
+            if !cfg!(feature = "force-avx2") && cfg!(target_feature = "avx512f") {
+                return kernels::relax_avx512;
+            }
+            if !skip_avx2 && cfg!(target_feature = "avx2") {
+                return kernels::relax_avx2;
+            }
+        }
+        #[cfg(all(target_arch = "aarch64", feature = "std"))]
+        {
+            if std::arch::is_aarch64_feature_detected!("neon") {
+                return kernels::relax_neon;
+            }
+        }
+        #[cfg(all(target_arch = "aarch64", not(feature = "std")))]
+        {
+            if cfg!(target_feature = "neon") {
+                return kernels::relax_neon;
+            }
+        }
+        kernels::relax_scalar::<u32>
+    }
+}
 
-};
+impl Weight for u64 {
+    const ZERO: Self = 0;
+    const INFINITY: Self = u64::MAX;
 
+    fn sat_add(self, other: Self) -> Self {
+        self.saturating_add(other)
+    }
+}
 
+impl Weight for f64 {
+    const ZERO: Self = 0.0;
+    const INFINITY: Self = f64::INFINITY;
 
-/// Solver for the bitmask‐DP Traveling Salesman Problem.
+    fn sat_add(self, other: Self) -> Self {
+        let sum = self + other;
+        if sum.is_nan() { f64::INFINITY } else { sum }
+    }
+}
 
-pub struct DpSolver {
+/// Whether the route must return to where it started.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PathMode {
+    /// Hamiltonian cycle through the solver's start city (the classic TSP).
+    #[default]
+    Cycle,
+    /// Hamiltonian path with no return edge, beginning at `start` if given
+    /// and at whichever city is cheapest otherwise.
+    Open { start: Option<usize> },
+}
 
-    pub n: usize,
+/// How [`DpSolver`] treats sums that exceed the weight type.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ArithmeticMode {
+    /// Clamp to `INFINITY` and carry on; states that saturate simply lose
+    /// every comparison, so only a saturated optimum shows.
+    #[default]
+    Saturating,
+    /// Also check every state for saturation once the table is filled
+    /// (about doubling the work); `try_compute` then fails with
+    /// [`TspError::Overflow`] even if the optimum itself fits.
+    Checked,
+}
 
-    pub dist: Vec<Vec<u32>>,
+/// Largest `n` that [`DpSolver::try_new`] and `solve_tsp` accept for the
+/// DP by default: about 2 GiB of tables with `u32` weights.
+pub const DP_MAX_N: usize = 24;
 
-    pub dp: Vec<u32>,
+/// [`DpSolver::memory_estimate`] for the default `u32` weights.
+pub fn memory_estimate(n: usize) -> Option<usize> {
+    DpSolver::<u32>::memory_estimate(n)
+}
 
+/// Optimal cycle lengths of several instances, reusing one solver's tables
+/// across them (see [`DpSolver::reset`]).
+pub fn solve_many<W, I>(matrices: I) -> Vec<W>
+where
+    W: Weight,
+    I: IntoIterator<Item = Vec<Vec<W>>>,
+{
+    let mut solver = DpSolver::new(0, Vec::new());
+    matrices
+        .into_iter()
+        .map(|dist| {
+            solver.reset(dist);
+            solver.compute()
+        })
+        .collect()
 }
 
+/// Entries in the `(1 << n) * n` DP table, or `None` if that overflows.
+fn table_len(n: usize) -> Option<usize> {
+    1usize.checked_shl(u32::try_from(n).ok()?)?.checked_mul(n)
+}
 
+/// Everything wrong with `dist` as an `n`-city matrix, row by row.
+fn matrix_issues<W: Weight>(n: usize, dist: &[Vec<W>], symmetric: bool) -> Vec<MatrixIssue> {
+    let mut issues = Vec::new();
+    if dist.len() != n {
+        issues.push(MatrixIssue::RowCount { expected: n, got: dist.len() });
+    }
+    for (i, row) in dist.iter().enumerate() {
+        if row.len() != n {
+            issues.push(MatrixIssue::RowLength { row: i, expected: n, got: row.len() });
+            continue;
+        }
+        for (j, &d) in row.iter().enumerate() {
+            // NaN compares as neither above nor below zero
+            if d.partial_cmp(&W::ZERO).is_none_or(|o| o.is_lt()) {
+                issues.push(MatrixIssue::Negative { from: i, to: j });
+            } else if d >= W::INFINITY {
+                issues.push(MatrixIssue::Overflowing { from: i, to: j });
+            } else if i == j && d != W::ZERO {
+                issues.push(MatrixIssue::NonZeroDiagonal { city: i });
+            } else if symmetric && j < i && dist.get(j).and_then(|r| r.get(i)) != Some(&d) {
+                issues.push(MatrixIssue::Asymmetric { from: j, to: i });
+            }
+        }
+    }
+    issues
+}
 
-impl DpSolver {
+/// Solver for the bitmask‐DP Traveling Salesman Problem.
+pub struct DpSolver<W: Weight = u32> {
+    pub n: usize,
+    pub dist: Vec<Vec<W>>,
+    pub dp: Vec<W>,
+    /// `parent[mask * n + i]` is the city visited just before `i` on the best
+    /// path for state `(mask, i)`, or [`NO_PARENT`].
+    pub parent: Vec<u8>,
+    /// Final city of the optimal route, once `compute` has run.
+    last: Option<usize>,
+    /// Where cycles begin and end (city 0 unless configured).
+    start: usize,
+    /// City that must be visited last (before the return edge of a cycle).
+    end: Option<usize>,
+    monitor: progress::Monitor,
+    arithmetic: ArithmeticMode,
+    /// Whether the last `compute*` saw a state saturate (`Checked` only).
+    overflowed: bool,
+    /// Mode the current `dp` table was seeded for.
+    mode: PathMode,
+    /// Popcount layers of `dp` (and `parent`) that are final: `n` after a
+    /// completed solve, fewer after a cancelled one, 0 without a table.
+    layers_done: usize,
+}
 
+impl<W: Weight> DpSolver<W> {
     /// Initialize a new solver for `n` cities with the given distance matrix.
-
-    pub fn new(n: usize, dist: Vec<Vec<u32>>) -> Self {
-
+    ///
+    /// The cost table `dp` stays empty until a `compute*` method needs it.
+    /// Nothing bounds `n` here; see [`DpSolver::try_new`].
+    pub fn new(n: usize, dist: Vec<Vec<W>>) -> Self {
         let size = (1 << n) * n;
+        DpSolver {
+            n,
+            dist,
+            dp: Vec::new(),
+            parent: vec![NO_PARENT; size],
+            last: None,
+            start: 0,
+            end: None,
+            monitor: progress::Monitor::default(),
+            arithmetic: ArithmeticMode::Saturating,
+            overflowed: false,
+            mode: PathMode::Cycle,
+            layers_done: 0,
+        }
+    }
 
-        let mut dp = vec![u32::MAX; size];
+    /// Like [`DpSolver::new`], but checks that `dist` is an `n`×`n` matrix
+    /// and refuses more than [`DP_MAX_N`] cities instead of attempting the
+    /// allocation.
+    pub fn try_new(n: usize, dist: Vec<Vec<W>>) -> Result<Self, TspError> {
+        Self::try_new_with_limit(n, dist, DP_MAX_N)
+    }
 
-        if n > 0 {
+    /// Like [`DpSolver::try_new`] with a caller-chosen largest `n`; limits
+    /// above what `usize` can address are capped there.
+    pub fn try_new_with_limit(
+        n: usize,
+        dist: Vec<Vec<W>>,
+        max_n: usize,
+    ) -> Result<Self, TspError> {
+        if dist.len() != n {
+            return Err(TspError::NonSquare { n, rows: dist.len() });
+        }
+        if let Some((i, row)) = dist.iter().enumerate().find(|(_, row)| row.len() != n) {
+            return Err(TspError::BadRow { line: i + 1, expected: n, got: row.len() });
+        }
+        if n > max_n || Self::memory_estimate(n).is_none() {
+            let addressable = (0..n).rfind(|&m| Self::memory_estimate(m).is_some());
+            let max = max_n.min(addressable.unwrap_or(0));
+            return Err(TspError::TooLarge { n, max });
+        }
+        Ok(Self::new(n, dist))
+    }
 
-            dp[(1 << 0) * n + 0] = 0;
+    /// Switch to a new instance with `dist.len()` cities, keeping the
+    /// allocated tables: no reallocation happens unless the new instance is
+    /// larger than any solved before. Endpoints go back to the defaults;
+    /// the progress callback and cancel token stay.
+    pub fn reset(&mut self, dist: Vec<Vec<W>>) {
+        let n = dist.len();
+        self.n = n;
+        self.dist = dist;
+        self.parent.clear();
+        self.parent.resize((1 << n) * n, NO_PARENT);
+        self.last = None;
+        self.start = 0;
+        self.end = None;
+        self.layers_done = 0;
+    }
 
-        }
+    /// Bytes of the cost and parent tables for `n` cities, or `None` if
+    /// that overflows `usize`. `new` allocates the parents up front and
+    /// the first `compute*` the costs (`compute_low_memory` keeps only
+    /// two layers of them).
+    pub fn memory_estimate(n: usize) -> Option<usize> {
+        table_len(n)?.checked_mul(core::mem::size_of::<W>() + 1)
+    }
 
-        DpSolver { n, dist, dp }
+    /// Check that `dist` is an `n`×`n` matrix with a zero diagonal and no
+    /// negative, NaN or `INFINITY` entries; the solvers would otherwise
+    /// return a meaningless (or saturated) cost without complaint.
+    ///
+    /// Reports every problem found, not just the first.
+    pub fn validate(&self) -> Result<(), TspError> {
+        Self::check(matrix_issues(self.n, &self.dist, false))
+    }
 
+    /// [`DpSolver::validate`], also requiring `d(i,j) == d(j,i)`.
+    pub fn validate_symmetric(&self) -> Result<(), TspError> {
+        Self::check(matrix_issues(self.n, &self.dist, true))
     }
 
+    fn check(issues: Vec<MatrixIssue>) -> Result<(), TspError> {
+        if issues.is_empty() { Ok(()) } else { Err(TspError::InvalidMatrix(issues)) }
+    }
 
+    /// Like [`DpSolver::new`], but cycles start at `start` instead of city 0
+    /// and, if `end` is given, every route must finish at that city.
+    ///
+    /// Fails with [`TspError::NoSuchCity`] if either index is out of range,
+    /// or [`TspError::EndIsStart`] if a cycle would have to end where it
+    /// starts.
+    pub fn with_endpoints(
+        n: usize,
+        dist: Vec<Vec<W>>,
+        start: usize,
+        end: Option<usize>,
+    ) -> Result<Self, TspError> {
+        if n > 0 && start >= n {
+            return Err(TspError::NoSuchCity { city: start, n });
+        }
+        if let Some(e) = end {
+            if e >= n {
+                return Err(TspError::NoSuchCity { city: e, n });
+            }
+            if e == start && n > 1 {
+                return Err(TspError::EndIsStart { city: e });
+            }
+        }
+        let mut solver = Self::new(n, dist);
+        solver.start = start;
+        solver.end = end;
+        Ok(solver)
+    }
 
     /// Compute the shortest Hamiltonian cycle length.
-
     ///
+    /// For `u32` weights uses AVX-512, AVX2 or NEON SIMD if detected at runtime;
+    /// every other case runs the generic scalar DP. Returns 0 immediately
+    /// for n ≤ 1.
+    pub fn compute(&mut self) -> W {
+        self.compute_with(PathMode::Cycle)
+    }
 
-    /// Uses AVX2 SIMD if detected at runtime, otherwise falls back to scalar.
+    /// Like [`DpSolver::compute`], but fails with [`TspError::Cancelled`]
+    /// or [`TspError::Overflow`] instead of returning `INFINITY` (or, in
+    /// [`ArithmeticMode::Checked`], a cost computed past a saturation).
+    pub fn try_compute(&mut self) -> Result<W, TspError> {
+        let cost = self.compute();
+        self.verdict(cost)
+    }
 
-    /// Returns 0 immediately for n ≤ 1.
+    /// `cost` as the last `compute*` left it, or the error it amounts to.
+    fn verdict(&self, cost: W) -> Result<W, TspError> {
+        if self.monitor.interrupted {
+            return Err(TspError::Cancelled);
+        }
+        if self.overflowed || (self.n > 1 && cost == W::INFINITY) {
+            return Err(TspError::Overflow);
+        }
+        Ok(cost)
+    }
 
-    pub fn compute(&mut self) -> u32 {
+    /// Call `report(masks processed, total masks)` periodically while a
+    /// `compute*` method runs, and once with `(total, total)` at the end.
+    pub fn with_progress(mut self, report: impl FnMut(usize, usize) + Send + 'static) -> Self {
+        self.monitor.progress = Some(Box::new(report));
+        self
+    }
 
-        if self.n <= 1 {
+    /// Stop `compute*` early once `token` is cancelled; the cost is then
+    /// `INFINITY` and `tour` is `None` (`try_compute` reports
+    /// [`TspError::Cancelled`]).
+    pub fn with_cancel(mut self, token: CancelToken) -> Self {
+        self.monitor.cancel = Some(token);
+        self
+    }
 
-            return 0;
+    /// Select saturating (the default) or checked arithmetic.
+    pub fn with_arithmetic(mut self, mode: ArithmeticMode) -> Self {
+        self.arithmetic = mode;
+        self
+    }
 
+    /// Compute the optimal route length for the given `mode`.
+    ///
+    /// Panics if an `Open` start city is out of range.
+    pub fn compute_with(&mut self, mode: PathMode) -> W {
+        if self.n <= 1 {
+            return W::ZERO;
         }
-
         let full_mask = (1 << self.n) - 1;
+        self.seed(mode);
+        // SAFETY: `W::kernel` only hands out kernels the CPU supports
+        unsafe { self.fill(W::kernel(), full_mask) };
+        self.close(mode, full_mask)
+    }
 
-        #[cfg(target_arch = "x86_64")]
-
-        {
-
-            if is_x86_feature_detected!("avx2") {
-
-                // SAFETY: AVX2 support was checked
+    /// [`DpSolver::compute`] pinned to the portable scalar kernel whatever
+    /// the CPU supports; the baseline SIMD paths are measured against.
+    pub fn compute_scalar(&mut self) -> W {
+        if self.n <= 1 {
+            return W::ZERO;
+        }
+        let full_mask = (1 << self.n) - 1;
+        self.seed(PathMode::Cycle);
+        // SAFETY: the scalar kernel needs no target features
+        unsafe { self.fill(kernels::relax_scalar::<W>, full_mask) };
+        self.close(PathMode::Cycle, full_mask)
+    }
 
-                return unsafe { self.compute_simd(full_mask) };
+    /// [`DpSolver::compute`] and [`DpSolver::tour`] in one call: the
+    /// optimal cycle length and the visiting order achieving it. The order
+    /// is empty where `tour` would be `None`.
+    pub fn compute_with_path(&mut self) -> (W, Vec<usize>) {
+        let cost = self.compute();
+        (cost, self.tour().unwrap_or_default())
+    }
 
+    /// Optimal visiting order from the route's first city (for a cycle the
+    /// return edge is implicit), or `None` before `compute`, after a
+    /// cancelled one, or when the optimum saturated.
+    pub fn tour(&self) -> Option<Vec<usize>> {
+        let n = self.n;
+        if n <= 1 {
+            return Some((0..n).collect());
+        }
+        let mut city = self.last?;
+        let mut mask = (1usize << n) - 1;
+        let mut order = Vec::with_capacity(n);
+        while mask.count_ones() > 1 {
+            order.push(city);
+            let prev = self.parent[mask * n + city];
+            if prev == NO_PARENT {
+                return None;
             }
-
+            mask ^= 1 << city;
+            city = prev as usize;
         }
-
-        self.compute_scalar(full_mask)
-
+        order.push(city);
+        order.reverse();
+        Some(order)
     }
 
+    /// Optimal cycle cost anchored at each city: `out[s]` is what
+    /// [`DpSolver::with_endpoints`] with start `s` would compute.
+    ///
+    /// Every Hamiltonian cycle passes through every city, so one table
+    /// (anchored at the solver's start) answers all `n` anchors instead of
+    /// `n` separate solves: `out[s]` is the length of the optimal tour
+    /// rotated to begin at `s`, as [`DpSolver::tour_from`] returns it. An
+    /// `end` constraint is ignored here, since it is tied to one particular
+    /// start. Where no tour can be rebuilt (a cancelled or saturated
+    /// solve), every entry is what `compute` returned.
+    pub fn best_from_each_start(&mut self) -> Vec<W> {
+        let end = self.end.take();
+        let cost = self.compute();
+        self.end = end;
+        (0..self.n)
+            .map(|s| self.tour_from(s).map_or(cost, |order| self.cycle_cost(&order)))
+            .collect()
+    }
 
+    /// Length of visiting `order` and returning to its first city.
+    fn cycle_cost(&self, order: &[usize]) -> W {
+        if order.len() <= 1 {
+            return W::ZERO;
+        }
+        let next = order.iter().skip(1).chain(order.first());
+        order.iter().zip(next).fold(W::ZERO, |sum, (&a, &b)| sum.sat_add(self.dist[a][b]))
+    }
 
-    /// Scalar fallback implementation.
+    /// [`DpSolver::tour`] rotated to begin at `start`; `None` under the same
+    /// conditions, or if `start` is out of range.
+    pub fn tour_from(&self, start: usize) -> Option<Vec<usize>> {
+        let mut order = self.tour()?;
+        let at = order.iter().position(|&c| c == start)?;
+        order.rotate_left(at);
+        Some(order)
+    }
 
-    fn compute_scalar(&mut self, full: usize) -> u32 {
+    /// Cities that begin a route in `mode`.
+    fn starts(&self, mode: PathMode) -> core::ops::Range<usize> {
+        match mode {
+            PathMode::Cycle => self.start..self.start + 1,
+            PathMode::Open { start: Some(s) } => {
+                assert!(s < self.n, "start city {} out of range for n = {}", s, self.n);
+                s..s + 1
+            }
+            PathMode::Open { start: None } => 0..self.n,
+        }
+    }
 
+    /// Reset the table and plant the zero-cost starting state(s) for `mode`.
+    fn seed(&mut self, mode: PathMode) {
         let n = self.n;
+        self.dp.clear();
+        self.dp.resize((1 << n) * n, W::INFINITY);
+        self.parent.fill(NO_PARENT);
+        self.monitor.interrupted = false;
+        self.overflowed = false;
+        self.mode = mode;
+        self.layers_done = 1;
+        for s in self.starts(mode) {
+            self.dp[(1 << s) * n + s] = W::ZERO;
+        }
+    }
 
+    /// Fill every state in increasing mask order with `kernel`, stopping
+    /// early if cancelled.
+    ///
+    /// # Safety
+    ///
+    /// The CPU must support the kernel's target features.
+    unsafe fn fill(&mut self, kernel: Kernel<W>, full: usize) {
+        let n = self.n;
+        let cols = kernels::columns(&self.dist);
         for mask in 1..=full {
-
-            for i in 0..n {
-
-                if mask & (1 << i) == 0 { continue; }
-
-                let prev = mask ^ (1 << i);
-
-                if prev == 0 {         // keep the seed dp[1*n + 0] = 0
-
-                    continue;
-
-                }
-
-                let base_prev = prev * n;
-
-                let idx = mask * n + i;
-
-                let mut best = u32::MAX;
-
-                for j in 0..n {
-
-                    if prev & (1 << j) != 0 {
-
-                        let cost = self.dp[base_prev + j].saturating_add(self.dist[j][i]);
-
-                        if cost < best { best = cost; }
-
-                    }
-
-                }
-
-                self.dp[idx] = best;
-
+            if mask.is_multiple_of(progress::STRIDE) && !self.monitor.checkpoint(mask, full) {
+                // Every layer whose highest mask lies behind us is complete
+                let highest = |k: usize| ((1usize << k) - 1) << (n - k);
+                self.layers_done = (2..=n).take_while(|&k| highest(k) < mask).last().unwrap_or(1);
+                return;
             }
-
+            self.relax_mask(kernel, &cols, mask);
         }
+        self.layers_done = n;
+        self.monitor.finish(full);
+    }
 
-        // close cycle
-
-        let mut result = u32::MAX;
-
+    /// Relax every state `(mask, i)` from the row of `mask ^ (1 << i)`;
+    /// `cols` is [`kernels::columns`] of `dist`.
+    ///
+    /// # Safety
+    ///
+    /// The CPU must support the kernel's target features.
+    unsafe fn relax_mask(&mut self, kernel: Kernel<W>, cols: &[W], mask: usize) {
+        let n = self.n;
         for i in 0..n {
-
-            let cost = self
-
-                .dp[full * n + i]
-
-                .saturating_add(self.dist[i][0]);
-
-            if cost < result {
-
-                result = cost;
-
+            if mask & (1 << i) == 0 { continue; }
+            let prev = mask ^ (1 << i);
+            if prev == 0 {         // keep the seeds dp[{s}][s] = 0
+                continue;
             }
-
+            let idx = mask * n + i;
+            let (best, best_j) = kernel(self.dp.as_ptr().add(prev * n), &cols[i * n..][..n], prev);
+            self.dp[idx] = best;
+            self.parent[idx] = best_j;
         }
+    }
 
+    /// Finish the route (returning to the start for a cycle) and remember the
+    /// last city for `tour`.
+    fn close(&mut self, mode: PathMode, full: usize) -> W {
+        if self.monitor.interrupted {
+            self.last = None;
+            return W::INFINITY;
+        }
+        let n = self.n;
+        if self.arithmetic == ArithmeticMode::Checked {
+            self.overflowed = self.table_saturated(mode, full);
+        }
+        let (result, last) = self.best_end(mode, &self.dp[full * n..(full + 1) * n]);
+        self.last = last;
         result
-
     }
 
+    /// Whether state `(prev | 1 << i, i)` with cost `cost` saturated: it is
+    /// `INFINITY` although some finite state in `prev_row` has a finite
+    /// edge into `i`.
+    fn saturated(&self, prev_row: &[W], prev: usize, i: usize, cost: W) -> bool {
+        cost >= W::INFINITY
+            && (0..self.n).any(|j| {
+                prev & (1 << j) != 0 && prev_row[j] < W::INFINITY && self.dist[j][i] < W::INFINITY
+            })
+    }
 
+    /// Whether closing the route from the all-cities states saturates.
+    fn closing_saturated(&self, mode: PathMode, full_row: &[W]) -> bool {
+        let start = self.start;
+        mode == PathMode::Cycle
+            && full_row.iter().enumerate().any(|(i, &cost)| {
+                let edge = self.dist[i][start];
+                i != start
+                    && self.end.is_none_or(|e| e == i)
+                    && cost < W::INFINITY
+                    && edge < W::INFINITY
+                    && cost.sat_add(edge) >= W::INFINITY
+            })
+    }
 
-    /// Unsafe SIMD‐accelerated implementation (AVX2).
-
-    #[target_feature(enable = "avx2")]
-
-    pub unsafe fn compute_simd(&mut self, full_mask: usize) -> u32 {
-
+    /// [`ArithmeticMode::Checked`] scan of the filled table.
+    fn table_saturated(&self, mode: PathMode, full: usize) -> bool {
         let n = self.n;
+        let row = |mask: usize| &self.dp[mask * n..(mask + 1) * n];
+        let state_saturated = |mask: usize, i: usize| {
+            let prev = mask ^ (1 << i);
+            mask & (1 << i) != 0 && prev != 0 && self.saturated(row(prev), prev, i, row(mask)[i])
+        };
+        (1..=full).any(|mask| (0..n).any(|i| state_saturated(mask, i)))
+            || self.closing_saturated(mode, row(full))
+    }
 
-        let lane = 8;
-
-        let chunks = n / lane;
-
-        for mask in 1..=full_mask {
-
-            for i in 0..n {
-
-                if mask & (1 << i) == 0 { continue; }
-
-                let prev = mask ^ (1 << i);
-
-                if prev == 0 {                 continue;           }
-
-                let base = mask * n + i;
-
-                let base_prev = prev * n;
-
-
-
-                let mut best_vec: __m256i = _mm256_set1_epi32(-1);
-
-                for c in 0..chunks {
-
-                    let j0 = c * lane;
-
-                    let dp_ptr = self.dp.as_ptr().add(base_prev + j0) as *const __m256i;
-
-                    let dp_vec = _mm256_loadu_si256(dp_ptr);
-
-
-
-                    let mut ds = [0u32; 8];
-
-                    for k in 0..lane {
-
-                        ds[k] = self.dist[j0 + k][i];
-
-                    }
-
-                    let dist_vec = _mm256_loadu_si256(ds.as_ptr() as *const __m256i);
-
-
-
-                    let sum = _mm256_add_epi32(dp_vec, dist_vec);
-
-                    best_vec = _mm256_min_epu32(best_vec, sum);
-
-                }
-
-
-
-                let mut tmp = [0u32; 8];
-
-                _mm256_storeu_si256(tmp.as_mut_ptr() as *mut __m256i, best_vec);
-
-                let mut best = tmp.iter().cloned().min().unwrap_or(u32::MAX);
-
-
-
-                for j in (chunks * lane)..n {
-
-                    if prev & (1 << j) != 0 {
-
-                        let cost = self.dp[base_prev + j].saturating_add(self.dist[j][i]);
-
-                        if cost < best { best = cost; }
-
-                    }
-
-                }
-
-
-
-                self.dp[base] = best;
-
+    /// Cheapest way to finish given the costs of the all-cities states.
+    fn best_end(&self, mode: PathMode, full_row: &[W]) -> (W, Option<usize>) {
+        let start = self.start;
+        let mut result = W::INFINITY;
+        let mut last = None;
+        for (i, &cost) in full_row.iter().enumerate() {
+            if self.end.is_some_and(|e| e != i) {
+                continue;
             }
-
-        }
-
-        // close cycle
-
-        let mut result = u32::MAX;
-
-        for i in 0..n {
-
-            let cost = self
-
-                .dp[full_mask * n + i]
-
-                .saturating_add(self.dist[i][0]);
-
+            let cost = match mode {
+                PathMode::Cycle if i == start => continue,
+                PathMode::Cycle => cost.sat_add(self.dist[i][start]),
+                PathMode::Open { .. } => cost,
+            };
             if cost < result {
-
                 result = cost;
-
+                last = Some(i);
             }
-
         }
-
-        result
-
+        (result, last)
     }
-
 }
 
-
-
-/// Parse input, validate, run the solver, and write output.
-
-pub fn solve_tsp<R: BufRead, W: Write>(
-
-    input: &mut R,
-
-    output: &mut W,
-
-) -> io::Result<()> {
-
-    let mut buf = String::new();
-
-    input.read_line(&mut buf)?;
-
-    let n: usize = buf.trim().parse().map_err(|_| {
-
-        io::Error::new(io::ErrorKind::InvalidData, "Invalid N")
-
-    })?;
-
-
-
-    if n == 0 {
-
-        writeln!(output, "0")?;
-
-        return Ok(());
-
+impl DpSolver<u32> {
+    /// Unsafe SIMD‐accelerated implementation (AVX2).
+    ///
+    /// # Safety
+    ///
+    /// The CPU must support AVX2; `compute` checks this at runtime.
+    #[cfg(target_arch = "x86_64")]
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn compute_simd(&mut self, full_mask: usize) -> u32 {
+        self.seed(PathMode::Cycle);
+        self.fill(kernels::relax_avx2, full_mask);
+        self.close(PathMode::Cycle, full_mask)
     }
 
-
-
-    let mut dist = Vec::with_capacity(n);
-
-    for line_idx in 0..n {
-
-        buf.clear();
-
-        input.read_line(&mut buf)?;
-
-        let row: Vec<u32> = buf
-
-            .split_whitespace()
-
-            .map(|s| s.parse().unwrap_or(u32::MAX))
-
-            .collect();
-
-        if row.len() != n {
-
-            return Err(io::Error::new(
-
-                io::ErrorKind::InvalidData,
-
-                format!("Line {}: expected {} values, got {}", line_idx + 1, n, row.len()),
-
-            ));
-
-        }
-
-        dist.push(row);
-
+    /// Unsafe SIMD‐accelerated implementation (NEON).
+    ///
+    /// # Safety
+    ///
+    /// The CPU must support NEON; `compute` checks this at runtime.
+    #[cfg(target_arch = "aarch64")]
+    #[target_feature(enable = "neon")]
+    pub unsafe fn compute_simd(&mut self, full_mask: usize) -> u32 {
+        self.seed(PathMode::Cycle);
+        self.fill(kernels::relax_neon, full_mask);
+        self.close(PathMode::Cycle, full_mask)
     }
-
-
-
-    let mut solver = DpSolver::new(n, dist);
-
-    let ans = solver.compute();
-
-    writeln!(output, "{}", ans)?;
-
-    Ok(())
-
 }
-
-
@@ -0,0 +1,305 @@
+//! Lin–Kernighan-style local search for symmetric instances.
+//!
+//! Each step breaks an edge `(t1, t2)` of the tour, in either direction,
+//! and then grows a chain of 2-opt moves: join `t2` to a near neighbor
+//! `t3`, break `t3`'s edge `(t4, t3)` on the same side, and continue from
+//! `t4` while the running gain stays positive. The chain is cut back to its
+//! most profitable prefix, or undone and retried with the next-best first
+//! move. Restarts perturb or replace the tour and keep the best.
+
+use std::collections::VecDeque;
+use std::time::Instant;
+
+/// How [`improve`] looks for better local optima once one is reached.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Restart {
+    /// Stop at the first local optimum.
+    None,
+    /// Apply this many random double-bridge kicks to the best tour so far.
+    Kicks(usize),
+    /// Also optimize this many random tours from scratch.
+    RandomTours(usize),
+}
+
+/// Knobs for [`improve`].
+#[derive(Clone, Debug)]
+pub struct LkConfig {
+    /// Longest chain of 2-opt moves in one step.
+    pub max_depth: usize,
+    /// First moves tried per step before giving up on it.
+    pub breadth: usize,
+    /// Candidate `t3`s per city (its nearest neighbors).
+    pub neighbors: usize,
+    pub restart: Restart,
+    /// Seed for kicks and random tours.
+    pub seed: u64,
+}
+
+impl Default for LkConfig {
+    fn default() -> Self {
+        LkConfig { max_depth: 12, breadth: 5, neighbors: 8, restart: Restart::Kicks(20), seed: 1 }
+    }
+}
+
+/// Improve `tour` on the symmetric matrix `dist` until no chain helps, the
+/// restarts run out or `deadline` passes. Returns a tour that is never
+/// worse, starting at city 0.
+pub fn improve(
+    dist: &[Vec<u32>],
+    tour: &[usize],
+    config: &LkConfig,
+    deadline: Option<Instant>,
+) -> Vec<usize> {
+    let n = tour.len();
+    if n < 5 {
+        return rotated(tour.to_vec());
+    }
+    let search = Search::new(dist, config, deadline);
+    let mut best = Tour::new(tour.to_vec());
+    search.optimize(&mut best);
+    let mut best_cost = search.cost(&best.order);
+
+    let mut rng = config.seed | 1;
+    let (rounds, kick) = match config.restart {
+        Restart::None => (0, true),
+        Restart::Kicks(k) => (k, true),
+        Restart::RandomTours(k) => (k, false),
+    };
+    for _ in 0..rounds {
+        if search.expired() || (kick && n < 8) {
+            break;
+        }
+        let order = if kick {
+            double_bridge(&best.order, &mut rng)
+        } else {
+            let mut order: Vec<usize> = (0..n).collect();
+            for i in (1..n).rev() {
+                order.swap(i, (next(&mut rng) % (i as u64 + 1)) as usize);
+            }
+            order
+        };
+        let mut candidate = Tour::new(order);
+        search.optimize(&mut candidate);
+        let cost = search.cost(&candidate.order);
+        if cost < best_cost {
+            best = candidate;
+            best_cost = cost;
+        }
+    }
+    rotated(best.order)
+}
+
+/// Cyclic order with position lookup.
+struct Tour {
+    order: Vec<usize>,
+    pos: Vec<usize>,
+}
+
+impl Tour {
+    fn new(order: Vec<usize>) -> Self {
+        let mut pos = vec![0; order.len()];
+        for (i, &c) in order.iter().enumerate() {
+            pos[c] = i;
+        }
+        Tour { order, pos }
+    }
+
+    fn succ(&self, c: usize) -> usize {
+        self.order[(self.pos[c] + 1) % self.order.len()]
+    }
+
+    fn pred(&self, c: usize) -> usize {
+        let n = self.order.len();
+        self.order[(self.pos[c] + n - 1) % n]
+    }
+
+    /// Neighbor of `c` after it when walking `forward`, before it otherwise.
+    fn next(&self, c: usize, forward: bool) -> usize {
+        if forward { self.succ(c) } else { self.pred(c) }
+    }
+
+    /// Reverse the path `a -> ... -> b` in place.
+    fn reverse(&mut self, a: usize, b: usize) {
+        let n = self.order.len();
+        let (mut i, mut j) = (self.pos[a], self.pos[b]);
+        let len = (j + n - i) % n + 1;
+        for _ in 0..len / 2 {
+            self.order.swap(i, j);
+            self.pos[self.order[i]] = i;
+            self.pos[self.order[j]] = j;
+            i = (i + 1) % n;
+            j = (j + n - 1) % n;
+        }
+    }
+}
+
+struct Search<'a> {
+    dist: &'a [Vec<u32>],
+    /// Nearest neighbors of each city, closest first.
+    near: Vec<Vec<usize>>,
+    max_depth: usize,
+    breadth: usize,
+    deadline: Option<Instant>,
+}
+
+impl<'a> Search<'a> {
+    fn new(dist: &'a [Vec<u32>], config: &LkConfig, deadline: Option<Instant>) -> Self {
+        let n = dist.len();
+        let near = (0..n)
+            .map(|c| {
+                let mut others: Vec<usize> = (0..n).filter(|&o| o != c).collect();
+                others.sort_by_key(|&o| dist[c][o]);
+                others.truncate(config.neighbors);
+                others
+            })
+            .collect();
+        Search { dist, near, max_depth: config.max_depth, breadth: config.breadth.max(1), deadline }
+    }
+
+    fn d(&self, a: usize, b: usize) -> i64 {
+        i64::from(self.dist[a][b])
+    }
+
+    fn cost(&self, order: &[usize]) -> i64 {
+        (0..order.len()).map(|k| self.d(order[k], order[(k + 1) % order.len()])).sum()
+    }
+
+    fn expired(&self) -> bool {
+        self.deadline.is_some_and(|d| Instant::now() >= d)
+    }
+
+    /// Run chains from every city until none improves (don't-look bits).
+    fn optimize(&self, tour: &mut Tour) {
+        let n = tour.order.len();
+        let mut queue: VecDeque<usize> = (0..n).collect();
+        let mut queued = vec![true; n];
+        while let Some(t1) = queue.pop_front() {
+            queued[t1] = false;
+            if self.expired() {
+                return;
+            }
+            if self.chain(tour, t1, true) > 0 || self.chain(tour, t1, false) > 0 {
+                for c in [t1, tour.succ(t1), tour.pred(t1)] {
+                    if !queued[c] {
+                        queued[c] = true;
+                        queue.push_back(c);
+                    }
+                }
+            }
+        }
+    }
+
+    /// One LK step breaking `(t1, t2)` with `t2` after `t1` when walking
+    /// `forward`; returns the gain kept.
+    fn chain(&self, tour: &mut Tour, t1: usize, forward: bool) -> i64 {
+        let t2 = tour.next(t1, forward);
+        let gain = self.d(t1, t2);
+        let mut firsts = self.moves(tour, t2, gain, forward, &[t1]);
+        firsts.sort_by_key(|&(_, _, score)| std::cmp::Reverse(score));
+        for &(t3, t4, score) in firsts.iter().take(self.breadth) {
+            let kept = self.deepen(tour, t1, t2, (t3, t4, score), forward);
+            if kept > 0 {
+                return kept;
+            }
+        }
+        0
+    }
+
+    /// Candidate `(t3, t4, gain after the move)` joins from `t2` that keep
+    /// the gain positive, skipping `t3`s in `skip`.
+    fn moves(
+        &self,
+        tour: &Tour,
+        t2: usize,
+        gain: i64,
+        forward: bool,
+        skip: &[usize],
+    ) -> Vec<(usize, usize, i64)> {
+        let mut out = Vec::new();
+        for &t3 in &self.near[t2] {
+            let g1 = gain - self.d(t2, t3);
+            if g1 <= 0 {
+                break; // neighbors are sorted, so no later one helps
+            }
+            if t3 == tour.next(t2, forward) || skip.contains(&t3) {
+                continue;
+            }
+            let t4 = tour.next(t3, !forward);
+            out.push((t3, t4, g1 + self.d(t4, t3)));
+        }
+        out
+    }
+
+    /// Apply `first`, extend the chain greedily, and keep its best prefix.
+    fn deepen(
+        &self,
+        tour: &mut Tour,
+        t1: usize,
+        mut t2: usize,
+        first: (usize, usize, i64),
+        forward: bool,
+    ) -> i64 {
+        // Reversals applied so far, as the paths that undo them
+        let mut applied: Vec<(usize, usize)> = Vec::new();
+        let mut joined = vec![t1];
+        let (mut best_gain, mut best_len) = (0, 0);
+        let mut next = Some(first);
+
+        while let Some((t3, t4, gain)) = next {
+            // t1 -> t2 ... t4 -> t3 becomes t1 -> t4 ... t2 -> t3 (mirrored
+            // when walking backwards)
+            if forward {
+                tour.reverse(t2, t4);
+                applied.push((t4, t2));
+            } else {
+                tour.reverse(t4, t2);
+                applied.push((t2, t4));
+            }
+            joined.push(t3);
+            let closed = gain - self.d(t4, t1);
+            if closed > best_gain {
+                best_gain = closed;
+                best_len = applied.len();
+            }
+            t2 = t4;
+            next = if applied.len() < self.max_depth {
+                self.moves(tour, t2, gain, forward, &joined).into_iter().max_by_key(|&(_, _, g)| g)
+            } else {
+                None
+            };
+        }
+
+        while applied.len() > best_len {
+            let (a, b) = applied.pop().unwrap();
+            tour.reverse(a, b);
+        }
+        best_gain
+    }
+}
+
+/// Cut the tour into four runs `A B C D` and reconnect them as `A C B D`.
+fn double_bridge(order: &[usize], rng: &mut u64) -> Vec<usize> {
+    let n = order.len();
+    let mut cuts = [0usize; 3];
+    for c in cuts.iter_mut() {
+        *c = 1 + (next(rng) % (n as u64 - 1)) as usize;
+    }
+    cuts.sort_unstable();
+    let [a, b, c] = cuts;
+    [&order[..a], &order[b..c], &order[a..b], &order[c..]].concat()
+}
+
+fn next(state: &mut u64) -> u64 {
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+    *state
+}
+
+/// Rotate a cyclic order so it starts at city 0.
+fn rotated(mut order: Vec<usize>) -> Vec<usize> {
+    if let Some(zero) = order.iter().position(|&c| c == 0) {
+        order.rotate_left(zero);
+    }
+    order
+}
@@ -0,0 +1,132 @@
+//! Popcount-layered Held-Karp.
+//!
+//! States only depend on states with one fewer city, so costs are kept one
+//! layer at a time: layer `k` stores the masks with `k` cities, indexed by
+//! their rank among all `k`-subsets (the combinatorial number system). At
+//! most two layers are alive, and each is scanned front to back. Parents
+//! are still kept for every state so `tour` works; at one byte per state
+//! they cost a quarter of the full `u32` table.
+
+use crate::{masks_with_popcount, progress, ArithmeticMode, DpSolver, Kernel, PathMode, Weight};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Maps masks to their index within their popcount layer.
+pub(crate) struct Ranker {
+    /// `binom[a][b]` is C(a, b).
+    binom: Vec<Vec<usize>>,
+}
+
+impl Ranker {
+    pub(crate) fn new(n: usize) -> Self {
+        let mut binom = vec![vec![0; n + 2]; n + 1];
+        for a in 0..=n {
+            binom[a][0] = 1;
+            for b in 1..=a {
+                binom[a][b] = binom[a - 1][b - 1] + binom[a - 1][b];
+            }
+        }
+        Ranker { binom }
+    }
+
+    /// Rank, among the masks with one fewer city, of `mask` minus each of
+    /// its cities: `out[i]` for every `i` in `mask`, in O(popcount) total.
+    pub(crate) fn ranks_without_each(&self, mask: usize, out: &mut [usize]) {
+        // rank = sum over the t-th lowest set bit b of C(b, t); dropping the
+        // t-th bit shifts every higher bit down one position
+        let mut below = 0;
+        let mut above: usize = 0;
+        let mut t = 0;
+        let mut m = mask;
+        while m != 0 {
+            let bit = m.trailing_zeros() as usize;
+            t += 1;
+            above += self.binom[bit][t - 1];
+            m &= m - 1;
+        }
+        let mut t = 0;
+        let mut m = mask;
+        while m != 0 {
+            let bit = m.trailing_zeros() as usize;
+            t += 1;
+            above -= self.binom[bit][t - 1];
+            out[bit] = below + above;
+            below += self.binom[bit][t];
+            m &= m - 1;
+        }
+    }
+}
+
+impl<W: Weight> DpSolver<W> {
+    /// [`DpSolver::compute`] with the layered table: roughly half the peak
+    /// memory and better locality, same result and tour.
+    ///
+    /// The full `dp` table is never allocated (and is released if an
+    /// earlier `compute*` call built it), so it is empty afterwards.
+    pub fn compute_low_memory(&mut self) -> W {
+        self.compute_low_memory_with(PathMode::Cycle)
+    }
+
+    /// Layered [`DpSolver::compute_with`].
+    pub fn compute_low_memory_with(&mut self, mode: PathMode) -> W {
+        let n = self.n;
+        if n <= 1 {
+            return W::ZERO;
+        }
+        self.dp = Vec::new();
+        self.layers_done = 0;
+        self.parent.fill(crate::NO_PARENT);
+        self.monitor.interrupted = false;
+        self.overflowed = false;
+        let checked = self.arithmetic == ArithmeticMode::Checked;
+        let total = (1 << n) - 1;
+        let mut done = n;
+        let kernel: Kernel<W> = W::kernel();
+        let cols = crate::kernels::columns(&self.dist);
+        let ranker = Ranker::new(n);
+        let mut prev_ranks = vec![0; n];
+
+        // Layer 1: mask {s} has rank s
+        let mut prev_layer = vec![W::INFINITY; n * n];
+        for s in self.starts(mode) {
+            prev_layer[s * n + s] = W::ZERO;
+        }
+        for k in 2..=n {
+            let masks = masks_with_popcount(n, k);
+            let mut layer = vec![W::INFINITY; masks.len() * n];
+            for (rank, &mask) in masks.iter().enumerate() {
+                done += 1;
+                if done.is_multiple_of(progress::STRIDE) && !self.monitor.checkpoint(done, total) {
+                    self.last = None;
+                    return W::INFINITY;
+                }
+                ranker.ranks_without_each(mask, &mut prev_ranks);
+                for i in 0..n {
+                    if mask & (1 << i) == 0 {
+                        continue;
+                    }
+                    let prev = mask ^ (1 << i);
+                    let row = &prev_layer[prev_ranks[i] * n..][..n];
+                    // SAFETY: `row` holds the n entries for `prev`; `W::kernel`
+                    // only hands out kernels the CPU supports
+                    let (best, best_j) = unsafe { kernel(row.as_ptr(), &cols[i * n..][..n], prev) };
+                    if checked && self.saturated(row, prev, i, best) {
+                        self.overflowed = true;
+                    }
+                    layer[rank * n + i] = best;
+                    self.parent[mask * n + i] = best_j;
+                }
+            }
+            prev_layer = layer;
+        }
+
+        self.monitor.finish(total);
+        if checked && self.closing_saturated(mode, &prev_layer) {
+            self.overflowed = true;
+        }
+        // The last layer is the single all-cities mask
+        let (result, last) = self.best_end(mode, &prev_layer);
+        self.last = last;
+        result
+    }
+}
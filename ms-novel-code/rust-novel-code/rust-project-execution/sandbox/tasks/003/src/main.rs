@@ -1,19 +1,208 @@
 // src/main.rs
 
+use clap::{Parser, ValueEnum};
+use std::fs::{self, File};
+use std::io::{self, BufReader};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use task_ws::instances;
+use task_ws::{
+    render_dot, render_svg, try_solve, Algorithm, AutoThresholds, OutputMode, Solution,
+    SolveOptions, TspError,
+};
 
+#[derive(Parser)]
+#[command(
+    about = "Solve a TSP instance given as `N` + matrix, `COORDS N` + points, `SPARSE N` + \
+             matrix with `-` for missing edges, or `EDGES N M` + `u v w` lines"
+)]
+struct Args {
+    /// Read the instance from this file instead of stdin.
+    #[arg(long, value_name = "FILE")]
+    input: Option<PathBuf>,
 
-use std::io::{self, BufRead, Write};
+    /// What to print once solved.
+    #[arg(long, value_enum, default_value_t = Output::Cost)]
+    output: Output,
 
-use task_ws::solve_tsp; 
+    /// Solver to run; `auto` picks Held-Karp, branch and bound or the
+    /// heuristic by size and available memory.
+    #[arg(long, value_enum, default_value_t = Solver::Auto)]
+    algorithm: Solver,
 
+    /// Largest instance `auto` solves with Held-Karp.
+    #[arg(long, value_name = "N", default_value_t = AutoThresholds::default().held_karp_max_n)]
+    dp_max_n: usize,
 
+    /// Largest instance `auto` solves with branch and bound.
+    #[arg(long, value_name = "N", default_value_t = AutoThresholds::default().bnb_max_n)]
+    bnb_max_n: usize,
 
-fn main() -> io::Result<()> {
+    /// Memory Held-Karp may use under `auto`, in MiB; defaults to what the
+    /// OS reports as available.
+    #[arg(long, value_name = "MIB")]
+    memory_limit: Option<usize>,
+
+    /// Report on stderr which solver ran and whether the answer is
+    /// provably optimal.
+    #[arg(long)]
+    explain: bool,
+
+    /// Seconds the heuristic may search, or Held-Karp may run before it
+    /// gives up.
+    #[arg(long, value_name = "SECONDS", value_parser = parse_seconds)]
+    time_limit: Option<Duration>,
+
+    /// Run Held-Karp on this many threads, layer by layer (0 for one per
+    /// CPU); past the available memory it keeps only two layers of costs.
+    #[cfg(feature = "parallel")]
+    #[arg(long, value_name = "N")]
+    threads: Option<usize>,
+
+    /// Also draw the tour to this file: SVG if it ends in `.svg`, Graphviz
+    /// DOT otherwise. Needs `COORDS` input.
+    #[arg(long, value_name = "PATH")]
+    emit_dot: Option<PathBuf>,
+
+    /// Print a random instance of this kind instead of solving one.
+    #[arg(long, value_enum, value_name = "KIND")]
+    generate: Option<Kind>,
 
-    let stdin = io::stdin();
+    /// Cities in the `--generate`d instance.
+    #[arg(long, value_name = "N", default_value_t = 10, requires = "generate")]
+    cities: usize,
 
-    let stdout = io::stdout();
+    /// Seed for `--generate`; equal seeds give equal instances.
+    #[arg(long, default_value_t = 1, requires = "generate")]
+    seed: u64,
 
-    solve_tsp(&mut stdin.lock(), &mut stdout.lock())
+    /// Same as `--algorithm heuristic`.
+    #[arg(long, hide = true)]
+    heuristic: bool,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Output {
+    Cost,
+    Tour,
+    Both,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Solver {
+    Auto,
+    Dp,
+    Bnb,
+    Heuristic,
+    Christofides,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Kind {
+    /// Asymmetric matrix with entries in 1..=100.
+    Uniform,
+    /// Points spread over a 1000×1000 square.
+    Euclidean,
+    /// Points around √n cluster centers in a 1000×1000 square.
+    Clustered,
+}
+
+fn parse_seconds(arg: &str) -> Result<Duration, String> {
+    let secs: f64 = arg.parse().map_err(|_| format!("not a number: {}", arg))?;
+    Duration::try_from_secs_f64(secs).map_err(|e| e.to_string())
+}
+
+fn main() -> io::Result<()> {
+    let args = Args::parse();
+    if let Some(kind) = args.generate {
+        let (n, seed) = (args.cities, args.seed);
+        let instance = match kind {
+            Kind::Uniform => instances::matrix_input(&instances::uniform_matrix(n, 100, seed)),
+            Kind::Euclidean => instances::coords_input(&instances::euclidean_points(n, 1000, seed)),
+            Kind::Clustered => {
+                let clusters = (n as f64).sqrt().ceil() as usize;
+                instances::coords_input(&instances::clustered_points(n, clusters, 1000, seed))
+            }
+        };
+        print!("{}", instance);
+        return Ok(());
+    }
+    let algorithm = match args.algorithm {
+        // `--heuristic` trades the exactness guarantee for speed on large n
+        _ if args.heuristic => Algorithm::Heuristic,
+        Solver::Auto => Algorithm::Auto,
+        Solver::Dp => Algorithm::HeldKarp,
+        Solver::Bnb => Algorithm::BranchAndBound,
+        Solver::Heuristic => Algorithm::Heuristic,
+        Solver::Christofides => Algorithm::Christofides,
+    };
+    let output = match args.output {
+        Output::Cost => OutputMode::Cost,
+        Output::Tour => OutputMode::Tour,
+        Output::Both => OutputMode::Both,
+    };
+    let auto = AutoThresholds {
+        held_karp_max_n: args.dp_max_n,
+        bnb_max_n: args.bnb_max_n,
+        memory_limit: args.memory_limit.map(|mib| mib.saturating_mul(1 << 20)),
+    };
+    let opts =
+        SolveOptions { output, algorithm, time_limit: args.time_limit, auto, ..Default::default() };
+    #[cfg(feature = "parallel")]
+    let opts = SolveOptions { threads: args.threads, ..opts };
+
+    let solution = match &args.input {
+        Some(path) => try_solve(&mut BufReader::new(File::open(path)?), &opts),
+        None => try_solve(&mut io::stdin().lock(), &opts),
+    };
+    let solution = match solution {
+        Ok(solution) => solution,
+        Err(TspError::Infeasible { .. }) => {
+            println!("INFEASIBLE");
+            return Ok(());
+        }
+        Err(err) => return Err(err.into()),
+    };
+    solution.write(&mut io::stdout().lock(), output)?;
+    if args.explain {
+        explain(&solution);
+    }
+    match &args.emit_dot {
+        Some(path) => emit_drawing(path, &solution),
+        None => Ok(()),
+    }
+}
+
+fn explain(solution: &Solution) {
+    let solver = match solution.algorithm {
+        Algorithm::Auto | Algorithm::HeldKarp => "dp",
+        Algorithm::BranchAndBound => "bnb",
+        Algorithm::Heuristic => "heuristic",
+        Algorithm::Christofides => "christofides",
+    };
+    match (solution.optimal, solution.lower_bound, solution.gap()) {
+        (true, _, _) => eprintln!("algorithm: {}, optimal", solver),
+        (false, Some(bound), Some(gap)) => eprintln!(
+            "algorithm: {}, not proven optimal: at most {:.2}% above the lower bound {}",
+            solver,
+            gap * 100.0,
+            bound
+        ),
+        (false, _, _) => eprintln!("algorithm: {}, not proven optimal", solver),
+    }
+}
 
+fn emit_drawing(path: &Path, solution: &Solution) -> io::Result<()> {
+    let (Some(tour), Some(coords)) = (&solution.tour, &solution.coords) else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "--emit-dot needs a tour and COORDS input",
+        ));
+    };
+    let drawing = if path.extension().is_some_and(|e| e.eq_ignore_ascii_case("svg")) {
+        render_svg(tour, coords)
+    } else {
+        render_dot(tour, coords)
+    };
+    fs::write(path, drawing)
 }
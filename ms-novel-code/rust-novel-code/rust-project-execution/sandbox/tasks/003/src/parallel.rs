@@ -0,0 +1,230 @@
+//! Rayon-parallel Held-Karp (feature `parallel`).
+//!
+//! States only depend on states with one fewer city, so all masks of the
+//! same popcount can be relaxed concurrently, one layer after another. Each
+//! layer is cut into chunks whose rows fit in L1, and every worker relaxes
+//! a whole chunk with the SIMD kernel before stealing the next one. The
+//! table's pages are first touched by the workers, so on NUMA machines
+//! they spread across nodes instead of all landing on the caller's.
+//!
+//! The same layers also run over the two-layer storage of
+//! `compute_low_memory`, and [`DpSolver::compute_parallel_on`] picks that
+//! when the full table would not fit in the memory available.
+
+use crate::low_memory::Ranker;
+use crate::text_io::available_memory;
+use crate::{
+    kernels, masks_with_popcount, table_len, ArithmeticMode, DpSolver, Kernel, PathMode, TspError,
+    Weight, NO_PARENT,
+};
+use rayon::prelude::*;
+
+/// Bytes of DP rows one chunk of a layer writes.
+const CHUNK_BYTES: usize = 32 << 10;
+
+/// Raw view of the DP table shared across one layer's workers.
+struct Table<W> {
+    dp: *mut W,
+    parent: *mut u8,
+}
+
+// SAFETY: workers of a layer write disjoint rows and only read rows of the
+// previous layer, which nobody writes concurrently.
+unsafe impl<W: Send> Send for Table<W> {}
+unsafe impl<W: Send> Sync for Table<W> {}
+
+/// Raw view of the parent table for the layered path, whose costs live in
+/// per-layer vectors the workers split safely.
+struct Parents(*mut u8);
+
+// SAFETY: each state `(mask, i)` belongs to exactly one worker's chunk.
+unsafe impl Send for Parents {}
+unsafe impl Sync for Parents {}
+
+impl<W: Weight> DpSolver<W> {
+    /// Parallel [`DpSolver::compute`]: same result, layers spread over rayon's pool.
+    pub fn compute_parallel(&mut self) -> W {
+        self.compute_parallel_with(PathMode::Cycle)
+    }
+
+    /// Parallel [`DpSolver::compute`] on a pool of `threads` workers (0
+    /// for one per CPU) instead of rayon's global one. Uses the full table
+    /// if it is already allocated or fits in the memory the OS reports as
+    /// available, and the layered storage of
+    /// [`DpSolver::compute_parallel_low_memory`] otherwise.
+    pub fn compute_parallel_on(&mut self, threads: usize) -> W {
+        self.compute_parallel_on_with(threads, PathMode::Cycle)
+    }
+
+    /// [`DpSolver::compute_parallel_on`] with the errors of
+    /// [`DpSolver::try_compute`].
+    pub fn try_compute_parallel_on(&mut self, threads: usize) -> Result<W, TspError> {
+        let cost = self.compute_parallel_on(threads);
+        self.verdict(cost)
+    }
+
+    /// [`DpSolver::compute_parallel_on`] for the given `mode`.
+    pub fn compute_parallel_on_with(&mut self, threads: usize, mode: PathMode) -> W {
+        let full_table = table_len(self.n).and_then(|len| {
+            let bytes = len.checked_mul(core::mem::size_of::<W>())?;
+            Some(self.dp.capacity() >= len || available_memory().is_none_or(|free| bytes <= free))
+        });
+        let mut run = || {
+            if full_table == Some(true) {
+                self.compute_parallel_with(mode)
+            } else {
+                self.compute_parallel_low_memory_with(mode)
+            }
+        };
+        match rayon::ThreadPoolBuilder::new().num_threads(threads).build() {
+            Ok(pool) => pool.install(run),
+            // no threads to spare: the global pool still works
+            Err(_) => run(),
+        }
+    }
+
+    /// Parallel [`DpSolver::compute_with`].
+    pub fn compute_parallel_with(&mut self, mode: PathMode) -> W {
+        let n = self.n;
+        if n <= 1 {
+            return W::ZERO;
+        }
+        let full_mask = (1 << n) - 1;
+        let len = table_len(n).expect("DP table size overflows usize");
+        if self.dp.capacity() < len {
+            self.dp = (0..len).into_par_iter().map(|_| W::INFINITY).collect();
+        }
+        self.seed(mode);
+        let kernel: Kernel<W> = W::kernel();
+        let table = Table { dp: self.dp.as_mut_ptr(), parent: self.parent.as_mut_ptr() };
+        let cols = kernels::columns(&self.dist);
+        let cols = &cols;
+        let chunk = (CHUNK_BYTES / (n * core::mem::size_of::<W>())).max(1);
+        let mut done = n;
+        for k in 2..=n {
+            // Checked between layers: workers never see the monitor
+            if !self.monitor.checkpoint(done, full_mask) {
+                self.layers_done = k - 1;
+                break;
+            }
+            let masks = masks_with_popcount(n, k);
+            done += masks.len();
+            masks.par_chunks(chunk).for_each(|masks| {
+                let table = &table;
+                for &mask in masks {
+                    for i in 0..n {
+                        if mask & (1 << i) == 0 {
+                            continue;
+                        }
+                        let prev = mask ^ (1 << i);
+                        let col = &cols[i * n..][..n];
+                        // SAFETY: see `Table`; the kernel is supported by this CPU
+                        unsafe {
+                            let (best, best_j) = kernel(table.dp.add(prev * n), col, prev);
+                            table.dp.add(mask * n + i).write(best);
+                            table.parent.add(mask * n + i).write(best_j);
+                        }
+                    }
+                }
+            });
+        }
+        if !self.monitor.interrupted {
+            self.layers_done = n;
+            self.monitor.finish(full_mask);
+        }
+        self.close(mode, full_mask)
+    }
+
+    /// Parallel [`DpSolver::compute_low_memory`]: each layer's masks are
+    /// relaxed concurrently into a fresh layer vector, so the full `dp`
+    /// table is never allocated.
+    pub fn compute_parallel_low_memory(&mut self) -> W {
+        self.compute_parallel_low_memory_with(PathMode::Cycle)
+    }
+
+    /// Parallel [`DpSolver::compute_low_memory_with`].
+    pub fn compute_parallel_low_memory_with(&mut self, mode: PathMode) -> W {
+        let n = self.n;
+        if n <= 1 {
+            return W::ZERO;
+        }
+        self.dp = Vec::new();
+        self.layers_done = 0;
+        self.parent.fill(NO_PARENT);
+        self.monitor.interrupted = false;
+        self.overflowed = false;
+        let checked = self.arithmetic == ArithmeticMode::Checked;
+        let total = (1 << n) - 1;
+        let kernel: Kernel<W> = W::kernel();
+        let cols = kernels::columns(&self.dist);
+        let cols = &cols;
+        let ranker = Ranker::new(n);
+        let ranker = &ranker;
+        let parents = Parents(self.parent.as_mut_ptr());
+        let chunk = (CHUNK_BYTES / (n * core::mem::size_of::<W>())).max(1);
+
+        // Layer 1: mask {s} has rank s
+        let mut prev_layer = vec![W::INFINITY; n * n];
+        for s in self.starts(mode) {
+            prev_layer[s * n + s] = W::ZERO;
+        }
+        let mut done = n;
+        for k in 2..=n {
+            if !self.monitor.checkpoint(done, total) {
+                self.last = None;
+                return W::INFINITY;
+            }
+            let masks = masks_with_popcount(n, k);
+            done += masks.len();
+            let mut layer = vec![W::INFINITY; masks.len() * n];
+            let prev_layer_ref = &prev_layer;
+            layer.par_chunks_mut(chunk * n).zip(masks.par_chunks(chunk)).for_each(|(rows, masks)| {
+                let parents = &parents;
+                let mut prev_ranks = vec![0; n];
+                for (row, &mask) in rows.chunks_mut(n).zip(masks) {
+                    ranker.ranks_without_each(mask, &mut prev_ranks);
+                    for i in 0..n {
+                        if mask & (1 << i) == 0 {
+                            continue;
+                        }
+                        let prev = mask ^ (1 << i);
+                        let prev_row = &prev_layer_ref[prev_ranks[i] * n..][..n];
+                        // SAFETY: see `Parents`; the kernel is supported by this CPU
+                        unsafe {
+                            let (best, best_j) = kernel(prev_row.as_ptr(), &cols[i * n..][..n], prev);
+                            row[i] = best;
+                            parents.0.add(mask * n + i).write(best_j);
+                        }
+                    }
+                }
+            });
+            // the solver is not `Sync`, so saturation is checked afterwards
+            if checked && !self.overflowed {
+                self.overflowed = self.layer_saturated(&masks, &prev_layer, &layer, ranker);
+            }
+            prev_layer = layer;
+        }
+
+        self.monitor.finish(total);
+        if checked && self.closing_saturated(mode, &prev_layer) {
+            self.overflowed = true;
+        }
+        let (result, last) = self.best_end(mode, &prev_layer);
+        self.last = last;
+        result
+    }
+
+    /// [`ArithmeticMode::Checked`] scan of one finished layer.
+    fn layer_saturated(&self, masks: &[usize], prev_layer: &[W], layer: &[W], ranker: &Ranker) -> bool {
+        let n = self.n;
+        let mut prev_ranks = vec![0; n];
+        masks.iter().enumerate().any(|(rank, &mask)| {
+            ranker.ranks_without_each(mask, &mut prev_ranks);
+            (0..n).any(|i| {
+                let prev = mask ^ (1 << i);
+                mask & (1 << i) != 0
+                    && self.saturated(&prev_layer[prev_ranks[i] * n..][..n], prev, i, layer[rank * n + i])
+            })
+        })
+    }
+}
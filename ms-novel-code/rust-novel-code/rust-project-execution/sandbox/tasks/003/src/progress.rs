@@ -0,0 +1,64 @@
+//! Progress reporting and cooperative cancellation for long DP runs.
+//!
+//! The DP loops call into a [`Monitor`] every [`STRIDE`] masks (once per
+//! layer in the parallel path), so neither costs anything measurable.
+
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+/// Masks relaxed between two progress reports / cancellation checks.
+pub(crate) const STRIDE: usize = 1 << 12;
+
+/// Callback receiving `(masks processed, total masks)`.
+pub type ProgressFn = Box<dyn FnMut(usize, usize) + Send>;
+
+/// Shared flag asking a running solve to stop; clone it into whichever
+/// thread (or progress callback) decides when to give up.
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Progress callback and cancellation token of one solver.
+#[derive(Default)]
+pub(crate) struct Monitor {
+    pub(crate) progress: Option<ProgressFn>,
+    pub(crate) cancel: Option<CancelToken>,
+    /// Whether the last run stopped early because of `cancel`.
+    pub(crate) interrupted: bool,
+}
+
+impl Monitor {
+    /// Report `done` of `total` masks; `false` (and `interrupted` set) if
+    /// the run should stop.
+    pub(crate) fn checkpoint(&mut self, done: usize, total: usize) -> bool {
+        if let Some(report) = self.progress.as_mut() {
+            report(done, total);
+        }
+        if self.cancel.as_ref().is_some_and(CancelToken::is_cancelled) {
+            self.interrupted = true;
+            return false;
+        }
+        true
+    }
+
+    /// Final report of a run that completed.
+    pub(crate) fn finish(&mut self, total: usize) {
+        if let Some(report) = self.progress.as_mut() {
+            report(total, total);
+        }
+    }
+}
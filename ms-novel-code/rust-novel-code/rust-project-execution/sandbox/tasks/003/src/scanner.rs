@@ -0,0 +1,126 @@
+//! Byte-level scanner for the values after the header line.
+//!
+//! Works straight on the reader's buffer: no per-line `String`, no UTF-8
+//! validation and no `str::parse` for integers, so multi-megabyte matrices
+//! read at I/O speed. Values are taken in order regardless of line breaks,
+//! so rows may wrap across physical lines.
+
+use crate::TspError;
+use std::io::BufRead;
+
+pub(crate) struct Scanner<'a, R: BufRead> {
+    input: &'a mut R,
+    /// Physical line (1-based, after the header) of the next byte.
+    line: usize,
+    /// The current token's bytes, reused between tokens.
+    token: Vec<u8>,
+}
+
+impl<'a, R: BufRead> Scanner<'a, R> {
+    pub(crate) fn new(input: &'a mut R) -> Self {
+        Scanner { input, line: 1, token: Vec::new() }
+    }
+
+    /// `rows` × `cols` values converted by `parse`, which returns `None`
+    /// for malformed tokens. The line holding the last value must end
+    /// after it; anything on later lines is left unread.
+    pub(crate) fn values<T>(
+        &mut self,
+        rows: usize,
+        cols: usize,
+        parse: impl Fn(&[u8]) -> Option<T>,
+    ) -> Result<Vec<Vec<T>>, TspError> {
+        let mut out = Vec::with_capacity(rows);
+        for r in 0..rows {
+            let mut row = Vec::with_capacity(cols);
+            for c in 0..cols {
+                let Some(line) = self.next_token()? else {
+                    return Err(TspError::BadRow { line: r + 1, expected: cols, got: c });
+                };
+                let value = parse(&self.token).ok_or_else(|| self.invalid(line))?;
+                row.push(value);
+            }
+            out.push(row);
+        }
+        if rows * cols > 0 {
+            self.end_of_line()?;
+        }
+        Ok(out)
+    }
+
+    fn invalid(&self, line: usize) -> TspError {
+        TspError::InvalidValue { line, token: String::from_utf8_lossy(&self.token).into_owned() }
+    }
+
+    /// Read the next whitespace-delimited token into `self.token`; returns
+    /// its line, or `None` at end of input.
+    fn next_token(&mut self) -> Result<Option<usize>, TspError> {
+        self.token.clear();
+        loop {
+            let buf = self.input.fill_buf()?;
+            if buf.is_empty() {
+                return Ok(None);
+            }
+            let start = buf.iter().position(|b| !b.is_ascii_whitespace());
+            let skipped = start.unwrap_or(buf.len());
+            self.line += buf[..skipped].iter().filter(|&&b| b == b'\n').count();
+            self.input.consume(skipped);
+            if start.is_some() {
+                break;
+            }
+        }
+        loop {
+            let buf = self.input.fill_buf()?;
+            let end = buf.iter().position(u8::is_ascii_whitespace);
+            let taken = end.unwrap_or(buf.len());
+            self.token.extend_from_slice(&buf[..taken]);
+            self.input.consume(taken);
+            if end.is_some() || taken == 0 {
+                return Ok(Some(self.line));
+            }
+        }
+    }
+
+    /// Fail with `TrailingData` if the current line holds another token.
+    fn end_of_line(&mut self) -> Result<(), TspError> {
+        loop {
+            let buf = self.input.fill_buf()?;
+            let Some(&b) = buf.first() else { return Ok(()) };
+            if b == b'\n' {
+                return Ok(());
+            }
+            if !b.is_ascii_whitespace() {
+                let line = self.line;
+                self.next_token()?;
+                let token = String::from_utf8_lossy(&self.token).into_owned();
+                return Err(TspError::TrailingData { line, token });
+            }
+            self.input.consume(1);
+        }
+    }
+}
+
+/// Plain decimal `u32`: digits only, rejecting signs and overflow.
+pub(crate) fn parse_u32(token: &[u8]) -> Option<u32> {
+    if token.is_empty() {
+        return None;
+    }
+    token.iter().try_fold(0u32, |acc, &b| {
+        let digit = b.checked_sub(b'0').filter(|d| *d < 10)?;
+        acc.checked_mul(10)?.checked_add(u32::from(digit))
+    })
+}
+
+/// [`parse_u32`], or `Some(None)` for `-`, the missing-edge sentinel.
+pub(crate) fn parse_u32_or_missing(token: &[u8]) -> Option<Option<u32>> {
+    match token {
+        b"-" => Some(None),
+        _ => parse_u32(token).map(Some),
+    }
+}
+
+/// Finite `f64` in any notation `str::parse` accepts.
+pub(crate) fn parse_finite(token: &[u8]) -> Option<f64> {
+    let value: f64 = std::str::from_utf8(token).ok()?.parse().ok()?;
+    value.is_finite().then_some(value)
+}
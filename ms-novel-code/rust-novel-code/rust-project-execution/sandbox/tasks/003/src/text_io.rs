@@ -0,0 +1,353 @@
+//! Text front end: parse an instance, run the solver [`SolveOptions`]
+//! picks, and write the answer (feature `std`).
+
+use crate::scanner::{self, Scanner};
+use crate::{
+    christofides, lower_bound, matrix_issues, memory_estimate, metric_closure, tsplib,
+    ArithmeticMode, BnBSolver, CancelToken, DpSolver, HeuristicSolver, TspError, BNB_MAX_N,
+    DP_MAX_N,
+};
+use std::fmt::Display;
+use std::io::{self, BufRead, Write};
+use std::time::{Duration, Instant};
+
+/// What `solve_tsp_with` writes once the instance is solved.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputMode {
+    /// Only the optimal cycle length (the classic single-line output).
+    #[default]
+    Cost,
+    /// Only the visiting order, space separated, starting at city 0.
+    Tour,
+    /// The cost on the first line and the visiting order on the second.
+    Both,
+}
+
+/// Which solver `solve_tsp_with` runs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Algorithm {
+    /// Held-Karp, branch and bound or the heuristic, by instance size and
+    /// memory; see [`AutoThresholds`].
+    #[default]
+    Auto,
+    /// Always the bitmask DP (exponential memory).
+    HeldKarp,
+    /// Always [`BnBSolver`].
+    BranchAndBound,
+    /// [`HeuristicSolver`]: fast on any n, but the answer may not be optimal.
+    Heuristic,
+    /// [`christofides`]: within 1.5× of optimal; metric instances only.
+    Christofides,
+}
+
+/// Largest instance `Algorithm::Auto` hands to Held-Karp by default.
+pub const HELD_KARP_MAX_N: usize = 16;
+
+/// Largest instance `Algorithm::Auto` hands to branch and bound by default;
+/// past it the search time grows too fast to be worth the wait.
+pub const AUTO_BNB_MAX_N: usize = 40;
+
+/// Where `Algorithm::Auto` switches from one solver to the next.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AutoThresholds {
+    /// Largest `n` solved by Held-Karp.
+    pub held_karp_max_n: usize,
+    /// Largest `n` solved by branch and bound; larger instances get the
+    /// heuristic, with its gap to [`crate::lower_bound`] reported.
+    pub bnb_max_n: usize,
+    /// Bytes Held-Karp may allocate (see [`crate::memory_estimate`]);
+    /// instances needing more go to branch and bound. `None` means the
+    /// memory the OS reports as available, or no limit if it reports none.
+    pub memory_limit: Option<usize>,
+}
+
+impl Default for AutoThresholds {
+    fn default() -> Self {
+        AutoThresholds {
+            held_karp_max_n: HELD_KARP_MAX_N,
+            bnb_max_n: AUTO_BNB_MAX_N,
+            memory_limit: None,
+        }
+    }
+}
+
+/// Knobs for `solve_tsp_with`; the default matches `solve_tsp`.
+#[derive(Clone, Debug, Default)]
+pub struct SolveOptions {
+    pub output: OutputMode,
+    pub algorithm: Algorithm,
+    /// Largest `n` Held-Karp may allocate tables for; `None` means
+    /// [`DP_MAX_N`]. See [`crate::memory_estimate`] for what a limit costs.
+    pub dp_max_n: Option<usize>,
+    /// Arithmetic for Held-Karp; `Checked` turns any saturation into
+    /// [`TspError::Overflow`].
+    pub arithmetic: ArithmeticMode,
+    /// Wall-clock budget: the heuristic's search time (one second if
+    /// `None`), and the point where Held-Karp gives up with
+    /// [`TspError::Cancelled`]. Branch and bound always runs to the end.
+    pub time_limit: Option<Duration>,
+    /// How `Algorithm::Auto` picks a solver.
+    pub auto: AutoThresholds,
+    /// Run Held-Karp layer by layer on this many threads (0 for one per
+    /// CPU); see [`DpSolver::compute_parallel_on`].
+    #[cfg(feature = "parallel")]
+    pub threads: Option<usize>,
+}
+
+/// Parse input, validate, run the solver, and write output.
+pub fn solve_tsp<R: BufRead, W: Write>(
+    input: &mut R,
+    output: &mut W,
+) -> io::Result<()> {
+    solve_tsp_with(input, output, &SolveOptions::default())
+}
+
+/// Like [`solve_tsp`], with the output shaped by `opts`.
+///
+/// The first line is one of
+/// - `N`, followed by an `N`×`N` matrix;
+/// - `COORDS N`, followed by `N` lines of `x y`; coordinates are turned
+///   into Euclidean distances rounded to the nearest integer (TSPLIB
+///   `EUC_2D`);
+/// - `SPARSE N`, followed by an `N`×`N` matrix in which `-` marks a
+///   missing edge;
+/// - `EDGES N M`, followed by `M` lines of `u v w`: undirected edges
+///   between cities `u` and `v` (0-based) of length `w`.
+///
+/// Graphs with missing edges are solved on their [`metric_closure`]; if
+/// some city cannot reach another the output is `INFEASIBLE`.
+pub fn solve_tsp_with<R: BufRead, W: Write>(
+    input: &mut R,
+    output: &mut W,
+    opts: &SolveOptions,
+) -> io::Result<()> {
+    Ok(try_solve_tsp_with(input, output, opts)?)
+}
+
+/// Like [`solve_tsp`], reporting failures as a [`TspError`].
+pub fn try_solve_tsp<R: BufRead, W: Write>(
+    input: &mut R,
+    output: &mut W,
+) -> Result<(), TspError> {
+    try_solve_tsp_with(input, output, &SolveOptions::default())
+}
+
+/// Like [`solve_tsp_with`], reporting failures as a [`TspError`].
+pub fn try_solve_tsp_with<R: BufRead, W: Write>(
+    input: &mut R,
+    output: &mut W,
+    opts: &SolveOptions,
+) -> Result<(), TspError> {
+    match try_solve(input, opts) {
+        Ok(solution) => Ok(solution.write(output, opts.output)?),
+        Err(TspError::Infeasible { .. }) => Ok(writeln!(output, "INFEASIBLE")?),
+        Err(err) => Err(err),
+    }
+}
+
+/// A solved instance, as returned by [`try_solve`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Solution {
+    pub cost: u32,
+    /// Visiting order from city 0, when the solver reconstructs one. For
+    /// sparse input it is a tour of the metric closure.
+    pub tour: Option<Vec<usize>>,
+    /// City coordinates, when the input gave them (see [`crate::render_dot`]).
+    pub coords: Option<Vec<(f64, f64)>>,
+    /// The solver that ran; never `Auto`.
+    pub algorithm: Algorithm,
+    /// Whether `cost` is provably the optimum.
+    pub optimal: bool,
+    /// Lower bound on the optimum, computed when an approximate solver ran.
+    pub lower_bound: Option<u32>,
+}
+
+impl Solution {
+    /// Write the cost and/or tour as `solve_tsp_with` does.
+    pub fn write<W: Write>(&self, output: &mut W, mode: OutputMode) -> io::Result<()> {
+        write_result(output, mode, self.cost, self.tour.as_deref())
+    }
+
+    /// Relative distance of `cost` above [`Solution::lower_bound`], when
+    /// there is one (see [`crate::HeuristicTour::gap`]).
+    pub fn gap(&self) -> Option<f64> {
+        let bound = self.lower_bound?;
+        if bound == 0 {
+            return Some(if self.cost == 0 { 0.0 } else { f64::INFINITY });
+        }
+        Some((f64::from(self.cost) - f64::from(bound)) / f64::from(bound))
+    }
+}
+
+/// Parse and solve like [`try_solve_tsp_with`], returning the solution
+/// instead of writing it; an infeasible sparse graph is
+/// [`TspError::Infeasible`].
+pub fn try_solve<R: BufRead>(input: &mut R, opts: &SolveOptions) -> Result<Solution, TspError> {
+    let mut buf = String::new();
+    input.read_line(&mut buf)?;
+    let header = buf.trim();
+    let (format, count) = ["COORDS", "SPARSE", "EDGES"]
+        .into_iter()
+        .find_map(|f| Some((f, header.strip_prefix(f)?.trim())))
+        .unwrap_or(("", header));
+    if format == "EDGES" {
+        let mut counts = count.split_whitespace().map(str::parse::<usize>);
+        let (Some(Ok(n)), Some(Ok(m)), None) = (counts.next(), counts.next(), counts.next()) else {
+            return Err(TspError::InvalidN);
+        };
+        let edges = Scanner::new(input).values(m, 3, scanner::parse_u32)?;
+        return solve_matrix(metric_closure(&edge_matrix(n, &edges)?)?, opts);
+    }
+    let n: usize = count.parse().map_err(|_| TspError::InvalidN)?;
+    let mut values = Scanner::new(input);
+    if format == "SPARSE" {
+        let dist = values.values(n, n, scanner::parse_u32_or_missing)?;
+        return solve_matrix(metric_closure(&dist)?, opts);
+    }
+    if format == "COORDS" {
+        let points = values
+            .values(n, 2, scanner::parse_finite)?
+            .into_iter()
+            .map(|xy| (xy[0], xy[1]))
+            .collect::<Vec<_>>();
+        let solution = solve_matrix(tsplib::euclidean_matrix(&points), opts)?;
+        return Ok(Solution { coords: Some(points), ..solution });
+    }
+
+    let dist = values.values(n, n, scanner::parse_u32)?;
+    solve_matrix(dist, opts)
+}
+
+/// `n`×`n` matrix of the undirected `u v w` edges, keeping the shortest
+/// of parallel edges.
+fn edge_matrix(n: usize, edges: &[Vec<u32>]) -> Result<Vec<Vec<Option<u32>>>, TspError> {
+    let mut dist = vec![vec![None; n]; n];
+    for edge in edges {
+        let (u, v, w) = (edge[0] as usize, edge[1] as usize, edge[2]);
+        if let Some(&city) = [u, v].iter().find(|&&c| c >= n) {
+            return Err(TspError::NoSuchCity { city, n });
+        }
+        for (a, b) in [(u, v), (v, u)] {
+            if dist[a][b].is_none_or(|d| w < d) {
+                dist[a][b] = Some(w);
+            }
+        }
+    }
+    Ok(dist)
+}
+
+/// Like [`solve_tsp_with`], reading a TSPLIB instance (see [`tsplib`]).
+pub fn solve_tsp_tsplib<R: BufRead, W: Write>(
+    input: &mut R,
+    output: &mut W,
+    opts: &SolveOptions,
+) -> io::Result<()> {
+    let instance = tsplib::parse(input)?;
+    solve_matrix(instance.dist, opts)?.write(output, opts.output)
+}
+
+/// The solver `Algorithm::Auto` runs on `n` cities.
+fn pick_algorithm(n: usize, opts: &SolveOptions) -> Algorithm {
+    let auto = &opts.auto;
+    let memory_limit = auto.memory_limit.or_else(available_memory).unwrap_or(usize::MAX);
+    let dp_fits = n <= auto.held_karp_max_n.min(opts.dp_max_n.unwrap_or(DP_MAX_N))
+        && memory_estimate(n).is_some_and(|bytes| bytes <= memory_limit);
+    if dp_fits {
+        Algorithm::HeldKarp
+    } else if n <= auto.bnb_max_n.min(BNB_MAX_N) {
+        Algorithm::BranchAndBound
+    } else {
+        Algorithm::Heuristic
+    }
+}
+
+/// `MemAvailable` from `/proc/meminfo`, in bytes.
+pub(crate) fn available_memory() -> Option<usize> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    let line = meminfo.lines().find_map(|l| l.strip_prefix("MemAvailable:"))?;
+    let kib: usize = line.trim().strip_suffix("kB")?.trim().parse().ok()?;
+    kib.checked_mul(1024)
+}
+
+/// Run the solver `opts` selects on a parsed matrix.
+fn solve_matrix(dist: Vec<Vec<u32>>, opts: &SolveOptions) -> Result<Solution, TspError> {
+    let n = dist.len();
+    let issues = matrix_issues(n, &dist, false);
+    if !issues.is_empty() {
+        return Err(TspError::InvalidMatrix(issues));
+    }
+    let algorithm = match opts.algorithm {
+        Algorithm::Auto => pick_algorithm(n, opts),
+        other => other,
+    };
+    // Approximate answers come with a bound to measure them against
+    let bound = matches!(algorithm, Algorithm::Christofides | Algorithm::Heuristic)
+        .then(|| lower_bound(&dist));
+    let (cost, tour) = match algorithm {
+        Algorithm::Christofides => {
+            let found = christofides(&dist)?;
+            (found.cost, Some(found.tour))
+        }
+        Algorithm::Heuristic => {
+            let mut solver = HeuristicSolver::new(n, dist);
+            if let Some(limit) = opts.time_limit {
+                solver = solver.with_time_budget(limit);
+            }
+            let found = solver.solve();
+            (found.cost, Some(found.tour))
+        }
+        Algorithm::BranchAndBound => {
+            if n > BNB_MAX_N {
+                return Err(TspError::TooLarge { n, max: BNB_MAX_N });
+            }
+            let mut solver = BnBSolver::new(n, dist);
+            (solver.compute(), solver.tour())
+        }
+        Algorithm::Auto | Algorithm::HeldKarp => {
+            let max_n = opts.dp_max_n.unwrap_or(DP_MAX_N);
+            let mut solver =
+                DpSolver::try_new_with_limit(n, dist, max_n)?.with_arithmetic(opts.arithmetic);
+            if let Some(limit) = opts.time_limit {
+                let deadline = Instant::now() + limit;
+                let token = CancelToken::new();
+                let expire = token.clone();
+                solver = solver.with_cancel(token).with_progress(move |_, _| {
+                    if Instant::now() >= deadline {
+                        expire.cancel();
+                    }
+                });
+            }
+            #[cfg(feature = "parallel")]
+            let cost = match opts.threads {
+                Some(threads) => solver.try_compute_parallel_on(threads)?,
+                None => solver.try_compute()?,
+            };
+            #[cfg(not(feature = "parallel"))]
+            let cost = solver.try_compute()?;
+            (cost, solver.tour())
+        }
+    };
+    if n > 1 && cost == u32::MAX {
+        return Err(TspError::Overflow);
+    }
+    let optimal = bound.is_none_or(|bound| bound >= cost);
+    Ok(Solution { cost, tour, coords: None, algorithm, optimal, lower_bound: bound })
+}
+
+fn write_result<W: Write, C: Display>(
+    output: &mut W,
+    mode: OutputMode,
+    cost: C,
+    tour: Option<&[usize]>,
+) -> io::Result<()> {
+    if mode != OutputMode::Tour {
+        writeln!(output, "{}", cost)?;
+    }
+    if mode != OutputMode::Cost {
+        if let Some(tour) = tour {
+            let cities: Vec<String> = tour.iter().map(|c| c.to_string()).collect();
+            writeln!(output, "{}", cities.join(" "))?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,231 @@
+//! TSPLIB instance parsing.
+//!
+//! Reads the header (`KEY : VALUE` lines) and data sections of a `TSP` or
+//! `ATSP` file and produces the full distance matrix the solvers take.
+//! Supported edge weight types are `EXPLICIT` (every `EDGE_WEIGHT_FORMAT`),
+//! `EUC_2D` and `GEO`, with distances computed exactly as the TSPLIB
+//! specification prescribes so published optima can be compared directly.
+
+use std::io::{self, BufRead};
+
+/// A parsed instance.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Instance {
+    pub name: String,
+    pub dimension: usize,
+    pub dist: Vec<Vec<u32>>,
+    /// Node coordinates (or display data) when the file has them.
+    pub coords: Option<Vec<(f64, f64)>>,
+}
+
+/// Parse a TSPLIB file.
+pub fn parse<R: BufRead>(mut input: R) -> io::Result<Instance> {
+    let mut text = String::new();
+    input.read_to_string(&mut text)?;
+    let mut lines = text.lines().enumerate().map(|(i, l)| (i + 1, l));
+
+    let mut name = String::new();
+    let mut dimension = None;
+    let mut weight_type = None;
+    let mut weight_format = String::from("FULL_MATRIX");
+    let mut weights = None;
+    let mut coords = None;
+    let mut display = None;
+
+    while let Some((ln, line)) = lines.next() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (key, value) = match line.split_once(':') {
+            Some((k, v)) => (k.trim(), v.trim()),
+            None => (line, ""),
+        };
+        match key {
+            "NAME" => name = value.to_string(),
+            "TYPE" => {
+                if value != "TSP" && value != "ATSP" {
+                    return Err(bad(ln, format!("unsupported TYPE {}", value)));
+                }
+            }
+            "DIMENSION" => {
+                let n = value.parse().map_err(|_| bad(ln, format!("invalid DIMENSION {}", value)))?;
+                dimension = Some(n);
+            }
+            "EDGE_WEIGHT_TYPE" => match value {
+                "EXPLICIT" | "EUC_2D" | "GEO" => weight_type = Some(value.to_string()),
+                _ => return Err(bad(ln, format!("unsupported EDGE_WEIGHT_TYPE {}", value))),
+            },
+            "EDGE_WEIGHT_FORMAT" => weight_format = value.to_string(),
+            "COMMENT" | "NODE_COORD_TYPE" | "DISPLAY_DATA_TYPE" => {}
+            "NODE_COORD_SECTION" | "DISPLAY_DATA_SECTION" => {
+                let n = dimension.ok_or_else(|| bad(ln, format!("{} before DIMENSION", key)))?;
+                let tokens = take_tokens(&mut lines, 3 * n, ln, key)?;
+                let mut points = vec![None; n];
+                for node in tokens.chunks(3) {
+                    let number = |(ln, tok): (usize, &str)| {
+                        tok.parse::<f64>().map_err(|_| bad(ln, format!("invalid number {}", tok)))
+                    };
+                    let (id_ln, id) = node[0];
+                    let index = id
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|&i| (1..=n).contains(&i))
+                        .ok_or_else(|| {
+                            bad(id_ln, format!("node id {} out of range 1..={}", id, n))
+                        })?;
+                    points[index - 1] = Some((number(node[1])?, number(node[2])?));
+                }
+                let points = points
+                    .into_iter()
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(|| bad(ln, format!("{} repeats a node id", key)))?;
+                if key == "NODE_COORD_SECTION" {
+                    coords = Some(points);
+                } else {
+                    display = Some(points);
+                }
+            }
+            "EDGE_WEIGHT_SECTION" => {
+                let n = dimension.ok_or_else(|| bad(ln, format!("{} before DIMENSION", key)))?;
+                let count = weight_count(&weight_format, n).ok_or_else(|| {
+                    bad(ln, format!("unsupported EDGE_WEIGHT_FORMAT {}", weight_format))
+                })?;
+                let values = take_tokens(&mut lines, count, ln, key)?
+                    .into_iter()
+                    .map(|(ln, tok)| {
+                        tok.parse::<u32>().map_err(|_| bad(ln, format!("invalid weight {}", tok)))
+                    })
+                    .collect::<io::Result<Vec<u32>>>()?;
+                weights = Some(values);
+            }
+            "EOF" => break,
+            _ => return Err(bad(ln, format!("unknown keyword {}", key))),
+        }
+    }
+
+    let n = dimension.ok_or_else(|| invalid("missing DIMENSION".into()))?;
+    let dist = match weight_type.as_deref() {
+        Some("EXPLICIT") => {
+            let values = weights.ok_or_else(|| invalid("missing EDGE_WEIGHT_SECTION".into()))?;
+            explicit_matrix(&weight_format, n, &values)
+        }
+        Some(kind) => {
+            let points =
+                coords.as_ref().ok_or_else(|| invalid("missing NODE_COORD_SECTION".into()))?;
+            let metric: Metric = if kind == "GEO" { geo } else { euc_2d };
+            metric_matrix(points, metric)
+        }
+        None => return Err(invalid("missing EDGE_WEIGHT_TYPE".into())),
+    };
+    Ok(Instance { name, dimension: n, dist, coords: coords.or(display) })
+}
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("TSPLIB: {}", msg))
+}
+
+fn bad(line: usize, msg: String) -> io::Error {
+    invalid(format!("line {}: {}", line, msg))
+}
+
+/// The next `count` whitespace-separated tokens, with their line numbers.
+fn take_tokens<'a>(
+    lines: &mut impl Iterator<Item = (usize, &'a str)>,
+    count: usize,
+    section_line: usize,
+    section: &str,
+) -> io::Result<Vec<(usize, &'a str)>> {
+    let mut tokens = Vec::with_capacity(count);
+    while tokens.len() < count {
+        let Some((ln, line)) = lines.next() else {
+            let msg = format!("{} expects {} values, found {}", section, count, tokens.len());
+            return Err(bad(section_line, msg));
+        };
+        tokens.extend(line.split_whitespace().map(|tok| (ln, tok)));
+    }
+    if tokens.len() > count {
+        let (ln, _) = tokens[count];
+        return Err(bad(ln, format!("{} has more than {} values", section, count)));
+    }
+    Ok(tokens)
+}
+
+/// Number of values an `EDGE_WEIGHT_SECTION` holds in `format`.
+fn weight_count(format: &str, n: usize) -> Option<usize> {
+    match format {
+        "FULL_MATRIX" => Some(n * n),
+        "UPPER_ROW" | "LOWER_ROW" | "UPPER_COL" | "LOWER_COL" => Some(n * n.saturating_sub(1) / 2),
+        "UPPER_DIAG_ROW" | "LOWER_DIAG_ROW" | "UPPER_DIAG_COL" | "LOWER_DIAG_COL" => {
+            Some(n * (n + 1) / 2)
+        }
+        _ => None,
+    }
+}
+
+/// Expand the weights of an `EXPLICIT` instance to a full matrix.
+fn explicit_matrix(format: &str, n: usize, values: &[u32]) -> Vec<Vec<u32>> {
+    let mut dist = vec![vec![0; n]; n];
+    if format == "FULL_MATRIX" {
+        for (row, chunk) in dist.iter_mut().zip(values.chunks(n.max(1))) {
+            row.copy_from_slice(chunk);
+        }
+        return dist;
+    }
+    // Triangular formats; a column-wise upper triangle lists the same
+    // entries in the same order as a row-wise lower one, and vice versa
+    let (lower, diagonal) = match format {
+        "LOWER_ROW" | "UPPER_COL" => (true, false),
+        "LOWER_DIAG_ROW" | "UPPER_DIAG_COL" => (true, true),
+        "UPPER_ROW" | "LOWER_COL" => (false, false),
+        _ => (false, true),
+    };
+    let columns = move |i: usize| match (lower, diagonal) {
+        (true, false) => 0..i,
+        (true, true) => 0..i + 1,
+        (false, false) => i + 1..n,
+        (false, true) => i..n,
+    };
+    let cells = (0..n).flat_map(|i| columns(i).map(move |j| (i, j)));
+    for ((i, j), &d) in cells.zip(values) {
+        dist[i][j] = d;
+        dist[j][i] = d;
+    }
+    dist
+}
+
+/// `EUC_2D` distances between `points`, as a full matrix.
+pub(crate) fn euclidean_matrix(points: &[(f64, f64)]) -> Vec<Vec<u32>> {
+    metric_matrix(points, euc_2d)
+}
+
+type Metric = fn((f64, f64), (f64, f64)) -> u32;
+
+fn metric_matrix(points: &[(f64, f64)], metric: Metric) -> Vec<Vec<u32>> {
+    let d = |a: usize, b: usize| if a == b { 0 } else { metric(points[a], points[b]) };
+    (0..points.len()).map(|i| (0..points.len()).map(|j| d(i, j)).collect()).collect()
+}
+
+/// `EUC_2D`: Euclidean distance rounded to the nearest integer.
+fn euc_2d(a: (f64, f64), b: (f64, f64)) -> u32 {
+    ((a.0 - b.0).hypot(a.1 - b.1) + 0.5) as u32
+}
+
+/// `GEO`: great-circle distance in km on the TSPLIB idealized sphere, with
+/// coordinates given as `DDD.MM` degrees and minutes.
+fn geo(a: (f64, f64), b: (f64, f64)) -> u32 {
+    // The specification's truncated constants, not the exact ones
+    #[allow(clippy::approx_constant)]
+    const PI: f64 = 3.141592;
+    const RRR: f64 = 6378.388;
+    let radians = |x: f64| {
+        let deg = x.trunc();
+        PI * (deg + 5.0 * (x - deg) / 3.0) / 180.0
+    };
+    let (lat_a, lon_a) = (radians(a.0), radians(a.1));
+    let (lat_b, lon_b) = (radians(b.0), radians(b.1));
+    let q1 = (lon_a - lon_b).cos();
+    let q2 = (lat_a - lat_b).cos();
+    let q3 = (lat_a + lat_b).cos();
+    (RRR * (0.5 * ((1.0 + q1) * q2 - (1.0 - q1) * q3)).acos() + 1.0) as u32
+}
@@ -1,199 +1,1513 @@
 //! tests/integration.rs
-
 //! (save alongside your project’s Cargo.toml)
 
+use std::io::Cursor;
+use task_ws::solve_tsp;            // ← replace `task_ws` with your crate name
 
+/// Helper: run the solver and capture its single‑line output.
+fn run_ok(input: &str) -> String {
+    let mut rdr = Cursor::new(input);
+    let mut out = Vec::<u8>::new();
+    solve_tsp(&mut rdr, &mut out).unwrap();
+    String::from_utf8(out).unwrap().trim().to_string()
+}
 
-use std::io::Cursor;
+/// Helper: assert that the solver returns an error.
+fn run_err(input: &str) {
+    let mut rdr = Cursor::new(input);
+    let mut out = Vec::<u8>::new();
+    assert!(solve_tsp(&mut rdr, &mut out).is_err());
+}
 
-use task_ws::solve_tsp;            // ← replace `task_ws` with your crate name
+/* ---------- malformed‑input checks ---------- */
 
+#[test] fn invalid_n()                 { run_err("foo\n"); }
 
+#[test] fn bad_row_count()             { run_err(r#"3
+0 1 2
+3 4 5
+"#); }
 
-/// Helper: run the solver and capture its single‑line output.
+#[test] fn bad_row_too_short()         { run_err(r#"2
+0
+0 0
+"#); }
 
-fn run_ok(input: &str) -> String {
+#[test] fn bad_row_too_long()          { run_err(r#"2
+0 1 2
+0 0
+"#); }
 
-    let mut rdr = Cursor::new(input);
+/* ---------- trivial sizes ---------- */
 
-    let mut out = Vec::<u8>::new();
+#[test] fn n_zero()                   { assert_eq!(run_ok("0\n"), "0"); }
 
-    solve_tsp(&mut rdr, &mut out).unwrap();
+#[test] fn n_one()                    { assert_eq!(run_ok("1\n0\n"), "0"); }
 
-    String::from_utf8(out).unwrap().trim().to_string()
+/* ---------- prompt examples ---------- */
 
+#[test]
+fn example_four_city() {
+    let input = "4\n\
+                 0 29 20 21\n\
+                 29 0 15 17\n\
+                 20 15 0 28\n\
+                 21 17 28 0\n";
+    assert_eq!(run_ok(input), "73");
 }
 
+#[test]
+fn example_three_city() {
+    let input = "3\n\
+                 0 10 15\n\
+                 10 0 20\n\
+                 15 20 0\n";
+    assert_eq!(run_ok(input), "45");
+}
+/* ---------- edge cases & blind spots ---------- */
 
+#[test]
+fn overflow_saturates() {
+    let half = u32::MAX / 2;
+    let expect = (half * 2).to_string();              // 4 294 967 294
+    let inp = format!("2\n0 {}\n{} 0\n", half, half);
+    assert_eq!(run_ok(&inp), expect);
+}
 
-/// Helper: assert that the solver returns an error.
 
-fn run_err(input: &str) {
+#[test]
+fn simd_tail_handling() {
+    // N = 10, not a multiple of 8‑lane AVX2
+    let mut inp = String::from("10\n");
+    for _ in 0..10 { inp.push_str(&"0 ".repeat(10)); inp.push('\n'); }
+    assert_eq!(run_ok(&inp), "0");
+}
 
-    let mut rdr = Cursor::new(input);
+#[test]
+fn simd_exact_lane() {
+    // N = 8: exactly one AVX2 vector, all zeros
+    let mut inp = String::from("8\n");
+    for _ in 0..8 { inp.push_str(&"0 ".repeat(8)); inp.push('\n'); }
+    assert_eq!(run_ok(&inp), "0");
+}
 
+#[test]
+fn all_zero_n16() {
+    let mut inp = String::from("16\n");
+    for _ in 0..16 { inp.push_str(&"0 ".repeat(16)); inp.push('\n'); }
+    assert_eq!(run_ok(&inp), "0");
+}
+
+/* ---------- tour reconstruction ---------- */
+
+use task_ws::{solve_tsp_with, DpSolver, OutputMode, SolveOptions};
+
+/// Helper: run with an explicit output mode, returning the output lines.
+fn run_lines(input: &str, output: OutputMode) -> Vec<String> {
+    let mut rdr = Cursor::new(input);
     let mut out = Vec::<u8>::new();
+    solve_tsp_with(&mut rdr, &mut out, &SolveOptions { output, ..Default::default() }).unwrap();
+    String::from_utf8(out).unwrap().lines().map(str::to_string).collect()
+}
 
-    assert!(solve_tsp(&mut rdr, &mut out).is_err());
+/// Helper: cost of visiting `tour` in order and returning to its start.
+fn cycle_cost(dist: &[Vec<u32>], tour: &[usize]) -> u32 {
+    (0..tour.len()).map(|k| dist[tour[k]][tour[(k + 1) % tour.len()]]).sum()
+}
 
+/// Helper: O(n!) reference answer.
+fn brute_force(dist: &[Vec<u32>]) -> u32 {
+    fn go(dist: &[Vec<u32>], path: &mut Vec<usize>, used: u32, best: &mut u32) {
+        let n = dist.len();
+        if path.len() == n {
+            *best = (*best).min(cycle_cost(dist, path));
+            return;
+        }
+        for c in 1..n {
+            if used & (1 << c) == 0 {
+                path.push(c);
+                go(dist, path, used | (1 << c), best);
+                path.pop();
+            }
+        }
+    }
+    let mut best = u32::MAX;
+    go(dist, &mut vec![0], 1, &mut best);
+    best
 }
 
+/// Helper: deterministic pseudo-random asymmetric matrix.
+fn random_matrix(n: usize, seed: u64) -> Vec<Vec<u32>> {
+    let mut s = seed | 1;
+    (0..n)
+        .map(|i| {
+            (0..n)
+                .map(|j| {
+                    s ^= s << 13;
+                    s ^= s >> 7;
+                    s ^= s << 17;
+                    if i == j { 0 } else { (s % 100 + 1) as u32 }
+                })
+                .collect()
+        })
+        .collect()
+}
 
+#[test]
+fn example_four_city_tour() {
+    let input = "4\n0 29 20 21\n29 0 15 17\n20 15 0 28\n21 17 28 0\n";
+    let lines = run_lines(input, OutputMode::Both);
+    assert_eq!(lines[0], "73");
+    let tour: Vec<usize> = lines[1].split(' ').map(|c| c.parse().unwrap()).collect();
+    let dist = vec![
+        vec![0, 29, 20, 21],
+        vec![29, 0, 15, 17],
+        vec![20, 15, 0, 28],
+        vec![21, 17, 28, 0],
+    ];
+    assert_eq!(tour[0], 0);
+    assert_eq!(cycle_cost(&dist, &tour), 73);
+}
 
-/* ---------- malformed‑input checks ---------- */
+#[test]
+fn tour_only_output() {
+    let lines = run_lines("3\n0 10 15\n10 0 20\n15 20 0\n", OutputMode::Tour);
+    assert_eq!(lines.len(), 1);
+    let mut cities: Vec<&str> = lines[0].split(' ').collect();
+    cities.sort();
+    assert_eq!(cities, ["0", "1", "2"]);
+}
 
+#[test]
+fn simd_matches_brute_force_with_tours() {
+    // n = 9 and 10 exercise one full AVX2 vector plus a scalar tail
+    for seed in 1..=10 {
+        let n = 9 + (seed as usize % 2);
+        let dist = random_matrix(n, seed);
+        let mut solver = DpSolver::new(n, dist.clone());
+        let cost = solver.compute();
+        assert_eq!(cost, brute_force(&dist), "seed {}", seed);
+        let tour = solver.tour().unwrap();
+        let mut sorted = tour.clone();
+        sorted.sort();
+        assert_eq!(sorted, (0..n).collect::<Vec<_>>());
+        assert_eq!(cycle_cost(&dist, &tour), cost);
+    }
+}
 
+#[test]
+fn compute_with_path_matches_scalar_tour() {
+    for seed in 11..=16 {
+        let n = 6 + seed as usize % 5;
+        let dist = random_matrix(n, seed);
+        let (cost, path) = DpSolver::new(n, dist.clone()).compute_with_path();
+        let mut scalar = DpSolver::new(n, dist.clone());
+        assert_eq!(cost, scalar.compute_scalar(), "seed {}", seed);
+        assert_eq!(path[0], 0);
+        assert_eq!(path.len(), n);
+        assert_eq!(cycle_cost(&dist, &path), cost);
+        assert_eq!(cycle_cost(&dist, &scalar.tour().unwrap()), cost);
+    }
+    assert_eq!(DpSolver::new(1, vec![vec![0u32]]).compute_with_path(), (0, vec![0]));
+}
 
-#[test] fn invalid_n()                 { run_err("foo\n"); }
+/* ---------- generic weights ---------- */
 
+#[test]
+fn u64_weights_beyond_u32_range() {
+    let big = u64::from(u32::MAX) * 4;
+    let dist = vec![
+        vec![0, big, 1],
+        vec![big, 0, big],
+        vec![1, big, 0],
+    ];
+    let mut solver = DpSolver::<u64>::new(3, dist);
+    assert_eq!(solver.compute(), 2 * big + 1);
+}
 
+#[test]
+fn f64_fractional_weights() {
+    let dist = vec![
+        vec![0.0, 1.5, 2.25],
+        vec![1.5, 0.0, 0.5],
+        vec![2.25, 0.5, 0.0],
+    ];
+    let mut solver = DpSolver::<f64>::new(3, dist);
+    assert!((solver.compute() - 4.25).abs() < 1e-9);
+    assert_eq!(solver.tour().unwrap().len(), 3);
+}
 
-#[test] fn bad_row_count()             { run_err(r#"3
+#[test]
+fn f64_nan_edges_are_never_chosen() {
+    let dist = vec![
+        vec![0.0, f64::NAN, 1.0, 1.0],
+        vec![1.0, 0.0, 1.0, 1.0],
+        vec![1.0, 1.0, 0.0, 1.0],
+        vec![1.0, 1.0, 1.0, 0.0],
+    ];
+    let mut solver = DpSolver::<f64>::new(4, dist);
+    assert_eq!(solver.compute(), 4.0);
+    let tour = solver.tour().unwrap();
+    assert_ne!(tour[1], 1, "0 -> 1 is the NaN edge");
+}
 
-0 1 2
+/* ---------- open paths ---------- */
 
-3 4 5
+use task_ws::PathMode;
 
-"#); }
+/// Line graph 0 - 1 - 2 - 3 with unit hops; every other edge costs 10.
+fn line_matrix() -> Vec<Vec<u32>> {
+    (0..4)
+        .map(|i: i32| (0..4).map(|j: i32| match (i - j).abs() { 0 => 0, 1 => 1, _ => 10 }).collect())
+        .collect()
+}
 
+#[test]
+fn open_path_free_start() {
+    let mut solver = DpSolver::new(4, line_matrix());
+    assert_eq!(solver.compute_with(PathMode::Open { start: None }), 3);
+    let tour = solver.tour().unwrap();
+    assert!(tour == [0, 1, 2, 3] || tour == [3, 2, 1, 0], "{:?}", tour);
+    // The cycle still has to pay for one long edge back
+    assert_eq!(solver.compute(), 13);
+}
 
+#[test]
+fn open_path_fixed_start() {
+    let mut solver = DpSolver::new(4, line_matrix());
+    // Starting in the middle forces one backtrack across a long edge
+    assert_eq!(solver.compute_with(PathMode::Open { start: Some(1) }), 12);
+    assert_eq!(solver.tour().unwrap()[0], 1);
+}
 
-#[test] fn bad_row_too_short()         { run_err(r#"2
+/* ---------- start / end cities ---------- */
 
-0
+#[test]
+fn cycle_from_other_start_has_same_cost() {
+    let dist = random_matrix(7, 42);
+    let expected = DpSolver::new(7, dist.clone()).compute();
+    let mut solver = DpSolver::with_endpoints(7, dist.clone(), 3, None).unwrap();
+    assert_eq!(solver.compute(), expected);
+    let tour = solver.tour().unwrap();
+    assert_eq!(tour[0], 3);
+    assert_eq!(cycle_cost(&dist, &tour), expected);
+}
 
-0 0
+#[test]
+fn required_end_city() {
+    // Line graph: the best open path from 1 must end at 3, forced to end at 0
+    let mut solver = DpSolver::with_endpoints(4, line_matrix(), 1, Some(0)).unwrap();
+    assert_eq!(solver.compute_with(PathMode::Open { start: Some(1) }), 12);
+    assert_eq!(solver.tour().unwrap(), [1, 2, 3, 0]);
+
+    let mut cycle = DpSolver::with_endpoints(4, line_matrix(), 0, Some(2)).unwrap();
+    let cost = cycle.compute();
+    assert_eq!(*cycle.tour().unwrap().last().unwrap(), 2);
+    assert_eq!(cost, 1 + 10 + 1 + 10); // 0 1 3 2 0
+}
 
-"#); }
+#[test]
+fn endpoints_are_validated() {
+    assert!(DpSolver::with_endpoints(3, random_matrix(3, 1), 3, None).is_err());
+    assert!(DpSolver::with_endpoints(3, random_matrix(3, 1), 0, Some(5)).is_err());
+    assert!(DpSolver::with_endpoints(3, random_matrix(3, 1), 1, Some(1)).is_err());
+}
 
+/* ---------- parallel DP ---------- */
 
+#[cfg(feature = "parallel")]
+#[test]
+fn parallel_matches_sequential() {
+    for (seed, mode) in [(3, PathMode::Cycle), (4, PathMode::Open { start: None })] {
+        let dist = random_matrix(11, seed);
+        let mut seq = DpSolver::new(11, dist.clone());
+        let mut par = DpSolver::new(11, dist.clone());
+        let expected = seq.compute_with(mode);
+        assert_eq!(par.compute_parallel_with(mode), expected);
+        let tour = par.tour().unwrap();
+        let path: u32 = tour.windows(2).map(|w| dist[w[0]][w[1]]).sum();
+        let closing = if mode == PathMode::Cycle { dist[tour[10]][tour[0]] } else { 0 };
+        assert_eq!(path + closing, expected);
+    }
+}
 
-#[test] fn bad_row_too_long()          { run_err(r#"2
+#[cfg(feature = "parallel")]
+#[test]
+fn parallel_on_threads_matches_sequential() {
+    for (threads, mode) in [(1, PathMode::Cycle), (3, PathMode::Open { start: None }), (0, PathMode::Cycle)] {
+        let dist = random_matrix(12, 8);
+        let expected = DpSolver::new(12, dist.clone()).compute_with(mode);
+        let mut solver = DpSolver::new(12, dist.clone());
+        assert_eq!(solver.compute_parallel_on_with(threads, mode), expected, "{} threads", threads);
+        let tour = solver.tour().unwrap();
+        let path: u32 = tour.windows(2).map(|w| dist[w[0]][w[1]]).sum();
+        let closing = if mode == PathMode::Cycle { dist[tour[11]][tour[0]] } else { 0 };
+        assert_eq!(path + closing, expected);
+    }
+}
 
-0 1 2
+#[cfg(feature = "parallel")]
+#[test]
+fn parallel_low_memory_matches_full_table() {
+    let dist = random_matrix(11, 9);
+    let mut solver = DpSolver::with_endpoints(11, dist.clone(), 2, Some(5)).unwrap();
+    let expected = DpSolver::with_endpoints(11, dist.clone(), 2, Some(5)).unwrap().compute();
+    assert_eq!(solver.compute_parallel_low_memory(), expected);
+    assert!(solver.dp.is_empty());
+    let tour = solver.tour().unwrap();
+    assert_eq!((tour[0], tour[10]), (2, 5));
+    assert_eq!(cycle_cost(&dist, &tour), expected);
+
+    let input = format!("11\n{}", dist.iter().map(|r| {
+        r.iter().map(|d| d.to_string()).collect::<Vec<_>>().join(" ") + "\n"
+    }).collect::<String>());
+    let opts = SolveOptions { algorithm: Algorithm::HeldKarp, threads: Some(2), ..Default::default() };
+    assert_eq!(try_solve(&mut Cursor::new(&input), &opts).unwrap().cost, DpSolver::new(11, dist).compute());
+}
 
-0 0
+/* ---------- layered (low-memory) DP ---------- */
 
-"#); }
+#[test]
+fn low_memory_matches_full_table() {
+    for (seed, mode) in [(6, PathMode::Cycle), (7, PathMode::Open { start: None })] {
+        let dist = random_matrix(10, seed);
+        let expected = DpSolver::new(10, dist.clone()).compute_with(mode);
+        let mut solver = DpSolver::new(10, dist.clone());
+        assert_eq!(solver.compute_low_memory_with(mode), expected);
+        assert!(solver.dp.is_empty());
+        let tour = solver.tour().unwrap();
+        let path: u32 = tour.windows(2).map(|w| dist[w[0]][w[1]]).sum();
+        let closing = if mode == PathMode::Cycle { dist[tour[9]][tour[0]] } else { 0 };
+        assert_eq!(path + closing, expected);
+        // The full table comes back for the regular path
+        assert_eq!(solver.compute_with(mode), expected);
+    }
+}
 
+#[test]
+fn low_memory_respects_endpoints() {
+    let mut solver = DpSolver::with_endpoints(4, line_matrix(), 1, Some(0)).unwrap();
+    let expected = DpSolver::with_endpoints(4, line_matrix(), 1, Some(0)).unwrap().compute();
+    assert_eq!(solver.compute_low_memory(), expected);
+    assert_eq!(solver.tour().unwrap().last(), Some(&0));
+}
 
+/* ---------- branch and bound ---------- */
 
-/* ---------- trivial sizes ---------- */
+use task_ws::{Algorithm, BnBSolver};
 
+/// Mirror the upper triangle of `dist` onto the lower one.
+fn symmetric(dist: Vec<Vec<u32>>) -> Vec<Vec<u32>> {
+    let n = dist.len();
+    (0..n).map(|i| (0..n).map(|j| dist[i.min(j)][i.max(j)]).collect()).collect()
+}
 
+#[test]
+fn bnb_matches_held_karp() {
+    for seed in 1..=12 {
+        for n in [5, 9, 12] {
+            let asym = random_matrix(n, seed);
+            for dist in [symmetric(asym.clone()), asym] {
+                let expected = DpSolver::new(n, dist.clone()).compute();
+                let mut bnb = BnBSolver::new(n, dist.clone());
+                assert_eq!(bnb.compute(), expected, "n = {}, seed = {}", n, seed);
+                let tour = bnb.tour().unwrap();
+                assert_eq!(tour[0], 0);
+                assert_eq!(cycle_cost(&dist, &tour), expected);
+            }
+        }
+    }
+}
 
-#[test] fn n_zero()                   { assert_eq!(run_ok("0\n"), "0"); }
+#[test]
+fn bnb_solves_mid_size_symmetric() {
+    // Well past what the full Held-Karp table fits in memory for
+    let dist = symmetric(random_matrix(30, 11));
+    let mut bnb = BnBSolver::new(30, dist.clone());
+    let cost = bnb.compute();
+    let tour = bnb.tour().unwrap();
+    let mut seen = tour.clone();
+    seen.sort_unstable();
+    assert_eq!(seen, (0..30).collect::<Vec<_>>());
+    assert_eq!(cycle_cost(&dist, &tour), cost);
+}
 
+#[test]
+fn algorithm_override_gives_same_answer() {
+    let input = "4\n0 29 20 21\n29 0 15 17\n20 15 0 28\n21 17 28 0\n";
+    let algorithms = [
+        Algorithm::Auto,
+        Algorithm::HeldKarp,
+        Algorithm::BranchAndBound,
+        Algorithm::Heuristic,
+        Algorithm::Christofides, // this instance is metric
+    ];
+    for algorithm in algorithms {
+        let opts = SolveOptions { output: OutputMode::Cost, algorithm, ..Default::default() };
+        let mut out = Vec::new();
+        solve_tsp_with(&mut Cursor::new(input), &mut out, &opts).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "73\n");
+    }
+}
 
+/* ---------- heuristic ---------- */
 
-#[test] fn n_one()                    { assert_eq!(run_ok("1\n0\n"), "0"); }
+use std::time::Duration;
+use task_ws::HeuristicSolver;
 
+#[test]
+fn heuristic_tour_is_valid_and_close() {
+    for (n, seed) in [(10, 1), (12, 2), (12, 5)] {
+        for (sym, dist) in [(false, random_matrix(n, seed)), (true, symmetric(random_matrix(n, seed)))] {
+            let optimum = DpSolver::new(n, dist.clone()).compute();
+            // Generous budget: a local optimum is reached in microseconds
+            let found = HeuristicSolver::new(n, dist.clone())
+                .with_time_budget(Duration::from_secs(30))
+                .solve();
+            assert!(!found.optimal);
+            assert_eq!(found.tour[0], 0);
+            let mut seen = found.tour.clone();
+            seen.sort_unstable();
+            assert_eq!(seen, (0..n).collect::<Vec<_>>());
+            assert_eq!(cycle_cost(&dist, &found.tour), found.cost);
+            assert!(found.cost >= optimum);
+            if sym {
+                assert!(found.cost <= optimum * 5 / 4, "{} vs {}", found.cost, optimum);
+            }
+        }
+    }
+}
 
+#[test]
+fn heuristic_small_instances_are_optimal() {
+    let dist = vec![vec![0, 1, 9], vec![9, 0, 1], vec![1, 9, 0]];
+    let found = HeuristicSolver::new(3, dist).solve();
+    assert!(found.optimal);
+    assert_eq!((found.cost, found.tour), (3, vec![0, 1, 2]));
+}
 
-/* ---------- prompt examples ---------- */
+#[test]
+fn heuristic_respects_time_budget() {
+    let dist = symmetric(random_matrix(300, 4));
+    let start = std::time::Instant::now();
+    let found = HeuristicSolver::new(300, dist).with_time_budget(Duration::from_millis(50)).solve();
+    assert!(start.elapsed() < Duration::from_secs(2));
+    assert_eq!(found.tour.len(), 300);
+}
 
+/* ---------- Lin–Kernighan ---------- */
 
+use task_ws::lk::{self, LkConfig, Restart};
 
 #[test]
+fn lk_reaches_optimum_on_small_symmetric() {
+    for seed in [1, 4, 6, 8] {
+        let dist = symmetric(random_matrix(12, seed));
+        let optimum = DpSolver::new(12, dist.clone()).compute();
+        let start: Vec<usize> = (0..12).collect();
+        let config = LkConfig { restart: Restart::Kicks(50), ..Default::default() };
+        let tour = lk::improve(&dist, &start, &config, None);
+        assert_eq!(tour[0], 0);
+        assert_eq!(cycle_cost(&dist, &tour), optimum, "seed {}", seed);
+    }
+}
 
-fn example_four_city() {
+#[test]
+fn lk_never_worsens_the_input() {
+    let dist = symmetric(random_matrix(60, 9));
+    let start = HeuristicSolver::new(60, dist.clone()).with_lk(None).solve().tour;
+    for restart in [Restart::None, Restart::RandomTours(3)] {
+        let config = LkConfig { restart, ..Default::default() };
+        let tour = lk::improve(&dist, &start, &config, None);
+        let mut seen = tour.clone();
+        seen.sort_unstable();
+        assert_eq!(seen, (0..60).collect::<Vec<_>>());
+        assert!(cycle_cost(&dist, &tour) <= cycle_cost(&dist, &start));
+    }
+}
 
-    let input = "4\n\
+/* ---------- Christofides ---------- */
 
-                 0 29 20 21\n\
+use task_ws::christofides;
 
-                 29 0 15 17\n\
+/// Points on a 1000×1000 grid with distances rounded up, which keeps the
+/// triangle inequality intact.
+fn euclidean_matrix(n: usize, seed: u64) -> Vec<Vec<u32>> {
+    let coords = random_matrix(n, seed);
+    let pts: Vec<(f64, f64)> = (0..n)
+        .map(|i| (f64::from(coords[i][(i + 1) % n] * 10), f64::from(coords[(i + 1) % n][i] * 10)))
+        .collect();
+    pts.iter()
+        .map(|p| pts.iter().map(|q| (p.0 - q.0).hypot(p.1 - q.1).ceil() as u32).collect())
+        .collect()
+}
 
-                 20 15 0 28\n\
+#[test]
+fn christofides_within_one_and_a_half_of_optimal() {
+    for seed in 1..=8 {
+        let dist = euclidean_matrix(11, seed);
+        let optimum = DpSolver::new(11, dist.clone()).compute();
+        let found = christofides(&dist).unwrap();
+        let mut seen = found.tour.clone();
+        seen.sort_unstable();
+        assert_eq!(seen, (0..11).collect::<Vec<_>>());
+        assert_eq!(found.tour[0], 0);
+        assert_eq!(cycle_cost(&dist, &found.tour), found.cost);
+        assert!(found.cost * 2 <= optimum * 3, "seed {}: {} vs {}", seed, found.cost, optimum);
+    }
+}
 
-                 21 17 28 0\n";
+#[test]
+fn christofides_rejects_non_metric() {
+    let asym = vec![vec![0, 1, 2], vec![5, 0, 1], vec![2, 1, 0]];
+    assert!(christofides(&asym).is_err());
+    // 0 -> 2 directly is dearer than going through 1
+    let shortcut = vec![vec![0, 1, 9], vec![1, 0, 1], vec![9, 1, 0]];
+    let err = christofides(&shortcut).unwrap_err();
+    assert!(err.to_string().contains("triangle"), "{}", err);
+}
 
-    assert_eq!(run_ok(input), "73");
+/* ---------- lower bounds ---------- */
+
+use task_ws::lower_bound;
 
+#[test]
+fn lower_bound_never_exceeds_optimum() {
+    for seed in 1..=6 {
+        let instances =
+            [random_matrix(11, seed), symmetric(random_matrix(11, seed)), euclidean_matrix(11, seed)];
+        for dist in instances {
+            let optimum = DpSolver::new(11, dist.clone()).compute();
+            let bound = lower_bound(&dist);
+            assert!(bound <= optimum, "seed {}: {} > {}", seed, bound, optimum);
+            assert!(bound * 10 >= optimum * 7, "seed {}: {} is loose for {}", seed, bound, optimum);
+        }
+    }
 }
 
+#[test]
+fn lower_bound_is_exact_on_tiny_instances() {
+    assert_eq!(lower_bound(&[]), 0);
+    assert_eq!(lower_bound(&[vec![0, 4], vec![6, 0]]), 10);
+    assert_eq!(lower_bound(&[vec![0, 1, 9], vec![9, 0, 1], vec![1, 9, 0]]), 3);
+}
+
+#[test]
+fn heuristic_gap_against_lower_bound() {
+    let dist = euclidean_matrix(40, 3);
+    let found = HeuristicSolver::new(40, dist.clone()).solve();
+    let gap = found.gap(lower_bound(&dist));
+    assert!((0.0..0.1).contains(&gap), "gap {}", gap);
+}
 
+/* ---------- TSPLIB ---------- */
+
+use task_ws::{solve_tsp_tsplib, tsplib};
+
+const BURMA14: &str = "NAME: burma14
+TYPE: TSP
+COMMENT: 14-Staedte in Burma (Zaw Win)
+DIMENSION: 14
+EDGE_WEIGHT_TYPE: GEO
+EDGE_WEIGHT_FORMAT: FUNCTION
+DISPLAY_DATA_TYPE: COORD_DISPLAY
+NODE_COORD_SECTION
+   1  16.47       96.10
+   2  16.47       94.44
+   3  20.09       92.54
+   4  22.39       93.37
+   5  25.23       97.24
+   6  22.00       96.05
+   7  20.47       97.02
+   8  17.20       96.29
+   9  16.30       97.38
+  10  14.05       98.12
+  11  16.53       97.38
+  12  21.52       95.59
+  13  19.41       97.13
+  14  20.09       94.55
+EOF
+";
 
 #[test]
+fn tsplib_geo_reaches_published_optimum() {
+    let instance = tsplib::parse(Cursor::new(BURMA14)).unwrap();
+    assert_eq!(instance.name, "burma14");
+    assert_eq!(instance.dimension, 14);
+    assert_eq!(instance.coords.as_ref().map(Vec::len), Some(14));
+    assert_eq!(instance.dist[0][1], 153);
+    let mut out = Vec::new();
+    solve_tsp_tsplib(&mut Cursor::new(BURMA14), &mut out, &SolveOptions::default()).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "3323\n");
+}
 
-fn example_three_city() {
+#[test]
+fn tsplib_explicit_formats_agree() {
+    let full =
+        vec![vec![0, 29, 20, 21], vec![29, 0, 15, 17], vec![20, 15, 0, 28], vec![21, 17, 28, 0]];
+    let sections = [
+        ("FULL_MATRIX", "0 29 20 21\n29 0 15 17\n20 15 0 28\n21 17 28 0"),
+        ("UPPER_ROW", "29 20 21\n15 17\n28"),
+        ("LOWER_COL", "29 20 21 15 17 28"),
+        ("LOWER_ROW", "29\n20 15\n21 17 28"),
+        ("UPPER_COL", "29 20 15\n21 17 28"),
+        ("UPPER_DIAG_ROW", "0 29 20 21\n0 15 17\n0 28\n0"),
+        ("LOWER_DIAG_ROW", "0\n29 0\n20 15 0\n21 17 28 0"),
+    ];
+    for (format, section) in sections {
+        let text = format!(
+            "NAME : four\nTYPE : TSP\nDIMENSION : 4\nEDGE_WEIGHT_TYPE : EXPLICIT\n\
+             EDGE_WEIGHT_FORMAT : {}\nEDGE_WEIGHT_SECTION\n{}\nEOF\n",
+            format, section
+        );
+        let instance = tsplib::parse(Cursor::new(text)).unwrap();
+        assert_eq!(instance.dist, full, "{}", format);
+        assert_eq!(instance.coords, None);
+    }
+}
 
-    let input = "3\n\
+#[test]
+fn tsplib_euc_2d_rounds_to_nearest() {
+    let text = "NAME: square\nTYPE: TSP\nDIMENSION: 4\nEDGE_WEIGHT_TYPE: EUC_2D\n\
+                NODE_COORD_SECTION\n1 0 0\n2 3 0\n3 3 4.5\n4 0 4.5\nEOF\n";
+    let instance = tsplib::parse(Cursor::new(text)).unwrap();
+    // 3 across, 4.5 rounds up to 5, and the 5.408 diagonal down to 5
+    assert_eq!(instance.dist[0], vec![0, 3, 5, 5]);
+    let mut out = Vec::new();
+    solve_tsp_tsplib(&mut Cursor::new(text), &mut out, &SolveOptions::default()).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "16\n");
+}
 
-                 0 10 15\n\
+#[test]
+fn tsplib_rejects_bad_instances() {
+    let cases = [
+        ("DIMENSION: 2\nEDGE_WEIGHT_TYPE: ATT\n", "ATT"),
+        ("TYPE: HCP\nDIMENSION: 2\n", "HCP"),
+        ("DIMENSION: x\n", "DIMENSION"),
+        ("DIMENSION: 2\nEDGE_WEIGHT_TYPE: EUC_2D\nNODE_COORD_SECTION\n1 0 0\n", "expects 6"),
+        ("DIMENSION: 2\nEDGE_WEIGHT_TYPE: EUC_2D\nNODE_COORD_SECTION\n1 0 0\n3 1 1\n", "line 5"),
+        ("DIMENSION: 2\nEDGE_WEIGHT_TYPE: EXPLICIT\nEDGE_WEIGHT_SECTION\n0 1\n1 x\n", "line 5"),
+        ("DIMENSION: 2\nEDGE_WEIGHT_TYPE: EXPLICIT\nEOF\n", "EDGE_WEIGHT_SECTION"),
+        ("EDGE_WEIGHT_TYPE: GEO\n", "DIMENSION"),
+    ];
+    for (text, needle) in cases {
+        let err = tsplib::parse(Cursor::new(text)).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains(needle), "{:?}: {}", text, err);
+    }
+}
 
-                 10 0 20\n\
+/* ---------- coordinate input ---------- */
 
-                 15 20 0\n";
+#[test]
+fn coords_header_uses_rounded_euclidean_distances() {
+    // Same square as the EUC_2D test: sides 3 and 4.5 (rounded to 5)
+    assert_eq!(run_ok("COORDS 4\n0 0\n3 0\n3 4.5\n0 4.5\n"), "16");
+    assert_eq!(run_ok("COORDS 1\n7 7\n"), "0");
+}
 
-    assert_eq!(run_ok(input), "45");
+#[test]
+fn coords_header_matches_matrix_input() {
+    let points = [(0.0, 0.0), (2.5, 7.0), (-4.0, 1.5), (6.0, -3.0), (1.0, 1.0)];
+    let mut coords = format!("COORDS {}\n", points.len());
+    let mut matrix = format!("{}\n", points.len());
+    for &(x, y) in &points {
+        coords += &format!("{} {}\n", x, y);
+        let row: Vec<String> = points
+            .iter()
+            .map(|&(u, v): &(f64, f64)| ((x - u).hypot(y - v) + 0.5).floor().to_string())
+            .collect();
+        matrix += &format!("{}\n", row.join(" "));
+    }
+    assert_eq!(run_ok(&coords), run_ok(&matrix));
+}
 
+#[test]
+fn coords_header_rejects_bad_points() {
+    run_err("COORDS x\n");
+    run_err("COORDS 2\n0 0\n1\n");
+    run_err("COORDS 2\n0 0\n1 2 3\n");
+    run_err("COORDS 2\n0 0\n1 nan\n");
+    run_err("COORDS 2\n0 0\n");
 }
 
-/* ---------- edge cases & blind spots ---------- */
+/* ---------- sparse graphs ---------- */
 
+use task_ws::metric_closure;
 
+#[test]
+fn metric_closure_fills_missing_edges_with_shortest_paths() {
+    // a path 0 - 1 - 2 - 3 with a shortcut 0 - 3
+    let dist = vec![
+        vec![None, Some(1), None, Some(10)],
+        vec![Some(1), None, Some(2), None],
+        vec![None, Some(2), None, Some(3)],
+        vec![Some(10), None, Some(3), None],
+    ];
+    let closure = metric_closure(&dist).unwrap();
+    assert_eq!(closure[0], vec![0, 1, 3, 6]);
+    assert_eq!(closure[3], vec![6, 5, 3, 0]);
+}
 
 #[test]
+fn sparse_and_edge_inputs_agree() {
+    // a star around city 0: every tour goes back through the centre
+    let sparse = "SPARSE 4\n- 1 2 3\n1 - - -\n2 - - -\n3 - - -\n";
+    let edges = "EDGES 4 3\n0 1 1\n0 2 2\n0 3 3\n";
+    assert_eq!(run_ok(sparse), "12");
+    assert_eq!(run_ok(edges), "12");
+}
 
-fn overflow_saturates() {
+#[test]
+fn parallel_edges_keep_the_cheapest() {
+    assert_eq!(run_ok("EDGES 2 3\n0 1 9\n1 0 4\n0 1 7\n"), "8");
+}
 
-    let half = std::u32::MAX / 2;
+#[test]
+fn disconnected_graphs_are_infeasible() {
+    assert_eq!(run_ok("EDGES 4 2\n0 1 1\n2 3 1\n"), "INFEASIBLE");
+    // one-way edges: 1 reaches 0 but not the other way round
+    let one_way = "SPARSE 2\n0 -\n5 0\n";
+    assert_eq!(run_ok(one_way), "INFEASIBLE");
+    let err = try_solve(&mut Cursor::new(one_way), &SolveOptions::default());
+    assert!(matches!(err, Err(TspError::Infeasible { from: 0, to: 1 })));
+}
 
-    let expect = (half * 2).to_string();              // 4 294 967 294
+#[test]
+fn sparse_headers_reject_bad_input() {
+    run_err("EDGES 3\n");
+    run_err("EDGES 2 1\n0 2 5\n");
+    run_err("EDGES 2 2\n0 1 5\n");
+    run_err("SPARSE 2\n0 x\n1 0\n");
+    run_err("SPARSE 2\n0 -1\n1 0\n");
+}
 
-    let inp = format!("2\n0 {}\n{} 0\n", half, half);
+/* ---------- automatic algorithm selection ---------- */
 
-    assert_eq!(run_ok(&inp), expect);
+use task_ws::AutoThresholds;
 
+fn solve_auto(dist: &[Vec<u32>], auto: AutoThresholds) -> task_ws::Solution {
+    let opts =
+        SolveOptions { auto, time_limit: Some(Duration::from_millis(200)), ..Default::default() };
+    try_solve(&mut Cursor::new(instances::matrix_input(dist)), &opts).unwrap()
 }
 
+#[test]
+fn auto_picks_the_solver_by_size() {
+    let small = solve_auto(&random_matrix(8, 1), AutoThresholds::default());
+    assert_eq!((small.algorithm, small.optimal, small.lower_bound), (Algorithm::HeldKarp, true, None));
+    assert_eq!(small.cost, brute_force(&random_matrix(8, 1)));
+
+    let mid = solve_auto(&symmetric(random_matrix(20, 2)), AutoThresholds::default());
+    assert_eq!((mid.algorithm, mid.optimal), (Algorithm::BranchAndBound, true));
+
+    let dist = euclidean_matrix(60, 3);
+    let large = solve_auto(&dist, AutoThresholds::default());
+    assert_eq!(large.algorithm, Algorithm::Heuristic);
+    assert_eq!(large.lower_bound, Some(lower_bound(&dist)));
+    let gap = large.gap().unwrap();
+    assert!((0.0..0.1).contains(&gap), "gap {}", gap);
+    assert_eq!(large.optimal, gap == 0.0);
+}
 
+#[test]
+fn auto_thresholds_and_memory_limit_are_configurable() {
+    let dist = random_matrix(8, 4);
+    let optimum = brute_force(&dist);
+    let no_memory = AutoThresholds { memory_limit: Some(0), ..Default::default() };
+    let lower_dp = AutoThresholds { held_karp_max_n: 7, ..Default::default() };
+    for auto in [no_memory, lower_dp] {
+        let solution = solve_auto(&dist, auto);
+        assert_eq!((solution.algorithm, solution.cost), (Algorithm::BranchAndBound, optimum));
+    }
+    let roomy = AutoThresholds { memory_limit: memory_estimate(8), ..Default::default() };
+    assert_eq!(solve_auto(&dist, roomy).algorithm, Algorithm::HeldKarp);
+    let heuristic_only = AutoThresholds { held_karp_max_n: 0, bnb_max_n: 0, memory_limit: None };
+    let solution = solve_auto(&dist, heuristic_only);
+    assert_eq!(solution.algorithm, Algorithm::Heuristic);
+    assert!(solution.cost >= optimum && solution.lower_bound.unwrap() <= optimum);
+}
 
+#[test]
+fn explicit_algorithms_report_what_ran() {
+    let input = "4\n0 29 20 21\n29 0 15 17\n20 15 0 28\n21 17 28 0\n";
+    let opts = SolveOptions { algorithm: Algorithm::Christofides, ..Default::default() };
+    let solution = try_solve(&mut Cursor::new(input), &opts).unwrap();
+    assert_eq!(solution.algorithm, Algorithm::Christofides);
+    // n = 4 is small enough for the lower bound to meet the optimum
+    assert_eq!((solution.cost, solution.optimal), (73, solution.lower_bound == Some(73)));
+}
 
+/* ---------- structured errors ---------- */
+
+use task_ws::{memory_estimate, try_solve_tsp, TspError, BNB_MAX_N, DP_MAX_N};
+
+fn try_run(input: &str) -> Result<String, TspError> {
+    let mut out = Vec::new();
+    try_solve_tsp(&mut Cursor::new(input), &mut out)?;
+    Ok(String::from_utf8(out).unwrap())
+}
 
 #[test]
+fn try_api_reports_each_failure() {
+    assert!(matches!(try_run("foo\n"), Err(TspError::InvalidN)));
+    assert!(matches!(
+        try_run("2\n0 1\n1\n"),
+        Err(TspError::BadRow { line: 2, expected: 2, got: 1 })
+    ));
+    match try_run("2\n0 -1\n1 0\n") {
+        Err(TspError::InvalidValue { line: 1, token }) => assert_eq!(token, "-1"),
+        other => panic!("{:?}", other),
+    }
+    let big = u32::MAX - 1;
+    let saturating = format!("2\n0 {}\n{} 0\n", big, big);
+    assert!(matches!(try_run(&saturating), Err(TspError::Overflow)));
+    assert_eq!(try_run("2\n0 1\n1 0\n").unwrap(), "2\n");
+}
 
-fn simd_tail_handling() {
+#[test]
+fn endpoint_errors_name_the_city() {
+    let err = DpSolver::with_endpoints(3, random_matrix(3, 1), 0, Some(5)).err().unwrap();
+    assert!(matches!(err, TspError::NoSuchCity { city: 5, n: 3 }));
+    let err = DpSolver::with_endpoints(3, random_matrix(3, 1), 1, Some(1)).err().unwrap();
+    assert_eq!(err.to_string(), "end city 1 is also the start city");
+    assert_eq!(std::io::Error::from(err).kind(), std::io::ErrorKind::InvalidInput);
+}
 
-    // N = 10, not a multiple of 8‑lane AVX2
+#[test]
+fn try_new_checks_the_matrix_shape() {
+    let err = DpSolver::try_new(3, vec![vec![0u32; 3]; 2]).err().unwrap();
+    assert!(matches!(err, TspError::NonSquare { n: 3, rows: 2 }));
+    let ragged = vec![vec![0u32; 3], vec![0; 2], vec![0; 3]];
+    let err = DpSolver::try_new(3, ragged).err().unwrap();
+    assert!(matches!(err, TspError::BadRow { line: 2, expected: 3, got: 2 }));
+    assert_eq!(DpSolver::try_new(2, vec![vec![0u32, 4], vec![6, 0]]).unwrap().compute(), 10);
+}
 
-    let mut inp = String::from("10\n");
+#[test]
+fn too_large_instances_are_rejected_not_allocated() {
+    let n = usize::BITS as usize;
+    let dist = vec![vec![0u32; n]; n];
+    assert!(matches!(DpSolver::try_new(n, dist).err(), Some(TspError::TooLarge { .. })));
+    let opts = SolveOptions { algorithm: Algorithm::BranchAndBound, ..Default::default() };
+    let mut input = format!("{}\n", BNB_MAX_N + 1);
+    for _ in 0..=BNB_MAX_N {
+        input += &format!("{}\n", vec!["0"; BNB_MAX_N + 1].join(" "));
+    }
+    let err = solve_tsp_with(&mut Cursor::new(input), &mut Vec::new(), &opts).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    let inner = err.get_ref().and_then(|e| e.downcast_ref::<TspError>());
+    assert!(matches!(inner, Some(TspError::TooLarge { max: BNB_MAX_N, .. })), "{}", err);
+}
 
-    for _ in 0..10 { inp.push_str(&"0 ".repeat(10)); inp.push('\n'); }
+#[test]
+fn dp_size_limit_is_configurable() {
+    assert_eq!(memory_estimate(20), Some((1 << 20) * 20 * 5));
+    assert_eq!(DpSolver::<f64>::memory_estimate(3), Some(8 * 3 * 9));
+    assert_eq!(memory_estimate(usize::BITS as usize), None);
+
+    // Refused before anything is allocated
+    let err = DpSolver::try_new(DP_MAX_N + 1, vec![vec![0u32; DP_MAX_N + 1]; DP_MAX_N + 1]);
+    assert!(matches!(err.err(), Some(TspError::TooLarge { max: DP_MAX_N, .. })));
+    let err = DpSolver::try_new_with_limit(3, vec![vec![0u32; 3]; 3], 2).err();
+    assert!(matches!(err, Some(TspError::TooLarge { n: 3, max: 2 })));
+    let err = DpSolver::try_new_with_limit(64, vec![vec![0u32; 64]; 64], usize::MAX).err();
+    assert!(matches!(err, Some(TspError::TooLarge { n: 64, max }) if max < 64));
+
+    let input = "4\n0 29 20 21\n29 0 15 17\n20 15 0 28\n21 17 28 0\n";
+    let mut opts =
+        SolveOptions { algorithm: Algorithm::HeldKarp, dp_max_n: Some(3), ..Default::default() };
+    let err = solve_tsp_with(&mut Cursor::new(input), &mut Vec::new(), &opts).unwrap_err();
+    assert!(err.to_string().contains("limit of 3"), "{}", err);
+    opts.dp_max_n = Some(4);
+    let mut out = Vec::new();
+    solve_tsp_with(&mut Cursor::new(input), &mut out, &opts).unwrap();
+    assert_eq!(out, b"73\n");
+}
 
-    assert_eq!(run_ok(&inp), "0");
+/* ---------- matrix validation ---------- */
+
+use task_ws::MatrixIssue;
 
+fn issues<W: task_ws::Weight>(solver: &DpSolver<W>, symmetric: bool) -> Vec<MatrixIssue> {
+    let result = if symmetric { solver.validate_symmetric() } else { solver.validate() };
+    match result {
+        Ok(()) => Vec::new(),
+        Err(TspError::InvalidMatrix(issues)) => issues,
+        Err(other) => panic!("{}", other),
+    }
 }
 
+#[test]
+fn validate_accepts_well_formed_matrices() {
+    let dist =
+        vec![vec![0, 29, 20, 21], vec![29, 0, 15, 17], vec![20, 15, 0, 28], vec![21, 17, 28, 0]];
+    let solver = DpSolver::<u32>::new(4, dist);
+    assert_eq!(issues(&solver, false), vec![]);
+    assert_eq!(issues(&solver, true), vec![]);
+    assert_eq!(issues(&DpSolver::new(11, random_matrix(11, 2)), false), vec![]);
+}
 
+#[test]
+fn validate_reports_every_issue() {
+    let dist = vec![vec![0, 5, u32::MAX], vec![5, 3, 1], vec![2, 1]];
+    let solver = DpSolver::new(3, dist);
+    assert_eq!(
+        issues(&solver, false),
+        vec![
+            MatrixIssue::Overflowing { from: 0, to: 2 },
+            MatrixIssue::NonZeroDiagonal { city: 1 },
+            MatrixIssue::RowLength { row: 2, expected: 3, got: 2 },
+        ]
+    );
+    let short = DpSolver::new(3, vec![vec![0u64; 3]; 2]);
+    assert_eq!(issues(&short, false), vec![MatrixIssue::RowCount { expected: 3, got: 2 }]);
+}
 
 #[test]
+fn validate_checks_floats_and_symmetry() {
+    let dist =
+        vec![vec![0.0, -1.0, 2.0], vec![1.0, 0.0, f64::NAN], vec![2.0, f64::INFINITY, 0.0]];
+    let solver = DpSolver::new(3, dist);
+    assert_eq!(
+        issues(&solver, false),
+        vec![
+            MatrixIssue::Negative { from: 0, to: 1 },
+            MatrixIssue::Negative { from: 1, to: 2 },
+            MatrixIssue::Overflowing { from: 2, to: 1 },
+        ]
+    );
+    let asym = DpSolver::new(3, random_matrix(3, 5));
+    assert_eq!(issues(&asym, false), vec![]);
+    assert!(issues(&asym, true).contains(&MatrixIssue::Asymmetric { from: 0, to: 1 }));
+    assert_eq!(issues(&DpSolver::new(6, symmetric(random_matrix(6, 5))), true), vec![]);
+}
 
-fn simd_exact_lane() {
+#[test]
+fn solve_tsp_rejects_invalid_matrices() {
+    let err = try_run("3\n0 1 2\n1 7 1\n2 1 0\n").unwrap_err();
+    let diagonal = [MatrixIssue::NonZeroDiagonal { city: 1 }];
+    assert!(matches!(&err, TspError::InvalidMatrix(v) if v == &diagonal));
+    assert_eq!(err.to_string(), "invalid distance matrix: d(1,1) is not zero");
+    let max = u32::MAX;
+    let err = try_run(&format!("2\n0 {}\n{} 0\n", max, max)).unwrap_err();
+    assert!(matches!(err, TspError::InvalidMatrix(_)), "{}", err);
+}
 
-    // N = 8: exactly one AVX2 vector, all zeros
+/* ---------- progress & cancellation ---------- */
 
-    let mut inp = String::from("8\n");
+use std::sync::{Arc, Mutex};
+use task_ws::{try_solve_tsp_with, CancelToken};
 
-    for _ in 0..8 { inp.push_str(&"0 ".repeat(8)); inp.push('\n'); }
+#[test]
+fn progress_reports_up_to_the_total() {
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let log = Arc::clone(&seen);
+    let dist = random_matrix(14, 4);
+    let expected = DpSolver::new(14, dist.clone()).compute();
+    let mut solver = DpSolver::new(14, dist)
+        .with_progress(move |done, total| log.lock().unwrap().push((done, total)));
+    assert_eq!(solver.compute(), expected);
+    let seen = seen.lock().unwrap();
+    let total = (1 << 14) - 1;
+    assert!(seen.len() > 1);
+    assert!(seen.windows(2).all(|w| w[0].0 < w[1].0));
+    assert!(seen.iter().all(|&(_, t)| t == total));
+    assert_eq!(seen.last(), Some(&(total, total)));
+}
 
-    assert_eq!(run_ok(&inp), "0");
+#[test]
+fn cancelled_solves_stop_without_an_answer() {
+    let token = CancelToken::new();
+    token.cancel();
+    let mut solver = DpSolver::new(14, random_matrix(14, 4)).with_cancel(token);
+    assert!(matches!(solver.try_compute(), Err(TspError::Cancelled)));
+    assert_eq!(solver.tour(), None);
+    assert_eq!(solver.compute_low_memory(), u32::MAX);
+    assert_eq!(solver.tour(), None);
+
+    // Cancelled from the progress callback after the first report
+    let token = CancelToken::new();
+    let trigger = token.clone();
+    let calls = Arc::new(Mutex::new(0));
+    let count = Arc::clone(&calls);
+    let mut solver = DpSolver::new(15, random_matrix(15, 4))
+        .with_cancel(token)
+        .with_progress(move |_, _| {
+            *count.lock().unwrap() += 1;
+            trigger.cancel();
+        });
+    assert_eq!(solver.compute(), u32::MAX);
+    assert_eq!(*calls.lock().unwrap(), 1);
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn parallel_solve_honours_cancellation() {
+    let token = CancelToken::new();
+    let mut solver = DpSolver::new(12, random_matrix(12, 4)).with_cancel(token.clone());
+    let expected = solver.compute();
+    assert_eq!(solver.compute_parallel(), expected);
+    token.cancel();
+    assert_eq!(solver.compute_parallel(), u32::MAX);
+    assert_eq!(solver.tour(), None);
+}
 
+#[test]
+fn time_limit_stops_held_karp_and_bounds_the_heuristic() {
+    let dist = random_matrix(18, 6);
+    let mut input = String::from("18\n");
+    for row in &dist {
+        let row: Vec<String> = row.iter().map(u32::to_string).collect();
+        input += &format!("{}\n", row.join(" "));
+    }
+    let mut opts = SolveOptions {
+        algorithm: Algorithm::HeldKarp,
+        time_limit: Some(Duration::ZERO),
+        ..Default::default()
+    };
+    let result = try_solve_tsp_with(&mut Cursor::new(&input), &mut Vec::new(), &opts);
+    assert!(matches!(result, Err(TspError::Cancelled)));
+
+    opts.algorithm = Algorithm::Heuristic;
+    let mut out = Vec::new();
+    try_solve_tsp_with(&mut Cursor::new(&input), &mut out, &opts).unwrap();
+    let cost: u32 = String::from_utf8(out).unwrap().trim().parse().unwrap();
+    assert!(cost >= DpSolver::new(18, dist).compute());
 }
 
+/* ---------- solver reuse ---------- */
 
+use task_ws::solve_many;
 
 #[test]
+fn reset_reuses_the_tables() {
+    let mut solver = DpSolver::new(12, random_matrix(12, 1));
+    solver.compute();
+    let (dp, parent) = (solver.dp.as_ptr(), solver.parent.as_ptr());
+    for (n, seed) in [(12, 2), (9, 3), (12, 4)] {
+        let dist = random_matrix(n, seed);
+        let expected = DpSolver::new(n, dist.clone()).compute();
+        solver.reset(dist.clone());
+        assert_eq!(solver.n, n);
+        assert_eq!(solver.compute(), expected);
+        assert_eq!(cycle_cost(&dist, &solver.tour().unwrap()), expected);
+        assert_eq!(solver.dp.as_ptr(), dp);
+        assert_eq!(solver.parent.as_ptr(), parent);
+    }
+}
 
-fn all_zero_n16() {
+#[test]
+fn solve_many_matches_individual_solves() {
+    let matrices: Vec<_> = (1..=5).map(|seed| random_matrix(4 + seed as usize, seed)).collect();
+    let expected: Vec<u32> =
+        matrices.iter().map(|d| DpSolver::new(d.len(), d.clone()).compute()).collect();
+    assert_eq!(solve_many(matrices), expected);
+    assert_eq!(solve_many(Vec::<Vec<Vec<f64>>>::new()), Vec::<f64>::new());
+}
 
-    let mut inp = String::from("16\n");
+/* ---------- tour drawings ---------- */
 
-    for _ in 0..16 { inp.push_str(&"0 ".repeat(16)); inp.push('\n'); }
+use task_ws::{render_dot, render_svg, try_solve};
 
-    assert_eq!(run_ok(&inp), "0");
+#[test]
+fn render_dot_pins_cities_and_closes_the_tour() {
+    let coords = [(0.0, 0.0), (3.0, 0.0), (3.0, 4.5)];
+    let dot = render_dot(&[0, 2, 1], &coords);
+    assert!(dot.starts_with("graph tour {"));
+    assert!(dot.contains("2 [pos=\"3,4.5!\"];"));
+    for edge in ["0 -- 2;", "2 -- 1;", "1 -- 0;"] {
+        assert!(dot.contains(edge), "{}", dot);
+    }
+    assert_eq!(dot.matches("--").count(), 3);
+    assert_eq!(render_dot(&[0], &coords).matches("--").count(), 0);
+}
+
+#[test]
+fn render_svg_draws_one_polygon() {
+    let svg = render_svg(&[0, 1, 2, 3], &[(0.0, 0.0), (10.0, 0.0), (10.0, 5.0), (0.0, 5.0)]);
+    assert!(svg.starts_with("<svg"));
+    assert!(svg.trim_end().ends_with("</svg>"));
+    assert_eq!(svg.matches("<polygon").count(), 1);
+    assert_eq!(svg.matches("<circle").count(), 4);
+    // One coincident point still renders without dividing by zero
+    assert!(!render_svg(&[0], &[(2.0, 2.0)]).contains("NaN"));
+}
+
+#[test]
+fn try_solve_keeps_coordinates_for_drawing() {
+    let input = "COORDS 4\n0 0\n3 0\n3 4.5\n0 4.5\n";
+    let solution = try_solve(&mut Cursor::new(input), &SolveOptions::default()).unwrap();
+    assert_eq!(solution.cost, 16);
+    let coords = solution.coords.as_deref().unwrap();
+    assert_eq!(coords.len(), 4);
+    let dot = render_dot(solution.tour.as_deref().unwrap(), coords);
+    assert_eq!(dot.matches("--").count(), 4);
+    let matrix = try_solve(&mut Cursor::new("2\n0 1\n1 0\n"), &SolveOptions::default()).unwrap();
+    assert_eq!(matrix.coords, None);
+}
+
+/* ---------- instance generators ---------- */
+
+use task_ws::instances;
+
+#[test]
+fn generators_are_seeded_and_well_formed() {
+    let a = instances::uniform_matrix(9, 50, 7);
+    assert_eq!(a, instances::uniform_matrix(9, 50, 7));
+    assert_ne!(a, instances::uniform_matrix(9, 50, 8));
+    for (i, row) in a.iter().enumerate() {
+        assert_eq!(row.len(), 9);
+        for (j, &d) in row.iter().enumerate() {
+            assert!(if i == j { d == 0 } else { (1..=50).contains(&d) });
+        }
+    }
+
+    let points = instances::euclidean_points(50, 100, 3);
+    assert_eq!(points, instances::euclidean_points(50, 100, 3));
+    assert!(points.iter().all(|&(x, y)| (0.0..100.0).contains(&x) && (0.0..100.0).contains(&y)));
+
+    let clustered = instances::clustered_points(60, 4, 1000, 3);
+    assert_eq!(clustered.len(), 60);
+    assert!(clustered.iter().all(|&(x, y)| x.fract() == 0.0 && (0.0..=1000.0).contains(&y)));
+}
+
+#[test]
+fn generated_inputs_round_trip_through_solve_tsp() {
+    let dist = instances::uniform_matrix(8, 100, 11);
+    let expected = DpSolver::new(8, dist.clone()).compute();
+    assert_eq!(run_ok(&instances::matrix_input(&dist)), expected.to_string());
+
+    let points = instances::clustered_points(10, 3, 1000, 5);
+    let input = instances::coords_input(&points);
+    let solution = try_solve(&mut Cursor::new(input), &SolveOptions::default()).unwrap();
+    assert_eq!(solution.coords.as_deref(), Some(&points[..]));
+}
+
+/* ---------- checked arithmetic ---------- */
+
+use task_ws::ArithmeticMode;
+
+#[test]
+fn checked_mode_reports_hidden_saturation() {
+    // 0 -> 1 costs nearly u32::MAX, so every state through it saturates,
+    // but the optimum 0 -> 2 -> 1 -> 0 avoids it
+    let dist = vec![vec![0, u32::MAX - 1, 1], vec![1, 0, 1], vec![1, 1, 0]];
+    assert_eq!(DpSolver::new(3, dist.clone()).try_compute().unwrap(), 3);
+    let mut checked = DpSolver::new(3, dist.clone()).with_arithmetic(ArithmeticMode::Checked);
+    assert!(matches!(checked.try_compute(), Err(TspError::Overflow)));
+    assert_eq!(checked.compute_low_memory(), 3);
+    assert!(matches!(checked.try_compute(), Err(TspError::Overflow)));
+    #[cfg(feature = "parallel")]
+    assert!(matches!(checked.try_compute_parallel_on(2), Err(TspError::Overflow)));
+    #[cfg(feature = "parallel")]
+    assert_eq!(checked.compute_parallel_low_memory(), 3);
+
+    let input = format!("3\n0 {} 1\n1 0 1\n1 1 0\n", u32::MAX - 1);
+    let mut opts = SolveOptions::default();
+    assert_eq!(try_solve(&mut Cursor::new(&input), &opts).unwrap().cost, 3);
+    opts.arithmetic = ArithmeticMode::Checked;
+    let err = try_solve(&mut Cursor::new(&input), &opts).unwrap_err();
+    assert!(matches!(err, TspError::Overflow));
+}
+
+#[test]
+fn checked_mode_accepts_sums_that_fit() {
+    // The `overflow_saturates` instance: u32::MAX - 1 fits exactly
+    let half = u32::MAX / 2;
+    let mut solver = DpSolver::new(2, vec![vec![0, half], vec![half, 0]])
+        .with_arithmetic(ArithmeticMode::Checked);
+    assert_eq!(solver.try_compute().unwrap(), half * 2);
+    // Unreachable states are INFINITY by design, not by overflow
+    for seed in 1..=3 {
+        let dist = random_matrix(9, seed);
+        let expected = DpSolver::new(9, dist.clone()).compute();
+        let mut solver = DpSolver::new(9, dist).with_arithmetic(ArithmeticMode::Checked);
+        assert_eq!(solver.try_compute().unwrap(), expected);
+        assert_eq!(solver.compute_low_memory(), expected);
+    }
+}
+
+/* ---------- all starts ---------- */
+
+/// Helper: `dist` with cities 0 and `s` swapped, so that `brute_force`
+/// anchors its cycles at `s`.
+fn anchored_at(dist: &[Vec<u32>], s: usize) -> Vec<Vec<u32>> {
+    let swap = |c: usize| if c == 0 { s } else if c == s { 0 } else { c };
+    (0..dist.len()).map(|i| (0..dist.len()).map(|j| dist[swap(i)][swap(j)]).collect()).collect()
+}
+
+#[test]
+fn best_from_each_start_matches_anchored_solves() {
+    for seed in 1..=3 {
+        let dist = random_matrix(7, seed);
+        let mut solver = DpSolver::new(7, dist.clone());
+        let costs = solver.best_from_each_start();
+        assert_eq!(costs.len(), 7);
+        for (s, &cost) in costs.iter().enumerate() {
+            assert_eq!(cost, brute_force(&anchored_at(&dist, s)), "seed {} start {}", seed, s);
+
+            let mut anchored = DpSolver::with_endpoints(7, dist.clone(), s, None).unwrap();
+            anchored.compute();
+            let own = anchored.tour().unwrap();
+            assert_eq!(own[0], s);
+            assert_eq!(cycle_cost(&dist, &own), cost, "seed {} start {}", seed, s);
+
+            let tour = solver.tour_from(s).unwrap();
+            assert_eq!(tour[0], s);
+            let mut cities = tour.clone();
+            cities.sort_unstable();
+            assert_eq!(cities, (0..7).collect::<Vec<_>>());
+            assert_eq!(cycle_cost(&dist, &tour), cost);
+        }
+        assert_eq!(solver.tour_from(7), None);
+    }
+    assert_eq!(DpSolver::<u32>::new(0, Vec::new()).best_from_each_start(), Vec::<u32>::new());
+    assert_eq!(DpSolver::new(1, vec![vec![0u32]]).best_from_each_start(), vec![0]);
+}
+
+/* ---------- streaming parser ---------- */
+
+use task_ws::instances::{matrix_input, uniform_matrix};
+
+#[test]
+fn rows_may_wrap_across_lines() {
+    assert_eq!(run_ok("3\n0 1\n2 1 0\n3\n2 3 0\n"), run_ok("3\n0 1 2\n1 0 3\n2 3 0\n"));
+    assert_eq!(run_ok("COORDS 2\n0\n0 3\n4\n"), "10");
+    assert_eq!(run_ok("2\r\n0 4\r\n6 0\r\n"), "10");
+}
 
+#[test]
+fn scanner_rejects_malformed_tokens() {
+    for token in ["-1", "+1", "1.5", "1e3", "4294967296", "0x10", "½"] {
+        match try_run(&format!("2\n0 1\n{} 0\n", token)) {
+            Err(TspError::InvalidValue { line: 2, token: got }) => assert_eq!(got, token),
+            other => panic!("{}: {:?}", token, other),
+        }
+    }
+    assert!(matches!(try_run("2\n0 1\n4294967295 0\n"), Err(TspError::InvalidMatrix(_))));
+}
+
+#[test]
+fn values_after_the_matrix_are_reported_on_the_same_line_only() {
+    match try_run("2\n0 1\n1 0 7\n") {
+        Err(TspError::TrailingData { line: 2, token }) => assert_eq!(token, "7"),
+        other => panic!("{:?}", other),
+    }
+    assert!(matches!(try_run("2\n0 1 1\n0 5\n"), Err(TspError::TrailingData { line: 2, .. })));
+    assert_eq!(try_run("2\n0 1\n1 0\nnot part of the instance\n").unwrap(), "2\n");
+    assert!(matches!(try_run("2\n0 1 1\n"), Err(TspError::BadRow { line: 2, got: 1, .. })));
+}
+
+#[test]
+fn large_matrices_parse_without_per_line_allocation() {
+    // ~4 MB of text; the heuristic keeps the solve itself cheap
+    let dist = uniform_matrix(1000, 999, 3);
+    let text = matrix_input(&dist).replace(' ', "\n");
+    let opts = SolveOptions { algorithm: Algorithm::Heuristic, ..Default::default() };
+    let solution = task_ws::try_solve(&mut Cursor::new(text), &opts).unwrap();
+    assert!(solution.cost >= 1000);
+}
+
+/* ---------- checkpoints ---------- */
+
+use task_ws::Checkpoint;
+
+/// Checkpoint of a solve cancelled once `stop` masks are done.
+fn cancelled_checkpoint(dist: &[Vec<u32>], mode: PathMode, stop: usize) -> Checkpoint<u32> {
+    let token = CancelToken::new();
+    let expire = token.clone();
+    let mut solver = DpSolver::new(dist.len(), dist.to_vec())
+        .with_cancel(token)
+        .with_progress(move |done, _| if done >= stop { expire.cancel() });
+    assert_eq!(solver.compute_with(mode), u32::MAX);
+    solver.save_checkpoint().unwrap()
 }
 
+#[test]
+fn resumed_solve_matches_an_uninterrupted_one() {
+    let dist = random_matrix(16, 21);
+    let expected = DpSolver::new(16, dist.clone()).compute();
+    for stop in [1 << 12, 1 << 15, 60_000] {
+        let checkpoint = cancelled_checkpoint(&dist, PathMode::Cycle, stop);
+        assert!((1..16).contains(&checkpoint.layer), "{}", checkpoint.layer);
+        let mut solver = DpSolver::new(0, Vec::new());
+        assert_eq!(solver.resume(checkpoint).unwrap(), expected);
+        assert_eq!(cycle_cost(&dist, &solver.tour().unwrap()), expected);
+    }
+}
+
+#[test]
+fn checkpoints_keep_the_route_shape() {
+    let dist = random_matrix(16, 8);
+    let open = PathMode::Open { start: Some(3) };
+    let expected = DpSolver::new(16, dist.clone()).compute_with(open);
+    let checkpoint = cancelled_checkpoint(&dist, open, 1 << 13);
+    assert_eq!(checkpoint.mode, open);
+    assert_eq!(DpSolver::new(0, Vec::new()).resume(checkpoint).unwrap(), expected);
+
+    let mut done = DpSolver::new(16, dist.clone());
+    let cost = done.compute();
+    let checkpoint = done.save_checkpoint().unwrap();
+    assert_eq!(checkpoint.layer, 16);
+    assert_eq!(DpSolver::new(0, Vec::new()).resume(checkpoint).unwrap(), cost);
+}
+
+#[test]
+fn a_resumed_solve_can_be_cancelled_and_resumed_again() {
+    let dist = random_matrix(16, 2);
+    let expected = DpSolver::new(16, dist.clone()).compute();
+    let first = cancelled_checkpoint(&dist, PathMode::Cycle, 1 << 12);
+    let token = CancelToken::new();
+    let expire = token.clone();
+    let mut solver = DpSolver::new(0, Vec::new())
+        .with_cancel(token)
+        .with_progress(move |done, _| if done >= 1 << 15 { expire.cancel() });
+    assert!(matches!(solver.resume(first.clone()), Err(TspError::Cancelled)));
+    let second = solver.save_checkpoint().unwrap();
+    assert!(second.layer > first.layer);
+    assert_eq!(DpSolver::new(0, Vec::new()).resume(second).unwrap(), expected);
+}
+
+#[test]
+fn inconsistent_checkpoints_are_rejected() {
+    let mut solver = DpSolver::new(5, random_matrix(5, 4));
+    assert!(solver.save_checkpoint().is_none());
+    solver.compute_low_memory();
+    assert!(solver.save_checkpoint().is_none());
+    solver.compute();
+    let good = solver.save_checkpoint().unwrap();
+
+    let mut short = good.clone();
+    short.dp.pop();
+    let mut layer = good.clone();
+    layer.layer = 6;
+    let mut parent = good.clone();
+    parent.parent[40] = 9;
+    for bad in [short, layer, parent] {
+        assert!(matches!(solver.resume(bad), Err(TspError::InvalidCheckpoint(_))));
+    }
+}
 
+#[cfg(feature = "serde")]
+#[test]
+fn checkpoints_round_trip_through_serde() {
+    let dist = random_matrix(14, 6);
+    let expected = DpSolver::new(14, dist.clone()).compute();
+    let checkpoint = cancelled_checkpoint(&dist, PathMode::Cycle, 1 << 12);
+    let json = serde_json::to_string(&checkpoint).unwrap();
+    let loaded: Checkpoint<u32> = serde_json::from_str(&json).unwrap();
+    assert_eq!(loaded, checkpoint);
+    assert_eq!(DpSolver::new(0, Vec::new()).resume(loaded).unwrap(), expected);
+}
+
+/* ---------- SIMD kernels ---------- */
+
+#[test]
+fn u32_simd_matches_generic_scalar_n17() {
+    // n = 17: one full AVX-512 vector (two AVX2 vectors) plus a tail
+    let dist = random_matrix(17, 9);
+    let wide: Vec<Vec<u64>> = dist.iter().map(|r| r.iter().map(|&d| d.into()).collect()).collect();
+    let expected = DpSolver::<u64>::new(17, wide).compute();
+    assert_eq!(u64::from(DpSolver::new(17, dist).compute()), expected);
+}
+
+#[test]
+fn kernel_columns_are_the_transposed_matrix() {
+    let dist = vec![vec![0u32, 1, 2], vec![3, 0, 4], vec![5, 6, 0]];
+    assert_eq!(task_ws::kernels::columns(&dist), [0, 3, 5, 1, 0, 6, 2, 4, 0]);
+}
+
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn x86_kernels_agree_with_scalar() {
+    use task_ws::kernels::{columns, relax_avx2, relax_avx512, relax_scalar};
+    let n = 17;
+    let dist = random_matrix(n, 5);
+    let prev = (1usize << n) - 1 - (1 << 4) - (1 << 16);
+    // Row `prev` mixes unreachable, near-overflow and ordinary entries
+    let mut dp = vec![u32::MAX; (1 << n) * n];
+    for (j, v) in dp[prev * n..(prev + 1) * n].iter_mut().enumerate() {
+        *v = match j % 3 { 0 => u32::MAX, 1 => u32::MAX - 50, _ => j as u32 * 7 };
+    }
+    let row = dp[prev * n..].as_ptr();
+    let cols = columns(&dist);
+    for i in [4, 16] {
+        let col = &cols[i * n..][..n];
+        let expect = unsafe { relax_scalar(row, col, prev) };
+        if is_x86_feature_detected!("avx2") {
+            assert_eq!(unsafe { relax_avx2(row, col, prev) }.0, expect.0);
+        }
+        if is_x86_feature_detected!("avx512f") {
+            assert_eq!(unsafe { relax_avx512(row, col, prev) }.0, expect.0);
+        }
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn neon_kernel_agrees_with_scalar() {
+    use task_ws::kernels::{columns, relax_neon, relax_scalar};
+    let n = 17;
+    let dist = random_matrix(n, 5);
+    let prev = (1usize << n) - 1 - (1 << 4) - (1 << 16);
+    let mut dp = vec![u32::MAX; (1 << n) * n];
+    for (j, v) in dp[prev * n..(prev + 1) * n].iter_mut().enumerate() {
+        *v = match j % 3 { 0 => u32::MAX, 1 => u32::MAX - 50, _ => j as u32 * 7 };
+    }
+    let row = dp[prev * n..].as_ptr();
+    let cols = columns(&dist);
+    for i in [4, 16] {
+        let col = &cols[i * n..][..n];
+        let expect = unsafe { relax_scalar(row, col, prev) };
+        assert_eq!(unsafe { relax_neon(row, col, prev) }.0, expect.0);
+    }
+}
+
+/* ---------- generated SIMD equivalence ---------- */
+
+use proptest::prelude::*;
+
+/// Symmetric `n`×`n` matrices whose entries are either ordinary or within
+/// 1000 of `u32::MAX`, where the SIMD kernels' saturation tricks kick in.
+fn symmetric_matrix() -> impl Strategy<Value = Vec<Vec<u32>>> {
+    (2usize..=13).prop_flat_map(|n| {
+        let entry = prop_oneof![0u32..1000, u32::MAX - 1000..=u32::MAX];
+        prop::collection::vec(entry, n * (n - 1) / 2).prop_map(move |upper| {
+            let mut dist = vec![vec![0; n]; n];
+            let pairs = (0..n).flat_map(|i| (i + 1..n).map(move |j| (i, j)));
+            for ((i, j), d) in pairs.zip(upper) {
+                dist[i][j] = d;
+                dist[j][i] = d;
+            }
+            dist
+        })
+    })
+}
+
+/// `compute_simd` on this CPU, or `None` without the needed features.
+fn simd_cost(dist: &[Vec<u32>]) -> Option<u32> {
+    let mut solver = DpSolver::new(dist.len(), dist.to_vec());
+    let full = (1 << dist.len()) - 1;
+    #[cfg(target_arch = "x86_64")]
+    if is_x86_feature_detected!("avx2") {
+        return Some(unsafe { solver.compute_simd(full) });
+    }
+    #[cfg(target_arch = "aarch64")]
+    if std::arch::is_aarch64_feature_detected!("neon") {
+        return Some(unsafe { solver.compute_simd(full) });
+    }
+    None
+}
+
+proptest! {
+    #[test]
+    fn simd_and_scalar_costs_are_identical(dist in symmetric_matrix()) {
+        let n = dist.len();
+        let scalar = DpSolver::new(n, dist.clone()).compute_scalar();
+        if let Some(simd) = simd_cost(&dist) {
+            prop_assert_eq!(simd, scalar);
+        }
+        prop_assert_eq!(DpSolver::new(n, dist).compute(), scalar);
+    }
+}
@@ -1,201 +1,188 @@
 //! build.rs – generates a perfect Tic‑Tac‑Toe solver at compile‑time
-
 //!
-
 //! * enumerates every possible board (3^9 states)
-
 //! * runs minimax to label each state:
-
-//!     1 = “X wins”, ‑1 = “O wins”,  0 = “forced draw”
-
-//! * computes the *best move* (cell 0‑8) for every “X to move” state
-
-//! * writes a `const fn lookup()` that returns (score, best_move)
-
+//!   1 = “X wins”, ‑1 = “O wins”,  0 = “forced draw”
+//! * computes the *best move* (cell 0‑8) for every state, whoever is to
+//!   move, so the engine can play either side
+//! * only solves one board per symmetry class (the 8 rotations and
+//!   reflections), then maps scores and moves back to the other members
+//! * does it all twice: normal rules, then misère (completing a line loses)
+//! * writes `TABLE_CHECKSUM`, an FNV-1a hash of every entry, so the crate
+//!   can check the tables it was built with
+//! * writes the tables bit-packed: 2 bits per score, 4 bits per move
+//!   (15 = none); the `unpacked-tables` feature writes plain `i8`/`u8`
+//!   arrays instead, which are easier to eyeball while debugging
 //!
-
 //! No external data or crates required.
 
-
-
 use std::{env,fs,path::Path};
 
-
-
 const POW3: [u32;10] = {
-
     let mut p = [1u32;10];
-
     let mut i=1; while i<10 { p[i] = p[i-1]*3; i+=1; }
-
     p
-
 };
 
-
-
 #[derive(Clone,Copy,PartialEq)]
-
 enum Cell { E=0, X=1, O=2 }
 
-
-
 #[derive(Clone)]
-
 struct Board([Cell;9]);
 
-
-
 impl Board {
-
     fn from_id(mut id:u32)->Self{
-
         let mut b=[Cell::E;9];
-
         for c in &mut b {
-
             *c = match id%3 {0=>Cell::E,1=>Cell::X,_=>Cell::O};
-
             id/=3;
-
         }
-
         Board(b)
-
     }
-
     fn id(&self)->u32{
-
         self.0.iter().enumerate().map(|(i,c)| (*c as u32)*POW3[i]).sum()
-
     }
-
     fn turn(&self)->Cell{
-
         let xs=self.0.iter().filter(|&&c|c==Cell::X).count();
-
         let os=self.0.iter().filter(|&&c|c==Cell::O).count();
-
         if xs==os {Cell::X} else {Cell::O}
-
     }
-
+    /// Owner of a line; X if both have one (only after play went on past
+    /// the end), so the answer is the same for every symmetric board.
     fn winner(&self)->Option<Cell>{
-
         const LINES:[[usize;3];8]=[
-
             [0,1,2],[3,4,5],[6,7,8],[0,3,6],
-
             [1,4,7],[2,5,8],[0,4,8],[2,4,6]];
-
-        for line in &LINES{
-
-            let [a,b,c]=*line;
-
-            let ca=self.0[a];
-
-            if ca!=Cell::E && ca==self.0[b] && ca==self.0[c] {return Some(ca);}
-
-        }
-
-        None
-
+        [Cell::X,Cell::O].into_iter().find(|&who|
+            LINES.iter().any(|line| line.iter().all(|&i| self.0[i]==who)))
     }
-
+    /// Empty cells, centre first, then corners, then edges (the order
+    /// `Board::moves` uses at runtime, so ties break the same way).
     fn moves(&self)->Vec<usize>{
-
-        self.0.iter().enumerate().filter_map(|(i,c)|
-
-            if *c==Cell::E {Some(i)} else {None}).collect()
-
+        const ORDER:[usize;9]=[4,0,2,6,8,1,3,5,7];
+        ORDER.iter().copied().filter(|&i| self.0[i]==Cell::E).collect()
     }
-
     fn play(&mut self, idx:usize){
-
         self.0[idx]=self.turn();
-
     }
-
 }
 
+/// The 8 symmetries of the square as cell permutations: cell `i` of a
+/// board lands on cell `SYMS[t][i]` of its image.
+const SYMS: [[usize;9];8] = {
+    let mut syms=[[0usize;9];8];
+    let mut t=0; while t<8 {
+        let mut i=0; while i<9 {
+            let (mut r,mut c)=(i/3,i%3);
+            if t>=4 { c=2-c; }                                  // mirror first …
+            let mut k=0; while k<t%4 { let r0=r; r=c; c=2-r0; k+=1; } // … then rotate
+            syms[t][i]=r*3+c;
+            i+=1;
+        }
+        t+=1;
+    }
+    syms
+};
 
+/// One FNV-1a step; the library's `verify_tables` hashes the same way.
+fn fnv1a(h:u64, byte:u8)->u64{ (h^byte as u64).wrapping_mul(0x0000_0100_0000_01B3) }
 
-/* minimax with memoisation on 19 683 states */
+impl Board {
+    fn transform(&self, t:usize)->Board{
+        let mut b=[Cell::E;9];
+        for i in 0..9 { b[SYMS[t][i]]=self.0[i]; }
+        Board(b)
+    }
+    /// Representative of the board's symmetry class (smallest id) and a
+    /// symmetry `t` with `self.transform(t)` equal to it.
+    fn canonical(&self)->(Board,usize){
+        (0..8).map(|t| (self.transform(t),t)).min_by_key(|(b,_)| b.id()).unwrap()
+    }
+}
 
+/* minimax with memoisation on the canonical boards among 19 683 states */
 fn main(){
-
-    let mut score  = vec![None::<i8>; 19_683];
-
-    let mut best   = vec![255u8;      19_683];
-
-
-
-    fn solve(b:&mut Board, cache:&mut[Option<i8>], best:&mut[ u8]) -> i8 {
-
+    // `runtime-search` solves positions on demand, `const-tables` during
+    // compilation; either way there is nothing to generate
+    if env::var_os("CARGO_FEATURE_RUNTIME_SEARCH").is_some()
+        || env::var_os("CARGO_FEATURE_CONST_TABLES").is_some() { return; }
+
+    /// Solve the canonical form of `b`; results are stored under the
+    /// canonical id, moves in canonical coordinates.
+    fn solve(b:&Board, cache:&mut[Option<i8>], best:&mut[ u8], misere:bool) -> i8 {
+        let b=b.canonical().0;
         let id=b.id() as usize;
-
         if let Some(s)=cache[id]{ return s; }
-
         if let Some(w)=b.winner(){
-
-            let s = if w==Cell::X {1} else {-1};
-
+            // misère: whoever completed the line loses
+            let s = if (w==Cell::X)!=misere {1} else {-1};
             cache[id]=Some(s); return s;
-
         }
-
         if b.moves().is_empty(){ cache[id]=Some(0); return 0; }
 
-
-
-        let mut best_score=-2; // worse than loss
-
+        // Scores are from X's side: X maximises, O minimises
+        let sign = if b.turn()==Cell::X {1} else {-1};
+        let mut best_score=-2; // worse than loss, for the side to move
         let mut best_move=255;
-
         for m in b.moves(){
-
             let mut nb=b.clone(); nb.play(m);
-
-            let s = -solve(&mut nb, cache, best); // opponent perspective
-
+            let s = sign*solve(&nb, cache, best, misere);
             if s>best_score { best_score=s; best_move=m as u8; }
-
             if best_score==1 {break;}
-
         }
-
+        let best_score=sign*best_score;
         cache[id]=Some(best_score);
-
         best[id]=best_move;
-
         best_score
-
     }
 
-
-
-    for id in 0..19_683{
-
-        let mut brd = Board::from_id(id as u32);
-
-        if brd.turn()==Cell::X { solve(&mut brd,&mut score,&mut best); }
-
-    }
-
-
-
     /* generate Rust source */
-
     let out = env::var("OUT_DIR").unwrap();
-
     let dest= Path::new(&out).join("tictac_tables.rs");
-
+    let unpacked = env::var_os("CARGO_FEATURE_UNPACKED_TABLES").is_some();
     let mut code = String::from("/// Auto‑generated perfect‑play tables\n");
-
-    code.push_str(&format!("pub static SCORE: [i8;19683] = {:?};\n",score.iter().map(|o|o.unwrap_or(0)).collect::<Vec<_>>()));
-
-    code.push_str(&format!("pub static BEST : [u8;19683] = {:?};\n",best));
-
+    let mut checksum:u64 = 0xCBF2_9CE4_8422_2325;
+    for (prefix,misere) in [("",false),("MISERE_",true)]{
+        let mut score  = vec![None::<i8>; 19_683];
+        let mut best   = vec![255u8;      19_683];
+        for id in 0..19_683{
+            let brd = Board::from_id(id as u32);
+            solve(&brd,&mut score,&mut best,misere);
+        }
+        // copy each class's result to the boards it stands for
+        for id in 0..19_683{
+            let (canon,t) = Board::from_id(id as u32).canonical();
+            let cid = canon.id() as usize;
+            if cid==id { continue; }
+            score[id]=score[cid];
+            let m=best[cid] as usize;
+            best[id] = if m==255 {255} else {SYMS[t].iter().position(|&j| j==m).unwrap() as u8};
+        }
+        let score:Vec<i8>=score.iter().map(|o|o.unwrap_or(0)).collect();
+        for (&s,&m) in score.iter().zip(&best){
+            checksum=fnv1a(fnv1a(checksum,s as u8),m);
+        }
+        if unpacked {
+            code.push_str(&format!("pub static {}SCORE: [i8;19683] = {:?};\n",prefix,score));
+            code.push_str(&format!("pub static {}BEST : [u8;19683] = {:?};\n",prefix,best));
+            continue;
+        }
+        // scores: 0 draw, 1 X wins, 2 O wins; four to a byte, lowest bits first
+        let mut bits=vec![0u8;19_683usize.div_ceil(4)];
+        for (id,&s) in score.iter().enumerate(){
+            bits[id/4] |= (match s {1=>1,-1=>2,_=>0}) << (id%4*2);
+        }
+        // moves: two to a byte, low nibble first
+        let mut nibbles=vec![0u8;19_683usize.div_ceil(2)];
+        for (id,&m) in best.iter().enumerate(){
+            nibbles[id/2] |= (if m==255 {15} else {m}) << (id%2*4);
+        }
+        code.push_str(&format!("static {}SCORE_BITS: [u8;{}] = {:?};\n",prefix,bits.len(),bits));
+        code.push_str(&format!("static {}BEST_NIBBLES: [u8;{}] = {:?};\n",
+            prefix,nibbles.len(),nibbles));
+    }
+    code.push_str("/// FNV-1a hash of every table entry (score byte, then move byte with\n");
+    code.push_str("/// 255 for none; normal rules, then misère), see [`verify_tables`].\n");
+    code.push_str(&format!("pub const TABLE_CHECKSUM: u64 = 0x{:016X};\n",checksum));
     fs::write(dest,code).unwrap();
-
 }
@@ -0,0 +1,85 @@
+//! Whole-game statistics for the 3×3 board, read off the perfect-play
+//! tables: how many positions a game can reach, how each of them ends
+//! with perfect play, and how long a won position takes to finish when
+//! the winner hurries and the loser holds out.
+
+use crate::{table_score,Board,Cell,Position,Variant,BOARD_IDS};
+use std::cmp::Reverse;
+
+/// Counts over every position reachable from the empty board, from
+/// [`tree_stats`].
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub struct TreeStats{
+    /// Positions some game passes through, the empty board and finished
+    /// games included.
+    pub reachable:usize,
+    /// Reachable positions where the game is over.
+    pub terminal:usize,
+    /// Reachable positions X wins with perfect play.
+    pub x_wins:usize,
+    /// Reachable positions O wins with perfect play.
+    pub o_wins:usize,
+    /// Reachable positions drawn with perfect play.
+    pub draws:usize,
+    /// By [`Board::id`]: plies until a won position is over, the winner
+    /// taking the shortest win and the loser the longest defence. `None`
+    /// for drawn and unreachable positions, `Some(0)` once a line is done.
+    pub win_length:Vec<Option<u8>>,
+}
+
+impl TreeStats{
+    /// [`TreeStats::win_length`] of `board`.
+    pub fn forced_win(&self, board:&Board)->Option<u8>{ self.win_length[board.id()] }
+    /// The longest forced win over all reachable positions, with the
+    /// lowest-id position that needs it; `None` if every position draws.
+    pub fn longest_forced_win(&self)->Option<(Board,u8)>{
+        let (id,plies)=self.win_length.iter().enumerate()
+            .filter_map(|(id,w)| Some((id,(*w)?)))
+            .min_by_key(|&(id,plies)| (Reverse(plies),id))?;
+        Some((Board::from_id(id as u32),plies))
+    }
+}
+
+/// [`tree_stats_with`] under normal rules.
+pub fn tree_stats()->TreeStats{ tree_stats_with(Variant::Normal) }
+
+/// Walk every position reachable from the empty board and classify it by
+/// its tabulated score under `variant`.
+pub fn tree_stats_with(variant:Variant)->TreeStats{
+    let mut seen=vec![false;BOARD_IDS as usize];
+    let mut reachable=vec![Board::default()];
+    seen[0]=true;
+    let mut next=0;
+    while let Some(b)=reachable.get(next).cloned(){
+        for nb in children(&b){
+            if !seen[nb.id()] { seen[nb.id()]=true; reachable.push(nb); }
+        }
+        next+=1;
+    }
+    // Every move adds a mark, so fuller boards first means children first
+    reachable.sort_by_key(|b| Reverse(b.0.iter().filter(|&&c| c!=Cell::E).count()));
+
+    let mut stats=TreeStats{reachable:reachable.len(),terminal:0,x_wins:0,o_wins:0,draws:0,
+        win_length:vec![None;BOARD_IDS as usize]};
+    for b in &reachable{
+        let score=table_score(variant,b.id());
+        match score { 1=>stats.x_wins+=1, -1=>stats.o_wins+=1, _=>stats.draws+=1 }
+        let moves=children(b);
+        if moves.is_empty() { stats.terminal+=1; }
+        if score==0 { continue; }
+        // Only moves that keep the result count; the loser has no others
+        let lengths=moves.iter()
+            .filter(|nb| table_score(variant,nb.id())==score)
+            .filter_map(|nb| stats.win_length[nb.id()]);
+        let winner_to_move=(b.turn()==Cell::X)==(score==1);
+        let plies = if moves.is_empty() { Some(0) }
+            else if winner_to_move { lengths.min().map(|p| p+1) }
+            else { lengths.max().map(|p| p+1) };
+        stats.win_length[b.id()]=plies;
+    }
+    stats
+}
+
+fn children(b:&Board)->Vec<Board>{
+    b.moves().into_iter().map(|m|{ let mut nb=b.clone(); nb.play(m); nb }).collect()
+}
@@ -0,0 +1,182 @@
+//! The perfect-play tables computed by const evaluation (feature
+//! `const-tables`), for builds where build scripts cannot run.
+//!
+//! Same rules, move order and symmetry handling as `build.rs`, so the
+//! tables come out identical. Instead of recursing, positions are solved
+//! backwards from full boards: a child always has one more mark than its
+//! parent, so going by decreasing mark count only ever looks up finished
+//! entries. The const evaluation adds roughly half a minute to compiling.
+#![allow(long_running_const_eval)]
+
+const N:usize=19_683;
+const POW3:[usize;10]=[1,3,9,27,81,243,729,2187,6561,19_683];
+const LINES:[[usize;3];8]=crate::LINES;
+/// Centre first, then corners, then edges, like `build.rs`.
+const ORDER:[usize;9]=[4,0,2,6,8,1,3,5,7];
+/// Cell `i` of a board lands on cell `SYMS[t][i]` of its `t`-th image, in
+/// the order `build.rs` lists them.
+const SYMS:[[usize;9];8]=[
+    [0,1,2,3,4,5,6,7,8],[2,5,8,1,4,7,0,3,6],[8,7,6,5,4,3,2,1,0],[6,3,0,7,4,1,8,5,2],
+    [2,1,0,5,4,3,8,7,6],[8,5,2,7,4,1,6,3,0],[6,7,8,3,4,5,0,1,2],[0,3,6,1,4,7,2,5,8]];
+
+/// Cell `i` of board `id`: 0 empty, 1 X, 2 O.
+const fn cell(id:usize, i:usize)->usize{ id/POW3[i]%3 }
+
+const fn count(id:usize, who:usize)->usize{
+    let (mut n,mut i)=(0,0);
+    while i<9 { if cell(id,i)==who { n+=1; } i+=1; }
+    n
+}
+
+/// 1 or 2 for the owner of a line (X if both have one), 0 for none.
+const fn winner(id:usize)->usize{
+    let mut who=1; while who<=2 {
+        let mut l=0; while l<8 {
+            let [a,b,c]=LINES[l];
+            if cell(id,a)==who && cell(id,b)==who && cell(id,c)==who { return who; }
+            l+=1;
+        }
+        who+=1;
+    }
+    0
+}
+
+/// Smallest-id image of `id` and the symmetry that produces it.
+const fn canonical(id:usize)->(usize,usize){
+    let (mut best,mut best_t)=(id,0);
+    let mut t=1; while t<8 {
+        let (mut img,mut i)=(0,0);
+        while i<9 { img+=cell(id,i)*POW3[SYMS[t][i]]; i+=1; }
+        if img<best { best=img; best_t=t; }
+        t+=1;
+    }
+    (best,best_t)
+}
+
+/// Score (X's side) and best move (255 for none) of every board.
+const fn solve(misere:bool)->([i8;N],[u8;N]){
+    let mut score=[0i8;N];
+    let mut best=[255u8;N];
+    let mut done=[false;N];
+    let order=by_marks();
+    // fullest boards first, so every child is solved before its parent
+    let mut k=N; while k>0 {
+        k-=1;
+        let id=order[k];
+        let (c,t)=canonical(id);
+        if !done[c] {
+            let (s,m)=solve_one(c,&score,misere);
+            score[c]=s; best[c]=m; done[c]=true;
+        }
+        if id!=c {
+            score[id]=score[c];
+            // back from canonical coordinates
+            if best[c]!=255 {
+                let mut i=0; while SYMS[t][i]!=best[c] as usize { i+=1; }
+                best[id]=i as u8;
+            }
+        }
+    }
+    (score,best)
+}
+
+/// Every board id, sorted by the number of marks on it.
+const fn by_marks()->[usize;N]{
+    let mut start=[0usize;11];
+    let mut id=0; while id<N { start[10-count(id,0)]+=1; id+=1; }
+    let mut i=1; while i<11 { start[i]+=start[i-1]; i+=1; }
+    let mut order=[0usize;N];
+    let mut id=0; while id<N {
+        let m=9-count(id,0);
+        order[start[m]]=id; start[m]+=1;
+        id+=1;
+    }
+    order
+}
+
+/// Solve `id` given the scores of all boards with one more mark.
+const fn solve_one(id:usize, score:&[i8;N], misere:bool)->(i8,u8){
+    let w=winner(id);
+    if w!=0 { return (if (w==1)!=misere {1} else {-1}, 255); }
+    let mover = if count(id,1)==count(id,2) {1} else {2};
+    // scores are from X's side: X maximises, O minimises
+    let sign:i8 = if mover==1 {1} else {-1};
+    let (mut best_score,mut best_move)=(-2i8,255u8);
+    let mut k=0; while k<9 {
+        let m=ORDER[k];
+        if cell(id,m)==0 {
+            let s=sign*score[id+mover*POW3[m]];
+            if s>best_score { best_score=s; best_move=m as u8; }
+            if best_score==1 { break; }
+        }
+        k+=1;
+    }
+    if best_move==255 { return (0,255); } // full board
+    (sign*best_score,best_move)
+}
+
+// statics, not consts: other crates reading `TABLE_CHECKSUM` would
+// otherwise have to run the solve again
+static NORMAL:([i8;N],[u8;N])=solve(false);
+static MISERE:([i8;N],[u8;N])=solve(true);
+
+/// 2 bits per score (0 draw, 1 X wins, 2 O wins), as `build.rs` packs them.
+#[cfg(not(feature="unpacked-tables"))]
+const fn pack_scores(score:&[i8;N])->[u8;N.div_ceil(4)]{
+    let mut bits=[0u8;N.div_ceil(4)];
+    let mut id=0; while id<N {
+        let v = match score[id] {1=>1,-1=>2,_=>0};
+        bits[id/4] |= v << (id%4*2);
+        id+=1;
+    }
+    bits
+}
+
+/// 4 bits per move, 15 for none, as `build.rs` packs them.
+#[cfg(not(feature="unpacked-tables"))]
+const fn pack_moves(best:&[u8;N])->[u8;N.div_ceil(2)]{
+    let mut nibbles=[0u8;N.div_ceil(2)];
+    let mut id=0; while id<N {
+        let m = if best[id]==255 {15} else {best[id]};
+        nibbles[id/2] |= m << (id%2*4);
+        id+=1;
+    }
+    nibbles
+}
+
+/// FNV-1a over every entry, the way `build.rs` computes it.
+const fn checksum()->u64{
+    let mut h:u64=0xCBF2_9CE4_8422_2325;
+    let mut v=0; while v<2 {
+        let (score,best) = if v==0 {&NORMAL} else {&MISERE};
+        let mut id=0; while id<N {
+            h=(h^score[id] as u8 as u64).wrapping_mul(0x0000_0100_0000_01B3);
+            h=(h^best[id] as u64).wrapping_mul(0x0000_0100_0000_01B3);
+            id+=1;
+        }
+        v+=1;
+    }
+    h
+}
+
+#[cfg(not(feature="unpacked-tables"))]
+pub(crate) static SCORE_BITS:[u8;N.div_ceil(4)]=pack_scores(&NORMAL.0);
+#[cfg(not(feature="unpacked-tables"))]
+pub(crate) static BEST_NIBBLES:[u8;N.div_ceil(2)]=pack_moves(&NORMAL.1);
+#[cfg(not(feature="unpacked-tables"))]
+pub(crate) static MISERE_SCORE_BITS:[u8;N.div_ceil(4)]=pack_scores(&MISERE.0);
+#[cfg(not(feature="unpacked-tables"))]
+pub(crate) static MISERE_BEST_NIBBLES:[u8;N.div_ceil(2)]=pack_moves(&MISERE.1);
+
+#[cfg(feature="unpacked-tables")]
+pub static SCORE:[i8;N]=NORMAL.0;
+#[cfg(feature="unpacked-tables")]
+pub static BEST:[u8;N]=NORMAL.1;
+#[cfg(feature="unpacked-tables")]
+pub static MISERE_SCORE:[i8;N]=MISERE.0;
+#[cfg(feature="unpacked-tables")]
+pub static MISERE_BEST:[u8;N]=MISERE.1;
+
+/// FNV-1a hash of every table entry (score byte, then move byte with 255
+/// for none; normal rules, then misère), see [`crate::verify_tables`].
+pub const TABLE_CHECKSUM:u64=checksum();
@@ -0,0 +1,146 @@
+//! Opponents of adjustable strength.
+//!
+//! [`Engine`] picks moves for whichever side is to move. `Perfect` plays
+//! the table (or search) move, `Easy` a uniformly random legal one, and
+//! `Medium` the perfect move except for a configurable share of random
+//! blunders. Randomness comes from a seeded generator, so games replay
+//! exactly for a given seed.
+//!
+//! [`Bot`]s are weaker, table-free opponents, and [`Player`] lets code
+//! take any of them.
+
+use crate::{Board,Cell,Event,Game,Position};
+
+/// How strongly an [`Engine`] plays.
+#[derive(Clone,Copy,Debug,Default,PartialEq,Eq)]
+pub enum Difficulty {
+    /// Random legal moves.
+    Easy,
+    /// Perfect moves, except for random ones at the blunder rate.
+    Medium,
+    /// Always a perfect-play move.
+    #[default]
+    Perfect,
+}
+
+/// Move chooser for a [`Game`], with its own seeded random stream.
+#[derive(Clone,Debug)]
+pub struct Engine{ difficulty:Difficulty, blunder_rate:f64, rng:SplitMix64 }
+
+impl Engine{
+    /// Engine at `difficulty`, seed 0 and a blunder rate of 0.3.
+    pub fn with_difficulty(difficulty:Difficulty)->Self{
+        Self{difficulty,blunder_rate:0.3,rng:SplitMix64(0)}
+    }
+    /// Restart the random stream from `seed`.
+    pub fn with_seed(self, seed:u64)->Self{ Self{rng:SplitMix64(seed),..self} }
+    /// Share of `Medium` moves (0.0 to 1.0) that are random instead of
+    /// perfect; panics outside that range.
+    pub fn with_blunder_rate(self, blunder_rate:f64)->Self{
+        assert!((0.0..=1.0).contains(&blunder_rate),"blunder rate must be in 0..=1");
+        Self{blunder_rate,..self}
+    }
+    pub fn difficulty(&self)->Difficulty{ self.difficulty }
+    /// Move for the side to move, `None` once the game is over.
+    pub fn choose<B:Position>(&mut self, game:&Game<B>)->Option<usize>{
+        let moves=game.board().moves();
+        if moves.is_empty() { return None; }
+        let random = match self.difficulty {
+            Difficulty::Easy => true,
+            Difficulty::Medium => self.rng.next_f64()<self.blunder_rate,
+            Difficulty::Perfect => false,
+        };
+        if random { Some(moves[self.rng.below(moves.len())]) } else { game.best_move() }
+    }
+    /// Choose a move and play it, announcing it with
+    /// [`Event::EngineDecision`]; returns the cell, `None` if the game was
+    /// already over.
+    pub fn play<B:Position>(&mut self, game:&mut Game<B>)->Option<usize>{
+        let m=self.choose(game)?;
+        let mut b=game.board().clone(); b.play(m);
+        game.emit(Event::EngineDecision{cell:m,score:b.solve(game.variant()).0});
+        game.play(m);
+        Some(m)
+    }
+}
+
+/// Something that picks moves in a [`Game`]: an [`Engine`] or a [`Bot`].
+pub trait Player<B:Position=Board>{
+    /// Move for the side to move, `None` once the game is over.
+    fn choose(&mut self, game:&Game<B>)->Option<usize>;
+    /// Choose a move and play it; returns the cell, `None` if the game was
+    /// already over.
+    fn play(&mut self, game:&mut Game<B>)->Option<usize>{
+        let m=self.choose(game)?;
+        game.play(m);
+        Some(m)
+    }
+}
+
+impl<B:Position> Player<B> for Engine{
+    fn choose(&mut self, game:&Game<B>)->Option<usize>{ Engine::choose(self,game) }
+    fn play(&mut self, game:&mut Game<B>)->Option<usize>{ Engine::play(self,game) }
+}
+
+/// Simple opponents that do not look the position up: one that plays at
+/// random and one that only sees a move ahead.
+#[derive(Clone,Debug)]
+pub struct Bot{ strategy:Strategy }
+
+#[derive(Clone,Debug)]
+enum Strategy{ Random(SplitMix64), Greedy }
+
+impl Bot{
+    /// Uniformly random legal moves from a stream seeded with `seed`.
+    pub fn random(seed:u64)->Self{ Self{strategy:Strategy::Random(SplitMix64(seed))} }
+    /// Completes a line when that wins, otherwise avoids moves that lose
+    /// at once or let the opponent win next move; ties go to the first
+    /// move in engine move order.
+    pub fn greedy()->Self{ Self{strategy:Strategy::Greedy} }
+}
+
+impl<B:Position> Player<B> for Bot{
+    fn choose(&mut self, game:&Game<B>)->Option<usize>{
+        let moves=game.board().moves();
+        match &mut self.strategy{
+            _ if moves.is_empty() => None,
+            Strategy::Random(rng) => Some(moves[rng.below(moves.len())]),
+            Strategy::Greedy => {
+                // a finished board's value for the side to move
+                let sign = if game.board().turn()==Cell::X {1} else {-1};
+                let value=|b:&B| b.winner().map_or(0,|w| sign*game.variant().line_value(w));
+                let mut best:Option<(usize,i8)>=None;
+                for m in moves{
+                    let mut b=game.board().clone(); b.play(m);
+                    let v = match value(&b){
+                        0 if b.moves().into_iter().any(|r|{
+                            let mut c=b.clone(); c.play(r);
+                            value(&c)<0
+                        }) => -1,
+                        v => v,
+                    };
+                    if best.is_none_or(|(_,bv)| v>bv) { best=Some((m,v)); }
+                }
+                best.map(|(m,_)| m)
+            }
+        }
+    }
+}
+
+/// SplitMix64: tiny, fast, and plenty for picking moves.
+#[derive(Clone,Debug)]
+pub(crate) struct SplitMix64(pub(crate) u64);
+
+impl SplitMix64{
+    pub(crate) fn next_u64(&mut self)->u64{
+        self.0=self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z=self.0;
+        z=(z^(z>>30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z=(z^(z>>27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z^(z>>31)
+    }
+    /// Uniform in `[0, 1)`.
+    pub(crate) fn next_f64(&mut self)->f64{ (self.next_u64()>>11) as f64/(1u64<<53) as f64 }
+    /// Uniform in `0..n`; `n` must be nonzero.
+    pub(crate) fn below(&mut self, n:usize)->usize{ (self.next_u64()%n as u64) as usize }
+}
@@ -0,0 +1,75 @@
+//! N×N boards won by k in a row (k = N = 3 is classic tic-tac-toe;
+//! Gomoku-style games use larger boards and k).
+
+use crate::{Cell,Position};
+use std::fmt::{self,Display};
+
+/// `size`×`size` board won by `k` marks in a row, horizontally,
+/// vertically or diagonally. Cells are numbered row by row from 0.
+#[derive(Clone,Debug,PartialEq,Eq,Hash)]
+pub struct GridBoard{ size:usize, k:usize, cells:Vec<Cell> }
+
+impl GridBoard{
+    /// Empty board; panics unless `1 <= k <= size`.
+    pub fn new(size:usize, k:usize)->Self{
+        assert!(k>=1 && k<=size,"need 1 <= k <= size, got k = {} for size {}",k,size);
+        Self{ size, k, cells:vec![Cell::E;size*size] }
+    }
+    pub fn size(&self)->usize{ self.size }
+    pub fn k(&self)->usize{ self.k }
+    pub fn cells(&self)->&[Cell]{ &self.cells }
+
+    /// Whether `k` cells from `(r,c)` stepping by `(dr,dc)` stay on the
+    /// board and all hold `who`.
+    fn line_from(&self, r:usize, c:usize, (dr,dc):(isize,isize), who:Cell)->bool{
+        (0..self.k as isize).all(|t|{
+            let (rr,cc)=(r as isize+dr*t, c as isize+dc*t);
+            let n=self.size as isize;
+            rr>=0 && rr<n && cc>=0 && cc<n && self.cells[(rr*n+cc) as usize]==who
+        })
+    }
+}
+
+impl Position for GridBoard{
+    fn turn(&self)->Cell{
+        let xs=self.cells.iter().filter(|&&c|c==Cell::X).count();
+        let os=self.cells.iter().filter(|&&c|c==Cell::O).count();
+        if xs==os {Cell::X} else {Cell::O}
+    }
+    fn winner(&self)->Option<Cell>{
+        const DIRS:[(isize,isize);4]=[(0,1),(1,0),(1,1),(1,-1)];
+        [Cell::X,Cell::O].into_iter().find(|&who| (0..self.cells.len()).any(|i|{
+            let (r,c)=(i/self.size, i%self.size);
+            self.cells[i]==who && DIRS.iter().any(|&d| self.line_from(r,c,d,who))
+        }))
+    }
+    /// Empty cells nearest the centre first, which finds wins (and so
+    /// cut-offs) sooner.
+    fn moves(&self)->Vec<usize>{
+        if self.winner().is_some() { return Vec::new(); }
+        let mid=self.size-1; // twice the centre coordinate
+        let dist=|i:usize| (2*(i/self.size)).abs_diff(mid)+(2*(i%self.size)).abs_diff(mid);
+        let mut free:Vec<usize>=(0..self.cells.len()).filter(|&i| self.cells[i]==Cell::E).collect();
+        free.sort_by_key(|&i| dist(i));
+        free
+    }
+    fn cell(&self, idx:usize)->Option<Cell>{ self.cells.get(idx).copied() }
+    fn play(&mut self, idx:usize){
+        assert!(self.cells[idx]==Cell::E,"square occupied");
+        self.cells[idx]=self.turn();
+    }
+}
+
+impl Display for GridBoard{
+    fn fmt(&self,f:&mut fmt::Formatter<'_>)->fmt::Result{
+        let rule=vec!["—";self.size].join("+");
+        for (r,row) in self.cells.chunks(self.size).enumerate(){
+            if r>0 { writeln!(f,"\n{}",rule)?; }
+            for (c,cell) in row.iter().enumerate(){
+                if c>0 {write!(f,"|")?;}
+                write!(f,"{}",cell)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,177 +1,629 @@
 //! # Perfect‑Play Tic‑Tac‑Toe
-
-//! The decision table is embedded at **compile time** by `build.rs`.
-
+//! The decision table is embedded at **compile time** by `build.rs`
+//! (or, with the `const-tables` feature, by const evaluation without a
+//! build script; with `runtime-search`, searched in-process on demand).
+//!
+//! Both normal rules and misère ([`Variant::Misere`]: completing a line
+//! loses) are tabulated; pick one with [`Game::new_with`].
+//!
+//! Weaker opponents for humans to beat come from [`Engine`] at a chosen
+//! [`Difficulty`]; [`simulate`] and [`tournament`] pit engines against
+//! each other.
+//!
+//! Larger boards ([`GridBoard`]: N×N, k in a row) go through the same
+//! [`Game`] API, backed by a runtime search instead of tables. Ultimate
+//! tic-tac-toe, too big to solve, has its own engine in [`ultimate`].
+//!
+//! [`analysis::tree_stats`] sums the tables up over the whole game tree,
+//! for checks that cover every position rather than a few.
 //!
-
 //! Public API highlights
-
 //! ```rust
-
-//! use task_ws::{Board,Cell,Game};
-
+//! use task_ws::{Board,Cell,Game,GridBoard,Variant};
 //! let g = Game::new();
-
 //! let next = g.best_move();            // engine suggestion
-
+//! let big = Game::from_board(GridBoard::new(4,3));
+//! let misere = Game::new_with(Variant::Misere);
 //! ```
 
-
-
 use std::fmt::{self,Display};
-
-
-
-#[derive(Clone,Copy,PartialEq)]
-
+use std::hash::Hash;
+use std::str::FromStr;
+
+pub mod analysis;
+#[cfg(all(feature="const-tables",not(feature="runtime-search")))]
+mod const_tables;
+mod engine;
+mod grid;
+pub mod notation;
+#[cfg(feature="runtime-search")]
+mod runtime;
+mod search;
+mod simulate;
+pub mod ultimate;
+#[cfg(feature="wasm")]
+pub mod wasm;
+
+pub use engine::{Bot,Difficulty,Engine,Player};
+pub use grid::GridBoard;
+pub use simulate::{simulate,tournament,MatchStats};
+
+/// Rows, columns and diagonals of a 3×3 grid.
+pub(crate) const LINES:[[usize;3];8]=[
+    [0,1,2],[3,4,5],[6,7,8],[0,3,6],
+    [1,4,7],[2,5,8],[0,4,8],[2,4,6]];
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq,Hash)]
+#[cfg_attr(feature="serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Cell { E=0, X=1, O=2 }
 
-
-
 impl Display for Cell{
-
     fn fmt(&self,f:&mut fmt::Formatter<'_>)->fmt::Result{
-
         write!(f,"{}",match self{Cell::E=>' ',Cell::X=>'X',Cell::O=>'O'})
-
     }
+}
 
+/// Who a completed line counts for.
+#[derive(Clone,Copy,Debug,Default,PartialEq,Eq,Hash)]
+#[cfg_attr(feature="serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Variant {
+    /// Three in a row wins.
+    #[default]
+    Normal,
+    /// Three in a row loses.
+    Misere,
 }
 
+impl Variant{
+    /// Score (from X's side) of a game ended by `owner` completing a line.
+    pub fn line_value(self, owner:Cell)->i8{
+        if (owner==Cell::X)!=(self==Variant::Misere) {1} else {-1}
+    }
+}
+
+/// Why a move was rejected by [`Position::try_play`].
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum MoveError {
+    /// No such cell on the board.
+    OutOfRange(usize),
+    /// The cell already holds a mark.
+    Occupied(usize),
+    /// Someone has won or the board is full.
+    GameOver,
+}
 
+impl Display for MoveError{
+    fn fmt(&self,f:&mut fmt::Formatter<'_>)->fmt::Result{
+        match self{
+            MoveError::OutOfRange(i)=>write!(f,"there is no square {}",i),
+            MoveError::Occupied(i)=>write!(f,"square {} is already taken",i),
+            MoveError::GameOver=>write!(f,"the game is over"),
+        }
+    }
+}
 
-#[derive(Clone)]
+impl std::error::Error for MoveError {}
+
+/// Rules of a board the [`Game`] API can drive. Scores are from X's side:
+/// 1 = X wins, -1 = O wins, 0 = draw (with perfect play from here).
+pub trait Position: Clone+Eq+Hash {
+    /// Side to move; X moves first.
+    fn turn(&self)->Cell;
+    /// Owner of a completed line, if any; X if both sides have one.
+    fn winner(&self)->Option<Cell>;
+    /// Empty cells, in the order the engine tries them; empty once the
+    /// game is over.
+    fn moves(&self)->Vec<usize>;
+    /// Contents of cell `idx`, `None` past the edge of the board.
+    fn cell(&self, idx:usize)->Option<Cell>;
+    /// Mark `idx` for the side to move; panics if it is occupied.
+    fn play(&mut self, idx:usize);
+    /// [`Position::play`], or why `idx` is not a legal move.
+    fn try_play(&mut self, idx:usize)->Result<(),MoveError>{
+        let cell=self.cell(idx).ok_or(MoveError::OutOfRange(idx))?;
+        if self.moves().is_empty() { return Err(MoveError::GameOver); }
+        if cell!=Cell::E { return Err(MoveError::Occupied(idx)); }
+        self.play(idx);
+        Ok(())
+    }
+    /// Perfect-play value under `variant` and a move achieving it (`None`
+    /// once the game is over); exhaustive search by default.
+    fn solve(&self, variant:Variant)->(i8,Option<usize>){ search::solve(self,variant) }
+    /// Perfect-play value of the position under normal rules.
+    fn score(&self)->i8{ self.solve(Variant::Normal).0 }
+    /// Perfect-play move under normal rules, `None` once the game is over.
+    fn best_move(&self)->Option<usize>{ self.solve(Variant::Normal).1 }
+}
 
+/// The classic 3×3 board, solved ahead of time by `build.rs`.
+///
+/// With the `serde` feature it serializes as its compact string, see
+/// [`Board::to_compact`].
+#[derive(Clone,Debug,PartialEq,Eq,Hash)]
+#[cfg_attr(feature="serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature="serde", serde(into="String", try_from="String"))]
 pub struct Board([Cell;9]);
 
+/// Why a compact board string was rejected.
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub enum ParseBoardError {
+    /// Not 9 cells once the `/` row separators are dropped.
+    Length(usize),
+    /// A cell that is not `X`, `O` or a space.
+    Char(char),
+    /// A decimal board id of [`BOARD_IDS`] or more.
+    Id(u32),
+}
 
+impl Display for ParseBoardError{
+    fn fmt(&self,f:&mut fmt::Formatter<'_>)->fmt::Result{
+        match self{
+            ParseBoardError::Length(n)=>write!(f,"expected 9 cells, got {}",n),
+            ParseBoardError::Char(c)=>write!(f,"unexpected {:?} (cells are X, O or space)",c),
+            ParseBoardError::Id(id)=>write!(f,"board id {} out of range (below {})",id,BOARD_IDS),
+        }
+    }
+}
 
-impl Default for Board { fn default()->Self{ Self([Cell::E;9]) }}
+impl std::error::Error for ParseBoardError {}
 
+/// Number of board ids: each of the 9 cells is empty, X or O.
+pub const BOARD_IDS: u32 = 19_683;
 
+impl Default for Board { fn default()->Self{ Self([Cell::E;9]) }}
 
 impl Board{
-
+    /// Base-3 index of the board (cell 0 least significant), as used by the
+    /// generated tables; always below [`BOARD_IDS`].
     pub fn id(&self)->usize{
-
         const POW3:[usize;10]=[1,3,9,27,81,243,729,2187,6561,19683];
-
         self.0.iter().enumerate().map(|(i,c)| *c as usize * POW3[i]).sum()
-
     }
-
+    /// Inverse of [`Board::id`]; panics if `id >= BOARD_IDS`.
+    pub fn from_id(id:u32)->Self{
+        Self::try_from_id(id).expect("board id out of range")
+    }
+    /// Inverse of [`Board::id`], or `None` if `id >= BOARD_IDS`.
+    pub fn try_from_id(mut id:u32)->Option<Self>{
+        if id>=BOARD_IDS { return None; }
+        let mut b=[Cell::E;9];
+        for c in &mut b {
+            *c = match id%3 {0=>Cell::E,1=>Cell::X,_=>Cell::O};
+            id/=3;
+        }
+        Some(Board(b))
+    }
+    /// [`Board::id`] in decimal, read back by [`Board::parse`].
+    pub fn to_string_id(&self)->String{ self.id().to_string() }
+    /// A decimal board id as [`Board::to_string_id`] writes it, or else the
+    /// compact form of [`Board::from_compact`].
+    pub fn parse(s:&str)->Result<Self,ParseBoardError>{
+        let t=s.trim();
+        if !t.is_empty() && t.bytes().all(|b| b.is_ascii_digit()) {
+            let id=t.parse::<u32>().unwrap_or(u32::MAX);
+            return Self::try_from_id(id).ok_or(ParseBoardError::Id(id));
+        }
+        Self::from_compact(s)
+    }
+    pub fn cells(&self)->&[Cell;9]{ &self.0 }
+    /// Cells of a completed line, X's if both sides have one.
+    pub fn winning_line(&self)->Option<[usize;3]>{
+        [Cell::X,Cell::O].into_iter().find_map(|who|
+            LINES.iter().copied().find(|line| line.iter().all(|&i| self.0[i]==who)))
+    }
+    /// Rows top to bottom separated by `/`, empty cells as spaces:
+    /// `"XOX/ XO/   "`.
+    pub fn to_compact(&self)->String{
+        self.0.chunks(3).map(|row| row.iter().map(|c| c.to_string()).collect::<String>())
+            .collect::<Vec<_>>().join("/")
+    }
+    /// Inverse of [`Board::to_compact`]; `x`/`o` are accepted too, and the
+    /// `/` separators are optional.
+    pub fn from_compact(s:&str)->Result<Self,ParseBoardError>{
+        let mut b=[Cell::E;9];
+        let mut n=0;
+        for ch in s.chars().filter(|&c| c!='/'){
+            let cell = match ch {
+                'X'|'x' => Cell::X,
+                'O'|'o' => Cell::O,
+                ' ' => Cell::E,
+                _ => return Err(ParseBoardError::Char(ch)),
+            };
+            if n<9 { b[n]=cell; }
+            n+=1;
+        }
+        if n!=9 { return Err(ParseBoardError::Length(n)); }
+        Ok(Board(b))
+    }
     pub fn turn(&self)->Cell{
-
         let xs=self.0.iter().filter(|&&c|c==Cell::X).count();
-
         let os=self.0.iter().filter(|&&c|c==Cell::O).count();
-
         if xs==os {Cell::X} else {Cell::O}
-
     }
-
     pub fn play(&mut self, idx:usize){
-
         assert!(self.0[idx]==Cell::E,"square occupied");
-
         self.0[idx]=self.turn();
+    }
+    /// [`Board::play`], or why `idx` is not a legal move.
+    pub fn try_play(&mut self, idx:usize)->Result<(),MoveError>{ Position::try_play(self,idx) }
+    /// Number of legal move sequences of exactly `depth` plies from here;
+    /// games that end sooner do not count. From the empty board, depth 9
+    /// counts the games that fill the board.
+    pub fn perft(&self, depth:usize)->u64{
+        if depth==0 { return 1; }
+        let moves=Position::moves(self);
+        if depth==1 { return moves.len() as u64; }
+        moves.into_iter().map(|m|{
+            let mut b=self.clone(); b.play(m);
+            b.perft(depth-1)
+        }).sum()
+    }
+}
 
+impl Position for Board{
+    fn turn(&self)->Cell{ Board::turn(self) }
+    fn winner(&self)->Option<Cell>{
+        self.winning_line().map(|[a,_,_]| self.0[a])
+    }
+    /// Centre first, then corners, then edges: the generator's order.
+    fn moves(&self)->Vec<usize>{
+        const ORDER:[usize;9]=[4,0,2,6,8,1,3,5,7];
+        if self.winner().is_some() { return Vec::new(); }
+        ORDER.iter().copied().filter(|&i| self.0[i]==Cell::E).collect()
     }
+    fn cell(&self, idx:usize)->Option<Cell>{ self.0.get(idx).copied() }
+    fn play(&mut self, idx:usize){ Board::play(self,idx) }
+    /// Table lookup.
+    fn solve(&self, variant:Variant)->(i8,Option<usize>){
+        (table_score(variant,self.id()), table_best(variant,self.id()))
+    }
+}
 
+impl From<Board> for String{
+    fn from(b:Board)->String{ b.to_compact() }
 }
 
+impl TryFrom<String> for Board{
+    type Error=ParseBoardError;
+    fn try_from(s:String)->Result<Self,Self::Error>{ Board::from_compact(&s) }
+}
 
+impl FromStr for Board{
+    type Err=ParseBoardError;
+    /// Same as [`Board::from_compact`].
+    fn from_str(s:&str)->Result<Self,Self::Err>{ Board::from_compact(s) }
+}
 
+/// The grid, rows separated by rules; `{:#}` gives the one-line compact
+/// form instead, which [`FromStr`] reads back.
 impl Display for Board{
-
     fn fmt(&self,f:&mut fmt::Formatter<'_>)->fmt::Result{
-
+        if f.alternate() { return f.write_str(&self.to_compact()); }
         for r in 0..3{
-
             if r>0 { writeln!(f,"\n—+—+—")?; }
-
             for c in 0..3{
-
                 if c>0 {write!(f,"|")?;}
-
                 write!(f,"{}",self.0[r*3+c])?;
-
             }
-
         }
-
         Ok(())
-
     }
-
 }
 
-
-
-/* tables generated by build.rs */
-
+/* tables generated by build.rs, or by const evaluation */
+#[cfg(not(any(feature="runtime-search",feature="const-tables")))]
 include!(concat!(env!("OUT_DIR"),"/tictac_tables.rs"));
+#[cfg(all(feature="const-tables",not(any(feature="unpacked-tables",feature="runtime-search"))))]
+use const_tables::{BEST_NIBBLES,MISERE_BEST_NIBBLES,MISERE_SCORE_BITS,SCORE_BITS};
+#[cfg(all(feature="const-tables",not(feature="runtime-search")))]
+pub use const_tables::TABLE_CHECKSUM;
+#[cfg(all(feature="const-tables",feature="unpacked-tables",not(feature="runtime-search")))]
+pub use const_tables::{BEST,MISERE_BEST,MISERE_SCORE,SCORE};
+
+/// Tabulated perfect-play score of the board with [`Board::id`] `id`;
+/// panics if `id >= BOARD_IDS`.
+#[cfg(not(any(feature="unpacked-tables",feature="runtime-search")))]
+#[inline]
+pub fn table_score(variant:Variant, id:usize)->i8{
+    let bits = match variant {
+        Variant::Normal=>&SCORE_BITS,
+        Variant::Misere=>&MISERE_SCORE_BITS,
+    };
+    match bits[id/4]>>(id%4*2)&3 { 1=>1, 2=>-1, _=>0 }
+}
 
+/// Tabulated perfect-play move for the board with [`Board::id`] `id`;
+/// panics if `id >= BOARD_IDS`.
+#[cfg(not(any(feature="unpacked-tables",feature="runtime-search")))]
+#[inline]
+pub fn table_best(variant:Variant, id:usize)->Option<usize>{
+    let nibbles = match variant {
+        Variant::Normal=>&BEST_NIBBLES,
+        Variant::Misere=>&MISERE_BEST_NIBBLES,
+    };
+    let m = nibbles[id/2]>>(id%2*4)&15;
+    if m==15 {None} else {Some(m as usize)}
+}
 
+/// Tabulated perfect-play score of the board with [`Board::id`] `id`;
+/// panics if `id >= BOARD_IDS`.
+#[cfg(all(feature="unpacked-tables",not(feature="runtime-search")))]
+#[inline]
+pub fn table_score(variant:Variant, id:usize)->i8{
+    match variant { Variant::Normal=>SCORE[id], Variant::Misere=>MISERE_SCORE[id] }
+}
 
-pub struct Game{ board:Board }
-
-impl Game{
-
-    pub fn new()->Self{ Self{board:Board::default()} }
+/// Tabulated perfect-play move for the board with [`Board::id`] `id`;
+/// panics if `id >= BOARD_IDS`.
+#[cfg(all(feature="unpacked-tables",not(feature="runtime-search")))]
+#[inline]
+pub fn table_best(variant:Variant, id:usize)->Option<usize>{
+    let m = match variant { Variant::Normal=>BEST[id], Variant::Misere=>MISERE_BEST[id] };
+    if m==255 {None} else {Some(m as usize)}
+}
 
-    pub fn board(&self)->&Board{ &self.board }
+/// Whether the tables can be trusted: every position reachable in a game
+/// either is over and has no move, or has a move to an empty cell that
+/// keeps its score. With build-time tables, their hash must also match
+/// [`TABLE_CHECKSUM`].
+pub fn verify_tables()->bool{
+    let variants=[Variant::Normal,Variant::Misere];
+    #[cfg(not(feature="runtime-search"))]
+    {
+        let fnv1a=|h:u64, byte:u8| (h^byte as u64).wrapping_mul(0x0000_0100_0000_01B3);
+        let mut h:u64=0xCBF2_9CE4_8422_2325;
+        for variant in variants{
+            for id in 0..BOARD_IDS as usize{
+                h=fnv1a(h,table_score(variant,id) as u8);
+                h=fnv1a(h,table_best(variant,id).map_or(255,|m| m as u8));
+            }
+        }
+        if h!=TABLE_CHECKSUM { return false; }
+    }
+    variants.into_iter().all(|variant| (0..BOARD_IDS).all(|id|{
+        let b=Board::from_id(id);
+        let xs=b.0.iter().filter(|&&c| c==Cell::X).count();
+        let os=b.0.iter().filter(|&&c| c==Cell::O).count();
+        if xs!=os && xs!=os+1 { return true; } // unreachable
+        let (score,best)=b.solve(variant);
+        match best {
+            None => b.moves().is_empty(),
+            Some(m) => b.0[m]==Cell::E && {
+                let mut nb=b.clone(); nb.play(m);
+                nb.solve(variant).0==score
+            },
+        }
+    }))
+}
 
-    pub fn best_move(&self)->Option<usize>{
+/// Perfect-play score of the board with [`Board::id`] `id`, searched on
+/// first use; panics if `id >= BOARD_IDS`.
+#[cfg(feature="runtime-search")]
+pub fn table_score(variant:Variant, id:usize)->i8{ runtime::lookup(variant,id).0 }
+
+/// Perfect-play move for the board with [`Board::id`] `id`, searched on
+/// first use; panics if `id >= BOARD_IDS`.
+#[cfg(feature="runtime-search")]
+pub fn table_best(variant:Variant, id:usize)->Option<usize>{ runtime::lookup(variant,id).1 }
+
+/// One mark played through [`Game::play`].
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+#[cfg_attr(feature="serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Move{
+    pub cell:usize,
+    /// Side that played it.
+    pub player:Cell,
+    /// 1 for the first move of the game, 2 for the reply, and so on.
+    pub ply:usize,
+}
 
-        let id=self.board.id();
+/// Something that happened in a [`Game`], collected by [`Game::events`].
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+#[cfg_attr(feature="serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Event{
+    /// A mark went on the board, by [`Game::play`] or [`Game::redo`].
+    MovePlayed(Move),
+    /// An [`Engine`] picked `cell`, worth `score` from X's side with
+    /// perfect play afterwards; the `MovePlayed` for it follows.
+    EngineDecision{ cell:usize, score:i8 },
+    /// The last move ended the game.
+    GameOver{ outcome:Outcome },
+}
 
-        let m = BEST[id] as usize;
+/// How a game stands, or would end with perfect play.
+#[derive(Clone,Copy,Debug,PartialEq,Eq,Hash)]
+#[cfg_attr(feature="serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Outcome { XWins, OWins, Draw, InProgress }
 
-        if m==255 {None} else {Some(m)}
+impl Outcome{
+    /// Result a perfect-play score (1, 0, -1 from X's side) stands for.
+    pub fn from_score(score:i8)->Self{
+        match score { 1=>Outcome::XWins, -1=>Outcome::OWins, _=>Outcome::Draw }
+    }
+}
 
+impl Display for Outcome{
+    fn fmt(&self,f:&mut fmt::Formatter<'_>)->fmt::Result{
+        f.write_str(match self{
+            Outcome::XWins=>"X wins",
+            Outcome::OWins=>"O wins",
+            Outcome::Draw=>"draw",
+            Outcome::InProgress=>"in progress",
+        })
     }
+}
 
-    pub fn score(&self)->i8{ SCORE[self.board.id()] }
+/// Why [`Hint::cell`] is the move to play, from [`Game::hint`].
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub struct Hint{
+    /// The engine's move.
+    pub cell:usize,
+    /// Result with perfect play from here, from X's side.
+    pub score:i8,
+    /// Principal variation: `cell`, then perfect replies to the end.
+    pub line:Vec<usize>,
+    /// Every legal move with the score it leads to, in engine move order.
+    pub candidates:Vec<(usize,i8)>,
+}
 
+/// A game on any [`Position`]; the classic 3×3 board by default.
+///
+/// Moves made with [`Game::play`] (or [`Game::play_best`]) are kept in a
+/// history that [`Game::undo`] and [`Game::redo`] walk. Edits through
+/// [`Game::board_mut`] are not recorded.
+///
+/// Front ends can follow along through [`Game::events`] instead of
+/// inspecting the board after every step.
+///
+/// With the `serde` feature a game serializes with its whole history, so
+/// a restored game can still undo and redo; pending events are dropped.
+#[cfg_attr(feature="serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Game<B=Board>{
+    board:B,
+    variant:Variant,
+    history:Vec<Move>,
+    /// Board before each move in `history`.
+    before:Vec<B>,
+    /// Undone moves, most recently undone last.
+    undone:Vec<Move>,
+    /// Not yet taken by [`Game::events`].
+    #[cfg_attr(feature="serde", serde(skip))]
+    events:Vec<Event>,
+}
+impl Default for Game { fn default()->Self{ Self::new() }}
+impl Game{
+    pub fn new()->Self{ Self::new_with(Variant::Normal) }
+    /// Empty 3×3 board played under `variant`.
+    pub fn new_with(variant:Variant)->Self{
+        Self::from_board(Board::default()).with_variant(variant)
+    }
+    /// Play a [`notation`] move list from the empty board, checking every
+    /// move; the game keeps the moves as its history.
+    pub fn replay(notation:&str)->Result<Self,notation::NotationError>{
+        let mut g=Self::new();
+        for (i,cell) in notation::decode(notation)?.into_iter().enumerate(){
+            g.try_play(cell).map_err(|error| notation::NotationError::Illegal{ply:i+1,error})?;
+        }
+        Ok(g)
+    }
+    /// The game's history in [`notation`].
+    pub fn notation(&self)->String{ notation::encode(&self.history) }
+}
+impl<B:Position> Game<B>{
+    /// Continue from `board` under normal rules.
+    pub fn from_board(board:B)->Self{
+        Self{board,variant:Variant::Normal,history:Vec::new(),before:Vec::new(),undone:Vec::new(),
+            events:Vec::new()}
+    }
+    /// Same game, scored under `variant`.
+    pub fn with_variant(self, variant:Variant)->Self{ Self{variant,..self} }
+    pub fn variant(&self)->Variant{ self.variant }
+    pub fn board(&self)->&B{ &self.board }
+    pub fn board_mut(&mut self)->&mut B{ &mut self.board }
+    pub fn best_move(&self)->Option<usize>{ self.board.solve(self.variant).1 }
+    /// Perfect-play value from here, from X's side; see also
+    /// [`Game::expected_outcome`].
+    pub fn score(&self)->i8{ self.board.solve(self.variant).0 }
+    /// Result on the board as it stands (the variant decides who a
+    /// completed line counts for).
+    pub fn outcome(&self)->Outcome{
+        match self.board.winner(){
+            Some(w) => Outcome::from_score(self.variant.line_value(w)),
+            None if self.board.moves().is_empty() => Outcome::Draw,
+            None => Outcome::InProgress,
+        }
+    }
+    /// How the game ends if both sides play perfectly from here.
+    pub fn expected_outcome(&self)->Outcome{ Outcome::from_score(self.score()) }
+    /// Every legal move with the perfect-play value it leads to, from X's
+    /// side, in engine move order; empty once the game is over.
+    pub fn evaluate_moves(&self)->Vec<(usize,i8)>{
+        self.board.moves().into_iter().map(|m|{
+            let mut b=self.board.clone(); b.play(m);
+            (m,b.solve(self.variant).0)
+        }).collect()
+    }
+    /// The best move with the reasoning behind it, `None` once the game
+    /// is over.
+    pub fn hint(&self)->Option<Hint>{
+        let (score,cell)=self.board.solve(self.variant);
+        let cell=cell?;
+        let mut line=Vec::new();
+        let mut b=self.board.clone();
+        while let Some(m)=b.solve(self.variant).1 {
+            line.push(m);
+            b.play(m);
+        }
+        Some(Hint{cell,score,line,candidates:self.evaluate_moves()})
+    }
     pub fn play_best(&mut self){
-
-        if let Some(m)=self.best_move(){ self.board.play(m); }
-
+        if let Some(m)=self.best_move(){ self.play(m); }
+    }
+    /// Mark `cell` for the side to move and record it, clearing anything
+    /// that could be redone; panics if the cell is occupied.
+    pub fn play(&mut self, cell:usize){
+        self.push(cell);
+        self.undone.clear();
+    }
+    /// [`Game::play`], or why `cell` is not a legal move (the game is then
+    /// unchanged).
+    pub fn try_play(&mut self, cell:usize)->Result<(),MoveError>{
+        self.board.clone().try_play(cell)?;
+        self.play(cell);
+        Ok(())
+    }
+    /// Moves played so far, oldest first.
+    pub fn history(&self)->&[Move]{ &self.history }
+    /// Take back the last move, restoring the board from before it.
+    pub fn undo(&mut self)->Option<Move>{
+        let m=self.history.pop()?;
+        self.board=self.before.pop().expect("one board per move");
+        self.undone.push(m);
+        Some(m)
+    }
+    /// Replay the most recently undone move.
+    pub fn redo(&mut self)->Option<Move>{
+        let m=self.undone.pop()?;
+        self.push(m.cell);
+        Some(m)
+    }
+    /// Events since the last call, oldest first. They queue up until
+    /// taken, so call this regularly or not at all.
+    pub fn events(&mut self)->impl Iterator<Item=Event>+'_{ self.events.drain(..) }
+    pub(crate) fn emit(&mut self, event:Event){ self.events.push(event); }
+    fn push(&mut self, cell:usize){
+        let player=self.board.turn();
+        self.before.push(self.board.clone());
+        self.board.play(cell);
+        let m=Move{cell,player,ply:self.history.len()+1};
+        self.history.push(m);
+        self.emit(Event::MovePlayed(m));
+        let outcome=self.outcome();
+        if outcome!=Outcome::InProgress { self.emit(Event::GameOver{outcome}); }
     }
-
 }
 
-
-
 /* -------------- unit tests ----------------------------------------- */
-
 #[cfg(test)]
-
 mod unit{
-
     use super::*;
-
     #[test]
-
     fn opening_move_is_center(){
-
         let g = Game::new();
-
         assert_eq!(g.best_move(),Some(4)); // index 4 = center
-
     }
-
     #[test]
-
+    fn board_ids_round_trip(){
+        for id in [0,1,4,9_841,BOARD_IDS-1]{
+            assert_eq!(Board::from_id(id).id(),id as usize);
+        }
+        assert!(Board::try_from_id(BOARD_IDS).is_none());
+        assert!(Board::try_from_id(u32::MAX).is_none());
+    }
+    #[test]
     fn perfect_game_draw(){
-
         let mut g=Game::new();
-
         while g.best_move().is_some(){ g.play_best(); }
-
         assert_eq!(g.score(),0); // draw
-
     }
-
 }
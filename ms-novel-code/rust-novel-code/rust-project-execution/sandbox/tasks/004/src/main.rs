@@ -1,45 +1,79 @@
 use std::io::{self,Write};
+use std::time::{SystemTime,UNIX_EPOCH};
+use task_ws::{Game,Cell,Difficulty,Engine,Event,Hint,Outcome};
+
+#[cfg(feature="tui")]
+mod tui;
+
+/// Next line of input, trimmed and lowercased; `None` at end of input.
+fn read_line()->Option<String>{
+    let mut inp=String::new();
+    match io::stdin().read_line(&mut inp) {
+        Ok(0)|Err(_) => None,
+        Ok(_) => Some(inp.trim().to_lowercase()),
+    }
+}
 
-use task_ws::{Game,Cell};
-
-
+fn explain(hint:&Hint){
+    println!("Play {}: with best play, {}.",hint.cell,Outcome::from_score(hint.score));
+    let line:Vec<String>=hint.line.iter().map(|m| m.to_string()).collect();
+    println!("Expected line: {}",line.join(" → "));
+    for &(m,s) in &hint.candidates {
+        println!("  {} leads to {}",m,Outcome::from_score(s));
+    }
+}
 
 fn main(){
-
+    print!("Difficulty (easy/medium/perfect) [perfect]: "); io::stdout().flush().unwrap();
+    let difficulty = match read_line().unwrap_or_default().as_str(){
+        "easy"|"e" => Difficulty::Easy,
+        "medium"|"m" => Difficulty::Medium,
+        _ => Difficulty::Perfect,
+    };
+    let seed=SystemTime::now().duration_since(UNIX_EPOCH).map_or(0,|d| d.as_nanos() as u64);
+    let mut engine=Engine::with_difficulty(difficulty).with_seed(seed);
     let mut game = Game::new();
-
+    #[cfg(feature="tui")]
+    {
+        use std::io::IsTerminal;
+        if io::stdin().is_terminal() && io::stdout().is_terminal() {
+            tui::run(&mut game,&mut engine).expect("terminal UI failed");
+            return;
+        }
+    }
     loop{
-
         println!("\n{}\n",game.board());
-
-        if game.best_move().is_none(){
-
-            println!("Game over!  Score {}",game.score());
-
-            break;
-
+        let mut over=false;
+        for event in game.events(){
+            match event{
+                Event::EngineDecision{cell,score} => {
+                    let expected=Outcome::from_score(score);
+                    println!("Engine played {}; with best play, {}.",cell,expected);
+                }
+                Event::GameOver{outcome} => { println!("Game over!  Result: {}",outcome); over=true; }
+                Event::MovePlayed(_) => {}
+            }
         }
-
+        if over { break; }
         // engine plays as X
-
-        if game.board().turn()==Cell::X { game.play_best(); continue; }
-
-
-
-        print!("Your move (0‑8): "); io::stdout().flush().unwrap();
-
-        let mut inp=String::new(); io::stdin().read_line(&mut inp).unwrap();
-
-        if let Ok(idx)=inp.trim().parse::<usize>() {
-
-            if idx<9 && game.board().best_move()!=Some(idx){
-
-                game.board_mut().play(idx);
-
+        if game.board().turn()==Cell::X { engine.play(&mut game); continue; }
+
+        print!("Your move (0‑8, u = undo, h = hint, q = quit): "); io::stdout().flush().unwrap();
+        let Some(inp)=read_line() else { break };
+        match inp.as_str() {
+            "q" => break,
+            "h" => if let Some(hint)=game.hint() { explain(&hint); },
+            "u" => {
+                // take back the engine's reply and your move before it
+                if game.history().len()>=2 { game.undo(); game.undo(); }
+                else { println!("Nothing to undo."); }
             }
-
+            _ => match inp.parse::<usize>() {
+                Ok(idx) => if let Err(e)=game.try_play(idx) {
+                    println!("Can't play there: {}.",e);
+                },
+                Err(_) => println!("Enter a square number from 0 to 8."),
+            },
         }
-
     }
-
 }
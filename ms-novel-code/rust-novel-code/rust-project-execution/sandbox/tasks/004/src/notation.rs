@@ -0,0 +1,41 @@
+//! Move-list notation for 3×3 games: one digit per move, the cell played
+//! (0‑8, reading order), oldest first. `"40812"` is X centre, O top-left,
+//! X bottom-right, and so on. Whitespace is ignored when reading.
+
+use crate::{Move,MoveError};
+use std::fmt::{self,Display};
+
+/// Why a notation string could not be replayed.
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum NotationError {
+    /// Something other than a cell digit, at this character index.
+    BadChar{ index:usize, ch:char },
+    /// The move with this ply number (1 = first) is not legal.
+    Illegal{ ply:usize, error:MoveError },
+}
+
+impl Display for NotationError{
+    fn fmt(&self,f:&mut fmt::Formatter<'_>)->fmt::Result{
+        match self{
+            NotationError::BadChar{index,ch}=>
+                write!(f,"character {}: {:?} is not a cell (0-8)",index,ch),
+            NotationError::Illegal{ply,error}=>write!(f,"move {}: {}",ply,error),
+        }
+    }
+}
+
+impl std::error::Error for NotationError {}
+
+/// Notation for `moves`.
+pub fn encode(moves:&[Move])->String{
+    moves.iter().map(|m| char::from(b'0'+m.cell as u8)).collect()
+}
+
+/// Cells of a notation string, in order; legality is not checked.
+pub fn decode(s:&str)->Result<Vec<usize>,NotationError>{
+    s.chars().enumerate().filter(|(_,ch)| !ch.is_whitespace()).map(|(index,ch)|
+        match ch.to_digit(10) {
+            Some(d) if d<9 => Ok(d as usize),
+            _ => Err(NotationError::BadChar{index,ch}),
+        }).collect()
+}
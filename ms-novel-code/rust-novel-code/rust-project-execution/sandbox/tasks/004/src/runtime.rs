@@ -0,0 +1,84 @@
+//! On-demand replacement for the build.rs tables (feature `runtime-search`).
+//!
+//! Positions are solved when first asked about, by negamax with alpha-beta
+//! pruning. Results go into a transposition table per variant, keyed by the
+//! id of the position's canonical form under the 8 board symmetries, and
+//! stay there for the life of the process. Best moves are chosen the way
+//! the generator chooses them (first in move order on the canonical board),
+//! so answers match the tables on every position reachable in a game.
+
+use crate::{Board,Cell,Position,Variant,BOARD_IDS};
+use std::sync::Mutex;
+
+/// How a stored value relates to the true one.
+#[derive(Clone,Copy)]
+enum Bound { Exact, Lower, Upper }
+
+/// Values for the side to move, by canonical board id.
+type Table=Vec<Option<(i8,Bound)>>;
+
+static TABLES:[Mutex<Table>;2]=[Mutex::new(Vec::new()),Mutex::new(Vec::new())];
+
+/// Cell `i` of a board lands on cell `SYMS[t][i]` of its `t`-th image.
+const SYMS:[[usize;9];8]=[
+    [0,1,2,3,4,5,6,7,8],[2,5,8,1,4,7,0,3,6],[8,7,6,5,4,3,2,1,0],[6,3,0,7,4,1,8,5,2],
+    [2,1,0,5,4,3,8,7,6],[8,5,2,7,4,1,6,3,0],[6,7,8,3,4,5,0,1,2],[0,3,6,1,4,7,2,5,8]];
+
+/// Smallest-id image of `b` and the symmetry that produces it.
+fn canonical(b:&Board)->(Board,usize){
+    (0..8).map(|t|{
+        let mut img=[Cell::E;9];
+        for (&to,&c) in SYMS[t].iter().zip(b.cells()) { img[to]=c; }
+        (Board(img),t)
+    }).min_by_key(|(img,_)| img.id()).unwrap()
+}
+
+/// Score (from X's side) and best move of the board with id `id`, as the
+/// generated tables would give them.
+pub(crate) fn lookup(variant:Variant, id:usize)->(i8,Option<usize>){
+    let (b,t)=canonical(&Board::from_id(id as u32));
+    let mut tt=TABLES[variant as usize].lock().unwrap_or_else(|e| e.into_inner());
+    if tt.is_empty() { tt.resize(BOARD_IDS as usize,None); }
+    let sign = if b.turn()==Cell::X {1} else {-1};
+    let value=negamax(&b,variant,-1,1,&mut tt);
+    let best=b.moves().into_iter().find(|&m|{
+        let mut nb=b.clone(); nb.play(m);
+        -negamax(&nb,variant,-1,1,&mut tt)==value
+    });
+    // back from canonical coordinates
+    let best=best.map(|m| SYMS[t].iter().position(|&j| j==m).unwrap());
+    (sign*value,best)
+}
+
+/// Value of `b` for the side to move, exact when it lies strictly inside
+/// `(alpha, beta)`; otherwise a bound on the far side of the window.
+/// Values are -1..=1, so a `(-1, 1)` window is always exact.
+fn negamax(b:&Board, variant:Variant, mut alpha:i8, beta:i8, tt:&mut Table)->i8{
+    let id=canonical(b).0.id();
+    match tt[id] {
+        Some((v,Bound::Exact)) => return v,
+        Some((v,Bound::Lower)) if v>=beta => return v,
+        Some((v,Bound::Upper)) if v<=alpha => return v,
+        _ => {}
+    }
+    let alpha0=alpha;
+    let sign = if b.turn()==Cell::X {1} else {-1};
+    let value = match b.winner() {
+        Some(w) => sign*variant.line_value(w),
+        None => {
+            let mut best=0; // a full board is a draw
+            for (i,m) in b.moves().into_iter().enumerate(){
+                let mut nb=b.clone(); nb.play(m);
+                let s=-negamax(&nb,variant,-beta,-alpha,tt);
+                if i==0 || s>best { best=s; }
+                alpha=alpha.max(s);
+                if alpha>=beta {break;}
+            }
+            best
+        }
+    };
+    let bound =
+        if value<=alpha0 {Bound::Upper} else if value>=beta {Bound::Lower} else {Bound::Exact};
+    tt[id]=Some((value,bound));
+    value
+}
@@ -0,0 +1,42 @@
+//! Exhaustive minimax for boards without precomputed tables.
+//!
+//! Values are memoised per position, so transpositions are solved once;
+//! a side stops looking at moves once it has found a win. Still
+//! exponential: fine up to 4×4, hopeless much beyond.
+
+use crate::{Cell,Position,Variant};
+use std::collections::HashMap;
+
+/// Perfect-play value of `board` under `variant` (from X's side) and the
+/// first move, in `moves()` order, that achieves it.
+pub(crate) fn solve<B:Position>(board:&B, variant:Variant)->(i8,Option<usize>){
+    let mut memo=HashMap::new();
+    best(board,variant,&mut memo)
+}
+
+fn best<B:Position>(b:&B, variant:Variant, memo:&mut HashMap<B,i8>)->(i8,Option<usize>){
+    if let Some(w)=b.winner(){
+        return (variant.line_value(w), None);
+    }
+    // Scores are from X's side: X maximises, O minimises
+    let sign = if b.turn()==Cell::X {1} else {-1};
+    let mut best_score=-2; // worse than loss, for the side to move
+    let mut best_move=None;
+    for m in b.moves(){
+        let mut nb=b.clone(); nb.play(m);
+        let s = sign*value(&nb,variant,memo);
+        if s>best_score { best_score=s; best_move=Some(m); }
+        if best_score==1 {break;}
+    }
+    match best_move {
+        Some(_) => (sign*best_score, best_move),
+        None => (0, None), // full board, nobody won
+    }
+}
+
+fn value<B:Position>(b:&B, variant:Variant, memo:&mut HashMap<B,i8>)->i8{
+    if let Some(&s)=memo.get(b){ return s; }
+    let s=best(b,variant,memo).0;
+    memo.insert(b.clone(),s);
+    s
+}
@@ -0,0 +1,76 @@
+//! Engine-versus-engine matches.
+//!
+//! [`simulate`] plays a series of games between two engines, alternating
+//! who has X, and counts results from the first engine's side;
+//! [`tournament`] runs it for every pair of a field of engines. Each game
+//! reseeds both engines from the match seed, so a whole match replays
+//! exactly.
+
+use crate::engine::SplitMix64;
+use crate::{Cell,Engine,Game,Position};
+
+/// Results of a match, from one engine's side.
+#[derive(Clone,Copy,Debug,Default,PartialEq,Eq)]
+pub struct MatchStats{
+    pub wins:usize,
+    pub draws:usize,
+    pub losses:usize,
+    /// Plies over all games.
+    pub moves:usize,
+}
+
+impl MatchStats{
+    pub fn games(&self)->usize{ self.wins+self.draws+self.losses }
+    /// Plies per game; 0 for an empty record.
+    pub fn average_length(&self)->f64{
+        if self.games()==0 {0.0} else {self.moves as f64/self.games() as f64}
+    }
+    /// The same games from the opponent's side.
+    pub fn reversed(&self)->Self{ Self{wins:self.losses,losses:self.wins,..*self} }
+    fn add(&mut self, other:&MatchStats){
+        self.wins+=other.wins;
+        self.draws+=other.draws;
+        self.losses+=other.losses;
+        self.moves+=other.moves;
+    }
+}
+
+/// Play `games` games of classic tic-tac-toe between `engine_a` (X in the
+/// even-numbered games, O in the odd ones) and `engine_b`; results are
+/// `engine_a`'s.
+pub fn simulate(engine_a:&Engine, engine_b:&Engine, games:usize, seed:u64)->MatchStats{
+    let mut seeds=SplitMix64(seed);
+    let mut stats=MatchStats::default();
+    for n in 0..games{
+        let mut a=engine_a.clone().with_seed(seeds.next_u64());
+        let mut b=engine_b.clone().with_seed(seeds.next_u64());
+        let a_side = if n%2==0 {Cell::X} else {Cell::O};
+        let mut game=Game::new();
+        loop{
+            let mover = if game.board().turn()==a_side {&mut a} else {&mut b};
+            if mover.play(&mut game).is_none() { break; }
+        }
+        stats.moves+=game.history().len();
+        match game.board().winner(){
+            Some(w) if w==a_side => stats.wins+=1,
+            Some(_) => stats.losses+=1,
+            None => stats.draws+=1,
+        }
+    }
+    stats
+}
+
+/// Round robin: every pair of `engines` plays a `games`-game match.
+/// Returns each engine's combined record, in the order given.
+pub fn tournament(engines:&[Engine], games:usize, seed:u64)->Vec<MatchStats>{
+    let mut seeds=SplitMix64(seed);
+    let mut table=vec![MatchStats::default();engines.len()];
+    for i in 0..engines.len(){
+        for j in i+1..engines.len(){
+            let stats=simulate(&engines[i],&engines[j],games,seeds.next_u64());
+            table[i].add(&stats);
+            table[j].add(&stats.reversed());
+        }
+    }
+    table
+}
@@ -0,0 +1,121 @@
+//! Full-screen front end (feature `tui`). The engine plays X; arrow keys
+//! move the cursor, Enter or Space plays, `u` takes back a move pair and
+//! `q` quits. Once the game is over ←/→ step through a replay.
+
+use ratatui::crossterm::event::{self,Event,KeyCode,KeyEventKind};
+use ratatui::layout::{Constraint,Layout};
+use ratatui::style::{Color,Modifier,Style};
+use ratatui::text::{Line,Span};
+use ratatui::widgets::{Block,Paragraph};
+use ratatui::{DefaultTerminal,Frame};
+use std::io;
+use task_ws::{Board,Cell,Engine,Event as GameEvent,Game,Outcome,Position};
+
+struct App{
+    cursor:usize,
+    /// Plies shown in the post-game replay, once the game is over.
+    replay:Option<usize>,
+    message:String,
+}
+
+/// Play `game` against `engine` until the player quits.
+pub fn run(game:&mut Game, engine:&mut Engine)->io::Result<()>{
+    let mut terminal=ratatui::init();
+    let result=event_loop(&mut terminal,game,engine);
+    ratatui::restore();
+    result
+}
+
+fn event_loop(terminal:&mut DefaultTerminal, game:&mut Game, engine:&mut Engine)->io::Result<()>{
+    let mut app=App{cursor:4,replay:None,message:String::new()};
+    loop{
+        let over=game.board().moves().is_empty();
+        if !over && game.board().turn()==Cell::X { engine.play(game); }
+        let plies=game.history().len();
+        for event in game.events(){
+            match event{
+                GameEvent::EngineDecision{cell,..} => app.message=format!("Engine played {}",cell),
+                GameEvent::GameOver{..} => app.replay=Some(plies),
+                GameEvent::MovePlayed(_) => {}
+            }
+        }
+        let over=game.board().moves().is_empty();
+        terminal.draw(|f| draw(f,game,&app))?;
+
+        let Event::Key(key)=event::read()? else { continue };
+        if key.kind!=KeyEventKind::Press { continue; }
+        let (r,c)=(app.cursor/3,app.cursor%3);
+        match key.code{
+            KeyCode::Char('q')|KeyCode::Esc => return Ok(()),
+            KeyCode::Up if r>0 => app.cursor-=3,
+            KeyCode::Down if r<2 => app.cursor+=3,
+            KeyCode::Left if over => app.replay=app.replay.map(|p| p.saturating_sub(1)),
+            KeyCode::Right if over => {
+                app.replay=app.replay.map(|p| (p+1).min(game.history().len()));
+            }
+            KeyCode::Left if c>0 => app.cursor-=1,
+            KeyCode::Right if c<2 => app.cursor+=1,
+            KeyCode::Char('u') if game.history().len()>=2 => {
+                game.undo(); game.undo();
+                app.replay=None;
+                app.message.clear();
+            }
+            KeyCode::Enter|KeyCode::Char(' ') if !over => {
+                if game.board().cells()[app.cursor]==Cell::E {
+                    game.play(app.cursor);
+                    app.message.clear();
+                } else {
+                    app.message=format!("Square {} is taken",app.cursor);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn draw(f:&mut Frame, game:&Game, app:&App){
+    let over=game.board().moves().is_empty();
+    // the live board, or the replay position once the game is over
+    let mut board=Board::default();
+    let shown=app.replay.unwrap_or(game.history().len());
+    for m in &game.history()[..shown] { board.play(m.cell); }
+    let line=board.winning_line();
+
+    let mut rows=Vec::new();
+    for r in 0..3{
+        if r>0 { rows.push(Line::from("───┼───┼───")); }
+        let mut spans=Vec::new();
+        for c in 0..3{
+            let i=r*3+c;
+            if c>0 { spans.push(Span::raw("│")); }
+            let mut style=Style::default();
+            if board.cells()[i]==Cell::X { style=style.fg(Color::Red); }
+            if board.cells()[i]==Cell::O { style=style.fg(Color::Blue); }
+            if line.is_some_and(|l| l.contains(&i)) {
+                style=style.bg(Color::Green).add_modifier(Modifier::BOLD);
+            }
+            if !over && i==app.cursor { style=style.add_modifier(Modifier::REVERSED); }
+            spans.push(Span::styled(format!(" {} ",board.cells()[i]),style));
+        }
+        rows.push(Line::from(spans));
+    }
+
+    let status = if over {
+        let result = match game.outcome() {
+            Outcome::XWins => "X (engine) wins",
+            Outcome::OWins => "O (you) win",
+            _ => "Draw",
+        };
+        let ply=app.replay.unwrap_or(0);
+        format!("{}. Replay: move {}/{} (←/→)",result,ply,game.history().len())
+    } else {
+        format!("Your move. Perfect play from here: {}",game.expected_outcome())
+    };
+    let help = if over {"q quit"} else {"arrows move · enter play · u undo · q quit"};
+
+    let [top,bottom]=Layout::vertical([Constraint::Length(7),Constraint::Length(4)])
+        .areas(f.area());
+    f.render_widget(Paragraph::new(rows).block(Block::bordered().title(" Tic-tac-toe ")),top);
+    let info=vec![Line::from(status),Line::from(app.message.as_str()),Line::from(help)];
+    f.render_widget(Paragraph::new(info),bottom);
+}
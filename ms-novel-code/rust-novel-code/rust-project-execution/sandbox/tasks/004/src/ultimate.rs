@@ -0,0 +1,222 @@
+//! Ultimate tic-tac-toe: nine small boards arranged as a big one.
+//!
+//! Moves are numbered `board*9 + cell`, both 0‑8 in reading order. The cell
+//! a player marks sends the opponent to the small board in that position;
+//! if that board is already won or full, the opponent may play anywhere.
+//! Winning three small boards in a row wins the game.
+//!
+//! The game tree is far too large for tables, so [`UltimateGame`] runs an
+//! alpha-beta search with iterative deepening until its time budget runs
+//! out, scoring the frontier with a heuristic.
+
+use crate::{Cell,LINES};
+use std::fmt::{self,Display};
+use std::time::{Duration,Instant};
+
+/// Position of an ultimate tic-tac-toe game.
+#[derive(Clone,Debug,PartialEq,Eq,Hash)]
+pub struct UltimateBoard{
+    cells:[Cell;81],
+    /// Owner of each small board, `Cell::E` while nobody has a line in it.
+    won:[Cell;9],
+    /// Small board the side to move must play in, `None` for a free move.
+    next:Option<usize>,
+}
+
+impl Default for UltimateBoard{
+    fn default()->Self{ Self{cells:[Cell::E;81],won:[Cell::E;9],next:None} }
+}
+
+fn line_owner(cells:&[Cell])->Option<Cell>{
+    LINES.iter().find_map(|&[a,b,c]|{
+        let ca=cells[a];
+        (ca!=Cell::E && ca==cells[b] && ca==cells[c]).then_some(ca)
+    })
+}
+
+impl UltimateBoard{
+    /// Position with the given marks; `next` is the small board the side to
+    /// move is sent to (ignored if that board is closed).
+    pub fn from_cells(cells:[Cell;81], next:Option<usize>)->Self{
+        let mut b=Self{cells,won:[Cell::E;9],next:None};
+        for sb in 0..9 {
+            b.won[sb]=line_owner(&b.cells[sb*9..sb*9+9]).unwrap_or(Cell::E);
+        }
+        b.next=next.filter(|&sb| !b.is_closed(sb));
+        b
+    }
+    pub fn cells(&self)->&[Cell;81]{ &self.cells }
+    /// Owner of small board `sb`, if someone has a line in it.
+    pub fn board_winner(&self, sb:usize)->Option<Cell>{
+        Some(self.won[sb]).filter(|&c| c!=Cell::E)
+    }
+    /// Small board the side to move must play in, `None` for a free move.
+    pub fn forced_board(&self)->Option<usize>{ self.next }
+    /// Whether small board `sb` takes no more moves: won or full.
+    pub fn is_closed(&self, sb:usize)->bool{
+        self.won[sb]!=Cell::E || self.cells[sb*9..sb*9+9].iter().all(|&c| c!=Cell::E)
+    }
+    pub fn turn(&self)->Cell{
+        let xs=self.cells.iter().filter(|&&c|c==Cell::X).count();
+        let os=self.cells.iter().filter(|&&c|c==Cell::O).count();
+        if xs==os {Cell::X} else {Cell::O}
+    }
+    /// Owner of three small boards in a row, if any.
+    pub fn winner(&self)->Option<Cell>{ line_owner(&self.won) }
+    /// Legal moves; empty once the game is won or every small board is
+    /// closed (a draw).
+    pub fn moves(&self)->Vec<usize>{
+        if self.winner().is_some() { return Vec::new(); }
+        let boards = match self.next { Some(sb)=>sb..sb+1, None=>0..9 };
+        boards.filter(|&sb| !self.is_closed(sb))
+            .flat_map(|sb| (sb*9..sb*9+9).filter(|&i| self.cells[i]==Cell::E))
+            .collect()
+    }
+    pub fn is_legal(&self, idx:usize)->bool{
+        idx<81 && self.cells[idx]==Cell::E && self.winner().is_none()
+            && !self.is_closed(idx/9) && self.next.is_none_or(|sb| sb==idx/9)
+    }
+    /// Mark `idx` for the side to move; panics if the move is illegal.
+    pub fn play(&mut self, idx:usize){
+        assert!(self.is_legal(idx),"illegal move {}",idx);
+        let sb=idx/9;
+        self.cells[idx]=self.turn();
+        if self.won[sb]==Cell::E {
+            self.won[sb]=line_owner(&self.cells[sb*9..sb*9+9]).unwrap_or(Cell::E);
+        }
+        let target=idx%9;
+        self.next=if self.is_closed(target) {None} else {Some(target)};
+    }
+
+    /// Heuristic value from X's side: small boards won, weighted by
+    /// position, plus open two-in-a-rows on the big board and inside the
+    /// small boards still in play.
+    fn evaluate(&self)->i32{
+        const WEIGHT:[i32;9]=[3,2,3,2,4,2,3,2,3];
+        let side=|c:Cell| match c {Cell::X=>1,Cell::O=>-1,Cell::E=>0};
+        let twos=|cells:&[Cell], open:&dyn Fn(usize)->bool| -> i32 {
+            LINES.iter().map(|line|{
+                let xs=line.iter().filter(|&&i| cells[i]==Cell::X).count();
+                let os=line.iter().filter(|&&i| cells[i]==Cell::O).count();
+                let free=line.iter().any(|&i| cells[i]==Cell::E && open(i));
+                match (xs,os,free) { (2,0,true)=>1, (0,2,true)=>-1, _=>0 }
+            }).sum()
+        };
+        let mut s=0;
+        for (sb,&weight) in WEIGHT.iter().enumerate() {
+            if self.won[sb]!=Cell::E { s+=100*weight*side(self.won[sb]); }
+            else if !self.is_closed(sb) {
+                s+=10*twos(&self.cells[sb*9..sb*9+9],&|_| true);
+            }
+        }
+        s+200*twos(&self.won,&|sb| !self.is_closed(sb))
+    }
+}
+
+impl Display for UltimateBoard{
+    /// The big board as a 9×9 grid, small boards separated by rules.
+    fn fmt(&self,f:&mut fmt::Formatter<'_>)->fmt::Result{
+        for r in 0..9{
+            if r>0 && r%3==0 { writeln!(f,"———+———+———")?; }
+            for c in 0..9{
+                if c>0 && c%3==0 {write!(f,"|")?;}
+                write!(f,"{}",self.cells[(r/3*3+c/3)*9+(r%3)*3+c%3])?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Search score of a won position; wins found sooner score higher.
+const WIN:i32=1_000_000;
+
+/// Best move and value (for the side to move) of the deepest search that
+/// finished before `deadline`, going no deeper than `max_depth` plies.
+fn search(board:&UltimateBoard, deadline:Instant, max_depth:u32)->(i32,Option<usize>){
+    let mut moves=board.moves();
+    if moves.is_empty() { return (0,None); }
+    let mut result=(0,Some(moves[0]));
+    let empty=board.cells.iter().filter(|&&c| c==Cell::E).count() as u32;
+    for depth in 1..=max_depth.min(empty).max(1) {
+        let mut s=Search{deadline,nodes:0,timed_out:false};
+        let (mut alpha,mut best)=(-WIN-1,moves[0]);
+        for &m in &moves {
+            let mut nb=board.clone(); nb.play(m);
+            let v=-s.negamax(&nb,depth-1,1,-WIN-1,-alpha);
+            if s.timed_out && depth>1 { break; }
+            if v>alpha { alpha=v; best=m; }
+        }
+        if s.timed_out && depth>1 { break; }
+        result=(alpha,Some(best));
+        // search the best move first next time
+        moves.retain(|&m| m!=best);
+        moves.insert(0,best);
+        if alpha.abs()>=WIN-81 { break; } // decided
+    }
+    result
+}
+
+struct Search{ deadline:Instant, nodes:u64, timed_out:bool }
+
+impl Search{
+    fn negamax(&mut self, b:&UltimateBoard, depth:u32, ply:i32, mut alpha:i32, beta:i32)->i32{
+        self.nodes+=1;
+        if self.nodes.is_multiple_of(1024) && Instant::now()>=self.deadline { self.timed_out=true; }
+        if self.timed_out { return 0; }
+        // the previous mover completed the big line
+        if b.winner().is_some() { return -(WIN-ply); }
+        let moves=b.moves();
+        if moves.is_empty() { return 0; }
+        if depth==0 {
+            return if b.turn()==Cell::X {b.evaluate()} else {-b.evaluate()};
+        }
+        for m in moves {
+            let mut nb=b.clone(); nb.play(m);
+            let v=-self.negamax(&nb,depth-1,ply+1,-beta,-alpha);
+            if v>alpha { alpha=v; }
+            if alpha>=beta { break; }
+        }
+        alpha
+    }
+}
+
+/// An ultimate tic-tac-toe game with a time-limited engine.
+pub struct UltimateGame{ board:UltimateBoard, budget:Duration, max_depth:u32 }
+
+impl Default for UltimateGame{ fn default()->Self{ Self::new() }}
+
+impl UltimateGame{
+    /// Empty board; the engine thinks for up to 100 ms per call.
+    pub fn new()->Self{
+        Self{board:UltimateBoard::default(),budget:Duration::from_millis(100),max_depth:81}
+    }
+    /// Continue from `board`.
+    pub fn from_board(board:UltimateBoard)->Self{ Self{board,..Self::new()} }
+    /// Wall-clock time each `best_move`/`score` call may search for.
+    pub fn with_budget(self, budget:Duration)->Self{ Self{budget,..self} }
+    /// Deepest search in plies; with a generous budget, fixes the answer
+    /// regardless of machine speed.
+    pub fn with_max_depth(self, max_depth:u32)->Self{ Self{max_depth,..self} }
+    pub fn board(&self)->&UltimateBoard{ &self.board }
+    /// Mark `idx` for the side to move; panics if the move is illegal.
+    pub fn play(&mut self, idx:usize){ self.board.play(idx) }
+    /// Engine's move for the side to move, `None` once the game is over.
+    pub fn best_move(&self)->Option<usize>{ self.think().1 }
+    /// 1 if the search proved X wins, -1 if it proved O wins, 0 for a draw
+    /// or a position still undecided within the budget.
+    pub fn score(&self)->i8{
+        let (v,_)=self.think();
+        let v = if self.board.turn()==Cell::X {v} else {-v};
+        if v>=WIN-81 {1} else if v<=-(WIN-81) {-1} else {0}
+    }
+    pub fn play_best(&mut self){
+        if let Some(m)=self.best_move(){ self.board.play(m); }
+    }
+    fn think(&self)->(i32,Option<usize>){
+        if let Some(w)=self.board.winner(){
+            return (if w==self.board.turn() {WIN} else {-WIN}, None);
+        }
+        search(&self.board,Instant::now()+self.budget,self.max_depth)
+    }
+}
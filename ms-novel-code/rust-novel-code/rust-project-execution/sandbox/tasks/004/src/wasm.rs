@@ -0,0 +1,57 @@
+//! Browser bindings (feature `wasm`), for use through wasm-bindgen:
+//!
+//! ```js
+//! const g = new_game("medium", 42n);
+//! g.play(4);
+//! g.play_engine();
+//! render(JSON.parse(g.board_json()));
+//! ```
+//!
+//! Everything here is plain computation: the tables are static data and
+//! [`Engine`] brings its own seeded generator, so nothing needs the
+//! JavaScript clock or `crypto`. (The [`crate::ultimate`] engine does read
+//! the clock and is not exported.)
+
+use crate::{Cell,Difficulty,Engine,Game,Position};
+use wasm_bindgen::prelude::*;
+
+/// A 3×3 game with an engine opponent.
+#[wasm_bindgen]
+pub struct WasmGame{ game:Game, engine:Engine }
+
+/// New game against an engine at `difficulty` (`"easy"`, `"medium"`,
+/// anything else for perfect), randomised by `seed`.
+#[wasm_bindgen]
+pub fn new_game(difficulty:&str, seed:u64)->WasmGame{
+    let difficulty = match difficulty {
+        "easy" => Difficulty::Easy,
+        "medium" => Difficulty::Medium,
+        _ => Difficulty::Perfect,
+    };
+    WasmGame{ game:Game::new(), engine:Engine::with_difficulty(difficulty).with_seed(seed) }
+}
+
+#[wasm_bindgen]
+impl WasmGame{
+    /// Mark `cell` for the side to move; throws if it is not a legal move.
+    pub fn play(&mut self, cell:usize)->Result<(),String>{
+        self.game.try_play(cell).map_err(|e| e.to_string())
+    }
+    /// Let the engine move for the side to move; returns its cell, or
+    /// `undefined` if the game is over.
+    pub fn play_engine(&mut self)->Option<usize>{ self.engine.play(&mut self.game) }
+    /// Perfect-play move for the side to move, `undefined` once over.
+    pub fn best_move(&self)->Option<usize>{ self.game.best_move() }
+    /// Take back the last move; returns whether there was one.
+    pub fn undo(&mut self)->bool{ self.game.undo().is_some() }
+    /// `{"cells":["X"," ",…],"turn":"O","winner":null,"over":false}`,
+    /// cells in reading order.
+    pub fn board_json(&self)->String{
+        let b=self.game.board();
+        let quote=|c:Cell| format!("\"{}\"",c);
+        let cells:Vec<String>=b.cells().iter().map(|&c| quote(c)).collect();
+        format!("{{\"cells\":[{}],\"turn\":{},\"winner\":{},\"over\":{}}}",
+            cells.join(","),quote(b.turn()),b.winner().map_or("null".into(),quote),
+            b.moves().is_empty())
+    }
+}
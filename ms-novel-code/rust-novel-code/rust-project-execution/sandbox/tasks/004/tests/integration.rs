@@ -1,269 +1,816 @@
 //! Integration‑level tests for the compile‑time perfect‑play engine.
-
 //!
-
 //! All tests are fully deterministic; the only randomness lives in
-
 //! `random_play_never_beats_engine` (fixed‐seed).
 
-
-
-use task_ws::{Board, Cell, Game};
-
-
+use task_ws::{
+    Board, Bot, Cell, Difficulty, Engine, Event, Game, GridBoard, Move, MoveError, Outcome, Player,
+    Position, Variant,
+};
 
 /// Helper: create board from “ascii art” (rows separated by `/`).
-
 /// `'X'`, `'O'`, or `' '`   e.g. "X O/ XO/   X"
-
 fn parse_board(pat: &str) -> Board {
-
-    let mut b = [Cell::E; 9];
-
-    for (i, ch) in pat.chars().filter(|&c| c != '/').enumerate() {
-
-        b[i] = match ch {
-
-            'X' | 'x' => Cell::X,
-
-            'O' | 'o' => Cell::O,
-
-            _          => Cell::E,
-
-        };
-
-    }
-
-    Board(b)
-
+    pat.parse().unwrap()
 }
 
-
-
 /* ───────────────────────── 1. Opening move  ───────────────────────── */
-
 #[test]
-
 fn opening_move_is_center() {
-
     let g = Game::new();
-
     assert_eq!(g.best_move(), Some(4)); // 0‑based center
-
 }
 
-
-
 /* ──────────────────────── 2. Symmetry test  ──────────────────────── */
-
 #[test]
-
 fn symmetry_corner_openings() {
-
     // Place X in each corner and ensure best reply is still center if free
-
     for &corner in &[0, 2, 6, 8] {
-
         let mut g = Game::new();
-
         g.board_mut().play(corner); // X
-
-        g.board_mut().play(4);      // O random centre
-
-        assert_eq!(g.score(), -1);  // centre for O should lose vs perfect X
-
+        g.board_mut().play(4);      // O takes the centre
+        assert_eq!(g.score(), 0);   // corner against centre is a draw
     }
-
 }
 
-
-
 /* ─────────────────────── 3. Idempotent scores ────────────────────── */
-
 #[test]
-
 fn score_is_idempotent() {
-
     let g = Game::new();
-
     let s1 = g.score();
-
     let s2 = g.score();
-
     assert_eq!(s1, s2);
-
 }
 
-
-
 /* ───────────── 4. Engine never chooses illegal square ────────────── */
-
 #[test]
-
 fn engine_chooses_empty_square_only() {
-
     let mut g = Game::new();
-
     g.board_mut().play(0); // human O
-
     let mv = g.best_move().unwrap();
-
     assert_eq!(g.board().cells()[mv], Cell::E);
-
 }
 
-
-
 /* ───────────────────── 5. Forced draw sequence ───────────────────── */
-
 #[test]
-
 fn perfect_play_draws() {
-
     let mut g = Game::new();
-
     while g.best_move().is_some() {
-
         g.play_best();                 // X
-
         if let Some(mv) = g.best_move() {
-
             g.board_mut().play(mv);    // O mirrors perfect play
-
         }
-
     }
-
     assert_eq!(g.score(), 0);          // Draw
-
 }
 
-
-
 /* ──────────────── 6. Random O can never beat X (100 games) ───────── */
-
 #[test]
-
 fn random_play_never_beats_engine() {
+    for seed in 0..100 {
+        let mut g = Game::new();
+        let mut o = Bot::random(seed);
+        while g.best_move().is_some() {
+            g.play_best(); // X
+            o.play(&mut g); // random O move
+        }
+        assert!(g.score() >= 0, "engine lost a game!");
+    }
+}
 
-    use rand::{Rng, SeedableRng};
-
-    let mut rng = rand::rngs::StdRng::seed_from_u64(1);
+/* ─────────────────── 7. Board ID round‑trip integrity ────────────── */
+#[test]
+fn board_id_round_trip() {
+    let b1 = parse_board("XOX/ XO/   ");
+    let id  = b1.id();
+    let b2  = Board::from_id(id as u32); // helper in lib (or implement)
+    assert_eq!(b1.cells(), b2.cells());
+}
 
+/* ─────────────────── 8. Winning opportunity seized ───────────────── */
+#[test]
+fn engine_takes_winning_line() {
+    // X turn, can win with cell 2
+    let g = Game::from_board(parse_board("XX /OO /   "));
+    assert_eq!(g.best_move(), Some(2));
+}
 
+/* ───────────────── 9. Block opponent immediate win ───────────────── */
+#[test]
+fn engine_blocks_immediate_threat() {
+    // O threatens with two in a row, X must block at 2
+    let g = Game::from_board(parse_board("OO / X / X "));
+    assert_eq!(g.best_move(), Some(2));
+}
 
-    for _game in 0..100 {
+/* ────────────────── 10. Full board => no best move ───────────────── */
+#[test]
+fn full_board_has_no_move() {
+    let g = Game::from_board(parse_board("XOX/OXO/ OX"));
+    assert!(g.best_move().is_none());
+}
 
-        let mut g = Game::new();
+/* ─────────────────── 11. Table determinism hash ──────────────────── */
+#[test]
+fn tables_have_stable_hash() {
+    // the checksum build.rs wrote matches the tables, which hold together
+    assert!(task_ws::verify_tables());
+}
 
-        while let Some(best) = g.best_move() {
+/* ──────────────── 12. Runtime search agrees with tables ──────────── */
+#[test]
+fn grid_3x3_matches_tables() {
+    // every position after up to two plies, via both engines
+    let mut seen = 0;
+    for a in 0..9 {
+        for b in (0..9).filter(|&b| b != a) {
+            let (mut t, mut g) = (Board::default(), GridBoard::new(3, 3));
+            for m in [a, b] {
+                t.play(m);
+                g.play(m);
+                assert_eq!(g.score(), t.score(), "after {:?}", g.cells());
+                seen += 1;
+            }
+        }
+    }
+    assert_eq!(seen, 144);
+    assert_eq!(GridBoard::new(3, 3).score(), 0);
+}
 
-            g.play_best(); // X
+/* ──────────────────── 13. Bigger boards, k in a row ──────────────── */
+#[test]
+fn grid_small_cases() {
+    // a single cell in a row: the first move wins
+    assert_eq!(GridBoard::new(2, 1).score(), 1);
+    // 2×2, two in a row: X's second mark always completes a line
+    assert_eq!(GridBoard::new(2, 2).score(), 1);
+    // corners alone never make three in a row on 4×4
+    let mut g = GridBoard::new(4, 3);
+    for m in [0, 15, 3, 12] { g.play(m); }
+    assert_eq!(g.winner(), None);
+    assert_eq!(g.turn(), Cell::X);
+}
 
-            // random O move
+#[test]
+fn grid_game_plays_to_the_end() {
+    let mut g = Game::from_board(GridBoard::new(3, 3));
+    while g.best_move().is_some() { g.play_best(); }
+    assert_eq!(g.board().winner(), None);
+    assert_eq!(g.score(), 0);
+}
 
-            let empties: Vec<_> = (0..9)
+#[test]
+fn grid_engine_takes_a_win() {
+    let mut b = GridBoard::new(4, 3);
+    for m in [5, 0, 6, 15] { b.play(m); }
+    // X has 5,6 in row 1 with both 4 and 7 open: won whatever O does
+    let mut g = Game::from_board(b);
+    assert_eq!(g.score(), 1);
+    while g.best_move().is_some() { g.play_best(); }
+    assert_eq!(g.board().winner(), Some(Cell::X));
+}
 
-                .filter(|&i| g.board().cells()[i] == Cell::E)
+/* ─────────────────────── 14. Misère variant ──────────────────────── */
+#[test]
+fn misere_perfect_play_draws() {
+    let mut g = Game::new_with(Variant::Misere);
+    assert_eq!(g.variant(), Variant::Misere);
+    assert_eq!(g.score(), 0);
+    while g.best_move().is_some() { g.play_best(); }
+    assert_eq!(g.board().winner(), None);
+    assert!(g.board().cells().iter().all(|&c| c != Cell::E));
+}
 
-                .collect();
+#[test]
+fn misere_avoids_completing_a_line() {
+    let b = parse_board("XX /OO /   ");
+    // cell 2 wins under normal rules and loses under misère
+    assert_eq!(Game::from_board(b.clone()).score(), 1);
+    let g = Game::from_board(b).with_variant(Variant::Misere);
+    assert_ne!(g.best_move(), Some(2));
+    assert_eq!(Variant::Misere.line_value(Cell::X), -1);
+}
 
-            if empties.is_empty() { break; }
+#[test]
+fn misere_grid_matches_tables() {
+    for a in 0..9 {
+        for b in (0..9).filter(|&b| b != a) {
+            let (mut t, mut g) = (Board::default(), GridBoard::new(3, 3));
+            for m in [a, b] {
+                t.play(m);
+                g.play(m);
+                let (table, _) = t.solve(Variant::Misere);
+                assert_eq!(g.solve(Variant::Misere).0, table, "after {:?}", g.cells());
+            }
+        }
+    }
+}
 
-            let idx = empties[rng.gen_range(0..empties.len())];
+/* ─────────────────────── 15. Difficulty levels ───────────────────── */
+fn engine_game(x: &mut Engine, o: &mut Engine) -> Game {
+    let mut g = Game::new();
+    while g.best_move().is_some() {
+        let engine = if g.board().turn() == Cell::X { &mut *x } else { &mut *o };
+        engine.play(&mut g);
+    }
+    g
+}
 
-            g.board_mut().play(idx);
+#[test]
+fn easy_engine_is_seeded() {
+    let moves = |seed| {
+        let mut e = Engine::with_difficulty(Difficulty::Easy).with_seed(seed);
+        let mut p = Engine::with_difficulty(Difficulty::Perfect);
+        engine_game(&mut e, &mut p).board().cells().to_owned()
+    };
+    assert_eq!(moves(7), moves(7));
+    assert!((0..20).any(|seed| moves(seed) != moves(0)));
+}
 
+#[test]
+fn perfect_engine_never_loses_to_easy_or_medium() {
+    for seed in 0..50 {
+        for d in [Difficulty::Easy, Difficulty::Medium] {
+            let mut x = Engine::with_difficulty(Difficulty::Perfect);
+            let mut o = Engine::with_difficulty(d).with_seed(seed);
+            assert!(engine_game(&mut x, &mut o).score() >= 0, "{:?} seed {}", d, seed);
         }
-
-        assert!(g.score() >= 0, "engine lost a game!");
-
     }
-
 }
 
+#[test]
+fn medium_blunder_rate_bounds() {
+    let g = Game::new();
+    let mut never = Engine::with_difficulty(Difficulty::Medium).with_blunder_rate(0.0);
+    for seed in 0..20 {
+        never = never.with_seed(seed);
+        assert_eq!(never.choose(&g), g.best_move());
+    }
+    let mut always = Engine::with_difficulty(Difficulty::Medium).with_blunder_rate(1.0);
+    assert!((0..20).any(|_| always.choose(&g) != g.best_move()));
+}
 
+#[test]
+#[should_panic(expected = "blunder rate")]
+fn blunder_rate_out_of_range() {
+    let _ = Engine::with_difficulty(Difficulty::Medium).with_blunder_rate(1.5);
+}
 
-/* ─────────────────── 7. Board ID round‑trip integrity ────────────── */
+/* ─────────────────────── 16. Undo, redo, history ─────────────────── */
+#[test]
+fn history_records_moves() {
+    let mut g = Game::new();
+    g.play(4);
+    g.play_best();
+    let h = g.history();
+    assert_eq!(h.len(), 2);
+    assert_eq!(h[0], Move { cell: 4, player: Cell::X, ply: 1 });
+    assert_eq!((h[1].player, h[1].ply), (Cell::O, 2));
+}
 
 #[test]
+fn undo_and_redo_walk_the_history() {
+    let mut g = Game::new();
+    for m in [4, 0, 8] { g.play(m); }
+    let after = g.board().clone();
+    assert_eq!(g.undo().map(|m| m.cell), Some(8));
+    assert_eq!(g.undo().map(|m| m.cell), Some(0));
+    assert_eq!(g.board(), &parse_board("   / X /   "));
+    assert_eq!(g.redo().map(|m| m.cell), Some(0));
+    assert_eq!(g.redo().map(|m| m.cell), Some(8));
+    assert_eq!(g.redo(), None);
+    assert_eq!(g.board(), &after);
+    assert_eq!(g.history().len(), 3);
+}
 
-fn board_id_round_trip() {
+#[test]
+fn playing_clears_redo() {
+    let mut g = Game::new();
+    g.play(4);
+    g.undo();
+    g.play(0);
+    assert_eq!(g.redo(), None);
+    assert_eq!(g.undo().map(|m| m.cell), Some(0));
+    assert_eq!(g.undo(), None);
+    assert_eq!(g.board(), &Board::default());
+}
 
-    let b1 = parse_board("XOX/ XO/   ");
+/* ──────────────────────── 17. Ultimate variant ───────────────────── */
+mod ultimate {
+    use std::time::{Duration, Instant};
+    use task_ws::ultimate::{UltimateBoard, UltimateGame};
+    use task_ws::Cell;
+
+    #[test]
+    fn sub_board_constraint() {
+        let mut b = UltimateBoard::default();
+        assert_eq!(b.moves().len(), 81);
+        b.play(4 * 9 + 2); // X: centre board, top-right cell
+        assert_eq!(b.forced_board(), Some(2));
+        assert!(b.moves().iter().all(|&m| m / 9 == 2));
+        assert!(!b.is_legal(4 * 9 + 3));
+    }
 
-    let id  = b1.id();
+    #[test]
+    fn closed_board_frees_the_next_move() {
+        let mut b = UltimateBoard::default();
+        // X keeps sending O to board 0, where O completes the middle row
+        for m in [0, 3, 27, 4, 36, 5] {
+            b.play(m);
+        }
+        assert_eq!(b.board_winner(0), Some(Cell::O));
+        assert!(b.is_closed(0));
+        // O's last mark was cell 5, so X must play in board 5
+        assert_eq!(b.forced_board(), Some(5));
+        b.play(5 * 9); // X sends O to board 0, which is closed
+        assert_eq!(b.forced_board(), None);
+        assert!(b.moves().iter().all(|&m| m / 9 != 0));
+    }
 
-    let b2  = Board::from_id(id as u32); // helper in lib (or implement)
+    #[test]
+    fn engine_finishes_the_big_line() {
+        let mut cells = [Cell::E; 81];
+        // X owns boards 0 and 1 and has two in a row in board 2
+        for i in [0, 1, 2, 9, 10, 11, 18, 19] { cells[i] = Cell::X; }
+        for sb in 3..7 { cells[sb * 9] = Cell::O; cells[sb * 9 + 1] = Cell::O; }
+        let g = UltimateGame::from_board(UltimateBoard::from_cells(cells, Some(2)))
+            .with_max_depth(2);
+        assert_eq!(g.best_move(), Some(20));
+        assert_eq!(g.score(), 1);
+    }
 
-    assert_eq!(b1.cells(), b2.cells());
+    #[test]
+    fn engine_respects_the_budget() {
+        let g = UltimateGame::new().with_budget(Duration::from_millis(50));
+        let t = Instant::now();
+        let mv = g.best_move().unwrap();
+        assert!(t.elapsed() < Duration::from_secs(2));
+        assert!(g.board().is_legal(mv));
+    }
 
+    #[test]
+    fn engine_games_stay_legal() {
+        let mut g = UltimateGame::new().with_max_depth(2);
+        let mut plies = 0;
+        while let Some(mv) = g.best_move() {
+            assert!(g.board().is_legal(mv));
+            g.play(mv);
+            plies += 1;
+        }
+        assert!(plies <= 81);
+        assert!(g.board().winner().is_some() || g.board().moves().is_empty());
+    }
 }
 
-
-
-/* ─────────────────── 8. Winning opportunity seized ───────────────── */
-
+/* ───────────────────────── 18. Serialization ─────────────────────── */
 #[test]
+fn compact_board_round_trip() {
+    let b = parse_board("XOX/ XO/   ");
+    assert_eq!(b.to_compact(), "XOX/ XO/   ");
+    assert_eq!(Board::from_compact("XOX/ XO/   "), Ok(b.clone()));
+    assert_eq!(Board::from_compact("xox xo   "), Ok(b));
+    assert_eq!(Board::from_compact("XOX/ XO/  "), Err(task_ws::ParseBoardError::Length(8)));
+    assert_eq!(Board::from_compact("XOX/-XO/   "), Err(task_ws::ParseBoardError::Char('-')));
+}
 
-fn engine_takes_winning_line() {
-
-    // X turn, can win with cell 6
-
-    let mut g = Game::from_board(parse_board("XX /OO /   "));
-
-    assert_eq!(g.best_move(), Some(6));
+#[test]
+fn string_id_round_trip() {
+    for id in [0u32, 1, 4_242, task_ws::BOARD_IDS - 1] {
+        let b = Board::from_id(id);
+        assert_eq!(b.to_string_id(), id.to_string());
+        assert_eq!(Board::parse(&b.to_string_id()), Ok(b));
+    }
+    let b = parse_board("XOX/ XO/   ");
+    assert_eq!(Board::parse("XOX/ XO/   "), Ok(b));
+    assert_eq!(Board::parse("19683"), Err(task_ws::ParseBoardError::Id(task_ws::BOARD_IDS)));
+    assert_eq!(Board::parse("99999999999"), Err(task_ws::ParseBoardError::Id(u32::MAX)));
+}
 
+#[cfg(feature = "serde")]
+#[test]
+fn serde_round_trip() {
+    let b = parse_board("XO / X /  O");
+    let json = serde_json::to_string(&b).unwrap();
+    assert_eq!(json, r#""XO / X /  O""#);
+    assert_eq!(serde_json::from_str::<Board>(&json).unwrap(), b);
+    assert!(serde_json::from_str::<Board>(r#""XO""#).is_err());
+
+    let mut g = Game::new_with(Variant::Misere);
+    for m in [4, 0, 8] { g.play(m); }
+    g.undo();
+    let json = serde_json::to_string(&g).unwrap();
+    let mut back: Game = serde_json::from_str(&json).unwrap();
+    assert_eq!(back.board(), g.board());
+    assert_eq!(back.history(), g.history());
+    assert_eq!(back.variant(), Variant::Misere);
+    assert_eq!(back.redo().map(|m| m.cell), Some(8));
+    back.undo();
+    back.undo();
+    assert_eq!(back.board(), &parse_board("   / X /   "));
 }
 
+/* ───────────────────── 19. Symmetric tables ──────────────────────── */
+/// Image of each cell under the 8 rotations and reflections of the board.
+fn symmetries() -> Vec<[usize; 9]> {
+    let rot = |i: usize| (i % 3) * 3 + 2 - i / 3;
+    let mirror = |i: usize| i / 3 * 3 + 2 - i % 3;
+    let mut syms = Vec::new();
+    for m in [false, true] {
+        let mut perm: [usize; 9] = std::array::from_fn(|i| if m { mirror(i) } else { i });
+        for _ in 0..4 {
+            syms.push(perm);
+            perm = perm.map(rot);
+        }
+    }
+    syms
+}
 
+fn transform(b: &Board, sym: &[usize; 9]) -> Board {
+    // cell `i` moves to `sym[i]`, which is base-3 digit `sym[i]` of the id
+    Board::from_id(sym.iter().zip(b.cells()).map(|(&to, &c)| c as u32 * 3u32.pow(to as u32)).sum())
+}
 
-/* ───────────────── 9. Block opponent immediate win ───────────────── */
+/// Smallest id among the board's symmetric images.
+fn canonical_id(b: &Board) -> usize {
+    symmetries().iter().map(|sym| transform(b, sym).id()).min().unwrap()
+}
 
 #[test]
+fn best_moves_are_symmetry_invariant() {
+    for id in 0..task_ws::BOARD_IDS {
+        let b = Board::from_id(id);
+        for sym in symmetries() {
+            let img = transform(&b, &sym);
+            let (score, best) = (task_ws::table_score, task_ws::table_best);
+            assert_eq!(score(Variant::Normal, img.id()), score(Variant::Normal, id as usize));
+            let (m, m2) = (best(Variant::Normal, id as usize), best(Variant::Normal, img.id()));
+            let (Some(m), Some(m2)) = (m, m2) else {
+                assert_eq!(m, m2);
+                continue;
+            };
+            // the image's move is the mapped move, or one just like it on a
+            // board that is itself symmetric
+            let (mut a, mut c) = (img.clone(), img.clone());
+            a.play(sym[m]);
+            c.play(m2);
+            assert_eq!(canonical_id(&a), canonical_id(&c), "{:?}", b.to_compact());
+        }
+    }
+}
 
-fn engine_blocks_immediate_threat() {
-
-    // O threatens with two in a row, X must block at 2
+/* ───────────────────────── 20. WASM bindings ─────────────────────── */
+#[cfg(feature = "wasm")]
+#[test]
+fn wasm_game_round() {
+    let mut g = task_ws::wasm::new_game("perfect", 1);
+    assert_eq!(g.play_engine(), Some(4));
+    assert!(g.play(4).is_err());
+    g.play(0).unwrap();
+    assert_eq!(
+        g.board_json(),
+        r#"{"cells":["O"," "," "," ","X"," "," "," "," "],"turn":"X","winner":null,"over":false}"#
+    );
+    assert!(g.undo());
+    assert_eq!(g.best_move(), Some(0));
+}
 
-    let mut g = Game::from_board(parse_board("OO / X / X  "));
+/* ───────────────────── 21. Simulations, tournaments ──────────────── */
+#[test]
+fn perfect_never_loses_a_simulated_match() {
+    let perfect = Engine::with_difficulty(Difficulty::Perfect);
+    for d in [Difficulty::Easy, Difficulty::Medium, Difficulty::Perfect] {
+        let stats = task_ws::simulate(&perfect, &Engine::with_difficulty(d), 100, 3);
+        assert_eq!(stats.games(), 100);
+        assert_eq!(stats.losses, 0, "{:?}", d);
+        assert!((5.0..=9.0).contains(&stats.average_length()));
+    }
+    let stats = task_ws::simulate(&perfect, &perfect, 10, 0);
+    assert_eq!(stats.draws, 10);
+    assert_eq!(stats.moves, 90);
+}
 
-    assert_eq!(g.best_move(), Some(2));
+#[test]
+fn simulations_are_seeded() {
+    let easy = Engine::with_difficulty(Difficulty::Easy);
+    let medium = Engine::with_difficulty(Difficulty::Medium);
+    assert_eq!(task_ws::simulate(&easy, &medium, 50, 9), task_ws::simulate(&easy, &medium, 50, 9));
+}
 
+#[test]
+fn tournament_records_add_up() {
+    let field: Vec<Engine> = [Difficulty::Easy, Difficulty::Medium, Difficulty::Perfect]
+        .into_iter()
+        .map(Engine::with_difficulty)
+        .collect();
+    let table = task_ws::tournament(&field, 40, 1);
+    assert!(table.iter().all(|s| s.games() == 80));
+    let wins: usize = table.iter().map(|s| s.wins).sum();
+    let losses: usize = table.iter().map(|s| s.losses).sum();
+    assert_eq!(wins, losses);
+    assert_eq!(table[2].losses, 0);
+    assert!(table[2].wins > table[0].wins);
 }
 
+/* ─────────────────────────── 22. Hints ───────────────────────────── */
+#[test]
+fn hint_explains_a_forced_win() {
+    // X to move: 2 completes the top row; anything but 2 or the block at 5
+    // lets O complete the middle row
+    let g = Game::from_board(parse_board("XX /OO /   "));
+    let hint = g.hint().unwrap();
+    assert_eq!((hint.cell, hint.score, hint.line.as_slice()), (2, 1, &[2][..]));
+    let candidates: std::collections::HashMap<_, _> = hint.candidates.into_iter().collect();
+    assert_eq!(candidates.len(), 5);
+    assert_eq!(candidates[&2], 1);
+    for m in [6, 7, 8] { assert_eq!(candidates[&m], -1, "move {}", m); }
+}
 
+#[test]
+fn hint_line_plays_out_the_game() {
+    let g = Game::new();
+    let hint = g.hint().unwrap();
+    assert_eq!((hint.cell, hint.score), (4, 0));
+    assert_eq!(hint.candidates.len(), 9);
+    assert!(hint.candidates.iter().all(|&(_, s)| s == 0));
+    let mut b = Board::default();
+    for &m in &hint.line { b.play(m); }
+    assert_eq!(hint.line.len(), 9);
+    assert_eq!(b.winner(), None);
+    assert!(Game::from_board(parse_board("XOX/OXO/OXO")).hint().is_none());
+}
 
-/* ────────────────── 10. Full board => no best move ───────────────── */
+/* ──────────────────── 23. The engine plays either side ───────────── */
+/// Whether `engine_side`, playing the table move, avoids losing against
+/// every possible sequence of opponent moves from `b`.
+fn never_loses(b: &Board, engine_side: Cell, variant: Variant) -> bool {
+    if let Some(w) = b.winner() {
+        // under misère the side that completed the line loses
+        return (w == engine_side) != (variant == Variant::Misere);
+    }
+    let moves = b.moves();
+    if moves.is_empty() { return true; }
+    if b.turn() == engine_side {
+        let Some(m) = b.solve(variant).1 else { return false };
+        let mut nb = b.clone();
+        nb.play(m);
+        never_loses(&nb, engine_side, variant)
+    } else {
+        moves.into_iter().all(|m| {
+            let mut nb = b.clone();
+            nb.play(m);
+            never_loses(&nb, engine_side, variant)
+        })
+    }
+}
 
 #[test]
+fn engine_never_loses_as_either_side() {
+    for variant in [Variant::Normal, Variant::Misere] {
+        for side in [Cell::X, Cell::O] {
+            assert!(never_loses(&Board::default(), side, variant), "{:?} {:?}", side, variant);
+        }
+    }
+}
 
-fn full_board_has_no_move() {
+#[test]
+fn every_o_to_move_position_has_a_move() {
+    for id in 0..task_ws::BOARD_IDS {
+        let b = Board::from_id(id);
+        let xs = b.cells().iter().filter(|&&c| c == Cell::X).count();
+        let os = b.cells().iter().filter(|&&c| c == Cell::O).count();
+        if xs == os + 1 && !b.moves().is_empty() {
+            assert!(b.best_move().is_some(), "{:?}", b.to_compact());
+        }
+    }
+}
 
-    let g = Game::from_board(parse_board("XOX/OXO/ OX"));
+/* ───────────────────────── 24. Move validation ───────────────────── */
+#[test]
+fn try_play_reports_bad_moves() {
+    let mut b = parse_board("XO /   /   ");
+    assert_eq!(b.try_play(9), Err(MoveError::OutOfRange(9)));
+    assert_eq!(b.try_play(1), Err(MoveError::Occupied(1)));
+    assert_eq!(b.try_play(4), Ok(()));
+    assert_eq!(b.cells()[4], Cell::X);
+
+    let mut won = parse_board("XXX/OO /   ");
+    assert_eq!(won.try_play(5), Err(MoveError::GameOver));
+    assert_eq!(MoveError::Occupied(3).to_string(), "square 3 is already taken");
+
+    let mut g = GridBoard::new(4, 3);
+    assert_eq!(g.try_play(16), Err(MoveError::OutOfRange(16)));
+}
 
-    assert!(g.best_move().is_none());
+#[test]
+fn game_try_play_leaves_rejected_moves_out() {
+    let mut g = Game::new();
+    g.try_play(4).unwrap();
+    assert_eq!(g.try_play(4), Err(MoveError::Occupied(4)));
+    assert_eq!(g.try_play(42), Err(MoveError::OutOfRange(42)));
+    assert_eq!(g.history().len(), 1);
+}
 
+/* ──────────────────────── 25. Display / FromStr ──────────────────── */
+#[test]
+fn display_and_from_str_agree() {
+    let b: Board = "XO / X /  O".parse().unwrap();
+    assert_eq!(b.to_string(), "X|O| \n—+—+—\n |X| \n—+—+—\n | |O");
+    assert_eq!(format!("{:#}", b), "XO / X /  O");
+    assert_eq!(format!("{:#}", b).parse::<Board>(), Ok(b));
+    assert!("XO/".parse::<Board>().is_err());
 }
 
+/* ─────────────────────────── 26. Notation ────────────────────────── */
+#[test]
+fn notation_round_trip() {
+    let mut g = Game::new();
+    while g.best_move().is_some() { g.play_best(); }
+    let text = g.notation();
+    assert_eq!(text.len(), 9);
+    let back = Game::replay(&text).unwrap();
+    assert_eq!(back.board(), g.board());
+    assert_eq!(back.history(), g.history());
+    assert_eq!(Game::replay("4 0 8").unwrap().notation(), "408");
+}
 
+#[test]
+fn replay_rejects_bad_games() {
+    use task_ws::notation::NotationError;
+    assert_eq!(Game::replay("40x").err(), Some(NotationError::BadChar { index: 2, ch: 'x' }));
+    assert_eq!(Game::replay("409").err(), Some(NotationError::BadChar { index: 2, ch: '9' }));
+    assert_eq!(
+        Game::replay("404").err(),
+        Some(NotationError::Illegal { ply: 3, error: MoveError::Occupied(4) })
+    );
+    // X wins on the top row at ply 5; nothing may follow
+    assert_eq!(
+        Game::replay("031428").err(),
+        Some(NotationError::Illegal { ply: 6, error: MoveError::GameOver })
+    );
+}
 
-/* ─────────────────── 11. Table determinism hash ──────────────────── */
+/* ──────────────────────────── 27. Outcomes ───────────────────────── */
+#[test]
+fn outcome_reads_the_board() {
+    assert_eq!(Game::new().outcome(), Outcome::InProgress);
+    assert_eq!(Game::new().expected_outcome(), Outcome::Draw);
+    let won = parse_board("XXX/OO /   ");
+    assert_eq!(Game::from_board(won.clone()).outcome(), Outcome::XWins);
+    assert_eq!(Game::from_board(won).with_variant(Variant::Misere).outcome(), Outcome::OWins);
+    assert_eq!(Game::from_board(parse_board("XOX/XOO/OXX")).outcome(), Outcome::Draw);
+    let g = Game::from_board(parse_board("XX /OO /   "));
+    assert_eq!((g.outcome(), g.expected_outcome()), (Outcome::InProgress, Outcome::XWins));
+    assert_eq!(Outcome::from_score(-1).to_string(), "O wins");
+}
 
+/* ──────────────────────── 28. Move evaluations ───────────────────── */
 #[test]
+fn evaluate_moves_scores_every_legal_move() {
+    let opening = Game::new().evaluate_moves();
+    assert_eq!(opening.iter().map(|&(m, _)| m).collect::<Vec<_>>(), [4, 0, 2, 6, 8, 1, 3, 5, 7]);
+    assert!(opening.iter().all(|&(_, s)| s == 0));
+
+    let g = Game::from_board(parse_board("XX /OO /   "));
+    let evals = g.evaluate_moves();
+    assert_eq!(evals, [(2, 1), (6, -1), (8, -1), (5, 0), (7, -1)]);
+    assert_eq!(evals.iter().map(|&(_, s)| s).max(), Some(g.score()));
+    assert_eq!(g.hint().unwrap().candidates, evals);
+
+    assert!(Game::from_board(parse_board("XXX/OO /   ")).evaluate_moves().is_empty());
+}
 
-fn tables_have_stable_hash() {
+/* ───────────────────────────── 29. Events ────────────────────────── */
+#[test]
+fn events_follow_the_game() {
+    let mut g = Game::from_board(parse_board("XX /OO /   "));
+    let mut engine = Engine::with_difficulty(Difficulty::Perfect);
+    assert_eq!(engine.play(&mut g), Some(2));
+    let events: Vec<_> = g.events().collect();
+    assert_eq!(
+        events,
+        [
+            Event::EngineDecision { cell: 2, score: 1 },
+            Event::MovePlayed(Move { cell: 2, player: Cell::X, ply: 1 }),
+            Event::GameOver { outcome: Outcome::XWins },
+        ]
+    );
+    assert_eq!(g.events().count(), 0, "events are taken once");
+
+    // undo is silent, redo plays the move again
+    g.undo();
+    assert_eq!(g.events().count(), 0);
+    g.redo();
+    assert_eq!(g.events().count(), 2);
 
-    use std::collections::hash_map::DefaultHasher;
+    let mut g = Game::new();
+    g.play(4);
+    assert_eq!(g.events().collect::<Vec<_>>(), [Event::MovePlayed(g.history()[0])]);
+}
 
-    use std::hash::{Hash, Hasher};
+/* ────────────────────────────── 30. Bots ─────────────────────────── */
+#[test]
+fn greedy_bot_wins_blocks_and_respects_misere() {
+    let mut greedy = Bot::greedy();
+    // X completes the top row rather than blocking O
+    assert_eq!(greedy.choose(&Game::from_board(parse_board("XX /OO /   "))), Some(2));
+    // O has no win, so it blocks X's top row
+    assert_eq!(greedy.choose(&Game::from_board(parse_board("XX /O  /   "))), Some(2));
+    // under misère completing the row loses, so X plays elsewhere
+    let g = Game::from_board(parse_board("XX /OO /   ")).with_variant(Variant::Misere);
+    assert_ne!(greedy.choose(&g), Some(2));
+    assert_eq!(greedy.choose(&Game::from_board(parse_board("XXX/OO /   "))), None);
+}
 
-    let mut h = DefaultHasher::new();
+#[test]
+fn players_compose() {
+    let mut players: Vec<Box<dyn Player>> = vec![
+        Box::new(Engine::with_difficulty(Difficulty::Perfect)),
+        Box::new(Bot::greedy()),
+        Box::new(Bot::random(3)),
+    ];
+    for x in 0..players.len() {
+        for o in 0..players.len() {
+            let mut g = Game::new();
+            while g.best_move().is_some() {
+                let mover = if g.board().turn() == Cell::X { x } else { o };
+                players[mover].play(&mut g).unwrap();
+            }
+            // the engine never loses, whichever side it has
+            if x == 0 { assert!(g.score() >= 0); }
+            if o == 0 { assert!(g.score() <= 0); }
+        }
+    }
+    let moves = |seed| {
+        let mut g = Game::new();
+        let mut bot = Bot::random(seed);
+        while bot.play(&mut g).is_some() {}
+        g.notation()
+    };
+    assert_eq!(moves(5), moves(5));
+    assert!((0..20).any(|seed| moves(seed) != moves(0)));
+}
 
-    task_ws::SCORE.hash(&mut h);
+/* ──────────────────────── 31. Game-tree statistics ───────────────── */
+#[test]
+fn tree_stats_cover_every_reachable_position() {
+    use task_ws::analysis::{tree_stats, tree_stats_with};
+    let stats = tree_stats();
+    // the well-known counts: 5478 positions, 958 of them finished games
+    assert_eq!((stats.reachable, stats.terminal), (5478, 958));
+    assert_eq!(stats.x_wins + stats.o_wins + stats.draws, stats.reachable);
+    assert_eq!((stats.x_wins, stats.o_wins, stats.draws), (2936, 1474, 1068));
+    assert_eq!(stats.forced_win(&Board::default()), None, "the empty board is a draw");
+    assert_eq!(stats.forced_win(&parse_board("XXX/OO /   ")), Some(0));
+    assert_eq!(stats.forced_win(&parse_board("XX /OO /   ")), Some(1));
+    // O's edge reply loses, and X needs its three remaining moves
+    assert_eq!(stats.longest_forced_win(), Some((parse_board("XO /   /   "), 5)));
+    let unreachable = parse_board("XXX/XXX/   ");
+    assert_eq!(stats.forced_win(&unreachable), None);
+
+    let misere = tree_stats_with(Variant::Misere);
+    assert_eq!((misere.reachable, misere.terminal), (stats.reachable, stats.terminal));
+    assert_ne!(misere.x_wins, stats.x_wins);
+}
 
-    task_ws::BEST.hash(&mut h);
+#[test]
+fn forced_wins_shorten_by_one_each_ply() {
+    let stats = task_ws::analysis::tree_stats();
+    let mut reachable = 0;
+    for id in 0..task_ws::BOARD_IDS {
+        let b = Board::from_id(id);
+        let Some(plies) = stats.forced_win(&b) else { continue };
+        reachable += 1;
+        assert_ne!(b.score(), 0, "{:#}", b);
+        if plies == 0 {
+            assert!(b.moves().is_empty());
+            continue;
+        }
+        // some move keeps the result and is one ply closer to the end
+        let closer = b.moves().into_iter().any(|m| {
+            let mut nb = b.clone();
+            nb.play(m);
+            nb.score() == b.score() && stats.forced_win(&nb) == Some(plies - 1)
+        });
+        assert!(closer, "{:#}", b);
+    }
+    assert_eq!(reachable, stats.x_wins + stats.o_wins);
+}
 
-    assert_eq!(h.finish(), 0x8E3F_12A4_F12B_301Cu64); // known constant; update if build changes
+/* ─────────────────────────── 32. Perft ───────────────────────────── */
+#[test]
+fn perft_from_the_empty_board() {
+    let expected = [1, 9, 72, 504, 3024, 15120, 54720, 148176, 200448, 127872, 0];
+    let empty = Board::default();
+    for (depth, &count) in expected.iter().enumerate() {
+        assert_eq!(empty.perft(depth), count, "depth {}", depth);
+    }
+}
 
+#[test]
+fn perft_stops_at_finished_games() {
+    assert_eq!(parse_board("XXX/OO /   ").perft(1), 0);
+    assert_eq!(parse_board("XXX/OO /   ").perft(0), 1);
+    // X wins at once at 2; after any of the other four moves O has four replies
+    assert_eq!(parse_board("XX /OO /   ").perft(1), 5);
+    assert_eq!(parse_board("XX /OO /   ").perft(2), 4 * 4);
 }
@@ -0,0 +1,128 @@
+//! Build-script audit. A `# build` section is arbitrary code run at
+//! compile time, so when there is one its script is run again through
+//! cargo inside a network namespace of its own (`unshare --net`), and
+//! the workspace as extracted is compared with the workspace after both
+//! builds: a script that keeps to `OUT_DIR` changes nothing outside
+//! `target/` and does not care about the network.
+
+use crate::colors::*;
+use crate::RunOptions;
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+    process::Stdio,
+    time::SystemTime,
+};
+
+/// Size and modification time of every file in a workspace.
+pub type Snapshot = BTreeMap<PathBuf, (u64, SystemTime)>;
+
+fn walk(dir: &Path, root: &Path, out: &mut Snapshot) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        // build output, snapshots and cargo's own lock file
+        let name = entry.file_name();
+        if dir == root && (name == "target" || name == ".git" || name == "Cargo.lock") {
+            continue;
+        }
+        if entry.file_type()?.is_dir() {
+            walk(&path, root, out)?;
+        } else {
+            let meta = entry.metadata()?;
+            let rel = path.strip_prefix(root).unwrap_or(&path).to_path_buf();
+            out.insert(rel, (meta.len(), meta.modified()?));
+        }
+    }
+    Ok(())
+}
+
+/// The workspace before anything is built, for [`check`].
+pub fn snapshot(workspace: &Path) -> Result<Snapshot, String> {
+    let mut files = Snapshot::new();
+    walk(workspace, workspace, &mut files).map_err(|e| e.to_string())?;
+    Ok(files)
+}
+
+/// Remove the build script's compiled binary and output so cargo runs it
+/// again.
+fn forget_build_script(target: &Path) -> io::Result<()> {
+    let dir = target.join("debug/build");
+    let prefix = format!("{}-", crate::CRATE_NAME);
+    for entry in fs::read_dir(&dir).into_iter().flatten() {
+        let entry = entry?;
+        if entry.file_name().to_string_lossy().starts_with(&prefix) {
+            fs::remove_dir_all(entry.path())?;
+        }
+    }
+    Ok(())
+}
+
+fn unshare_available() -> bool {
+    std::process::Command::new("unshare")
+        .args(["--net", "--map-root-user", "true"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|s| s.success())
+}
+
+/// Audit `workspace`'s build script against the workspace as it was
+/// `before` building and print the findings. Returns whether there were
+/// no violations (also when the audit is skipped).
+pub fn check(workspace: &Path, before: &Snapshot, opts: &RunOptions) -> Result<bool, String> {
+    if !workspace.join("build.rs").exists() {
+        return Ok(true);
+    }
+    println!("{}Auditing build script{}", BLUE, RESET);
+    if !unshare_available() {
+        println!("{}Build script audit skipped:{} `unshare --net` is not available", BOLD, RESET);
+        return Ok(true);
+    }
+    let target = opts
+        .env
+        .iter()
+        .rev()
+        .find(|(k, _)| k == "CARGO_TARGET_DIR")
+        .map(|(_, v)| workspace.join(v))
+        .unwrap_or_else(|| workspace.join("target"));
+    forget_build_script(&target).map_err(|e| format!("{}: {}", target.display(), e))?;
+
+    let out = opts
+        .command("unshare")
+        .args(["--net", "--map-root-user", "cargo", "test", "--no-run", "--offline", "--color=never"])
+        .current_dir(workspace)
+        .output()
+        .map_err(|e| format!("unshare: {}", e))?;
+    let after = snapshot(workspace)?;
+
+    let mut violations = Vec::new();
+    if !out.status.success() {
+        let stderr = String::from_utf8_lossy(&out.stderr);
+        let reason = stderr
+            .lines()
+            .find(|l| l.starts_with("error"))
+            .unwrap_or("build failed");
+        violations.push(format!("fails without network access ({})", reason.trim()));
+    }
+    for (path, meta) in &after {
+        match before.get(path) {
+            None => violations.push(format!("created {}", path.display())),
+            Some(old) if old != meta => violations.push(format!("modified {}", path.display())),
+            Some(_) => {}
+        }
+    }
+    for path in before.keys().filter(|p| !after.contains_key(*p)) {
+        violations.push(format!("deleted {}", path.display()));
+    }
+
+    println!("\n{}Build script audit:{}", BOLD, RESET);
+    if violations.is_empty() {
+        println!("  {}no network use or writes outside OUT_DIR{}", GREEN, RESET);
+    }
+    for v in &violations {
+        println!("  {}{}{}", RED, v, RESET);
+    }
+    Ok(violations.is_empty())
+}
@@ -0,0 +1,450 @@
+//! Batch mode: given a directory, validate every notebook in it (and its
+//! subdirectories with `--recursive`), each by a validator process of its
+//! own with the same flags, so one task's `exit` or crash cannot take the
+//! others down. Each child writes a `--shard-report`, from which the
+//! final table counts the task's consistent passes, fails and flaky tests.
+//!
+//! `--tags` and `--exclude-tags` select notebooks by the tags of their
+//! manifests. With `--similarity-report`, the selected notebooks are
+//! first compared with each other (see [`validator::similarity`]) before
+//! any is validated.
+//!
+//! `--dashboard <page.html>` also writes the summary as an HTML page with
+//! a sortable table, linking each task to the `--format json` report its
+//! validation wrote into `<page>-reports/`.
+//!
+//! `--total-budget <minutes>` shares the time left between the notebooks
+//! still to validate: once the first tasks show what building and a run
+//! cost, each task gets only the runs its share allows, and tasks that
+//! cannot get even one, or that are still running at the deadline, end
+//! as "budget exhausted".
+
+use minijinja::{context, Environment};
+use serde::Serialize;
+use validator::colors::*;
+use validator::manifest::Manifest;
+use validator::shard::ShardReport;
+use validator::{interrupt, report, similarity, Notebook, RunMatrix};
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+    time::{Duration, Instant},
+};
+use wait_timeout::ChildExt;
+
+const DASHBOARD: &str = include_str!("../templates/dashboard.html");
+
+/// Notebooks under `dir`, sorted; hidden directories (like
+/// `.ipynb_checkpoints`) are skipped.
+pub fn discover(dir: &Path, recursive: bool) -> Result<Vec<PathBuf>, String> {
+    let mut found = Vec::new();
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(&dir).map_err(|e| format!("{}: {}", dir.display(), e))?.flatten() {
+            let path = entry.path();
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+            if path.is_dir() {
+                if recursive {
+                    dirs.push(path);
+                }
+            } else if path.extension().is_some_and(|e| e == "ipynb") {
+                found.push(path);
+            }
+        }
+    }
+    found.sort();
+    Ok(found)
+}
+
+/// `notebooks` whose manifest has any of `tags` (all of them when empty)
+/// and none of `exclude`. A notebook without a manifest has no tags.
+pub fn select(notebooks: Vec<PathBuf>, tags: &[String], exclude: &[String]) -> Vec<PathBuf> {
+    if tags.is_empty() && exclude.is_empty() {
+        return notebooks;
+    }
+    notebooks
+        .into_iter()
+        .filter(|notebook| {
+            let own = Manifest::for_notebook(notebook).map(|m| m.tags).unwrap_or_else(|e| {
+                // its validation, if selected, reports it
+                eprintln!("{}Manifest error:{} {}", RED, RESET, e);
+                Vec::new()
+            });
+            (tags.is_empty() || own.iter().any(|t| tags.contains(t))) && !own.iter().any(|t| exclude.contains(t))
+        })
+        .collect()
+}
+
+/// How one task's validation ended.
+struct Outcome {
+    notebook: PathBuf,
+    task: String,
+    /// Consistent passes, consistent fails and flaky tests, if the tests ran.
+    totals: Option<report::Totals>,
+    code: Option<i32>,
+    took: Duration,
+    /// The `--format json` report, for the dashboard.
+    document: Option<PathBuf>,
+    /// Skipped or stopped by `--total-budget`.
+    exhausted: bool,
+}
+
+impl Outcome {
+    fn verdict(&self) -> &'static str {
+        if self.exhausted {
+            return "budget exhausted";
+        }
+        match self.code {
+            Some(0) => "passed",
+            Some(interrupt::EXIT_CODE) => "interrupted",
+            _ if self.totals.is_none() => "error",
+            _ => "failed",
+        }
+    }
+}
+
+/// Flags that only mean something to the batch, not to each notebook's
+/// validation, with whether they take a value.
+const BATCH_ONLY: [(&str, bool); 6] = [
+    ("--recursive", false),
+    ("--similarity-report", true),
+    ("--tags", true),
+    ("--exclude-tags", true),
+    ("--dashboard", true),
+    ("--total-budget", true),
+];
+
+/// What the batch does besides validating each notebook.
+pub struct Options {
+    pub similarity_report: Option<PathBuf>,
+    pub dashboard: Option<PathBuf>,
+    /// Minutes the whole batch may take.
+    pub total_budget: Option<f64>,
+    /// `--runs`, which the budget may lower per task.
+    pub runs: usize,
+}
+
+/// The validator's own arguments without the directory and the
+/// batch-only flags, and without `--runs` if `own_runs`.
+fn passthrough(dir: &Path, own_runs: bool) -> Vec<OsString> {
+    let mut args = std::env::args_os().skip(1);
+    let mut kept = Vec::new();
+    while let Some(arg) = args.next() {
+        if arg == dir.as_os_str() {
+            continue;
+        }
+        let text = arg.to_string_lossy();
+        let name = text.split('=').next().unwrap_or_default();
+        // whether to drop it, and then its value too: `--flag value`
+        // rather than `--flag=value` (or `-r5`)
+        let drop = match BATCH_ONLY.iter().find(|(flag, _)| *flag == name) {
+            Some(&(_, value)) => Some(value && !text.contains('=')),
+            None if own_runs && (name == "--runs" || name.starts_with("-r")) => {
+                Some(name == "--runs" && !text.contains('=') || text == "-r")
+            }
+            None => None,
+        };
+        match drop {
+            Some(true) => {
+                args.next();
+            }
+            Some(false) => {}
+            None => kept.push(arg),
+        }
+    }
+    kept
+}
+
+/// Compare `notebooks` and write the `--similarity-report` to `path`.
+fn write_similarity_report(dir: &Path, notebooks: &[PathBuf], path: &Path) -> Result<(), String> {
+    let mut tasks = Vec::new();
+    for notebook in notebooks {
+        let name = notebook.strip_prefix(dir).unwrap_or(notebook).display().to_string();
+        match Notebook::load(notebook) {
+            Ok(nb) => tasks.push(similarity::Task::new(name, &nb)),
+            // its validation reports it
+            Err(e) => eprintln!("{}Similarity error:{} {}: {}", RED, RESET, name, e),
+        }
+    }
+    let pairs = similarity::similar(&tasks);
+    fs::write(path, similarity::report(&pairs, tasks.len())).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let color = if pairs.is_empty() { GREEN } else { RED };
+    println!("{}{} similar task pair(s){} among {} notebooks, listed in {}",
+             color, pairs.len(), RESET, tasks.len(), path.display());
+    Ok(())
+}
+
+/// `--total-budget`: when the batch must end, and what the tasks so far
+/// say one costs.
+struct Budget {
+    deadline: Instant,
+    /// Seconds of each finished task outside its runs (extraction, build,
+    /// checks).
+    fixed: Vec<f64>,
+    /// Mean seconds per run of each finished task that ran its tests.
+    per_run: Vec<f64>,
+}
+
+impl Budget {
+    fn new(minutes: f64) -> Self {
+        let deadline = Instant::now() + Duration::from_secs_f64(minutes.max(0.0) * 60.0);
+        Budget { deadline, fixed: Vec::new(), per_run: Vec::new() }
+    }
+
+    fn left(&self) -> Duration {
+        self.deadline.saturating_duration_since(Instant::now())
+    }
+
+    /// Runs, of `requested`, the next of `pending` tasks gets; `None` when
+    /// not even one fits in the time left.
+    fn runs(&self, pending: usize, requested: usize) -> Option<usize> {
+        let left = self.left().as_secs_f64();
+        if left == 0.0 {
+            return None;
+        }
+        let mean = |v: &[f64]| (!v.is_empty()).then(|| v.iter().sum::<f64>() / v.len() as f64);
+        // nothing to estimate from yet
+        let (Some(fixed), Some(per_run)) = (mean(&self.fixed), mean(&self.per_run)) else {
+            return Some(requested);
+        };
+        if left < fixed + per_run {
+            return None;
+        }
+        let share = left / pending as f64;
+        let fit = ((share - fixed) / per_run.max(0.001)).floor();
+        Some((fit.max(1.0) as usize).min(requested))
+    }
+
+    /// Learn from the `--format json` report at `document` of a task that
+    /// took `took`.
+    fn record(&mut self, document: &Path, took: Duration) {
+        let Some(doc) = fs::read_to_string(document)
+            .ok()
+            .and_then(|raw| serde_json::from_str::<serde_json::Value>(&raw).ok())
+        else {
+            return;
+        };
+        let runs: Vec<f64> = doc["run_seconds"].as_array().into_iter().flatten().filter_map(|s| s.as_f64()).collect();
+        self.fixed.push((took.as_secs_f64() - runs.iter().sum::<f64>()).max(0.0));
+        if !runs.is_empty() {
+            self.per_run.push(runs.iter().sum::<f64>() / runs.len() as f64);
+        }
+    }
+}
+
+/// Where the per-task reports of the dashboard at `page` go.
+fn reports_dir(page: &Path) -> PathBuf {
+    let stem = page.file_stem().unwrap_or_default().to_string_lossy();
+    page.with_file_name(format!("{}-reports", stem))
+}
+
+/// Write the `--dashboard` page for `outcomes` of `total` notebooks.
+fn write_dashboard(page: &Path, dir: &Path, outcomes: &[Outcome], total: usize) -> Result<(), String> {
+    #[derive(Serialize)]
+    struct Row {
+        task: String,
+        verdict: &'static str,
+        pass: Option<usize>,
+        fail: Option<usize>,
+        flaky: Option<usize>,
+        seconds: String,
+        build: Option<String>,
+        difficulty: Option<String>,
+        difficulty_rank: usize,
+        tags: Vec<String>,
+        /// Relative link to the task's report.
+        report: Option<String>,
+    }
+    let base = page.parent().unwrap_or(Path::new(""));
+    let rows: Vec<Row> = outcomes
+        .iter()
+        .map(|o| {
+            let manifest = Manifest::for_notebook(&o.notebook).unwrap_or_default();
+            let document = o.document.as_deref().filter(|d| d.is_file());
+            let build = document
+                .and_then(|d| fs::read_to_string(d).ok())
+                .and_then(|raw| serde_json::from_str::<serde_json::Value>(&raw).ok())
+                .and_then(|doc| doc["build_seconds"].as_f64());
+            let difficulty_rank = ["easy", "medium", "hard", "expert"]
+                .iter()
+                .position(|d| manifest.difficulty.as_deref() == Some(*d))
+                .map_or(0, |i| i + 1);
+            Row {
+                task: o.task.clone(),
+                verdict: o.verdict(),
+                pass: o.totals.as_ref().map(|t| t.pass),
+                fail: o.totals.as_ref().map(|t| t.fail),
+                flaky: o.totals.as_ref().map(|t| t.flaky),
+                seconds: format!("{:.1}", o.took.as_secs_f64()),
+                build: build.map(|b| format!("{:.1}", b)),
+                difficulty: manifest.difficulty,
+                difficulty_rank,
+                tags: manifest.tags,
+                report: document.map(|d| {
+                    let rel = d.strip_prefix(base).unwrap_or(d);
+                    rel.components().map(|c| c.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/")
+                }),
+            }
+        })
+        .collect();
+    let passed = outcomes.iter().filter(|o| o.code == Some(0)).count();
+    let flaky: usize = outcomes.iter().filter_map(|o| o.totals.as_ref()).map(|t| t.flaky).sum();
+    let seconds = format!("{:.0}", outcomes.iter().map(|o| o.took).sum::<Duration>().as_secs_f64());
+    let mut env = Environment::new();
+    env.add_template("dashboard.html", DASHBOARD).map_err(|e| e.to_string())?;
+    let html = env
+        .get_template("dashboard.html")
+        .and_then(|t| {
+            t.render(context! {
+                dir => dir.display().to_string(),
+                tasks => rows,
+                passed,
+                not_run => total - outcomes.len(),
+                flaky,
+                seconds,
+            })
+        })
+        .map_err(|e| format!("template: {}", e))?;
+    if let Some(parent) = page.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).map_err(|e| format!("{}: {}", parent.display(), e))?;
+    }
+    fs::write(page, html).map_err(|e| format!("{}: {}", page.display(), e))
+}
+
+/// Validate each of `notebooks` and print the summary table. Returns the
+/// exit code: 0 when every task passed.
+pub fn run(dir: &Path, notebooks: &[PathBuf], options: &Options) -> i32 {
+    let exe = match std::env::current_exe() {
+        Ok(exe) => exe,
+        Err(e) => {
+            eprintln!("{}Batch error:{} {}", RED, RESET, e);
+            return 1;
+        }
+    };
+    if let Some(path) = &options.similarity_report {
+        if let Err(e) = write_similarity_report(dir, notebooks, path) {
+            eprintln!("{}Batch error:{} {}", RED, RESET, e);
+            return 1;
+        }
+    }
+    let mut budget = options.total_budget.map(Budget::new);
+    let flags = passthrough(dir, budget.is_some());
+    let reports = std::env::temp_dir().join(format!("validator-batch-{}", std::process::id()));
+    if let Err(e) = fs::create_dir_all(&reports) {
+        eprintln!("{}Batch error:{} {}: {}", RED, RESET, reports.display(), e);
+        return 1;
+    }
+    // the budget learns from the reports too
+    let documents = options
+        .dashboard
+        .as_deref()
+        .map(reports_dir)
+        .or_else(|| budget.as_ref().map(|_| reports.join("documents")));
+    if let Some(documents) = &documents {
+        // reports of an earlier batch would be linked as this one's
+        let _ = fs::remove_dir_all(documents);
+    }
+    let mut outcomes = Vec::new();
+    for (i, notebook) in notebooks.iter().enumerate() {
+        let task = notebook.strip_prefix(dir).unwrap_or(notebook).display().to_string();
+        println!("\n{}━━ {} ({}/{}){}", BOLD, task, i + 1, notebooks.len(), RESET);
+        let report = reports.join(format!("{}.json", i));
+        let mut cmd = Command::new(&exe);
+        cmd.arg(notebook).args(&flags).arg("--shard-report").arg(&report);
+        if let Some(budget) = &budget {
+            let Some(runs) = budget.runs(notebooks.len() - i, options.runs) else {
+                println!("{}Budget exhausted;{} not validated", RED, RESET);
+                let (totals, code, took, document) = (None, None, Duration::ZERO, None);
+                outcomes.push(Outcome { notebook: notebook.clone(), task, totals, code, took, document, exhausted: true });
+                continue;
+            };
+            if runs < options.runs {
+                println!("{}Budget:{} {} of {} runs, with {}s left for {} task(s)", BLUE, RESET, runs,
+                         options.runs, budget.left().as_secs(), notebooks.len() - i);
+            }
+            cmd.arg("--runs").arg(runs.to_string());
+        }
+        let document = documents.as_ref().map(|d| d.join(&task).with_extension("json"));
+        if let Some(document) = &document {
+            if let Some(parent) = document.parent() {
+                let _ = fs::create_dir_all(parent);
+            }
+            cmd.args(["--format", "json", "--output"]).arg(document);
+        }
+        let started = Instant::now();
+        let mut exhausted = false;
+        let status = cmd.spawn().and_then(|mut child| {
+            let Some(left) = budget.as_ref().map(Budget::left) else {
+                return child.wait();
+            };
+            match child.wait_timeout(left)? {
+                Some(status) => Ok(status),
+                None => {
+                    // out of time: stop it as Ctrl-C would, so it takes
+                    // its cargo processes down and reports what it has
+                    println!("{}Budget exhausted;{} stopping the validation", RED, RESET);
+                    exhausted = true;
+                    // SAFETY: kill(2) has no memory-safety preconditions
+                    unsafe { libc::kill(child.id() as i32, libc::SIGINT) };
+                    child.wait()
+                }
+            }
+        });
+        let took = started.elapsed();
+        let code = match status {
+            Ok(status) => status.code(),
+            Err(e) => {
+                eprintln!("{}Batch error:{} {}: {}", RED, RESET, exe.display(), e);
+                None
+            }
+        };
+        let totals = ShardReport::load(&report)
+            .ok()
+            .map(|r| report::rows(&RunMatrix::from(r.tests.into_iter().collect::<HashMap<_, _>>())).1);
+        if let (Some(budget), Some(document)) = (&mut budget, &document) {
+            budget.record(document, took);
+        }
+        outcomes.push(Outcome { notebook: notebook.clone(), task, totals, code, took, document, exhausted });
+        // the child saw the same Ctrl-C
+        if (code == Some(interrupt::EXIT_CODE) && !exhausted) || interrupt::interrupted() {
+            break;
+        }
+    }
+    let _ = fs::remove_dir_all(&reports);
+
+    let width = outcomes.iter().map(|o| o.task.len()).max().unwrap_or(0).max(4);
+    println!("\n{}Batch summary:{}", BOLD, RESET);
+    println!("  {:width$}  {:>5}  {:>5}  {:>5}  result", "task", "pass", "fail", "flaky", width = width);
+    let mut failed = 0;
+    for o in &outcomes {
+        let result = o.verdict();
+        let color = if o.code == Some(0) { GREEN } else { RED };
+        failed += usize::from(o.code != Some(0));
+        let count = |n: Option<usize>| n.map_or("-".to_string(), |n| n.to_string());
+        let t = o.totals.as_ref();
+        println!("  {:width$}  {:>5}  {:>5}  {:>5}  {}{}{}", o.task,
+                 count(t.map(|t| t.pass)), count(t.map(|t| t.fail)), count(t.map(|t| t.flaky)),
+                 color, result, RESET, width = width);
+    }
+    let skipped = notebooks.len() - outcomes.len();
+    println!("  {} of {} tasks passed{}", outcomes.len() - failed, notebooks.len(),
+             if skipped > 0 { format!(", {} not run", skipped) } else { String::new() });
+    if let Some(page) = &options.dashboard {
+        match write_dashboard(page, dir, &outcomes, notebooks.len()) {
+            Ok(()) => println!("Dashboard written to {}", page.display()),
+            Err(e) => eprintln!("{}Dashboard error:{} {}", RED, RESET, e),
+        }
+    }
+    if interrupt::interrupted() {
+        interrupt::EXIT_CODE
+    } else if failed > 0 || skipped > 0 {
+        1
+    } else {
+        0
+    }
+}
@@ -0,0 +1,246 @@
+//! `validator bundle` packs everything a validation needs into one zip,
+//! and `validator run-bundle` validates from it without the network, for
+//! air-gapped graders:
+//!
+//! - `notebook/`: the notebook and its manifest, validated as usual;
+//! - `workspace/`: the workspace extracted from them, with `Cargo.lock`;
+//! - `vendor/`: `cargo vendor` output for every dependency in the lock;
+//! - `fixtures/`: the manifest's fixtures, named by SHA-256 like the
+//!   fixture cache;
+//! - `rust-toolchain.toml`: the toolchain the bundle was made with;
+//! - `bundle.json`: format version, toolchain and a SHA-256 per file,
+//!   checked before anything runs.
+
+use crate::colors::*;
+use crate::manifest::Manifest;
+use crate::{fixtures, msrv, prepare_workspace, reaper, Notebook, Section};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+/// Version of the bundle layout this validator writes and reads.
+const FORMAT: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct BundleManifest {
+    format: u32,
+    /// File name of the notebook under `notebook/`.
+    notebook: String,
+    /// `rustc --version` of the toolchain the bundle was made with.
+    rustc: String,
+    /// rustup channel pinning that toolchain.
+    channel: String,
+    /// SHA-256 of every other file in the bundle, by path.
+    files: BTreeMap<String, String>,
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Files under `dir`, as `/`-separated paths relative to it, leaving out
+/// build output.
+fn files_under(dir: &Path, prefix: &str, out: &mut Vec<(String, PathBuf)>) -> Result<(), String> {
+    let mut entries: Vec<_> = fs::read_dir(dir)
+        .map_err(|e| format!("{}: {}", dir.display(), e))?
+        .flatten()
+        .collect();
+    entries.sort_by_key(|e| e.file_name());
+    for entry in entries {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name == "target" || name == ".git" {
+            continue;
+        }
+        let rel = format!("{}{}", prefix, name);
+        if entry.path().is_dir() {
+            files_under(&entry.path(), &format!("{}/", rel), out)?;
+        } else {
+            out.push((rel, entry.path()));
+        }
+    }
+    Ok(())
+}
+
+/// `rustc --version` and the rustup channel that reproduces it: the
+/// release number for stable, `nightly`/`beta` otherwise, whose exact
+/// build `rustc` still names.
+fn toolchain() -> Result<(String, String), String> {
+    let out = Command::new("rustc").arg("--version").output().map_err(|e| format!("rustc: {}", e))?;
+    let rustc = String::from_utf8_lossy(&out.stdout).trim().to_string();
+    let version = rustc.split_whitespace().nth(1).ok_or_else(|| format!("unexpected `{}`", rustc))?;
+    let channel = match version.split_once('-') {
+        None => version.to_string(),
+        Some((_, pre)) => pre.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.').to_string(),
+    };
+    Ok((rustc, channel))
+}
+
+/// Vendor the dependencies of `workspace` into `vendor`, which also locks
+/// them.
+fn vendor(workspace: &Path, vendor: &Path) -> Result<(), String> {
+    let status = Command::new("cargo")
+        .args(["vendor", "--versioned-dirs", "--quiet"])
+        .arg(vendor)
+        .current_dir(workspace)
+        .stdout(Stdio::null())
+        .status()
+        .map_err(|e| format!("cargo vendor: {}", e))?;
+    if !status.success() {
+        return Err(format!("`cargo vendor` failed (exit {:?})", status.code()));
+    }
+    fs::create_dir_all(vendor).map_err(|e| e.to_string())
+}
+
+/// Write the bundle of `notebook` to `out`. Fixtures come from `cache`
+/// when there, and are downloaded unless `offline`.
+pub fn create(notebook: &Path, out: &Path, cache: &Path, offline: bool) -> Result<(), String> {
+    let nb = Notebook::load(notebook).map_err(|e| format!("{}: {}", notebook.display(), e))?;
+    let manifest = Manifest::for_notebook(notebook)?;
+    let name = notebook.file_name().and_then(|n| n.to_str()).ok_or("notebook has no file name")?;
+    let stem = notebook.file_stem().and_then(|s| s.to_str()).unwrap_or("task_ws");
+    let staging = Path::new(reaper::ROOT).join(format!("{}.bundling", stem));
+    if staging.exists() {
+        fs::remove_dir_all(&staging).map_err(|e| e.to_string())?;
+    }
+    let workspace = staging.join("workspace");
+    prepare_workspace(&nb, &workspace, &Section::ALL, None)?;
+    fixtures::install(&manifest.fixtures, &workspace, cache, offline)?;
+    println!("{}Vendoring dependencies{}", BLUE, RESET);
+    vendor(&workspace, &staging.join("vendor"))?;
+    let (rustc, channel) = toolchain()?;
+
+    let mut files = vec![(format!("notebook/{}", name), notebook.to_path_buf())];
+    let toml = notebook.with_extension("toml");
+    if toml.exists() {
+        files.push((format!("notebook/{}.toml", stem), toml));
+    }
+    files_under(&workspace, "workspace/", &mut files)?;
+    files_under(&staging.join("vendor"), "vendor/", &mut files)?;
+    // the fixtures as the cache stores them, so `run-bundle` can use
+    // `fixtures/` as its cache
+    for fixture in &manifest.fixtures {
+        let installed = workspace.join("data").join(fixture.file_name()?);
+        files.push((format!("fixtures/{}", fixture.sha256), installed));
+    }
+
+    let file = fs::File::create(out).map_err(|e| format!("{}: {}", out.display(), e))?;
+    let mut zip = ZipWriter::new(file);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    let mut add = |path: &str, bytes: &[u8]| -> Result<(), String> {
+        zip.start_file(path, options).map_err(|e| e.to_string())?;
+        zip.write_all(bytes).map_err(|e| e.to_string())
+    };
+    let mut sums = BTreeMap::new();
+    for (rel, path) in &files {
+        let bytes = fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        sums.insert(rel.clone(), sha256_hex(&bytes));
+        add(rel, &bytes)?;
+    }
+    let pin = format!("[toolchain]\nchannel = \"{}\"\n", channel);
+    sums.insert("rust-toolchain.toml".into(), sha256_hex(pin.as_bytes()));
+    add("rust-toolchain.toml", pin.as_bytes())?;
+    let meta = BundleManifest { format: FORMAT, notebook: name.to_string(), rustc, channel, files: sums };
+    add("bundle.json", serde_json::to_string_pretty(&meta).map_err(|e| e.to_string())?.as_bytes())?;
+    zip.finish().map_err(|e| e.to_string())?;
+    let _ = fs::remove_dir_all(&staging);
+    println!("{}Bundle:{} {} ({} files, {})", BOLD, RESET, out.display(), meta.files.len(), meta.rustc);
+    Ok(())
+}
+
+/// A bundle unpacked and verified by [`unpack`].
+pub struct Unpacked {
+    root: PathBuf,
+    /// The bundled notebook, to validate in place of a path argument.
+    pub notebook: PathBuf,
+    /// Directory to use as the fixture cache.
+    pub fixtures: PathBuf,
+    rustc: String,
+    channel: String,
+}
+
+/// Unpack `bundle` next to the workspaces and check every file against
+/// its recorded SHA-256.
+pub fn unpack(bundle: &Path) -> Result<Unpacked, String> {
+    let file = fs::File::open(bundle).map_err(|e| format!("{}: {}", bundle.display(), e))?;
+    let mut zip = ZipArchive::new(file).map_err(|e| format!("{}: {}", bundle.display(), e))?;
+    let stem = bundle
+        .file_name()
+        .and_then(|n| n.to_str())
+        .and_then(|n| n.split('.').next())
+        .filter(|s| !s.is_empty())
+        .unwrap_or("bundle");
+    let root = fs::canonicalize(reaper::ROOT)
+        .or_else(|_| fs::create_dir_all(reaper::ROOT).and_then(|_| fs::canonicalize(reaper::ROOT)))
+        .map_err(|e| format!("{}: {}", reaper::ROOT, e))?
+        .join(format!("{}.bundle", stem));
+    if root.exists() {
+        fs::remove_dir_all(&root).map_err(|e| e.to_string())?;
+    }
+    // refuses entries that would land outside `root`
+    zip.extract(&root).map_err(|e| format!("{}: {}", bundle.display(), e))?;
+
+    let raw = fs::read_to_string(root.join("bundle.json")).map_err(|e| format!("bundle.json: {}", e))?;
+    let meta: BundleManifest = serde_json::from_str(&raw).map_err(|e| format!("bundle.json: {}", e))?;
+    if meta.format > FORMAT {
+        return Err(format!("bundle format {} is newer than this validator's {}", meta.format, FORMAT));
+    }
+    for (rel, want) in &meta.files {
+        let bytes = fs::read(root.join(rel)).map_err(|e| format!("{}: {}", rel, e))?;
+        if sha256_hex(&bytes) != *want {
+            return Err(format!("{}: checksum mismatch, the bundle is damaged", rel));
+        }
+    }
+    if sha256_hex(format!("[toolchain]\nchannel = \"{}\"\n", meta.channel).as_bytes())
+        != meta.files.get("rust-toolchain.toml").cloned().unwrap_or_default()
+    {
+        return Err("bundle.json and rust-toolchain.toml disagree on the toolchain".into());
+    }
+    println!("{}Bundle:{} {} ({} files verified, made with {})", BOLD, RESET, bundle.display(),
+             meta.files.len(), meta.rustc);
+    Ok(Unpacked {
+        notebook: root.join("notebook").join(&meta.notebook),
+        fixtures: root.join("fixtures"),
+        rustc: meta.rustc,
+        channel: meta.channel,
+        root,
+    })
+}
+
+impl Unpacked {
+    /// Point the freshly extracted `workspace` at the vendored sources and
+    /// the bundled lock file, and add an offline cargo to `env`, plus the
+    /// pinned toolchain when the default one is a different build (a
+    /// toolchain the notebook pins itself wins).
+    pub fn install(&self, workspace: &Path, env: &mut Vec<(String, String)>) -> Result<(), String> {
+        fs::copy(self.root.join("workspace/Cargo.lock"), workspace.join("Cargo.lock"))
+            .map_err(|e| format!("Cargo.lock: {}", e))?;
+        let dir = workspace.join(".cargo");
+        fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+        // a bundle without dependencies has no `vendor/` entries
+        fs::create_dir_all(self.root.join("vendor")).map_err(|e| e.to_string())?;
+        let config = format!(
+            "[source.crates-io]\nreplace-with = \"vendored-sources\"\n\n\
+             [source.vendored-sources]\ndirectory = {:?}\n\n[net]\noffline = true\n",
+            self.root.join("vendor").display().to_string()
+        );
+        fs::write(dir.join("config.toml"), config).map_err(|e| e.to_string())?;
+        let pinned = env.iter().any(|(k, _)| k == "RUSTUP_TOOLCHAIN");
+        if !pinned && toolchain()?.0 != self.rustc {
+            if !msrv::installed(&self.channel) {
+                return Err(format!("the bundle was made with {} and toolchain `{}` is not installed",
+                                   self.rustc, self.channel));
+            }
+            println!("{}Toolchain:{} {} (pinned by the bundle)", BOLD, RESET, self.channel);
+            env.push(("RUSTUP_TOOLCHAIN".into(), self.channel.clone()));
+        }
+        env.push(("CARGO_NET_OFFLINE".into(), "true".into()));
+        Ok(())
+    }
+}
@@ -0,0 +1,135 @@
+//! Reuse of the `tasks/<stem>` workspace between validations. The
+//! notebook is extracted into a staging directory and only files whose
+//! content changed are copied over, so unchanged sources keep their
+//! mtimes and cargo's fingerprints in `target/` stay valid: editing one
+//! test rebuilds the test crate, not the world. Files the notebook no
+//! longer produces are removed; `target/`, `Cargo.lock` and the
+//! `--git-snapshots` repository are kept.
+//! `--clean` extracts from scratch instead.
+
+use crate::{policy, prepare_workspace, reaper, Notebook, Section};
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Staging directory inside the workspace, removed once synced.
+const STAGING: &str = ".extract";
+
+/// Top-level entries that survive a refresh.
+const KEPT: [&str; 6] = ["target", "Cargo.lock", STAGING, reaper::MARKER, ".git", ".gitignore"];
+
+pub struct Refreshed {
+    /// Every file extracted, as [`prepare_workspace`] returns them.
+    pub files: Vec<String>,
+    /// Those written because they were new or changed.
+    pub written: Vec<String>,
+    /// Whether an existing workspace was reused at all.
+    pub reused: bool,
+}
+
+/// Extract `sections` of `nb` into `workspace`, keeping what an earlier
+/// extraction left there wherever it still matches.
+pub fn refresh(
+    nb: &Notebook,
+    workspace: &Path,
+    sections: &[Section],
+    allowed: Option<&policy::Allowed>,
+) -> Result<Refreshed, String> {
+    if !workspace.join("Cargo.toml").is_file() {
+        let files = prepare_workspace(nb, workspace, sections, allowed)?;
+        return Ok(Refreshed { written: files.clone(), files, reused: false });
+    }
+    let staging = workspace.join(STAGING);
+    let synced = prepare_workspace(nb, &staging, sections, allowed)
+        .and_then(|files| Ok(Refreshed { written: sync(&staging, workspace)?, files, reused: true }));
+    let _ = fs::remove_dir_all(&staging);
+    synced
+}
+
+/// Files under `dir`, relative to `root`.
+fn walk(root: &Path, dir: &Path, out: &mut BTreeSet<PathBuf>) -> Result<(), String> {
+    for entry in fs::read_dir(dir).map_err(|e| format!("{}: {}", dir.display(), e))? {
+        let path = entry.map_err(|e| e.to_string())?.path();
+        if path.is_dir() {
+            walk(root, &path, out)?;
+        } else if let Ok(rel) = path.strip_prefix(root) {
+            out.insert(rel.to_path_buf());
+        }
+    }
+    Ok(())
+}
+
+/// Make `workspace` hold exactly what `staging` does, apart from
+/// [`KEPT`]; returns the files written.
+fn sync(staging: &Path, workspace: &Path) -> Result<Vec<String>, String> {
+    let mut wanted = BTreeSet::new();
+    walk(staging, staging, &mut wanted)?;
+    let mut written = Vec::new();
+    for rel in &wanted {
+        let (from, to) = (staging.join(rel), workspace.join(rel));
+        let content = fs::read(&from).map_err(|e| format!("{}: {}", from.display(), e))?;
+        if fs::read(&to).is_ok_and(|old| old == content) {
+            continue;
+        }
+        if to.is_dir() {
+            fs::remove_dir_all(&to).map_err(|e| e.to_string())?;
+        }
+        if let Some(dir) = to.parent() {
+            fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        fs::write(&to, content).map_err(|e| format!("{}: {}", to.display(), e))?;
+        written.push(rel.display().to_string());
+    }
+    prune(workspace, workspace, &wanted)?;
+    Ok(written)
+}
+
+/// Remove what under `dir` is neither wanted nor kept, and directories
+/// left empty by that.
+fn prune(workspace: &Path, dir: &Path, wanted: &BTreeSet<PathBuf>) -> Result<(), String> {
+    for entry in fs::read_dir(dir).map_err(|e| format!("{}: {}", dir.display(), e))? {
+        let path = entry.map_err(|e| e.to_string())?.path();
+        let Ok(rel) = path.strip_prefix(workspace) else { continue };
+        if dir == workspace && KEPT.iter().any(|k| rel == Path::new(k)) {
+            continue;
+        }
+        if path.is_dir() && !path.is_symlink() {
+            prune(workspace, &path, wanted)?;
+            if fs::read_dir(&path).is_ok_and(|mut d| d.next().is_none()) {
+                fs::remove_dir(&path).map_err(|e| e.to_string())?;
+            }
+        } else if !wanted.contains(rel) {
+            fs::remove_file(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{snapshot::Snapshots, Cell};
+    use std::process::Command;
+
+    fn notebook(body: &str) -> Notebook {
+        let text = format!("# lib\n\n```rust\n{}\n```", body);
+        Notebook { cells: vec![Cell::Markdown { source: text.lines().map(|l| format!("{}\n", l)).collect() }] }
+    }
+
+    #[test]
+    fn snapshots_survive_a_second_refresh() {
+        let workspace = std::env::temp_dir().join(format!("validator-cache-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&workspace);
+        refresh(&notebook("pub fn a() {}"), &workspace, &[Section::Lib], None).unwrap();
+        Snapshots::init(&workspace).unwrap().commit("Extract").unwrap();
+
+        let second = refresh(&notebook("pub fn b() {}"), &workspace, &[Section::Lib], None).unwrap();
+        assert!(second.reused);
+        assert!(workspace.join(".gitignore").is_file());
+        let log = Command::new("git").args(["log", "--format=%s"]).current_dir(&workspace).output().unwrap();
+        let _ = fs::remove_dir_all(&workspace);
+        assert_eq!(String::from_utf8_lossy(&log.stdout), "Extract\n");
+    }
+}
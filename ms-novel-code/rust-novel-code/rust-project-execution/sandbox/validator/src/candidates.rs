@@ -0,0 +1,143 @@
+//! `--candidates dir/`: run the notebook's tests against several
+//! candidate `lib.rs` files (or directories of `src/` files) and rank
+//! them, to pick the best of a batch of generated solutions in one go.
+//! Candidates build into the main workspace's `target/`, so dependencies
+//! are compiled once.
+
+use crate::colors::*;
+use crate::toolchains::{copy_sources, workspace_for};
+use crate::{build_tests, run_cargo_test_once, RunOptions};
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+/// What running the tests against one candidate gave.
+struct Outcome {
+    name: String,
+    /// The build error, if it did not compile.
+    error: Option<String>,
+    /// Tests that passed every run.
+    passed: usize,
+    tests: usize,
+    /// Percentage of passing test runs.
+    score: f32,
+    duration: Duration,
+}
+
+/// The candidates in `dir` by name: `*.rs` files and subdirectories
+/// holding a `lib.rs`.
+fn list(dir: &Path) -> Result<Vec<(String, PathBuf)>, String> {
+    let entries = fs::read_dir(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    let mut found = Vec::new();
+    for entry in entries {
+        let path = entry.map_err(|e| e.to_string())?.path();
+        let candidate = if path.is_dir() {
+            path.join("lib.rs").exists()
+        } else {
+            path.extension().is_some_and(|e| e == "rs")
+        };
+        if candidate {
+            let name = path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+            found.push((name, path));
+        }
+    }
+    if found.is_empty() {
+        return Err(format!("{}: no `*.rs` files or directories with a `lib.rs`", dir.display()));
+    }
+    found.sort();
+    Ok(found)
+}
+
+/// A copy of `workspace` with `candidate` in place of its `src/lib.rs`,
+/// or of the files it shares with a candidate directory.
+fn install(workspace: &Path, dir: &Path, candidate: &Path) -> io::Result<()> {
+    copy_sources(workspace, dir)?;
+    if candidate.is_dir() {
+        copy_sources(candidate, &dir.join("src"))
+    } else {
+        fs::copy(candidate, dir.join("src/lib.rs")).map(|_| ())
+    }
+}
+
+fn evaluate(workspace: &Path, name: &str, candidate: &Path, runs: usize, opts: &RunOptions) -> Outcome {
+    let t0 = Instant::now();
+    let dir = workspace_for(workspace, name);
+    let mut outcome = Outcome {
+        name: name.to_string(),
+        error: None,
+        passed: 0,
+        tests: 0,
+        score: 0.0,
+        duration: Duration::ZERO,
+    };
+    let matrix = install(workspace, &dir, candidate)
+        .map_err(|e| format!("{}: {}", dir.display(), e))
+        .and_then(|()| build_tests(&dir, opts).map_err(|e| e.to_string()))
+        .and_then(|_| {
+            let mut matrix: BTreeMap<String, Vec<bool>> = BTreeMap::new();
+            for _ in 0..runs {
+                for (test, passed) in run_cargo_test_once(&dir, opts)? {
+                    matrix.entry(test).or_default().push(passed);
+                }
+            }
+            Ok(matrix)
+        });
+    match matrix {
+        Ok(matrix) => {
+            let results: Vec<bool> = matrix.values().flatten().copied().collect();
+            outcome.tests = matrix.len();
+            outcome.passed = matrix.values().filter(|r| r.iter().all(|&p| p)).count();
+            if !results.is_empty() {
+                outcome.score =
+                    100.0 * results.iter().filter(|&&p| p).count() as f32 / results.len() as f32;
+            }
+        }
+        Err(e) => outcome.error = Some(e),
+    }
+    outcome.duration = t0.elapsed();
+    outcome
+}
+
+/// Evaluate every candidate in `dir` with `runs` runs each and print
+/// them ranked: compiling ones first, then by score, then by speed.
+pub fn compare(workspace: &Path, dir: &Path, runs: usize, base: &RunOptions) -> Result<(), String> {
+    let candidates = list(dir)?;
+    let mut env = base.env.to_vec();
+    if !env.iter().any(|(k, _)| k == "CARGO_TARGET_DIR") {
+        let target = workspace.join("target");
+        let target = fs::canonicalize(&target).map_err(|e| format!("{}: {}", target.display(), e))?;
+        env.push(("CARGO_TARGET_DIR".to_string(), target.display().to_string()));
+    }
+    let opts = RunOptions { env: &env, ..*base };
+
+    let mut outcomes = Vec::new();
+    for (name, path) in &candidates {
+        println!("{}Candidate {}{}", BLUE, name, RESET);
+        outcomes.push(evaluate(workspace, name, path, runs, &opts));
+    }
+    outcomes.sort_by(|a, b| {
+        a.error.is_some().cmp(&b.error.is_some())
+            .then(b.score.total_cmp(&a.score))
+            .then(a.duration.cmp(&b.duration))
+    });
+
+    println!("\n{}Candidates:{}", BOLD, RESET);
+    println!("{:>4} | {:<30} | {:<8} | {:>9} | {:>6} | {:>8}",
+             "Rank", "Candidate", "Build", "Passed", "Score", "Time");
+    println!("{:-<5}+{:-<32}+{:-<10}+{:-<11}+{:-<8}+{:-<9}", "", "", "", "", "", "");
+    for (rank, o) in outcomes.iter().enumerate() {
+        let (build, col) = if o.error.is_some() { ("FAIL", RED) } else { ("ok", GREEN) };
+        println!("{:>4} | {:<30} | {}{:<8}{} | {:>9} | {:>5.1}% | {:>7.1}s",
+                 rank + 1, o.name, col, build, RESET,
+                 format!("{}/{}", o.passed, o.tests), o.score, o.duration.as_secs_f32());
+    }
+    for o in &outcomes {
+        if let Some(e) = &o.error {
+            println!("{}{} did not build:{} {}", RED, o.name, RESET, e);
+        }
+    }
+    Ok(())
+}
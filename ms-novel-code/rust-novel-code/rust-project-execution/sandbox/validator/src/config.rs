@@ -0,0 +1,131 @@
+//! `--config <validator.toml>`: which checker stages run, and in what
+//! order, for every task, with overrides per task (by notebook stem):
+//!
+//! ```toml
+//! [stages]
+//! order = ["msrv", "quality"]   # these first in their phase, the rest as usual
+//! disable = ["hermeticity"]
+//!
+//! [tasks.003.stages]            # 003.ipynb only
+//! enable = ["hermeticity"]
+//! disable = ["coverage"]
+//! ```
+//!
+//! With the `external-stages` feature, `[[stages.external]]` entries (in
+//! either table) add third-party checks: a command run in the workspace
+//! whose exit status is the verdict.
+
+use serde::Deserialize;
+use std::{collections::BTreeMap, fs, path::Path};
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// The stages of every task.
+    pub stages: StagePolicy,
+    /// Overrides per notebook stem.
+    pub tasks: BTreeMap<String, TaskConfig>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct TaskConfig {
+    pub stages: StagePolicy,
+}
+
+#[derive(Deserialize, Default, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct StagePolicy {
+    /// Stages to run before the others of their phase, in this order.
+    pub order: Vec<String>,
+    /// Stages not to run.
+    pub disable: Vec<String>,
+    /// Stages to run after all, though disabled for every task.
+    pub enable: Vec<String>,
+    /// Third-party stages; need the `external-stages` feature.
+    pub external: Vec<ExternalStage>,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ExternalStage {
+    pub name: String,
+    /// Program and arguments, run in the workspace; exit status 0 passes.
+    #[cfg_attr(not(feature = "external-stages"), allow(dead_code))]
+    pub command: Vec<String>,
+    /// `build` or `tests`: the phase after which it runs.
+    #[serde(default = "after_tests")]
+    pub after: String,
+}
+
+fn after_tests() -> String {
+    "tests".into()
+}
+
+impl Config {
+    /// The config at `path`, or the defaults when there is no such file.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let raw = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        toml::from_str(&raw).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// The stages of the task `stem`: its own `order` if it has one, the
+    /// shared `disable` less its `enable` plus its own `disable`, and both
+    /// sets of external stages.
+    pub fn stages_for(&self, stem: &str) -> StagePolicy {
+        let shared = &self.stages;
+        let Some(task) = self.tasks.get(stem).map(|t| &t.stages) else { return shared.clone() };
+        let order = if task.order.is_empty() { &shared.order } else { &task.order };
+        let disable = shared
+            .disable
+            .iter()
+            .filter(|s| !task.enable.contains(s))
+            .chain(&task.disable)
+            .cloned()
+            .collect();
+        StagePolicy {
+            order: order.clone(),
+            disable,
+            // kept for `stages::pipeline` to check the names
+            enable: task.enable.clone(),
+            external: shared.external.iter().chain(&task.external).cloned().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn task_tables_override_the_shared_stages() {
+        let config: Config = toml::from_str(
+            r#"
+            [stages]
+            order = ["msrv"]
+            disable = ["hermeticity", "lint"]
+
+            [tasks.003.stages]
+            enable = ["hermeticity"]
+            disable = ["coverage"]
+
+            [tasks.004.stages]
+            order = ["quality"]
+            "#,
+        )
+        .unwrap();
+        let own = config.stages_for("003");
+        assert_eq!((own.order, own.disable), (vec!["msrv".to_string()], vec!["lint".into(), "coverage".into()]));
+        assert_eq!(config.stages_for("004").order, ["quality"]);
+        assert_eq!(config.stages_for("005").disable, ["hermeticity", "lint"]);
+    }
+
+    #[test]
+    fn unknown_keys_are_errors() {
+        assert!(toml::from_str::<Config>("[stage]\ndisable = []").is_err());
+        assert!(toml::from_str::<Config>("[tasks.003]\ndisable = []").is_err());
+    }
+}
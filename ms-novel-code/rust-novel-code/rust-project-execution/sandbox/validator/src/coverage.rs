@@ -0,0 +1,226 @@
+//! `--coverage`: how much of the library the integration tests exercise.
+//! The tests in `tests/` are built again with `-C instrument-coverage`
+//! (and branch counters, unlocked by `RUSTC_BOOTSTRAP`) into their own
+//! target directory, run once, and their profiles summarized by
+//! `llvm-cov` from rustup's `llvm-tools` component. Every library source
+//! counts (`src/**` but `main.rs` and `src/bin/`), not the tests' own
+//! code. `--min-coverage` fails the validation when the line coverage
+//! is lower.
+
+use crate::colors::*;
+use crate::{interrupt, sandbox, RunOptions};
+use serde_json::Value;
+use std::{
+    fmt, fs,
+    io::Read,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    thread,
+    time::Duration,
+};
+use wait_timeout::ChildExt;
+
+/// Covered and total lines or branches.
+#[derive(Clone, Copy, Default)]
+struct Ratio {
+    covered: u64,
+    count: u64,
+}
+
+impl Ratio {
+    fn of(summary: &Value) -> Self {
+        Ratio {
+            covered: summary["covered"].as_u64().unwrap_or(0),
+            count: summary["count"].as_u64().unwrap_or(0),
+        }
+    }
+
+    /// Nothing to cover counts as fully covered.
+    fn percent(self) -> f64 {
+        if self.count == 0 {
+            100.0
+        } else {
+            self.covered as f64 * 100.0 / self.count as f64
+        }
+    }
+
+    fn add(&mut self, other: Ratio) {
+        self.covered += other.covered;
+        self.count += other.count;
+    }
+}
+
+impl fmt::Display for Ratio {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.count == 0 {
+            return f.write_str("     -");
+        }
+        write!(f, "{:5.1}% ({}/{})", self.percent(), self.covered, self.count)
+    }
+}
+
+/// `llvm-profdata` and `llvm-cov` of the toolchain `opts` builds with.
+struct Tools {
+    profdata: PathBuf,
+    cov: PathBuf,
+}
+
+/// Find the `llvm-tools` matching the toolchain; rustc's profiles only
+/// read with the LLVM that wrote them.
+fn tools(opts: &RunOptions) -> Result<Tools, String> {
+    let query = |args: &[&str]| {
+        opts.command("rustc")
+            .args(args)
+            .output()
+            .map(|o| String::from_utf8_lossy(&o.stdout).into_owned())
+            .map_err(|e| format!("rustc: {}", e))
+    };
+    let sysroot = query(&["--print", "sysroot"])?;
+    let host = query(&["-vV"])?;
+    let host = host.lines().find_map(|l| l.strip_prefix("host: ")).ok_or("rustc -vV names no host")?;
+    let bin = Path::new(sysroot.trim()).join("lib/rustlib").join(host).join("bin");
+    let tools = Tools { profdata: bin.join("llvm-profdata"), cov: bin.join("llvm-cov") };
+    if !tools.profdata.is_file() || !tools.cov.is_file() {
+        return Err("--coverage needs the toolchain's LLVM tools (`rustup component add llvm-tools`)".to_string());
+    }
+    Ok(tools)
+}
+
+/// Check that the tools are installed, before anything is built.
+pub fn probe(opts: &RunOptions) -> Result<(), String> {
+    tools(opts).map(|_| ())
+}
+
+/// The value `opts` gives `key`, if any.
+fn var<'a>(opts: &'a RunOptions, key: &str) -> Option<&'a str> {
+    opts.env.iter().rev().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+}
+
+/// `cargo test` of the integration tests, instrumented, up to `--`.
+fn cargo(opts: &RunOptions, target: &Path, profiles: &Path) -> Command {
+    let flags = var(opts, "RUSTFLAGS").map_or(String::new(), |f| format!("{} ", f));
+    let mut cmd = opts.command("cargo");
+    cmd.args(["test", "--test", "*", "--no-fail-fast", "--color=never"])
+        .env("RUSTFLAGS", format!("{}-C instrument-coverage -Z coverage-options=branch", flags))
+        .env("RUSTC_BOOTSTRAP", "1")
+        .env("CARGO_TARGET_DIR", target)
+        .env("LLVM_PROFILE_FILE", profiles.join("%p-%m.profraw"));
+    cmd
+}
+
+/// Run `cmd` to completion or `timeout`; returns its stdout, or stderr's
+/// end as the error when it fails and `must_succeed`.
+fn run(cmd: &mut Command, timeout: u64, must_succeed: bool) -> Result<String, String> {
+    let mut child = interrupt::spawn(cmd.stdout(Stdio::piped()).stderr(Stdio::piped())).map_err(|e| e.to_string())?;
+    let drain = |pipe: Option<Box<dyn Read + Send>>| {
+        thread::spawn(move || {
+            let mut text = String::new();
+            if let Some(mut pipe) = pipe {
+                let _ = pipe.read_to_string(&mut text);
+            }
+            text
+        })
+    };
+    let out = drain(child.stdout.take().map(|p| Box::new(p) as Box<dyn Read + Send>));
+    let err = drain(child.stderr.take().map(|p| Box::new(p) as Box<dyn Read + Send>));
+    let status = child.wait_timeout(Duration::from_secs(timeout)).map_err(|e| e.to_string())?;
+    if status.is_none() {
+        interrupt::kill(&child);
+        let _ = child.wait();
+    }
+    interrupt::reap(&child);
+    let (stdout, stderr) = (out.join().unwrap_or_default(), err.join().unwrap_or_default());
+    match status {
+        None => Err(format!("timeout reached ({}s)", timeout)),
+        Some(status) if must_succeed && !status.success() => {
+            let lines: Vec<&str> = stderr.trim().lines().collect();
+            Err(lines[lines.len().saturating_sub(20)..].join("\n"))
+        }
+        Some(_) => Ok(stdout),
+    }
+}
+
+/// Whether `file` is library source of `workspace`.
+fn is_library(file: &Path, workspace: &Path) -> bool {
+    let Ok(rel) = file.strip_prefix(workspace.join("src")) else { return false };
+    rel != Path::new("main.rs") && !rel.starts_with("bin")
+}
+
+/// Run the integration tests instrumented, print each library file's
+/// coverage and return whether the line coverage reaches `min` percent.
+pub fn check(workspace: &Path, min: f64, opts: &RunOptions) -> Result<bool, String> {
+    println!("\n{}Coverage{} of the library by the integration tests", BOLD, RESET);
+    let tools = tools(opts)?;
+    let workspace = fs::canonicalize(workspace).map_err(|e| format!("{}: {}", workspace.display(), e))?;
+    let target = var(opts, "CARGO_TARGET_DIR").map_or_else(|| workspace.join("target"), PathBuf::from).join("coverage");
+    let profiles = target.join("profiles");
+    let _ = fs::remove_dir_all(&profiles);
+
+    let build_opts = RunOptions { limits: sandbox::Limits::default(), ..*opts };
+    let built = run(
+        cargo(&build_opts, &target, &profiles).args(["--no-run", "--message-format=json"]).current_dir(&workspace),
+        opts.build_timeout,
+        true,
+    )
+    .map_err(|e| format!("instrumented build failed:\n{}", e))?;
+    let objects: Vec<String> = built
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .filter(|msg| msg["reason"] == "compiler-artifact" && msg["profile"]["test"] == true)
+        .filter_map(|msg| msg["executable"].as_str().map(str::to_string))
+        .collect();
+    if objects.is_empty() {
+        return Err("no integration test to measure".to_string());
+    }
+    // failing tests are the matrix's business; what they ran still counts
+    run(cargo(opts, &target, &profiles).current_dir(&workspace), opts.timeout, false)?;
+
+    let raw: Vec<PathBuf> = fs::read_dir(&profiles)
+        .map_err(|e| format!("{}: {}", profiles.display(), e))?
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|e| e == "profraw"))
+        .collect();
+    let merged = target.join("coverage.profdata");
+    run(
+        Command::new(&tools.profdata).args(["merge", "-sparse"]).args(&raw).arg("-o").arg(&merged),
+        opts.timeout,
+        true,
+    )
+    .map_err(|e| format!("llvm-profdata: {}", e))?;
+    let mut export = Command::new(&tools.cov);
+    export.args(["export", "-summary-only", "-instr-profile"]).arg(&merged);
+    for object in &objects {
+        export.args(["-object", object]);
+    }
+    let summary = run(&mut export, opts.timeout, true).map_err(|e| format!("llvm-cov: {}", e))?;
+    let summary: Value = serde_json::from_str(&summary).map_err(|e| format!("llvm-cov: {}", e))?;
+
+    let mut files: Vec<(String, Ratio, Ratio)> = summary["data"][0]["files"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|f| {
+            let path = Path::new(f["filename"].as_str()?);
+            is_library(path, &workspace).then(|| {
+                let rel = path.strip_prefix(&workspace).unwrap_or(path).display().to_string();
+                (rel, Ratio::of(&f["summary"]["lines"]), Ratio::of(&f["summary"]["branches"]))
+            })
+        })
+        .collect();
+    files.sort_by(|a, b| a.0.cmp(&b.0));
+    let (mut lines, mut branches) = (Ratio::default(), Ratio::default());
+    let width = files.iter().map(|f| f.0.len()).max().unwrap_or(0).max(5);
+    println!("  {:width$}  {:<20}  branches", "file", "lines", width = width);
+    for (file, l, b) in &files {
+        println!("  {:width$}  {:<20}  {}", file, l.to_string(), b, width = width);
+        lines.add(*l);
+        branches.add(*b);
+    }
+    println!("  {:width$}  {:<20}  {}", "total", lines.to_string(), branches, width = width);
+    let ok = lines.percent() >= min;
+    if !ok {
+        println!("  {}line coverage {:.1}% is below --min-coverage {}%{}", RED, lines.percent(), min, RESET);
+    }
+    Ok(ok)
+}
@@ -0,0 +1,217 @@
+//! Tests that kill their test binary (SIGSEGV, SIGABRT, …) instead of
+//! failing an assertion. libtest then reports nothing for them or for the
+//! tests that had not run yet, and cargo only says which signal ended the
+//! binary. Each test left without an outcome is run again on its own with
+//! `RUST_BACKTRACE=full`: that gives the others their outcomes and pins
+//! the crash on the right test, with its output, a gdb backtrace when gdb
+//! is installed and, with `--core-dumps`, the core file.
+
+use crate::sandbox::Violation;
+use crate::{interrupt, RunOptions};
+use std::{
+    collections::HashMap,
+    fs,
+    io::Read,
+    os::unix::process::{CommandExt, ExitStatusExt},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    thread,
+    time::Duration,
+};
+use wait_timeout::ChildExt;
+
+/// Lines of a crashed test's output kept for the report.
+const MAX_LINES: usize = 60;
+
+pub struct Crash {
+    pub test: String,
+    /// Like `SIGSEGV (11)`.
+    pub signal: String,
+    /// Output of the solo run, or gdb's backtrace when available.
+    pub details: String,
+    pub core: Option<PathBuf>,
+}
+
+/// The test binary cargo reports as killed by a signal, from its
+/// `process didn't exit successfully: `…` (signal: 11, SIGSEGV: …)`.
+pub fn crashed_binary(stderr: &str) -> Option<PathBuf> {
+    let line = stderr
+        .lines()
+        .find(|l| l.contains("process didn't exit successfully") && l.contains("(signal: "))?;
+    let command = line.split('`').nth(1)?;
+    Some(PathBuf::from(command.split_whitespace().next()?))
+}
+
+fn signal_name(signal: i32) -> String {
+    let name = match signal {
+        libc::SIGSEGV => "SIGSEGV",
+        libc::SIGABRT => "SIGABRT",
+        libc::SIGBUS => "SIGBUS",
+        libc::SIGILL => "SIGILL",
+        libc::SIGFPE => "SIGFPE",
+        libc::SIGKILL => "SIGKILL",
+        libc::SIGTRAP => "SIGTRAP",
+        libc::SIGXCPU => "SIGXCPU",
+        _ => "signal",
+    };
+    format!("{} ({})", name, signal)
+}
+
+/// Every test `exe` holds.
+fn list(exe: &Path, workspace: &Path, opts: &RunOptions) -> Vec<String> {
+    opts.command(exe)
+        .args(["--list", "--format", "terse"])
+        .current_dir(workspace)
+        .output()
+        .map(|out| {
+            String::from_utf8_lossy(&out.stdout)
+                .lines()
+                .filter_map(|l| l.strip_suffix(": test"))
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// How a single process ended.
+struct Ended {
+    success: bool,
+    /// Its stdout, then its stderr.
+    output: String,
+    signal: Option<i32>,
+    pid: u32,
+}
+
+/// Run `cmd` to completion or `timeout`.
+fn run(cmd: &mut Command, timeout: u64) -> Result<Ended, String> {
+    let mut child = interrupt::spawn(cmd.stdout(Stdio::piped()).stderr(Stdio::piped()))
+        .map_err(|e| e.to_string())?;
+    let pid = child.id();
+    let drain = |pipe: Option<Box<dyn Read + Send>>| {
+        thread::spawn(move || {
+            let mut text = String::new();
+            if let Some(mut pipe) = pipe {
+                let _ = pipe.read_to_string(&mut text);
+            }
+            text
+        })
+    };
+    let out = drain(child.stdout.take().map(|p| Box::new(p) as Box<dyn Read + Send>));
+    let err = drain(child.stderr.take().map(|p| Box::new(p) as Box<dyn Read + Send>));
+    let status = child.wait_timeout(Duration::from_secs(timeout)).map_err(|e| e.to_string())?;
+    if status.is_none() {
+        interrupt::kill(&child);
+    }
+    interrupt::reap(&child);
+    let status = match status {
+        Some(s) => s,
+        None => child.wait().map_err(|e| e.to_string())?,
+    };
+    let output = out.join().unwrap_or_default() + &err.join().unwrap_or_default();
+    Ok(Ended { success: status.success(), output, signal: status.signal(), pid })
+}
+
+/// Where the kernel put the core of process `pid` run in `workspace`, if
+/// it wrote one there. The workspace is fresh, so a core in it is from
+/// this validation (the kernel does not replace an existing one).
+fn core_file(workspace: &Path, pid: u32) -> Option<PathBuf> {
+    let pattern = fs::read_to_string("/proc/sys/kernel/core_pattern").ok()?;
+    let pattern = pattern.trim();
+    if pattern.starts_with('|') {
+        // handed to a program such as systemd-coredump
+        return None;
+    }
+    let name = pattern.replace("%p", &pid.to_string()).replace("%%", "%");
+    let with_pid = fs::read_to_string("/proc/sys/kernel/core_uses_pid").is_ok_and(|v| v.trim() == "1");
+    let mut candidates = vec![workspace.join(&name)];
+    if with_pid {
+        candidates.insert(0, workspace.join(format!("{}.{}", name, pid)));
+    }
+    candidates.into_iter().find(|p| p.is_file())
+}
+
+fn gdb_available() -> bool {
+    Command::new("gdb").arg("--version").stdout(Stdio::null()).stderr(Stdio::null())
+        .status().is_ok_and(|s| s.success())
+}
+
+/// The last `MAX_LINES` lines of `text`.
+fn tail(text: &str) -> String {
+    let lines: Vec<&str> = text.trim().lines().collect();
+    lines[lines.len().saturating_sub(MAX_LINES)..].join("\n")
+}
+
+/// What running the tests of a crashed binary alone found.
+pub struct Investigation {
+    /// Outcomes of the tests that had none.
+    pub found: HashMap<String, bool>,
+    pub crashes: Vec<Crash>,
+    /// Those tests that ran into a sandbox limit.
+    pub violations: Vec<(String, Violation)>,
+}
+
+/// Run each test of the crashed binary `exe` that has no outcome in
+/// `outcomes` alone.
+pub fn investigate(
+    exe: &Path,
+    outcomes: &HashMap<String, bool>,
+    workspace: &Path,
+    opts: &RunOptions,
+) -> Result<Investigation, String> {
+    let manifest_dir = fs::canonicalize(workspace).map_err(|e| e.to_string())?;
+    let solo = |test: &str| {
+        let mut cmd = opts.command(exe);
+        cmd.args([test, "--exact", "--test-threads=1", "--nocapture"])
+            .current_dir(workspace)
+            .env("CARGO_MANIFEST_DIR", &manifest_dir)
+            .env("RUST_BACKTRACE", "full");
+        cmd
+    };
+    let mut found = HashMap::new();
+    let mut crashes = Vec::new();
+    let mut violations = Vec::new();
+    for test in list(exe, workspace, opts).into_iter().filter(|t| !outcomes.contains_key(t)) {
+        let mut cmd = solo(&test);
+        if opts.core_dumps {
+            // SAFETY: setrlimit is async-signal-safe
+            unsafe {
+                cmd.pre_exec(|| {
+                    let unlimited = libc::rlimit { rlim_cur: libc::RLIM_INFINITY, rlim_max: libc::RLIM_INFINITY };
+                    libc::setrlimit(libc::RLIMIT_CORE, &unlimited);
+                    Ok(())
+                });
+            }
+        }
+        let ended = run(&mut cmd, opts.timeout)?;
+        found.insert(test.clone(), ended.success);
+        if !ended.success {
+            let signal = ended.signal.map(signal_name);
+            if let Some(v) = opts.limits.violation(signal.as_deref(), &ended.output) {
+                violations.push((test.clone(), v));
+            }
+        }
+        let Some(signal) = ended.signal else { continue };
+        let mut details = tail(&ended.output);
+        if gdb_available() {
+            let mut gdb = opts.command("gdb");
+            gdb.args(["-batch", "-ex", "run", "-ex", "bt"])
+                .arg("--args")
+                .arg(exe)
+                .args([&test, "--exact", "--test-threads=1"])
+                .current_dir(workspace)
+                .env("CARGO_MANIFEST_DIR", &manifest_dir);
+            if let Ok(traced) = run(&mut gdb, opts.timeout) {
+                details = tail(&traced.output);
+            }
+        } else {
+            details += "\n(install gdb for a symbolized backtrace)";
+        }
+        crashes.push(Crash {
+            test,
+            signal: signal_name(signal),
+            details,
+            core: opts.core_dumps.then(|| core_file(workspace, ended.pid)).flatten(),
+        });
+    }
+    Ok(Investigation { found, crashes, violations })
+}
@@ -0,0 +1,213 @@
+//! Dependencies of the extracted crate. A `# deps` cell holds TOML that
+//! is merged into the generated manifest: bare `name = "1.2"` entries are
+//! `[dependencies]`, and `[dev-dependencies]`/`[build-dependencies]`
+//! tables may follow. (A `# file: Cargo.toml` cell may also replace the
+//! manifest outright.) After a successful build, every declared
+//! dependency whose crate name never appears in the code that can use it
+//! is flagged, to keep manifests minimal.
+
+use crate::source::{strip_comments_and_strings, words};
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Dependency tables and the code each one is visible to, relative to
+/// the workspace.
+const TABLES: [(&str, &[&str]); 3] = [
+    ("dependencies", &["src", "tests", "benches", "examples", "build.rs"]),
+    ("dev-dependencies", &["tests", "benches", "examples", "src"]),
+    ("build-dependencies", &["build.rs"]),
+];
+
+pub fn rust_files(path: &Path, out: &mut Vec<PathBuf>) {
+    if path.is_dir() {
+        for entry in fs::read_dir(path).into_iter().flatten().flatten() {
+            rust_files(&entry.path(), out);
+        }
+    } else if path.extension().is_some_and(|e| e == "rs") {
+        out.push(path.to_path_buf());
+    }
+}
+
+/// Whether `req` is a Cargo version requirement: comma-separated
+/// comparators like `1`, `^0.8.5`, `>=1.2, <2`, `~1.4` or `1.*`.
+fn valid_requirement(req: &str) -> bool {
+    let number = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    req.split(',').all(|comparator| {
+        let c = comparator.trim();
+        let c = ["<=", ">=", "^", "~", "=", "<", ">"]
+            .iter()
+            .find_map(|op| c.strip_prefix(op))
+            .unwrap_or(c)
+            .trim();
+        if c == "*" {
+            return true;
+        }
+        let (core, pre) = match c.split_once(['-', '+']) {
+            Some((core, pre)) => (core, Some(pre)),
+            None => (c, None),
+        };
+        let parts: Vec<&str> = core.split('.').collect();
+        let wildcard = |p: &&str| matches!(*p, "*" | "x" | "X");
+        let first_wild = parts.iter().position(wildcard).unwrap_or(parts.len());
+        parts.len() <= 3
+            && first_wild > 0
+            && parts[..first_wild].iter().all(|p| number(p))
+            && parts[first_wild..].iter().all(wildcard)
+            && pre.is_none_or(|p| !p.is_empty() && (parts.len() == 3 && first_wild == 3))
+    })
+}
+
+/// Check one dependency entry of the `# deps` cell.
+fn check_entry(table: &str, name: &str, entry: &toml::Value) -> Result<(), String> {
+    let req = match entry {
+        toml::Value::String(req) => Some(req.as_str()),
+        toml::Value::Table(t) => {
+            if !["version", "path", "git"].iter().any(|k| t.contains_key(*k)) {
+                return Err(format!("`{}.{}` needs a `version`, `path` or `git`", table, name));
+            }
+            match t.get("version") {
+                Some(toml::Value::String(req)) => Some(req.as_str()),
+                Some(other) => return Err(format!("`{}.{}`: version {} is not a string", table, name, other)),
+                None => None,
+            }
+        }
+        other => return Err(format!("`{}.{} = {}` is neither a version nor a table", table, name, other)),
+    };
+    match req {
+        Some(req) if !valid_requirement(req) => {
+            Err(format!("`{}.{}`: `{}` is not a version requirement", table, name, req))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Merge the `# deps` cell `cell` into `manifest`, returning the
+/// dependencies added as `table.name`.
+pub fn merge(manifest: &mut toml::Table, cell: &str) -> Result<Vec<String>, String> {
+    let mut declared: toml::Table = cell.parse().map_err(|e| format!("`# deps`: {}", e))?;
+    // entries before any table header belong to [dependencies]
+    let mut tables = toml::Table::new();
+    for (table, _) in TABLES {
+        if let Some(value) = declared.remove(table) {
+            tables.insert(table.to_string(), value);
+        }
+    }
+    if !declared.is_empty() {
+        let bare = tables.entry("dependencies").or_insert_with(|| toml::Table::new().into());
+        let Some(bare) = bare.as_table_mut() else { return Err("`# deps`: `dependencies` is not a table".into()) };
+        for (name, entry) in declared {
+            if bare.insert(name.clone(), entry).is_some() {
+                return Err(format!("`# deps`: `{}` is declared twice", name));
+            }
+        }
+    }
+    let mut added = Vec::new();
+    for (table, deps) in tables {
+        let deps = deps.as_table().ok_or_else(|| format!("`# deps`: `{}` is not a table", table))?;
+        let into = manifest.entry(table.clone()).or_insert_with(|| toml::Table::new().into());
+        let into = into.as_table_mut().ok_or_else(|| format!("Cargo.toml: `{}` is not a table", table))?;
+        for (name, entry) in deps {
+            check_entry(&table, name, entry).map_err(|e| format!("`# deps`: {}", e))?;
+            if into.insert(name.clone(), entry.clone()).is_some() {
+                return Err(format!("`# deps`: `{}.{}` is already in Cargo.toml", table, name));
+            }
+            added.push(format!("{}.{}", table, name));
+        }
+    }
+    Ok(added)
+}
+
+/// Identifiers used anywhere in `roots` under `workspace`.
+fn identifiers(workspace: &Path, roots: &[&str]) -> HashSet<String> {
+    let mut files = Vec::new();
+    for root in roots {
+        rust_files(&workspace.join(root), &mut files);
+    }
+    files
+        .iter()
+        .filter_map(|f| fs::read_to_string(f).ok())
+        .flat_map(|src| words(&strip_comments_and_strings(&src)).map(str::to_string).collect::<Vec<_>>())
+        .collect()
+}
+
+/// Declared dependencies never referred to, as `table.name`.
+pub fn unused(workspace: &Path) -> Result<Vec<String>, String> {
+    let path = workspace.join("Cargo.toml");
+    let raw = fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let manifest: toml::Table = raw.parse().map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mut unused = Vec::new();
+    for (table, roots) in TABLES {
+        let Some(deps) = manifest.get(table).and_then(|t| t.as_table()) else {
+            continue;
+        };
+        let used = identifiers(workspace, roots);
+        for name in deps.keys() {
+            // the key is what the code calls it, even with `package = …`
+            if !used.contains(&name.replace('-', "_")) {
+                unused.push(format!("{}.{}", table, name));
+            }
+        }
+    }
+    Ok(unused)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn version_requirements() {
+        for req in ["1", "0.8", "^0.8.5", "~1.4", "=1.2.3", ">=1.2, <2", "1.*", "1.2.x", "*", "1.0.0-beta.1", "1.2.3+build"] {
+            assert!(valid_requirement(req), "{}", req);
+        }
+        for req in ["", "latest", "1.2.3.4", "*.1", "1.*.3", "1.2-beta", "1.2.3-", ">=1.2,", "v1"] {
+            assert!(!valid_requirement(req), "{}", req);
+        }
+    }
+
+    #[test]
+    fn entries_need_a_source_and_a_valid_version() {
+        let entry = |s: &str| s.parse::<toml::Table>().unwrap().remove("x").unwrap();
+        assert!(check_entry("dependencies", "x", &entry(r#"x = "1.2""#)).is_ok());
+        assert!(check_entry("dependencies", "x", &entry(r#"x = { path = "../x" }"#)).is_ok());
+        assert!(check_entry("dependencies", "x", &entry(r#"x = { version = "1", features = ["a"] }"#)).is_ok());
+        assert_eq!(
+            check_entry("dependencies", "x", &entry(r#"x = { features = ["a"] }"#)),
+            Err("`dependencies.x` needs a `version`, `path` or `git`".to_string())
+        );
+        assert_eq!(
+            check_entry("dev-dependencies", "x", &entry(r#"x = "newest""#)),
+            Err("`dev-dependencies.x`: `newest` is not a version requirement".to_string())
+        );
+        assert!(check_entry("dependencies", "x", &entry("x = 1")).is_err());
+        assert!(check_entry("dependencies", "x", &entry("x = { version = 1 }")).is_err());
+    }
+
+    #[test]
+    fn merge_puts_bare_entries_in_dependencies() {
+        let mut manifest: toml::Table = "[package]\nname = \"t\"\n[dependencies]\nrand = \"0.8\"\n".parse().unwrap();
+        let cell = "itertools = \"0.12\"\n\n[dev-dependencies]\nproptest = \"1\"\n";
+        let added = merge(&mut manifest, cell).unwrap();
+        assert_eq!(added, ["dependencies.itertools", "dev-dependencies.proptest"]);
+        assert!(manifest["dependencies"].get("rand").is_some());
+        assert!(manifest["dependencies"].get("itertools").is_some());
+        assert!(manifest["dev-dependencies"].get("proptest").is_some());
+    }
+
+    #[test]
+    fn merge_rejects_duplicates_and_bad_cells() {
+        let manifest: toml::Table = "[dependencies]\nrand = \"0.8\"\n".parse().unwrap();
+        let merged = |cell: &str| merge(&mut manifest.clone(), cell);
+        assert_eq!(merged("rand = \"0.9\""), Err("`# deps`: `dependencies.rand` is already in Cargo.toml".to_string()));
+        assert_eq!(
+            merged("serde = \"1\"\n[dependencies]\nserde = \"1\"\n"),
+            Err("`# deps`: `serde` is declared twice".to_string())
+        );
+        assert!(merged("serde = ").is_err());
+        assert!(merged("dev-dependencies = 3").is_err());
+        assert!(merged("serde = \"one\"").is_err());
+    }
+}
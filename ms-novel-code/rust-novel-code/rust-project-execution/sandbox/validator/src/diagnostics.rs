@@ -0,0 +1,88 @@
+//! Compiler diagnostics from cargo's JSON messages, classified by rustc
+//! error code so reports can say what kinds of errors a solution makes.
+
+use serde_json::Value;
+
+/// One `compiler-message` of level `error`.
+pub struct CompileError {
+    pub code: Option<String>,
+    pub message: String,
+    /// Whether the primary span comes from a macro expansion.
+    pub in_macro: bool,
+    /// Workspace-relative file and 1-based line of the primary span.
+    pub location: Option<(String, usize)>,
+    /// The error as rustc prints it, source excerpt included.
+    pub rendered: String,
+}
+
+/// Categories in report order.
+pub const CATEGORIES: [&str; 6] =
+    ["type error", "borrow-check", "missing trait impl", "unresolved import", "macro error", "other"];
+
+impl CompileError {
+    /// Parse one line of `cargo --message-format=json` output, printing
+    /// the rendered form of every diagnostic (as cargo would have) and
+    /// returning it if it is an error.
+    pub fn from_message(line: &str) -> Option<CompileError> {
+        let msg: Value = serde_json::from_str(line).ok()?;
+        if msg["reason"] != "compiler-message" {
+            return None;
+        }
+        let diag = &msg["message"];
+        let rendered = diag["rendered"].as_str().unwrap_or_default();
+        eprint!("{}", rendered);
+        if diag["level"] != "error" {
+            return None;
+        }
+        let primary = diag["spans"]
+            .as_array()
+            .and_then(|spans| spans.iter().find(|s| s["is_primary"] == true));
+        Some(CompileError {
+            code: diag["code"]["code"].as_str().map(str::to_string),
+            message: diag["message"].as_str().unwrap_or_default().to_string(),
+            in_macro: primary.is_some_and(|s| !s["expansion"].is_null()),
+            location: primary.and_then(|s| {
+                Some((s["file_name"].as_str()?.to_string(), s["line_start"].as_u64()? as usize))
+            }),
+            rendered: rendered.to_string(),
+        })
+    }
+
+    pub fn category(&self) -> &'static str {
+        match self.code.as_deref() {
+            Some(
+                "E0308" | "E0061" | "E0106" | "E0282" | "E0283" | "E0560" | "E0604"
+                | "E0605" | "E0606" | "E0607" | "E0609" | "E0610" | "E0614" | "E0618" | "E0620",
+            ) => "type error",
+            Some(
+                "E0373" | "E0381" | "E0382" | "E0384" | "E0499" | "E0502" | "E0503" | "E0505"
+                | "E0506" | "E0507" | "E0515" | "E0596" | "E0597" | "E0621" | "E0716",
+            ) => "borrow-check",
+            Some("E0046" | "E0117" | "E0119" | "E0204" | "E0277" | "E0369" | "E0599" | "E0600") => {
+                "missing trait impl"
+            }
+            Some("E0405" | "E0407" | "E0412" | "E0422" | "E0423" | "E0425" | "E0432" | "E0433"
+                | "E0583") => "unresolved import",
+            _ if self.in_macro || self.message.contains("macro") => "macro error",
+            _ => "other",
+        }
+    }
+}
+
+/// `3 compile errors (type error 2, borrow-check 1)`, or `None` if there
+/// were none.
+pub fn summarize(errors: &[CompileError]) -> Option<String> {
+    // rustc's closing "aborting due to …" is an error without a code
+    let errors: Vec<&CompileError> =
+        errors.iter().filter(|e| !e.message.starts_with("aborting due to")).collect();
+    if errors.is_empty() {
+        return None;
+    }
+    let counts: Vec<String> = CATEGORIES
+        .iter()
+        .map(|c| (c, errors.iter().filter(|e| e.category() == *c).count()))
+        .filter(|(_, n)| *n > 0)
+        .map(|(c, n)| format!("{} {}", c, n))
+        .collect();
+    Some(format!("{} compile error(s) ({})", errors.len(), counts.join(", ")))
+}
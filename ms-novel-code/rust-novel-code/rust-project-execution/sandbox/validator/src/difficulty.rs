@@ -0,0 +1,78 @@
+//! Rough difficulty estimate for a task from what validation measured,
+//! compared against the `difficulty` declared in its manifest. Coverage
+//! would belong here too, but the validator does not measure it.
+
+use crate::source::{strip_comments_and_strings, words};
+use std::{fmt, time::Duration};
+
+/// Labels from easiest to hardest; manifests use the same spellings.
+pub const LEVELS: [&str; 4] = ["easy", "medium", "hard", "expert"];
+
+/// Inputs to the estimate, gathered during one validation.
+pub struct Metrics {
+    pub build_time: Duration,
+    pub tests: usize,
+    /// Non-blank, non-comment lines of the `# lib` section.
+    pub loc: usize,
+    /// Cyclomatic complexity of the `# lib` section, summed over functions.
+    pub complexity: usize,
+}
+
+impl Metrics {
+    /// Measure the `# lib` source; timings and counts come from the runs.
+    pub fn new(lib: &str, build_time: Duration, tests: usize) -> Self {
+        let code = strip_comments_and_strings(lib);
+        Metrics { build_time, tests, loc: count_loc(&code), complexity: complexity(&code) }
+    }
+}
+
+pub struct Estimate {
+    /// 0–10; each metric contributes up to a fixed share.
+    pub score: f32,
+    pub level: &'static str,
+}
+
+impl Estimate {
+    pub fn from_metrics(m: &Metrics) -> Self {
+        let share = |value: f32, per_point: f32, cap: f32| (value / per_point).min(cap);
+        let score = share(m.loc as f32, 100.0, 3.0)
+            + share(m.complexity as f32, 40.0, 3.0)
+            + share(m.build_time.as_secs_f32(), 30.0, 2.0)
+            + share(m.tests as f32, 10.0, 2.0);
+        let level = LEVELS[((score / 2.5) as usize).min(LEVELS.len() - 1)];
+        Estimate { score, level }
+    }
+
+    /// Whether `declared` sits two or more levels away from the estimate;
+    /// unknown labels never diverge.
+    pub fn diverges_from(&self, declared: &str) -> bool {
+        let rank = |l: &str| LEVELS.iter().position(|x| x.eq_ignore_ascii_case(l));
+        match (rank(declared), rank(self.level)) {
+            (Some(a), Some(b)) => a.abs_diff(b) >= 2,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Estimate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ({:.1}/10)", self.level, self.score)
+    }
+}
+
+fn count_loc(code: &str) -> usize {
+    code.lines().filter(|l| !l.trim().is_empty()).count()
+}
+
+/// McCabe-style count: one per function plus one per branch point.
+fn complexity(code: &str) -> usize {
+    let branches = words(code)
+        .filter(|w| matches!(*w, "fn" | "if" | "while" | "for" | "loop"))
+        .count();
+    let operators = code.matches("&&").count() + code.matches("||").count();
+    // every match arm beyond the first is a branch; count them all and
+    // take one back per `match`
+    let arms = code.matches("=>").count();
+    let matches = words(code).filter(|w| *w == "match").count();
+    branches + operators + arms.saturating_sub(matches)
+}
@@ -0,0 +1,126 @@
+//! `--digest <path.json>`: a compact account of what failed, meant to be
+//! handed back to a model in a repair loop. Compile errors come with
+//! rustc's rendering of the offending lines; test failures with the
+//! panic message and the test's source. Every text field is trimmed so
+//! the whole digest stays within a token budget.
+
+use crate::diagnostics::CompileError;
+use crate::redact::Redactor;
+use crate::source::fn_source;
+use serde_json::{json, Value};
+use std::{collections::HashMap, fs, path::Path};
+
+/// Rough characters per token for code and compiler output.
+const CHARS_PER_TOKEN: usize = 4;
+
+/// `text` cut to `max` characters at a line boundary where possible.
+fn trim(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    let cut: String = text.chars().take(max).collect();
+    let cut = cut.rfind('\n').filter(|&i| i > max / 2).map_or(cut.as_str(), |i| &cut[..i]);
+    format!("{}\n…[truncated]", cut)
+}
+
+/// Captured output of each failing test in libtest's stdout, by name,
+/// without backtraces or the hint to enable them.
+pub fn failure_output(stdout: &str) -> HashMap<String, String> {
+    let mut out: HashMap<String, String> = HashMap::new();
+    let mut current: Option<String> = None;
+    for line in stdout.lines() {
+        if let Some(name) = line.strip_prefix("---- ").and_then(|l| l.strip_suffix(" stdout ----")) {
+            current = Some(name.to_string());
+            continue;
+        }
+        if line.starts_with("failures:") || line.starts_with("test result:") || line == "stack backtrace:" {
+            current = None;
+        }
+        if let Some(name) = &current {
+            if !line.starts_with("note: run with `RUST_BACKTRACE") {
+                let text = out.entry(name.clone()).or_default();
+                text.push_str(line);
+                text.push('\n');
+            }
+        }
+    }
+    for text in out.values_mut() {
+        *text = text.trim().to_string();
+    }
+    out
+}
+
+fn write(
+    path: &Path,
+    notebook: &Path,
+    budget: usize,
+    key: &str,
+    entries: Vec<Value>,
+    redact: &Redactor,
+) -> Result<(), String> {
+    let digest = json!({
+        "notebook": notebook.display().to_string(),
+        "budget_tokens": budget,
+        key: entries,
+    });
+    let text = serde_json::to_string_pretty(&digest).map_err(|e| e.to_string())?;
+    fs::write(path, redact.apply(&text) + "\n").map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Digest of a failed build.
+pub fn write_build(
+    path: &Path,
+    notebook: &Path,
+    budget: usize,
+    errors: &[CompileError],
+    redact: &Redactor,
+) -> Result<(), String> {
+    let errors: Vec<&CompileError> =
+        errors.iter().filter(|e| !e.message.starts_with("aborting due to")).collect();
+    let share = budget * CHARS_PER_TOKEN / errors.len().max(1);
+    let entries = errors
+        .iter()
+        .map(|e| {
+            json!({
+                "code": e.code,
+                "category": e.category(),
+                "location": e.location.as_ref().map(|(file, line)| format!("{}:{}", file, line)),
+                "error": trim(e.rendered.trim_end(), share),
+            })
+        })
+        .collect();
+    write(path, notebook, budget, "compile_errors", entries, redact)
+}
+
+/// Digest of the tests that failed at least once. `matrix` holds every
+/// test's results, `outputs` the last captured output of each failure and
+/// `tests_src` the unstripped test file.
+pub fn write_tests(
+    path: &Path,
+    notebook: &Path,
+    budget: usize,
+    matrix: &HashMap<String, Vec<bool>>,
+    outputs: &HashMap<String, String>,
+    tests_src: &str,
+    redact: &Redactor,
+) -> Result<(), String> {
+    let mut failed: Vec<(&String, &Vec<bool>)> =
+        matrix.iter().filter(|(_, runs)| runs.contains(&false)).collect();
+    failed.sort();
+    // each failure's share goes two parts message, three parts source
+    let share = budget * CHARS_PER_TOKEN / failed.len().max(1);
+    let entries = failed
+        .into_iter()
+        .map(|(name, runs)| {
+            let short = name.rsplit("::").next().unwrap_or(name);
+            json!({
+                "test": name,
+                "failed_runs": runs.iter().filter(|&&r| !r).count(),
+                "runs": runs.len(),
+                "message": trim(outputs.get(name).map_or("", String::as_str), share * 2 / 5),
+                "source": fn_source(tests_src, short).map(|s| trim(&s, share * 3 / 5)),
+            })
+        })
+        .collect();
+    write(path, notebook, budget, "test_failures", entries, redact)
+}
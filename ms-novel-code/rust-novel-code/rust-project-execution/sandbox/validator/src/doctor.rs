@@ -0,0 +1,212 @@
+//! `validator doctor`: check that the machine can validate notebooks
+//! before a run fails halfway with a bare "No such file or directory".
+//! Tools, toolchains and components are probed by running them; the
+//! workspace roots must be writable and have room; a throwaway crate is
+//! built as a smoke test. `--install` has rustup add what is missing.
+
+use crate::colors::*;
+use std::{
+    ffi::CString,
+    fs,
+    os::unix::ffi::OsStrExt,
+    path::Path,
+    process::{Command, Stdio},
+    time::Instant,
+};
+
+/// Free space below which a workspace root is flagged, in MiB.
+const MIN_FREE_MB: u64 = 2048;
+
+/// How an optional tool is installed when missing.
+enum Source {
+    /// `rustup component add <name>`.
+    Component(&'static str),
+    /// A crate to `cargo install`; never installed automatically.
+    Crate(&'static str),
+}
+
+/// Optional tools `--tool` can ask for, with the cargo subcommand that
+/// probes each.
+const TOOLS: [(&str, &str, Source); 5] = [
+    ("clippy", "clippy", Source::Component("clippy")),
+    ("rustfmt", "fmt", Source::Component("rustfmt")),
+    ("miri", "miri", Source::Component("miri")),
+    ("llvm-cov", "llvm-cov", Source::Crate("cargo-llvm-cov")),
+    ("nextest", "nextest", Source::Crate("cargo-nextest")),
+];
+
+/// Names `--tool` accepts.
+pub const TOOL_NAMES: [&str; 5] = ["clippy", "rustfmt", "miri", "llvm-cov", "nextest"];
+
+struct Doctor {
+    problems: usize,
+}
+
+impl Doctor {
+    fn ok(&self, what: &str, detail: &str) {
+        println!("  {}ok{}      {} {}", GREEN, RESET, what, detail);
+    }
+
+    fn fail(&mut self, what: &str, detail: &str) {
+        self.problems += 1;
+        println!("  {}missing{} {} {}", RED, RESET, what, detail);
+    }
+
+    fn note(&self, what: &str, detail: &str) {
+        println!("  {}note{}    {} {}", BLUE, RESET, what, detail);
+    }
+}
+
+/// First line of `program args`' stdout if it ran and succeeded.
+fn probe(program: &str, args: &[&str], toolchain: Option<&str>) -> Option<String> {
+    let mut cmd = Command::new(program);
+    if let Some(tc) = toolchain {
+        cmd.env("RUSTUP_TOOLCHAIN", tc);
+    }
+    let out = cmd.args(args).stderr(Stdio::null()).output().ok()?;
+    out.status.success().then(|| String::from_utf8_lossy(&out.stdout).lines().next().unwrap_or("").to_string())
+}
+
+fn rustup(args: &[&str]) -> bool {
+    println!("  {}rustup {}{}", BLUE, args.join(" "), RESET);
+    Command::new("rustup").args(args).status().is_ok_and(|s| s.success())
+}
+
+/// Free space on the filesystem holding `path`, in MiB.
+fn free_mb(path: &Path) -> Option<u64> {
+    let c = CString::new(path.as_os_str().as_bytes()).ok()?;
+    // SAFETY: `c` is a valid NUL-terminated path and `stat` a plain out-param
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(c.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    Some(stat.f_bavail as u64 * stat.f_frsize as u64 / (1024 * 1024))
+}
+
+/// Whether `root` can hold workspaces at all.
+fn check_root(doc: &mut Doctor, root: &Path) -> bool {
+    let what = root.display().to_string();
+    let probe = root.join(".doctor-probe");
+    let writable = fs::create_dir_all(root).and_then(|()| fs::write(&probe, b"")).is_ok();
+    let _ = fs::remove_file(&probe);
+    if !writable {
+        doc.fail(&what, "is not writable");
+        return false;
+    }
+    match free_mb(root) {
+        Some(mb) if mb < MIN_FREE_MB => doc.fail(&what, &format!("has only {} MiB free", mb)),
+        Some(mb) => doc.ok(&what, &format!("writable, {} MiB free", mb)),
+        None => doc.ok(&what, "writable"),
+    }
+    true
+}
+
+/// Build a dependency-free crate under `root` and time it.
+fn smoke_test(doc: &mut Doctor, root: &Path) {
+    let dir = root.join(".doctor-smoke");
+    let _ = fs::remove_dir_all(&dir);
+    let made = fs::create_dir_all(dir.join("src")).and_then(|()| {
+        fs::write(dir.join("Cargo.toml"), "[package]\nname = \"smoke\"\nversion = \"0.1.0\"\nedition = \"2021\"\n")?;
+        fs::write(dir.join("src/lib.rs"), "#[test]\nfn it_works() { assert_eq!(2 + 2, 4); }\n")
+    });
+    if let Err(e) = made {
+        doc.fail("smoke test", &format!("({})", e));
+        return;
+    }
+    let t0 = Instant::now();
+    let out = Command::new("cargo")
+        .args(["test", "--offline", "-q", "--color=never"])
+        .current_dir(&dir)
+        .output();
+    let _ = fs::remove_dir_all(&dir);
+    match out {
+        Ok(out) if out.status.success() => {
+            doc.ok("smoke test", &format!("built and ran a trivial crate in {:.1}s", t0.elapsed().as_secs_f64()))
+        }
+        Ok(out) => {
+            let stderr = String::from_utf8_lossy(&out.stderr);
+            doc.fail("smoke test", &format!("failed:\n{}", stderr.trim_end()));
+        }
+        Err(e) => doc.fail("smoke test", &format!("(cargo: {})", e)),
+    }
+}
+
+/// Run every check, printing one line per item. Returns whether all
+/// required ones passed.
+pub fn run(toolchains: &[String], tools: &[String], install: bool, roots: &[&Path]) -> bool {
+    let mut doc = Doctor { problems: 0 };
+    println!("{}Tools:{}", BOLD, RESET);
+    for (program, required) in [("cargo", true), ("rustc", true), ("rustup", false), ("gdb", false), ("unshare", false)] {
+        match probe(program, &["--version"], None) {
+            Some(version) => doc.ok(program, &format!("({})", version)),
+            None if required => doc.fail(program, "(not on PATH)"),
+            None => doc.note(program, "not found; some checks will be skipped"),
+        }
+    }
+    let has_cargo = probe("cargo", &["--version"], None).is_some();
+    let has_rustup = probe("rustup", &["--version"], None).is_some();
+
+    let mut chains: Vec<Option<&str>> = vec![None];
+    chains.extend(toolchains.iter().map(|t| Some(t.as_str())));
+    println!("\n{}Toolchains:{}", BOLD, RESET);
+    for tc in &chains {
+        let name = tc.unwrap_or("default");
+        let mut version = probe("rustc", &["--version"], *tc);
+        if version.is_none() && install && has_rustup {
+            if let Some(tc) = tc {
+                if rustup(&["toolchain", "install", tc, "--profile", "minimal"]) {
+                    version = probe("rustc", &["--version"], Some(tc));
+                }
+            }
+        }
+        match version {
+            Some(v) => doc.ok(name, &format!("({})", v)),
+            None => doc.fail(name, "(not installed; --install adds it)"),
+        }
+    }
+
+    if !tools.is_empty() {
+        println!("\n{}Components:{}", BOLD, RESET);
+    }
+    for tc in &chains {
+        for (name, subcommand, source) in TOOLS.iter().filter(|(n, ..)| tools.iter().any(|t| t == n)) {
+            let what = format!("{} on {}", name, tc.unwrap_or("default"));
+            let check = || probe("cargo", &[subcommand, "--version"], *tc);
+            let mut found = check();
+            if found.is_none() && install && has_rustup {
+                if let Source::Component(component) = source {
+                    let mut args = vec!["component", "add", component];
+                    if let Some(tc) = tc {
+                        args.extend(["--toolchain", tc]);
+                    }
+                    if rustup(&args) {
+                        found = check();
+                    }
+                }
+            }
+            match (found, source) {
+                (Some(v), _) => doc.ok(&what, &format!("({})", v)),
+                (None, Source::Component(c)) => {
+                    doc.fail(&what, &format!("(`rustup component add {}`, or --install)", c))
+                }
+                (None, Source::Crate(c)) => doc.fail(&what, &format!("(`cargo install {}`)", c)),
+            }
+        }
+    }
+
+    println!("\n{}Workspace roots:{}", BOLD, RESET);
+    let usable: Vec<&Path> = roots.iter().copied().filter(|root| check_root(&mut doc, root)).collect();
+
+    println!("\n{}Smoke test:{}", BOLD, RESET);
+    match usable.first() {
+        Some(root) if has_cargo => smoke_test(&mut doc, root),
+        _ => doc.note("smoke test", "skipped without cargo and a writable workspace root"),
+    }
+
+    if doc.problems == 0 {
+        println!("\n{}Ready to validate.{}", GREEN, RESET);
+    } else {
+        println!("\n{}{} problem(s) found.{}", RED, doc.problems, RESET);
+    }
+    doc.problems == 0
+}
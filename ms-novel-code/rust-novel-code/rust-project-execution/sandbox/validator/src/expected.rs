@@ -0,0 +1,305 @@
+//! End-to-end checks of the task's binary: `src/main.rs` runs once per
+//! case with the case's input as stdin, and its stdout is diffed against
+//! the expectation. The manifest's `[output]` table says which
+//! differences in whitespace do not count.
+//!
+//! Cases come from `# run_input` / `# expected_output` cell pairs, and
+//! from `# cases` cells holding any number of them:
+//!
+//! ````text
+//! # cases
+//!
+//! ## two numbers
+//! ```input
+//! 1 2
+//! ```
+//! ```output
+//! 3
+//! ```
+//! ````
+//!
+//! `input` and `output` blocks pair up in order, an `output` alone having
+//! empty stdin; a `## name` line names the pair after it. Every run of the
+//! suite also runs the cases, as tests `case::<name>` of the consistency
+//! matrix, so a binary whose output varies shows as flaky.
+
+use crate::colors::*;
+use crate::fix::diff;
+use crate::manifest::OutputPolicy;
+use crate::{extract_fenced_lines, file_cell_path, has_marker, interrupt, Cell, Notebook, RunOptions};
+use serde_json::Value;
+use std::{
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    process::Stdio,
+    thread,
+    time::Duration,
+};
+use wait_timeout::ChildExt;
+
+/// One run of the binary.
+#[derive(Clone)]
+pub struct Case {
+    /// The `## name` before it in a `# cases` cell, else its number.
+    pub name: String,
+    pub stdin: String,
+    pub expected: String,
+}
+
+impl Case {
+    /// Its name in the run matrix.
+    pub fn test_name(&self) -> String {
+        format!("case::{}", self.name)
+    }
+}
+
+/// The cases of a `# cases` cell, numbered on from `first`.
+fn case_cell(src: &[String], first: usize) -> Result<Vec<Case>, String> {
+    let mut cases = Vec::new();
+    let mut name: Option<String> = None;
+    let mut stdin: Option<String> = None;
+    // info string and lines of the block being read
+    let mut block: Option<(String, Vec<String>)> = None;
+    for line in src.iter().flat_map(|l| l.lines()) {
+        let t = line.trim_start();
+        let Some((info, lines)) = &mut block else {
+            if let Some(info) = t.strip_prefix("```") {
+                block = Some((info.trim().to_string(), Vec::new()));
+            } else if let Some(heading) = t.strip_prefix("## ") {
+                name = Some(heading.trim().to_string());
+            }
+            continue;
+        };
+        if !t.starts_with("```") {
+            lines.push(line.to_string());
+            continue;
+        }
+        let text = if lines.is_empty() { String::new() } else { lines.join("\n") + "\n" };
+        match info.as_str() {
+            "input" if stdin.is_some() => return Err("`# cases`: two `input` blocks without an `output` between".into()),
+            "input" => stdin = Some(text),
+            "output" => {
+                let number = first + cases.len();
+                cases.push(Case {
+                    name: name.take().unwrap_or_else(|| number.to_string()),
+                    stdin: stdin.take().unwrap_or_default(),
+                    expected: text,
+                });
+            }
+            other => return Err(format!("`# cases`: blocks are `input` or `output`, not `{}`", other)),
+        }
+        block = None;
+    }
+    if block.is_some() {
+        return Err("`# cases`: unclosed block".into());
+    }
+    if stdin.is_some() {
+        return Err("`# cases`: `input` without an `output` after it".into());
+    }
+    Ok(cases)
+}
+
+/// Fenced block of a cell as text, newline-terminated unless empty.
+fn block(src: &[String]) -> String {
+    let lines = extract_fenced_lines(src, "");
+    if lines.is_empty() {
+        String::new()
+    } else {
+        lines.join("\n") + "\n"
+    }
+}
+
+/// The notebook's cases, in order. A `# run_input` cell must be followed
+/// by an `# expected_output` cell before the next one. Names must be
+/// unique.
+pub fn cases(nb: &Notebook) -> Result<Vec<Case>, String> {
+    let mut cases = Vec::new();
+    let mut stdin: Option<String> = None;
+    for cell in &nb.cells {
+        let (Cell::Markdown { source } | Cell::Code { source }) = cell;
+        if file_cell_path(source).is_some() {
+            continue;
+        }
+        let joined = source.join("");
+        if has_marker(&joined, "cases") {
+            if stdin.is_some() {
+                return Err("`# run_input` without an `# expected_output` after it".into());
+            }
+            let found = case_cell(source, cases.len() + 1)?;
+            cases.extend(found);
+        } else if has_marker(&joined, "run_input") {
+            if stdin.is_some() {
+                return Err("`# run_input` without an `# expected_output` after it".into());
+            }
+            stdin = Some(block(source));
+        } else if has_marker(&joined, "expected_output") {
+            cases.push(Case {
+                name: (cases.len() + 1).to_string(),
+                stdin: stdin.take().unwrap_or_default(),
+                expected: block(source),
+            });
+        }
+    }
+    if stdin.is_some() {
+        return Err("`# run_input` without an `# expected_output` after it".into());
+    }
+    for (i, case) in cases.iter().enumerate() {
+        if cases[..i].iter().any(|c| c.name == case.name) {
+            return Err(format!("two cases named `{}`", case.name));
+        }
+    }
+    Ok(cases)
+}
+
+/// Path of the binary built from `src/main.rs`, building it if the test
+/// build has not already.
+pub fn binary(workspace: &Path, opts: &RunOptions) -> Result<PathBuf, String> {
+    let out = opts.command("cargo")
+        .args(["build", "--bins", "--color=never", "--message-format=json"])
+        .current_dir(workspace)
+        .stderr(Stdio::null())
+        .output()
+        .map_err(|e| e.to_string())?;
+    if !out.status.success() {
+        return Err(format!("building the binary failed (exit {:?})", out.status.code()));
+    }
+    String::from_utf8_lossy(&out.stdout)
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .filter(|msg| msg["reason"] == "compiler-artifact")
+        .filter(|msg| msg["target"]["src_path"].as_str().is_some_and(|p| p.ends_with("src/main.rs")))
+        .find_map(|msg| msg["executable"].as_str().map(PathBuf::from))
+        .ok_or_else(|| "no binary built from src/main.rs".to_string())
+}
+
+/// Run `exe` with `stdin`, returning its stdout and a note on how it
+/// exited when that was not successfully.
+fn run(exe: &Path, stdin: &str, workspace: &Path, opts: &RunOptions) -> Result<(String, Option<String>), String> {
+    let mut child = interrupt::spawn(
+        opts.command(exe)
+            .current_dir(workspace)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null()),
+    )
+    .map_err(|e| format!("{}: {}", exe.display(), e))?;
+    // feed and drain from threads, so neither pipe can fill up and stall
+    let mut input = child.stdin.take().expect("stdin is piped");
+    let bytes = stdin.as_bytes().to_vec();
+    let writer = thread::spawn(move || {
+        // a program that exits without reading closes the pipe early
+        let _ = input.write_all(&bytes);
+    });
+    let mut output = child.stdout.take().expect("stdout is piped");
+    let reader = thread::spawn(move || {
+        let mut buf = Vec::new();
+        let _ = output.read_to_end(&mut buf);
+        buf
+    });
+    let status = child.wait_timeout(Duration::from_secs(opts.timeout)).map_err(|e| e.to_string())?;
+    if status.is_none() {
+        interrupt::kill(&child);
+        let _ = child.wait();
+    }
+    interrupt::reap(&child);
+    let _ = writer.join();
+    let stdout = String::from_utf8_lossy(&reader.join().unwrap_or_default()).into_owned();
+    match status {
+        None => Err(format!("timeout reached ({}s)", opts.timeout)),
+        Some(s) if s.success() => Ok((stdout, None)),
+        Some(s) => Ok((stdout, Some(format!("exited with {}", s)))),
+    }
+}
+
+/// `text` as lines, with the differences `policy` ignores smoothed out.
+fn normalize(text: &str, policy: &OutputPolicy) -> Vec<String> {
+    let text = if policy.normalize_line_endings { text.replace("\r\n", "\n") } else { text.to_string() };
+    let mut lines: Vec<String> = text
+        .split('\n')
+        .map(|l| {
+            if policy.normalize_whitespace {
+                l.split_whitespace().collect::<Vec<_>>().join(" ")
+            } else if policy.trim_trailing_whitespace {
+                l.trim_end().to_string()
+            } else {
+                l.to_string()
+            }
+        })
+        .collect();
+    if policy.trim_trailing_whitespace || policy.normalize_whitespace {
+        while lines.last().is_some_and(|l| l.is_empty()) {
+            lines.pop();
+        }
+    }
+    lines
+}
+
+/// How one case went.
+pub struct Outcome {
+    pub matched: bool,
+    /// A note on a non-zero exit, or why the binary did not finish.
+    pub note: Option<String>,
+    /// `- expected, + actual` lines when it did not match.
+    pub diff: Vec<String>,
+}
+
+impl Outcome {
+    /// What went wrong, as a failing test's output reads.
+    pub fn report(&self) -> String {
+        let mut text = self.note.clone().unwrap_or_default();
+        for line in &self.diff {
+            text = text + "\n" + line;
+        }
+        text.trim().to_string()
+    }
+}
+
+/// Run `exe`, the binary, on every case.
+pub fn run_all(
+    exe: &Path,
+    workspace: &Path,
+    cases: &[Case],
+    policy: &OutputPolicy,
+    opts: &RunOptions,
+) -> Result<Vec<Outcome>, String> {
+    let mut outcomes = Vec::new();
+    for case in cases {
+        if interrupt::interrupted() {
+            return Err("interrupted".into());
+        }
+        let (stdout, exit) = match run(exe, &case.stdin, workspace, opts) {
+            Ok(done) => done,
+            Err(e) => {
+                outcomes.push(Outcome { matched: false, note: Some(e), diff: Vec::new() });
+                continue;
+            }
+        };
+        let (expected, actual) = (normalize(&case.expected, policy), normalize(&stdout, policy));
+        let diff = if expected == actual { Vec::new() } else { diff(&expected, &actual) };
+        outcomes.push(Outcome { matched: diff.is_empty(), note: exit, diff });
+    }
+    Ok(outcomes)
+}
+
+/// Run every case and print the outcome, with a diff for mismatches.
+/// Returns whether all matched.
+pub fn check(workspace: &Path, cases: &[Case], policy: &OutputPolicy, opts: &RunOptions) -> Result<bool, String> {
+    println!("{}Expected output{}", BLUE, RESET);
+    let exe = binary(workspace, opts)?;
+    let mut ok = true;
+    for (case, outcome) in cases.iter().zip(run_all(&exe, workspace, cases, policy, opts)?) {
+        ok &= outcome.matched;
+        let note = outcome.note.as_ref().map(|e| format!(" ({})", e)).unwrap_or_default();
+        if outcome.matched {
+            println!("  case {}: {}matches{}{}", case.name, GREEN, RESET, note);
+        } else if outcome.diff.is_empty() {
+            println!("  case {}: {}{}{}", case.name, RED, outcome.note.unwrap_or_default(), RESET);
+        } else {
+            println!("  case {}: {}differs{}{} (- expected, + actual)", case.name, RED, RESET, note);
+            for line in &outcome.diff {
+                println!("    {}", line);
+            }
+        }
+    }
+    Ok(ok)
+}
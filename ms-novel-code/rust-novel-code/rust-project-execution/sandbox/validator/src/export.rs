@@ -0,0 +1,241 @@
+//! `--format json|junit`: the run matrix as a document for CI, written to
+//! `--output` or, without it, to stdout. In the latter case everything
+//! else the validator prints (cargo included) goes to stderr instead, so
+//! stdout carries the document alone.
+
+use crate::matrix::RunMatrix;
+use crate::sandbox::Violation;
+use crate::report;
+use clap::ValueEnum;
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::File,
+    io::{self, Write},
+    os::fd::FromRawFd,
+    path::Path,
+    sync::atomic::{AtomicI32, Ordering},
+    time::Duration,
+};
+
+#[derive(ValueEnum, Clone, Copy, PartialEq)]
+pub enum Format {
+    Text,
+    Json,
+    Junit,
+}
+
+/// Where the document goes when it is not a file: the process's original
+/// stdout, set aside by [`divert_stdout`].
+static DOCUMENT_FD: AtomicI32 = AtomicI32::new(libc::STDOUT_FILENO);
+
+/// Point stdout at stderr, keeping the original for the document.
+pub fn divert_stdout() -> Result<(), String> {
+    // SAFETY: dup/dup2 have no memory-safety preconditions
+    let fd = unsafe { libc::dup(libc::STDOUT_FILENO) };
+    if fd < 0 || unsafe { libc::dup2(libc::STDERR_FILENO, libc::STDOUT_FILENO) } < 0 {
+        return Err(io::Error::last_os_error().to_string());
+    }
+    DOCUMENT_FD.store(fd, Ordering::SeqCst);
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct Test {
+    name: String,
+    /// `pass`, `fail`, `flaky` or `unsound`.
+    classification: &'static str,
+    /// Pass/fail of every run, in order.
+    results: Vec<bool>,
+    /// What Miri or the sanitizer reported, for `unsound`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    finding: Option<String>,
+    /// Duration of every run that timed the test, in order.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    seconds: Vec<f64>,
+}
+
+#[derive(Serialize)]
+pub struct Document {
+    notebook: String,
+    /// `passed`, `failed`, `violation` (a test hit a sandbox limit),
+    /// `build-failed`, `error` or `interrupted`.
+    status: &'static str,
+    exit_code: i32,
+    build_seconds: Option<f64>,
+    /// Wall time of each completed run.
+    run_seconds: Vec<f64>,
+    totals: report::Totals,
+    tests: Vec<Test>,
+    /// Captured output of each failing test's last failure.
+    #[serde(skip)]
+    failures: HashMap<String, String>,
+    /// Tests stopped by a sandbox limit, with the limit.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    violations: BTreeMap<String, String>,
+    /// Why the validation ended early, for `error` and `build-failed`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+}
+
+impl Document {
+    pub fn new(notebook: &Path, status: &'static str, exit_code: i32) -> Self {
+        Document {
+            notebook: notebook.display().to_string(),
+            status,
+            exit_code,
+            build_seconds: None,
+            run_seconds: Vec::new(),
+            totals: report::Totals { pass: 0, fail: 0, flaky: 0, unsound: 0 },
+            tests: Vec::new(),
+            failures: HashMap::new(),
+            violations: BTreeMap::new(),
+            message: None,
+        }
+    }
+
+    pub fn status(&self) -> &'static str {
+        self.status
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        self.exit_code
+    }
+
+    pub fn message(mut self, message: String) -> Self {
+        self.message = Some(message);
+        self
+    }
+
+    /// Fill in the timings, `matrix` and the captured `failures`.
+    pub fn runs(
+        mut self,
+        build: Duration,
+        runs: &[Duration],
+        matrix: &RunMatrix,
+        failures: &HashMap<String, String>,
+    ) -> Self {
+        self.build_seconds = Some(build.as_secs_f64());
+        self.run_seconds = runs.iter().map(Duration::as_secs_f64).collect();
+        self.totals = matrix.totals();
+        let tests = matrix
+            .classified()
+            .into_iter()
+            .map(|(name, classification)| Test {
+                name: name.to_string(),
+                classification: classification.as_str(),
+                results: matrix.results(name).unwrap_or_default().to_vec(),
+                finding: matrix.finding(name).map(str::to_string),
+                seconds: matrix.times(name).unwrap_or_default().iter().map(Duration::as_secs_f64).collect(),
+            })
+            .collect();
+        self.tests = tests;
+        self.failures = failures.clone();
+        self
+    }
+
+    pub fn violations(mut self, violations: &[(String, Violation)]) -> Self {
+        self.violations = violations.iter().map(|(test, v)| (test.clone(), v.to_string())).collect();
+        self
+    }
+
+    fn junit(&self) -> String {
+        let total: f64 = self.run_seconds.iter().sum();
+        let failures = self.tests.iter().filter(|t| t.classification != "pass").count();
+        let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        out += &format!(
+            "<testsuites name=\"validator\" tests=\"{}\" failures=\"{}\" errors=\"{}\" time=\"{:.3}\">\n",
+            self.tests.len(), failures, usize::from(self.message.is_some()), total
+        );
+        out += &format!(
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" time=\"{:.3}\">\n",
+            xml(&self.notebook), self.tests.len(), failures, total
+        );
+        out += "    <properties>\n";
+        out += &format!("      <property name=\"status\" value=\"{}\"/>\n", self.status);
+        out += &format!("      <property name=\"runs\" value=\"{}\"/>\n", self.run_seconds.len());
+        out += "    </properties>\n";
+        for test in &self.tests {
+            // tests outside modules are classed under the notebook
+            let (class, name) = test.name.rsplit_once("::").unwrap_or((&self.notebook, &test.name));
+            out += &format!("    <testcase classname=\"{}\" name=\"{}\"", xml(class), xml(name));
+            if !test.seconds.is_empty() {
+                let mean = test.seconds.iter().sum::<f64>() / test.seconds.len() as f64;
+                out += &format!(" time=\"{:.3}\"", mean);
+            }
+            let failed = test.results.iter().filter(|&&r| !r).count();
+            if failed == 0 && test.finding.is_none() {
+                out += "/>\n";
+                continue;
+            }
+            out += ">\n";
+            if let Some(finding) = &test.finding {
+                out += &format!(
+                    "      <failure type=\"unsound\" message=\"{}\">{}</failure>\n    </testcase>\n",
+                    xml(finding),
+                    xml(self.failures.get(&test.name).map_or("", String::as_str))
+                );
+                continue;
+            }
+            let (kind, what) = match self.violations.get(&test.name) {
+                Some(violation) => ("violation", violation.as_str()),
+                None if test.classification == "flaky" => (test.classification, "flaky: failed"),
+                None => (test.classification, "failed"),
+            };
+            out += &format!(
+                "      <failure type=\"{}\" message=\"{} in {} of {} runs\">{}</failure>\n",
+                kind,
+                xml(what),
+                failed,
+                test.results.len(),
+                xml(self.failures.get(&test.name).map_or("", String::as_str))
+            );
+            out += "    </testcase>\n";
+        }
+        if let Some(message) = &self.message {
+            out += &format!("    <error message=\"{}\"/>\n", xml(message));
+        }
+        out += "  </testsuite>\n</testsuites>\n";
+        out
+    }
+
+    /// The document as pretty-printed JSON.
+    pub fn json(&self) -> Result<String, String> {
+        serde_json::to_string_pretty(self).map(|json| json + "\n").map_err(|e| e.to_string())
+    }
+
+    /// Write the document in `format` to `output`, or to the stdout set
+    /// aside by [`divert_stdout`]. `Text` writes nothing.
+    pub fn write(&self, format: Format, output: Option<&Path>) -> Result<(), String> {
+        let text = match format {
+            Format::Text => return Ok(()),
+            Format::Json => self.json()?,
+            Format::Junit => self.junit(),
+        };
+        match output {
+            Some(path) => std::fs::write(path, text).map_err(|e| format!("{}: {}", path.display(), e)),
+            None => {
+                // SAFETY: the fd is open, and the File is forgotten below
+                // so it is never closed
+                let mut out = unsafe { File::from_raw_fd(DOCUMENT_FD.load(Ordering::SeqCst)) };
+                let written = out.write_all(text.as_bytes()).and_then(|_| out.flush());
+                std::mem::forget(out);
+                written.map_err(|e| e.to_string())
+            }
+        }
+    }
+}
+
+/// `text` escaped for an XML attribute or element.
+fn xml(text: &str) -> String {
+    text.chars()
+        .filter(|&c| c == '\t' || c == '\n' || c == '\r' || c >= ' ')
+        .map(|c| match c {
+            '&' => "&amp;".to_string(),
+            '<' => "&lt;".to_string(),
+            '>' => "&gt;".to_string(),
+            '"' => "&quot;".to_string(),
+            c => c.to_string(),
+        })
+        .collect()
+}
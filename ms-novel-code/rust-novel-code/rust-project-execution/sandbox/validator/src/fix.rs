@@ -0,0 +1,253 @@
+//! `validator fix`: rewrite a notebook into the canonical layout.
+//!
+//! Section markers are respelled (`#LIB:` becomes `# lib`), code sections
+//! without a fence get one, cells are put in the canonical section order,
+//! and trailing whitespace and mojibake are cleaned up. Everything else in
+//! the notebook JSON is written back untouched.
+
+use serde_json::Value;
+use std::{fs, path::Path};
+
+/// Sections in canonical order, spelled the way markers should be.
+const SECTIONS: [&str; 15] = [
+    "Metadata", "Setup", "Prompt", "Requirements", "env", "deps", "build", "lib", "main", "testutil", "test",
+    "snapshots", "run_input", "expected_output", "cases",
+];
+
+/// Canonical spelling of the marker `line`, if it is one.
+pub fn marker(line: &str) -> Option<&'static str> {
+    let name = line.trim().strip_prefix('#')?.trim_start_matches('#').trim();
+    let name = name.strip_suffix(':').unwrap_or(name).trim();
+    SECTIONS.iter().copied().find(|s| s.eq_ignore_ascii_case(name))
+}
+
+/// Position of a cell in the canonical order: its section's index, fixture
+/// cells right after the sections, anything else at the end.
+fn rank(lines: &[String]) -> usize {
+    match lines.iter().find(|l| !l.trim().is_empty()) {
+        Some(l) if l.trim().starts_with("# file:") => SECTIONS.len(),
+        Some(l) => marker(l)
+            .and_then(|m| SECTIONS.iter().position(|s| *s == m))
+            .unwrap_or(SECTIONS.len() + 1),
+        None => SECTIONS.len() + 1,
+    }
+}
+
+pub fn source_lines(cell: &Value) -> Vec<String> {
+    let text = match &cell["source"] {
+        Value::String(s) => s.clone(),
+        Value::Array(parts) => parts.iter().filter_map(Value::as_str).collect(),
+        _ => String::new(),
+    };
+    text.split('\n').map(str::to_string).collect()
+}
+
+/// Back to nbformat's list of lines, each but the last ending in `\n`.
+pub fn to_source(lines: &[String]) -> Value {
+    let last = lines.len().saturating_sub(1);
+    let parts = lines
+        .iter()
+        .enumerate()
+        .map(|(i, l)| if i < last { format!("{}\n", l) } else { l.clone() })
+        .filter(|l| !l.is_empty())
+        .map(Value::String)
+        .collect();
+    Value::Array(parts)
+}
+
+/// The cell's lines with its marker respelled, a repeated marker line
+/// dropped, a fence added to code sections that lack one, and text
+/// cleaned. `# file:` cells only get their text cleaned.
+fn normalize_cell(lines: Vec<String>) -> Vec<String> {
+    let mut lines: Vec<String> = lines.iter().map(|l| clean_line(l)).collect();
+    let Some(first) = lines.iter().position(|l| !l.trim().is_empty()) else { return lines };
+    if lines[first].trim().starts_with("# file:") {
+        return lines;
+    }
+    let Some(section) = marker(&lines[first]) else { return lines };
+    lines[first] = format!("# {}", section);
+    if lines.get(first + 1).and_then(|l| marker(l)) == Some(section) {
+        lines.remove(first + 1);
+    }
+    let fence = match section {
+        "build" | "lib" | "main" | "testutil" | "test" => "```rust",
+        "Setup" | "deps" => "```toml",
+        "env" | "run_input" | "expected_output" => "```",
+        _ => return lines,
+    };
+    if lines.iter().any(|l| l.trim_start().starts_with("```")) {
+        return lines;
+    }
+    let body: Vec<String> = lines.split_off(first + 1);
+    let start = body.iter().position(|l| !l.trim().is_empty()).unwrap_or(body.len());
+    let end = body.iter().rposition(|l| !l.trim().is_empty()).map_or(start, |e| e + 1);
+    lines.push(String::new());
+    lines.push(fence.to_string());
+    lines.extend_from_slice(&body[start..end]);
+    lines.push("```".to_string());
+    lines
+}
+
+/// Strip trailing whitespace, keeping a markdown hard break (two trailing
+/// spaces) as exactly two, and undo mojibake.
+fn clean_line(line: &str) -> String {
+    let line = demojibake(line);
+    let trimmed = line.trim_end();
+    if line[trimmed.len()..].starts_with("  ") && !trimmed.is_empty() {
+        format!("{}  ", trimmed)
+    } else {
+        trimmed.to_string()
+    }
+}
+
+/// Windows-1252 byte for `c`, where it has one outside ASCII.
+fn cp1252_byte(c: char) -> Option<u8> {
+    const HIGH: [char; 32] = [
+        '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8D}', 'Ž',
+        '\u{8F}', '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ',
+        '\u{9D}', 'ž', 'Ÿ',
+    ];
+    match c as u32 {
+        0xA0..=0xFF => Some(c as u8),
+        _ => HIGH.iter().position(|&h| h == c).map(|i| 0x80 + i as u8),
+    }
+}
+
+/// Undo UTF-8 text that was decoded as Windows-1252 (`â€™` for `’`): each
+/// run of such characters is re-encoded and kept only if it decodes as
+/// UTF-8, so genuine accented text is left alone.
+fn demojibake(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut run = String::new();
+    let mut bytes = Vec::new();
+    let flush = |out: &mut String, run: &mut String, bytes: &mut Vec<u8>| {
+        match std::str::from_utf8(bytes) {
+            Ok(fixed) => out.push_str(fixed),
+            Err(_) => out.push_str(run),
+        }
+        run.clear();
+        bytes.clear();
+    };
+    for c in line.chars() {
+        match cp1252_byte(c) {
+            Some(b) => {
+                run.push(c);
+                bytes.push(b);
+            }
+            None => {
+                flush(&mut out, &mut run, &mut bytes);
+                out.push(c);
+            }
+        }
+    }
+    flush(&mut out, &mut run, &mut bytes);
+    out
+}
+
+/// Normalize the notebook JSON in place; returns whether anything changed.
+fn normalize(nb: &mut Value) -> Result<bool, String> {
+    let cells = nb["cells"].as_array_mut().ok_or("notebook has no `cells` list")?;
+    let before = cells.clone();
+    for cell in cells.iter_mut() {
+        let lines = normalize_cell(source_lines(cell));
+        cell["source"] = to_source(&lines);
+    }
+    // stable, so cells of the same rank keep their order
+    cells.sort_by_key(|c| rank(&source_lines(c)));
+    Ok(*cells != before)
+}
+
+/// Text of every cell, one line per source line, for diffing.
+fn render(nb: &Value) -> Vec<String> {
+    let mut out = Vec::new();
+    for (i, cell) in nb["cells"].as_array().into_iter().flatten().enumerate() {
+        let kind = cell["cell_type"].as_str().unwrap_or("?");
+        out.push(format!("── cell {} ({}) ──", i, kind));
+        out.extend(source_lines(cell));
+    }
+    out
+}
+
+/// Line diff of `a` to `b` with two lines of context around changes.
+pub fn diff(a: &[String], b: &[String]) -> Vec<String> {
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (x, y) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+
+    // longest common subsequence table over the differing middle
+    let mut lcs = vec![vec![0u32; y.len() + 1]; x.len() + 1];
+    for i in (0..x.len()).rev() {
+        for j in (0..y.len()).rev() {
+            lcs[i][j] = if x[i] == y[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let mut ops: Vec<(char, &String)> = a[..prefix].iter().map(|l| (' ', l)).collect();
+    let (mut i, mut j) = (0, 0);
+    while i < x.len() || j < y.len() {
+        if i < x.len() && j < y.len() && x[i] == y[j] {
+            ops.push((' ', &x[i]));
+            i += 1;
+            j += 1;
+        } else if j < y.len() && (i == x.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            ops.push(('+', &y[j]));
+            j += 1;
+        } else {
+            ops.push(('-', &x[i]));
+            i += 1;
+        }
+    }
+    ops.extend(a[a.len() - suffix..].iter().map(|l| (' ', l)));
+
+    const CONTEXT: usize = 2;
+    let near_change = |k: usize| {
+        ops[k.saturating_sub(CONTEXT)..(k + CONTEXT + 1).min(ops.len())]
+            .iter()
+            .any(|(op, _)| *op != ' ')
+    };
+    let mut out = Vec::new();
+    let mut skipped = false;
+    for (k, (op, line)) in ops.iter().enumerate() {
+        if near_change(k) {
+            if skipped {
+                out.push("   ...".to_string());
+                skipped = false;
+            }
+            out.push(format!("{} {}", op, line));
+        } else {
+            skipped = true;
+        }
+    }
+    out
+}
+
+/// Entry point of `validator fix`.
+pub fn run(path: &Path, dry_run: bool) -> Result<(), String> {
+    let raw = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mut nb: Value =
+        serde_json::from_str(&raw).map_err(|e| format!("{}: JSON error: {}", path.display(), e))?;
+    let original = nb.clone();
+    if !normalize(&mut nb)? {
+        println!("{} is already normalized", path.display());
+        return Ok(());
+    }
+    if dry_run {
+        for line in diff(&render(&original), &render(&nb)) {
+            println!("{}", line);
+        }
+        return Ok(());
+    }
+    let mut out = serde_json::to_string_pretty(&nb).map_err(|e| e.to_string())?;
+    out.push('\n');
+    fs::write(path, out).map_err(|e| format!("{}: {}", path.display(), e))?;
+    println!("Rewrote {}", path.display());
+    Ok(())
+}
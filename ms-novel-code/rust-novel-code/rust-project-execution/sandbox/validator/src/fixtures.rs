@@ -0,0 +1,110 @@
+//! Checksum-pinned remote fixtures with a content-addressed local cache.
+
+use crate::{manifest::Fixture, sanitize_relative_path};
+use sha2::{Digest, Sha256};
+use std::{
+    fs,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn download(url: &str) -> Result<Vec<u8>, String> {
+    let resp = ureq::get(url).call().map_err(|e| e.to_string())?;
+    let mut body = Vec::new();
+    resp.into_reader()
+        .read_to_end(&mut body)
+        .map_err(|e| e.to_string())?;
+    Ok(body)
+}
+
+/// Bytes for `fixture`: served from `cache` when a verified copy exists,
+/// otherwise downloaded (unless `offline`), verified, and cached.
+fn fetch(fixture: &Fixture, cache: &Path, offline: bool) -> Result<Vec<u8>, String> {
+    // the checksum names the cache entry, so it must not be a path
+    let want = &fixture.sha256;
+    if want.len() != 64 || !want.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f')) {
+        return Err(format!(
+            "fixture {}: sha256 must be 64 lowercase hex digits, got `{}`",
+            fixture.url, want
+        ));
+    }
+    let cached = cache.join(want);
+    if let Ok(bytes) = fs::read(&cached) {
+        if sha256_hex(&bytes) == *want {
+            return Ok(bytes);
+        }
+    }
+    if offline {
+        return Err(format!("fixture {} is not cached and --offline is set", fixture.url));
+    }
+
+    let bytes = download(&fixture.url).map_err(|e| format!("fetching {}: {}", fixture.url, e))?;
+    let got = sha256_hex(&bytes);
+    if got != *want {
+        return Err(format!(
+            "fixture {}: checksum mismatch (expected {}, got {})",
+            fixture.url, want, got
+        ));
+    }
+
+    // Write-then-rename so a crashed download never poisons the cache
+    fs::create_dir_all(cache).map_err(|e| e.to_string())?;
+    let tmp = cache.join(format!("{}.part", want));
+    fs::write(&tmp, &bytes).map_err(|e| e.to_string())?;
+    fs::rename(&tmp, &cached).map_err(|e| e.to_string())?;
+    Ok(bytes)
+}
+
+/// Materialize every fixture under `workspace/data/`, returning the
+/// workspace-relative paths written.
+pub fn install(
+    fixtures: &[Fixture],
+    workspace: &Path,
+    cache: &Path,
+    offline: bool,
+) -> Result<Vec<String>, String> {
+    let mut written = Vec::new();
+    for fixture in fixtures {
+        let rel = format!("data/{}", fixture.file_name()?);
+        let dest: PathBuf = sanitize_relative_path(workspace, &rel)?;
+        let bytes = fetch(fixture, cache, offline)?;
+        if let Some(dir) = dest.parent() {
+            fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        fs::write(&dest, bytes).map_err(|e| e.to_string())?;
+        written.push(rel);
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(url: &str, sha256: &str) -> Fixture {
+        Fixture { url: url.into(), sha256: sha256.into(), path: None }
+    }
+
+    #[test]
+    fn rejects_checksums_that_are_not_lowercase_hex() {
+        let cache = Path::new("/nonexistent");
+        for sha in ["../../etc/passwd", &"A".repeat(64), &"a".repeat(63), &format!("{}/", "a".repeat(63))] {
+            let err = fetch(&fixture("https://example.com/x.csv", sha), cache, true).unwrap_err();
+            assert!(err.contains("64 lowercase hex digits"), "{}", err);
+        }
+        let err = fetch(&fixture("https://example.com/x.csv", &"a".repeat(64)), cache, true).unwrap_err();
+        assert!(err.contains("not cached"), "{}", err);
+    }
+
+    #[test]
+    fn file_name_must_be_a_plain_name() {
+        assert_eq!(fixture("https://example.com/d/x.csv?v=2#top", "").file_name(), Ok("x.csv"));
+        for url in ["https://example.com/d/", "https://example.com/..", "https://example.com/.", "x/..?a"] {
+            assert!(fixture(url, "").file_name().is_err(), "{}", url);
+        }
+    }
+}
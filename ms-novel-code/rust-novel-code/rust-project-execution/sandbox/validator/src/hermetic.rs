@@ -0,0 +1,168 @@
+//! `--hermeticity`: re-run the suite with one piece of the environment
+//! changed at a time and flag tests whose outcome flips. Such tests pass
+//! today only because of the grader's timezone, locale, clock or a
+//! writable workspace, and will turn flaky when that changes.
+
+use crate::colors::*;
+use crate::{run_cargo_test_once, RunOptions};
+use std::{
+    collections::HashMap,
+    fs, io,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+};
+
+/// Where distributions install libfaketime's preload library.
+const LIBFAKETIME: [&str; 4] = [
+    "/usr/lib/x86_64-linux-gnu/faketime/libfaketime.so.1",
+    "/usr/lib/aarch64-linux-gnu/faketime/libfaketime.so.1",
+    "/usr/lib/faketime/libfaketime.so.1",
+    "/usr/local/lib/faketime/libfaketime.so.1",
+];
+
+/// One perturbation of the run environment.
+struct Probe {
+    name: &'static str,
+    env: Vec<(String, String)>,
+    read_only: bool,
+}
+
+fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+    pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+}
+
+/// The probes this machine supports, and a reason for each it skips.
+fn probes() -> (Vec<Probe>, Vec<(&'static str, String)>) {
+    let mut probes = vec![
+        // UTC+14, so "today" differs from UTC for most of the day
+        Probe { name: "timezone", env: vars(&[("TZ", "Pacific/Kiritimati")]), read_only: false },
+        // Turkish casing rules (`i` does not upper-case to `I`), comma decimals
+        Probe {
+            name: "locale",
+            env: vars(&[("LANG", "tr_TR.UTF-8"), ("LC_ALL", "tr_TR.UTF-8")]),
+            read_only: false,
+        },
+    ];
+    let mut skipped = Vec::new();
+    match LIBFAKETIME.iter().find(|p| Path::new(p).exists()) {
+        Some(lib) => probes.push(Probe {
+            name: "clock",
+            // a year and a day ahead; monotonic time stays real so the
+            // timeout still works
+            env: vars(&[
+                ("LD_PRELOAD", lib),
+                ("FAKETIME", "+366d"),
+                ("FAKETIME_DONT_FAKE_MONOTONIC", "1"),
+            ]),
+            read_only: false,
+        }),
+        None => skipped.push(("clock", "libfaketime not installed".to_string())),
+    }
+    probes.push(Probe { name: "read-only", env: Vec::new(), read_only: true });
+    (probes, skipped)
+}
+
+/// Workspace files made read-only, `target/` excepted; restores the
+/// original permissions when dropped.
+struct ReadOnly(Vec<(PathBuf, fs::Permissions)>);
+
+impl ReadOnly {
+    fn apply(workspace: &Path) -> io::Result<Self> {
+        fn walk(dir: &Path, out: &mut Vec<(PathBuf, fs::Permissions)>) -> io::Result<()> {
+            for entry in fs::read_dir(dir)? {
+                let path = entry?.path();
+                if path.file_name().is_some_and(|n| n == "target") {
+                    continue;
+                }
+                if path.is_dir() {
+                    walk(&path, out)?;
+                }
+                out.push((path.clone(), fs::metadata(&path)?.permissions()));
+            }
+            Ok(())
+        }
+        let mut saved = Vec::new();
+        walk(workspace, &mut saved)?;
+        saved.push((workspace.to_path_buf(), fs::metadata(workspace)?.permissions()));
+        let guard = ReadOnly(saved);
+        for (path, perms) in &guard.0 {
+            let mut ro = perms.clone();
+            ro.set_mode(perms.mode() & !0o222);
+            fs::set_permissions(path, ro)?;
+        }
+        Ok(guard)
+    }
+}
+
+impl Drop for ReadOnly {
+    fn drop(&mut self) {
+        for (path, perms) in self.0.drain(..) {
+            let _ = fs::set_permissions(path, perms);
+        }
+    }
+}
+
+/// Whether writing into `dir` fails, i.e. permissions are enforced (they
+/// are not for root).
+fn enforced(dir: &Path) -> bool {
+    let probe = dir.join(".validator-write-probe");
+    let denied = fs::write(&probe, b"").is_err();
+    let _ = fs::remove_file(probe);
+    denied
+}
+
+/// Run every probe once against the consistent outcomes in `matrix`
+/// and print the hermeticity section. Returns whether no test flipped.
+pub fn check(
+    workspace: &Path,
+    matrix: &HashMap<String, Vec<bool>>,
+    base: &RunOptions,
+) -> Result<bool, String> {
+    // flaky tests flip on their own, so only consistent ones can tell
+    let expected: HashMap<&str, bool> = matrix
+        .iter()
+        .filter(|(_, runs)| runs.iter().all(|&r| r == runs[0]))
+        .map(|(name, runs)| (name.as_str(), runs[0]))
+        .collect();
+    let (probes, mut skipped) = probes();
+    let mut flips: Vec<(&str, Vec<String>)> = Vec::new();
+    for probe in probes {
+        println!("{}Hermeticity probe: {}{}", BLUE, probe.name, RESET);
+        let _guard = if probe.read_only {
+            let guard = ReadOnly::apply(workspace).map_err(|e| e.to_string())?;
+            if !enforced(workspace) {
+                skipped.push((probe.name, "permissions are not enforced (running as root?)".into()));
+                continue;
+            }
+            Some(guard)
+        } else {
+            None
+        };
+        let mut env = base.env.to_vec();
+        env.extend(probe.env);
+        let opts = RunOptions { env: &env, ..*base };
+        let results = run_cargo_test_once(workspace, &opts)?;
+        let mut flipped: Vec<String> = results
+            .iter()
+            .filter(|(name, passed)| expected.get(name.as_str()).is_some_and(|e| e != *passed))
+            .map(|(name, passed)| {
+                format!("{} ({})", name, if *passed { "now passes" } else { "now fails" })
+            })
+            .collect();
+        flipped.sort();
+        flips.push((probe.name, flipped));
+    }
+
+    println!("\n{}Hermeticity:{}", BOLD, RESET);
+    for (probe, flipped) in &flips {
+        if flipped.is_empty() {
+            println!("  {:<10} {}no change{}", probe, GREEN, RESET);
+        } else {
+            println!("  {:<10} {}{}{}", probe, RED, flipped.join(", "), RESET);
+        }
+    }
+    for (probe, reason) in &skipped {
+        println!("  {:<10} skipped: {}", probe, reason);
+    }
+    Ok(flips.iter().all(|(_, f)| f.is_empty()))
+}
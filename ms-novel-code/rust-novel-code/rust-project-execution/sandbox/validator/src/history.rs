@@ -0,0 +1,292 @@
+//! `--history <db.sqlite>`: every validation recorded in a SQLite
+//! database (its verdict, timings, toolchain and each test's runs) for
+//! `validator report trends` to chart, and for marking the tests whose
+//! outcome flipped across the notebook's recent validations as
+//! historically flaky. Validations of a batch each write their own row,
+//! so the database may be shared between concurrent runs.
+
+use crate::RunMatrix;
+use clap::ValueEnum;
+use rusqlite::{params, Connection};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs,
+    path::Path,
+    process::Stdio,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS validations (
+        id INTEGER PRIMARY KEY,
+        task TEXT NOT NULL,
+        notebook_hash TEXT NOT NULL,
+        finished INTEGER NOT NULL,
+        toolchain TEXT NOT NULL,
+        status TEXT NOT NULL,
+        build_seconds REAL,
+        run_seconds REAL NOT NULL,
+        runs INTEGER NOT NULL,
+        pass INTEGER NOT NULL,
+        fail INTEGER NOT NULL,
+        flaky INTEGER NOT NULL,
+        unsound INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS tests (
+        validation INTEGER NOT NULL REFERENCES validations (id),
+        name TEXT NOT NULL,
+        passed INTEGER NOT NULL,
+        runs INTEGER NOT NULL,
+        PRIMARY KEY (validation, name)
+    );
+    CREATE INDEX IF NOT EXISTS validations_by_task ON validations (task, id);
+    CREATE INDEX IF NOT EXISTS validations_by_notebook ON validations (notebook_hash, id);
+";
+
+/// How long a validation waits for another one's write to finish.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// What `--flake-policy` does with historically flaky tests.
+#[derive(ValueEnum, Clone, Copy, PartialEq)]
+pub enum FlakePolicy {
+    /// Mark them in the table only.
+    Report,
+    /// Also leave them out of the exit code.
+    Exclude,
+}
+
+pub struct History {
+    db: Connection,
+}
+
+/// One validation, to record.
+pub struct Validation<'a> {
+    /// The notebook's stem.
+    pub task: &'a str,
+    /// SHA-256 of the notebook, from [`notebook_hash`].
+    pub notebook_hash: String,
+    /// `rustc --version` of the toolchain the tests built with.
+    pub toolchain: String,
+    /// The `--format` document's status.
+    pub status: &'a str,
+    pub build: Option<Duration>,
+    pub runs: &'a [Duration],
+    pub matrix: &'a RunMatrix,
+}
+
+/// A recorded validation, as the trends read it.
+pub struct Record {
+    pub task: String,
+    /// Unix time it was recorded.
+    pub finished: u64,
+    pub toolchain: String,
+    pub status: String,
+    /// Build and runs together.
+    pub seconds: f64,
+    pub tests: usize,
+    pub pass: usize,
+    pub flaky: usize,
+}
+
+impl Record {
+    pub fn passed(&self) -> bool {
+        self.status == "passed"
+    }
+
+    /// Share of the tests that passed every run; 0 without tests.
+    pub fn pass_rate(&self) -> f64 {
+        if self.tests == 0 {
+            0.0
+        } else {
+            self.pass as f64 / self.tests as f64
+        }
+    }
+}
+
+/// Hex SHA-256 of the notebook at `path`.
+pub fn notebook_hash(path: &Path) -> Result<String, String> {
+    let bytes = fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(Sha256::digest(bytes).iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// `rustc --version` as `opts` runs it in `workspace` (which may pin a
+/// toolchain of its own), or `unknown`.
+pub fn toolchain(workspace: &Path, opts: &crate::RunOptions) -> String {
+    let out = opts.command("rustc").arg("--version").current_dir(workspace).stderr(Stdio::null()).output();
+    match out {
+        Ok(out) if out.status.success() => String::from_utf8_lossy(&out.stdout).trim().to_string(),
+        _ => "unknown".to_string(),
+    }
+}
+
+impl History {
+    /// Open the database at `path`, creating it and its tables as needed.
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let err = |e: rusqlite::Error| format!("{}: {}", path.display(), e);
+        let db = Connection::open(path).map_err(err)?;
+        db.busy_timeout(BUSY_TIMEOUT).map_err(err)?;
+        db.execute_batch(SCHEMA).map_err(err)?;
+        Ok(History { db })
+    }
+
+    /// Record `v` with the time now.
+    pub fn record(&mut self, v: &Validation) -> Result<(), String> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        let totals = v.matrix.totals();
+        let count = |n: usize| n as i64;
+        let tx = self.db.transaction().map_err(|e| e.to_string())?;
+        tx.execute(
+            "INSERT INTO validations (task, notebook_hash, finished, toolchain, status, build_seconds, \
+             run_seconds, runs, pass, fail, flaky, unsound) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+            params![
+                v.task,
+                v.notebook_hash,
+                now as i64,
+                v.toolchain,
+                v.status,
+                v.build.map(|b| b.as_secs_f64()),
+                v.runs.iter().sum::<Duration>().as_secs_f64(),
+                count(v.runs.len()),
+                count(totals.pass),
+                count(totals.fail),
+                count(totals.flaky),
+                count(totals.unsound),
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+        let id = tx.last_insert_rowid();
+        {
+            let mut insert = tx
+                .prepare("INSERT INTO tests (validation, name, passed, runs) VALUES (?1, ?2, ?3, ?4)")
+                .map_err(|e| e.to_string())?;
+            for (name, results) in v.matrix.as_map() {
+                let passed = results.iter().filter(|&&r| r).count();
+                insert.execute(params![id, name, count(passed), count(results.len())]).map_err(|e| e.to_string())?;
+            }
+        }
+        tx.commit().map_err(|e| e.to_string())
+    }
+
+    /// Tests whose outcome flipped in the last `window` validations of
+    /// the notebook hashing to `notebook_hash`: that passed in some and
+    /// failed in others, or both within one.
+    pub fn flipped(&self, notebook_hash: &str, window: usize) -> Result<BTreeSet<String>, String> {
+        let mut query = self
+            .db
+            .prepare(
+                "SELECT name, passed, tests.runs FROM tests JOIN (
+                     SELECT id FROM validations WHERE notebook_hash = ?1 ORDER BY id DESC LIMIT ?2
+                 ) AS recent ON tests.validation = recent.id",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = query
+            .query_map(params![notebook_hash, window as i64], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?, row.get::<_, i64>(2)?))
+            })
+            .map_err(|e| e.to_string())?;
+        // (passed in some validation, failed in some validation) per test
+        let mut outcomes: HashMap<String, (bool, bool)> = HashMap::new();
+        for row in rows {
+            let (name, passed, runs) = row.map_err(|e| e.to_string())?;
+            let seen = outcomes.entry(name).or_default();
+            seen.0 |= passed > 0;
+            seen.1 |= passed < runs;
+        }
+        Ok(outcomes.into_iter().filter(|(_, (passed, failed))| *passed && *failed).map(|(name, _)| name).collect())
+    }
+
+    /// The last `last` validations of every task, by task and then
+    /// oldest first.
+    pub fn recent(&self, last: usize) -> Result<Vec<Record>, String> {
+        let mut query = self
+            .db
+            .prepare(
+                "SELECT task, finished, toolchain, status, COALESCE(build_seconds, 0) + run_seconds, \
+                 pass + fail + flaky + unsound, pass, flaky FROM (
+                     SELECT *, ROW_NUMBER() OVER (PARTITION BY task ORDER BY id DESC) AS age FROM validations
+                 ) WHERE age <= ?1 ORDER BY task, id",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = query
+            .query_map([last as i64], |row| {
+                Ok(Record {
+                    task: row.get(0)?,
+                    finished: row.get::<_, i64>(1)? as u64,
+                    toolchain: row.get(2)?,
+                    status: row.get(3)?,
+                    seconds: row.get(4)?,
+                    tests: row.get::<_, i64>(5)? as usize,
+                    pass: row.get::<_, i64>(6)? as usize,
+                    flaky: row.get::<_, i64>(7)? as usize,
+                })
+            })
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn matrix(runs: &[&[(&str, bool)]]) -> RunMatrix {
+        let mut matrix = RunMatrix::new();
+        for run in runs {
+            matrix.record(run.iter().map(|&(name, passed)| (name.to_string(), passed)).collect::<HashMap<_, _>>());
+        }
+        matrix
+    }
+
+    const RUNS: &[Duration] = &[Duration::from_secs(3)];
+
+    fn validation<'a>(task: &'a str, status: &'a str, matrix: &'a RunMatrix) -> Validation<'a> {
+        Validation {
+            task,
+            notebook_hash: format!("hash of {}", task),
+            toolchain: "rustc 1.88.0".to_string(),
+            status,
+            build: Some(Duration::from_secs(2)),
+            runs: RUNS,
+            matrix,
+        }
+    }
+
+    #[test]
+    fn recent_keeps_the_last_validations_of_each_task() {
+        let mut history = History::open(Path::new(":memory:")).unwrap();
+        let good = matrix(&[&[("a", true), ("b", true)]]);
+        let bad = matrix(&[&[("a", true), ("b", false)]]);
+        for (task, status, m) in [("x", "passed", &good), ("x", "failed", &bad), ("y", "passed", &good), ("x", "passed", &good)] {
+            history.record(&validation(task, status, m)).unwrap();
+        }
+        let records = history.recent(2).unwrap();
+        let seen: Vec<(&str, &str)> = records.iter().map(|r| (r.task.as_str(), r.status.as_str())).collect();
+        assert_eq!(seen, [("x", "failed"), ("x", "passed"), ("y", "passed")]);
+        assert_eq!((records[0].pass_rate(), records[0].seconds), (0.5, 5.0));
+    }
+
+    #[test]
+    fn flipped_tests_are_those_whose_outcome_changed_within_the_window() {
+        let mut history = History::open(Path::new(":memory:")).unwrap();
+        let matrices = [
+            matrix(&[&[("steady", true), ("late", true), ("early", false), ("within", true), ("broken", false)]]),
+            matrix(&[&[("steady", true), ("late", true), ("early", true), ("within", true), ("broken", false)]]),
+            matrix(&[
+                &[("steady", true), ("late", false), ("early", true), ("within", true), ("broken", false)],
+                &[("steady", true), ("late", false), ("early", true), ("within", false), ("broken", false)],
+            ]),
+        ];
+        for m in &matrices {
+            history.record(&validation("x", "failed", m)).unwrap();
+        }
+        // another notebook's validations do not count
+        history.record(&validation("y", "failed", &matrices[0])).unwrap();
+        let flipped = |window| history.flipped("hash of x", window).unwrap().into_iter().collect::<Vec<_>>();
+        assert_eq!(flipped(3), ["early", "late", "within"]);
+        assert_eq!(flipped(2), ["late", "within"]);
+        assert_eq!(flipped(1), ["within"]);
+        assert!(history.flipped("hash of z", 3).unwrap().is_empty());
+    }
+}
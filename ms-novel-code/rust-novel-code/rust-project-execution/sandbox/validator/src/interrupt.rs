@@ -0,0 +1,100 @@
+//! Ctrl-C handling. Cargo children run in their own process groups so
+//! the handler can take down the whole tree (cargo, rustc, test binaries)
+//! instead of orphaning it with target directories still locked.
+//!
+//! Interrupted while a workspace is being prepared, the partial workspace
+//! is removed. Interrupted during the runs, the main loop prints what it
+//! has, marked as interrupted. Either way the exit code is [`EXIT_CODE`],
+//! though the library leaves exiting to its caller.
+
+use crate::reaper;
+use std::{
+    fs,
+    io,
+    os::unix::process::CommandExt,
+    path::{Path, PathBuf},
+    process::{Child, Command},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
+
+/// Exit code after an interrupt, as shells report for SIGINT.
+pub const EXIT_CODE: i32 = 130;
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+/// Whether the run loop reports and exits itself after an interrupt.
+static DEFERRED: AtomicBool = AtomicBool::new(false);
+/// Process groups of the running children.
+static GROUPS: Mutex<Vec<u32>> = Mutex::new(Vec::new());
+/// Workspace being written, removed if interrupted meanwhile.
+static PREPARING: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// What the validator was doing when interrupted outside the run loop.
+pub enum Interrupted {
+    /// Preparing this workspace, which has been removed.
+    Preparing(PathBuf),
+    /// Anything else.
+    Elsewhere,
+}
+
+/// Handle Ctrl-C: kill the children, remove a partial workspace, and
+/// unless the exit is deferred call `on_interrupt`, which is expected not
+/// to return (the caller picks the exit, normally [`EXIT_CODE`]).
+pub fn install(on_interrupt: impl Fn(Interrupted) + Send + 'static) -> Result<(), String> {
+    ctrlc::set_handler(move || {
+        INTERRUPTED.store(true, Ordering::SeqCst);
+        for &group in GROUPS.lock().unwrap_or_else(|e| e.into_inner()).iter() {
+            // SAFETY: kill(2) has no memory-safety preconditions
+            unsafe { libc::kill(-(group as i32), libc::SIGKILL) };
+        }
+        if let Some(ws) = PREPARING.lock().unwrap_or_else(|e| e.into_inner()).take() {
+            let _ = fs::remove_dir_all(&ws);
+            on_interrupt(Interrupted::Preparing(ws));
+        } else if !DEFERRED.load(Ordering::SeqCst) {
+            on_interrupt(Interrupted::Elsewhere);
+        }
+    })
+    .map_err(|e| e.to_string())
+}
+
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// Mark `workspace` as being prepared (`None` once it is complete).
+pub fn preparing(workspace: Option<&Path>) {
+    *PREPARING.lock().unwrap_or_else(|e| e.into_inner()) = workspace.map(Path::to_path_buf);
+}
+
+/// While set, an interrupt only kills the children and leaves reporting
+/// and exiting to the caller, which checks [`interrupted`].
+pub fn defer_exit(defer: bool) {
+    DEFERRED.store(defer, Ordering::SeqCst);
+}
+
+/// Spawn `cmd` in a process group of its own and track it until
+/// [`reap`] is called.
+pub fn spawn(cmd: &mut Command) -> io::Result<Child> {
+    let mut groups = GROUPS.lock().unwrap_or_else(|e| e.into_inner());
+    if interrupted() {
+        return Err(io::Error::new(io::ErrorKind::Interrupted, "interrupted"));
+    }
+    let child = cmd.process_group(0).spawn()?;
+    groups.push(child.id());
+    reaper::track(child.id());
+    Ok(child)
+}
+
+/// Kill `child` and everything it started.
+pub fn kill(child: &Child) {
+    // SAFETY: kill(2) has no memory-safety preconditions
+    unsafe { libc::kill(-(child.id() as i32), libc::SIGKILL) };
+}
+
+/// Stop tracking `child`, which has exited or been killed.
+pub fn reap(child: &Child) {
+    GROUPS.lock().unwrap_or_else(|e| e.into_inner()).retain(|&g| g != child.id());
+    reaper::untrack(child.id());
+}
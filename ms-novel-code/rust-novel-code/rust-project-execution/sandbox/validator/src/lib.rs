@@ -0,0 +1,863 @@
+//! Validation of Rust-task notebooks as a library: load a notebook
+//! ([`Notebook::load`]), extract its crate ([`Workspace::prepare`]),
+//! build and run its tests ([`TestRunner`]) and classify the outcomes
+//! ([`RunMatrix`]). The `validator` binary is the command-line front end;
+//! a grading server or GUI can embed the same pipeline.
+
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt, fs,
+    io::{self, BufRead, ErrorKind, Read},
+    path::{Component, Path, PathBuf},
+    process::Command,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+use wait_timeout::ChildExt;
+
+pub mod audit;
+pub mod bundle;
+pub mod cache;
+pub mod candidates;
+pub mod config;
+pub mod coverage;
+pub mod crash;
+pub mod deps;
+pub mod diagnostics;
+pub mod difficulty;
+pub mod digest;
+pub mod doctor;
+pub mod expected;
+pub mod export;
+pub mod fix;
+pub mod fixtures;
+pub mod hermetic;
+pub mod history;
+pub mod interrupt;
+pub mod lint;
+pub mod lock;
+pub mod manifest;
+pub mod matrix;
+pub mod memory;
+pub mod msrv;
+pub mod nightly;
+pub mod patch;
+pub mod policy;
+pub mod quality;
+pub mod reaper;
+pub mod redact;
+pub mod report;
+pub mod resources;
+pub mod runs;
+pub mod sandbox;
+pub mod shard;
+pub mod shell;
+pub mod similarity;
+pub mod snapshot;
+pub mod snapshot_tests;
+pub mod source;
+pub mod stages;
+pub mod structure;
+pub mod style;
+pub mod toolchains;
+pub mod traceability;
+pub mod trends;
+pub mod ub;
+pub mod upload;
+pub use matrix::{Classification, RunMatrix};
+use manifest::Manifest;
+
+/// Package name of the generated workspace, which tests import.
+pub const CRATE_NAME: &str = "task_ws";
+
+/// Variable carrying the `--seed-matrix` seed to the tests.
+pub const TEST_SEED_VAR: &str = "VALIDATOR_TEST_SEED";
+
+/// ANSI color codes
+pub mod colors {
+    pub const RESET: &str = "\x1B[0m";
+    pub const RED: &str   = "\x1B[91m";
+    pub const GREEN: &str = "\x1B[92m";
+    pub const BLUE: &str  = "\x1B[94m";
+    pub const BOLD: &str  = "\x1B[1m";
+}
+
+
+/// Notebook sections `prepare_workspace` extracts; `files` are the
+/// `# file: <path>` cells, which write their fenced block to any path in
+/// the workspace (`src/solver.rs`, a second `tests/*.rs`, fixtures).
+#[derive(ValueEnum, Clone, Copy, PartialEq)]
+pub enum Section {
+    Lib,
+    Main,
+    #[value(alias = "tests")]
+    Test,
+    Build,
+    Testutil,
+    Snapshots,
+    Files,
+    Deps,
+}
+
+impl Section {
+    pub const ALL: [Section; 8] = [
+        Section::Lib,
+        Section::Main,
+        Section::Test,
+        Section::Build,
+        Section::Testutil,
+        Section::Snapshots,
+        Section::Files,
+        Section::Deps,
+    ];
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "cell_type", rename_all = "lowercase")]
+pub enum Cell {
+    Markdown { source: Vec<String> },
+    Code     { source: Vec<String> },
+}
+
+#[derive(Deserialize)]
+pub struct Notebook { pub cells: Vec<Cell> }
+
+impl Notebook {
+    /// Read and parse the `.ipynb` file at `path`.
+    pub fn load(path: &Path) -> io::Result<Notebook> {
+        if !path.exists() {
+            return Err(io::Error::new(ErrorKind::NotFound, "Notebook file not found"));
+        }
+        if path.extension().and_then(|s| s.to_str()) != Some("ipynb") {
+            return Err(io::Error::new(ErrorKind::InvalidInput, "Expected a .ipynb file"));
+        }
+        let raw = fs::read_to_string(path)?;
+        serde_json::from_str(&raw)
+            .map_err(|e| io::Error::other(format!("JSON error: {}", e)))
+    }
+}
+
+fn extract_rust_block(lines: &[String]) -> String {
+    // one line each, not the notebook's `\n`-terminated source lines
+    // joined with another `\n`, which rustfmt would undo
+    let mut code = extract_fenced_lines(lines, "rust").join("\n");
+    code.push('\n');
+    code
+}
+
+/// Lines of the first fenced block whose info string starts with `lang`
+/// (an empty `lang` accepts any fence), without trailing newlines.
+fn extract_fenced_lines(lines: &[String], lang: &str) -> Vec<String> {
+    let mut in_block = false;
+    let mut out = Vec::new();
+    for line in lines.iter().flat_map(|l| l.lines()) {
+        let t = line.trim_start();
+        if !in_block {
+            in_block = t.strip_prefix("```").is_some_and(|info| info.starts_with(lang));
+            continue;
+        }
+        if t.starts_with("```") {
+            break;
+        }
+        out.push(line.to_string());
+    }
+    out
+}
+
+/// Target path of a `# file:<path>` cell, if this is one.
+fn file_cell_path(lines: &[String]) -> Option<&str> {
+    lines
+        .iter()
+        .find_map(|l| l.trim().strip_prefix("# file:"))
+        .map(str::trim)
+}
+
+/// Resolve a fixture path inside `workspace`, refusing anything that could
+/// escape it (absolute paths, `..`, drive prefixes).
+fn sanitize_relative_path(workspace: &Path, rel: &str) -> Result<PathBuf, String> {
+    let path = Path::new(rel);
+    if rel.is_empty() || !path.components().all(|c| matches!(c, Component::Normal(_))) {
+        return Err(format!("`# file:{}`: path must be relative and stay inside the workspace", rel));
+    }
+    Ok(workspace.join(path))
+}
+
+/// Collect `KEY=VALUE` pairs from the manifest's `[env]` table and the
+/// `# env` cell; they are exported to the test process and echoed in the report.
+pub fn extract_env(nb: &Notebook, manifest: &Manifest) -> Result<Vec<(String, String)>, String> {
+    let mut env: Vec<(String, String)> =
+        manifest.env.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+    for cell in &nb.cells {
+        let src = match cell {
+            Cell::Markdown { source } | Cell::Code { source } => source,
+        };
+        let joined = src.join("");
+        if file_cell_path(src).is_some() || !(has_marker(&joined, "env") && joined.contains("```")) {
+            continue;
+        }
+        for line in extract_fenced_lines(src, "") {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| format!("`# env`: expected KEY=VALUE, got `{}`", line))?;
+            let key = key.trim();
+            if key.is_empty() || key.contains(char::is_whitespace) {
+                return Err(format!("`# env`: invalid variable name `{}`", key));
+            }
+            env.retain(|(k, _)| k != key);
+            env.push((key.to_string(), value.trim().to_string()));
+        }
+    }
+    Ok(env)
+}
+
+/// Whether `joined` has the marker `# <name>` itself, not just a longer
+/// one starting with it (`# test` in `# testutil`).
+fn has_marker(joined: &str, name: &str) -> bool {
+    let marker = format!("# {}", name);
+    joined.match_indices(&marker).any(|(i, _)| {
+        !joined[i + marker.len()..].starts_with(|c: char| c.is_alphanumeric() || c == '_')
+    })
+}
+
+/// Record `rel` as written by a cell, refusing a second cell for the
+/// same file (the generated `Cargo.toml` is not a cell's).
+fn claim(files: &mut Vec<String>, claimed: &mut HashSet<String>, rel: &str) -> Result<(), String> {
+    if !claimed.insert(rel.to_string()) {
+        return Err(format!("`{}` is written by more than one cell", rel));
+    }
+    if !files.iter().any(|f| f == rel) {
+        files.push(rel.to_string());
+    }
+    Ok(())
+}
+
+/// Whether `rel` is an integration test target, `tests/<name>.rs`.
+fn is_test_target(rel: &str) -> bool {
+    rel.strip_prefix("tests/").is_some_and(|name| !name.contains('/') && name.ends_with(".rs"))
+}
+
+pub fn prepare_workspace(
+    nb: &Notebook,
+    workspace: &Path,
+    sections: &[Section],
+    allowed: Option<&policy::Allowed>,
+) -> Result<Vec<String>, String> {
+    if workspace.exists() {
+        fs::remove_dir_all(workspace).map_err(|e| e.to_string())?;
+    }
+    fs::create_dir_all(workspace).map_err(|e| e.to_string())?;
+
+    fs::write(
+        workspace.join("Cargo.toml"),
+        format!(
+            "[package]\nname = \"{}\"\nversion = \"0.1.0\"\nedition = \"2021\"\n[dependencies]\n",
+            CRATE_NAME
+        ),
+    ).map_err(|e| e.to_string())?;
+
+    let mut seen = HashMap::new();
+    let mut files = vec!["Cargo.toml".into()];
+    let mut claimed = HashSet::new();
+    let mut testutil = None;
+    let mut deps_cell = None;
+
+    for cell in &nb.cells {
+        let src = match cell {
+            Cell::Markdown { source } | Cell::Code { source } => source,
+        };
+        let joined = src.join("");
+
+        // Fixture cells are opaque text; never scan them for other markers
+        if let Some(rel) = file_cell_path(src) {
+            if !sections.contains(&Section::Files) {
+                continue;
+            }
+            let dest = sanitize_relative_path(workspace, rel)?;
+            if let Some(dir) = dest.parent() {
+                fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+            }
+            let mut content = extract_fenced_lines(src, "").join("\n");
+            content.push('\n');
+            fs::write(&dest, content).map_err(|e| e.to_string())?;
+            claim(&mut files, &mut claimed, rel)?;
+            continue;
+        }
+
+        if sections.contains(&Section::Lib) && joined.contains("# lib") && joined.contains("```rust") {
+            let dir = workspace.join("src");
+            fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+            fs::write(dir.join("lib.rs"), extract_rust_block(src))
+                .map_err(|e| e.to_string())?;
+            seen.insert("lib", true);
+            claim(&mut files, &mut claimed, "src/lib.rs")?;
+        }
+        if sections.contains(&Section::Main) && joined.contains("# main") && joined.contains("```rust") {
+            let dir = workspace.join("src");
+            fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+            fs::write(dir.join("main.rs"), extract_rust_block(src))
+                .map_err(|e| e.to_string())?;
+            seen.insert("main", true);
+            claim(&mut files, &mut claimed, "src/main.rs")?;
+        }
+        if sections.contains(&Section::Test) && has_marker(&joined, "test") && joined.contains("```rust") {
+            let dir = workspace.join("tests");
+            fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+            fs::write(dir.join("integration.rs"), extract_rust_block(src))
+                .map_err(|e| e.to_string())?;
+            seen.insert("test", true);
+            claim(&mut files, &mut claimed, "tests/integration.rs")?;
+        }
+        if sections.contains(&Section::Build) && joined.contains("# build") && joined.contains("```rust") {
+            fs::write(workspace.join("build.rs"), extract_rust_block(src))
+                .map_err(|e| e.to_string())?;
+            seen.insert("build", true);
+            claim(&mut files, &mut claimed, "build.rs")?;
+        }
+        if sections.contains(&Section::Snapshots) && has_marker(&joined, "snapshots") {
+            for rel in snapshot_tests::extract(src, workspace)? {
+                claim(&mut files, &mut claimed, &rel)?;
+            }
+        }
+        if sections.contains(&Section::Testutil) && has_marker(&joined, "testutil") && joined.contains("```rust") {
+            testutil = Some(extract_rust_block(src));
+        }
+        if sections.contains(&Section::Deps) && has_marker(&joined, "deps") && joined.contains("```") {
+            deps_cell = Some(extract_fenced_lines(src, "").join("\n"));
+        }
+    }
+
+    // helpers shared by the tests; `mod common;` in the test cell uses
+    // them, and a lib declaring `mod test_support` gets a copy for its
+    // unit tests
+    if let Some(code) = testutil {
+        let dir = workspace.join("tests/common");
+        fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+        fs::write(dir.join("mod.rs"), &code).map_err(|e| e.to_string())?;
+        claim(&mut files, &mut claimed, "tests/common/mod.rs")?;
+        let lib = fs::read_to_string(workspace.join("src/lib.rs")).unwrap_or_default();
+        if source::words(&source::strip_comments_and_strings(&lib))
+            .collect::<Vec<_>>()
+            .windows(2)
+            .any(|w| w == ["mod", "test_support"])
+        {
+            fs::write(workspace.join("src/test_support.rs"), &code).map_err(|e| e.to_string())?;
+            claim(&mut files, &mut claimed, "src/test_support.rs")?;
+        }
+    }
+
+    // merged last, into a `# file: Cargo.toml` too if there is one
+    if let Some(cell) = deps_cell {
+        let path = workspace.join("Cargo.toml");
+        let raw = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+        let mut manifest: toml::Table = raw.parse().map_err(|e| format!("Cargo.toml: {}", e))?;
+        deps::merge(&mut manifest, &cell)?;
+        fs::write(&path, manifest.to_string()).map_err(|e| e.to_string())?;
+    }
+
+    // `# file:` cells may stand in for the shorthands
+    let from_files = |req: &str| match req {
+        "lib" => claimed.contains("src/lib.rs"),
+        "main" => claimed.contains("src/main.rs"),
+        _ => claimed.iter().any(|f| is_test_target(f)),
+    };
+    for (req, section) in [("lib", Section::Lib), ("main", Section::Main), ("test", Section::Test)] {
+        if sections.contains(&section) && !seen.contains_key(req) && !from_files(req) {
+            return Err(format!("Missing required code section: `# {}`", req));
+        }
+    }
+    if let Some(allowed) = allowed {
+        allowed.check(workspace)?;
+    }
+    Ok(files)
+}
+
+/// A notebook's crate, extracted to disk.
+pub struct Workspace {
+    pub path: PathBuf,
+    /// Files written, relative to `path`.
+    pub files: Vec<String>,
+}
+
+impl Workspace {
+    /// Extract `sections` of `nb` into a fresh crate at `path`, replacing
+    /// whatever was there.
+    pub fn prepare(
+        nb: &Notebook,
+        path: impl Into<PathBuf>,
+        sections: &[Section],
+        allowed: Option<&policy::Allowed>,
+    ) -> Result<Workspace, String> {
+        let path = path.into();
+        let files = prepare_workspace(nb, &path, sections, allowed)?;
+        Ok(Workspace { path, files })
+    }
+}
+
+/// Builds a workspace's tests and runs them repeatedly.
+pub struct TestRunner<'a> {
+    workspace: &'a Workspace,
+    opts: RunOptions<'a>,
+}
+
+impl<'a> TestRunner<'a> {
+    pub fn new(workspace: &'a Workspace, opts: RunOptions<'a>) -> Self {
+        TestRunner { workspace, opts }
+    }
+
+    /// Compile the tests; returns the build time.
+    pub fn build(&self) -> Result<Duration, BuildError> {
+        build_tests(&self.workspace.path, &self.opts)
+    }
+
+    /// Run the suite `n` times, stopping at the first run that gives no
+    /// results or at Ctrl-C.
+    pub fn run(&self, n: usize) -> Result<RunMatrix, RunError> {
+        let mut matrix = RunMatrix::new();
+        for _ in 0..n {
+            if interrupt::interrupted() {
+                return Err("interrupted".to_string().into());
+            }
+            let run = run_cargo_test_capture(&self.workspace.path, &self.opts)?;
+            matrix.record(run.outcomes);
+            matrix.record_times(run.times);
+        }
+        Ok(matrix)
+    }
+}
+
+/// Busy-loop worker threads that keep every core contended until dropped.
+pub struct CpuStress {
+    stop: Arc<AtomicBool>,
+    workers: Vec<thread::JoinHandle<()>>,
+}
+
+impl CpuStress {
+    pub fn start(threads: usize) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let workers = (0..threads)
+            .map(|_| {
+                let stop = Arc::clone(&stop);
+                thread::spawn(move || {
+                    let mut x: u64 = 0x9E37_79B9_7F4A_7C15;
+                    while !stop.load(Ordering::Relaxed) {
+                        for _ in 0..10_000 {
+                            x ^= x << 13;
+                            x ^= x >> 7;
+                            x ^= x << 17;
+                        }
+                        std::hint::black_box(x);
+                    }
+                })
+            })
+            .collect();
+        CpuStress { stop, workers }
+    }
+}
+
+impl Drop for CpuStress {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// Per-run knobs for the `cargo test` child process.
+pub struct RunOptions<'a> {
+    /// Seconds allowed for compiling the tests.
+    pub build_timeout: u64,
+    /// Seconds allowed for each run of the suite.
+    pub timeout: u64,
+    /// With a seed, libtest runs the tests in seeded random order. Its
+    /// shuffle flags are unstable, so `RUSTC_BOOTSTRAP` unlocks them on stable.
+    pub shuffle_seed: Option<u64>,
+    pub env: &'a [(String, String)],
+    /// Run only these tests (exact names); none at all when empty.
+    pub tests: Option<&'a [String]>,
+    /// Whether `env` is the whole environment rather than additions to
+    /// the validator's own.
+    pub hermetic: bool,
+    /// Let crashing tests dump core, for the crash report.
+    pub core_dumps: bool,
+    /// Sample the run's resource usage this often.
+    pub sample_interval: Option<Duration>,
+    /// Limits and isolation of the processes run, except the build.
+    pub limits: sandbox::Limits,
+    /// Keep cargo's stderr in [`RunOutput::stderr`] only, for runs
+    /// sharing the terminal with others.
+    pub buffered: bool,
+    /// Time each test with libtest's unstable `--report-time`, for
+    /// [`RunOutput::times`]; left empty otherwise.
+    pub report_time: bool,
+}
+
+impl<'a> RunOptions<'a> {
+    /// Options with the command line's default timeouts, in order and
+    /// unsampled, adding `env` to the validator's environment.
+    pub fn new(env: &'a [(String, String)]) -> Self {
+        RunOptions {
+            build_timeout: 120,
+            timeout: 120,
+            shuffle_seed: None,
+            env,
+            tests: None,
+            hermetic: false,
+            core_dumps: false,
+            sample_interval: None,
+            limits: sandbox::Limits::default(),
+            buffered: false,
+            report_time: false,
+        }
+    }
+
+    /// `program` with the environment these options give it.
+    pub fn command(&self, program: impl AsRef<std::ffi::OsStr>) -> Command {
+        let mut cmd = Command::new(program);
+        if self.hermetic {
+            cmd.env_clear();
+        }
+        cmd.envs(self.env.iter().map(|(k, v)| (k, v)));
+        self.limits.apply(&mut cmd);
+        cmd
+    }
+}
+
+/// Variables a hermetic child keeps from the validator's environment:
+/// what cargo and rustup need to find toolchains, caches and scratch space.
+const HERMETIC_KEEP: [&str; 9] = [
+    "PATH", "HOME", "USER", "TMPDIR", "CARGO_HOME", "RUSTUP_HOME", "RUSTUP_TOOLCHAIN",
+    "CARGO_TARGET_DIR", "CARGO_NET_OFFLINE",
+];
+
+/// Variables a hermetic child always gets, unless the notebook sets them.
+const HERMETIC_PINNED: [(&str, &str); 4] =
+    [("TZ", "UTC"), ("LANG", "C.UTF-8"), ("LC_ALL", "C.UTF-8"), ("RUST_BACKTRACE", "1")];
+
+/// The whole environment of a hermetic child: the allowlisted parent
+/// variables, the pinned ones, then the notebook's.
+pub fn hermetic_env(task_env: &[(String, String)]) -> Vec<(String, String)> {
+    let mut env: Vec<(String, String)> = HERMETIC_KEEP
+        .iter()
+        .filter_map(|k| std::env::var(k).ok().map(|v| (k.to_string(), v)))
+        .collect();
+    env.extend(HERMETIC_PINNED.iter().map(|(k, v)| (k.to_string(), v.to_string())));
+    env.extend(task_env.iter().cloned());
+    env
+}
+
+/// Why `build_tests` failed, with the compile errors if it got that far.
+pub struct BuildError {
+    pub message: String,
+    pub errors: Vec<diagnostics::CompileError>,
+}
+
+impl From<String> for BuildError {
+    fn from(message: String) -> Self {
+        BuildError { message, errors: Vec::new() }
+    }
+}
+
+/// The message, then the compile errors counted by category.
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)?;
+        match diagnostics::summarize(&self.errors) {
+            Some(summary) => write!(f, "; {}", summary),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Why cargo could not be started, pointing at `validator doctor` when
+/// it is not installed.
+fn spawn_error(e: io::Error) -> String {
+    match e.kind() {
+        io::ErrorKind::NotFound => format!("cargo: {} (run `validator doctor`)", e),
+        _ => e.to_string(),
+    }
+}
+
+/// Compile the test targets without running them, so the build is timed
+/// (and fails) separately from the runs. Returns the build time.
+pub fn build_tests(workspace: &Path, opts: &RunOptions) -> Result<Duration, BuildError> {
+    let t0 = Instant::now();
+    let opts = RunOptions { limits: sandbox::Limits::default(), ..*opts };
+    let mut child = interrupt::spawn(
+        opts.command("cargo")
+            .args(["test", "--no-run", "--color=never", "--message-format=json"])
+            .current_dir(workspace)
+            .stdout(std::process::Stdio::piped()),
+    )
+    .map_err(spawn_error)?;
+    // drain the messages while waiting, or a chatty build fills the pipe
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let reader = thread::spawn(move || {
+        io::BufReader::new(stdout)
+            .lines()
+            .map_while(Result::ok)
+            .filter_map(|line| diagnostics::CompileError::from_message(&line))
+            .collect::<Vec<_>>()
+    });
+    let status = child.wait_timeout(Duration::from_secs(opts.build_timeout));
+    interrupt::reap(&child);
+    match status.map_err(|e| e.to_string())? {
+        Some(s) if s.success() => Ok(t0.elapsed()),
+        Some(s) => Err(BuildError {
+            message: format!("build failed (exit {:?})", s.code()),
+            errors: reader.join().unwrap_or_default(),
+        }),
+        None => {
+            let _ = child.kill();
+            Err(format!("Build timeout reached ({}s)", opts.build_timeout).into())
+        }
+    }
+}
+
+/// Names of every test in the workspace, as libtest reports them.
+pub fn list_tests(workspace: &Path, opts: &RunOptions) -> Result<Vec<String>, String> {
+    let out = opts.command("cargo")
+        .args(["test", "-q", "--color=never", "--", "--list", "--format", "terse"])
+        .current_dir(workspace)
+        .output()
+        .map_err(|e| e.to_string())?;
+    if !out.status.success() {
+        return Err(format!("listing tests failed (exit {:?})", out.status.code()));
+    }
+    Ok(String::from_utf8_lossy(&out.stdout)
+        .lines()
+        .filter_map(|l| l.strip_suffix(": test"))
+        .map(str::to_string)
+        .collect())
+}
+
+/// Why a `cargo test` run gave no results.
+pub struct RunError {
+    pub message: String,
+    /// After a timeout, the outcomes of the tests that had finished.
+    pub finished: Option<HashMap<String, bool>>,
+    /// Resource usage up to the failure, with `--sample-interval`.
+    pub samples: Vec<resources::Sample>,
+}
+
+impl From<String> for RunError {
+    fn from(message: String) -> Self {
+        RunError { message, finished: None, samples: Vec::new() }
+    }
+}
+
+impl fmt::Display for RunError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// `test <name> ... ok/FAILED` lines of libtest's output, with the
+/// duration `--report-time` appends (`ok <0.012s>`) when it was passed; a
+/// test cut off by a timeout has no outcome yet.
+fn parse_outcomes(stdout: &str) -> (HashMap<String, bool>, HashMap<String, Duration>) {
+    let mut map = HashMap::new();
+    let mut times = HashMap::new();
+    for line in stdout.lines() {
+        if let Some(rest) = line.strip_prefix("test ") {
+            let mut parts = rest.split(" ... ");
+            if let (Some(name), Some(res)) = (parts.next(), parts.next()) {
+                let (res, time) = res.trim().split_once(' ').unwrap_or((res.trim(), ""));
+                if res.is_empty() {
+                    continue;
+                }
+                map.insert(name.to_string(), res == "ok");
+                let secs = time.strip_prefix('<').and_then(|t| t.strip_suffix("s>"));
+                if let Some(secs) = secs.and_then(|t| t.parse::<f64>().ok()) {
+                    times.insert(name.to_string(), Duration::from_secs_f64(secs));
+                }
+            }
+        }
+    }
+    (map, times)
+}
+
+/// Run `cargo test` once, capture each test’s pass/fail outcome.
+pub fn run_cargo_test_once(
+    workspace: &Path,
+    opts: &RunOptions,
+) -> Result<HashMap<String,bool>, String> {
+    run_cargo_test_capture(workspace, opts).map(|run| run.outcomes).map_err(|e| e.message)
+}
+
+/// Everything one `cargo test` run tells.
+pub struct RunOutput {
+    pub outcomes: HashMap<String, bool>,
+    /// How long each test that finished took.
+    pub times: HashMap<String, Duration>,
+    /// libtest's stdout.
+    pub stdout: String,
+    /// cargo's stderr, passed through as well unless `buffered`.
+    pub stderr: String,
+    /// Tests that killed their test binary.
+    pub crashes: Vec<crash::Crash>,
+    /// Resource usage over the run, with `--sample-interval`.
+    pub samples: Vec<resources::Sample>,
+    /// Tests stopped by a sandbox limit.
+    pub violations: Vec<(String, sandbox::Violation)>,
+}
+
+/// `run_cargo_test_once`, also returning libtest's stdout and crashes.
+pub fn run_cargo_test_capture(workspace: &Path, opts: &RunOptions) -> Result<RunOutput, RunError> {
+    let mut cmd = opts.command("cargo");
+    cmd.arg("test")
+        .arg("--color=never")
+        .current_dir(workspace)
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped());
+    // mismatched or missing insta snapshots leave `.snap.new` files
+    if !opts.env.iter().any(|(k, _)| k == "INSTA_UPDATE") {
+        cmd.env("INSTA_UPDATE", "new");
+    }
+    // per-test durations and seeded order are unstable libtest flags;
+    // `RUSTC_BOOTSTRAP` unlocks them without invalidating the build
+    let mut test_args: Vec<String> = Vec::new();
+    if opts.report_time || opts.shuffle_seed.is_some() {
+        cmd.env("RUSTC_BOOTSTRAP", "1");
+        test_args.extend(["-Z", "unstable-options"].map(String::from));
+    }
+    if opts.report_time {
+        test_args.push("--report-time".to_string());
+    }
+    if let Some(seed) = opts.shuffle_seed {
+        test_args.extend(["--shuffle-seed".to_string(), seed.to_string()]);
+    }
+    if let Some(tests) = opts.tests {
+        if tests.is_empty() {
+            return Ok(RunOutput {
+                outcomes: HashMap::new(),
+                times: HashMap::new(),
+                stdout: String::new(),
+                stderr: String::new(),
+                crashes: Vec::new(),
+                samples: Vec::new(),
+                violations: Vec::new(),
+            });
+        }
+        test_args.push("--exact".to_string());
+        test_args.extend(tests.iter().cloned());
+    }
+    cmd.arg("--").args(test_args);
+    let mut child = interrupt::spawn(&mut cmd).map_err(spawn_error)?;
+    let sampler = opts.sample_interval.map(|every| resources::Sampler::start(child.id(), every));
+
+    // read stdout, and pass stderr through while keeping a copy, as the
+    // run goes so neither pipe fills up
+    let mut out = child.stdout.take().expect("stdout is piped");
+    let stdout = thread::spawn(move || {
+        let mut buf = String::new();
+        let _ = out.read_to_string(&mut buf);
+        buf
+    });
+    let err = child.stderr.take().expect("stderr is piped");
+    let echo = !opts.buffered;
+    let stderr = thread::spawn(move || {
+        let mut copy = String::new();
+        for line in io::BufReader::new(err).lines().map_while(Result::ok) {
+            if echo {
+                eprintln!("{}", line);
+            }
+            copy += &line;
+            copy.push('\n');
+        }
+        copy
+    });
+
+    // wait with timeout
+    let status = child.wait_timeout(Duration::from_secs(opts.timeout));
+    let status = status.map_err(|e| e.to_string())?;
+    if status.is_none() {
+        // the whole group, or the test binary keeps the pipe open
+        interrupt::kill(&child);
+    }
+    interrupt::reap(&child);
+    let samples = sampler.map(resources::Sampler::finish).unwrap_or_default();
+    let buf = stdout.join().unwrap_or_default();
+    let stderr = stderr.join().unwrap_or_default();
+
+    // parse lines: test <name> ... ok/FAILED <time>
+    let (mut map, times) = parse_outcomes(&buf);
+
+    let Some(status) = status else {
+        let _ = child.wait();
+        return Err(RunError {
+            message: format!("Test timeout reached ({}s)", opts.timeout),
+            finished: Some(map),
+            samples,
+        });
+    };
+    let mut violations: Vec<_> = digest::failure_output(&buf)
+        .into_iter()
+        .filter_map(|(test, output)| opts.limits.violation(None, &output).map(|v| (test, v)))
+        .collect();
+    let mut crashes = Vec::new();
+    if let Some(exe) = crash::crashed_binary(&stderr) {
+        let investigation = crash::investigate(&exe, &map, workspace, opts)?;
+        map.extend(investigation.found);
+        crashes = investigation.crashes;
+        violations.extend(investigation.violations);
+    }
+    if !status.success() && map.is_empty() {
+        return Err(format!("`cargo test` failed (exit {:?})", status.code()).into());
+    }
+
+    Ok(RunOutput { outcomes: map, times, stdout: buf, stderr, crashes, samples, violations })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_outcomes_and_report_times() {
+        // `d` was cut off by a timeout
+        let stdout = "\nrunning 4 tests\n\
+            test a::fast ... ok <0.012s>\n\
+            test a::slow ... FAILED <1.500s>\n\
+            test c ... ok\n\
+            test d ... \n\
+            \ntest result: FAILED. 2 passed; 1 failed\n";
+        let (outcomes, times) = parse_outcomes(stdout);
+        assert_eq!(outcomes.len(), 3);
+        assert_eq!((outcomes["a::fast"], outcomes["a::slow"], outcomes["c"]), (true, false, true));
+        assert_eq!(times.len(), 2);
+        assert_eq!(times["a::fast"], Duration::from_millis(12));
+        assert_eq!(times["a::slow"], Duration::from_millis(1500));
+    }
+
+    #[test]
+    fn plain_output_has_no_times() {
+        let (outcomes, times) = parse_outcomes("test x ... ok\ntest y ... FAILED\ntest z ... ok <soon>\n");
+        assert_eq!(outcomes.len(), 3);
+        assert!(outcomes["x"] && !outcomes["y"] && outcomes["z"]);
+        assert!(times.is_empty());
+    }
+
+    #[test]
+    fn env_cell_needs_its_own_marker() {
+        let cell = |text: &str| Cell::Markdown { source: text.lines().map(|l| format!("{}\n", l)).collect() };
+        let nb = Notebook {
+            cells: vec![
+                cell("# environment\n\n```\nsetup notes, not variables\n```"),
+                cell("# env\n\n```\nSEED=7\n```"),
+            ],
+        };
+        let env = extract_env(&nb, &Manifest::default()).unwrap();
+        assert_eq!(env, [("SEED".to_string(), "7".to_string())]);
+    }
+}
@@ -0,0 +1,165 @@
+//! `validator lint`: static checks over a notebook, starting with the
+//! problem statement (the `# Prompt` and `# Requirements` cells).
+//!
+//! Each check has a name; the manifest's `[lint]` table sets its level
+//! (`off`, `warn` or `error`) and any thresholds. Errors fail the lint.
+
+use crate::{fix::marker, manifest::LintPolicy, Cell, Notebook};
+use serde::Deserialize;
+use std::fmt;
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    Off,
+    Warn,
+    Error,
+}
+
+/// Every check, with its level when the manifest does not set one.
+pub const CHECKS: [(&str, Level); 4] = [
+    ("min-words", Level::Warn),
+    ("example", Level::Warn),
+    ("constraints", Level::Warn),
+    ("placeholder", Level::Error),
+];
+
+pub struct Finding {
+    pub check: &'static str,
+    pub level: Level,
+    pub message: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let level = if self.level == Level::Error { "error" } else { "warning" };
+        write!(f, "{} [{}]: {}", level, self.check, self.message)
+    }
+}
+
+/// Lines of the statement cells, with the cell index for messages.
+pub fn statement(nb: &Notebook) -> Vec<(usize, &str)> {
+    let mut lines = Vec::new();
+    for (i, cell) in nb.cells.iter().enumerate() {
+        let (Cell::Markdown { source } | Cell::Code { source }) = cell;
+        let first = source.iter().flat_map(|l| l.lines()).find(|l| !l.trim().is_empty());
+        if matches!(first.and_then(marker), Some("Prompt" | "Requirements")) {
+            lines.extend(source.iter().flat_map(|l| l.lines()).map(|l| (i, l)));
+        }
+    }
+    lines
+}
+
+/// A markdown heading or a line that is entirely bold, like `**Examples**`.
+pub fn is_heading(line: &str) -> bool {
+    let t = line.trim();
+    t.starts_with('#') || (t.len() > 4 && t.starts_with("**") && t.ends_with("**"))
+}
+
+/// Identifiers written in backticks on `line`, like `N` or `max_len`.
+fn backticked_names(line: &str) -> impl Iterator<Item = &str> {
+    line.split('`').skip(1).step_by(2).filter(|s| {
+        s.chars().next().is_some_and(|c| c.is_alphabetic() || c == '_')
+            && s.chars().all(|c| c.is_alphanumeric() || c == '_')
+    })
+}
+
+/// Whether `line` states a bound: a comparison next to a number.
+fn has_bound(line: &str) -> bool {
+    let comparison = ["≤", "≥", "<", ">", "at most", "at least", "up to", "between"]
+        .iter()
+        .any(|op| line.contains(op));
+    comparison && line.chars().any(|c| c.is_ascii_digit())
+}
+
+fn check_min_words(text: &[(usize, &str)], min_words: usize) -> Vec<String> {
+    let words: usize = text
+        .iter()
+        .filter(|(_, l)| marker(l).is_none())
+        .map(|(_, l)| l.split_whitespace().filter(|w| w.chars().any(char::is_alphanumeric)).count())
+        .sum();
+    if words < min_words {
+        vec![format!("statement has {} words, at least {} expected", words, min_words)]
+    } else {
+        Vec::new()
+    }
+}
+
+fn check_example(text: &[(usize, &str)]) -> Vec<String> {
+    let lower: Vec<String> = text.iter().map(|(_, l)| l.to_lowercase()).collect();
+    let example_heading =
+        text.iter().zip(&lower).any(|((_, l), low)| is_heading(l) && low.contains("example"));
+    let io_pair = lower.iter().any(|l| l.trim_start().starts_with("input"))
+        && lower.iter().any(|l| l.trim_start().starts_with("output"));
+    if example_heading || io_pair {
+        Vec::new()
+    } else {
+        vec!["no worked example (an `Example` heading or an Input/Output pair)".to_string()]
+    }
+}
+
+/// Every name the constraints sections introduce in backticks needs a
+/// line there that bounds it.
+fn check_constraints(text: &[(usize, &str)]) -> Vec<String> {
+    let mut sections: Vec<Vec<&str>> = Vec::new();
+    let mut inside = false;
+    for (_, line) in text {
+        if is_heading(line) {
+            inside = line.to_lowercase().contains("constraint");
+            if inside {
+                sections.push(Vec::new());
+            }
+        } else if let (true, Some(section)) = (inside, sections.last_mut()) {
+            section.push(line);
+        }
+    }
+    if sections.is_empty() {
+        return vec!["no constraints section".to_string()];
+    }
+    let lines: Vec<&str> = sections.concat();
+    let mut names: Vec<&str> = lines.iter().flat_map(|l| backticked_names(l)).collect();
+    names.sort_unstable();
+    names.dedup();
+    names
+        .into_iter()
+        .filter(|name| {
+            !lines.iter().any(|l| backticked_names(l).any(|n| n == *name) && has_bound(l))
+        })
+        .map(|name| format!("`{}` has no bound in the constraints section", name))
+        .collect()
+}
+
+fn check_placeholders(text: &[(usize, &str)]) -> Vec<String> {
+    const WORDS: [&str; 4] = ["TODO", "FIXME", "TBD", "XXX"];
+    text.iter()
+        .filter_map(|(cell, line)| {
+            let word = line
+                .split(|c: char| !c.is_alphanumeric())
+                .find(|w| WORDS.contains(w))?;
+            Some(format!("{} placeholder in cell {}: {}", word, cell, line.trim()))
+        })
+        .collect()
+}
+
+/// Run every enabled check over `nb`.
+pub fn lint(nb: &Notebook, policy: &LintPolicy) -> Result<Vec<Finding>, String> {
+    if let Some(name) = policy.levels.keys().find(|k| !CHECKS.iter().any(|(c, _)| c == k)) {
+        return Err(format!("[lint.levels]: unknown check `{}`", name));
+    }
+    let text = statement(nb);
+    let mut findings = Vec::new();
+    for (check, default) in CHECKS {
+        let level = policy.levels.get(check).copied().unwrap_or(default);
+        if level == Level::Off {
+            continue;
+        }
+        let messages = match check {
+            "min-words" => check_min_words(&text, policy.min_words),
+            "example" => check_example(&text),
+            "constraints" => check_constraints(&text),
+            _ => check_placeholders(&text),
+        };
+        findings.extend(messages.into_iter().map(|message| Finding { check, level, message }));
+    }
+    Ok(findings)
+}
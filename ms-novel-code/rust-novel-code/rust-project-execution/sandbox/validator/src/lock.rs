@@ -0,0 +1,72 @@
+//! Advisory locks that keep validators launched side by side (parallel CI
+//! jobs) from racing on the same `tasks/<stem>` workspace or shared
+//! target directory. Locks are `flock(2)`s on a file next to the guarded
+//! directory, released when the process exits however it exits.
+
+use crate::colors::*;
+use std::{
+    fs::{self, File, OpenOptions},
+    io,
+    os::unix::io::AsRawFd,
+    path::{Path, PathBuf},
+};
+
+/// Held lock; dropping it (or exiting) releases it.
+pub struct Lock {
+    _file: File,
+}
+
+/// `<dir>.lock` beside `dir`, so removing `dir` leaves it alone.
+pub fn path_of(dir: &Path) -> PathBuf {
+    let mut name = dir.file_name().unwrap_or_default().to_os_string();
+    name.push(".lock");
+    dir.with_file_name(name)
+}
+
+fn flock(file: &File, flags: i32) -> io::Result<()> {
+    // SAFETY: the descriptor is owned by `file` and open for the call
+    if unsafe { libc::flock(file.as_raw_fd(), flags) } == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+fn open(path: &Path) -> Result<File, String> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).map_err(|e| format!("{}: {}", parent.display(), e))?;
+    }
+    OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path)
+        .map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Lock `dir` exclusively, waiting (and saying so) while another
+/// validator holds it.
+pub fn acquire(dir: &Path) -> Result<Lock, String> {
+    let path = path_of(dir);
+    let file = open(&path)?;
+    match flock(&file, libc::LOCK_EX | libc::LOCK_NB) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+            println!("{}Waiting for {}, held by another validator{}", BLUE, path.display(), RESET);
+            flock(&file, libc::LOCK_EX).map_err(|e| format!("{}: {}", path.display(), e))?;
+        }
+        Err(e) => return Err(format!("{}: {}", path.display(), e)),
+    }
+    Ok(Lock { _file: file })
+}
+
+/// Lock `dir` if no one else holds it.
+pub fn try_acquire(dir: &Path) -> Result<Option<Lock>, String> {
+    let path = path_of(dir);
+    let file = open(&path)?;
+    match flock(&file, libc::LOCK_EX | libc::LOCK_NB) {
+        Ok(()) => Ok(Some(Lock { _file: file })),
+        Err(e) if e.kind() == io::ErrorKind::WouldBlock => Ok(None),
+        Err(e) => Err(format!("{}: {}", path.display(), e)),
+    }
+}
@@ -1,289 +1,934 @@
-use clap::Parser;
-use serde::Deserialize;
+use clap::{Parser, Subcommand};
 use std::{
-    collections::HashMap,
-    fs,
-    io::{self, ErrorKind, Read},
     path::{Path, PathBuf},
-    process::{Command, ExitStatus},
-    time::{Duration, Instant},
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
-use wait_timeout::ChildExt;
-
-/// ANSI color codes
-mod colors {
-    pub const RESET: &str = "\x1B[0m";
-    pub const RED: &str   = "\x1B[91m";
-    pub const GREEN: &str = "\x1B[92m";
-    pub const BLUE: &str  = "\x1B[94m";
-    pub const BOLD: &str  = "\x1B[1m";
-}
-use colors::*;
+use validator::colors::*;
+use validator::manifest::Manifest;
+use validator::*;
+
+mod batch;
+mod watch;
 
 #[derive(Parser)]
-#[command(about = "Validate & run a Rust‑task notebook")]
+#[command(
+    about = "Validate & run a Rust‑task notebook",
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
 struct Args {
-    #[arg(value_hint = clap::ValueHint::FilePath)]
-    task_file: PathBuf,
+    #[command(subcommand)]
+    command: Option<Tool>,
+
+    /// Notebook to validate, or a directory whose notebooks are each
+    /// validated with the same flags.
+    #[arg(value_hint = clap::ValueHint::AnyPath, required = true)]
+    task_file: Option<PathBuf>,
+
+    /// With a directory, also validate the notebooks in its subdirectories.
+    #[arg(long)]
+    recursive: bool,
+
+    /// With a directory, also compare the notebooks' problem statements
+    /// and code with each other and write the pairs similar enough to be
+    /// duplicates, with excerpts, to PATH.
+    #[arg(long, value_name = "PATH")]
+    similarity_report: Option<PathBuf>,
+
+    /// With a directory, validate only the notebooks whose manifest has
+    /// any of these tags.
+    #[arg(long, value_name = "LIST", value_delimiter = ',')]
+    tags: Vec<String>,
+
+    /// With a directory, skip the notebooks whose manifest has any of
+    /// these tags.
+    #[arg(long, value_name = "LIST", value_delimiter = ',')]
+    exclude_tags: Vec<String>,
+
+    /// With a directory, also write the summary as an HTML page with a
+    /// sortable table, linking each task to the JSON report its
+    /// validation writes into `<page>-reports/`.
+    #[arg(long, value_name = "PATH")]
+    dashboard: Option<PathBuf>,
+
+    /// With a directory, finish within this many minutes: later tasks get
+    /// fewer `--runs` as time runs short, and those left without time for
+    /// one run are skipped as "budget exhausted".
+    #[arg(long, value_name = "MINUTES")]
+    total_budget: Option<f64>,
+
+    /// Validate again every time the notebook or its manifest is saved,
+    /// listing the tests whose classification changed, until Ctrl-C.
+    #[arg(long, conflicts_with_all = ["recursive", "similarity_report", "tags", "exclude_tags", "dashboard", "total_budget", "shard_report", "format", "output"])]
+    watch: bool,
 
     #[arg(short, long, default_value_t = 1)]
     runs: usize,
 
+    /// Runs to execute at once, each job building into a target directory
+    /// of its own; results still merge in run order.
+    #[arg(short, long, default_value_t = 1, value_name = "N")]
+    jobs: usize,
+
+    /// Seconds for both the build and each test run; the specific flags
+    /// below override it.
     #[arg(short, long, default_value_t = 120)]
     timeout: u64,
+
+    /// Seconds the test build may take, cold dependency builds included.
+    #[arg(long, value_name = "SECS")]
+    build_timeout: Option<u64>,
+
+    /// Seconds each `cargo test` run may take once built.
+    #[arg(long, value_name = "SECS")]
+    test_timeout: Option<u64>,
+
+    /// Seconds a single test may take; a test over it fails that run,
+    /// however its assertions went.
+    #[arg(long, value_name = "SECS")]
+    max_test_time: Option<f64>,
+
+    /// Time each test and add min/mean/max columns to the report. Like
+    /// `--max-test-time`, this needs libtest's unstable `--report-time`.
+    #[arg(long)]
+    test_times: bool,
+
+    /// Run cargo with an empty environment apart from PATH, HOME and the
+    /// cargo/rustup locations, with TZ=UTC, LANG=C.UTF-8 and
+    /// RUST_BACKTRACE=1 pinned and the notebook's variables on top.
+    #[arg(long)]
+    hermetic_env: bool,
+
+    /// Show the diff of every pending insta snapshot.
+    #[arg(long)]
+    review_snapshots: bool,
+
+    /// Write pending insta snapshots into the notebook's `# snapshots`
+    /// cell.
+    #[arg(long)]
+    accept_snapshots: bool,
+
+    /// Let tests that crash dump core and report the core file's path.
+    #[arg(long)]
+    core_dumps: bool,
+
+    /// Run the tests once more under Miri (nightly with its `miri`
+    /// component), classifying tests with undefined behavior unsound.
+    #[arg(long, conflicts_with = "sanitizer")]
+    miri: bool,
+
+    /// Run the tests once more under a sanitizer (nightly), classifying
+    /// tests it reports unsound.
+    #[arg(long, value_enum, value_name = "SANITIZER")]
+    sanitizer: Option<ub::Sanitizer>,
+
+    /// Strip absolute paths, user and host names and environment values
+    /// from uploaded reports, digests and shard reports.
+    #[arg(long)]
+    redact: bool,
+
+    /// TOML file adjusting the `--redact` rules (implies it).
+    #[arg(long, value_name = "FILE")]
+    redact_rules: Option<PathBuf>,
+
+    /// Retry a run that times out once with its timeout multiplied by
+    /// FACTOR, reporting tests that finish then as timeout-marginal
+    /// rather than hung.
+    #[arg(long, value_name = "FACTOR")]
+    timeout_retry: Option<u64>,
+
+    /// Sample the CPU use, RSS and thread count of each run's processes
+    /// every MS milliseconds and report them as sparklines.
+    #[arg(long, value_name = "MS")]
+    sample_interval: Option<u64>,
+
+    /// Also write the `--sample-interval` series as an HTML page.
+    #[arg(long, value_name = "PATH", requires = "sample_interval")]
+    resource_html: Option<PathBuf>,
+
+    /// Address space each test process may use, in MiB.
+    #[arg(long, value_name = "MB")]
+    memory_limit: Option<u64>,
+
+    /// CPU seconds each test process may use.
+    #[arg(long, value_name = "SECS")]
+    cpu_limit: Option<u64>,
+
+    /// Processes the tests may have running at once (rlimit of the user;
+    /// root is exempt).
+    #[arg(long, value_name = "N")]
+    max_procs: Option<u64>,
+
+    /// Run the tests without network access, loopback aside.
+    #[arg(long)]
+    no_network: bool,
+
+    /// Spin N busy background threads while the suite runs, to surface
+    /// tests whose assertions depend on timing.
+    #[arg(long, value_name = "N")]
+    stress_cpu: Option<usize>,
+
+    /// Shuffle test order with a different seed on every run.
+    #[arg(long)]
+    shuffle: bool,
+
+    /// Base seed for `--shuffle` (implies it); run k uses `seed + k - 1`.
+    #[arg(long, value_name = "SEED")]
+    shuffle_seed: Option<u64>,
+
+    /// Repeat the runs K times, exporting `VALIDATOR_TEST_SEED` = 0..K-1
+    /// for tests to seed their RNGs from, and report outcomes per seed.
+    #[arg(long, value_name = "K")]
+    seed_matrix: Option<u64>,
+
+    /// After the runs, re-run once per changed timezone, locale, clock
+    /// (with libfaketime) and read-only workspace, flagging tests whose
+    /// outcome flips.
+    #[arg(long)]
+    hermeticity: bool,
+
+    /// Also run `cargo clippy -- -D warnings` and `cargo fmt --check` in
+    /// the workspace and report them in a quality gate section.
+    #[arg(long)]
+    lint: bool,
+
+    /// Whether `--lint` findings fail the validation.
+    #[arg(long, value_enum, default_value_t = style::Gate::Fatal, requires = "lint")]
+    lint_policy: style::Gate,
+
+    /// Also measure the line and branch coverage of the library by the
+    /// integration tests, with an instrumented build (needs rustup's
+    /// `llvm-tools`).
+    #[arg(long)]
+    coverage: bool,
+
+    /// Fail when `--coverage` finds fewer than this percentage of the
+    /// library's lines covered.
+    #[arg(long, value_name = "PCT", requires = "coverage")]
+    min_coverage: Option<f64>,
+
+    /// Also validate under each of these rustup toolchains, in separate
+    /// workspaces, and print a per-test grid flagging divergences.
+    #[arg(long, value_name = "LIST", value_delimiter = ',')]
+    toolchains: Vec<String>,
+
+    /// What to do when the code uses `#![feature]` or `#[bench]`:
+    /// `reject`, or a nightly toolchain (like `nightly-2024-06-01`) to
+    /// build and test with. The manifest's `nightly` overrides it.
+    #[arg(long, value_name = "POLICY", default_value = "reject", value_parser = nightly::Policy::parse)]
+    nightly: nightly::Policy,
+
+    /// Also write the run matrix as JSON or JUnit XML, to `--output` or
+    /// stdout (the rest of the output then goes to stderr).
+    #[arg(long, value_enum, default_value_t = export::Format::Text)]
+    format: export::Format,
+
+    /// File for the `--format` document.
+    #[arg(long, value_name = "PATH")]
+    output: Option<PathBuf>,
+
+    /// Directory with a `console.txt` minijinja template replacing the
+    /// built-in consistency table and totals.
+    #[arg(long, value_name = "DIR")]
+    template_dir: Option<PathBuf>,
+
+    /// Upload the report, its JSON document and the extracted workspace
+    /// to `s3://bucket/prefix` however the validation ends, a failed
+    /// build included (credentials from the standard `AWS_*` vars).
+    #[arg(long, value_name = "URL")]
+    upload: Option<String>,
+
+    /// SQLite database recording every validation (verdict, timings,
+    /// toolchain and each test's runs), for `validator report trends`
+    /// and to mark historically flaky tests.
+    #[arg(long, value_name = "PATH")]
+    history: Option<PathBuf>,
+
+    /// Mark a test historically flaky when its outcome flipped in any of
+    /// the notebook's last N `--history` validations (of the same
+    /// notebook contents).
+    #[arg(long, value_name = "N", default_value_t = 10, requires = "history")]
+    flake_window: usize,
+
+    /// Whether historically flaky tests count toward the exit code.
+    #[arg(long, value_enum, default_value_t = history::FlakePolicy::Report, requires = "history")]
+    flake_policy: history::FlakePolicy,
+
+    /// When validation fails, however it fails (a build error too), open
+    /// the triage shell in the workspace.
+    #[arg(long)]
+    shell_on_failure: bool,
+
+    /// Extract only these sections (lib, main, test, build, testutil,
+    /// snapshots, files), for flows that supply the others themselves;
+    /// only the extracted ones are required.
+    #[arg(long, value_enum, value_delimiter = ',', conflicts_with = "skip")]
+    only: Vec<Section>,
+
+    /// Sections not to extract, e.g. `--skip main`.
+    #[arg(long, value_enum, value_delimiter = ',')]
+    skip: Vec<Section>,
+
+    /// Extract into a fresh workspace, discarding the build cache an
+    /// earlier validation of this notebook left.
+    #[arg(long)]
+    clean: bool,
+
+    /// Keep a git repository in the workspace, committing after
+    /// extraction, after each change to it, and once per run.
+    #[arg(long)]
+    git_snapshots: bool,
+
+    /// Run only the tests that hash to shard I of N (1-based).
+    #[arg(long, value_name = "I/N")]
+    shard: Option<shard::Shard>,
+
+    /// Write the per-test outcomes as JSON, for `validator merge-reports`.
+    #[arg(long, value_name = "PATH")]
+    shard_report: Option<PathBuf>,
+
+    /// Write a compact JSON digest of compile errors or failing tests,
+    /// for feeding back to a model.
+    #[arg(long, value_name = "PATH")]
+    digest: Option<PathBuf>,
+
+    /// Approximate token budget for `--digest`.
+    #[arg(long, value_name = "TOKENS", default_value_t = 2000)]
+    digest_budget: usize,
+
+    /// Apply this unified diff (paths relative to the workspace) after
+    /// extraction, to check a candidate fix.
+    #[arg(long, value_name = "DIFF")]
+    apply_patch: Option<PathBuf>,
+
+    /// Directory of candidate `lib.rs` files (or subdirectories of `src/`
+    /// files) to run the notebook's tests against and rank.
+    #[arg(long, value_name = "DIR", value_hint = clap::ValueHint::DirPath)]
+    candidates: Option<PathBuf>,
+
+    /// Run each test alone and report its peak RSS, failing tests over
+    /// the manifest's `memory_budget_mb`.
+    #[arg(long)]
+    measure_memory: bool,
+
+    /// Let rustup install the manifest's `msrv` toolchain when missing;
+    /// without this the MSRV check is skipped on machines lacking it.
+    #[arg(long)]
+    install_msrv: bool,
+
+    /// Policy file mapping task tags to the dependencies a task may
+    /// declare; a task using anything else is rejected.
+    #[arg(long, value_name = "PATH")]
+    crate_policy: Option<PathBuf>,
+
+    /// Spec (TOML or JSON) of the sections, metadata fields, statement
+    /// headings and forbidden content a notebook must follow; checked
+    /// before anything is built.
+    #[arg(long, value_name = "PATH")]
+    structure: Option<PathBuf>,
+
+    /// Which checker stages run, and in what order, for every task and
+    /// per notebook stem; a missing file means all of them as usual.
+    #[arg(long, value_name = "PATH", default_value = "validator.toml")]
+    config: PathBuf,
+
+    /// Cache directory for manifest fixtures, keyed by SHA-256.
+    #[arg(long, value_name = "DIR", default_value = ".fixture-cache")]
+    fixture_cache: PathBuf,
+
+    /// Before validating, remove other workspaces this validator
+    /// extracted that were unused for this many hours; orphaned processes
+    /// are always reaped.
+    #[arg(long, value_name = "HOURS")]
+    reap_after: Option<u64>,
+
+    /// Never download: cargo runs offline and fixtures come only from
+    /// verified cached copies.
+    #[arg(long)]
+    offline: bool,
 }
 
-#[derive(Deserialize)]
-#[serde(tag = "cell_type", rename_all = "lowercase")]
-enum Cell {
-    Markdown { source: Vec<String> },
-    Code     { source: Vec<String> },
+/// Maintenance commands; without one the notebook is validated.
+#[derive(Subcommand)]
+enum Tool {
+    /// Normalize a notebook in place: marker spelling, missing fences,
+    /// cell order, trailing whitespace and mojibake.
+    Fix {
+        #[arg(value_hint = clap::ValueHint::FilePath)]
+        notebook: PathBuf,
+        /// Show the changes as a diff instead of writing them.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Check a notebook's problem statement against the manifest's lint
+    /// policy, without building anything.
+    Lint {
+        #[arg(value_hint = clap::ValueHint::FilePath)]
+        notebook: PathBuf,
+    },
+    /// Open an interactive triage prompt in a prepared workspace: rerun
+    /// single tests, diff against the notebook, inspect files.
+    Shell {
+        #[arg(value_hint = clap::ValueHint::DirPath)]
+        workspace: PathBuf,
+        /// Notebook the workspace came from, for `diff` and its env.
+        #[arg(long, value_hint = clap::ValueHint::FilePath)]
+        notebook: Option<PathBuf>,
+    },
+    /// Merge `--shard-report` files of one notebook into a single
+    /// consistency table.
+    MergeReports {
+        #[arg(required = true, value_hint = clap::ValueHint::FilePath)]
+        reports: Vec<PathBuf>,
+    },
+    /// Check that cargo, the requested toolchains and tools, and the
+    /// workspace roots are usable, and build a trivial crate.
+    Doctor {
+        /// Toolchains to check besides the default one.
+        #[arg(long = "toolchain", value_name = "NAME")]
+        toolchains: Vec<String>,
+        /// Optional tools to check on each toolchain.
+        #[arg(long = "tool", value_name = "NAME",
+              value_parser = clap::builder::PossibleValuesParser::new(doctor::TOOL_NAMES))]
+        tools: Vec<String>,
+        /// Install missing toolchains and rustup components.
+        #[arg(long)]
+        install: bool,
+    },
+    /// Print the JSON Schema of the `--shard-report` format.
+    Schema,
+    /// Pack a notebook with its extracted workspace, vendored
+    /// dependencies, fixtures and toolchain pin into one zip.
+    Bundle {
+        #[arg(value_hint = clap::ValueHint::FilePath)]
+        notebook: PathBuf,
+        #[arg(short, long, value_name = "PATH")]
+        output: PathBuf,
+    },
+    /// Validate a `validator bundle` zip without network access.
+    RunBundle {
+        #[arg(value_hint = clap::ValueHint::FilePath)]
+        bundle: PathBuf,
+        #[arg(short, long, default_value_t = 1)]
+        runs: usize,
+    },
+    /// Reports over the `--history` database.
+    Report {
+        #[command(subcommand)]
+        report: ReportTool,
+    },
+    /// Kill process groups left by validators that died, and remove
+    /// workspaces a validator extracted that nobody has used for a while.
+    Reap {
+        /// Hours a workspace must have been unused to be removed.
+        #[arg(long, value_name = "HOURS", default_value_t = 24)]
+        older_than: u64,
+    },
 }
 
-#[derive(Deserialize)]
-struct Notebook { cells: Vec<Cell> }
+#[derive(Subcommand)]
+enum ReportTool {
+    /// Per-task series of pass rate, duration and flaky tests over the
+    /// last validations, and the corpus by day and by toolchain.
+    Trends {
+        /// Database the validations were recorded in with `--history`.
+        #[arg(long, value_name = "PATH", value_hint = clap::ValueHint::FilePath)]
+        history: PathBuf,
+        /// Validations of each task to chart.
+        #[arg(long, value_name = "N", default_value_t = 20)]
+        last: usize,
+        /// Also write the charts as an HTML page to PATH.
+        #[arg(long, value_name = "PATH")]
+        html: Option<PathBuf>,
+    },
+}
 
-fn load_notebook(path: &Path) -> io::Result<Notebook> {
-    if !path.exists() {
-        return Err(io::Error::new(ErrorKind::NotFound, "Notebook file not found"));
+/// `validator report trends`: print the trends, then exit.
+fn run_trends(history: &Path, last: usize, html: Option<&Path>) -> ! {
+    // opening would create a database where there was none
+    if !history.is_file() {
+        eprintln!("{}Trends error:{} {}: no such database", RED, RESET, history.display());
+        std::process::exit(1);
     }
-    if path.extension().and_then(|s| s.to_str()) != Some("ipynb") {
-        return Err(io::Error::new(ErrorKind::InvalidInput, "Expected a .ipynb file"));
+    let written = history::History::open(history).and_then(|h| h.recent(last)).and_then(|records| {
+        let trends = trends::Trends::new(&records, last);
+        print!("{}", trends.text());
+        match html {
+            Some(path) => trends.write_html(path, &history.display().to_string()),
+            None => Ok(()),
+        }
+    });
+    if let Err(e) = written {
+        eprintln!("{}Trends error:{} {}", RED, RESET, e);
+        std::process::exit(1);
     }
-    let raw = fs::read_to_string(path)?;
-    serde_json::from_str(&raw)
-        .map_err(|e| io::Error::new(ErrorKind::Other, format!("JSON error: {}", e)))
+    std::process::exit(0);
 }
 
-fn extract_rust_block(lines: &[String]) -> String {
-    let mut in_block = false;
-    let mut out = Vec::new();
-    for line in lines {
-        let t = line.trim_start();
-        if t.starts_with("```rust") {
-            in_block = true;
-            continue;
-        }
-        if in_block && t.starts_with("```") {
-            break;
-        }
-        if in_block {
-            out.push(line.clone());
-        }
+/// `validator lint`: print the findings and exit, failing on errors.
+fn run_lint(path: &Path) -> ! {
+    let findings = Notebook::load(path)
+        .map_err(|e| format!("{}: {}", path.display(), e))
+        .and_then(|nb| Ok((nb, Manifest::for_notebook(path)?)))
+        .and_then(|(nb, manifest)| lint::lint(&nb, &manifest.lint))
+        .unwrap_or_else(|e| {
+            eprintln!("{}Lint error:{} {}", RED, RESET, e);
+            std::process::exit(1);
+        });
+    let errors = findings.iter().filter(|f| f.level == lint::Level::Error).count();
+    for finding in &findings {
+        let col = if finding.level == lint::Level::Error { RED } else { BLUE };
+        println!("{}{}{}", col, finding, RESET);
     }
-    out.join("\n")
+    println!("{}: {} error(s), {} warning(s)", path.display(), errors, findings.len() - errors);
+    std::process::exit(if errors == 0 { 0 } else { 1 });
 }
 
-fn prepare_workspace(nb: &Notebook, workspace: &Path) -> Result<Vec<String>, String> {
-    if workspace.exists() {
-        fs::remove_dir_all(workspace).map_err(|e| e.to_string())?;
-    }
-    fs::create_dir_all(workspace).map_err(|e| e.to_string())?;
-
-    fs::write(
-        workspace.join("Cargo.toml"),
-        r#"[package]
-name = "task_ws"
-version = "0.1.0"
-edition = "2021"
-[dependencies]
-"#,
-    ).map_err(|e| e.to_string())?;
-
-    let mut seen = HashMap::new();
-    let mut files = vec!["Cargo.toml".into()];
-
-    for cell in &nb.cells {
-        let src = match cell {
-            Cell::Markdown { source } | Cell::Code { source } => source,
-        };
-        let joined = src.join("");
-
-        if joined.contains("# lib") && joined.contains("```rust") {
-            let dir = workspace.join("src");
-            fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
-            fs::write(dir.join("lib.rs"), extract_rust_block(src))
-                .map_err(|e| e.to_string())?;
-            seen.insert("lib", true);
-            files.push("src/lib.rs".into());
-        }
-        if joined.contains("# main") && joined.contains("```rust") {
-            let dir = workspace.join("src");
-            fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
-            fs::write(dir.join("main.rs"), extract_rust_block(src))
-                .map_err(|e| e.to_string())?;
-            seen.insert("main", true);
-            files.push("src/main.rs".into());
-        }
-        if joined.contains("# test") && joined.contains("```rust") {
-            let dir = workspace.join("tests");
-            fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
-            fs::write(dir.join("integration.rs"), extract_rust_block(src))
-                .map_err(|e| e.to_string())?;
-            seen.insert("test", true);
-            files.push("tests/integration.rs".into());
-        }
-        if joined.contains("# build") && joined.contains("```rust") {
-            fs::write(workspace.join("build.rs"), extract_rust_block(src))
-                .map_err(|e| e.to_string())?;
-            seen.insert("build", true);
-            files.push("build.rs".into());
-        }
+/// `validator shell`: run the prompt with the notebook's env, then exit.
+fn run_shell(workspace: &Path, notebook: Option<&Path>) -> ! {
+    let env = match notebook {
+        Some(path) => Notebook::load(path)
+            .map_err(|e| format!("{}: {}", path.display(), e))
+            .and_then(|nb| Ok((nb, Manifest::for_notebook(path)?)))
+            .and_then(|(nb, manifest)| extract_env(&nb, &manifest)),
+        None => Ok(Vec::new()),
+    };
+    if let Err(e) = env.and_then(|env| shell::run(workspace, notebook, &env)) {
+        eprintln!("{}Shell error:{} {}", RED, RESET, e);
+        std::process::exit(1);
     }
+    std::process::exit(0);
+}
 
-    for &req in &["lib", "main", "test"] {
-        if !seen.contains_key(req) {
-            return Err(format!("Missing required code section: `# {}`", req));
+/// `validator merge-reports`: one table from shard reports, exiting like
+/// a validation would.
+fn run_merge(reports: &[PathBuf], template_dir: Option<&Path>) -> ! {
+    let (matrix, missing) = shard::merge(reports).unwrap_or_else(|e| {
+        eprintln!("{}Merge error:{} {}", RED, RESET, e);
+        std::process::exit(1);
+    });
+    let (rows, totals) = report::rows(&RunMatrix::from(matrix));
+    match report::render(template_dir, &rows, &totals) {
+        Ok(text) => print!("{}", text),
+        Err(e) => {
+            eprintln!("{}Report error:{} {}", RED, RESET, e);
+            std::process::exit(1);
         }
     }
-    Ok(files)
+    if !missing.is_empty() {
+        let missing: Vec<String> = missing.iter().map(ToString::to_string).collect();
+        println!("{}Missing shards:{} {}", RED, RESET, missing.join(", "));
+    }
+    std::process::exit(if totals.fail == 0 && totals.flaky == 0 && missing.is_empty() { 0 } else { 1 });
 }
 
-fn run_cargo_test(workspace: &Path, timeout: u64) -> Result<ExitStatus, String> {
-    let mut child = Command::new("cargo")
-        .arg("test")
-        .current_dir(workspace)
-        .spawn()
-        .map_err(|e| e.to_string())?;
-    match child.wait_timeout(Duration::from_secs(timeout))
-               .map_err(|e| e.to_string())? {
-        Some(status) => Ok(status),
-        None => {
-            let _ = child.kill();
-            Err("Timeout reached".into())
+/// Ctrl-C outside the run loop: say what was dropped and exit.
+fn exit_on_interrupt(interrupted: interrupt::Interrupted) {
+    match interrupted {
+        interrupt::Interrupted::Preparing(ws) => {
+            eprintln!("{}Interrupted;{} removed partial workspace {}", RED, RESET, ws.display())
         }
+        interrupt::Interrupted::Elsewhere => eprintln!("{}Interrupted{}", RED, RESET),
     }
+    std::process::exit(interrupt::EXIT_CODE);
 }
 
-/// Run `cargo test` once, capture each test’s pass/fail outcome.
-fn run_cargo_test_once(
-    workspace: &Path,
-    timeout: u64
-) -> Result<HashMap<String,bool>, String> {
-    let mut child = Command::new("cargo")
-        .arg("test")
-        .arg("--color=never")
-        .current_dir(workspace)
-        .stdout(std::process::Stdio::piped())
-        .spawn()
-        .map_err(|e| e.to_string())?;
-
-    // wait with timeout
-    let status = match child
-        .wait_timeout(Duration::from_secs(timeout))
-        .map_err(|e| e.to_string())? {
-        Some(s) => s,
-        None => { let _ = child.kill(); return Err("Timeout reached".into()); }
+fn main() {
+    let mut args = Args::parse();
+    match &args.command {
+        Some(Tool::Fix { notebook, dry_run }) => {
+            if let Err(e) = fix::run(notebook, *dry_run) {
+                eprintln!("{}Fix error:{} {}", RED, RESET, e);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(Tool::Lint { notebook }) => run_lint(notebook),
+        Some(Tool::Shell { workspace, notebook }) => run_shell(workspace, notebook.as_deref()),
+        Some(Tool::MergeReports { reports }) => run_merge(reports, args.template_dir.as_deref()),
+        Some(Tool::Schema) => {
+            println!("{}", shard::schema());
+            return;
+        }
+        Some(Tool::Bundle { notebook, output }) => {
+            if let Err(e) = bundle::create(notebook, output, &args.fixture_cache, args.offline) {
+                eprintln!("{}Bundle error:{} {}", RED, RESET, e);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(Tool::RunBundle { .. }) => {}
+        Some(Tool::Report { report: ReportTool::Trends { history, last, html } }) => {
+            run_trends(history, *last, html.as_deref())
+        }
+        Some(Tool::Reap { older_than }) => {
+            reaper::sweep(Some(Duration::from_secs(older_than * 3600)));
+            return;
+        }
+        Some(Tool::Doctor { toolchains, tools, install }) => {
+            let roots = [Path::new(reaper::ROOT), args.fixture_cache.as_path()];
+            std::process::exit(if doctor::run(toolchains, tools, *install, &roots) { 0 } else { 1 });
+        }
+        None => {}
+    }
+    // a bundle stands in for the notebook and the fixture cache, offline
+    let unpacked = match &args.command {
+        Some(Tool::RunBundle { bundle, runs }) => {
+            args.runs = *runs;
+            args.offline = true;
+            Some(bundle::unpack(bundle).unwrap_or_else(|e| {
+                eprintln!("{}Bundle error:{} {}", RED, RESET, e);
+                std::process::exit(1);
+            }))
+        }
+        _ => None,
     };
-
-    // read stdout
-    let mut buf = String::new();
-    if let Some(mut out) = child.stdout.take() {
-        out.read_to_string(&mut buf).unwrap();
+    if let Some(unpacked) = &unpacked {
+        args.fixture_cache = unpacked.fixtures.clone();
     }
-
-    // parse lines: test <name> ... ok/FAILED
-    let mut map = HashMap::new();
-    for line in buf.lines() {
-        if let Some(rest) = line.strip_prefix("test ") {
-            let mut parts = rest.split(" ... ");
-            if let (Some(name), Some(res)) = (parts.next(), parts.next()) {
-                map.insert(name.to_string(), res.trim() == "ok");
-            }
+    let task_file = unpacked
+        .as_ref()
+        .map(|u| u.notebook.as_path())
+        .or(args.task_file.as_deref())
+        .expect("clap requires a notebook");
+    if args.watch {
+        if task_file.is_dir() {
+            eprintln!("{}Watch error:{} --watch needs a single notebook", RED, RESET);
+            std::process::exit(1);
         }
+        if let Err(e) = interrupt::install(exit_on_interrupt) {
+            eprintln!("{}Signal handler error:{} {}", RED, RESET, e);
+        }
+        interrupt::defer_exit(true);
+        std::process::exit(watch::run(task_file));
     }
-
-    if !status.success() && map.is_empty() {
-        return Err(format!("`cargo test` failed (exit {:?})", status.code()));
+    if task_file.is_dir() {
+        if args.shard_report.is_some() || args.format != export::Format::Text {
+            eprintln!("{}Batch error:{} --shard-report and --format need a single notebook", RED, RESET);
+            std::process::exit(1);
+        }
+        let notebooks = batch::discover(task_file, args.recursive).unwrap_or_else(|e| {
+            eprintln!("{}Batch error:{} {}", RED, RESET, e);
+            std::process::exit(1);
+        });
+        if notebooks.is_empty() {
+            eprintln!("{}Batch error:{} no notebooks in {}", RED, RESET, task_file.display());
+            std::process::exit(1);
+        }
+        let found = notebooks.len();
+        let notebooks = batch::select(notebooks, &args.tags, &args.exclude_tags);
+        if notebooks.is_empty() {
+            eprintln!("{}Batch error:{} none of the {} notebooks in {} has the selected tags",
+                      RED, RESET, found, task_file.display());
+            std::process::exit(1);
+        }
+        if notebooks.len() < found {
+            println!("{}Selected {} of {} notebooks by tag{}", BLUE, notebooks.len(), found, RESET);
+        }
+        if let Err(e) = interrupt::install(exit_on_interrupt) {
+            eprintln!("{}Signal handler error:{} {}", RED, RESET, e);
+        }
+        // each child reports its own interrupted run; the summary follows
+        interrupt::defer_exit(true);
+        let options = batch::Options {
+            similarity_report: args.similarity_report.clone(),
+            dashboard: args.dashboard.clone(),
+            total_budget: args.total_budget,
+            runs: args.runs,
+        };
+        std::process::exit(batch::run(task_file, &notebooks, &options));
     }
-
-    Ok(map)
-}
-
-fn main() {
-    let args = Args::parse();
-
-    let stem = args
-        .task_file
+    let batch_only = args.similarity_report.is_some() || args.dashboard.is_some() || args.total_budget.is_some()
+        || !args.tags.is_empty() || !args.exclude_tags.is_empty();
+    if batch_only {
+        eprintln!("{}Batch error:{} --similarity-report, --dashboard, --total-budget, --tags and --exclude-tags need a directory of notebooks",
+                  RED, RESET);
+        std::process::exit(1);
+    }
+    if args.format != export::Format::Text && args.output.is_none() {
+        if let Err(e) = export::divert_stdout() {
+            eprintln!("{}Output error:{} {}", RED, RESET, e);
+            std::process::exit(1);
+        }
+    }
+    let stem = task_file
         .file_stem()
         .and_then(|s| s.to_str())
         .unwrap_or("task_ws");
-    let workspace = Path::new("tasks").join(stem);
+    let workspace = Path::new(reaper::ROOT).join(stem);
+    let upload_target = args.upload.as_deref().map(upload::Target::parse).transpose()
+        .unwrap_or_else(|e| {
+            eprintln!("{}Upload error:{} {}", RED, RESET, e);
+            std::process::exit(1);
+        });
 
-    let nb = load_notebook(&args.task_file).unwrap_or_else(|e| {
-        eprintln!("{}Error loading {}: {}{}", RED, args.task_file.display(), e, RESET);
+    let nb = Notebook::load(task_file).unwrap_or_else(|e| {
+        eprintln!("{}Error loading {}: {}{}", RED, task_file.display(), e, RESET);
         std::process::exit(1);
     });
 
-    let files = match prepare_workspace(&nb, &workspace) {
-        Ok(f) => f,
-        Err(err) => {
-            eprintln!("{}Validation error:{} {}", RED, BOLD, RESET);
-            eprintln!("  {}", err);
+    let manifest = Manifest::for_notebook(task_file).unwrap_or_else(|e| {
+        eprintln!("{}Error loading manifest:{} {}", RED, RESET, e);
+        std::process::exit(1);
+    });
+    if let Some(path) = &args.structure {
+        let spec = structure::Spec::load(path).unwrap_or_else(|e| {
+            eprintln!("{}Structure spec error:{} {}", RED, RESET, e);
+            std::process::exit(1);
+        });
+        let violations = spec.check(&nb);
+        if !violations.is_empty() {
+            eprintln!("{}Structure error:{} {} does not follow {}", RED, RESET, task_file.display(), path.display());
+            for v in &violations {
+                eprintln!("  {}", v);
+            }
             std::process::exit(1);
         }
-    };
+        println!("{}Structure:{} follows {}", BOLD, RESET, path.display());
+    }
+    let config = config::Config::load(&args.config).unwrap_or_else(|e| {
+        eprintln!("{}Config error:{} {}", RED, RESET, e);
+        std::process::exit(1);
+    });
+    let pipeline = stages::pipeline(&config.stages_for(stem)).unwrap_or_else(|e| {
+        eprintln!("{}Config error:{} stages: {}", RED, RESET, e);
+        std::process::exit(1);
+    });
+
+    if let Err(e) = interrupt::install(exit_on_interrupt) {
+        eprintln!("{}Signal handler error:{} {}", RED, RESET, e);
+    }
+    reaper::sweep(args.reap_after.map(|hours| Duration::from_secs(hours * 3600)));
+    let _workspace_lock = lock::acquire(&workspace).unwrap_or_else(|e| {
+        eprintln!("{}Lock error:{} {}", RED, RESET, e);
+        std::process::exit(1);
+    });
+    let flags = stage_flags(&args);
+    let mut cx = stages::Context::new(task_file, &nb, &manifest, &workspace, &flags, unpacked.as_ref());
+    let outcome = stages::run(&pipeline, &mut cx);
+    finish(&args, &cx, upload_target.as_ref(), outcome)
+}
 
-    // Build per-test pass/fail matrix over N runs
-    let mut matrix: HashMap<String, Vec<bool>> = HashMap::new();
-
-    for run in 1..=args.runs {
-        println!("{}Run {}/{}{}", BLUE, run, args.runs, RESET);
-        let t0 = Instant::now();
-        match run_cargo_test_once(&workspace, args.timeout) {
-            Ok(results) => {
-                println!("  {}completed in {:.2}s{}", GREEN, t0.elapsed().as_secs_f32(), RESET);
-                for (name, passed) in results {
-                    matrix.entry(name).or_default().push(passed);
+/// The switches the stages consult, from the command line.
+fn stage_flags(args: &Args) -> stages::Flags {
+    let base = if args.only.is_empty() { &Section::ALL[..] } else { &args.only[..] };
+    // Per-run shuffle seeds, derived from one base so a whole session replays
+    let shuffle_seed = args.shuffle_seed.or_else(|| {
+        args.shuffle.then(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_nanos() as u64)
+                .unwrap_or(0)
+        })
+    });
+    stages::Flags {
+        sections: base.iter().copied().filter(|s| !args.skip.contains(s)).collect(),
+        clean: args.clean,
+        crate_policy: args.crate_policy.clone(),
+        git_snapshots: args.git_snapshots,
+        fixture_cache: args.fixture_cache.clone(),
+        offline: args.offline,
+        apply_patch: args.apply_patch.clone(),
+        nightly: args.nightly.clone(),
+        redact: args.redact,
+        redact_rules: args.redact_rules.clone(),
+        options: RunOptions {
+            build_timeout: args.build_timeout.unwrap_or(args.timeout),
+            timeout: args.test_timeout.unwrap_or(args.timeout),
+            hermetic: args.hermetic_env,
+            core_dumps: args.core_dumps,
+            sample_interval: args.sample_interval.map(|ms| Duration::from_millis(ms.max(1))),
+            limits: sandbox::Limits {
+                memory_mb: args.memory_limit,
+                cpu_secs: args.cpu_limit,
+                max_procs: args.max_procs,
+                no_network: args.no_network,
+            },
+            report_time: args.test_times || args.max_test_time.is_some(),
+            ..RunOptions::new(&[])
+        },
+        runs: args.runs,
+        jobs: args.jobs,
+        max_test_time: args.max_test_time,
+        timeout_retry: args.timeout_retry,
+        stress_cpu: args.stress_cpu,
+        shuffle_seed,
+        seed_matrix: args.seed_matrix,
+        shard: args.shard,
+        resource_html: args.resource_html.clone(),
+        template_dir: args.template_dir.clone(),
+        history: args.history.clone(),
+        flake_window: args.flake_window,
+        flake_policy: args.flake_policy,
+        review_snapshots: args.review_snapshots,
+        accept_snapshots: args.accept_snapshots,
+        ub: if args.miri { Some(ub::Mode::Miri) } else { args.sanitizer.map(ub::Mode::Sanitizer) },
+        hermeticity: args.hermeticity,
+        measure_memory: args.measure_memory,
+        install_msrv: args.install_msrv,
+        lint: args.lint.then_some(args.lint_policy),
+        coverage: args.coverage.then(|| args.min_coverage.unwrap_or(0.0)),
+        toolchains: args.toolchains.clone(),
+        candidates: args.candidates.clone(),
+    }
+}
+
+/// What `--upload` stores as `report.txt`: the report as printed and how
+/// the validation ended.
+fn upload_report(cx: &stages::Context, doc: &export::Document, error: Option<&stages::StageError>) -> String {
+    let mut report = cx.report.clone();
+    if cx.flags.options.hermetic {
+        report += "\nEnvironment:\n";
+        for (key, value) in &cx.env {
+            report += &format!("  {}={}\n", key, value);
+        }
+    }
+    if let Some((toolchain, uses)) = &cx.nightly {
+        report += &format!("\nToolchain: {} (nightly features: {})\n", toolchain, uses.join(", "));
+    }
+    if let Some(e) = error {
+        report += &format!("\nStage {} error: {}\n", e.stage, e.message);
+    }
+    report += &format!("\nResult: {}\n", doc.status());
+    report
+}
+
+/// Record the validation in the `--history` database at `path`.
+fn record_history(path: &Path, cx: &stages::Context, status: &str) -> Result<(), String> {
+    let stem = cx.notebook.file_stem().and_then(|s| s.to_str()).unwrap_or("task_ws");
+    history::History::open(path)?.record(&history::Validation {
+        task: stem,
+        notebook_hash: history::notebook_hash(cx.notebook)?,
+        toolchain: history::toolchain(cx.workspace, &cx.opts()),
+        status,
+        build: cx.build_time,
+        runs: &cx.runs.times,
+        matrix: &cx.runs.matrix,
+    })
+}
+
+/// Write what the validation leaves behind (shard report, digest,
+/// history, upload, `--format` document) and exit with its verdict.
+fn finish(
+    args: &Args,
+    cx: &stages::Context,
+    upload_target: Option<&upload::Target>,
+    outcome: Result<stages::Outcome, stages::StageError>,
+) -> ! {
+    let task_file = cx.notebook;
+    // every failure ends here, whichever stage it came from
+    let fail = |code: i32| -> ! {
+        if args.shell_on_failure {
+            if let Err(e) = shell::run(cx.workspace, Some(task_file), &cx.env) {
+                eprintln!("{}Shell error:{} {}", RED, RESET, e);
+            }
+        }
+        std::process::exit(code);
+    };
+    // and every validation here: upload and export its document, then
+    // exit with the document's code
+    let leave = |doc: export::Document, error: Option<&stages::StageError>| -> ! {
+        // a cut-short validation says nothing about the trend
+        if let (Some(path), false) = (&args.history, doc.exit_code() == interrupt::EXIT_CODE) {
+            if let Err(e) = record_history(path, cx, doc.status()) {
+                eprintln!("{}History error:{} {}", RED, RESET, e);
+            }
+        }
+        if let Some(target) = upload_target {
+            let stem = task_file.file_stem().and_then(|s| s.to_str()).unwrap_or("task_ws");
+            let report = cx.redactor.apply(&upload_report(cx, &doc, error));
+            let uploaded = doc
+                .json()
+                .and_then(|json| upload::upload(target, stem, &report, &cx.redactor.apply(&json), cx.workspace));
+            match uploaded {
+                Ok(location) => println!("{}Uploaded to {}{}", BLUE, location, RESET),
+                Err(e) => {
+                    eprintln!("{}Upload error:{} {}", RED, RESET, e);
+                    fail(1);
                 }
             }
-            Err(e) => {
-                eprintln!("{}cargo test error:{} {}", RED, RESET, e);
-                std::process::exit(1);
+        }
+        if let Err(e) = doc.write(args.format, args.output.as_deref()) {
+            eprintln!("{}Output error:{} {}", RED, RESET, e);
+        }
+        match doc.exit_code() {
+            0 => {
+                println!("{}All tests consistently passed 🎉{}", GREEN, RESET);
+                std::process::exit(0);
             }
+            interrupt::EXIT_CODE => std::process::exit(interrupt::EXIT_CODE),
+            code => fail(code),
         }
-    }
-
-    // Print consistency table
-    println!("\n{:<45} | {:<16} | {:>6} | {:>6}",
-             "Test", "Consistency", "Pass%", "Fail%");
-    println!("{:-<45}-+-{:-<16}-+-{:-<6}-+-{:-<6}", "", "", "", "");
-
-    let mut consistent_pass = 0;
-    let mut consistent_fail = 0;
-    let mut flaky = 0;
-
-    for (test, runs) in &matrix {
-        let pass_count = runs.iter().filter(|&&b| b).count() as f32;
-        let total = runs.len() as f32;
-        let pass_pct = 100.0 * pass_count / total;
-        let fail_pct = 100.0 - pass_pct;
-
-        let (label, col) = if pass_pct == 100.0 {
-            consistent_pass += 1;
-            ("Consistent pass", GREEN)
-        } else if fail_pct == 100.0 {
-            consistent_fail += 1;
-            ("Consistent fail", RED)
-        } else {
-            flaky += 1;
-            ("Flaky", BLUE)
+    };
+    let runs = &cx.runs;
+    let outcome = match outcome {
+        Ok(outcome) => outcome,
+        Err(e) => {
+            eprintln!("{}Stage {} error:{} {}", RED, e.stage, RESET, e.message);
+            let doc = match cx.build_time {
+                Some(build_time) => export::Document::new(task_file, "error", 1)
+                    .runs(build_time, &runs.times, &runs.matrix, &runs.failure_outputs),
+                None if e.stage == "build" => {
+                    if let Some(path) = &args.digest {
+                        let written =
+                            digest::write_build(path, task_file, args.digest_budget, &cx.build_errors, &cx.redactor);
+                        if let Err(e) = written {
+                            eprintln!("{}Digest error:{} {}", RED, RESET, e);
+                        }
+                    }
+                    export::Document::new(task_file, "build-failed", 1)
+                }
+                None => export::Document::new(task_file, "error", 1),
+            };
+            leave(doc.message(e.message.clone()), Some(&e));
+        }
+    };
+    let build_time = cx.build_time.unwrap_or_default();
+
+    if let Some(path) = &args.shard_report {
+        let shard_report = shard::ShardReport {
+            schema_version: shard::SCHEMA_VERSION,
+            notebook: task_file.display().to_string(),
+            shard: args.shard.map(|s| s.to_string()),
+            runs: runs.completed,
+            tests: runs.matrix.as_map().iter().map(|(k, v)| (k.clone(), v.clone())).collect(),
+            resources: runs.usage.clone(),
         };
+        if let Err(e) = shard_report.save(path, &cx.redactor) {
+            eprintln!("{}Report error:{} {}", RED, RESET, e);
+            fail(1);
+        }
+    }
 
-        println!("{:<45} | {}{:<16}{} | {:>5.0}% | {:>5.0}%",
-                 test, col, label, RESET, pass_pct, fail_pct);
+    if outcome == stages::Outcome::Interrupted {
+        leave(
+            export::Document::new(task_file, "interrupted", interrupt::EXIT_CODE)
+                .runs(build_time, &runs.times, &runs.matrix, &runs.failure_outputs),
+            None,
+        );
     }
 
-    // Totals & exit
-    println!("\nTotals:");
-    println!("Consistent pass : {}", consistent_pass);
-    println!("Consistent fail : {}", consistent_fail);
-    println!("Flaky           : {}", flaky);
+    if let Some(path) = &args.digest {
+        let (_, tests) = source::read_crate(cx.workspace);
+        let written = digest::write_tests(
+            path, task_file, args.digest_budget, runs.matrix.as_map(), &runs.failure_outputs, &tests, &cx.redactor);
+        if let Err(e) = written {
+            eprintln!("{}Digest error:{} {}", RED, RESET, e);
+        }
+    }
 
-    if consistent_fail == 0 && flaky == 0 {
-        println!("{}All tests consistently passed 🎉{}", GREEN, RESET);
-        std::process::exit(0);
+    let passed = outcome == stages::Outcome::Passed;
+    let status = if passed {
+        "passed"
+    } else if runs.violations.is_empty() {
+        "failed"
     } else {
-        std::process::exit(1);
-    }
+        "violation"
+    };
+    leave(
+        export::Document::new(task_file, status, i32::from(!passed))
+            .runs(build_time, &runs.times, &runs.matrix, &runs.failure_outputs)
+            .violations(&runs.violations),
+        None,
+    );
 }
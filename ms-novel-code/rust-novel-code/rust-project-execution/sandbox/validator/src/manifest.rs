@@ -0,0 +1,130 @@
+//! Optional per-task manifest: `<notebook stem>.toml` next to the notebook.
+
+use crate::lint::Level;
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Component, Path},
+};
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct Manifest {
+    /// Variables exported to the test process (the `# env` cell wins on clashes).
+    pub env: BTreeMap<String, String>,
+    /// Task tags, which select the `--crate-policy` rules that apply.
+    pub tags: Vec<String>,
+    /// Remote inputs downloaded into the workspace's `data/` directory.
+    pub fixtures: Vec<Fixture>,
+    /// Declared difficulty (`easy`, `medium`, `hard` or `expert`), checked
+    /// against the estimate from validation.
+    pub difficulty: Option<String>,
+    /// Oldest Rust release (like `1.70`) the code must build with.
+    pub msrv: Option<String>,
+    /// `reject` or the nightly toolchain to build with when the code
+    /// uses nightly-only features; overrides `--nightly`.
+    pub nightly: Option<String>,
+    /// Peak RSS any single test may reach, checked by `--measure-memory`.
+    pub memory_budget_mb: Option<u64>,
+    /// Policy for `validator lint`.
+    pub lint: LintPolicy,
+    /// Requirements on the test suite, checked during validation.
+    pub quality: QualityPolicy,
+    /// How `# expected_output` cells are compared with the binary's stdout.
+    pub output: OutputPolicy,
+    /// Requirement IDs and what each asks for, traced to the tests that
+    /// cover them.
+    pub requirements: BTreeMap<String, String>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct QualityPolicy {
+    /// Tests allowed to check nothing (no assertion, panic or `?`).
+    pub max_vacuous: usize,
+    /// Fewest `#[test]` functions the suite may have.
+    pub min_tests: usize,
+    /// Fewest tests per category, like `edge_case = 2`. A test is in a
+    /// category if its name starts with it or a `// category: a, b`
+    /// comment sits above it.
+    pub categories: BTreeMap<String, usize>,
+    /// Test functions the suite must define, each of which must run in
+    /// every run (typically one per spec requirement).
+    pub required_tests: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct OutputPolicy {
+    /// Ignore whitespace at the end of lines and blank lines at the end.
+    pub trim_trailing_whitespace: bool,
+    /// Read `\r\n` as `\n`.
+    pub normalize_line_endings: bool,
+    /// Compare each line's words only, so the amount of whitespace around
+    /// and between them does not count, nor do blank lines at the end.
+    pub normalize_whitespace: bool,
+}
+
+impl Default for OutputPolicy {
+    fn default() -> Self {
+        OutputPolicy { trim_trailing_whitespace: true, normalize_line_endings: true, normalize_whitespace: false }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct LintPolicy {
+    /// Fewest words the problem statement may have.
+    pub min_words: usize,
+    /// Level per check name, overriding the defaults in [`crate::lint::CHECKS`].
+    pub levels: BTreeMap<String, Level>,
+}
+
+impl Default for LintPolicy {
+    fn default() -> Self {
+        LintPolicy { min_words: 150, levels: BTreeMap::new() }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct Fixture {
+    pub url: String,
+    /// Lowercase hex SHA-256 of the expected content.
+    pub sha256: String,
+    /// Destination under `data/`; defaults to the last URL segment.
+    pub path: Option<String>,
+}
+
+impl Fixture {
+    /// `path`, or else the last URL segment, which must be a plain file
+    /// name so it cannot leave `data/`.
+    pub fn file_name(&self) -> Result<&str, String> {
+        if let Some(path) = &self.path {
+            return Ok(path);
+        }
+        let name = self
+            .url
+            .split(['?', '#'])
+            .next()
+            .and_then(|u| u.rsplit('/').next())
+            .unwrap_or_default();
+        let mut parts = Path::new(name).components();
+        match (parts.next(), parts.next()) {
+            (Some(Component::Normal(_)), None) => Ok(name),
+            _ => Err(format!("fixture {}: no file name in the URL; set `path`", self.url)),
+        }
+    }
+}
+
+impl Manifest {
+    /// Load the manifest belonging to `notebook`, or an empty one if absent.
+    pub fn for_notebook(notebook: &Path) -> Result<Self, String> {
+        let path = notebook.with_extension("toml");
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let raw = fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        toml::from_str(&raw).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+}
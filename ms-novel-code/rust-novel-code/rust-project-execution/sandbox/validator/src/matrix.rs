@@ -0,0 +1,138 @@
+//! The per-test pass/fail matrix over repeated runs, and the flakiness
+//! classification every report is built on.
+
+use crate::report::Totals;
+use std::{collections::HashMap, time::Duration};
+
+/// How a test behaved over all its runs.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Classification {
+    /// Passed every run.
+    Pass,
+    /// Failed every run.
+    Fail,
+    /// Passed some runs and failed others.
+    Flaky,
+    /// Reported by Miri or a sanitizer, however its runs went.
+    Unsound,
+}
+
+impl Classification {
+    /// Classification of a test with these per-run `results`.
+    pub fn of(results: &[bool]) -> Self {
+        if results.iter().all(|&r| r) {
+            Classification::Pass
+        } else if results.iter().all(|&r| !r) {
+            Classification::Fail
+        } else {
+            Classification::Flaky
+        }
+    }
+
+    /// `pass`, `fail` or `flaky`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Classification::Pass => "pass",
+            Classification::Fail => "fail",
+            Classification::Flaky => "flaky",
+            Classification::Unsound => "unsound",
+        }
+    }
+}
+
+/// Pass/fail of every test in every run, in run order.
+#[derive(Clone, Default)]
+pub struct RunMatrix {
+    results: HashMap<String, Vec<bool>>,
+    /// Durations libtest reported, in run order; not every run reports
+    /// every test (an end-to-end case has none).
+    times: HashMap<String, Vec<Duration>>,
+    /// Tests found unsound, with the finding.
+    unsound: HashMap<String, String>,
+}
+
+impl RunMatrix {
+    pub fn new() -> Self {
+        RunMatrix::default()
+    }
+
+    /// Add one run's outcomes.
+    pub fn record(&mut self, outcomes: HashMap<String, bool>) {
+        for (name, passed) in outcomes {
+            self.results.entry(name).or_default().push(passed);
+        }
+    }
+
+    /// Add one run's per-test durations.
+    pub fn record_times(&mut self, times: HashMap<String, Duration>) {
+        for (name, took) in times {
+            self.times.entry(name).or_default().push(took);
+        }
+    }
+
+    /// Per-run durations of the test `name`.
+    pub fn times(&self, name: &str) -> Option<&[Duration]> {
+        self.times.get(name).map(Vec::as_slice)
+    }
+
+    /// Per-run results of the test `name`.
+    pub fn results(&self, name: &str) -> Option<&[bool]> {
+        self.results.get(name).map(Vec::as_slice)
+    }
+
+    pub fn classify(&self, name: &str) -> Option<Classification> {
+        let results = self.results(name)?;
+        Some(if self.unsound.contains_key(name) { Classification::Unsound } else { Classification::of(results) })
+    }
+
+    /// Classify the test `name` unsound for `finding`.
+    pub fn mark_unsound(&mut self, name: &str, finding: String) {
+        if self.results.contains_key(name) {
+            self.unsound.insert(name.to_string(), finding);
+        }
+    }
+
+    /// Why the test `name` is unsound, if it is.
+    pub fn finding(&self, name: &str) -> Option<&str> {
+        self.unsound.get(name).map(String::as_str)
+    }
+
+    /// Every test with its classification, by name.
+    pub fn classified(&self) -> Vec<(&str, Classification)> {
+        let mut tests: Vec<_> = self
+            .results
+            .keys()
+            .filter_map(|name| Some((name.as_str(), self.classify(name)?)))
+            .collect();
+        tests.sort_by_key(|&(name, _)| name);
+        tests
+    }
+
+    /// Consistent passes, consistent fails and flaky tests.
+    pub fn totals(&self) -> Totals {
+        let mut totals = Totals { pass: 0, fail: 0, flaky: 0, unsound: 0 };
+        for (_, classification) in self.classified() {
+            totals.count(classification);
+        }
+        totals
+    }
+
+    pub fn len(&self) -> usize {
+        self.results.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.results.is_empty()
+    }
+
+    /// The matrix as the test-to-results map reports take.
+    pub fn as_map(&self) -> &HashMap<String, Vec<bool>> {
+        &self.results
+    }
+}
+
+impl From<HashMap<String, Vec<bool>>> for RunMatrix {
+    fn from(results: HashMap<String, Vec<bool>>) -> Self {
+        RunMatrix { results, ..RunMatrix::default() }
+    }
+}
@@ -0,0 +1,132 @@
+//! `--measure-memory`: run every test on its own (`<binary> --exact
+//! <test>`) and record the process's peak RSS from `wait4`'s resource
+//! usage, flagging tests over the manifest's `memory_budget_mb`. For
+//! tasks where memory, not time, is the real constraint.
+
+use crate::colors::*;
+use crate::{interrupt, RunOptions};
+use serde_json::Value;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Stdio,
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Test executables of the workspace, from cargo's build messages (the
+/// tests are already built, so this only re-reads the artifacts).
+fn test_binaries(workspace: &Path, opts: &RunOptions) -> Result<Vec<PathBuf>, String> {
+    let out = opts.command("cargo")
+        .args(["test", "--no-run", "--color=never", "--message-format=json"])
+        .current_dir(workspace)
+        .stderr(Stdio::null())
+        .output()
+        .map_err(|e| e.to_string())?;
+    Ok(String::from_utf8_lossy(&out.stdout)
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .filter(|msg| msg["reason"] == "compiler-artifact" && msg["profile"]["test"] == true)
+        .filter_map(|msg| msg["executable"].as_str().map(PathBuf::from))
+        .collect())
+}
+
+fn list(exe: &Path, workspace: &Path, opts: &RunOptions) -> Result<Vec<String>, String> {
+    let out = opts.command(exe)
+        .args(["--list", "--format", "terse"])
+        .current_dir(workspace)
+        .output()
+        .map_err(|e| format!("{}: {}", exe.display(), e))?;
+    Ok(String::from_utf8_lossy(&out.stdout)
+        .lines()
+        .filter_map(|l| l.strip_suffix(": test"))
+        .map(str::to_string)
+        .collect())
+}
+
+/// Peak RSS in KiB of running `test` alone, and whether it passed.
+fn measure(exe: &Path, test: &str, workspace: &Path, opts: &RunOptions) -> Result<(u64, bool), String> {
+    let manifest_dir = fs::canonicalize(workspace).map_err(|e| e.to_string())?;
+    let child = interrupt::spawn(
+        opts.command(exe)
+            .args([test, "--exact", "--test-threads=1", "-q"])
+            .current_dir(workspace)
+            // as `cargo test` would set it
+            .env("CARGO_MANIFEST_DIR", manifest_dir)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null()),
+    )
+    .map_err(|e| format!("{}: {}", exe.display(), e))?;
+    let pid = child.id() as libc::pid_t;
+    let deadline = Instant::now() + Duration::from_secs(opts.timeout);
+    let mut status = 0;
+    // SAFETY: an all-zero rusage is a valid value
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    let mut timed_out = false;
+    loop {
+        // SAFETY: `status` and `usage` are valid for writes; `pid` is our
+        // unreaped child, which std never waits for once dropped
+        let flags = if timed_out { 0 } else { libc::WNOHANG };
+        let reaped = unsafe { libc::wait4(pid, &mut status, flags, &mut usage) };
+        if reaped == pid {
+            break;
+        }
+        if reaped < 0 {
+            interrupt::reap(&child);
+            return Err(format!("wait4: {}", std::io::Error::last_os_error()));
+        }
+        if Instant::now() >= deadline {
+            // SAFETY: kill(2) has no memory-safety preconditions
+            unsafe { libc::kill(pid, libc::SIGKILL) };
+            timed_out = true;
+        } else {
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+    interrupt::reap(&child);
+    if timed_out {
+        return Err(format!("{}: Test timeout reached ({}s)", test, opts.timeout));
+    }
+    // Linux reports ru_maxrss in KiB
+    let passed = libc::WIFEXITED(status) && libc::WEXITSTATUS(status) == 0;
+    Ok((usage.ru_maxrss as u64, passed))
+}
+
+/// Measure every test and print their high-water marks, largest first.
+/// Returns whether all stayed within `budget_mb` (always, without one).
+pub fn check(workspace: &Path, budget_mb: Option<u64>, opts: &RunOptions) -> Result<bool, String> {
+    println!("{}Measuring memory per test{}", BLUE, RESET);
+    let mut peaks: Vec<(String, u64, bool)> = Vec::new();
+    for exe in test_binaries(workspace, opts)? {
+        for test in list(&exe, workspace, opts)? {
+            if interrupt::interrupted() {
+                return Err("interrupted".into());
+            }
+            let (kib, passed) = measure(&exe, &test, workspace, opts)?;
+            peaks.push((test, kib, passed));
+        }
+    }
+    peaks.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    match budget_mb {
+        Some(mb) => println!("\n{}Memory high-water marks{} (budget {} MiB):", BOLD, RESET, mb),
+        None => println!("\n{}Memory high-water marks:{}", BOLD, RESET),
+    }
+    let mut over = Vec::new();
+    for (test, kib, passed) in &peaks {
+        let mib = *kib as f64 / 1024.0;
+        let exceeds = budget_mb.is_some_and(|mb| mib > mb as f64);
+        match (exceeds, passed) {
+            (true, _) => println!("  {:<45} {:>9.1} MiB  {}over budget{}", test, mib, RED, RESET),
+            (false, false) => println!("  {:<45} {:>9.1} MiB  failed", test, mib),
+            (false, true) => println!("  {:<45} {:>9.1} MiB", test, mib),
+        }
+        if exceeds {
+            over.push(test.as_str());
+        }
+    }
+    if !over.is_empty() {
+        println!("{}Over the memory budget:{} {}", RED, RESET, over.join(", "));
+    }
+    Ok(over.is_empty())
+}
@@ -0,0 +1,63 @@
+//! Minimum supported Rust version: a manifest's `msrv = "1.70"` means the
+//! extracted code must also build with that toolchain. The check runs in
+//! a separate workspace after the ordinary build, so an MSRV violation is
+//! reported as such rather than as a compile failure.
+
+use crate::colors::*;
+use crate::toolchains::{copy_sources, workspace_for};
+use crate::{build_tests, RunOptions};
+use std::{
+    path::Path,
+    process::{Command, Stdio},
+};
+
+pub fn installed(toolchain: &str) -> bool {
+    Command::new("rustup")
+        .args(["run", toolchain, "rustc", "--version"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|s| s.success())
+}
+
+fn install(toolchain: &str) -> Result<(), String> {
+    println!("{}Installing Rust {}{}", BLUE, toolchain, RESET);
+    let status = Command::new("rustup")
+        .args(["toolchain", "install", toolchain, "--profile", "minimal"])
+        .status()
+        .map_err(|e| format!("rustup: {}", e))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("`rustup toolchain install {}` failed (exit {:?})", toolchain, status.code()))
+    }
+}
+
+/// Build the workspace with `msrv` and print the outcome. Returns `false`
+/// only for a violation; a missing toolchain skips the check unless
+/// `allow_install` lets rustup fetch it.
+pub fn check(workspace: &Path, msrv: &str, allow_install: bool, base: &RunOptions) -> Result<bool, String> {
+    println!("{}MSRV {}{}", BLUE, msrv, RESET);
+    if !installed(msrv) {
+        if !allow_install {
+            println!("{}MSRV {} skipped:{} toolchain not installed (pass --install-msrv)",
+                     BOLD, msrv, RESET);
+            return Ok(true);
+        }
+        install(msrv)?;
+    }
+    let dir = workspace_for(workspace, &format!("msrv-{}", msrv));
+    copy_sources(workspace, &dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    let mut env = base.env.to_vec();
+    env.push(("RUSTUP_TOOLCHAIN".to_string(), msrv.to_string()));
+    match build_tests(&dir, &RunOptions { env: &env, ..*base }) {
+        Ok(_) => {
+            println!("{}MSRV {}:{} {}builds{}", BOLD, msrv, RESET, GREEN, RESET);
+            Ok(true)
+        }
+        Err(e) => {
+            println!("{}MSRV {}:{} {}violated{} ({})", BOLD, msrv, RESET, RED, RESET, e);
+            Ok(false)
+        }
+    }
+}
@@ -0,0 +1,54 @@
+//! Code that only nightly Rust builds: `#![feature(...)]` attributes and
+//! `#[bench]` functions. Found in the extracted workspace before the
+//! build, they either reject the notebook or route every cargo command to
+//! a pinned nightly toolchain, so a stable compiler's error about
+//! unstable features never stands in for the real reason.
+
+use crate::deps::rust_files;
+use crate::source::strip_comments_and_strings;
+use std::{fs, path::Path};
+
+/// What to do with a notebook needing nightly.
+#[derive(Clone)]
+pub enum Policy {
+    Reject,
+    /// Build with this toolchain, like `nightly-2024-06-01`.
+    Route(String),
+}
+
+impl Policy {
+    /// `reject` or a nightly toolchain name.
+    pub fn parse(s: &str) -> Result<Policy, String> {
+        match s {
+            "reject" => Ok(Policy::Reject),
+            _ if s.starts_with("nightly") => Ok(Policy::Route(s.to_string())),
+            _ => Err(format!("expected `reject` or a nightly toolchain, not `{}`", s)),
+        }
+    }
+}
+
+/// The nightly-only constructs in `workspace`, as `file: construct`.
+pub fn detect(workspace: &Path) -> Vec<String> {
+    let mut files = Vec::new();
+    for root in ["src", "tests", "benches", "examples", "build.rs"] {
+        rust_files(&workspace.join(root), &mut files);
+    }
+    files.sort();
+    let mut uses = Vec::new();
+    for file in files {
+        let Ok(src) = fs::read_to_string(&file) else { continue };
+        let rel = file.strip_prefix(workspace).unwrap_or(&file).display().to_string();
+        // whitespace is allowed anywhere in an attribute
+        let code: String = strip_comments_and_strings(&src).split_whitespace().collect();
+        let mut rest = code.as_str();
+        while let Some(at) = rest.find("#![feature(") {
+            rest = &rest[at + "#![feature(".len()..];
+            let end = rest.find(')').unwrap_or(rest.len());
+            uses.push(format!("{}: #![feature({})]", rel, rest[..end].replace(',', ", ")));
+        }
+        if code.contains("#[bench]") {
+            uses.push(format!("{}: #[bench]", rel));
+        }
+    }
+    uses
+}
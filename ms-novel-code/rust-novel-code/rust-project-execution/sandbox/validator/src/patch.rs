@@ -0,0 +1,234 @@
+//! `--apply-patch <diff>`: apply a unified diff to the extracted
+//! workspace before building, to check whether a candidate fix makes the
+//! tests pass without regenerating the notebook. Paths in the diff are
+//! relative to the workspace, with or without git's `a/` and `b/`.
+
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+/// How far from its stated line a hunk may have drifted.
+const MAX_OFFSET: usize = 200;
+
+struct Hunk {
+    /// 1-based line in the original file.
+    start: usize,
+    old: Vec<String>,
+    new: Vec<String>,
+}
+
+struct FilePatch {
+    /// `None` for `/dev/null`.
+    old: Option<String>,
+    new: Option<String>,
+    hunks: Vec<Hunk>,
+}
+
+fn path_of(header: &str, prefix: &str) -> Option<String> {
+    // git appends a tab and timestamp in some modes
+    let path = header.split('\t').next().unwrap_or(header).trim();
+    if path == "/dev/null" {
+        return None;
+    }
+    Some(path.strip_prefix(prefix).unwrap_or(path).to_string())
+}
+
+/// `-12,3` or `+12` as (start, length).
+fn range(spec: &str) -> Result<(usize, usize), String> {
+    let bad = || format!("bad hunk range `{}`", spec);
+    let (start, len) = spec[1..].split_once(',').unwrap_or((&spec[1..], "1"));
+    Ok((start.parse().map_err(|_| bad())?, len.parse().map_err(|_| bad())?))
+}
+
+fn parse(diff: &str) -> Result<Vec<FilePatch>, String> {
+    let mut files: Vec<FilePatch> = Vec::new();
+    let mut lines = diff.lines().peekable();
+    while let Some(line) = lines.next() {
+        if let Some(old) = line.strip_prefix("--- ") {
+            let new = lines
+                .next()
+                .and_then(|l| l.strip_prefix("+++ "))
+                .ok_or_else(|| format!("`--- {}` is not followed by `+++`", old))?;
+            files.push(FilePatch { old: path_of(old, "a/"), new: path_of(new, "b/"), hunks: Vec::new() });
+        } else if let Some(header) = line.strip_prefix("@@ ") {
+            let file = files.last_mut().ok_or("hunk before any file header")?;
+            let mut specs = header.split_whitespace();
+            let (start, mut old_left) = range(specs.next().unwrap_or(""))?;
+            let (_, mut new_left) = range(specs.next().unwrap_or(""))?;
+            let mut hunk = Hunk { start, old: Vec::new(), new: Vec::new() };
+            while old_left + new_left > 0 {
+                let body = lines.next().ok_or("diff ends inside a hunk")?;
+                // an empty line is an empty context line to some editors
+                let (tag, text) = body.split_at(body.len().min(1));
+                match tag {
+                    " " | "" => {
+                        hunk.old.push(text.to_string());
+                        hunk.new.push(text.to_string());
+                        old_left = old_left.saturating_sub(1);
+                        new_left = new_left.saturating_sub(1);
+                    }
+                    "-" => {
+                        hunk.old.push(text.to_string());
+                        old_left = old_left.saturating_sub(1);
+                    }
+                    "+" => {
+                        hunk.new.push(text.to_string());
+                        new_left = new_left.saturating_sub(1);
+                    }
+                    "\\" => {}
+                    _ => return Err(format!("unexpected line in hunk: `{}`", body)),
+                }
+            }
+            while lines.peek().is_some_and(|l| l.starts_with('\\')) {
+                lines.next();
+            }
+            file.hunks.push(hunk);
+        }
+    }
+    if files.is_empty() {
+        return Err("no file changes in the diff".into());
+    }
+    Ok(files)
+}
+
+/// `path` under `workspace`, refusing anything that would leave it.
+fn inside(workspace: &Path, path: &str) -> Result<PathBuf, String> {
+    let rel = Path::new(path);
+    if rel.components().any(|c| !matches!(c, Component::Normal(_) | Component::CurDir)) {
+        return Err(format!("`{}` is not a path inside the workspace", path));
+    }
+    Ok(workspace.join(rel))
+}
+
+/// Where `old` occurs in `lines`, searching outwards from `at`.
+fn locate(lines: &[String], old: &[String], at: usize) -> Option<usize> {
+    let fits = |i: usize| lines.get(i..i + old.len()).is_some_and(|s| s == old);
+    (0..=MAX_OFFSET).find_map(|d| {
+        [at.checked_add(d), at.checked_sub(d)].into_iter().flatten().find(|&i| fits(i))
+    })
+}
+
+/// `lines` with `hunks` applied, in order.
+fn patch_lines(mut lines: Vec<String>, hunks: &[Hunk], target: &str) -> Result<Vec<String>, String> {
+    // hunks shift later ones by however many lines they add or remove
+    let mut shift: isize = 0;
+    for (n, hunk) in hunks.iter().enumerate() {
+        // a hunk without old lines (`-5,0`, from `diff -U0`) inserts
+        // after its stated line, any other replaces from it
+        let start = if hunk.old.is_empty() { hunk.start } else { hunk.start.saturating_sub(1) };
+        let at = (start as isize + shift).max(0) as usize;
+        let i = locate(&lines, &hunk.old, at)
+            .ok_or_else(|| format!("{}: hunk {} does not apply", target, n + 1))?;
+        lines.splice(i..i + hunk.old.len(), hunk.new.iter().cloned());
+        shift += hunk.new.len() as isize - hunk.old.len() as isize;
+    }
+    Ok(lines)
+}
+
+fn apply_file(workspace: &Path, patch: &FilePatch) -> Result<String, String> {
+    let target = patch.new.as_ref().or(patch.old.as_ref()).ok_or("diff between two /dev/nulls")?;
+    let source = match &patch.old {
+        Some(old) => {
+            let path = inside(workspace, old)?;
+            fs::read_to_string(&path).map_err(|e| format!("{}: {}", old, e))?
+        }
+        None => String::new(),
+    };
+    let lines = patch_lines(source.lines().map(str::to_string).collect(), &patch.hunks, target)?;
+
+    match &patch.new {
+        Some(new) => {
+            let path = inside(workspace, new)?;
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).map_err(|e| format!("{}: {}", new, e))?;
+            }
+            let mut text = lines.join("\n");
+            if !lines.is_empty() {
+                text.push('\n');
+            }
+            fs::write(&path, text).map_err(|e| format!("{}: {}", new, e))?;
+            if let Some(old) = patch.old.as_ref().filter(|old| *old != new) {
+                fs::remove_file(inside(workspace, old)?).map_err(|e| format!("{}: {}", old, e))?;
+            }
+        }
+        None => {
+            let path = inside(workspace, target)?;
+            fs::remove_file(&path).map_err(|e| format!("{}: {}", target, e))?;
+        }
+    }
+    Ok(target.clone())
+}
+
+/// Apply the unified diff at `diff` to `workspace`, returning the files it
+/// touched. Files are patched in order, so a hunk that does not apply
+/// leaves the earlier files patched; the workspace is rejected anyway.
+pub fn apply(workspace: &Path, diff: &Path) -> Result<Vec<String>, String> {
+    let text = fs::read_to_string(diff).map_err(|e| format!("{}: {}", diff.display(), e))?;
+    let files = parse(&text).map_err(|e| format!("{}: {}", diff.display(), e))?;
+    files.iter().map(|f| apply_file(workspace, f)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(str::to_string).collect()
+    }
+
+    /// `diff` (one file) applied to `source`.
+    fn patched(source: &str, diff: &str) -> Result<String, String> {
+        let files = parse(diff)?;
+        Ok(patch_lines(lines(source), &files[0].hunks, "f")?.join("\n"))
+    }
+
+    const SOURCE: &str = "a\nb\nc\nd\ne\nf\ng";
+
+    #[test]
+    fn context_hunk_replaces_a_line() {
+        let diff = "--- a/f\n+++ b/f\n@@ -2,3 +2,3 @@\n b\n-c\n+C\n d\n";
+        assert_eq!(patched(SOURCE, diff).unwrap(), "a\nb\nC\nd\ne\nf\ng");
+    }
+
+    #[test]
+    fn zero_context_insertion_goes_after_its_line() {
+        let diff = "--- a/f\n+++ b/f\n@@ -5,0 +6,2 @@\n+x\n+y\n";
+        assert_eq!(patched(SOURCE, diff).unwrap(), "a\nb\nc\nd\ne\nx\ny\nf\ng");
+        let at_top = "--- a/f\n+++ b/f\n@@ -0,0 +1 @@\n+x\n";
+        assert_eq!(patched(SOURCE, at_top).unwrap(), "x\na\nb\nc\nd\ne\nf\ng");
+    }
+
+    #[test]
+    fn zero_context_deletion_and_later_hunks_shift() {
+        let diff = "--- a/f\n+++ b/f\n@@ -2 +1,0 @@\n-b\n@@ -4,0 +4 @@\n+x\n@@ -6 +6 @@\n-f\n+F\n";
+        assert_eq!(patched(SOURCE, diff).unwrap(), "a\nc\nd\nx\ne\nF\ng");
+    }
+
+    #[test]
+    fn offset_hunk_is_found_near_its_line() {
+        // written against a file with two fewer lines at the top
+        let diff = "--- a/f\n+++ b/f\n@@ -3,3 +3,3 @@\n e\n-f\n+F\n g\n";
+        assert_eq!(patched(SOURCE, diff).unwrap(), "a\nb\nc\nd\ne\nF\ng");
+        let stale = "--- a/f\n+++ b/f\n@@ -3 +3 @@\n-z\n+Z\n";
+        assert_eq!(patched(SOURCE, stale), Err("f: hunk 1 does not apply".to_string()));
+    }
+
+    #[test]
+    fn parses_git_headers_and_new_files() {
+        let diff = "diff --git a/src/x.rs b/src/x.rs\nnew file mode 100644\n--- /dev/null\n+++ b/src/x.rs\t2024-01-01\n@@ -0,0 +1,2 @@\n+fn x() {}\n+\n\\ No newline at end of file\n";
+        let files = parse(diff).unwrap();
+        assert_eq!((files[0].old.as_deref(), files[0].new.as_deref()), (None, Some("src/x.rs")));
+        assert_eq!(patch_lines(Vec::new(), &files[0].hunks, "x").unwrap(), ["fn x() {}", ""]);
+        assert!(parse("@@ -1 +1 @@\n-a\n+b\n").is_err());
+        assert!(parse("--- a/f\n+++ b/f\n@@ -1,2 +1 @@\n-a\n").is_err());
+    }
+
+    #[test]
+    fn refuses_paths_outside_the_workspace() {
+        let ws = Path::new("/ws");
+        assert_eq!(inside(ws, "src/lib.rs"), Ok(PathBuf::from("/ws/src/lib.rs")));
+        assert!(inside(ws, "../etc/passwd").is_err());
+        assert!(inside(ws, "/etc/passwd").is_err());
+    }
+}
@@ -0,0 +1,132 @@
+//! `--crate-policy <path.toml>`: which dependencies a task's manifest may
+//! declare, by task tag. The policy lists `crate` or `crate@version`
+//! entries allowed everywhere and per tag:
+//!
+//! ```toml
+//! default = []
+//! [tags]
+//! random = ["rand@0.8"]
+//! parsing = ["nom@7", "regex"]
+//! ```
+//!
+//! A task may use the default entries plus those of every tag its own
+//! manifest lists; anything else in the extracted `Cargo.toml` rejects
+//! the workspace.
+
+use serde::Deserialize;
+use std::{collections::BTreeMap, fs, path::Path};
+
+const TABLES: [&str; 3] = ["dependencies", "dev-dependencies", "build-dependencies"];
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Policy {
+    default: Vec<String>,
+    tags: BTreeMap<String, Vec<String>>,
+}
+
+/// One allowed entry, with the policy key it came from.
+struct Rule {
+    origin: String,
+    name: String,
+    /// Leading version components a requirement must agree with.
+    version: Option<String>,
+}
+
+impl Rule {
+    fn new(origin: &str, spec: &str) -> Self {
+        let (name, version) = match spec.split_once('@') {
+            Some((name, version)) => (name, Some(version.trim().to_string())),
+            None => (spec, None),
+        };
+        Rule { origin: origin.to_string(), name: name.trim().to_string(), version }
+    }
+
+    fn spec(&self) -> String {
+        match &self.version {
+            Some(v) => format!("{}@{}", self.name, v),
+            None => self.name.clone(),
+        }
+    }
+
+    /// Whether `req` (like `0.8`, `^0.8.5` or `=0.8.1`) only picks
+    /// versions the rule allows.
+    fn admits(&self, req: Option<&str>) -> bool {
+        let Some(version) = &self.version else { return true };
+        let Some(req) = req else { return false };
+        let req = req.trim().trim_start_matches(['^', '=', '~']).trim();
+        req == version || req.strip_prefix(version.as_str()).is_some_and(|rest| rest.starts_with('.'))
+    }
+}
+
+/// The rules in force for one task.
+pub struct Allowed {
+    tags: Vec<String>,
+    rules: Vec<Rule>,
+}
+
+impl Policy {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let raw = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        toml::from_str(&raw).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// The rules for a task tagged `tags`.
+    pub fn resolve(&self, tags: &[String]) -> Allowed {
+        let mut rules: Vec<Rule> = self.default.iter().map(|s| Rule::new("default", s)).collect();
+        for tag in tags {
+            for spec in self.tags.get(tag).into_iter().flatten() {
+                rules.push(Rule::new(&format!("tags.{}", tag), spec));
+            }
+        }
+        Allowed { tags: tags.to_vec(), rules }
+    }
+}
+
+/// Crate name and version requirement of a dependency entry.
+fn requirement(key: &str, entry: &toml::Value) -> (String, Option<String>) {
+    match entry {
+        toml::Value::String(req) => (key.to_string(), Some(req.clone())),
+        toml::Value::Table(t) => (
+            t.get("package").and_then(|p| p.as_str()).unwrap_or(key).to_string(),
+            t.get("version").and_then(|v| v.as_str()).map(str::to_string),
+        ),
+        _ => (key.to_string(), None),
+    }
+}
+
+impl Allowed {
+    /// Reject the first dependency in `workspace`'s `Cargo.toml` that no
+    /// rule allows.
+    pub fn check(&self, workspace: &Path) -> Result<(), String> {
+        let path = workspace.join("Cargo.toml");
+        let raw = fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let manifest: toml::Table = raw.parse().map_err(|e| format!("{}: {}", path.display(), e))?;
+        for table in TABLES {
+            let Some(deps) = manifest.get(table).and_then(|t| t.as_table()) else { continue };
+            for (key, entry) in deps {
+                let (name, req) = requirement(key, entry);
+                let same_name: Vec<&Rule> = self.rules.iter().filter(|r| r.name == name).collect();
+                if same_name.iter().any(|r| r.admits(req.as_deref())) {
+                    continue;
+                }
+                let declared = format!("{}.{} = {}", table, key, entry);
+                return Err(match same_name.first() {
+                    Some(rule) => format!(
+                        "`{}` is outside the crate policy: `{}` allows {}",
+                        declared,
+                        rule.origin,
+                        same_name.iter().map(|r| r.spec()).collect::<Vec<_>>().join(", ")
+                    ),
+                    None => format!(
+                        "`{}` is outside the crate policy: no rule for tags [{}] allows `{}`",
+                        declared,
+                        self.tags.join(", "),
+                        name
+                    ),
+                });
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,218 @@
+//! Static checks on the extracted test suite, reported in the quality
+//! section after the runs.
+
+use crate::colors::*;
+use crate::manifest::QualityPolicy;
+use crate::source::{
+    functions, imports_from, pub_items, strip_comments_and_strings, words, FnItem,
+};
+use crate::CRATE_NAME;
+use std::collections::{HashMap, HashSet};
+
+#[derive(Default)]
+pub struct Report {
+    /// Tests that never reach the crate under test, directly or through a
+    /// helper; they pass whatever the solution does.
+    pub unreferenced: Vec<String>,
+    /// Tests that check nothing: no assertion or panic, no `?` in a test
+    /// returning `Result`, and no call to a helper that checks.
+    pub vacuous: Vec<String>,
+    /// Every test with the categories it is tagged with.
+    pub tests: Vec<(String, Vec<String>)>,
+    /// Public items of the crate that no test (or helper a test calls)
+    /// mentions.
+    pub untested_api: Vec<String>,
+}
+
+/// Values of `// <key>: a, b` comments directly above `fn name` (among
+/// its attributes, other comments and blank lines) in the unstripped
+/// source, like the categories of `// category: …`.
+pub fn comment_tags(raw: &str, name: &str, key: &str) -> Vec<String> {
+    let lines: Vec<&str> = raw.lines().collect();
+    let declares = |l: &str| {
+        l.split("fn ").skip(1).any(|rest| {
+            rest.trim_start().strip_prefix(name).is_some_and(|after| {
+                !after.starts_with(|c: char| c.is_alphanumeric() || c == '_')
+            })
+        })
+    };
+    let Some(at) = lines.iter().position(|l| declares(l)) else { return Vec::new() };
+    lines[..at]
+        .iter()
+        .rev()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty())
+        // an attribute on the line of another `fn` belongs to that one
+        .take_while(|l| (l.starts_with("#[") && !l.contains("fn ")) || l.starts_with("//"))
+        .filter_map(|l| l.trim_start_matches('/').trim().strip_prefix(key)?.strip_prefix(':'))
+        .flat_map(|list| list.split([',', ' ']).filter(|c| !c.is_empty()).map(str::to_string))
+        .collect()
+}
+
+/// Helper functions with `property`, directly or by calling another helper
+/// that has it.
+fn helpers_with(
+    fns: &[FnItem],
+    property: impl Fn(&FnItem, &HashSet<&str>) -> bool,
+) -> HashSet<&str> {
+    let mut found = HashSet::new();
+    loop {
+        let before = found.len();
+        for f in fns.iter().filter(|f| !f.is_test()) {
+            if property(f, &found) {
+                found.insert(f.name.as_str());
+            }
+        }
+        if found.len() == before {
+            return found;
+        }
+    }
+}
+
+/// Whether `f` asserts or panics itself: `assert*!`, `panic!`,
+/// `unreachable!`, or `?` when it returns `Result`.
+fn checks_directly(f: &FnItem) -> bool {
+    let is_check = |name: &str| {
+        name.starts_with("assert")
+            || name.starts_with("debug_assert")
+            || matches!(name, "panic" | "unreachable")
+    };
+    let macro_call = f.body.match_indices('!').any(|(i, _)| {
+        let name = words(&f.body[..i]).last().unwrap_or("");
+        f.body[..i].ends_with(name)
+            && is_check(name)
+            && f.body[i + 1..].trim_start().starts_with(['(', '[', '{'])
+    });
+    macro_call || (f.returns_result() && f.body.contains('?'))
+}
+
+impl Report {
+    /// Check `raw` (the `# test` section) against `lib` (the `# lib`
+    /// section).
+    pub fn new(raw: &str, lib: &str) -> Self {
+        let code = strip_comments_and_strings(raw);
+        let api = pub_items(&strip_comments_and_strings(lib));
+        let (mut imported, glob) = imports_from(&code, CRATE_NAME);
+        if glob {
+            imported.extend(api.iter().map(|(_, name)| name.clone()));
+        }
+        let fns = functions(&code);
+        let tests = || fns.iter().filter(|f| f.is_test());
+
+        let uses_crate = |f: &FnItem, helpers: &HashSet<&str>| {
+            words(&f.body).any(|w| {
+                w == CRATE_NAME || imported.iter().any(|i| i == w) || helpers.contains(w)
+            })
+        };
+        let reaching = helpers_with(&fns, uses_crate);
+        let unreferenced =
+            tests().filter(|f| !uses_crate(f, &reaching)).map(|f| f.name.clone()).collect();
+
+        let checks = |f: &FnItem, helpers: &HashSet<&str>| {
+            checks_directly(f) || words(&f.body).any(|w| helpers.contains(w))
+        };
+        let checking = helpers_with(&fns, checks);
+        let vacuous = tests()
+            .filter(|f| !f.attrs.contains("should_panic") && !checks(f, &checking))
+            .map(|f| f.name.clone())
+            .collect();
+        // every word in the tests and the helpers they reach
+        let mut mentioned: HashSet<&str> = tests().flat_map(|f| words(&f.body)).collect();
+        loop {
+            let before = mentioned.len();
+            let called: Vec<&FnItem> = fns
+                .iter()
+                .filter(|f| !f.is_test() && mentioned.contains(f.name.as_str()))
+                .collect();
+            mentioned.extend(called.iter().flat_map(|f| words(&f.body)));
+            if mentioned.len() == before {
+                break;
+            }
+        }
+        let untested_api = api
+            .iter()
+            .filter(|(kind, name)| {
+                kind != "mod" && kind != "use" && !mentioned.contains(name.as_str())
+            })
+            .map(|(kind, name)| format!("{} {}", kind, name))
+            .collect();
+
+        let tests = tests()
+            .map(|f| (f.name.clone(), comment_tags(raw, &f.name, "category")))
+            .collect();
+        Report { unreferenced, vacuous, tests, untested_api }
+    }
+
+    /// Print the quality section; returns whether the suite meets `policy`.
+    /// `matrix` holds every test's results over `runs` runs; when
+    /// `sharded`, tests of other shards are missing from it.
+    pub fn print(
+        &self,
+        policy: &QualityPolicy,
+        matrix: &HashMap<String, Vec<bool>>,
+        runs: usize,
+        sharded: bool,
+    ) -> bool {
+        let mut ok = true;
+        println!("\n{}Quality:{}", BOLD, RESET);
+        let few = self.tests.len() < policy.min_tests;
+        ok &= !few;
+        match policy.min_tests {
+            0 => println!("  {} tests", self.tests.len()),
+            min => println!("  {}{} tests{} ({} required)",
+                            if few { RED } else { "" }, self.tests.len(), RESET, min),
+        }
+        for (category, &needed) in &policy.categories {
+            let count = self
+                .tests
+                .iter()
+                .filter(|(name, tags)| name.starts_with(category.as_str()) || tags.contains(category))
+                .count();
+            ok &= count >= needed;
+            println!("  {}{}: {} of {} required{}",
+                     if count < needed { RED } else { "" }, category, count, needed, RESET);
+        }
+        for name in &policy.required_tests {
+            // libtest names tests in modules `module::name`
+            let results = matrix
+                .iter()
+                .find(|(test, _)| test.rsplit("::").next() == Some(name.as_str()))
+                .map(|(_, results)| results.len());
+            let problem = if !self.tests.iter().any(|(test, _)| test == name) {
+                Some("missing required test".to_string())
+            } else {
+                match results {
+                    None if sharded => None,
+                    n if n.unwrap_or(0) < runs => Some(format!(
+                        "required test ran in {} of {} runs", n.unwrap_or(0), runs)),
+                    _ => None,
+                }
+            };
+            if let Some(problem) = problem {
+                ok = false;
+                println!("  {}{}:{} `{}`", RED, problem, RESET, name);
+            }
+        }
+        if self.unreferenced.is_empty() {
+            println!("  every test uses {}", CRATE_NAME);
+        } else {
+            println!("  {}tests that never use {}:{} {}",
+                     BLUE, CRATE_NAME, RESET, self.unreferenced.join(", "));
+        }
+        if self.untested_api.is_empty() {
+            println!("  every public item is used by a test");
+        } else {
+            println!("  {}untested API:{} {}", BLUE, RESET, self.untested_api.join(", "));
+        }
+        let too_vacuous = self.vacuous.len() > policy.max_vacuous;
+        ok &= !too_vacuous;
+        if self.vacuous.is_empty() {
+            println!("  every test checks something");
+        } else {
+            println!("  {}vacuous tests ({} allowed):{} {}",
+                     if too_vacuous { RED } else { BLUE }, policy.max_vacuous, RESET,
+                     self.vacuous.join(", "));
+        }
+        ok
+    }
+}
@@ -0,0 +1,159 @@
+//! Cleanup after validators that died without cleaning up themselves
+//! (OOM-killed CI jobs, SIGKILLed runners). Every cargo process group a
+//! validator spawns gets a pid file under `tasks/.pids` naming its owner
+//! and its leader; a group whose owner is gone but whose leader still
+//! runs is an orphan and is killed. Workspaces a validator extracted
+//! (they carry [`MARKER`]) that nobody holds the lock of and nobody
+//! touched for a while are removed. `validator reap` does this on demand;
+//! each validation reaps the orphans on startup, and the workspaces too
+//! with `--reap-after`.
+
+use crate::colors::*;
+use crate::lock;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+/// Where validators keep their workspaces.
+pub const ROOT: &str = "tasks";
+/// Pid files of the running process groups, one per group, named after it.
+const PID_DIR: &str = "tasks/.pids";
+/// File a validator leaves in every workspace it extracts; directories
+/// without it (checked-in tasks, anything else under [`ROOT`]) are never
+/// removed.
+pub const MARKER: &str = ".validator-workspace";
+
+/// Start time of live process `pid` in clock ticks since boot, which
+/// tells it from a later one that reused its pid. Zombies count as dead.
+fn start_time(pid: u32) -> Option<u64> {
+    let stat = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    // fields after the parenthesized command name start at field 3 (state)
+    let fields: Vec<&str> = stat.rsplit_once(')')?.1.split_whitespace().collect();
+    if fields.first() == Some(&"Z") {
+        return None;
+    }
+    fields.get(19)?.parse().ok()
+}
+
+/// Record that this process owns process group `group`, with the start
+/// times of both so neither can be mistaken for a later process that
+/// reused the pid.
+pub fn track(group: u32) {
+    let me = std::process::id();
+    let (Some(started), Some(leader)) = (start_time(me), start_time(group)) else { return };
+    let _ = fs::create_dir_all(PID_DIR);
+    let _ = fs::write(Path::new(PID_DIR).join(group.to_string()), format!("{} {} {}\n", me, started, leader));
+}
+
+/// Mark `workspace` as extracted by a validator, so a sweep may remove it.
+pub fn mark(workspace: &Path) {
+    let _ = fs::write(workspace.join(MARKER), "extracted by validator; `validator reap` may remove this directory\n");
+}
+
+/// Forget process group `group`, which has exited or been killed.
+pub fn untrack(group: u32) {
+    let _ = fs::remove_file(Path::new(PID_DIR).join(group.to_string()));
+}
+
+/// Total size of the files under `path`, in bytes.
+fn size(path: &Path) -> u64 {
+    match fs::symlink_metadata(path) {
+        Ok(meta) if meta.is_dir() => {
+            fs::read_dir(path).into_iter().flatten().flatten().map(|e| size(&e.path())).sum()
+        }
+        Ok(meta) => meta.len(),
+        Err(_) => 0,
+    }
+}
+
+/// Kill the process groups whose owner is gone, returning how many were
+/// still running. A group is only killed while its leader is the process
+/// recorded: after a reboot or pid reuse the number names someone else's.
+fn reap_processes() -> usize {
+    let mut killed = 0;
+    for entry in fs::read_dir(PID_DIR).into_iter().flatten().flatten() {
+        let Some(group) = entry.file_name().to_str().and_then(|n| n.parse::<u32>().ok()) else { continue };
+        let owner = fs::read_to_string(entry.path()).unwrap_or_default();
+        let mut fields = owner.split_whitespace().map(|f| f.parse::<u64>().ok());
+        let (Some(Some(pid)), Some(Some(started))) = (fields.next(), fields.next()) else { continue };
+        if start_time(pid as u32) == Some(started) {
+            continue; // its validator is still running
+        }
+        let leader = fields.next().flatten();
+        if leader.is_none() || start_time(group) != leader {
+            let _ = fs::remove_file(entry.path());
+            continue; // the group is gone, or the number now names another
+        }
+        // SAFETY: kill(2) has no memory-safety preconditions
+        if unsafe { libc::kill(-(group as i32), libc::SIGKILL) } == 0 {
+            println!("{}Reaped{} process group {} left by validator {}", BLUE, RESET, group, pid);
+            killed += 1;
+        }
+        let _ = fs::remove_file(entry.path());
+    }
+    killed
+}
+
+/// When `dir` (or its lock file) was last touched.
+fn last_used(dir: &Path) -> Option<SystemTime> {
+    let lock = lock::path_of(dir);
+    [dir, lock.as_path()].iter().filter_map(|p| fs::metadata(p).and_then(|m| m.modified()).ok()).max()
+}
+
+/// Remove the marked workspaces unused for `max_age`, returning how many
+/// were removed and the bytes freed.
+fn reap_workspaces(max_age: Duration) -> (usize, u64) {
+    let mut dirs: Vec<PathBuf> = fs::read_dir(ROOT)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.is_dir() && p.join(MARKER).is_file())
+        .collect();
+    dirs.sort();
+    let (mut removed, mut freed) = (0, 0);
+    for dir in dirs {
+        let idle = last_used(&dir).and_then(|t| t.elapsed().ok()).unwrap_or_default();
+        if idle < max_age {
+            continue;
+        }
+        // holding the lock keeps a validator from starting on it meanwhile
+        let Ok(Some(_lock)) = lock::try_acquire(&dir) else { continue };
+        let bytes = size(&dir);
+        match fs::remove_dir_all(&dir) {
+            Ok(()) => {
+                println!(
+                    "{}Removed{} {} (unused for {}h, {} MiB)",
+                    BLUE,
+                    RESET,
+                    dir.display(),
+                    idle.as_secs() / 3600,
+                    bytes / (1024 * 1024)
+                );
+                removed += 1;
+                freed += bytes;
+            }
+            Err(e) => eprintln!("{}Reap error:{} {}: {}", RED, RESET, dir.display(), e),
+        }
+    }
+    (removed, freed)
+}
+
+/// Kill orphaned process groups and remove workspaces unused for
+/// `max_age` (none if `None`), printing what was reclaimed.
+pub fn sweep(max_age: Option<Duration>) {
+    let killed = reap_processes();
+    let (removed, freed) = max_age.map_or((0, 0), reap_workspaces);
+    if killed > 0 || removed > 0 {
+        println!(
+            "{}Reclaimed {} orphaned process group(s) and {} stale workspace(s), {} MiB{}",
+            GREEN,
+            killed,
+            removed,
+            freed / (1024 * 1024),
+            RESET
+        );
+    }
+}
@@ -0,0 +1,158 @@
+//! `--redact`: scrub infrastructure details (absolute paths, the user and
+//! host names, environment values) from everything the validator writes
+//! for others to read: uploaded reports, digests and shard reports,
+//! including the compiler and test output captured in them.
+//!
+//! `--redact-rules <file.toml>` turns individual rules off and adds
+//! literal replacements:
+//!
+//! ```toml
+//! env = false
+//! [[replace]]
+//! find = "grader-07.internal"
+//! with = "<grader>"
+//! ```
+
+use serde::Deserialize;
+use std::{fs, path::Path};
+
+/// Environment values shorter than this stay, or every `1` would go.
+const MIN_ENV_VALUE: usize = 4;
+
+#[derive(Deserialize)]
+#[serde(default)]
+struct Rules {
+    paths: bool,
+    users: bool,
+    hosts: bool,
+    env: bool,
+    replace: Vec<Replace>,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules { paths: true, users: true, hosts: true, env: true, replace: Vec::new() }
+    }
+}
+
+#[derive(Deserialize)]
+struct Replace {
+    find: String,
+    with: String,
+}
+
+/// Literal replacements, applied longest first so a path is replaced
+/// before the user name inside it.
+#[derive(Default)]
+pub struct Redactor {
+    rules: Vec<(String, String)>,
+}
+
+fn hostname() -> Option<String> {
+    let mut buf = [0u8; 256];
+    // SAFETY: the buffer is valid for `buf.len()` bytes
+    let ok = unsafe { libc::gethostname(buf.as_mut_ptr().cast(), buf.len()) } == 0;
+    let end = buf.iter().position(|&b| b == 0)?;
+    ok.then(|| String::from_utf8_lossy(&buf[..end]).into_owned())
+}
+
+impl Redactor {
+    /// The default rules, adjusted by the file at `rules` if given, for a
+    /// validation of `workspace` with the child environment `env`.
+    pub fn new(rules: Option<&Path>, workspace: &Path, env: &[(String, String)]) -> Result<Self, String> {
+        let rules: Rules = match rules {
+            Some(path) => {
+                let raw = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+                toml::from_str(&raw).map_err(|e| format!("{}: {}", path.display(), e))?
+            }
+            None => Rules::default(),
+        };
+        let mut found: Vec<(String, String)> =
+            rules.replace.into_iter().map(|r| (r.find, r.with)).collect();
+        if rules.env {
+            found.extend(
+                env.iter()
+                    .filter(|(_, v)| v.len() >= MIN_ENV_VALUE)
+                    .map(|(k, v)| (v.clone(), format!("<env:{}>", k))),
+            );
+        }
+        if rules.paths {
+            let workspace = fs::canonicalize(workspace).unwrap_or_else(|_| workspace.to_path_buf());
+            found.push((workspace.display().to_string(), "<workspace>".into()));
+            if let Ok(cwd) = std::env::current_dir() {
+                found.push((cwd.display().to_string(), "<cwd>".into()));
+            }
+            if let Some(home) = std::env::var_os("HOME").filter(|h| h.len() > 1) {
+                found.push((home.to_string_lossy().into_owned(), "<home>".into()));
+            }
+        }
+        if rules.users {
+            if let Ok(user) = std::env::var("USER") {
+                found.push((user, "<user>".into()));
+            }
+        }
+        if rules.hosts {
+            if let Some(host) = hostname() {
+                found.push((host, "<host>".into()));
+            }
+        }
+        found.retain(|(find, _)| !find.is_empty());
+        found.sort_by_key(|(find, _)| std::cmp::Reverse(find.len()));
+        Ok(Redactor { rules: found })
+    }
+
+    pub fn apply(&self, text: &str) -> String {
+        self.rules.iter().fold(text.to_string(), |text, (find, with)| text.replace(find, with))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A redactor with the rules file `rules`.
+    fn redactor(rules: &str, env: &[(String, String)]) -> Redactor {
+        let path = std::env::temp_dir().join(format!("redact-rules-{}-{}.toml", std::process::id(), rules.len()));
+        fs::write(&path, rules).unwrap();
+        let redactor = Redactor::new(Some(&path), Path::new("/nonexistent/ws"), env);
+        let _ = fs::remove_file(&path);
+        redactor.unwrap()
+    }
+
+    const ONLY_LISTED: &str = "paths = false\nusers = false\nhosts = false\nenv = false\n";
+
+    #[test]
+    fn replacements_apply_longest_first() {
+        let rules = format!(
+            "{}[[replace]]\nfind = \"grader\"\nwith = \"<g>\"\n[[replace]]\nfind = \"grader-07.internal\"\nwith = \"<host>\"\n",
+            ONLY_LISTED
+        );
+        let r = redactor(&rules, &[]);
+        assert_eq!(r.apply("on grader-07.internal, a grader"), "on <host>, a <g>");
+    }
+
+    #[test]
+    fn env_values_are_named_unless_short() {
+        let env = [("TOKEN".to_string(), "s3cr3t".to_string()), ("N".to_string(), "1".to_string())];
+        let r = redactor("paths = false\nusers = false\nhosts = false\n", &env);
+        assert_eq!(r.apply("token s3cr3t, 1 run"), "token <env:TOKEN>, 1 run");
+        let off = redactor(ONLY_LISTED, &env);
+        assert_eq!(off.apply("token s3cr3t"), "token s3cr3t");
+    }
+
+    #[test]
+    fn workspace_paths_are_replaced() {
+        let r = redactor("users = false\nhosts = false\nenv = false\n", &[]);
+        assert_eq!(r.apply("error at /nonexistent/ws/src/lib.rs:3"), "error at <workspace>/src/lib.rs:3");
+    }
+
+    #[test]
+    fn bad_rules_files_are_errors() {
+        let path = std::env::temp_dir().join(format!("redact-rules-{}-bad.toml", std::process::id()));
+        fs::write(&path, "paths = \"yes\"\n").unwrap();
+        let result = Redactor::new(Some(&path), Path::new("."), &[]);
+        let _ = fs::remove_file(&path);
+        assert!(result.is_err());
+        assert!(Redactor::new(Some(Path::new("/nonexistent/rules.toml")), Path::new("."), &[]).is_err());
+    }
+}
@@ -0,0 +1,134 @@
+//! Rendering of the consistency table and totals through minijinja
+//! templates. The built-in template lives in `templates/console.txt`;
+//! `--template-dir` may hold a `console.txt` that replaces it, to change
+//! wording or drop columns without forking the validator.
+
+use crate::colors::*;
+use crate::matrix::{Classification, RunMatrix};
+use minijinja::{context, Environment};
+use serde::Serialize;
+use std::{fs, path::Path, time::Duration};
+
+const CONSOLE: &str = include_str!("../templates/console.txt");
+
+#[derive(Serialize)]
+pub struct Row {
+    pub test: String,
+    /// "Consistent pass", "Consistent fail", "Flaky" or "Unsound".
+    pub label: &'static str,
+    /// Key into the template's `colors` map.
+    pub color: &'static str,
+    pub pass_pct: u32,
+    pub fail_pct: u32,
+    /// Over the runs that timed the test.
+    pub time: Option<Timing>,
+    /// Its outcome flipped in the notebook's recent `--history`.
+    pub historically_flaky: bool,
+}
+
+/// Shortest, mean and longest duration of a test, like `0.012s`.
+#[derive(Serialize)]
+pub struct Timing {
+    pub min: String,
+    pub mean: String,
+    pub max: String,
+}
+
+impl Timing {
+    pub fn of(times: &[Duration]) -> Option<Self> {
+        let min = times.iter().min()?;
+        let max = times.iter().max()?;
+        let mean = times.iter().sum::<Duration>() / times.len() as u32;
+        let secs = |d: &Duration| format!("{:.3}s", d.as_secs_f64());
+        Some(Timing { min: secs(min), mean: secs(&mean), max: secs(max) })
+    }
+}
+
+#[derive(Serialize)]
+pub struct Totals {
+    pub pass: usize,
+    pub fail: usize,
+    pub flaky: usize,
+    pub unsound: usize,
+}
+
+impl Totals {
+    /// Count one test classified `classification`.
+    pub fn count(&mut self, classification: Classification) {
+        match classification {
+            Classification::Pass => self.pass += 1,
+            Classification::Fail => self.fail += 1,
+            Classification::Flaky => self.flaky += 1,
+            Classification::Unsound => self.unsound += 1,
+        }
+    }
+}
+
+/// Table rows and totals for `matrix`.
+pub fn rows(matrix: &RunMatrix) -> (Vec<Row>, Totals) {
+    let mut rows = Vec::new();
+    for (test, classification) in matrix.classified() {
+        let runs = matrix.results(test).unwrap_or_default();
+        let pass_count = runs.iter().filter(|&&b| b).count() as f32;
+        let total = runs.len() as f32;
+        let pass_pct = 100.0 * pass_count / total;
+        let fail_pct = 100.0 - pass_pct;
+
+        let (label, color) = match classification {
+            Classification::Pass => ("Consistent pass", "green"),
+            Classification::Fail => ("Consistent fail", "red"),
+            Classification::Flaky => ("Flaky", "blue"),
+            Classification::Unsound => ("Unsound", "red"),
+        };
+
+        rows.push(Row {
+            test: test.to_string(),
+            label,
+            color,
+            pass_pct: pass_pct.round() as u32,
+            fail_pct: fail_pct.round() as u32,
+            time: matrix.times(test).and_then(Timing::of),
+            historically_flaky: false,
+        });
+    }
+    (rows, matrix.totals())
+}
+
+/// `value` as text, padded on the right (`ljust`) or left (`rjust`) to
+/// `width` characters with `fill` (a space by default).
+fn pad(value: &minijinja::Value, width: usize, fill: Option<&str>, left: bool) -> String {
+    let text = value.to_string();
+    let fill = fill.unwrap_or(" ");
+    let padding = fill.repeat(width.saturating_sub(text.chars().count()));
+    if left {
+        padding + &text
+    } else {
+        text + &padding
+    }
+}
+
+/// The console report for `rows` and `totals`, from `template_dir`'s
+/// `console.txt` when there is one.
+pub fn render(template_dir: Option<&Path>, rows: &[Row], totals: &Totals) -> Result<String, String> {
+    let source = match template_dir.map(|d| d.join("console.txt")).filter(|p| p.exists()) {
+        Some(path) => fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?,
+        None => CONSOLE.to_string(),
+    };
+    let mut env = Environment::new();
+    env.set_trim_blocks(true);
+    env.set_keep_trailing_newline(true);
+    env.add_filter("ljust", |v: minijinja::Value, w: usize, fill: Option<String>| {
+        pad(&v, w, fill.as_deref(), false)
+    });
+    env.add_filter("rjust", |v: minijinja::Value, w: usize, fill: Option<String>| {
+        pad(&v, w, fill.as_deref(), true)
+    });
+    env.add_template_owned("console.txt", source).map_err(|e| e.to_string())?;
+    let colors = context! { reset => RESET, red => RED, green => GREEN, blue => BLUE, bold => BOLD };
+    // the timing columns, when any test was timed
+    let timed = rows.iter().any(|r| r.time.is_some());
+    let historical = rows.iter().filter(|r| r.historically_flaky).count();
+    env.get_template("console.txt")
+        .and_then(|t| t.render(context! { rows, totals, colors, timed, historical }))
+        .map_err(|e| format!("template: {}", e))
+}
@@ -0,0 +1,205 @@
+//! `--sample-interval`: while `cargo test` runs, read `/proc` for every
+//! process in its group (cargo and the test binaries it starts) and
+//! record their combined CPU use, RSS and thread count. The series show
+//! whether a run that nears its timeout is busy or stuck: a deadlock sits
+//! at 0% CPU with a flat RSS, a slow test keeps a core busy.
+
+use minijinja::{context, Environment};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+const HTML: &str = include_str!("../templates/resources.html");
+
+/// Most points a sparkline shows; longer series keep each bucket's peak.
+const SPARK_WIDTH: usize = 60;
+
+/// The process group's usage at one moment.
+#[derive(Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Sample {
+    /// Milliseconds since the run started.
+    pub t_ms: u64,
+    /// CPU time used since the previous sample, in percent of one core.
+    pub cpu_pct: f32,
+    pub rss_kib: u64,
+    pub threads: u64,
+}
+
+/// Samples of one run, numbered like the `Run k/n` lines.
+#[derive(Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Series {
+    pub run: usize,
+    pub samples: Vec<Sample>,
+}
+
+/// Cumulative CPU ticks, RSS pages and threads of `pid`, if it is in
+/// process group `pgid`.
+fn stat(pid: &str, pgid: u32) -> Option<(u64, u64, u64)> {
+    let raw = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    // the command name may contain spaces; fields resume after its `)`
+    let fields: Vec<&str> = raw[raw.rfind(')')? + 2..].split(' ').collect();
+    let field = |n: usize| fields.get(n - 3).and_then(|f| f.parse::<u64>().ok());
+    if field(5)? != u64::from(pgid) {
+        return None;
+    }
+    Some((field(14)? + field(15)?, field(24)?, field(20)?))
+}
+
+/// Background thread sampling a process group until [`Sampler::finish`].
+pub struct Sampler {
+    stop: Arc<AtomicBool>,
+    worker: thread::JoinHandle<Vec<Sample>>,
+}
+
+impl Sampler {
+    pub fn start(pgid: u32, interval: Duration) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&stop);
+        let worker = thread::spawn(move || {
+            // SAFETY: sysconf has no memory-safety preconditions
+            let (ticks_per_sec, page) =
+                unsafe { (libc::sysconf(libc::_SC_CLK_TCK), libc::sysconf(libc::_SC_PAGESIZE)) };
+            let (ticks_per_sec, page_kib) = (ticks_per_sec.max(1) as f64, (page.max(1024) / 1024) as u64);
+            let t0 = Instant::now();
+            let mut last = Instant::now();
+            let mut before: HashMap<String, u64> = HashMap::new();
+            let mut samples = Vec::new();
+            while !flag.load(Ordering::Relaxed) {
+                thread::sleep(interval);
+                let Ok(dir) = fs::read_dir("/proc") else { break };
+                let mut now = HashMap::new();
+                let (mut used, mut rss, mut threads) = (0, 0, 0);
+                for entry in dir.flatten() {
+                    let pid = entry.file_name().to_string_lossy().into_owned();
+                    if let Some((ticks, pages, n)) = stat(&pid, pgid) {
+                        // a process new since the last sample used all its ticks since
+                        used += ticks - before.get(&pid).copied().unwrap_or(0).min(ticks);
+                        rss += pages * page_kib;
+                        threads += n;
+                        now.insert(pid, ticks);
+                    }
+                }
+                let elapsed = last.elapsed().as_secs_f64().max(1e-3);
+                last = Instant::now();
+                before = now;
+                samples.push(Sample {
+                    t_ms: t0.elapsed().as_millis() as u64,
+                    cpu_pct: (100.0 * used as f64 / ticks_per_sec / elapsed) as f32,
+                    rss_kib: rss,
+                    threads,
+                });
+            }
+            samples
+        });
+        Sampler { stop, worker }
+    }
+
+    pub fn finish(self) -> Vec<Sample> {
+        self.stop.store(true, Ordering::Relaxed);
+        self.worker.join().unwrap_or_default()
+    }
+}
+
+/// `values` cut down to at most `width` points, keeping each bucket's
+/// largest so short spikes survive.
+fn buckets(values: &[f64], width: usize) -> Vec<f64> {
+    let size = values.len().div_ceil(width).max(1);
+    values.chunks(size).map(|c| c.iter().copied().fold(0.0, f64::max)).collect()
+}
+
+/// `values` as block characters, scaled to their maximum.
+pub fn sparkline(values: &[f64]) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let values = buckets(values, SPARK_WIDTH);
+    let max = values.iter().copied().fold(0.0, f64::max);
+    values
+        .iter()
+        .map(|&v| if max > 0.0 { BARS[((v / max) * 7.0).round() as usize] } else { BARS[0] })
+        .collect()
+}
+
+/// CPU%, RSS in MiB and threads of `samples`, as separate series.
+fn columns(samples: &[Sample]) -> [Vec<f64>; 3] {
+    [
+        samples.iter().map(|s| s.cpu_pct as f64).collect(),
+        samples.iter().map(|s| s.rss_kib as f64 / 1024.0).collect(),
+        samples.iter().map(|s| s.threads as f64).collect(),
+    ]
+}
+
+/// Text block with a sparkline and peak per metric for every run.
+pub fn summary(series: &[Series]) -> String {
+    let mut out = String::from("Resource usage:\n");
+    for s in series {
+        if s.samples.is_empty() {
+            out += &format!("  run {}: finished before the first sample\n", s.run);
+            continue;
+        }
+        let [cpu, rss, threads] = columns(&s.samples);
+        let peak = |v: &[f64]| v.iter().copied().fold(0.0, f64::max);
+        out += &format!("  run {} ({} samples)\n", s.run, s.samples.len());
+        out += &format!("    cpu     {} peak {:.0}%\n", sparkline(&cpu), peak(&cpu));
+        out += &format!("    rss     {} peak {:.1} MiB\n", sparkline(&rss), peak(&rss));
+        out += &format!("    threads {} peak {}\n", sparkline(&threads), peak(&threads));
+    }
+    out
+}
+
+/// SVG polyline points for `values` in a `width`×`height` box whose top
+/// is `max`, or the largest value without one.
+pub(crate) fn points(values: &[f64], max: Option<f64>, width: f64, height: f64) -> String {
+    let max = max.unwrap_or_else(|| values.iter().copied().fold(0.0, f64::max)).max(f64::MIN_POSITIVE);
+    let step = width / (values.len().max(2) - 1) as f64;
+    values
+        .iter()
+        .enumerate()
+        .map(|(i, v)| format!("{:.1},{:.1}", i as f64 * step, height - v / max * height))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Write the series as an HTML page of sparklines to `path`.
+pub fn write_html(path: &Path, notebook: &str, series: &[Series]) -> Result<(), String> {
+    #[derive(Serialize)]
+    struct Chart {
+        label: &'static str,
+        points: String,
+        peak: String,
+    }
+    #[derive(Serialize)]
+    struct Run {
+        run: usize,
+        samples: usize,
+        charts: Vec<Chart>,
+    }
+    let runs: Vec<Run> = series
+        .iter()
+        .map(|s| {
+            let [cpu, rss, threads] = columns(&s.samples);
+            let peak = |v: &[f64]| v.iter().copied().fold(0.0, f64::max);
+            let charts = vec![
+                Chart { label: "CPU", points: points(&cpu, None, 300.0, 40.0), peak: format!("{:.0}%", peak(&cpu)) },
+                Chart { label: "RSS", points: points(&rss, None, 300.0, 40.0), peak: format!("{:.1} MiB", peak(&rss)) },
+                Chart { label: "Threads", points: points(&threads, None, 300.0, 40.0), peak: format!("{}", peak(&threads)) },
+            ];
+            Run { run: s.run, samples: s.samples.len(), charts }
+        })
+        .collect();
+    let mut env = Environment::new();
+    env.add_template("resources.html", HTML).map_err(|e| e.to_string())?;
+    let html = env
+        .get_template("resources.html")
+        .and_then(|t| t.render(context! { notebook, runs }))
+        .map_err(|e| format!("template: {}", e))?;
+    fs::write(path, html).map_err(|e| format!("{}: {}", path.display(), e))
+}
@@ -0,0 +1,295 @@
+//! The runs of the `test` stage: the suite `--runs` times (for each
+//! `--seed-matrix` seed), live or over `--jobs` workers that each build
+//! into a target directory of their own, merged into the results in run
+//! order.
+
+use crate::colors::*;
+use crate::stages::{write_resource_html, Context};
+use crate::{
+    build_tests, crash, digest, expected, interrupt, resources, run_cargo_test_capture, sandbox, CpuStress,
+    RunError, RunMatrix, RunOptions, RunOutput, TEST_SEED_VAR,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    path::Path,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// What the runs found.
+#[derive(Default)]
+pub struct Runs {
+    pub matrix: RunMatrix,
+    /// (run, seed, failed tests) for every shuffled run that had failures.
+    pub shuffled_failures: Vec<(usize, u64, Vec<String>)>,
+    /// Failed tests per `--seed-matrix` seed.
+    pub seed_failures: BTreeMap<u64, BTreeSet<String>>,
+    /// Last captured output of every failing test, for `--digest`.
+    pub failure_outputs: HashMap<String, String>,
+    /// Per retried run: the tests only the longer timeout let finish.
+    pub marginal: Vec<(usize, Duration, Vec<String>)>,
+    /// First crash of each test that killed its test binary.
+    pub crashes: Vec<crash::Crash>,
+    /// First sandbox limit each test ran into, reported apart from failures.
+    pub violations: Vec<(String, sandbox::Violation)>,
+    /// Resource usage per run, with `--sample-interval`.
+    pub usage: Vec<resources::Series>,
+    /// Wall time of each completed run.
+    pub times: Vec<Duration>,
+    /// Runs merged so far.
+    pub completed: usize,
+    /// Runs planned.
+    pub total: usize,
+}
+
+/// One run of the suite, not yet merged into the results.
+struct Attempt {
+    label: String,
+    seed: Option<u64>,
+    test_seed: Option<u64>,
+    outcome: Result<RunOutput, RunError>,
+    took: Duration,
+    /// After a `--timeout-retry`: how long the retry took and the tests
+    /// only it let finish.
+    slow_tests: Option<(Duration, Vec<String>)>,
+    /// The end-to-end cases, when the tests ran.
+    cases: Option<Result<Vec<expected::Outcome>, String>>,
+    /// What the run printed, when it ran alongside others.
+    log: String,
+}
+
+/// Run the suite as `cx.flags` asks into `cx.runs`, with the end-to-end
+/// `cases` (run by `case_exe`) as tests of the matrix. A run that gives
+/// no results is an error, leaving the runs before it in `cx.runs`.
+pub fn execute(cx: &mut Context, cases: &[expected::Case], case_exe: Option<&Path>) -> Result<(), String> {
+    let flags = cx.flags;
+    let mut runs = Runs { total: flags.runs * flags.seed_matrix.unwrap_or(1).max(1) as usize, ..Runs::default() };
+    let result = run_all(cx, &mut runs, cases, case_exe);
+    cx.runs = runs;
+    result
+}
+
+fn run_all(cx: &Context, runs: &mut Runs, cases: &[expected::Case], case_exe: Option<&Path>) -> Result<(), String> {
+    let (flags, workspace) = (cx.flags, cx.workspace);
+    let base = cx.opts();
+    let total_runs = runs.total;
+
+    // Keep the CPU contended for the whole run loop when requested
+    let _stress = flags.stress_cpu.filter(|&n| n > 0).map(|n| {
+        println!("{}Stressing CPU with {} busy thread(s){}", BLUE, n, RESET);
+        CpuStress::start(n)
+    });
+
+    // One run of the suite; `live` prints as it goes, otherwise the
+    // output is kept in the attempt's log.
+    let attempt = |run: usize, env: &[(String, String)], live: bool| -> Attempt {
+        let mut log = String::new();
+        let mut say = |line: String| {
+            if live {
+                println!("{}", line);
+            } else {
+                log += &line;
+                log.push('\n');
+            }
+        };
+        let seed = flags.shuffle_seed.map(|b| b.wrapping_add(run as u64 - 1));
+        let test_seed = flags.seed_matrix.map(|_| ((run - 1) / flags.runs) as u64);
+        let mut label = format!("Run {}/{}", run, total_runs);
+        if let Some(s) = test_seed {
+            label += &format!(" ({} {})", TEST_SEED_VAR, s);
+        }
+        if let Some(s) = seed {
+            label += &format!(" (shuffle seed {})", s);
+        }
+        say(format!("{}{}{}", BLUE, label, RESET));
+        let t0 = Instant::now();
+        let mut run_env = env.to_vec();
+        if let Some(s) = test_seed {
+            run_env.push((TEST_SEED_VAR.to_string(), s.to_string()));
+        }
+        let opts = RunOptions { shuffle_seed: seed, env: &run_env, buffered: !live, ..base };
+        let mut outcome = run_cargo_test_capture(workspace, &opts);
+        let mut slow_tests = None;
+        if let (Some(factor), Err(RunError { finished: Some(finished), .. })) = (flags.timeout_retry, &outcome) {
+            let finished = finished.clone();
+            let longer = RunOptions { timeout: opts.timeout * factor, ..opts };
+            say(format!("  {}timed out after {}s; retrying with {}s{}",
+                        RED, opts.timeout, longer.timeout, RESET));
+            let t1 = Instant::now();
+            outcome = run_cargo_test_capture(workspace, &longer);
+            if let Ok(output) = &outcome {
+                let mut slow: Vec<String> = output
+                    .outcomes
+                    .iter()
+                    .filter(|(name, _)| !finished.contains_key(*name))
+                    .map(|(name, &passed)| {
+                        format!("{} ({})", name, if passed { "passed" } else { "failed" })
+                    })
+                    .collect();
+                slow.sort();
+                slow_tests = Some((t1.elapsed(), slow));
+            }
+        }
+        let cases = match (&outcome, case_exe) {
+            (Ok(_), Some(exe)) => Some(expected::run_all(exe, workspace, cases, &cx.manifest.output, &opts)),
+            _ => None,
+        };
+        if let (false, Ok(output)) = (live, &outcome) {
+            log += &output.stderr;
+        }
+        Attempt { label, seed, test_seed, outcome, took: t0.elapsed(), slow_tests, cases, log }
+    };
+
+    // Merge a finished run into the results, in run order; false once the
+    // runs should stop.
+    let mut apply = |run: usize, attempt: Attempt| -> Result<bool, String> {
+        print!("{}", attempt.log);
+        let Attempt { label, seed, test_seed, outcome, took, slow_tests, cases: case_outcomes, .. } = attempt;
+        if let Some((took, slow)) = slow_tests {
+            runs.marginal.push((run, took, slow));
+        }
+        match outcome {
+            // a run cut short says nothing about the tests it did not reach
+            _ if interrupt::interrupted() => return Ok(false),
+            Ok(RunOutput { outcomes: mut results, times, stdout, crashes: crashed, samples, violations: violated, .. }) => {
+                if base.sample_interval.is_some() {
+                    runs.usage.push(resources::Series { run, samples });
+                }
+                runs.failure_outputs.extend(digest::failure_output(&stdout));
+                for c in crashed {
+                    if !runs.crashes.iter().any(|k| k.test == c.test) {
+                        runs.crashes.push(c);
+                    }
+                }
+                for (test, v) in violated {
+                    if !runs.violations.iter().any(|(t, _)| *t == test) {
+                        runs.violations.push((test, v));
+                    }
+                }
+                match case_outcomes {
+                    Some(Ok(outcomes)) => {
+                        for (case, outcome) in cases.iter().zip(outcomes) {
+                            if !outcome.matched {
+                                runs.failure_outputs.insert(case.test_name(), outcome.report());
+                            }
+                            results.insert(case.test_name(), outcome.matched);
+                        }
+                    }
+                    Some(Err(e)) => eprintln!("{}Case error:{} {}", RED, RESET, e),
+                    None => {}
+                }
+                if let Some(budget) = flags.max_test_time {
+                    let mut over: Vec<_> = times.iter().filter(|(_, t)| t.as_secs_f64() > budget).collect();
+                    over.sort();
+                    for (name, test_took) in over {
+                        let note = format!("took {:.3}s, over the --max-test-time budget of {}s",
+                                           test_took.as_secs_f64(), budget);
+                        println!("  {}{}{} {}", RED, name, RESET, note);
+                        runs.failure_outputs.insert(name.clone(), note);
+                        results.insert(name.clone(), false);
+                    }
+                }
+                runs.matrix.record_times(times);
+                println!("  {}completed in {:.2}s{}", GREEN, took.as_secs_f32(), RESET);
+                runs.times.push(took);
+                let mut failed: Vec<String> =
+                    results.iter().filter(|(_, &passed)| !passed).map(|(name, _)| name.clone()).collect();
+                runs.matrix.record(results);
+                if let Some(s) = test_seed {
+                    runs.seed_failures.entry(s).or_default().extend(failed.iter().cloned());
+                }
+                failed.sort();
+                let mut message = format!("{}: {} failed", label, failed.len());
+                if !failed.is_empty() {
+                    message += &format!("\n\n{}", failed.join("\n"));
+                }
+                if let Some(snapshots) = &cx.snapshots {
+                    snapshots.commit(&message).map_err(|e| format!("snapshot: {}", e))?;
+                }
+                if let (Some(s), false) = (seed, failed.is_empty()) {
+                    runs.shuffled_failures.push((run, s, failed));
+                }
+            }
+            Err(e) => {
+                // what the run was doing when it failed is the point of sampling
+                if base.sample_interval.is_some() {
+                    runs.usage.push(resources::Series { run, samples: e.samples });
+                    print!("{}", resources::summary(&runs.usage));
+                    write_resource_html(flags.resource_html.as_deref(), cx.notebook, &runs.usage);
+                }
+                return Err(e.message);
+            }
+        }
+        runs.completed = run;
+        Ok(true)
+    };
+
+    interrupt::defer_exit(true);
+    let jobs = flags.jobs.clamp(1, total_runs.max(1));
+    let result = if jobs == 1 {
+        (1..=total_runs).try_for_each(|run| match apply(run, attempt(run, &cx.env, true)) {
+            Ok(true) => Ok(()),
+            // stop at the first run that says so
+            Ok(false) => Err(None),
+            Err(e) => Err(Some(e)),
+        })
+        .or_else(|e| e.map_or(Ok(()), Err))
+    } else {
+        // each job builds into a target directory of its own, so its runs
+        // do not queue on cargo's lock behind the others'
+        let target = workspace.join(cx.shared_target.as_deref().unwrap_or(Path::new("target")));
+        let job_envs: Vec<Vec<(String, String)>> = (1..=jobs)
+            .map(|job| {
+                let mut job_env = cx.env.clone();
+                let dir = target.join(format!("job-{}", job));
+                job_env.push(("CARGO_TARGET_DIR".to_string(), dir.display().to_string()));
+                job_env
+            })
+            .collect();
+        println!("{}Running {} runs in {} jobs{}", BLUE, total_runs, jobs, RESET);
+        let next = AtomicUsize::new(1);
+        thread::scope(|scope| {
+            let (tx, rx) = mpsc::channel();
+            for job_env in &job_envs {
+                let (tx, next, attempt) = (tx.clone(), &next, &attempt);
+                scope.spawn(move || {
+                    let opts = RunOptions { env: job_env, buffered: true, ..base };
+                    if let Err(e) = build_tests(workspace, &opts) {
+                        let _ = tx.send(Err(e));
+                        return;
+                    }
+                    loop {
+                        let run = next.fetch_add(1, Ordering::SeqCst);
+                        if run > total_runs || interrupt::interrupted() {
+                            break;
+                        }
+                        if tx.send(Ok((run, attempt(run, job_env, false)))).is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+            drop(tx);
+            // runs finish in any order; merge them in run order
+            let mut finished = BTreeMap::new();
+            let mut due = 1;
+            for message in rx {
+                let (run, done) = message.map_err(|e| format!("cargo build: {}", e))?;
+                finished.insert(run, done);
+                while let Some(done) = finished.remove(&due) {
+                    if !apply(due, done)? {
+                        return Ok(());
+                    }
+                    due += 1;
+                }
+            }
+            Ok(())
+        })
+    };
+    interrupt::defer_exit(false);
+    result
+}
@@ -0,0 +1,159 @@
+//! Resource limits and network isolation for the test runs, which execute
+//! the notebook's arbitrary code. Limits are rlimits set in the child
+//! before it execs, so they hold for every process cargo starts: address
+//! space (`--memory-limit`), CPU seconds (`--cpu-limit`) and processes of
+//! the user (`--max-procs`; not enforced for root). `--no-network` runs
+//! the tests in a user and network namespace of their own with only a
+//! loopback interface, so tests may still serve each other on 127.0.0.1.
+//!
+//! The build is left unconfined: rustc needs the memory, and fetching
+//! dependencies the network (`--offline` forbids that instead). Tests
+//! stopped by a limit are reported as violations, apart from failures.
+//! Windows hosts run the validator in its Docker image (`task_runner.ps1`),
+//! so these Linux mechanisms cover them as well.
+
+use std::{fmt, io, os::unix::process::CommandExt, process::Command};
+
+#[derive(Clone, Copy, Default, PartialEq)]
+pub struct Limits {
+    /// Address space of each process, in MiB.
+    pub memory_mb: Option<u64>,
+    /// CPU time of each process (a test binary runs all its tests), in
+    /// seconds.
+    pub cpu_secs: Option<u64>,
+    /// Processes the user may have at once.
+    pub max_procs: Option<u64>,
+    /// Cut the tests off from the network.
+    pub no_network: bool,
+}
+
+/// Set `resource`'s soft and hard limit.
+///
+/// # Safety
+/// Only async-signal-safe calls, so it may run between fork and exec.
+unsafe fn set_limit(resource: libc::__rlimit_resource_t, soft: u64, hard: u64) -> io::Result<()> {
+    let limit = libc::rlimit { rlim_cur: soft as libc::rlim_t, rlim_max: hard as libc::rlim_t };
+    if libc::setrlimit(resource, &limit) != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Leave the network: a new user namespace (so no privileges are needed)
+/// holding a new network namespace, whose loopback is brought up.
+///
+/// # Safety
+/// Only async-signal-safe calls, so it may run between fork and exec.
+unsafe fn isolate_network() -> io::Result<()> {
+    if libc::unshare(libc::CLONE_NEWUSER | libc::CLONE_NEWNET) != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let fd = libc::socket(libc::AF_INET, libc::SOCK_DGRAM, 0);
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let mut req: libc::ifreq = std::mem::zeroed();
+    for (dst, &src) in req.ifr_name.iter_mut().zip(b"lo") {
+        *dst = src as libc::c_char;
+    }
+    let up = libc::ioctl(fd, libc::SIOCGIFFLAGS as _, &mut req) == 0 && {
+        req.ifr_ifru.ifru_flags |= libc::IFF_UP as libc::c_short;
+        libc::ioctl(fd, libc::SIOCSIFFLAGS as _, &req) == 0
+    };
+    let error = io::Error::last_os_error();
+    libc::close(fd);
+    if up {
+        Ok(())
+    } else {
+        Err(error)
+    }
+}
+
+impl Limits {
+    pub fn is_empty(&self) -> bool {
+        *self == Limits::default()
+    }
+
+    /// Make `cmd`'s process start under these limits.
+    pub fn apply(&self, cmd: &mut Command) {
+        if self.is_empty() {
+            return;
+        }
+        let limits = *self;
+        // SAFETY: the closure only makes async-signal-safe calls
+        unsafe {
+            cmd.pre_exec(move || {
+                if limits.no_network {
+                    isolate_network()?;
+                }
+                if let Some(mb) = limits.memory_mb {
+                    let bytes = mb.saturating_mul(1 << 20);
+                    set_limit(libc::RLIMIT_AS, bytes, bytes)?;
+                }
+                if let Some(secs) = limits.cpu_secs {
+                    // SIGXCPU at the soft limit tells the overrun from
+                    // other kills; the hard one is a backstop
+                    set_limit(libc::RLIMIT_CPU, secs, secs + 1)?;
+                }
+                if let Some(n) = limits.max_procs {
+                    set_limit(libc::RLIMIT_NPROC, n, n)?;
+                }
+                Ok(())
+            });
+        }
+    }
+
+    /// Check that the limits can be set here at all, so a missing
+    /// namespace permission is one clear error rather than every run
+    /// failing to start.
+    pub fn probe(&self) -> Result<(), String> {
+        let mut cmd = Command::new("true");
+        self.apply(&mut cmd);
+        match cmd.status() {
+            Ok(_) => Ok(()),
+            Err(e) if self.no_network => Err(format!("cannot isolate the network: {}", e)),
+            Err(e) => Err(format!("cannot set resource limits: {}", e)),
+        }
+    }
+
+    /// Which limit, if any, stopped a test, judged by the signal that
+    /// killed it (like `SIGXCPU (24)`) and its output.
+    pub fn violation(&self, signal: Option<&str>, output: &str) -> Option<Violation> {
+        if self.cpu_secs.is_some() && signal.is_some_and(|s| s.starts_with("SIGXCPU")) {
+            return Some(Violation::CpuTime);
+        }
+        if self.memory_mb.is_some() && (output.contains("memory allocation of") || output.contains("Cannot allocate memory")) {
+            return Some(Violation::Memory);
+        }
+        if self.max_procs.is_some() && output.contains("Resource temporarily unavailable") {
+            return Some(Violation::Processes);
+        }
+        if self.no_network && ["Network is unreachable", "failed to lookup address", "Temporary failure in name resolution"]
+            .iter()
+            .any(|m| output.contains(m))
+        {
+            return Some(Violation::Network);
+        }
+        None
+    }
+}
+
+/// A limit a test ran into.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Violation {
+    Memory,
+    CpuTime,
+    Processes,
+    Network,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Violation::Memory => "exceeded the memory limit",
+            Violation::CpuTime => "exceeded the CPU time limit",
+            Violation::Processes => "exceeded the process limit",
+            Violation::Network => "tried to reach the network",
+        })
+    }
+}
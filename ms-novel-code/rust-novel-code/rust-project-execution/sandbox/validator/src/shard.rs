@@ -0,0 +1,190 @@
+//! `--shard i/n` runs the tests whose name hashes to shard `i` of `n`, so
+//! an orchestrator can spread one large suite over machines; each shard
+//! writes its outcomes with `--shard-report` and `validator merge-reports`
+//! puts them back together.
+//!
+//! The report carries a `schema_version`, and `validator schema` prints
+//! its JSON Schema. Adding an optional field keeps the version; removing,
+//! renaming or retyping one, or changing what a field means, bumps it.
+//! `merge-reports` refuses reports newer than it understands.
+
+use crate::redact::Redactor;
+use crate::resources::Series;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt, fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct Shard {
+    /// 1-based.
+    pub index: usize,
+    pub count: usize,
+}
+
+impl FromStr for Shard {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let (i, n) = s.split_once('/').ok_or("expected I/N, like 2/4")?;
+        let parse = |v: &str| v.trim().parse::<usize>().map_err(|e| format!("`{}`: {}", v, e));
+        let (index, count) = (parse(i)?, parse(n)?);
+        if index == 0 || index > count {
+            return Err(format!("shard {} is not in 1..={}", index, count));
+        }
+        Ok(Shard { index, count })
+    }
+}
+
+impl fmt::Display for Shard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.index, self.count)
+    }
+}
+
+impl Shard {
+    /// Whether `test` belongs to this shard, by FNV-1a of its name, which
+    /// is stable across machines and releases.
+    pub fn contains(&self, test: &str) -> bool {
+        let hash = test
+            .bytes()
+            .fold(0xCBF2_9CE4_8422_2325u64, |h, b| (h ^ b as u64).wrapping_mul(0x0100_0000_01B3));
+        hash % self.count as u64 == (self.index - 1) as u64
+    }
+}
+
+/// Version of the `--shard-report` format this validator writes.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Outcomes of one (possibly sharded) validation, as `--shard-report`
+/// writes them.
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct ShardReport {
+    /// Bumped on every incompatible change; reports from before it
+    /// existed are version 1.
+    #[serde(default = "first_version")]
+    pub schema_version: u32,
+    pub notebook: String,
+    /// `i/n`, or absent for an unsharded run.
+    pub shard: Option<String>,
+    pub runs: usize,
+    /// Pass/fail per run for every test that ran.
+    pub tests: BTreeMap<String, Vec<bool>>,
+    /// Resource usage per run, with `--sample-interval`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub resources: Vec<Series>,
+}
+
+fn first_version() -> u32 {
+    1
+}
+
+/// JSON Schema of [`ShardReport`], for `validator schema`.
+pub fn schema() -> String {
+    let schema = schemars::schema_for!(ShardReport);
+    serde_json::to_string_pretty(&schema).expect("schemas serialize")
+}
+
+impl ShardReport {
+    pub fn save(&self, path: &Path, redact: &Redactor) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        fs::write(path, redact.apply(&json) + "\n").map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let raw = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let report: Self = serde_json::from_str(&raw).map_err(|e| format!("{}: {}", path.display(), e))?;
+        if report.schema_version > SCHEMA_VERSION {
+            return Err(format!(
+                "{}: schema version {} is newer than this validator's {}",
+                path.display(),
+                report.schema_version,
+                SCHEMA_VERSION
+            ));
+        }
+        Ok(report)
+    }
+}
+
+/// Pass/fail per run, by test name.
+type Matrix = HashMap<String, Vec<bool>>;
+
+/// One matrix from the shard reports at `paths`, which must all be of the
+/// same notebook and shard count, plus the shards none of them covers.
+pub fn merge(paths: &[PathBuf]) -> Result<(Matrix, Vec<Shard>), String> {
+    let mut matrix = HashMap::new();
+    let mut seen = BTreeSet::new();
+    let mut first: Option<(String, usize)> = None;
+    for path in paths {
+        let report = ShardReport::load(path)?;
+        let shard = report.shard.as_deref().map(Shard::from_str).transpose()
+            .map_err(|e| format!("{}: shard: {}", path.display(), e))?;
+        let count = shard.map_or(1, |s| s.count);
+        match &first {
+            None => first = Some((report.notebook.clone(), count)),
+            Some((notebook, _)) if *notebook != report.notebook => {
+                return Err(format!("{}: report of {}, not {}", path.display(), report.notebook, notebook));
+            }
+            Some((_, n)) if *n != count => {
+                return Err(format!("{}: {} shards, not {}", path.display(), count, n));
+            }
+            Some(_) => {}
+        }
+        if !seen.insert(shard.map_or(1, |s| s.index)) {
+            return Err(format!("{}: shard {} given twice", path.display(), shard.map_or(1, |s| s.index)));
+        }
+        for (test, runs) in report.tests {
+            if matrix.insert(test.clone(), runs).is_some() {
+                return Err(format!("{}: test `{}` is in more than one shard", path.display(), test));
+            }
+        }
+    }
+    let count = first.map_or(0, |(_, n)| n);
+    let missing = (1..=count)
+        .filter(|i| !seen.contains(i))
+        .map(|index| Shard { index, count })
+        .collect();
+    Ok((matrix, missing))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_index_and_count() {
+        let shard: Shard = " 2 / 4 ".parse().unwrap();
+        assert_eq!((shard.index, shard.count, shard.to_string()), (2, 4, "2/4".to_string()));
+        for bad in ["0/4", "5/4", "2", "a/4", "2/", "-1/4"] {
+            assert!(bad.parse::<Shard>().is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn hashing_is_stable() {
+        // FNV-1a("a") = 0xaf63dc4c8601ec8c, which is even
+        assert!(Shard { index: 1, count: 2 }.contains("a"));
+        assert!(!Shard { index: 2, count: 2 }.contains("a"));
+        // FNV-1a("") is the offset basis, 0xcbf29ce484222325
+        assert!(Shard { index: (0xcbf2_9ce4_8422_2325u64 % 7) as usize + 1, count: 7 }.contains(""));
+    }
+
+    #[test]
+    fn every_test_is_in_exactly_one_shard() {
+        let names: Vec<String> = (0..200).map(|i| format!("tests::case_{}", i)).collect();
+        for count in 1..=5 {
+            let shards: Vec<Shard> = (1..=count).map(|index| Shard { index, count }).collect();
+            for name in &names {
+                assert_eq!(shards.iter().filter(|s| s.contains(name)).count(), 1, "{} of {}", name, count);
+            }
+            // and the split is not lopsided
+            for shard in &shards {
+                assert!(names.iter().filter(|n| shard.contains(n)).count() >= 200 / count / 2);
+            }
+        }
+    }
+}
@@ -0,0 +1,114 @@
+//! Interactive triage prompt rooted at a prepared workspace, entered with
+//! `validator shell <workspace>` or `--shell-on-failure`.
+
+use crate::colors::*;
+use crate::{fix, prepare_workspace, Notebook, Section};
+use std::{
+    fs,
+    io::{self, BufRead, Write},
+    path::Path,
+    process::Command,
+};
+
+const HELP: &str = "\
+commands:
+  tests            list the suite's tests
+  run <test>       rerun one test (exact name)
+  output <test>    rerun one test showing everything it prints
+  diff [file]      compare the workspace with what the notebook extracts
+  cat <file>       print a workspace file
+  !<command>       run a shell command in the workspace
+  help             this list
+  quit             leave (also Ctrl-D)";
+
+/// Run `cargo test` in `workspace` with `args` after `--`, output inherited.
+fn cargo_test(workspace: &Path, env: &[(String, String)], args: &[&str]) -> io::Result<()> {
+    Command::new("cargo")
+        .args(["test", "-q", "--"])
+        .args(args)
+        .current_dir(workspace)
+        .envs(env.iter().map(|(k, v)| (k, v)))
+        .status()
+        .map(drop)
+}
+
+/// Diff every extracted file (or just `only`) in the workspace against a
+/// fresh extraction of `notebook`.
+fn diff(workspace: &Path, notebook: &Path, only: Option<&str>) -> Result<(), String> {
+    let nb = Notebook::load(notebook).map_err(|e| format!("{}: {}", notebook.display(), e))?;
+    let fresh = std::env::temp_dir().join(format!("validator-shell-{}", std::process::id()));
+    let files = prepare_workspace(&nb, &fresh, &Section::ALL, None);
+    let result = files.map(|files| {
+        let mut clean = true;
+        for rel in files.iter().filter(|f| only.is_none_or(|o| o == f.as_str())) {
+            let lines = |p: &Path| -> Vec<String> {
+                fs::read_to_string(p).unwrap_or_default().lines().map(str::to_string).collect()
+            };
+            let (theirs, ours) = (lines(&fresh.join(rel)), lines(&workspace.join(rel)));
+            if theirs != ours {
+                clean = false;
+                println!("{}--- notebook {}\n+++ workspace {}{}", BOLD, rel, rel, RESET);
+                for line in fix::diff(&theirs, &ours) {
+                    println!("{}", line);
+                }
+            }
+        }
+        if clean {
+            println!("workspace matches the notebook");
+        }
+    });
+    let _ = fs::remove_dir_all(&fresh);
+    result
+}
+
+/// Read commands until `quit` or end of input.
+pub fn run(workspace: &Path, notebook: Option<&Path>, env: &[(String, String)]) -> Result<(), String> {
+    if !workspace.join("Cargo.toml").exists() {
+        return Err(format!("{}: not a prepared workspace (no Cargo.toml)", workspace.display()));
+    }
+    println!("{}Shell in {}{} (type `help`)", BOLD, workspace.display(), RESET);
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("validator> ");
+        io::stdout().flush().map_err(|e| e.to_string())?;
+        let Some(line) = lines.next() else { println!(); return Ok(()) };
+        let line = line.map_err(|e| e.to_string())?;
+        let line = line.trim();
+        let (cmd, arg) = line.split_once(' ').map_or((line, ""), |(c, a)| (c, a.trim()));
+        let outcome = match (cmd, arg) {
+            ("", _) => Ok(()),
+            ("quit" | "exit", _) => return Ok(()),
+            ("help", _) => {
+                println!("{}", HELP);
+                Ok(())
+            }
+            ("tests", _) => cargo_test(workspace, env, &["--list"]).map_err(|e| e.to_string()),
+            ("run", name) if !name.is_empty() => {
+                cargo_test(workspace, env, &["--exact", name]).map_err(|e| e.to_string())
+            }
+            ("output", name) if !name.is_empty() => {
+                cargo_test(workspace, env, &["--exact", name, "--nocapture"])
+                    .map_err(|e| e.to_string())
+            }
+            ("diff", file) => match notebook {
+                Some(nb) => diff(workspace, nb, Some(file).filter(|f| !f.is_empty())),
+                None => Err("no notebook; pass --notebook to `validator shell`".to_string()),
+            },
+            ("cat", file) if !file.is_empty() => crate::sanitize_relative_path(workspace, file)
+                .and_then(|p| fs::read_to_string(&p).map_err(|e| format!("{}: {}", file, e)))
+                .map(|text| print!("{}", text)),
+            _ if cmd.starts_with('!') => Command::new("sh")
+                .args(["-c", &line[1..]])
+                .current_dir(workspace)
+                .envs(env.iter().map(|(k, v)| (k, v)))
+                .status()
+                .map(drop)
+                .map_err(|e| e.to_string()),
+            _ => Err(format!("unknown command `{}` (try `help`)", line)),
+        };
+        if let Err(e) = outcome {
+            eprintln!("{}error:{} {}", RED, RESET, e);
+        }
+    }
+}
@@ -0,0 +1,225 @@
+//! `--similarity-report <path>` in batch mode: compare the problem
+//! statements (the `# Prompt` and `# Requirements` cells) and the Rust
+//! code of every pair of notebooks, and list the pairs similar enough to
+//! be re-generated duplicates, most similar first, quoting the lines
+//! they share most.
+//!
+//! Similarity is the Jaccard index of the two texts' shingles (runs of
+//! consecutive words), which survives reordered paragraphs and small
+//! edits that an exact comparison would not.
+
+use crate::{extract_fenced_lines, fix, lint, source, Cell, Notebook};
+use std::{
+    cmp::Reverse,
+    collections::HashMap,
+    fmt::Write,
+};
+
+/// Words per statement shingle.
+const STATEMENT_SHINGLE: usize = 5;
+/// Tokens per code shingle; code repeats short runs like `let mut x =`
+/// far more than prose does.
+const CODE_SHINGLE: usize = 8;
+/// Statement or code similarity from which a pair is reported.
+pub const THRESHOLD: f64 = 0.5;
+/// Longest excerpt quoted, in characters.
+const EXCERPT: usize = 160;
+
+/// Text cut into shingles, remembering the line each one starts on.
+struct Shingled {
+    lines: Vec<String>,
+    shingles: HashMap<String, usize>,
+}
+
+impl Shingled {
+    /// `lines` with `tokens` of each, in `k`-token shingles; a text
+    /// shorter than that is a single shingle.
+    fn new(lines: Vec<String>, tokens: Vec<Vec<String>>, k: usize) -> Self {
+        let words: Vec<(usize, String)> = tokens
+            .into_iter()
+            .enumerate()
+            .flat_map(|(line, words)| words.into_iter().map(move |w| (line, w)))
+            .collect();
+        let mut shingles = HashMap::new();
+        for window in words.windows(k.min(words.len()).max(1)) {
+            let key = window.iter().map(|(_, w)| w.as_str()).collect::<Vec<_>>().join(" ");
+            shingles.entry(key).or_insert(window[0].0);
+        }
+        Shingled { lines, shingles }
+    }
+
+    fn similarity(&self, other: &Shingled) -> f64 {
+        let shared = self.shingles.keys().filter(|s| other.shingles.contains_key(*s)).count();
+        let union = self.shingles.len() + other.shingles.len() - shared;
+        if union == 0 {
+            0.0
+        } else {
+            shared as f64 / union as f64
+        }
+    }
+
+    /// The line starting the most shingles `other` has too.
+    fn excerpt(&self, other: &Shingled) -> Option<String> {
+        let mut counts: HashMap<usize, usize> = HashMap::new();
+        for (shingle, &line) in &self.shingles {
+            if other.shingles.contains_key(shingle) {
+                *counts.entry(line).or_default() += 1;
+            }
+        }
+        let (line, _) = counts.into_iter().max_by_key(|&(line, n)| (n, Reverse(line)))?;
+        let text = self.lines[line].trim();
+        Some(match text.char_indices().nth(EXCERPT) {
+            Some((end, _)) => format!("{}…", &text[..end]),
+            None => text.to_string(),
+        })
+    }
+}
+
+/// What of one notebook is compared.
+pub struct Task {
+    pub name: String,
+    statement: Shingled,
+    code: Shingled,
+}
+
+impl Task {
+    pub fn new(name: String, nb: &Notebook) -> Self {
+        let lines: Vec<String> = lint::statement(nb)
+            .into_iter()
+            .map(|(_, line)| line)
+            .filter(|line| fix::marker(line).is_none())
+            .map(str::to_string)
+            .collect();
+        let words = lines
+            .iter()
+            .map(|l| {
+                l.split(|c: char| !c.is_alphanumeric())
+                    .filter(|w| !w.is_empty())
+                    .map(str::to_lowercase)
+                    .collect()
+            })
+            .collect();
+        let statement = Shingled::new(lines, words, STATEMENT_SHINGLE);
+
+        let code: Vec<String> = nb
+            .cells
+            .iter()
+            .flat_map(|cell| {
+                let (Cell::Markdown { source } | Cell::Code { source }) = cell;
+                extract_fenced_lines(source, "rust")
+            })
+            .collect();
+        // comments and literals blanked, line for line
+        let stripped = source::strip_comments_and_strings(&code.join("\n"));
+        let tokens = stripped.split('\n').map(|l| source::words(l).map(str::to_string).collect()).collect();
+        let code = Shingled::new(code, tokens, CODE_SHINGLE);
+        Task { name, statement, code }
+    }
+}
+
+/// Two notebooks and how alike they are, from 0 to 1.
+pub struct Pair<'a> {
+    pub a: &'a Task,
+    pub b: &'a Task,
+    pub statement: f64,
+    pub code: f64,
+}
+
+/// Pairs of `tasks` with statement or code similarity of at least
+/// [`THRESHOLD`], most similar first.
+pub fn similar(tasks: &[Task]) -> Vec<Pair<'_>> {
+    let mut pairs: Vec<Pair> = tasks
+        .iter()
+        .enumerate()
+        .flat_map(|(i, a)| tasks[i + 1..].iter().map(move |b| (a, b)))
+        .map(|(a, b)| Pair { a, b, statement: a.statement.similarity(&b.statement), code: a.code.similarity(&b.code) })
+        .filter(|p| p.statement.max(p.code) >= THRESHOLD)
+        .collect();
+    pairs.sort_by(|x, y| {
+        let key = |p: &Pair| (p.statement.max(p.code), p.statement.min(p.code));
+        key(y).partial_cmp(&key(x)).unwrap_or(std::cmp::Ordering::Equal)
+    });
+    pairs
+}
+
+/// The report of `pairs` found among `compared` notebooks.
+pub fn report(pairs: &[Pair], compared: usize) -> String {
+    let mut out = format!(
+        "Similar task pairs: {} of {} at {:.2} or more in statement or code similarity\n\
+         (Jaccard index of {}-word statement and {}-token code shingles), most similar first.\n",
+        pairs.len(),
+        compared * compared.saturating_sub(1) / 2,
+        THRESHOLD,
+        STATEMENT_SHINGLE,
+        CODE_SHINGLE
+    );
+    for (i, p) in pairs.iter().enumerate() {
+        let _ = writeln!(out, "\n{}. {} ~ {}: statement {:.2}, code {:.2}", i + 1, p.a.name, p.b.name, p.statement, p.code);
+        for (label, score, a, b) in [
+            ("statement", p.statement, &p.a.statement, &p.b.statement),
+            ("code", p.code, &p.a.code, &p.b.code),
+        ] {
+            if score < THRESHOLD {
+                continue;
+            }
+            if let (Some(x), Some(y)) = (a.excerpt(b), b.excerpt(a)) {
+                let _ = writeln!(out, "   {}:\n     {}: {}\n     {}: {}", label, p.a.name, x, p.b.name, y);
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notebook(statement: &str, code: &str) -> Notebook {
+        let cell = |text: String| Cell::Markdown { source: text.lines().map(|l| format!("{}\n", l)).collect() };
+        Notebook {
+            cells: vec![
+                cell(format!("# Prompt\n\n{}", statement)),
+                cell(format!("# lib\n\n```rust\n{}\n```", code)),
+            ],
+        }
+    }
+
+    const PROMPT: &str = "Write a function that returns the shortest tour visiting every city exactly once.\n\
+                          The input is a square matrix of distances between the cities.";
+
+    #[test]
+    fn reworded_duplicates_rank_first() {
+        let code = "pub fn tour(d: &[Vec<u32>]) -> u32 { d.iter().map(|r| r.iter().sum::<u32>()).sum() }";
+        let tasks = [
+            Task::new("a".into(), &notebook(PROMPT, code)),
+            Task::new("b".into(), &notebook(&format!("{}\nReturn 0 for one city.", PROMPT), "pub fn other() {}")),
+            Task::new("c".into(), &notebook("Parse a date in ISO 8601 format and print its weekday.", code)),
+        ];
+        let pairs = similar(&tasks);
+        let names: Vec<_> = pairs.iter().map(|p| (p.a.name.as_str(), p.b.name.as_str())).collect();
+        assert_eq!(names, [("a", "c"), ("a", "b")]);
+        assert_eq!((pairs[0].statement, pairs[0].code), (0.0, 1.0));
+        assert!(pairs[1].statement > THRESHOLD && pairs[1].statement < 1.0);
+    }
+
+    #[test]
+    fn report_quotes_the_shared_lines() {
+        let tasks = [
+            Task::new("a.ipynb".into(), &notebook(&format!("Intro.\n{}", PROMPT), "")),
+            Task::new("b.ipynb".into(), &notebook(PROMPT, "")),
+        ];
+        let text = report(&similar(&tasks), tasks.len());
+        assert!(text.starts_with("Similar task pairs: 1 of 1 at 0.50"), "{}", text);
+        assert!(text.contains("\n1. a.ipynb ~ b.ipynb: statement "), "{}", text);
+        assert!(text.contains("     a.ipynb: Write a function that returns the shortest tour"), "{}", text);
+        assert!(!text.contains("code:"), "{}", text);
+    }
+
+    #[test]
+    fn comments_and_markers_do_not_count() {
+        let a = Task::new("a".into(), &notebook("", "// same words in a comment here and there\nfn a() {}"));
+        let b = Task::new("b".into(), &notebook("", "// same words in a comment here and there\nfn b() {}"));
+        assert!(a.code.similarity(&b.code) < THRESHOLD);
+        assert_eq!(a.statement.similarity(&b.statement), 0.0);
+    }
+}
@@ -0,0 +1,44 @@
+//! `--git-snapshots`: a git repository in the workspace with a commit
+//! after extraction, after every change to it, and per run, so a reviewer
+//! can see exactly which code produced which run's results.
+
+use crate::reaper;
+use std::{fs, path::Path, process::Command};
+
+pub struct Snapshots<'a> {
+    workspace: &'a Path,
+}
+
+impl<'a> Snapshots<'a> {
+    /// `git init` the workspace, ignoring build output.
+    pub fn init(workspace: &'a Path) -> Result<Self, String> {
+        let snapshots = Snapshots { workspace };
+        snapshots.git(&["init", "-q"])?;
+        fs::write(workspace.join(".gitignore"), format!("/target\n/{}\n", reaper::MARKER)).map_err(|e| e.to_string())?;
+        Ok(snapshots)
+    }
+
+    /// Commit the workspace as it is now (empty commits included, so
+    /// every run gets one).
+    pub fn commit(&self, message: &str) -> Result<(), String> {
+        self.git(&["add", "-A"])?;
+        self.git(&[
+            "-c", "user.name=validator",
+            "-c", "user.email=validator@localhost",
+            "commit", "-q", "--allow-empty", "--no-verify", "-m", message,
+        ])
+    }
+
+    fn git(&self, args: &[&str]) -> Result<(), String> {
+        let out = Command::new("git")
+            .args(args)
+            .current_dir(self.workspace)
+            .output()
+            .map_err(|e| format!("git: {}", e))?;
+        if out.status.success() {
+            Ok(())
+        } else {
+            Err(format!("git {}: {}", args[0], String::from_utf8_lossy(&out.stderr).trim()))
+        }
+    }
+}
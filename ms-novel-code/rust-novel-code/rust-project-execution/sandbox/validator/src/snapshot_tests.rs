@@ -0,0 +1,169 @@
+//! insta snapshot tests. A `# snapshots` cell holds the accepted
+//! snapshot files, each a `### <name>.snap` heading over a fenced block;
+//! a bare name goes to `tests/snapshots/`, a path (`src/snapshots/…`)
+//! stays as written. Runs set `INSTA_UPDATE=new`, so a mismatch or a
+//! missing snapshot leaves a `.snap.new` file behind: these are reported
+//! as pending, shown with `--review-snapshots` and written back into the
+//! notebook's cell with `--accept-snapshots`, since the workspace itself
+//! is regenerated on every validation.
+
+use crate::colors::*;
+use crate::fix::{diff, source_lines, to_source};
+use serde_json::{json, Value};
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+/// Where insta looks for snapshots of integration tests.
+const DEFAULT_DIR: &str = "tests/snapshots";
+
+/// Workspace-relative path of the snapshot `name` from a cell heading.
+fn path_of(name: &str) -> Result<PathBuf, String> {
+    let path = Path::new(name);
+    if !path.components().all(|c| matches!(c, Component::Normal(_))) || !name.ends_with(".snap") {
+        return Err(format!("`### {}`: expected a relative `.snap` path", name));
+    }
+    Ok(if path.components().count() == 1 { Path::new(DEFAULT_DIR).join(path) } else { path.to_path_buf() })
+}
+
+/// The (heading name, content) pairs of a `# snapshots` cell.
+pub fn parse(lines: &[String]) -> Vec<(String, String)> {
+    let mut snaps = Vec::new();
+    let mut name: Option<String> = None;
+    let mut body: Option<Vec<&str>> = None;
+    for line in lines.iter().flat_map(|l| l.lines()) {
+        match (&mut body, line.trim_start().starts_with("```")) {
+            (None, true) if name.is_some() => body = Some(Vec::new()),
+            (Some(b), true) => {
+                snaps.push((name.take().unwrap_or_default(), b.join("\n") + "\n"));
+                body = None;
+            }
+            (Some(b), false) => b.push(line),
+            (None, _) => {
+                if let Some(heading) = line.trim().strip_prefix("### ") {
+                    name = Some(heading.trim().to_string());
+                }
+            }
+        }
+    }
+    snaps
+}
+
+/// Write the snapshots of a `# snapshots` cell into `workspace`,
+/// returning their paths.
+pub fn extract(lines: &[String], workspace: &Path) -> Result<Vec<String>, String> {
+    let mut files = Vec::new();
+    for (name, content) in parse(lines) {
+        let rel = path_of(&name)?;
+        let dest = workspace.join(&rel);
+        if let Some(dir) = dest.parent() {
+            fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        fs::write(&dest, content).map_err(|e| e.to_string())?;
+        files.push(rel.display().to_string());
+    }
+    Ok(files)
+}
+
+/// A snapshot the last run disagreed with or had none for.
+pub struct Pending {
+    /// Workspace-relative path of the `.snap` it would replace.
+    pub path: PathBuf,
+    pub new: String,
+    pub old: Option<String>,
+}
+
+fn find_new(dir: &Path, root: &Path, out: &mut Vec<Pending>) {
+    for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            if entry.file_name() != "target" {
+                find_new(&path, root, out);
+            }
+            continue;
+        }
+        let Some(snap) = path.to_str().and_then(|p| p.strip_suffix(".new")) else { continue };
+        if !snap.ends_with(".snap") {
+            continue;
+        }
+        let Ok(new) = fs::read_to_string(&path) else { continue };
+        let snap = PathBuf::from(snap);
+        out.push(Pending {
+            path: snap.strip_prefix(root).unwrap_or(&snap).to_path_buf(),
+            old: fs::read_to_string(&snap).ok(),
+            new,
+        });
+    }
+}
+
+/// Every `.snap.new` file in the workspace.
+pub fn pending(workspace: &Path) -> Vec<Pending> {
+    let mut found = Vec::new();
+    find_new(workspace, workspace, &mut found);
+    found.sort_by(|a, b| a.path.cmp(&b.path));
+    found
+}
+
+pub fn print(pending: &[Pending], review: bool) {
+    println!("\n{}Pending snapshots:{}", BOLD, RESET);
+    for p in pending {
+        let state = if p.old.is_some() { "changed" } else { "new" };
+        println!("  {}{}{} ({})", RED, p.path.display(), RESET, state);
+        if !review {
+            continue;
+        }
+        let lines = |s: &str| s.lines().map(str::to_string).collect::<Vec<_>>();
+        for line in diff(&lines(p.old.as_deref().unwrap_or_default()), &lines(&p.new)) {
+            println!("    {}", line);
+        }
+    }
+    if !review {
+        println!("  (--review-snapshots shows them, --accept-snapshots takes them)");
+    }
+}
+
+/// Heading name for the snapshot at workspace-relative `path`.
+fn heading(path: &Path) -> String {
+    match path.strip_prefix(DEFAULT_DIR) {
+        Ok(name) => name.display().to_string(),
+        Err(_) => path.display().to_string(),
+    }
+}
+
+/// Store `pending` in the notebook's `# snapshots` cell, replacing the
+/// snapshots they supersede and adding the cell if there is none.
+pub fn accept(notebook: &Path, pending: &[Pending]) -> Result<(), String> {
+    let raw = fs::read_to_string(notebook).map_err(|e| format!("{}: {}", notebook.display(), e))?;
+    let mut nb: Value =
+        serde_json::from_str(&raw).map_err(|e| format!("{}: JSON error: {}", notebook.display(), e))?;
+    let cells = nb["cells"].as_array_mut().ok_or("notebook has no `cells` list")?;
+    let is_snapshots = |c: &Value| {
+        source_lines(c).iter().find(|l| !l.trim().is_empty()).is_some_and(|l| l.trim() == "# snapshots")
+    };
+    let index = match cells.iter().position(is_snapshots) {
+        Some(i) => i,
+        None => {
+            cells.push(json!({ "cell_type": "markdown", "metadata": {}, "source": [] }));
+            cells.len() - 1
+        }
+    };
+    let mut snaps = parse(&source_lines(&cells[index]));
+    for p in pending {
+        let name = heading(&p.path);
+        match snaps.iter_mut().find(|(n, _)| path_of(n).is_ok_and(|rel| rel == p.path)) {
+            Some(entry) => entry.1 = p.new.clone(),
+            None => snaps.push((name, p.new.clone())),
+        }
+    }
+    let mut lines = vec!["# snapshots".to_string()];
+    for (name, content) in &snaps {
+        lines.extend([String::new(), format!("### {}", name), "```".to_string()]);
+        lines.extend(content.trim_end_matches('\n').lines().map(str::to_string));
+        lines.push("```".to_string());
+    }
+    cells[index]["source"] = to_source(&lines);
+    let mut out = serde_json::to_string_pretty(&nb).map_err(|e| e.to_string())?;
+    out.push('\n');
+    fs::write(notebook, out).map_err(|e| format!("{}: {}", notebook.display(), e))
+}
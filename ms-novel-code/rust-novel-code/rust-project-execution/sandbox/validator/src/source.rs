@@ -0,0 +1,299 @@
+//! Light-weight scanning of Rust source: enough structure for the static
+//! checks (functions, `use` imports, public items) without a real parser.
+//! Everything here works on text that went through
+//! [`strip_comments_and_strings`], so keywords in comments and literals
+//! never count.
+
+use std::{fs, path::Path};
+
+/// `src` with comments removed and string/char literal contents blanked,
+/// so keywords inside them are not counted.
+pub fn strip_comments_and_strings(src: &str) -> String {
+    let chars: Vec<char> = src.chars().collect();
+    let mut out = String::with_capacity(src.len());
+    let mut i = 0;
+    while i < chars.len() {
+        match &chars[i..] {
+            ['/', '/', ..] => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+                continue;
+            }
+            ['/', '*', ..] => {
+                i += 2;
+                while i < chars.len() && !(chars[i - 1] == '*' && chars[i] == '/') {
+                    if chars[i] == '\n' {
+                        out.push('\n');
+                    }
+                    i += 1;
+                }
+            }
+            ['"', ..] => {
+                i += 1;
+                while i < chars.len() && chars[i] != '"' {
+                    match chars[i] {
+                        '\\' => i += 1,
+                        '\n' => out.push('\n'),
+                        _ => {}
+                    }
+                    i += 1;
+                }
+                out.push_str("\"\"");
+            }
+            // char literals; a lone `'` is a lifetime and stays
+            ['\'', '\\', ..] => {
+                while i + 1 < chars.len() && chars[i + 1] != '\'' {
+                    i += 1;
+                }
+                i += 1;
+                out.push_str("' '");
+            }
+            ['\'', _, '\'', ..] => {
+                i += 2;
+                out.push_str("' '");
+            }
+            _ => out.push(chars[i]),
+        }
+        i += 1;
+    }
+    out
+}
+
+/// Identifiers and keywords in `code`, in order.
+pub fn words(code: &str) -> impl Iterator<Item = &str> {
+    code.split(|c: char| !(c.is_alphanumeric() || c == '_')).filter(|w| !w.is_empty())
+}
+
+/// A function found in the source.
+pub struct FnItem {
+    pub name: String,
+    /// Attributes written before it, like `#[test]`.
+    pub attrs: String,
+    /// Parameters and return type.
+    pub signature: String,
+    /// Everything between its braces.
+    pub body: String,
+}
+
+impl FnItem {
+    pub fn is_test(&self) -> bool {
+        self.attrs.contains("#[test]") || self.attrs.contains("::test]")
+    }
+    pub fn returns_result(&self) -> bool {
+        self.signature.split("->").nth(1).is_some_and(|ret| ret.contains("Result"))
+    }
+}
+
+/// Every function with a body, including ones inside modules and impls
+/// (but not functions nested in other function bodies).
+pub fn functions(code: &str) -> Vec<FnItem> {
+    let mut items = Vec::new();
+    let mut pos = 0;
+    while let Some(found) = find_word(code, "fn", pos) {
+        let after = &code[found + 2..];
+        let name: String =
+            after.trim_start().chars().take_while(|c| c.is_alphanumeric() || *c == '_').collect();
+        // the item starts after the previous `}` or `;`
+        let start = code[..found].rfind(['}', ';', '{']).map_or(0, |p| p + 1);
+        let attrs = code[start..found].to_string();
+        let Some(open) = body_start(after) else {
+            pos = found + 2;
+            continue; // a declaration, as in a trait
+        };
+        let open = found + 2 + open;
+        let close = matching_brace(code, open).unwrap_or(code.len());
+        let signature = code[found + 2..open].to_string();
+        items.push(FnItem { name, attrs, signature, body: code[open + 1..close].to_string() });
+        pos = close;
+    }
+    items
+}
+
+/// Byte offset of `word` as a whole word in `code`, at or after `from`.
+fn find_word(code: &str, word: &str, from: usize) -> Option<usize> {
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    let mut pos = from;
+    while let Some(i) = code[pos..].find(word) {
+        let at = pos + i;
+        let before = code[..at].chars().next_back();
+        let after = code[at + word.len()..].chars().next();
+        if !before.is_some_and(is_ident) && !after.is_some_and(is_ident) {
+            return Some(at);
+        }
+        pos = at + word.len();
+    }
+    None
+}
+
+/// Offset of the `{` opening a function body in `sig` (the text after
+/// `fn`), or `None` if a `;` ends the signature first.
+fn body_start(sig: &str) -> Option<usize> {
+    let mut depth = 0i32;
+    for (i, c) in sig.char_indices() {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth -= 1,
+            '{' if depth == 0 => return Some(i),
+            ';' if depth == 0 => return None,
+            _ => {}
+        }
+    }
+    None
+}
+
+fn matching_brace(code: &str, open: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in code[open..].char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(open + i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Text of `fn name` in the unstripped `raw` source, with the attribute
+/// and comment lines directly above it. Braces inside literals may
+/// throw the end off; good enough for showing a test to a reader.
+pub fn fn_source(raw: &str, name: &str) -> Option<String> {
+    let mut pos = 0;
+    while let Some(found) = find_word(raw, "fn", pos) {
+        pos = found + 2;
+        let after = raw[pos..].trim_start();
+        if !after.strip_prefix(name).is_some_and(|rest| {
+            !rest.starts_with(|c: char| c.is_alphanumeric() || c == '_')
+        }) {
+            continue;
+        }
+        let open = pos + body_start(&raw[pos..])?;
+        let close = matching_brace(raw, open)?;
+        let mut start = raw[..found].rfind('\n').map_or(0, |p| p + 1);
+        while start > 0 {
+            let prev = raw[..start - 1].rfind('\n').map_or(0, |p| p + 1);
+            let line = raw[prev..start - 1].trim();
+            if !(line.is_empty() || line.starts_with("#[") || line.starts_with("//")) {
+                break;
+            }
+            start = prev;
+        }
+        return Some(raw[start..=close].trim().to_string());
+    }
+    None
+}
+
+/// Names that `use <krate>::…;` statements bring into scope, and whether
+/// one of them is a glob.
+pub fn imports_from(code: &str, krate: &str) -> (Vec<String>, bool) {
+    let mut names = Vec::new();
+    let mut glob = false;
+    let mut pos = 0;
+    while let Some(at) = find_word(code, "use", pos) {
+        let end = code[at..].find(';').map_or(code.len(), |e| at + e);
+        let tree = code[at + 3..end].trim();
+        pos = end;
+        let Some(rest) = tree.strip_prefix(krate).and_then(|r| r.trim_start().strip_prefix("::"))
+        else {
+            continue;
+        };
+        glob |= rest.contains('*');
+        names.extend(words(rest).filter(|w| !matches!(*w, "self" | "as")).map(str::to_string));
+    }
+    (names, glob)
+}
+
+/// `(kind, name)` of the `pub` items a crate root defines (`fn`,
+/// `struct`, …) or re-exports (kind `use`), sorted.
+pub fn pub_items(code: &str) -> Vec<(String, String)> {
+    const KINDS: [&str; 9] =
+        ["fn", "struct", "enum", "trait", "type", "const", "static", "mod", "union"];
+    let tokens: Vec<&str> = words(code).collect();
+    let mut items = Vec::new();
+    for (i, w) in tokens.iter().enumerate() {
+        if *w != "pub" {
+            continue;
+        }
+        // skip qualifiers such as `unsafe`, `async` or `const fn`; `pub(crate)`
+        // and friends are not public
+        let mut j = i + 1;
+        while let Some(&t) = tokens.get(j) {
+            let qualifier = matches!(t, "unsafe" | "async" | "extern")
+                || (t == "const"
+                    && tokens.get(j + 1).is_some_and(|n| matches!(*n, "fn" | "unsafe" | "async")));
+            if !qualifier {
+                break;
+            }
+            j += 1;
+        }
+        let (Some(&kind), Some(&name)) = (tokens.get(j), tokens.get(j + 1)) else { continue };
+        if j == i + 1 && matches!(kind, "crate" | "super" | "self" | "in") {
+            continue;
+        }
+        if KINDS.contains(&kind) {
+            items.push((kind.to_string(), name.to_string()));
+        }
+    }
+    // `pub use a::{b, c as d};` exports `b` and `d`
+    let mut pos = 0;
+    while let Some(at) = code[pos..].find("pub use ") {
+        let at = pos + at;
+        let end = code[at..].find(';').map_or(code.len(), |e| at + e);
+        let tree = &code[at + 8..end];
+        for part in tree.split([',', '{', '}']) {
+            if part.trim_end().ends_with("::") {
+                continue; // a path prefix, not a name
+            }
+            let last = words(part).last();
+            if let Some(name) = last.filter(|n| !matches!(*n, "self" | "crate" | "super")) {
+                items.push(("use".to_string(), name.to_string()));
+            }
+        }
+        pos = end;
+    }
+    items.sort();
+    items.dedup();
+    items
+}
+
+/// `.rs` files under `dir`, relative to `root`.
+fn rust_files(root: &Path, dir: &Path, out: &mut Vec<String>) {
+    let Ok(entries) = fs::read_dir(dir) else { return };
+    for path in entries.filter_map(Result::ok).map(|e| e.path()) {
+        if path.is_dir() {
+            rust_files(root, &path, out);
+        } else if path.extension().is_some_and(|e| e == "rs") {
+            if let Ok(rel) = path.strip_prefix(root) {
+                out.push(rel.display().to_string());
+            }
+        }
+    }
+}
+
+/// The extracted crate's library code and its test code, each file after
+/// the other: everything under `src/` but the binary (`src/main.rs`,
+/// `src/bin/`) and the `# testutil` copy, `src/lib.rs` first; and
+/// everything under `tests/`, `tests/integration.rs` first.
+pub fn read_crate(workspace: &Path) -> (String, String) {
+    let read = |dir: &str, first: &str, skip: &dyn Fn(&str) -> bool| {
+        let mut files = Vec::new();
+        rust_files(workspace, &workspace.join(dir), &mut files);
+        files.retain(|f| !skip(f));
+        files.sort_by_key(|f| (f != first, f.clone()));
+        files
+            .iter()
+            .filter_map(|f| fs::read_to_string(workspace.join(f)).ok())
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    let lib = read("src", "src/lib.rs", &|f| {
+        f == "src/main.rs" || f == "src/test_support.rs" || f.starts_with("src/bin/")
+    });
+    let tests = read("tests", "tests/integration.rs", &|_| false);
+    (lib, tests)
+}
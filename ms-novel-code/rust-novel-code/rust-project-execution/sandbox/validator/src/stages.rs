@@ -0,0 +1,955 @@
+//! A validation as an ordered list of stages behind one [`Checker`]
+//! trait: extracting, building, running the suite and reporting it are
+//! stages like the checks that hang off them, each in the [`Phase`] it
+//! needs. `validator.toml` (see [`crate::config`]) can disable or reorder
+//! the checks, for every task or per task; the core stages always run.
+//!
+//! With the `external-stages` feature, `[[stages.external]]` entries add
+//! third-party checks: a command run in the workspace whose exit status
+//! is the verdict.
+
+use crate::colors::*;
+use crate::config::{ExternalStage, StagePolicy};
+use crate::difficulty::{Estimate, Metrics};
+use crate::matrix::Classification;
+use crate::runs::{self, Runs};
+use crate::snapshot::Snapshots;
+use crate::{
+    audit, bundle, build_tests, cache, candidates, coverage, deps, diagnostics, expected, extract_env, fixtures,
+    hermetic, hermetic_env, history, interrupt, list_tests, lock, memory, msrv, nightly, patch, policy, prepare_workspace,
+    quality, reaper, redact, report, resources, shard, snapshot_tests, source, style, toolchains, traceability,
+    ub, Manifest, Notebook, RunOptions, Section, TEST_SEED_VAR,
+};
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+/// When a stage runs; the pipeline runs in this order.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Phase {
+    /// Writing the workspace.
+    Extract,
+    /// Building the tests.
+    Build,
+    /// Once the tests have built, before they run.
+    Built,
+    /// Running the suite.
+    Test,
+    /// After the runs, before the report: may still change outcomes.
+    Ran,
+    /// The consistency table and what the runs found.
+    Report,
+    /// After the report.
+    Tested,
+}
+
+/// The command-line switches the stages consult.
+pub struct Flags {
+    /// Sections to extract.
+    pub sections: Vec<Section>,
+    /// `--clean`: extract into a fresh workspace.
+    pub clean: bool,
+    /// `--crate-policy`.
+    pub crate_policy: Option<PathBuf>,
+    /// `--git-snapshots`.
+    pub git_snapshots: bool,
+    pub fixture_cache: PathBuf,
+    pub offline: bool,
+    /// `--apply-patch`.
+    pub apply_patch: Option<PathBuf>,
+    /// `--nightly`, unless the manifest says otherwise.
+    pub nightly: nightly::Policy,
+    /// `--redact`, or the `--redact-rules` implying it.
+    pub redact: bool,
+    pub redact_rules: Option<PathBuf>,
+    /// Timeouts, limits and the like of every cargo run; the context
+    /// supplies `env` and `tests` (see [`Context::opts`]).
+    pub options: RunOptions<'static>,
+    /// `--runs`, per `--seed-matrix` seed.
+    pub runs: usize,
+    pub jobs: usize,
+    pub max_test_time: Option<f64>,
+    pub timeout_retry: Option<u64>,
+    pub stress_cpu: Option<usize>,
+    /// Base of the per-run shuffle seeds: run k uses `seed + k - 1`.
+    pub shuffle_seed: Option<u64>,
+    pub seed_matrix: Option<u64>,
+    pub shard: Option<shard::Shard>,
+    pub resource_html: Option<PathBuf>,
+    pub template_dir: Option<PathBuf>,
+    /// `--history`, whose recent validations mark historically flaky
+    /// tests.
+    pub history: Option<PathBuf>,
+    /// `--flake-window`: how many of them.
+    pub flake_window: usize,
+    pub flake_policy: history::FlakePolicy,
+    pub review_snapshots: bool,
+    pub accept_snapshots: bool,
+    /// `--miri` or `--sanitizer`.
+    pub ub: Option<ub::Mode>,
+    /// `--hermeticity`: run the hermeticity probes.
+    pub hermeticity: bool,
+    /// `--measure-memory`: measure each test's peak RSS.
+    pub measure_memory: bool,
+    /// `--install-msrv`: let rustup fetch the MSRV toolchain.
+    pub install_msrv: bool,
+    /// `--lint`, with its `--lint-policy`.
+    pub lint: Option<style::Gate>,
+    /// `--coverage`, with its `--min-coverage` (0 without one).
+    pub coverage: Option<f64>,
+    pub toolchains: Vec<String>,
+    pub candidates: Option<PathBuf>,
+}
+
+/// The validation so far, which each stage reads and adds to.
+pub struct Context<'a> {
+    pub notebook: &'a Path,
+    pub nb: &'a Notebook,
+    pub manifest: &'a Manifest,
+    pub workspace: &'a Path,
+    pub flags: &'a Flags,
+    /// With `validator run-bundle`, the bundle standing in for the network.
+    pub bundle: Option<&'a bundle::Unpacked>,
+
+    /// Extracted files, relative to the workspace.
+    pub files: Vec<String>,
+    /// The workspace as extracted, before building.
+    pub extracted: audit::Snapshot,
+    /// Environment of every cargo run.
+    pub env: Vec<(String, String)>,
+    pub output_cases: Vec<expected::Case>,
+    /// The nightly toolchain the code builds with, and what needs it.
+    pub nightly: Option<(String, Vec<String>)>,
+    pub redactor: redact::Redactor,
+    pub snapshots: Option<Snapshots<'a>>,
+    /// A target directory shared with other workspaces.
+    pub shared_target: Option<PathBuf>,
+    _target_lock: Option<lock::Lock>,
+
+    pub build_time: Option<Duration>,
+    /// Compile errors of a failed build, for `--digest`.
+    pub build_errors: Vec<diagnostics::CompileError>,
+
+    /// With `--shard`, the tests it runs.
+    pub shard_tests: Option<Vec<String>>,
+    pub runs: Runs,
+    /// The report as printed, for `--upload`.
+    pub report: String,
+}
+
+impl<'a> Context<'a> {
+    pub fn new(
+        notebook: &'a Path,
+        nb: &'a Notebook,
+        manifest: &'a Manifest,
+        workspace: &'a Path,
+        flags: &'a Flags,
+        bundle: Option<&'a bundle::Unpacked>,
+    ) -> Self {
+        Context {
+            notebook,
+            nb,
+            manifest,
+            workspace,
+            flags,
+            bundle,
+            files: Vec::new(),
+            extracted: audit::Snapshot::new(),
+            env: Vec::new(),
+            output_cases: Vec::new(),
+            nightly: None,
+            redactor: redact::Redactor::default(),
+            snapshots: None,
+            shared_target: None,
+            _target_lock: None,
+            build_time: None,
+            build_errors: Vec::new(),
+            shard_tests: None,
+            runs: Runs::default(),
+            report: String::new(),
+        }
+    }
+
+    /// Options of a cargo run: the flags' with the environment and the
+    /// shard's tests.
+    pub fn opts(&self) -> RunOptions<'_> {
+        RunOptions { env: &self.env, tests: self.shard_tests.as_deref(), ..self.flags.options }
+    }
+
+    /// With `--git-snapshots`, commit the workspace as it is now.
+    fn snapshot(&self, message: &str) -> Result<(), String> {
+        self.snapshots.as_ref().map_or(Ok(()), |s| s.commit(message)).map_err(|e| format!("snapshot: {}", e))
+    }
+}
+
+/// One stage of the pipeline.
+pub trait Checker {
+    /// Name in `validator.toml` and in error messages.
+    fn name(&self) -> &str;
+    fn phase(&self) -> Phase;
+    /// Whether the flags and manifest ask for this stage at all.
+    fn applies(&self, _cx: &Context) -> bool {
+        true
+    }
+    /// Whether the stage can run here (toolchains, kernel support),
+    /// asked once the workspace is extracted and before anything builds.
+    fn probe(&self, _cx: &Context) -> Result<(), String> {
+        Ok(())
+    }
+    /// Run the stage and print its findings; returns whether it passed.
+    fn check(&self, cx: &mut Context) -> Result<bool, String>;
+}
+
+struct Extract;
+
+impl Checker for Extract {
+    fn name(&self) -> &str {
+        "extract"
+    }
+    fn phase(&self) -> Phase {
+        Phase::Extract
+    }
+    fn check(&self, cx: &mut Context) -> Result<bool, String> {
+        interrupt::preparing(Some(cx.workspace));
+        let refreshed = prepare(cx);
+        interrupt::preparing(None);
+        let refreshed = refreshed?;
+        println!("{}Workspace:{} {} ({})", BOLD, RESET, cx.workspace.display(), cx.files.join(", "));
+        match refreshed {
+            Some(written) if written.is_empty() => println!("  reused, unchanged"),
+            Some(written) => println!("  reused, rewrote {}", written.join(", ")),
+            None => {}
+        }
+
+        let flags = cx.flags;
+        let mut env = extract_env(cx.nb, cx.manifest)?;
+        if let Some(bundle) = cx.bundle {
+            bundle.install(cx.workspace, &mut env).map_err(|e| format!("bundle: {}", e))?;
+        }
+        cx.output_cases = expected::cases(cx.nb)?;
+        let nightly_uses = nightly::detect(cx.workspace);
+        if !nightly_uses.is_empty() {
+            let policy = cx.manifest.nightly.as_deref().map_or(Ok(flags.nightly.clone()), nightly::Policy::parse);
+            match policy.map_err(|e| format!("manifest `nightly`: {}", e))? {
+                nightly::Policy::Reject => {
+                    return Err(format!(
+                        "the code needs nightly Rust ({}); pass --nightly <toolchain> or set `nightly` \
+                         in the manifest to build it with a pinned nightly",
+                        nightly_uses.join(", ")
+                    ))
+                }
+                nightly::Policy::Route(toolchain) => {
+                    if !msrv::installed(&toolchain) {
+                        return Err(format!(
+                            "{} is not installed (`validator doctor --toolchain {} --install` adds it)",
+                            toolchain, toolchain
+                        ));
+                    }
+                    println!("{}Nightly:{} {}; building with {}", BOLD, RESET, nightly_uses.join(", "), toolchain);
+                    // a toolchain the notebook pins itself wins
+                    if !env.iter().any(|(k, _)| k == "RUSTUP_TOOLCHAIN") {
+                        env.push(("RUSTUP_TOOLCHAIN".to_string(), toolchain.clone()));
+                    }
+                    cx.nightly = Some((toolchain, nightly_uses));
+                }
+            }
+        }
+        if flags.offline && !env.iter().any(|(k, _)| k == "CARGO_NET_OFFLINE") {
+            env.push(("CARGO_NET_OFFLINE".to_string(), "true".to_string()));
+        }
+        // a target directory shared between workspaces gets its own lock
+        cx.shared_target = env
+            .iter()
+            .rev()
+            .find(|(k, _)| k == "CARGO_TARGET_DIR")
+            .map(|(_, v)| PathBuf::from(v))
+            .or_else(|| std::env::var_os("CARGO_TARGET_DIR").map(PathBuf::from));
+        if let Some(dir) = &cx.shared_target {
+            cx._target_lock = Some(lock::acquire(&cx.workspace.join(dir))?);
+        }
+        let hermetic = flags.options.hermetic;
+        cx.env = if hermetic { hermetic_env(&env) } else { env };
+        if flags.redact || flags.redact_rules.is_some() {
+            cx.redactor = redact::Redactor::new(flags.redact_rules.as_deref(), cx.workspace, &cx.env)
+                .map_err(|e| format!("redaction: {}", e))?;
+        }
+        if !cx.env.is_empty() {
+            let mode = if hermetic { " (hermetic, complete)" } else { "" };
+            println!("{}Environment{}:{}", BOLD, mode, RESET);
+            for (key, value) in &cx.env {
+                println!("  {}={}", key, value);
+            }
+        }
+        cx.extracted = audit::snapshot(cx.workspace)?;
+        Ok(true)
+    }
+}
+
+/// Write the workspace, then fixtures and patch, into `cx.files`;
+/// returns what a refresh rewrote, if it reused a workspace.
+fn prepare(cx: &mut Context) -> Result<Option<Vec<String>>, String> {
+    let (flags, workspace) = (cx.flags, cx.workspace);
+    let allowed = match &flags.crate_policy {
+        Some(path) => Some(policy::Policy::load(path).map_err(|e| format!("crate policy: {}", e))?),
+        None => None,
+    };
+    let allowed = allowed.map(|p| p.resolve(&cx.manifest.tags));
+    let refreshed = if flags.clean {
+        prepare_workspace(cx.nb, workspace, &flags.sections, allowed.as_ref())
+            .map(|files| cache::Refreshed { written: files.clone(), files, reused: false })?
+    } else {
+        cache::refresh(cx.nb, workspace, &flags.sections, allowed.as_ref())?
+    };
+    reaper::mark(workspace);
+    cx.files = refreshed.files;
+    if flags.git_snapshots {
+        cx.snapshots = Some(Snapshots::init(workspace).map_err(|e| format!("snapshot: {}", e))?);
+    }
+    cx.snapshot(&format!("Extract {}", cx.notebook.display()))?;
+    let fetched = fixtures::install(&cx.manifest.fixtures, workspace, &flags.fixture_cache, flags.offline)
+        .map_err(|e| format!("fixtures: {}", e))?;
+    if !fetched.is_empty() {
+        cx.snapshot(&format!("Install fixtures: {}", fetched.join(", ")))?;
+        cx.files.extend(fetched);
+    }
+    if let Some(diff) = &flags.apply_patch {
+        let patched = patch::apply(workspace, diff).map_err(|e| format!("patch: {}", e))?;
+        println!("{}Patched:{} {}", BOLD, RESET, patched.join(", "));
+        cx.snapshot(&format!("Apply {}", diff.display()))?;
+        for file in patched {
+            if !cx.files.contains(&file) {
+                cx.files.push(file);
+            }
+        }
+    }
+    Ok(refreshed.reused.then_some(refreshed.written))
+}
+
+struct Build;
+
+impl Checker for Build {
+    fn name(&self) -> &str {
+        "build"
+    }
+    fn phase(&self) -> Phase {
+        Phase::Build
+    }
+    fn probe(&self, cx: &Context) -> Result<(), String> {
+        cx.flags.options.limits.probe()
+    }
+    fn check(&self, cx: &mut Context) -> Result<bool, String> {
+        match build_tests(cx.workspace, &cx.opts()) {
+            Ok(took) => {
+                println!("{}Build completed in {:.2}s{}", BLUE, took.as_secs_f32(), RESET);
+                cx.build_time = Some(took);
+                Ok(true)
+            }
+            Err(e) => {
+                let message = e.to_string();
+                cx.build_errors = e.errors;
+                Err(message)
+            }
+        }
+    }
+}
+
+struct Test;
+
+impl Checker for Test {
+    fn name(&self) -> &str {
+        "test"
+    }
+    fn phase(&self) -> Phase {
+        Phase::Test
+    }
+    /// Records the runs; the report judges them.
+    fn check(&self, cx: &mut Context) -> Result<bool, String> {
+        let shard = cx.flags.shard;
+        if let Some(shard) = shard {
+            let all = list_tests(cx.workspace, &cx.opts())?;
+            let mine: Vec<String> = all.iter().filter(|t| shard.contains(t)).cloned().collect();
+            println!("{}Shard {}: {} of {} tests{}", BLUE, shard, mine.len(), all.len(), RESET);
+            cx.shard_tests = Some(mine);
+        }
+        // the end-to-end cases run with every run too, as tests of the matrix
+        let cases: Vec<expected::Case> = cx
+            .output_cases
+            .iter()
+            .filter(|c| shard.is_none_or(|shard| shard.contains(&c.test_name())))
+            .cloned()
+            .collect();
+        let case_exe = match cases.is_empty() {
+            true => None,
+            false => Some(expected::binary(cx.workspace, &cx.opts())?),
+        };
+        runs::execute(cx, &cases, case_exe.as_deref())?;
+        Ok(true)
+    }
+}
+
+struct UndefinedBehavior;
+
+impl Checker for UndefinedBehavior {
+    fn name(&self) -> &str {
+        "ub"
+    }
+    fn phase(&self) -> Phase {
+        Phase::Ran
+    }
+    fn applies(&self, cx: &Context) -> bool {
+        cx.flags.ub.is_some()
+    }
+    fn probe(&self, cx: &Context) -> Result<(), String> {
+        cx.flags.ub.map_or(Ok(()), |mode| ub::probe(mode, &cx.opts()))
+    }
+    /// Findings make their tests unsound, which the report judges.
+    fn check(&self, cx: &mut Context) -> Result<bool, String> {
+        let Some(mode) = cx.flags.ub else { return Ok(true) };
+        let findings = ub::check(mode, cx.workspace, &cx.opts()).map_err(|e| format!("{}: {}", mode, e))?;
+        for f in findings {
+            cx.runs.failure_outputs.insert(f.test.clone(), f.details);
+            cx.runs.matrix.mark_unsound(&f.test, f.summary);
+        }
+        Ok(true)
+    }
+}
+
+struct Report;
+
+impl Checker for Report {
+    fn name(&self) -> &str {
+        "report"
+    }
+    fn phase(&self) -> Phase {
+        Phase::Report
+    }
+    /// The only stage that runs after an interrupt, to show what the
+    /// runs got through.
+    fn check(&self, cx: &mut Context) -> Result<bool, String> {
+        let flags = cx.flags;
+        let (mut rows, totals) = report::rows(&cx.runs.matrix);
+        let historical = historically_flaky(cx);
+        for row in &mut rows {
+            row.historically_flaky = historical.contains(&row.test);
+        }
+        let mut text = report::render(flags.template_dir.as_deref(), &rows, &totals)?;
+        if !cx.runs.usage.is_empty() {
+            text += &format!("\n{}", resources::summary(&cx.runs.usage));
+            write_resource_html(flags.resource_html.as_deref(), cx.notebook, &cx.runs.usage);
+        }
+        print!("{}", text);
+        cx.report = text;
+        if interrupt::interrupted() {
+            println!("{}Interrupted after {} of {} runs; this report is partial{}",
+                     RED, cx.runs.completed, cx.runs.total, RESET);
+            return Ok(false);
+        }
+
+        let (lib, _) = source::read_crate(cx.workspace);
+        let metrics = Metrics::new(&lib, cx.build_time.unwrap_or_default(), cx.runs.matrix.len());
+        let estimate = Estimate::from_metrics(&metrics);
+        println!("\nDifficulty estimate: {} from {} LOC, complexity {}, {} tests, {:.1}s build",
+                 estimate, metrics.loc, metrics.complexity, metrics.tests,
+                 metrics.build_time.as_secs_f32());
+        if let Some(declared) = &cx.manifest.difficulty {
+            if estimate.diverges_from(declared) {
+                println!("{}Declared difficulty `{}` diverges from the estimate{}", RED, declared, RESET);
+            } else {
+                println!("Declared difficulty: {}", declared);
+            }
+        }
+
+        let runs = &mut cx.runs;
+        if !runs.shuffled_failures.is_empty() {
+            println!("\n{}Failing shuffled runs:{}", BOLD, RESET);
+            for (run, seed, failed) in &runs.shuffled_failures {
+                println!("  run {} (seed {}): {}", run, seed, failed.join(", "));
+                println!("    reproduce: RUSTC_BOOTSTRAP=1 cargo test -- -Z unstable-options --shuffle-seed {}", seed);
+            }
+        }
+
+        let pending = snapshot_tests::pending(cx.workspace);
+        if !pending.is_empty() {
+            snapshot_tests::print(&pending, flags.review_snapshots);
+            if flags.accept_snapshots {
+                snapshot_tests::accept(cx.notebook, &pending).map_err(|e| format!("snapshot: {}", e))?;
+                println!("{}Accepted {} snapshot(s) into {}{}", GREEN, pending.len(), cx.notebook.display(), RESET);
+            }
+        }
+
+        runs.violations.sort_by(|a, b| a.0.cmp(&b.0));
+        if !runs.violations.is_empty() {
+            println!("\n{}Sandbox violations:{}", BOLD, RESET);
+            for (test, violation) in &runs.violations {
+                println!("  {}{}{} {}", RED, test, RESET, violation);
+            }
+        }
+        let violations = &runs.violations;
+        runs.crashes.retain(|c| !violations.iter().any(|(t, _)| *t == c.test));
+
+        if !runs.crashes.is_empty() {
+            println!("\n{}Crashes:{}", BOLD, RESET);
+            for c in &runs.crashes {
+                println!("  {}{}{} killed by {}", RED, c.test, RESET, c.signal);
+                if let Some(core) = &c.core {
+                    println!("    core dump: {}", core.display());
+                }
+                for line in c.details.lines() {
+                    println!("    {}", line);
+                }
+            }
+        }
+
+        if !runs.marginal.is_empty() {
+            println!("\n{}Timeout-marginal:{} runs that only finished with a longer timeout", BOLD, RESET);
+            for (run, took, slow) in &runs.marginal {
+                println!("  run {}: {:.1}s; {}", run, took.as_secs_f32(), slow.join(", "));
+            }
+        }
+
+        if !runs.seed_failures.is_empty() {
+            println!("\n{}Seed matrix:{}", BOLD, RESET);
+            for (seed, failed) in &runs.seed_failures {
+                if failed.is_empty() {
+                    println!("  seed {}: {}all passed{}", seed, GREEN, RESET);
+                } else {
+                    let failed: Vec<&str> = failed.iter().map(String::as_str).collect();
+                    println!("  seed {}: {}failed{} {}", seed, RED, RESET, failed.join(", "));
+                    println!("    reproduce: {}={} cargo test", TEST_SEED_VAR, seed);
+                }
+            }
+        }
+
+        // known-flaky tests may fail or flake without failing the validation;
+        // undefined behavior is never excused
+        let mut counted = totals;
+        if flags.flake_policy == history::FlakePolicy::Exclude {
+            counted = report::Totals { pass: 0, fail: 0, flaky: 0, unsound: 0 };
+            let mut excused = Vec::new();
+            for (test, classification) in runs.matrix.classified() {
+                match classification {
+                    Classification::Fail | Classification::Flaky if historical.contains(test) => excused.push(test),
+                    _ => counted.count(classification),
+                }
+            }
+            if !excused.is_empty() {
+                println!("\n{}Not counted:{} historically flaky under --flake-policy exclude: {}",
+                         BOLD, RESET, excused.join(", "));
+            }
+        }
+
+        Ok(counted.fail == 0 && counted.flaky == 0 && counted.unsound == 0 && runs.marginal.is_empty()
+            && runs.violations.is_empty())
+    }
+}
+
+/// With `--history`, the tests whose outcome flipped in the notebook's
+/// last `--flake-window` recorded validations; none when the database
+/// cannot say.
+fn historically_flaky(cx: &Context) -> BTreeSet<String> {
+    let Some(path) = &cx.flags.history else { return BTreeSet::new() };
+    let flipped = history::notebook_hash(cx.notebook)
+        .and_then(|hash| history::History::open(path)?.flipped(&hash, cx.flags.flake_window));
+    flipped.unwrap_or_else(|e| {
+        eprintln!("{}History error:{} {}", RED, RESET, e);
+        BTreeSet::new()
+    })
+}
+
+/// `--resource-html`: write the page, reporting (not failing on) errors.
+pub(crate) fn write_resource_html(path: Option<&Path>, notebook: &Path, usage: &[resources::Series]) {
+    let Some(path) = path else { return };
+    match resources::write_html(path, &notebook.display().to_string(), usage) {
+        Ok(()) => println!("{}Resource usage written to {}{}", BLUE, path.display(), RESET),
+        Err(e) => eprintln!("{}Resource report error:{} {}", RED, RESET, e),
+    }
+}
+
+struct UnusedDeps;
+
+impl Checker for UnusedDeps {
+    fn name(&self) -> &str {
+        "unused-deps"
+    }
+    fn phase(&self) -> Phase {
+        Phase::Built
+    }
+    /// Advisory: reports, never fails.
+    fn check(&self, cx: &mut Context) -> Result<bool, String> {
+        match deps::unused(cx.workspace) {
+            Ok(unused) if unused.is_empty() => {}
+            Ok(unused) => println!("{}Unused dependencies:{} {}", RED, RESET, unused.join(", ")),
+            Err(e) => eprintln!("{}Dependency check error:{} {}", RED, RESET, e),
+        }
+        Ok(true)
+    }
+}
+
+struct BuildScriptAudit;
+
+impl Checker for BuildScriptAudit {
+    fn name(&self) -> &str {
+        "build-script-audit"
+    }
+    fn phase(&self) -> Phase {
+        Phase::Built
+    }
+    fn check(&self, cx: &mut Context) -> Result<bool, String> {
+        audit::check(cx.workspace, &cx.extracted, &cx.opts())
+    }
+}
+
+struct Quality;
+
+impl Checker for Quality {
+    fn name(&self) -> &str {
+        "quality"
+    }
+    fn phase(&self) -> Phase {
+        Phase::Tested
+    }
+    fn check(&self, cx: &mut Context) -> Result<bool, String> {
+        let (lib, tests) = source::read_crate(cx.workspace);
+        let report = quality::Report::new(&tests, &lib);
+        Ok(report.print(&cx.manifest.quality, cx.runs.matrix.as_map(), cx.runs.total, cx.flags.shard.is_some()))
+    }
+}
+
+struct Lint;
+
+impl Checker for Lint {
+    fn name(&self) -> &str {
+        "lint"
+    }
+    fn phase(&self) -> Phase {
+        Phase::Tested
+    }
+    fn applies(&self, cx: &Context) -> bool {
+        cx.flags.lint.is_some()
+    }
+    fn check(&self, cx: &mut Context) -> Result<bool, String> {
+        style::check(cx.workspace, cx.flags.lint.unwrap_or(style::Gate::Fatal), &cx.opts())
+    }
+}
+
+struct Traceability;
+
+impl Checker for Traceability {
+    fn name(&self) -> &str {
+        "traceability"
+    }
+    fn phase(&self) -> Phase {
+        Phase::Tested
+    }
+    fn applies(&self, cx: &Context) -> bool {
+        !cx.manifest.requirements.is_empty()
+    }
+    /// Advisory: uncovered requirements are warnings.
+    fn check(&self, cx: &mut Context) -> Result<bool, String> {
+        let (lib, tests) = source::read_crate(cx.workspace);
+        let sharded = cx.flags.shard.is_some();
+        traceability::print(&cx.manifest.requirements, cx.runs.matrix.as_map(), &[&tests, &lib], sharded);
+        Ok(true)
+    }
+}
+
+struct Hermeticity;
+
+impl Checker for Hermeticity {
+    fn name(&self) -> &str {
+        "hermeticity"
+    }
+    fn phase(&self) -> Phase {
+        Phase::Tested
+    }
+    fn applies(&self, cx: &Context) -> bool {
+        cx.flags.hermeticity
+    }
+    fn check(&self, cx: &mut Context) -> Result<bool, String> {
+        hermetic::check(cx.workspace, cx.runs.matrix.as_map(), &cx.opts())
+    }
+}
+
+struct Memory;
+
+impl Checker for Memory {
+    fn name(&self) -> &str {
+        "memory"
+    }
+    fn phase(&self) -> Phase {
+        Phase::Tested
+    }
+    fn applies(&self, cx: &Context) -> bool {
+        cx.flags.measure_memory
+    }
+    fn check(&self, cx: &mut Context) -> Result<bool, String> {
+        memory::check(cx.workspace, cx.manifest.memory_budget_mb, &cx.opts())
+    }
+}
+
+struct ExpectedOutput;
+
+impl Checker for ExpectedOutput {
+    fn name(&self) -> &str {
+        "expected-output"
+    }
+    fn phase(&self) -> Phase {
+        Phase::Tested
+    }
+    fn applies(&self, cx: &Context) -> bool {
+        !cx.output_cases.is_empty()
+    }
+    /// The cases ran with every run; this shows the diffs of one more.
+    fn check(&self, cx: &mut Context) -> Result<bool, String> {
+        expected::check(cx.workspace, &cx.output_cases, &cx.manifest.output, &cx.opts())
+    }
+}
+
+struct Coverage;
+
+impl Checker for Coverage {
+    fn name(&self) -> &str {
+        "coverage"
+    }
+    fn phase(&self) -> Phase {
+        Phase::Tested
+    }
+    fn applies(&self, cx: &Context) -> bool {
+        cx.flags.coverage.is_some()
+    }
+    fn probe(&self, cx: &Context) -> Result<(), String> {
+        coverage::probe(&cx.opts())
+    }
+    fn check(&self, cx: &mut Context) -> Result<bool, String> {
+        coverage::check(cx.workspace, cx.flags.coverage.unwrap_or(0.0), &cx.opts())
+    }
+}
+
+struct Msrv;
+
+impl Checker for Msrv {
+    fn name(&self) -> &str {
+        "msrv"
+    }
+    fn phase(&self) -> Phase {
+        Phase::Tested
+    }
+    fn applies(&self, cx: &Context) -> bool {
+        cx.manifest.msrv.is_some()
+    }
+    fn check(&self, cx: &mut Context) -> Result<bool, String> {
+        let version = cx.manifest.msrv.as_deref().unwrap_or_default();
+        msrv::check(cx.workspace, version, cx.flags.install_msrv, &cx.opts())
+    }
+}
+
+struct Toolchains;
+
+impl Checker for Toolchains {
+    fn name(&self) -> &str {
+        "toolchains"
+    }
+    fn phase(&self) -> Phase {
+        Phase::Tested
+    }
+    fn applies(&self, cx: &Context) -> bool {
+        !cx.flags.toolchains.is_empty()
+    }
+    /// Advisory: divergences are for the reader to judge.
+    fn check(&self, cx: &mut Context) -> Result<bool, String> {
+        toolchains::compare(cx.workspace, &cx.flags.toolchains, cx.flags.runs, &cx.opts());
+        Ok(true)
+    }
+}
+
+struct Candidates;
+
+impl Checker for Candidates {
+    fn name(&self) -> &str {
+        "candidates"
+    }
+    fn phase(&self) -> Phase {
+        Phase::Tested
+    }
+    fn applies(&self, cx: &Context) -> bool {
+        cx.flags.candidates.is_some()
+    }
+    /// Advisory: ranks the candidates, whichever fail.
+    fn check(&self, cx: &mut Context) -> Result<bool, String> {
+        let Some(dir) = &cx.flags.candidates else { return Ok(true) };
+        candidates::compare(cx.workspace, dir, cx.flags.runs, &cx.opts())?;
+        Ok(true)
+    }
+}
+
+#[cfg(feature = "external-stages")]
+struct External(ExternalStage);
+
+#[cfg(feature = "external-stages")]
+impl Checker for External {
+    fn name(&self) -> &str {
+        &self.0.name
+    }
+    fn phase(&self) -> Phase {
+        if self.0.after == "build" {
+            Phase::Built
+        } else {
+            Phase::Tested
+        }
+    }
+    fn check(&self, cx: &mut Context) -> Result<bool, String> {
+        use wait_timeout::ChildExt;
+
+        let opts = cx.opts();
+        let (program, args) = self.0.command.split_first().ok_or("empty `command`")?;
+        println!("{}{}{}", BLUE, self.0.name, RESET);
+        let mut child = interrupt::spawn(opts.command(program).args(args).current_dir(cx.workspace))
+            .map_err(|e| format!("{}: {}", program, e))?;
+        let status = child.wait_timeout(Duration::from_secs(opts.timeout)).map_err(|e| e.to_string())?;
+        let status = match status {
+            Some(status) => status,
+            None => {
+                interrupt::kill(&child);
+                let _ = child.wait();
+                interrupt::reap(&child);
+                return Err(format!("timeout reached ({}s)", opts.timeout));
+            }
+        };
+        interrupt::reap(&child);
+        if !status.success() {
+            println!("  {}failed{} ({})", RED, RESET, status);
+        }
+        Ok(status.success())
+    }
+}
+
+fn external(stage: &ExternalStage) -> Result<Box<dyn Checker>, String> {
+    if stage.after != "build" && stage.after != "tests" {
+        return Err(format!("stage `{}`: `after` is `build` or `tests`, not `{}`", stage.name, stage.after));
+    }
+    #[cfg(feature = "external-stages")]
+    return Ok(Box::new(External(stage.clone())));
+    #[cfg(not(feature = "external-stages"))]
+    Err(format!("stage `{}`: this validator was built without the `external-stages` feature", stage.name))
+}
+
+/// Stages every validation runs, which `disable` cannot name.
+const CORE: [&str; 4] = ["extract", "build", "test", "report"];
+
+/// The stages `policy` selects, in the order they run.
+pub fn pipeline(policy: &StagePolicy) -> Result<Vec<Box<dyn Checker>>, String> {
+    let mut stages: Vec<Box<dyn Checker>> = vec![
+        Box::new(Extract),
+        Box::new(Build),
+        Box::new(UnusedDeps),
+        Box::new(BuildScriptAudit),
+        Box::new(Test),
+        Box::new(UndefinedBehavior),
+        Box::new(Report),
+        Box::new(Quality),
+        Box::new(Lint),
+        Box::new(Traceability),
+        Box::new(Hermeticity),
+        Box::new(Memory),
+        Box::new(ExpectedOutput),
+        Box::new(Coverage),
+        Box::new(Msrv),
+        Box::new(Toolchains),
+        Box::new(Candidates),
+    ];
+    for stage in &policy.external {
+        if stages.iter().any(|s| s.name() == stage.name) {
+            return Err(format!("stage `{}` is defined twice", stage.name));
+        }
+        stages.push(external(stage)?);
+    }
+    for name in policy.order.iter().chain(&policy.disable).chain(&policy.enable) {
+        if !stages.iter().any(|s| s.name() == name) {
+            let known: Vec<&str> = stages.iter().map(|s| s.name()).collect();
+            return Err(format!("unknown stage `{}` (stages: {})", name, known.join(", ")));
+        }
+    }
+    if let Some(core) = policy.disable.iter().find(|d| CORE.contains(&d.as_str())) {
+        return Err(format!("stage `{}` cannot be disabled", core));
+    }
+    stages.retain(|s| !policy.disable.iter().any(|d| d == s.name()));
+    // stable, so the stages `order` leaves out keep their usual order
+    stages.sort_by_key(|s| (s.phase(), policy.order.iter().position(|o| o == s.name()).unwrap_or(usize::MAX)));
+    Ok(stages)
+}
+
+/// A stage that could not run at all, which ends the validation.
+pub struct StageError {
+    pub stage: String,
+    pub message: String,
+}
+
+/// How a validation that ran every stage went.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Outcome {
+    Passed,
+    Failed,
+    /// Ctrl-C during the runs; only the report ran after it.
+    Interrupted,
+}
+
+/// Run the stages that apply, in order, probing the later ones once the
+/// workspace is extracted. Returns how the validation went, or the first
+/// stage that could not run.
+pub fn run(stages: &[Box<dyn Checker>], cx: &mut Context) -> Result<Outcome, StageError> {
+    fn error(stage: &dyn Checker) -> impl FnOnce(String) -> StageError + '_ {
+        move |message| StageError { stage: stage.name().to_string(), message }
+    }
+    let mut passed = true;
+    let mut probed = false;
+    for stage in stages {
+        if stage.phase() > Phase::Extract && !probed {
+            for later in stages.iter().filter(|s| s.phase() > Phase::Extract && s.applies(cx)) {
+                later.probe(cx).map_err(error(later.as_ref()))?;
+            }
+            probed = true;
+        }
+        if interrupt::interrupted() && stage.phase() != Phase::Report || !stage.applies(cx) {
+            continue;
+        }
+        passed &= stage.check(cx).map_err(error(stage.as_ref()))?;
+    }
+    Ok(if interrupt::interrupted() {
+        Outcome::Interrupted
+    } else if passed {
+        Outcome::Passed
+    } else {
+        Outcome::Failed
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(policy: &StagePolicy) -> Vec<String> {
+        pipeline(policy).unwrap_or_else(|e| panic!("{}", e)).iter().map(|s| s.name().to_string()).collect()
+    }
+
+    #[test]
+    fn order_applies_within_each_phase() {
+        let policy = StagePolicy { order: vec!["msrv".into(), "build-script-audit".into()], ..StagePolicy::default() };
+        let names = names(&policy);
+        let at = |name: &str| names.iter().position(|n| n == name).unwrap();
+        assert!(at("build-script-audit") < at("unused-deps"));
+        assert!(at("msrv") < at("quality"));
+        // but never ahead of what its phase needs
+        assert!(at("build") < at("build-script-audit") && at("report") < at("msrv"));
+        assert_eq!(names[0], "extract");
+    }
+
+    #[test]
+    fn core_stages_cannot_be_disabled() {
+        let policy = StagePolicy { disable: vec!["build".into()], ..StagePolicy::default() };
+        assert_eq!(pipeline(&policy).err().as_deref(), Some("stage `build` cannot be disabled"));
+        let policy = StagePolicy { disable: vec!["lint".into()], ..StagePolicy::default() };
+        assert!(!names(&policy).contains(&"lint".to_string()));
+    }
+}
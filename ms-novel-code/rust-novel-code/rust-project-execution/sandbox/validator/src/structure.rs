@@ -0,0 +1,214 @@
+//! `--structure <spec.toml|spec.json>`: the layout every notebook of a
+//! batch must follow, checked before anything is extracted or built.
+//!
+//! ```toml
+//! # marker cells that must exist, in this order
+//! sections = ["Metadata", "Prompt", "Requirements", "lib", "test"]
+//! # `**Name** - value` fields the `# Metadata` cell must fill
+//! metadata = ["L1 Taxonomy", "L2 Taxonomy", "Subtopic"]
+//! # headings the statement must have, in this order
+//! headings = ["Constraints", "Example"]
+//! # reject statement lines copied from the `# lib` code
+//! solution_leak = true
+//!
+//! [[forbidden]]
+//! pattern = "```rust"
+//! sections = ["Prompt", "Requirements"]
+//! message = "code in the problem statement"
+//! ```
+//!
+//! Headings match case-insensitively by substring, so `Examples` and
+//! `## Example 1` both satisfy `Example`. Violations name the cell index.
+
+use crate::fix::marker;
+use crate::lint::{is_heading, statement};
+use crate::{extract_fenced_lines, Cell, Notebook};
+use serde::Deserialize;
+use std::{fmt, fs, path::Path};
+
+/// Shortest trimmed `# lib` line counted as a leak; shorter ones (`}`,
+/// `Ok(())`) turn up in any statement.
+const MIN_LEAK: usize = 20;
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Spec {
+    sections: Vec<String>,
+    metadata: Vec<String>,
+    headings: Vec<String>,
+    solution_leak: bool,
+    forbidden: Vec<Forbidden>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Forbidden {
+    pattern: String,
+    /// Sections it may not appear in; every cell when empty.
+    #[serde(default)]
+    sections: Vec<String>,
+    #[serde(default)]
+    message: Option<String>,
+}
+
+pub struct Violation {
+    pub cell: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.cell {
+            Some(cell) => write!(f, "cell {}: {}", cell, self.message),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+/// A cell's lines and the section its marker names, if any.
+fn cells(nb: &Notebook) -> Vec<(Option<&'static str>, &[String])> {
+    nb.cells
+        .iter()
+        .map(|cell| {
+            let (Cell::Markdown { source } | Cell::Code { source }) = cell;
+            let first = source.iter().flat_map(|l| l.lines()).find(|l| !l.trim().is_empty());
+            (first.and_then(marker), source.as_slice())
+        })
+        .collect()
+}
+
+/// Canonical spelling of a spec's section name.
+fn section(name: &str) -> Result<&'static str, String> {
+    marker(&format!("# {}", name)).ok_or_else(|| format!("unknown section `{}`", name))
+}
+
+/// The value of a `**Name** - value` (or `**Name**: value`) line.
+fn field<'a>(line: &'a str, name: &str) -> Option<&'a str> {
+    let rest = line.trim().strip_prefix("**")?;
+    let (key, value) = rest.split_once("**")?;
+    if !key.trim().trim_end_matches(':').trim().eq_ignore_ascii_case(name) {
+        return None;
+    }
+    Some(value.trim().trim_start_matches([':', '-', '–', '—']).trim())
+}
+
+impl Spec {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let raw = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let spec: Spec = if path.extension().is_some_and(|e| e == "json") {
+            serde_json::from_str(&raw).map_err(|e| format!("{}: {}", path.display(), e))?
+        } else {
+            toml::from_str(&raw).map_err(|e| format!("{}: {}", path.display(), e))?
+        };
+        let named = spec.sections.iter().chain(spec.forbidden.iter().flat_map(|f| &f.sections));
+        for name in named {
+            section(name).map_err(|e| format!("{}: {}", path.display(), e))?;
+        }
+        Ok(spec)
+    }
+
+    /// Every way `nb` departs from the spec, in cell order where it can.
+    pub fn check(&self, nb: &Notebook) -> Vec<Violation> {
+        let cells = cells(nb);
+        let mut found = Vec::new();
+        self.check_sections(&cells, &mut found);
+        self.check_metadata(&cells, &mut found);
+        self.check_headings(nb, &mut found);
+        if self.solution_leak {
+            check_leak(nb, &cells, &mut found);
+        }
+        self.check_forbidden(&cells, &mut found);
+        found
+    }
+
+    fn check_sections(&self, cells: &[(Option<&str>, &[String])], found: &mut Vec<Violation>) {
+        let mut previous: Option<(&str, usize)> = None;
+        for name in &self.sections {
+            let Ok(name) = section(name) else { continue };
+            let Some(at) = cells.iter().position(|(s, _)| *s == Some(name)) else {
+                found.push(Violation { cell: None, message: format!("no `# {}` cell", name) });
+                continue;
+            };
+            if let Some((before, before_at)) = previous.filter(|&(_, b)| b > at) {
+                found.push(Violation {
+                    cell: Some(at),
+                    message: format!("`# {}` comes before `# {}` (cell {})", name, before, before_at),
+                });
+            }
+            previous = Some((name, at));
+        }
+    }
+
+    fn check_metadata(&self, cells: &[(Option<&str>, &[String])], found: &mut Vec<Violation>) {
+        if self.metadata.is_empty() {
+            return;
+        }
+        let Some(at) = cells.iter().position(|(s, _)| *s == Some("Metadata")) else {
+            found.push(Violation { cell: None, message: "no `# Metadata` cell".to_string() });
+            return;
+        };
+        let lines: Vec<&str> = cells[at].1.iter().flat_map(|l| l.lines()).collect();
+        for name in &self.metadata {
+            match lines.iter().find_map(|l| field(l, name)) {
+                Some(value) if !value.is_empty() => {}
+                Some(_) => found.push(Violation { cell: Some(at), message: format!("`{}` is empty", name) }),
+                None => found.push(Violation { cell: Some(at), message: format!("no `**{}**` field", name) }),
+            }
+        }
+    }
+
+    fn check_headings(&self, nb: &Notebook, found: &mut Vec<Violation>) {
+        let headings: Vec<(usize, String)> = statement(nb)
+            .into_iter()
+            .filter(|(_, l)| is_heading(l) && marker(l).is_none())
+            .map(|(cell, l)| (cell, l.to_lowercase()))
+            .collect();
+        // the previous wanted heading and where it was
+        let mut previous: Option<(&str, usize)> = None;
+        for wanted in &self.headings {
+            let lower = wanted.to_lowercase();
+            match headings.iter().position(|(_, h)| h.contains(&lower)) {
+                None => found.push(Violation { cell: None, message: format!("statement has no `{}` heading", wanted) }),
+                Some(at) => {
+                    if let Some((before, _)) = previous.filter(|&(_, b)| b > at) {
+                        found.push(Violation {
+                            cell: Some(headings[at].0),
+                            message: format!("`{}` heading comes before `{}`", wanted, before),
+                        });
+                    }
+                    previous = Some((wanted, at));
+                }
+            }
+        }
+    }
+
+    fn check_forbidden(&self, cells: &[(Option<&str>, &[String])], found: &mut Vec<Violation>) {
+        for rule in &self.forbidden {
+            let within: Vec<&str> = rule.sections.iter().filter_map(|s| section(s).ok()).collect();
+            for (i, (sec, lines)) in cells.iter().enumerate() {
+                if !within.is_empty() && !sec.is_some_and(|s| within.contains(&s)) {
+                    continue;
+                }
+                if let Some(line) = lines.iter().flat_map(|l| l.lines()).find(|l| l.contains(&rule.pattern)) {
+                    let what = rule.message.clone().unwrap_or_else(|| format!("contains `{}`", rule.pattern));
+                    found.push(Violation { cell: Some(i), message: format!("{}: {}", what, line.trim()) });
+                }
+            }
+        }
+    }
+}
+
+/// Statement lines that repeat a non-trivial line of the `# lib` code.
+fn check_leak(nb: &Notebook, cells: &[(Option<&str>, &[String])], found: &mut Vec<Violation>) {
+    let Some((_, lib)) = cells.iter().find(|(s, _)| *s == Some("lib")) else { return };
+    let code: Vec<String> = extract_fenced_lines(lib, "rust")
+        .iter()
+        .map(|l| l.trim().to_string())
+        .filter(|l| l.len() >= MIN_LEAK && !l.starts_with("//"))
+        .collect();
+    for (cell, line) in statement(nb) {
+        if code.iter().any(|c| line.trim() == c) {
+            found.push(Violation { cell: Some(cell), message: format!("solution code in the statement: {}", line.trim()) });
+        }
+    }
+}
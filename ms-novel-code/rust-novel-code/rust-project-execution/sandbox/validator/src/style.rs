@@ -0,0 +1,171 @@
+//! `--lint`: `cargo clippy -- -D warnings` and `cargo fmt --check` over
+//! the extracted workspace, reported in the quality gate section.
+//! `--lint-policy` decides whether findings fail the validation or are
+//! only reported.
+
+use crate::colors::*;
+use crate::{interrupt, sandbox, RunOptions};
+use clap::ValueEnum;
+use serde_json::Value;
+use std::{
+    io::Read,
+    path::Path,
+    process::{Command, Stdio},
+    thread,
+    time::Duration,
+};
+use wait_timeout::ChildExt;
+
+#[derive(ValueEnum, Clone, Copy, PartialEq)]
+pub enum Gate {
+    /// Lint findings fail the validation.
+    Fatal,
+    /// Lint findings are reported only.
+    Advisory,
+}
+
+/// One clippy or rustc lint, denied by `-D warnings`.
+pub struct Finding {
+    /// Like `clippy::needless_return` or `unused_variables`.
+    pub lint: String,
+    pub message: String,
+    /// Workspace-relative file and 1-based line of the primary span.
+    pub location: Option<(String, usize)>,
+}
+
+/// The finding in one line of `cargo clippy --message-format=json`.
+fn finding(line: &str) -> Option<Finding> {
+    let msg: Value = serde_json::from_str(line).ok()?;
+    if msg["reason"] != "compiler-message" {
+        return None;
+    }
+    let diag = &msg["message"];
+    // the closing "aborting due to …" has no code
+    let lint = diag["code"]["code"].as_str()?;
+    let primary = diag["spans"]
+        .as_array()
+        .and_then(|spans| spans.iter().find(|s| s["is_primary"] == true));
+    Some(Finding {
+        lint: lint.to_string(),
+        message: diag["message"].as_str().unwrap_or_default().to_string(),
+        location: primary.and_then(|s| Some((s["file_name"].as_str()?.to_string(), s["line_start"].as_u64()? as usize))),
+    })
+}
+
+/// Run `cmd` to completion or `timeout`; returns whether it succeeded,
+/// its stdout and its stderr.
+fn run(cmd: &mut Command, timeout: u64) -> Result<(bool, String, String), String> {
+    let mut child = interrupt::spawn(cmd.stdout(Stdio::piped()).stderr(Stdio::piped())).map_err(|e| e.to_string())?;
+    let drain = |pipe: Option<Box<dyn Read + Send>>| {
+        thread::spawn(move || {
+            let mut text = String::new();
+            if let Some(mut pipe) = pipe {
+                let _ = pipe.read_to_string(&mut text);
+            }
+            text
+        })
+    };
+    let out = drain(child.stdout.take().map(|p| Box::new(p) as Box<dyn Read + Send>));
+    let err = drain(child.stderr.take().map(|p| Box::new(p) as Box<dyn Read + Send>));
+    let status = child.wait_timeout(Duration::from_secs(timeout)).map_err(|e| e.to_string())?;
+    if status.is_none() {
+        interrupt::kill(&child);
+        let _ = child.wait();
+    }
+    interrupt::reap(&child);
+    let (out, err) = (out.join().unwrap_or_default(), err.join().unwrap_or_default());
+    match status {
+        Some(status) => Ok((status.success(), out, err)),
+        None => Err(format!("timeout reached ({}s)", timeout)),
+    }
+}
+
+/// Whether `cargo <tool>` is installed.
+fn available(tool: &str, opts: &RunOptions) -> bool {
+    opts.command("cargo")
+        .args([tool, "--version"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|s| s.success())
+}
+
+/// Clippy's findings over every target, or `None` without clippy.
+fn clippy(workspace: &Path, opts: &RunOptions) -> Result<Option<Vec<Finding>>, String> {
+    if !available("clippy", opts) {
+        return Ok(None);
+    }
+    let (ok, out, err) = run(
+        opts.command("cargo")
+            .args(["clippy", "--all-targets", "--color=never", "--message-format=json", "--", "-D", "warnings"])
+            .current_dir(workspace),
+        opts.build_timeout,
+    )?;
+    let findings: Vec<Finding> = out.lines().filter_map(finding).collect();
+    if !ok && findings.is_empty() {
+        return Err(format!("cargo clippy failed:\n{}", err.trim()));
+    }
+    Ok(Some(findings))
+}
+
+/// Files rustfmt would change, with the first line of each, or `None`
+/// without rustfmt.
+fn rustfmt(workspace: &Path, opts: &RunOptions) -> Result<Option<Vec<(String, usize)>>, String> {
+    if !available("fmt", opts) {
+        return Ok(None);
+    }
+    let (ok, out, err) = run(opts.command("cargo").args(["fmt", "--check"]).current_dir(workspace), opts.build_timeout)?;
+    let mut files: Vec<(String, usize)> = Vec::new();
+    // `Diff in /abs/src/lib.rs:12:` (older rustfmt: `… at line 12:`)
+    for line in out.lines() {
+        let Some(rest) = line.strip_prefix("Diff in ") else { continue };
+        let rest = rest.trim_end_matches(':');
+        let (file, at) = rest.rsplit_once(" at line ").or_else(|| rest.rsplit_once(':')).unwrap_or((rest, "0"));
+        let file = Path::new(file).strip_prefix(workspace).map_or(file.to_string(), |p| p.display().to_string());
+        if !files.iter().any(|(f, _)| *f == file) {
+            files.push((file, at.parse().unwrap_or(0)));
+        }
+    }
+    if !ok && files.is_empty() {
+        return Err(format!("cargo fmt failed:\n{}", err.trim()));
+    }
+    Ok(Some(files))
+}
+
+/// Run both checks and print the quality gate section. Returns `false`
+/// for findings under [`Gate::Fatal`].
+pub fn check(workspace: &Path, gate: Gate, base: &RunOptions) -> Result<bool, String> {
+    // like the build, lints are not the code under test
+    let opts = RunOptions { limits: sandbox::Limits::default(), ..*base };
+    let workspace = &workspace.canonicalize().map_err(|e| e.to_string())?;
+    let (mark, label) = match gate {
+        Gate::Fatal => (RED, ""),
+        Gate::Advisory => (BLUE, " (advisory)"),
+    };
+    println!("\n{}Quality gate{}:{}", BOLD, label, RESET);
+    let mut clean = true;
+    match clippy(workspace, &opts)? {
+        None => println!("  clippy: skipped (not installed)"),
+        Some(findings) if findings.is_empty() => println!("  clippy: {}clean{}", GREEN, RESET),
+        Some(findings) => {
+            clean = false;
+            println!("  clippy: {}{} finding(s){}", mark, findings.len(), RESET);
+            for f in &findings {
+                let at = f.location.as_ref().map_or(String::new(), |(file, line)| format!("{}:{}: ", file, line));
+                println!("    {}{} [{}]", at, f.message, f.lint);
+            }
+        }
+    }
+    match rustfmt(workspace, &opts)? {
+        None => println!("  rustfmt: skipped (not installed)"),
+        Some(files) if files.is_empty() => println!("  rustfmt: {}formatted{}", GREEN, RESET),
+        Some(files) => {
+            clean = false;
+            println!("  rustfmt: {}{} file(s) not formatted{}", mark, files.len(), RESET);
+            for (file, line) in &files {
+                println!("    {}:{}", file, line);
+            }
+        }
+    }
+    Ok(clean || gate == Gate::Advisory)
+}
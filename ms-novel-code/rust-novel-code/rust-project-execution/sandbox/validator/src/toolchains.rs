@@ -0,0 +1,139 @@
+//! `--toolchains stable,beta,nightly`: validate the same workspace under
+//! each rustup toolchain and print a test × toolchain grid, so a change
+//! in beta or nightly shows up before it reaches the graders' stable.
+
+use crate::colors::*;
+use crate::{build_tests, run_cargo_test_once, RunOptions};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Outcome of one test under one toolchain over all its runs.
+#[derive(Clone, Copy, PartialEq)]
+enum Outcome {
+    Pass,
+    Fail,
+    Flaky,
+}
+
+impl Outcome {
+    fn of(runs: &[bool]) -> Self {
+        if runs.iter().all(|&r| r) {
+            Outcome::Pass
+        } else if runs.iter().all(|&r| !r) {
+            Outcome::Fail
+        } else {
+            Outcome::Flaky
+        }
+    }
+
+    fn label(self) -> (&'static str, &'static str) {
+        match self {
+            Outcome::Pass => ("pass", GREEN),
+            Outcome::Fail => ("FAIL", RED),
+            Outcome::Flaky => ("flaky", BLUE),
+        }
+    }
+}
+
+/// Each test's outcome under one toolchain, or why it could not run.
+type Results = Result<BTreeMap<String, Outcome>, String>;
+
+/// Copy `from` to `to`, leaving out build output and snapshots.
+pub fn copy_sources(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        if name == "target" || name == ".git" {
+            continue;
+        }
+        if entry.file_type()?.is_dir() {
+            copy_sources(&entry.path(), &to.join(&name))?;
+        } else {
+            fs::copy(entry.path(), to.join(&name))?;
+        }
+    }
+    Ok(())
+}
+
+/// Workspace for `variant` (a toolchain or candidate), next to the main
+/// one.
+pub fn workspace_for(workspace: &Path, variant: &str) -> PathBuf {
+    let mut name = workspace.file_name().unwrap_or_default().to_os_string();
+    name.push(format!("@{}", variant));
+    workspace.with_file_name(name)
+}
+
+/// Build and run the suite `runs` times under each toolchain, then print
+/// the grid. Divergences are reported but do not fail validation.
+pub fn compare(workspace: &Path, toolchains: &[String], runs: usize, base: &RunOptions) {
+    let mut results: Vec<(&str, Results)> = Vec::new();
+    for toolchain in toolchains {
+        println!("{}Toolchain {}{}", BLUE, toolchain, RESET);
+        // a workspace, and so a `target/`, per toolchain
+        let dir = workspace_for(workspace, toolchain);
+        let mut env = base.env.to_vec();
+        env.push(("RUSTUP_TOOLCHAIN".to_string(), toolchain.clone()));
+        let opts = RunOptions { env: &env, ..*base };
+        let outcome = copy_sources(workspace, &dir)
+            .map_err(|e| format!("{}: {}", dir.display(), e))
+            .and_then(|()| build_tests(&dir, &opts).map_err(|e| e.to_string()))
+            .and_then(|_| {
+                let mut matrix: BTreeMap<String, Vec<bool>> = BTreeMap::new();
+                for _ in 0..runs {
+                    for (name, passed) in run_cargo_test_once(&dir, &opts)? {
+                        matrix.entry(name).or_default().push(passed);
+                    }
+                }
+                Ok(matrix.into_iter().map(|(name, r)| (name, Outcome::of(&r))).collect())
+            });
+        results.push((toolchain, outcome));
+    }
+
+    let names: BTreeSet<&String> =
+        results.iter().filter_map(|(_, r)| r.as_ref().ok()).flat_map(|m| m.keys()).collect();
+    println!("\n{}Toolchains:{}", BOLD, RESET);
+    print!("{:<45}", "Test");
+    for (toolchain, _) in &results {
+        print!(" | {:<10}", toolchain);
+    }
+    println!();
+    println!("{:-<45}{}", "", "-+-----------".repeat(results.len()));
+    let mut divergent = Vec::new();
+    for name in names {
+        let row: Vec<Option<Outcome>> = results
+            .iter()
+            .filter_map(|(_, r)| r.as_ref().ok())
+            .map(|m| m.get(name).copied())
+            .collect();
+        if row.iter().any(|o| *o != row[0]) {
+            divergent.push(name.as_str());
+        }
+        print!("{:<45}", name);
+        let mut row = row.into_iter();
+        for (_, r) in &results {
+            match r.as_ref().ok().map(|_| row.next().flatten()) {
+                Some(Some(o)) => {
+                    let (label, col) = o.label();
+                    print!(" | {}{:<10}{}", col, label, RESET);
+                }
+                Some(None) => print!(" | {:<10}", "missing"),
+                None => print!(" | {:<10}", "-"),
+            }
+        }
+        println!();
+    }
+    for (toolchain, r) in &results {
+        if let Err(e) = r {
+            println!("{}{} did not run:{} {}", RED, toolchain, RESET, e);
+        }
+    }
+    if divergent.is_empty() {
+        println!("{}No divergence between toolchains{}", GREEN, RESET);
+    } else {
+        println!("{}Diverging across toolchains:{} {}", RED, RESET, divergent.join(", "));
+    }
+}
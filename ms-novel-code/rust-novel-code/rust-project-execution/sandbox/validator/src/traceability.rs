@@ -0,0 +1,107 @@
+//! Requirement traceability. The manifest's `[requirements]` table names
+//! the task's requirements (`R1 = "rejects empty input"`); a test covers
+//! one when its name has the ID as an `_`-separated word
+//! (`r1_rejects_empty`, `test_r2_overflow`) or a `// requirements: R1, R3`
+//! comment sits above it. After the runs every requirement is shown with
+//! its tests, and one that no test covers, or only failing ones do, is a
+//! warning.
+
+use crate::colors::*;
+use crate::quality::comment_tags;
+use crate::source::{functions, strip_comments_and_strings};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// How well one requirement is tested.
+#[derive(PartialEq)]
+enum Coverage {
+    /// Every mapped test passed in every run.
+    Covered,
+    /// A mapped test failed or was flaky.
+    Failing,
+    /// No mapped test ran.
+    Untested,
+}
+
+/// Requirement IDs `test` covers by name.
+fn named<'a>(test: &str, ids: impl Iterator<Item = &'a String>) -> Vec<String> {
+    let name = test.rsplit("::").next().unwrap_or(test).to_ascii_lowercase();
+    ids.filter(|id| name.split('_').any(|w| w == id.to_ascii_lowercase()))
+        .cloned()
+        .collect()
+}
+
+/// Tests mapped to each requirement by `// requirements:` comments in
+/// `sources`, and the IDs those comments name that are not requirements.
+fn tagged(sources: &[&str], requirements: &BTreeMap<String, String>) -> (HashMap<String, Vec<String>>, BTreeSet<String>) {
+    let mut by_test: HashMap<String, Vec<String>> = HashMap::new();
+    let mut unknown = BTreeSet::new();
+    for raw in sources {
+        for f in functions(&strip_comments_and_strings(raw)).iter().filter(|f| f.is_test()) {
+            for id in comment_tags(raw, &f.name, "requirements") {
+                match requirements.keys().find(|r| r.eq_ignore_ascii_case(&id)) {
+                    Some(r) => by_test.entry(f.name.clone()).or_default().push(r.clone()),
+                    None => {
+                        unknown.insert(id);
+                    }
+                }
+            }
+        }
+    }
+    (by_test, unknown)
+}
+
+/// Print the traceability matrix of `requirements` against the test
+/// outcomes in `matrix`, the tests' metadata read from `sources`. When
+/// `sharded`, other shards' tests are missing from `matrix`.
+pub fn print(
+    requirements: &BTreeMap<String, String>,
+    matrix: &HashMap<String, Vec<bool>>,
+    sources: &[&str],
+    sharded: bool,
+) {
+    let (by_test, unknown) = tagged(sources, requirements);
+    let mut tests: BTreeMap<&str, BTreeSet<&str>> = requirements.keys().map(|r| (r.as_str(), BTreeSet::new())).collect();
+    for test in matrix.keys() {
+        let short = test.rsplit("::").next().unwrap_or(test);
+        let mut ids = named(test, requirements.keys());
+        ids.extend(by_test.get(short).into_iter().flatten().cloned());
+        for id in ids {
+            if let Some(set) = tests.get_mut(id.as_str()) {
+                set.insert(test.as_str());
+            }
+        }
+    }
+
+    let scope = if sharded { " (this shard's tests only)" } else { "" };
+    println!("\n{}Traceability{}:{}", BOLD, scope, RESET);
+    let width = requirements.keys().map(String::len).max().unwrap_or(0);
+    let mut warnings = 0;
+    for (id, description) in requirements {
+        let mapped = &tests[id.as_str()];
+        let coverage = if mapped.is_empty() {
+            Coverage::Untested
+        } else if mapped.iter().all(|t| matrix[*t].iter().all(|&r| r)) {
+            Coverage::Covered
+        } else {
+            Coverage::Failing
+        };
+        let (label, color) = match coverage {
+            Coverage::Covered => ("covered ", GREEN),
+            Coverage::Failing => ("failing ", RED),
+            Coverage::Untested => ("untested", RED),
+        };
+        warnings += usize::from(coverage != Coverage::Covered);
+        println!("  {:width$}  {}{}{}  {}", id, color, label, RESET, description, width = width);
+        if !mapped.is_empty() {
+            println!("  {:width$}            {}", "", mapped.iter().copied().collect::<Vec<_>>().join(", "), width = width);
+        }
+    }
+    if !unknown.is_empty() {
+        let unknown: Vec<&str> = unknown.iter().map(String::as_str).collect();
+        println!("  {}warning:{} `// requirements:` names unknown IDs: {}", BLUE, RESET, unknown.join(", "));
+    }
+    if warnings > 0 {
+        println!("  {}warning:{} {} of {} requirements not covered by passing tests",
+                 BLUE, RESET, warnings, requirements.len());
+    }
+}
@@ -0,0 +1,245 @@
+//! `validator report trends`: the `--history` database as series over
+//! each task's last validations (pass rate, duration, flaky tests), and
+//! the same for the whole corpus per day and per toolchain, to tell
+//! whether a toolchain bump degraded the corpus.
+
+use crate::history::Record;
+use crate::resources::{points, sparkline};
+use minijinja::{context, Environment};
+use serde::Serialize;
+use std::{fmt::Write, fs, path::Path};
+
+const HTML: &str = include_str!("../templates/trends.html");
+
+/// Records of one task, day or toolchain, oldest first.
+struct Group<'a> {
+    label: String,
+    records: Vec<&'a Record>,
+}
+
+impl Group<'_> {
+    fn mean(&self, value: impl Fn(&Record) -> f64) -> f64 {
+        self.records.iter().map(|r| value(r)).sum::<f64>() / self.records.len().max(1) as f64
+    }
+
+    /// Share of the validations that passed.
+    fn passed(&self) -> f64 {
+        self.mean(|r| f64::from(u8::from(r.passed())))
+    }
+}
+
+/// `records` grouped by `key`, groups in the order of their first record.
+fn group<'a>(records: &[&'a Record], key: impl Fn(&Record) -> String) -> Vec<Group<'a>> {
+    let mut groups: Vec<Group> = Vec::new();
+    for &record in records {
+        let label = key(record);
+        match groups.iter_mut().find(|g| g.label == label) {
+            Some(g) => g.records.push(record),
+            None => groups.push(Group { label, records: vec![record] }),
+        }
+    }
+    groups
+}
+
+/// `YYYY-MM-DD` (UTC) of a Unix time.
+fn date(secs: u64) -> String {
+    // days to a civil date, after Howard Hinnant's `civil_from_days`
+    let z = (secs / 86_400) as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// The trends of `records` (the last `last` validations of each task).
+pub struct Trends<'a> {
+    last: usize,
+    tasks: Vec<Group<'a>>,
+    days: Vec<Group<'a>>,
+    toolchains: Vec<Group<'a>>,
+}
+
+impl<'a> Trends<'a> {
+    pub fn new(records: &'a [Record], last: usize) -> Self {
+        let all: Vec<&Record> = records.iter().collect();
+        let mut by_time = all.clone();
+        by_time.sort_by_key(|r| r.finished);
+        Trends {
+            last,
+            tasks: group(&all, |r| r.task.clone()),
+            days: group(&by_time, |r| date(r.finished)),
+            toolchains: group(&by_time, |r| r.toolchain.clone()),
+        }
+    }
+
+    /// The trends as text, with sparklines.
+    pub fn text(&self) -> String {
+        let mut out = format!("Trends over the last {} validations of each task, oldest first\n", self.last);
+        if self.tasks.is_empty() {
+            out += "\nNo validations recorded.\n";
+            return out;
+        }
+        for task in &self.tasks {
+            let records = &task.records;
+            let latest = records[records.len() - 1];
+            let rates: Vec<f64> = records.iter().map(|r| r.pass_rate()).collect();
+            let seconds: Vec<f64> = records.iter().map(|r| r.seconds).collect();
+            let flaky: Vec<f64> = records.iter().map(|r| r.flaky as f64).collect();
+            let verdicts: String = records.iter().map(|r| if r.passed() { '✓' } else { '✗' }).collect();
+            let _ = writeln!(out, "\n{}: {} validations, last {}", task.label, records.len(), latest.status);
+            let _ = writeln!(out, "  verdicts   {}", verdicts);
+            let _ = writeln!(out, "  pass rate  {} last {:.0}% of {} tests",
+                             sparkline(&rates), latest.pass_rate() * 100.0, latest.tests);
+            let _ = writeln!(out, "  duration   {} last {:.1}s, mean {:.1}s",
+                             sparkline(&seconds), latest.seconds, task.mean(|r| r.seconds));
+            let _ = writeln!(out, "  flaky      {} last {} tests", sparkline(&flaky), latest.flaky);
+        }
+
+        let (first, latest) = (&self.days[0], &self.days[self.days.len() - 1]);
+        let _ = writeln!(out, "\nCorpus by day, {} to {} ({} days with validations):",
+                         first.label, latest.label, self.days.len());
+        let passed: Vec<f64> = self.days.iter().map(Group::passed).collect();
+        let seconds: Vec<f64> = self.days.iter().map(|d| d.mean(|r| r.seconds)).collect();
+        let flaky: Vec<f64> = self.days.iter().map(|d| d.mean(|r| r.flaky as f64)).collect();
+        let _ = writeln!(out, "  passed     {} last day {:.0}% of {} validations",
+                         sparkline(&passed), latest.passed() * 100.0, latest.records.len());
+        let _ = writeln!(out, "  duration   {} last day mean {:.1}s", sparkline(&seconds), latest.mean(|r| r.seconds));
+        let _ = writeln!(out, "  flaky      {} last day {:.1} tests per validation",
+                         sparkline(&flaky), latest.mean(|r| r.flaky as f64));
+
+        out += "\nCorpus by toolchain, in the order first used:\n";
+        for t in &self.toolchains {
+            let _ = writeln!(out, "  {}: {} validations, {:.0}% passed, mean {:.1}s, {:.1} flaky tests each",
+                             t.label, t.records.len(), t.passed() * 100.0, t.mean(|r| r.seconds),
+                             t.mean(|r| r.flaky as f64));
+        }
+        out
+    }
+
+    /// Write the trends as an HTML page of charts to `path`.
+    pub fn write_html(&self, path: &Path, history: &str) -> Result<(), String> {
+        #[derive(Serialize)]
+        struct Chart {
+            label: &'static str,
+            points: String,
+            last: String,
+        }
+        #[derive(Serialize)]
+        struct Row {
+            label: String,
+            validations: usize,
+            verdicts: Vec<bool>,
+            charts: Vec<Chart>,
+        }
+        #[derive(Serialize)]
+        struct Toolchain {
+            label: String,
+            validations: usize,
+            passed: String,
+            seconds: String,
+            flaky: String,
+        }
+        let chart = |label, values: &[f64], max, last| Chart { label, points: points(values, max, 300.0, 40.0), last };
+        let tasks: Vec<Row> = self
+            .tasks
+            .iter()
+            .map(|task| {
+                let latest = task.records[task.records.len() - 1];
+                let values = |f: fn(&Record) -> f64| task.records.iter().map(|r| f(r)).collect::<Vec<_>>();
+                Row {
+                    label: task.label.clone(),
+                    validations: task.records.len(),
+                    verdicts: task.records.iter().map(|r| r.passed()).collect(),
+                    charts: vec![
+                        chart("Pass rate", &values(Record::pass_rate), Some(1.0),
+                              format!("{:.0}%", latest.pass_rate() * 100.0)),
+                        chart("Duration", &values(|r| r.seconds), None, format!("{:.1}s", latest.seconds)),
+                        chart("Flaky", &values(|r| r.flaky as f64), None, latest.flaky.to_string()),
+                    ],
+                }
+            })
+            .collect();
+        let days: Vec<Chart> = match self.days.last() {
+            None => Vec::new(),
+            Some(latest) => {
+                let passed: Vec<f64> = self.days.iter().map(Group::passed).collect();
+                let seconds: Vec<f64> = self.days.iter().map(|d| d.mean(|r| r.seconds)).collect();
+                let flaky: Vec<f64> = self.days.iter().map(|d| d.mean(|r| r.flaky as f64)).collect();
+                vec![
+                    chart("Passed", &passed, Some(1.0), format!("{:.0}%", latest.passed() * 100.0)),
+                    chart("Duration", &seconds, None, format!("{:.1}s", latest.mean(|r| r.seconds))),
+                    chart("Flaky", &flaky, None, format!("{:.1}", latest.mean(|r| r.flaky as f64))),
+                ]
+            }
+        };
+        let toolchains: Vec<Toolchain> = self
+            .toolchains
+            .iter()
+            .map(|t| Toolchain {
+                label: t.label.clone(),
+                validations: t.records.len(),
+                passed: format!("{:.0}%", t.passed() * 100.0),
+                seconds: format!("{:.1}", t.mean(|r| r.seconds)),
+                flaky: format!("{:.1}", t.mean(|r| r.flaky as f64)),
+            })
+            .collect();
+        let first_day = self.days.first().map(|d| d.label.clone());
+        let last_day = self.days.last().map(|d| d.label.clone());
+        let mut env = Environment::new();
+        env.add_template("trends.html", HTML).map_err(|e| e.to_string())?;
+        let html = env
+            .get_template("trends.html")
+            .and_then(|t| {
+                t.render(context! { history, last => self.last, tasks, days, first_day, last_day, toolchains })
+            })
+            .map_err(|e| format!("template: {}", e))?;
+        fs::write(path, html).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(task: &str, day: u64, toolchain: &str, status: &str, pass: usize, flaky: usize) -> Record {
+        Record {
+            task: task.to_string(),
+            finished: day * 86_400 + 3600,
+            toolchain: toolchain.to_string(),
+            status: status.to_string(),
+            seconds: 10.0 * (pass + 1) as f64,
+            tests: 4,
+            pass,
+            flaky,
+        }
+    }
+
+    #[test]
+    fn dates_are_civil_utc() {
+        assert_eq!(date(0), "1970-01-01");
+        assert_eq!(date(951_782_400), "2000-02-29");
+        assert_eq!(date(1_792_195_200), "2026-10-17");
+    }
+
+    #[test]
+    fn text_shows_tasks_days_and_toolchains() {
+        let records = [
+            record("003", 20_000, "rustc 1.88.0", "passed", 4, 0),
+            record("003", 20_001, "rustc 1.89.0", "failed", 2, 1),
+            record("004", 20_000, "rustc 1.88.0", "passed", 4, 0),
+        ];
+        let text = Trends::new(&records, 20).text();
+        assert!(text.contains("\n003: 2 validations, last failed\n  verdicts   ✓✗\n"), "{}", text);
+        assert!(text.contains("  pass rate  █▅ last 50% of 4 tests\n"), "{}", text);
+        assert!(text.contains("Corpus by day, 2024-10-04 to 2024-10-05 (2 days with validations):"), "{}", text);
+        assert!(text.contains("  passed     █▁ last day 0% of 1 validations\n"), "{}", text);
+        assert!(text.contains("  rustc 1.88.0: 2 validations, 100% passed, mean 50.0s, 0.0 flaky tests each\n\
+                               \x20 rustc 1.89.0: 1 validations, 0% passed, mean 30.0s, 1.0 flaky tests each\n"),
+                "{}", text);
+    }
+}
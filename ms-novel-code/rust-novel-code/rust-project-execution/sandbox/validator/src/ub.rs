@@ -0,0 +1,273 @@
+//! `--miri` and `--sanitizer address|thread`: run the tests once more
+//! under Miri or a sanitizer, for tasks whose `unsafe` code passes its
+//! tests and may still be undefined behavior. Both need a nightly
+//! toolchain (the one the notebook is routed to, else `nightly`), Miri
+//! its `miri` component as well. ThreadSanitizer rebuilds the standard
+//! library instrumented (`-Zbuild-std`), which takes `rust-src`.
+//!
+//! The suite runs on one test thread, so a test that aborts its binary
+//! is the one libtest announced last. It is run again alone for its
+//! report, and the suite again without it, until no test aborts. Tests
+//! with a report are classified unsound in the matrix, whatever their
+//! results.
+
+use crate::colors::*;
+use crate::{interrupt, msrv, sandbox, RunOptions};
+use clap::ValueEnum;
+use std::{
+    fmt,
+    io::Read,
+    path::Path,
+    process::{Command, Stdio},
+    thread,
+    time::Duration,
+};
+use wait_timeout::ChildExt;
+
+/// Lines of a finding's report kept.
+const MAX_LINES: usize = 40;
+
+#[derive(ValueEnum, Clone, Copy, PartialEq)]
+pub enum Sanitizer {
+    Address,
+    Thread,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Mode {
+    Miri,
+    Sanitizer(Sanitizer),
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Mode::Miri => "Miri",
+            Mode::Sanitizer(Sanitizer::Address) => "AddressSanitizer",
+            Mode::Sanitizer(Sanitizer::Thread) => "ThreadSanitizer",
+        })
+    }
+}
+
+/// A test Miri or a sanitizer reported.
+pub struct Finding {
+    pub test: String,
+    /// Like `Undefined Behavior: out-of-bounds pointer use` or
+    /// `AddressSanitizer: heap-buffer-overflow`.
+    pub summary: String,
+    /// The start of the report.
+    pub details: String,
+}
+
+/// The nightly toolchain to use: the one `opts` pins, if nightly.
+fn toolchain(opts: &RunOptions) -> String {
+    opts.env
+        .iter()
+        .rev()
+        .find(|(k, _)| k == "RUSTUP_TOOLCHAIN")
+        .map(|(_, v)| v.clone())
+        .filter(|t| t.starts_with("nightly"))
+        .unwrap_or_else(|| "nightly".to_string())
+}
+
+/// The value `opts` gives `key`, if any.
+fn var<'a>(opts: &'a RunOptions, key: &str) -> Option<&'a str> {
+    opts.env.iter().rev().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+}
+
+/// Check that the toolchain and components `mode` needs are installed.
+pub fn probe(mode: Mode, opts: &RunOptions) -> Result<(), String> {
+    let toolchain = toolchain(opts);
+    if !msrv::installed(&toolchain) {
+        return Err(format!("{} needs {}, which is not installed (`rustup toolchain install {}`)",
+                           mode, toolchain, toolchain));
+    }
+    if mode == Mode::Sanitizer(Sanitizer::Thread) {
+        let sysroot = Command::new("rustc")
+            .args(["--print", "sysroot"])
+            .env("RUSTUP_TOOLCHAIN", &toolchain)
+            .output()
+            .map_err(|e| format!("rustc: {}", e))?;
+        let sysroot = String::from_utf8_lossy(&sysroot.stdout).trim().to_string();
+        if !Path::new(&sysroot).join("lib/rustlib/src/rust/library/Cargo.lock").is_file() {
+            return Err(format!("{} needs the standard library's sources (`rustup component add --toolchain {} rust-src`)",
+                               mode, toolchain));
+        }
+    }
+    if mode == Mode::Miri {
+        let found = opts.command("cargo")
+            .args(["miri", "--version"])
+            .env("RUSTUP_TOOLCHAIN", &toolchain)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|s| s.success());
+        if !found {
+            return Err(format!("Miri is not installed for {} (`rustup component add --toolchain {} miri`)",
+                               toolchain, toolchain));
+        }
+    }
+    Ok(())
+}
+
+/// The host's target triple; sanitized builds name it, so build scripts
+/// and proc macros stay uninstrumented.
+fn host(toolchain: &str) -> Result<String, String> {
+    let out = Command::new("rustc")
+        .arg("-vV")
+        .env("RUSTUP_TOOLCHAIN", toolchain)
+        .output()
+        .map_err(|e| format!("rustc: {}", e))?;
+    String::from_utf8_lossy(&out.stdout)
+        .lines()
+        .find_map(|l| l.strip_prefix("host: "))
+        .map(str::to_string)
+        .ok_or_else(|| "rustc -vV names no host".to_string())
+}
+
+/// `cargo test` of every test target under `mode`, up to `--`.
+fn cargo(mode: Mode, opts: &RunOptions, toolchain: &str, host: &str) -> Command {
+    let mut cmd = opts.command("cargo");
+    cmd.env("RUSTUP_TOOLCHAIN", toolchain);
+    match mode {
+        Mode::Miri => {
+            cmd.args(["miri", "test"]);
+            // leaks are safe Rust, and tests may read files and clocks
+            if var(opts, "MIRIFLAGS").is_none() {
+                cmd.env("MIRIFLAGS", "-Zmiri-disable-isolation -Zmiri-ignore-leaks");
+            }
+        }
+        Mode::Sanitizer(sanitizer) => {
+            let name = match sanitizer {
+                Sanitizer::Address => "address",
+                Sanitizer::Thread => "thread",
+            };
+            let flags = var(opts, "RUSTFLAGS").map_or(String::new(), |f| format!("{} ", f));
+            cmd.args(["test", "--target", host])
+                .env("RUSTFLAGS", format!("{}-Zsanitizer={}", flags, name))
+                // stop at the first report, so it is the running test's;
+                // leaks are safe Rust
+                .env("ASAN_OPTIONS", "detect_leaks=0")
+                .env("TSAN_OPTIONS", "halt_on_error=1");
+            // an uninstrumented std is an ABI mismatch for TSan
+            if sanitizer == Sanitizer::Thread {
+                cmd.arg("-Zbuild-std");
+            }
+        }
+    }
+    cmd.args(["--tests", "--no-fail-fast", "--color=never"]);
+    cmd
+}
+
+/// How a command ended.
+struct Ended {
+    success: bool,
+    stdout: String,
+    stderr: String,
+}
+
+/// Run `cmd` to completion or `timeout`.
+fn run(cmd: &mut Command, timeout: u64) -> Result<Ended, String> {
+    let mut child = interrupt::spawn(cmd.stdout(Stdio::piped()).stderr(Stdio::piped())).map_err(|e| e.to_string())?;
+    let drain = |pipe: Option<Box<dyn Read + Send>>| {
+        thread::spawn(move || {
+            let mut text = String::new();
+            if let Some(mut pipe) = pipe {
+                let _ = pipe.read_to_string(&mut text);
+            }
+            text
+        })
+    };
+    let out = drain(child.stdout.take().map(|p| Box::new(p) as Box<dyn Read + Send>));
+    let err = drain(child.stderr.take().map(|p| Box::new(p) as Box<dyn Read + Send>));
+    let status = child.wait_timeout(Duration::from_secs(timeout)).map_err(|e| e.to_string())?;
+    if status.is_none() {
+        interrupt::kill(&child);
+        let _ = child.wait();
+    }
+    interrupt::reap(&child);
+    let (stdout, stderr) = (out.join().unwrap_or_default(), err.join().unwrap_or_default());
+    match status {
+        Some(status) => Ok(Ended { success: status.success(), stdout, stderr }),
+        None => Err(format!("timeout reached ({}s)", timeout)),
+    }
+}
+
+/// Tests libtest started and never finished: `test <name> ... ` with no
+/// outcome, as one test thread leaves it when the binary aborts.
+fn aborted(stdout: &str) -> Vec<String> {
+    stdout
+        .lines()
+        .filter_map(|l| l.strip_prefix("test ")?.strip_suffix(" ... "))
+        .map(str::to_string)
+        .collect()
+}
+
+/// The report in `output`: its first line shortened to the kind of
+/// error, and the first `MAX_LINES` lines of it.
+fn report(mode: Mode, output: &str) -> Option<(String, String)> {
+    let marker = match mode {
+        Mode::Miri => "Undefined Behavior:",
+        Mode::Sanitizer(Sanitizer::Address) => "AddressSanitizer:",
+        Mode::Sanitizer(Sanitizer::Thread) => "ThreadSanitizer:",
+    };
+    let lines: Vec<&str> = output.lines().collect();
+    let at = lines.iter().position(|l| l.contains(marker))?;
+    let from = &lines[at][lines[at].find(marker)?..];
+    let end = [" on address", " (pid=", " at pc"].iter().filter_map(|cut| from.find(cut)).min();
+    let summary = from[..end.unwrap_or(from.len())].trim().to_string();
+    Some((summary, lines[at..].iter().take(MAX_LINES).copied().collect::<Vec<_>>().join("\n")))
+}
+
+/// The last `MAX_LINES` lines of `text`.
+fn tail(text: &str) -> String {
+    let lines: Vec<&str> = text.trim().lines().collect();
+    lines[lines.len().saturating_sub(MAX_LINES)..].join("\n")
+}
+
+/// Run the suite under `mode` and print what it found. The build gets
+/// `opts.build_timeout`, each run of the suite `opts.timeout`.
+pub fn check(mode: Mode, workspace: &Path, opts: &RunOptions) -> Result<Vec<Finding>, String> {
+    let toolchain = toolchain(opts);
+    let host = host(&toolchain)?;
+    println!("\n{}{}:{} running the tests with {}", BOLD, mode, RESET, toolchain);
+    let build_opts = RunOptions { limits: sandbox::Limits::default(), ..*opts };
+    let built = run(cargo(mode, &build_opts, &toolchain, &host).arg("--no-run").current_dir(workspace),
+                    opts.build_timeout)?;
+    if !built.success {
+        return Err(format!("build failed:\n{}", tail(&built.stderr)));
+    }
+    // sanitizers reserve far more address space than any memory limit
+    let opts = RunOptions { limits: sandbox::Limits { memory_mb: None, ..opts.limits }, ..*opts };
+    let mut findings = Vec::new();
+    let mut skipped: Vec<String> = Vec::new();
+    while !interrupt::interrupted() {
+        let mut cmd = cargo(mode, &opts, &toolchain, &host);
+        cmd.current_dir(workspace).args(["--", "--test-threads=1", "--exact"]);
+        for test in &skipped {
+            cmd.args(["--skip", test]);
+        }
+        let suite = run(&mut cmd, opts.timeout)?;
+        let culprits: Vec<String> = aborted(&suite.stdout).into_iter().filter(|t| !skipped.contains(t)).collect();
+        if suite.success || culprits.is_empty() {
+            break;
+        }
+        for test in culprits {
+            let mut cmd = cargo(mode, &opts, &toolchain, &host);
+            cmd.current_dir(workspace).args(["--", "--test-threads=1", "--exact", &test]);
+            let solo = run(&mut cmd, opts.timeout)?;
+            let output = solo.stdout + &solo.stderr;
+            if let Some((summary, details)) = report(mode, &output) {
+                findings.push(Finding { test: test.clone(), summary, details });
+            }
+            skipped.push(test);
+        }
+    }
+    if findings.is_empty() {
+        println!("  {}no findings{}", GREEN, RESET);
+    }
+    for f in &findings {
+        println!("  {}{}{} {}", RED, f.test, RESET, f.summary);
+    }
+    Ok(findings)
+}
@@ -0,0 +1,216 @@
+//! `--upload s3://bucket/prefix`: push the report and the extracted
+//! workspace to S3-compatible storage after validation, so grading
+//! workers need no shared filesystem.
+//!
+//! Credentials and endpoint come from the standard variables:
+//! `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY`, optional
+//! `AWS_SESSION_TOKEN`, `AWS_REGION` (or `AWS_DEFAULT_REGION`), and
+//! `AWS_ENDPOINT_URL_S3` (or `AWS_ENDPOINT_URL`) for non-AWS stores.
+//! Objects are addressed path-style and signed with SigV4.
+
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use std::{
+    env, fs,
+    path::Path,
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+const ATTEMPTS: u32 = 3;
+
+pub struct Target {
+    bucket: String,
+    prefix: String,
+}
+
+impl Target {
+    /// Parse `s3://bucket[/prefix]`.
+    pub fn parse(url: &str) -> Result<Self, String> {
+        let rest = url.strip_prefix("s3://").ok_or(format!("{}: expected s3://bucket/prefix", url))?;
+        let (bucket, prefix) = rest.split_once('/').unwrap_or((rest, ""));
+        if bucket.is_empty() {
+            return Err(format!("{}: missing bucket", url));
+        }
+        Ok(Target { bucket: bucket.to_string(), prefix: prefix.trim_matches('/').to_string() })
+    }
+}
+
+struct Credentials {
+    access_key: String,
+    secret_key: String,
+    token: Option<String>,
+    region: String,
+    endpoint: String,
+}
+
+impl Credentials {
+    fn from_env() -> Result<Self, String> {
+        let var = |k: &str| env::var(k).ok().filter(|v| !v.is_empty());
+        let access_key = var("AWS_ACCESS_KEY_ID").ok_or("AWS_ACCESS_KEY_ID is not set")?;
+        let secret_key = var("AWS_SECRET_ACCESS_KEY").ok_or("AWS_SECRET_ACCESS_KEY is not set")?;
+        let region = var("AWS_REGION")
+            .or_else(|| var("AWS_DEFAULT_REGION"))
+            .unwrap_or_else(|| "us-east-1".to_string());
+        let endpoint = var("AWS_ENDPOINT_URL_S3")
+            .or_else(|| var("AWS_ENDPOINT_URL"))
+            .unwrap_or_else(|| format!("https://s3.{}.amazonaws.com", region));
+        Ok(Credentials {
+            access_key,
+            secret_key,
+            token: var("AWS_SESSION_TOKEN"),
+            region,
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+        })
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn hmac(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes any key length");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Percent-encode a key for the URL and canonical request; `/` is kept.
+fn encode_key(key: &str) -> String {
+    key.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// `(YYYYMMDD, YYYYMMDDTHHMMSSZ)` for `secs` since the epoch.
+fn amz_dates(secs: u64) -> (String, String) {
+    // civil-from-days, Howard Hinnant's algorithm
+    let days = (secs / 86_400) as i64;
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    let date = format!("{:04}{:02}{:02}", year, month, day);
+    let t = secs % 86_400;
+    let stamp = format!("{}T{:02}{:02}{:02}Z", date, t / 3600, t / 60 % 60, t % 60);
+    (date, stamp)
+}
+
+/// PUT one object, signed with SigV4. Errors say whether a retry may
+/// help: transport errors, throttling and server errors.
+fn put(creds: &Credentials, bucket: &str, key: &str, body: &[u8]) -> Result<(), (bool, String)> {
+    let host = creds.endpoint.split_once("://").map_or(&*creds.endpoint, |(_, h)| h);
+    let host = host.split('/').next().unwrap_or(host);
+    let path = format!("/{}/{}", bucket, encode_key(key));
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let (date, stamp) = amz_dates(now);
+    let payload = hex(&Sha256::digest(body));
+
+    let mut headers = vec![
+        ("host", host.to_string()),
+        ("x-amz-content-sha256", payload.clone()),
+        ("x-amz-date", stamp.clone()),
+    ];
+    if let Some(token) = &creds.token {
+        headers.push(("x-amz-security-token", token.clone()));
+    }
+    let signed: Vec<&str> = headers.iter().map(|(k, _)| *k).collect();
+    let signed = signed.join(";");
+    let canonical_headers: String = headers.iter().map(|(k, v)| format!("{}:{}\n", k, v)).collect();
+    let canonical = format!("PUT\n{}\n\n{}\n{}\n{}", path, canonical_headers, signed, payload);
+    let scope = format!("{}/{}/s3/aws4_request", date, creds.region);
+    let to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        stamp,
+        scope,
+        hex(&Sha256::digest(canonical.as_bytes()))
+    );
+    let mut key = format!("AWS4{}", creds.secret_key).into_bytes();
+    for part in [date.as_str(), &creds.region, "s3", "aws4_request"] {
+        key = hmac(&key, part);
+    }
+    let authorization = format!(
+        "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+        creds.access_key,
+        scope,
+        signed,
+        hex(&hmac(&key, &to_sign))
+    );
+
+    let mut req = ureq::put(&format!("{}{}", creds.endpoint, path));
+    for (k, v) in headers.iter().filter(|(k, _)| *k != "host") {
+        req = req.set(k, v);
+    }
+    match req.set("authorization", &authorization).send_bytes(body) {
+        Ok(_) => Ok(()),
+        Err(ureq::Error::Status(code, resp)) => {
+            let detail = resp.into_string().unwrap_or_default();
+            Err((code == 429 || code >= 500, format!("HTTP {}: {}", code, detail.trim())))
+        }
+        Err(e) => Err((true, e.to_string())),
+    }
+}
+
+/// `put`, retried with exponential backoff while a retry may help.
+fn put_with_retry(creds: &Credentials, bucket: &str, key: &str, body: &[u8]) -> Result<(), String> {
+    let mut attempt = 1;
+    loop {
+        let (retryable, err) = match put(creds, bucket, key, body) {
+            Ok(()) => return Ok(()),
+            Err(e) => e,
+        };
+        if !retryable || attempt == ATTEMPTS {
+            return Err(format!("s3://{}/{}: {}", bucket, key, err));
+        }
+        thread::sleep(Duration::from_secs(1 << (attempt - 1)));
+        attempt += 1;
+    }
+}
+
+/// Workspace files relative to `dir`, `target/` and `.git/` excepted.
+fn workspace_files(root: &Path, dir: &Path, out: &mut Vec<String>) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            if path.file_name().is_some_and(|n| n == "target" || n == ".git") {
+                continue;
+            }
+            workspace_files(root, &path, out)?;
+        } else if let Ok(rel) = path.strip_prefix(root) {
+            out.push(rel.to_string_lossy().into_owned());
+        }
+    }
+    Ok(())
+}
+
+/// Upload `report.txt`, the JSON `report.json` and the workspace sources
+/// under `<prefix>/<stem>/<unix time>/`; returns that location.
+pub fn upload(target: &Target, stem: &str, report: &str, document: &str, workspace: &Path) -> Result<String, String> {
+    let creds = Credentials::from_env()?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let base = [target.prefix.as_str(), stem, &now.to_string()]
+        .iter()
+        .filter(|p| !p.is_empty())
+        .copied()
+        .collect::<Vec<_>>()
+        .join("/");
+    put_with_retry(&creds, &target.bucket, &format!("{}/report.txt", base), report.as_bytes())?;
+    put_with_retry(&creds, &target.bucket, &format!("{}/report.json", base), document.as_bytes())?;
+    let mut files = Vec::new();
+    workspace_files(workspace, workspace, &mut files).map_err(|e| e.to_string())?;
+    for rel in files {
+        let bytes = fs::read(workspace.join(&rel)).map_err(|e| format!("{}: {}", rel, e))?;
+        put_with_retry(&creds, &target.bucket, &format!("{}/workspace/{}", base, rel), &bytes)?;
+    }
+    Ok(format!("s3://{}/{}/", target.bucket, base))
+}
@@ -0,0 +1,122 @@
+//! `--watch`: validate the notebook, then again every time it (or its
+//! manifest) is saved, until Ctrl-C. Each validation is a validator
+//! process of its own with the same flags, like batch mode's, and reuses
+//! the workspace, so only changed cells are rewritten and rebuilt. After
+//! each one, the tests whose classification flipped since the previous
+//! one are listed.
+
+use validator::colors::*;
+use validator::shard::ShardReport;
+use validator::{interrupt, Classification, RunMatrix};
+use std::{
+    collections::{BTreeMap, HashMap},
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+    thread,
+    time::{Duration, SystemTime},
+};
+
+/// How often the files are checked for changes.
+const POLL: Duration = Duration::from_millis(300);
+
+/// Each test's classification in one validation.
+type Classes = BTreeMap<String, Classification>;
+
+/// The validator's own arguments without `--watch`.
+fn passthrough() -> Vec<OsString> {
+    std::env::args_os().skip(1).filter(|a| a != "--watch").collect()
+}
+
+/// Modification time and size of each of `files`, to tell a save.
+fn stamp(files: &[PathBuf]) -> Vec<Option<(SystemTime, u64)>> {
+    files
+        .iter()
+        .map(|f| fs::metadata(f).ok().and_then(|m| Some((m.modified().ok()?, m.len()))))
+        .collect()
+}
+
+/// Print how `now` differs from `before`.
+fn print_changes(before: &Classes, now: &Classes) {
+    let mut lines = Vec::new();
+    for (test, &class) in now {
+        match before.get(test) {
+            Some(&old) if old == class => {}
+            Some(&old) => lines.push(format!("  {} {} → {}", test, old.as_str(), paint(class))),
+            None => lines.push(format!("  {} new, {}", test, paint(class))),
+        }
+    }
+    for test in before.keys().filter(|t| !now.contains_key(*t)) {
+        lines.push(format!("  {} gone", test));
+    }
+    if lines.is_empty() {
+        println!("\n{}No test changed{}", BOLD, RESET);
+    } else {
+        println!("\n{}Changed since the last validation:{}", BOLD, RESET);
+        println!("{}", lines.join("\n"));
+    }
+}
+
+fn paint(class: Classification) -> String {
+    let color = match class {
+        Classification::Pass => GREEN,
+        Classification::Flaky => BLUE,
+        Classification::Fail | Classification::Unsound => RED,
+    };
+    format!("{}{}{}", color, class.as_str(), RESET)
+}
+
+/// Validate `notebook` on every save until interrupted; returns the exit
+/// code.
+pub fn run(notebook: &Path) -> i32 {
+    let exe = match std::env::current_exe() {
+        Ok(exe) => exe,
+        Err(e) => {
+            eprintln!("{}Watch error:{} {}", RED, RESET, e);
+            return 1;
+        }
+    };
+    let flags = passthrough();
+    let report = std::env::temp_dir().join(format!("validator-watch-{}.json", std::process::id()));
+    let watched = [notebook.to_path_buf(), notebook.with_extension("toml")];
+    let mut previous: Option<Classes> = None;
+    loop {
+        let seen = stamp(&watched);
+        println!("\n{}━━ {}{}", BOLD, notebook.display(), RESET);
+        let _ = fs::remove_file(&report);
+        if let Err(e) = Command::new(&exe).args(&flags).arg("--shard-report").arg(&report).status() {
+            eprintln!("{}Watch error:{} {}: {}", RED, RESET, exe.display(), e);
+            return 1;
+        }
+        // the child saw the same Ctrl-C
+        if interrupt::interrupted() {
+            let _ = fs::remove_file(&report);
+            return interrupt::EXIT_CODE;
+        }
+        // a validation that stopped before its runs has no report
+        let classes = ShardReport::load(&report).ok().map(|r| {
+            RunMatrix::from(r.tests.into_iter().collect::<HashMap<_, _>>())
+                .classified()
+                .into_iter()
+                .map(|(test, class)| (test.to_string(), class))
+                .collect::<Classes>()
+        });
+        if let (Some(before), Some(now)) = (&previous, &classes) {
+            print_changes(before, now);
+        }
+        if classes.is_some() {
+            previous = classes;
+        }
+        println!("\n{}Watching {} for changes; Ctrl-C stops{}", BLUE, notebook.display(), RESET);
+        while stamp(&watched) == seen {
+            if interrupt::interrupted() {
+                let _ = fs::remove_file(&report);
+                return interrupt::EXIT_CODE;
+            }
+            thread::sleep(POLL);
+        }
+        // let an editor finish writing
+        thread::sleep(POLL);
+    }
+}